@@ -0,0 +1,159 @@
+//! Send calls needed with and without [`Rtc::batch_transmit()`].
+//!
+//! Without batching, every datagram is one `sendto`. With batching, every batch is one
+//! `sendmmsg` in an I/O layer that supports it. This sends 10 seconds of 3 Mbit/s VP8
+//! for each max batch size and reports the send calls and the time spent in str0m.
+//!
+//! Run with `cargo bench --bench transmit-batch`.
+
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use str0m::media::{Direction, MediaKind};
+use str0m::net::Receive;
+use str0m::{Candidate, Input, Output, Rtc, RtcError};
+use tracing::info_span;
+
+#[path = "../tests/common.rs"]
+mod common;
+use common::{negotiate, progress, TestRtc};
+
+struct Run {
+    datagrams: usize,
+    send_calls: usize,
+    elapsed: Duration,
+}
+
+fn main() -> Result<(), RtcError> {
+    println!(
+        "{:>9} {:>9} {:>10} {:>11} {:>9}",
+        "max batch", "datagrams", "send calls", "calls/dgram", "elapsed"
+    );
+
+    for max_batch in [1, 4, 16, 64] {
+        let run = run(max_batch)?;
+        println!(
+            "{:>9} {:>9} {:>10} {:>11.2} {:>7}ms",
+            max_batch,
+            run.datagrams,
+            run.send_calls,
+            run.send_calls as f64 / run.datagrams as f64,
+            run.elapsed.as_millis()
+        );
+    }
+
+    Ok(())
+}
+
+fn run(max_batch: usize) -> Result<Run, RtcError> {
+    let l_rtc = Rtc::builder().set_max_transmit_batch(max_batch).build();
+    let r_rtc = Rtc::builder().build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    while !(l.is_connected() && r.is_connected()) {
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    let mut run = Run {
+        datagrams: 0,
+        send_calls: 0,
+        elapsed: Duration::ZERO,
+    };
+
+    let start = l.last;
+    let mut write_at = l.last;
+
+    while l.last - start < Duration::from_secs(10) {
+        let work_start = Instant::now();
+
+        if l.last >= write_at {
+            write_at = l.last + Duration::from_millis(33);
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid)
+                .unwrap()
+                .write(pt, wallclock, time, vec![1_u8; 12_500])?;
+        }
+
+        l.rtc.handle_input(Input::Timeout(l.last))?;
+
+        match l.rtc.poll_output()? {
+            Output::Timeout(v) => {
+                let tick = l.last + Duration::from_millis(10);
+                l.last = if v == l.last { tick } else { tick.min(v) };
+
+                run.elapsed += work_start.elapsed();
+
+                // R only gives feedback, which isn't part of the measurement.
+                r.last = l.last;
+                drain(&mut r, &mut l)?;
+            }
+            Output::Transmit(t) => {
+                let batch = l.rtc.batch_transmit(t);
+                run.elapsed += work_start.elapsed();
+
+                run.send_calls += 1;
+                run.datagrams += batch.contents.len();
+
+                for contents in &batch.contents {
+                    let input = Input::Receive(
+                        l.last,
+                        Receive {
+                            proto: batch.proto,
+                            source: batch.source,
+                            destination: batch.destination,
+                            contents: (&**contents).try_into()?,
+                        },
+                    );
+                    r.rtc.handle_input(input)?;
+                }
+            }
+            Output::Event(_) => {
+                run.elapsed += work_start.elapsed();
+            }
+        }
+    }
+
+    Ok(run)
+}
+
+/// Polls `from` until it times out, delivering every transmit to `to`.
+fn drain(from: &mut TestRtc, to: &mut TestRtc) -> Result<(), RtcError> {
+    from.rtc.handle_input(Input::Timeout(from.last))?;
+
+    loop {
+        match from.rtc.poll_output()? {
+            Output::Timeout(_) => return Ok(()),
+            Output::Transmit(t) => {
+                let input = Input::Receive(
+                    from.last,
+                    Receive {
+                        proto: t.proto,
+                        source: t.source,
+                        destination: t.destination,
+                        contents: (&*t.contents).try_into()?,
+                    },
+                );
+                to.rtc.handle_input(input)?;
+            }
+            Output::Event(_) => {}
+        }
+    }
+}
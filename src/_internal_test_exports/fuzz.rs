@@ -7,7 +7,7 @@ use crate::change::{SdpAnswer, SdpOffer};
 use crate::crypto::KeyingMaterial;
 use crate::crypto::SrtpProfile;
 use crate::format::Codec;
-use crate::packet::{DepacketizingBuffer, RtpMeta};
+use crate::packet::{CodecDepacketizer, DepacketizingBuffer, RtpMeta};
 use crate::rtp_::{Frequency, MediaTime, RtpHeader};
 use crate::streams::register::ReceiverRegister;
 use crate::streams::rtx_cache_buf::EvictingBuffer;
@@ -96,7 +96,7 @@ pub fn depack(data: &[u8]) -> Option<()> {
         _ => unreachable!(),
     };
 
-    let mut depack = DepacketizingBuffer::new(codec.into(), rng.usize(300)?);
+    let mut depack = DepacketizingBuffer::new(CodecDepacketizer::new(codec)?, rng.usize(300)?);
 
     let exts = random_extmap(&mut rng, 10)?;
 
@@ -114,6 +114,7 @@ pub fn depack(data: &[u8]) -> Option<()> {
                 seq_no: rng.u64(u64::MAX)?.into(),
                 header,
                 last_sender_info: None,
+                end_of_frame: rng.bool()?,
             };
             let len = rng.usize(1200)?;
             let data = rng.slice(len)?.to_vec();
@@ -138,7 +139,7 @@ pub fn receive_register(data: &[u8]) -> Option<()> {
                 rr.update(seq.into(), arrival, rtp_time, clock_rate);
             }
             1 => {
-                rr.nack_report();
+                rr.nack_report(start, None);
             }
             2 => {
                 rr.reception_report();
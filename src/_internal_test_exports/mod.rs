@@ -5,7 +5,7 @@ use crate::ice_::IceCreds;
 use crate::media::Media;
 use crate::media::Mid;
 use crate::rtp::{ExtensionMap, RtpHeader};
-use crate::Rtc;
+use crate::{Candidate, Rtc};
 
 pub mod fuzz;
 mod rng;
@@ -13,6 +13,8 @@ use rng::Rng;
 
 mod setup;
 
+pub mod turn;
+
 impl Rtc {
     /// UNSTABLE: not public API!
     pub fn _mids(&self) -> Vec<Mid> {
@@ -28,6 +30,16 @@ impl Rtc {
     pub fn _local_ice_creds(&self) -> IceCreds {
         self.ice.local_credentials().clone()
     }
+
+    /// UNSTABLE: not public API!
+    pub fn _local_candidates(&self) -> Vec<Candidate> {
+        self.ice
+            .local_candidates()
+            .iter()
+            .filter(|c| !c.discarded())
+            .cloned()
+            .collect()
+    }
 }
 
 impl RtpHeader {
@@ -0,0 +1,79 @@
+//! A TURN server answering requests, for testing the TURN client.
+
+use std::net::SocketAddr;
+
+use crate::io::StunClass;
+use crate::turn::msg::{long_term_key, TurnMessage, TurnMethod};
+
+const REALM: &str = "example.org";
+
+/// UNSTABLE: not public API!
+///
+/// Answers the requests of a client with username `user` and password `pass`. Data is
+/// not relayed.
+pub struct TestTurnServer {
+    relayed: SocketAddr,
+    lifetime: u32,
+    fail_refresh: bool,
+}
+
+impl TestTurnServer {
+    /// UNSTABLE: not public API!
+    pub fn new(relayed: SocketAddr, lifetime: u32) -> Self {
+        TestTurnServer {
+            relayed,
+            lifetime,
+            fail_refresh: false,
+        }
+    }
+
+    /// UNSTABLE: not public API!
+    ///
+    /// Answer refreshes with an error, which makes the client lose the allocation.
+    pub fn set_fail_refresh(&mut self, fail: bool) {
+        self.fail_refresh = fail;
+    }
+
+    /// UNSTABLE: not public API!
+    ///
+    /// The reply to a message from `source`, if it is a request.
+    pub fn reply(&self, source: SocketAddr, buf: &[u8]) -> Option<Vec<u8>> {
+        let key = long_term_key("user", REALM, "pass");
+        let req = TurnMessage::parse(buf, Some(&key)).ok()?;
+
+        if req.class != StunClass::Request {
+            return None;
+        }
+
+        let mut res = TurnMessage::new(req.method, StunClass::Failure, Default::default());
+        res.trans_id = req.trans_id;
+
+        if !req.authenticated {
+            res.attrs.error_code = Some((401, "Unauthorized".into()));
+            res.attrs.realm = Some(REALM.into());
+            res.attrs.nonce = Some("nonce".into());
+            return Some(res.to_bytes(None));
+        }
+
+        if req.method == TurnMethod::Refresh && self.fail_refresh {
+            res.attrs.error_code = Some((437, "Allocation Mismatch".into()));
+            return Some(res.to_bytes(None));
+        }
+
+        res.class = StunClass::Success;
+
+        match req.method {
+            TurnMethod::Allocate => {
+                res.attrs.xor_relayed_address = Some(self.relayed);
+                res.attrs.xor_mapped_address = Some(source);
+                res.attrs.lifetime = Some(self.lifetime);
+            }
+            TurnMethod::Refresh => {
+                res.attrs.lifetime = Some(req.attrs.lifetime.unwrap_or(0).min(self.lifetime));
+            }
+            _ => {}
+        }
+
+        Some(res.to_bytes(Some(&key)))
+    }
+}
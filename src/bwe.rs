@@ -1,6 +1,7 @@
 //! Bandwidth estimation.
 
-use crate::{rtp_::Mid, Rtc};
+use crate::rtp_::{Mid, Ssrc};
+use crate::Rtc;
 
 pub use crate::rtp_::Bitrate;
 
@@ -13,6 +14,39 @@ pub enum BweKind {
     Remb(Mid, Bitrate),
 }
 
+/// A bitrate cap requested by the remote peer for streams we are sending.
+///
+/// Obtained via [`Event::RemoteBitrateLimit`][crate::Event::RemoteBitrateLimit]. This is
+/// useful for applications that do their own bitrate allocation and want to honor
+/// the caps signalled by the remote peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteBitrateLimit {
+    /// The kind of RTCP feedback that carried the limit.
+    pub kind: RemoteBitrateLimitKind,
+
+    /// The maximum bitrate.
+    pub bitrate: Bitrate,
+
+    /// The outgoing SSRCs the limit applies to, together with the media they belong to.
+    ///
+    /// SSRCs in the feedback that don't match any outgoing stream are left out.
+    pub ssrcs: Vec<(Mid, Ssrc)>,
+}
+
+/// The RTCP feedback carrying a [`RemoteBitrateLimit`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteBitrateLimitKind {
+    /// REMB (Receiver Estimated Maximum Bitrate). One limit for all listed SSRCs.
+    Remb,
+    /// TMMBR (Temporary Maximum Media Stream Bit Rate Request, RFC 5104).
+    ///
+    /// The measured per packet overhead in bytes, as signalled by the remote.
+    Tmmbr {
+        /// Per packet overhead in bytes.
+        overhead: u16,
+    },
+}
+
 /// Access to the Bandwidth Estimate subsystem.
 pub struct Bwe<'a>(pub(crate) &'a mut Rtc);
 
@@ -1,9 +1,10 @@
-use crate::channel::ChannelId;
+use crate::channel::{ChannelCloseReason, ChannelId};
 use crate::crypto::Fingerprint;
 use crate::media::{Media, MediaKind};
 use crate::rtp_::{Mid, Rid, Ssrc};
 use crate::sctp::ChannelConfig;
 use crate::streams::{StreamRx, StreamTx, DEFAULT_RTX_CACHE_DURATION};
+use crate::util::{InstanceRng, RngGuard};
 use crate::IceCreds;
 use crate::Rtc;
 use crate::RtcError;
@@ -31,6 +32,42 @@ use crate::RtcError;
 /// </div>
 pub struct DirectApi<'a> {
     rtc: &'a mut Rtc,
+    _rng: RngGuard,
+}
+
+/// One stream to declare with [`DirectApi::declare_streams`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamSpec {
+    /// An outgoing stream, as with [`DirectApi::declare_stream_tx`].
+    Tx {
+        /// The main SSRC.
+        ssrc: Ssrc,
+        /// The SSRC for resends, if any.
+        rtx: Option<Ssrc>,
+        /// The media the stream belongs to.
+        mid: Mid,
+        /// The rid, for simulcast.
+        rid: Option<Rid>,
+    },
+    /// An incoming stream, as with [`DirectApi::expect_stream_rx`].
+    Rx {
+        /// The main SSRC.
+        ssrc: Ssrc,
+        /// The SSRC for resends, if any.
+        rtx: Option<Ssrc>,
+        /// The media the stream belongs to.
+        mid: Mid,
+        /// The rid, for simulcast.
+        rid: Option<Rid>,
+    },
+}
+
+impl StreamSpec {
+    fn mid(&self) -> Mid {
+        match self {
+            StreamSpec::Tx { mid, .. } | StreamSpec::Rx { mid, .. } => *mid,
+        }
+    }
 }
 
 impl<'a> DirectApi<'a> {
@@ -39,7 +76,8 @@ impl<'a> DirectApi<'a> {
     /// The `DirectApi` struct provides a high-level API for interacting with a WebRTC peer connection,
     /// and the `Rtc` instance provides low-level access to the underlying WebRTC functionality.
     pub fn new(rtc: &'a mut Rtc) -> Self {
-        DirectApi { rtc }
+        let _rng = InstanceRng::enter(rtc.rng.as_ref());
+        DirectApi { rtc, _rng }
     }
 
     /// Sets the ICE controlling flag for this peer connection.
@@ -54,6 +92,20 @@ impl<'a> DirectApi<'a> {
         self.rtc.ice.set_controlling(controlling);
     }
 
+    /// The ICE tie-breaker of this peer connection.
+    ///
+    /// Sent in connectivity checks alongside the role. If both peers claim the same role,
+    /// the one with the larger tie-breaker becomes controlling, and the other switches,
+    /// which is signalled with [`Event::IceRoleChange`][crate::Event::IceRoleChange].
+    pub fn ice_tie_breaker(&self) -> u64 {
+        self.rtc.ice.tie_breaker()
+    }
+
+    /// Sets the ICE tie-breaker. Defaults to a random value.
+    pub fn set_ice_tie_breaker(&mut self, tie_breaker: u64) {
+        self.rtc.ice.set_tie_breaker(tie_breaker);
+    }
+
     /// Returns a reference to the local ICE credentials used by this peer connection.
     ///
     /// The ICE credentials consist of the username and password used by the ICE agent during
@@ -112,6 +164,19 @@ impl<'a> DirectApi<'a> {
         self.rtc.chan.close_channel(channel_id, &mut self.rtc.sctp);
     }
 
+    /// Close a data channel, telling the remote peer why.
+    ///
+    /// The remote str0m peer gets the reason as [`Event::ChannelCloseReason`][crate::Event::ChannelCloseReason].
+    pub fn close_data_channel_with_reason(
+        &mut self,
+        channel_id: ChannelId,
+        reason: ChannelCloseReason,
+    ) {
+        self.rtc
+            .chan
+            .close_channel_with_reason(channel_id, reason, &mut self.rtc.sctp);
+    }
+
     /// Set whether to enable ice-lite.
     pub fn set_ice_lite(&mut self, ice_lite: bool) {
         self.rtc.ice.set_ice_lite(ice_lite);
@@ -122,6 +187,44 @@ impl<'a> DirectApi<'a> {
         self.rtc.session.enable_twcc_feedback()
     }
 
+    /// Set whether to send TWCC feedback, overriding what was negotiated.
+    ///
+    /// The transport-wide sequence numbers are shared by all media, which means feedback
+    /// is for the whole session, not per media. Turn it off when the remote peer doesn't
+    /// do send side BWE. A renegotiation doesn't turn it back on.
+    pub fn set_twcc_feedback(&mut self, enabled: bool) {
+        self.rtc.session.set_disable_twcc_feedback(!enabled);
+        if enabled {
+            self.rtc.session.enable_twcc_feedback();
+        }
+    }
+
+    /// Whether TWCC feedback is sent.
+    ///
+    /// Either negotiated via SDP, or set with [`Self::enable_twcc_feedback()`] or
+    /// [`Self::set_twcc_feedback()`].
+    pub fn twcc_feedback(&self) -> bool {
+        self.rtc.session.twcc_feedback()
+    }
+
+    /// Set the RTP header extension id for TWCC on outgoing packets of a media.
+    ///
+    /// `None` stops sending transport-wide sequence numbers for the media, which means
+    /// those packets are not part of the send side bandwidth estimation. Read it back with
+    /// [`Media::twcc_extension_id()`]. For the SDP API, a renegotiation sets it again.
+    ///
+    /// This only changes outgoing packets, the [`Media::remote_extmap()`] used for the SDP
+    /// is untouched.
+    ///
+    /// Returns `false` if there is no media for the `mid`, or if the id is used by another
+    /// extension.
+    pub fn set_twcc_extension_id(&mut self, mid: Mid, id: Option<u8>) -> bool {
+        let Some(media) = self.rtc.session.media_by_mid_mut(mid) else {
+            return false;
+        };
+        media.set_twcc_extension_id(id)
+    }
+
     /// Generate a ssrc that is not already used in session
     pub fn new_ssrc(&self) -> Ssrc {
         self.rtc.session.streams.new_ssrc()
@@ -147,6 +250,9 @@ impl<'a> DirectApi<'a> {
     ///
     /// All streams belong to a media identified by a `mid`. This creates the media without
     /// doing any SDP dance.
+    ///
+    /// If [`RtcConfig::set_data_channel_only()`][crate::RtcConfig::set_data_channel_only]
+    /// is set, the media is declared inactive and never sends or receives.
     pub fn declare_media(&mut self, mid: Mid, kind: MediaKind) -> &mut Media {
         let max_index = self.rtc.session.medias.iter().map(|m| m.index()).max();
 
@@ -159,7 +265,7 @@ impl<'a> DirectApi<'a> {
         let exts = self.rtc.session.exts.cloned_with_type(kind.is_audio());
         let m = Media::from_direct_api(mid, next_index, kind, exts);
 
-        self.rtc.session.medias.push(m);
+        self.rtc.session.add_media(m);
         self.rtc.session.medias.last_mut().unwrap()
     }
 
@@ -214,6 +320,20 @@ impl<'a> DirectApi<'a> {
         self.rtc.session.streams.stream_rx_by_mid_rid(mid, rid)
     }
 
+    /// Block all incoming media for a mid/rid.
+    ///
+    /// Unlike [`StreamRx::set_blocked`], this also applies to streams that are created
+    /// later for the same mid/rid, e.g. when the remote changes SSRC, and to packets
+    /// recovered with FEC. A `None` rid blocks every rid of the mid.
+    pub fn set_blocked_rx(&mut self, mid: Mid, rid: Option<Rid>, blocked: bool) {
+        self.rtc.session.streams.set_blocked_rx(mid, rid, blocked);
+    }
+
+    /// Whether incoming media for the mid/rid is blocked via [`DirectApi::set_blocked_rx`].
+    pub fn is_blocked_rx(&self, mid: Mid, rid: Option<Rid>) -> bool {
+        self.rtc.session.streams.is_blocked_rx(mid, rid)
+    }
+
     /// Declare the intention to send data using the given SSRC.
     ///
     /// * The resend RTX is optional but necessary to do resends. str0m does not do
@@ -251,6 +371,70 @@ impl<'a> DirectApi<'a> {
         stream
     }
 
+    /// Declare many streams in one call.
+    ///
+    /// This is the same as calling [`DirectApi::declare_stream_tx`] and
+    /// [`DirectApi::expect_stream_rx`] for each spec, but sizes the internal maps once up
+    /// front. Use it when setting up many forwarding legs, like in an SFU.
+    ///
+    /// Panics if the media for any of the specs is not declared. This is checked before
+    /// any stream is declared, so either all or none of the streams are declared.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::change::StreamSpec;
+    /// # use str0m::media::MediaKind;
+    /// let mut rtc = Rtc::new();
+    /// let mut api = rtc.direct_api();
+    ///
+    /// let mid = "vid".into();
+    /// api.declare_media(mid, MediaKind::Video);
+    ///
+    /// api.declare_streams(&[
+    ///     StreamSpec::Tx { ssrc: 1.into(), rtx: Some(2.into()), mid, rid: Some("h".into()) },
+    ///     StreamSpec::Tx { ssrc: 3.into(), rtx: Some(4.into()), mid, rid: Some("l".into()) },
+    ///     StreamSpec::Rx { ssrc: 5.into(), rtx: Some(6.into()), mid, rid: None },
+    /// ]);
+    ///
+    /// assert!(api.stream_tx_by_mid(mid, Some("l".into())).is_some());
+    /// assert!(api.stream_rx(&5.into()).is_some());
+    /// ```
+    pub fn declare_streams(&mut self, specs: &[StreamSpec]) {
+        for spec in specs {
+            let mid = spec.mid();
+            if self.rtc.session.media_by_mid(mid).is_none() {
+                panic!("No media declared for mid: {}", mid);
+            }
+        }
+
+        let tx = specs
+            .iter()
+            .filter(|s| matches!(s, StreamSpec::Tx { .. }))
+            .count();
+        self.rtc.session.streams.reserve(specs.len() - tx, tx);
+
+        for spec in specs {
+            match *spec {
+                StreamSpec::Tx {
+                    ssrc,
+                    rtx,
+                    mid,
+                    rid,
+                } => {
+                    self.declare_stream_tx(ssrc, rtx, mid, rid);
+                }
+                StreamSpec::Rx {
+                    ssrc,
+                    rtx,
+                    mid,
+                    rid,
+                } => {
+                    self.expect_stream_rx(ssrc, rtx, mid, rid);
+                }
+            }
+        }
+    }
+
     /// Remove the transmit stream for the given SSRC.
     ///
     /// Returns true if stream existed and was removed.
@@ -13,11 +13,11 @@
 //! changes directly to the session, the remote side would not be aware of them unless you construct
 //! some "other way" keeping the two peers in sync.
 mod sdp;
-pub(crate) use sdp::AddMedia;
+pub(crate) use sdp::{AddMedia, SdpQueue};
 pub use sdp::{SdpAnswer, SdpApi, SdpOffer, SdpPendingOffer};
 
 mod direct;
-pub use direct::DirectApi;
+pub use direct::{DirectApi, StreamSpec};
 
-pub use crate::crypto::Fingerprint;
-pub use crate::dtls::DtlsCert;
+pub use crate::crypto::{Fingerprint, FingerprintHash};
+pub use crate::dtls::{DtlsCert, DtlsCipherSuite, DtlsGroup, DtlsKeyLog, DtlsRole};
@@ -2,21 +2,27 @@
 
 use std::fmt;
 use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Weak};
 
 use crate::channel::ChannelId;
 use crate::crypto::Fingerprint;
-use crate::format::CodecConfig;
+use crate::dtls::DtlsRole;
 use crate::format::PayloadParams;
+use crate::format::{Codec, CodecConfig};
 use crate::io::Id;
+use crate::limits::Limit;
 use crate::media::Media;
 use crate::packet::MediaKind;
+use crate::rtp_::RemapConflict;
 use crate::rtp_::Rid;
 use crate::rtp_::{Direction, Extension, ExtensionMap, Mid, Pt, Ssrc};
 use crate::sctp::ChannelConfig;
+use crate::sctp::SCTP_PORT;
 use crate::sdp::SimulcastGroups;
-use crate::sdp::{self, MediaAttribute, MediaLine, MediaType, Msid, Sdp};
+use crate::sdp::{self, FormatParam, MediaAttribute, MediaLine, MediaType, Msid, Sdp};
 use crate::sdp::{Proto, SessionAttribute, Setup};
 use crate::session::Session;
+use crate::warning::Warning;
 use crate::Rtc;
 use crate::RtcError;
 use crate::{Candidate, IceCreds};
@@ -24,18 +30,22 @@ use crate::{Candidate, IceCreds};
 pub use crate::sdp::{SdpAnswer, SdpOffer};
 use crate::streams::Streams;
 use crate::streams::DEFAULT_RTX_CACHE_DURATION;
+use crate::util::{InstanceRng, RngGuard};
 
 /// Changes to the Rtc via SDP Offer/Answer dance.
 pub struct SdpApi<'a> {
     rtc: &'a mut Rtc,
     changes: Changes,
+    _rng: RngGuard,
 }
 
 impl<'a> SdpApi<'a> {
     pub(crate) fn new(rtc: &'a mut Rtc) -> Self {
+        let _rng = InstanceRng::enter(rtc.rng.as_ref());
         SdpApi {
             rtc,
             changes: Changes::default(),
+            _rng,
         }
     }
 
@@ -79,7 +89,10 @@ impl<'a> SdpApi<'a> {
             ));
         }
 
+        check_limits(&self.rtc.session, &offer)?;
+
         add_ice_details(self.rtc, &offer, None)?;
+        warn_unbundled(self.rtc, &offer);
 
         if self.rtc.remote_fingerprint.is_none() {
             if let Some(f) = offer.fingerprint() {
@@ -156,7 +169,10 @@ impl<'a> SdpApi<'a> {
             ));
         }
 
+        check_limits(&self.rtc.session, &answer)?;
+
         add_ice_details(self.rtc, &answer, Some(&pending))?;
+        warn_unbundled(self.rtc, &answer);
 
         // Ensure setup=active/passive is corresponding remote and init dtls.
         init_dtls(self.rtc, &answer)?;
@@ -192,6 +208,7 @@ impl<'a> SdpApi<'a> {
     /// Test if any changes have been made.
     ///
     /// If changes have been made, nothing happens until we call [`SdpApi::apply()`].
+    /// This includes changes queued while a previous offer was outstanding.
     ///
     /// ```
     /// # use str0m::{Rtc, media::MediaKind, media::Direction};
@@ -204,19 +221,25 @@ impl<'a> SdpApi<'a> {
     /// assert!(changes.has_changes());
     /// ```
     pub fn has_changes(&self) -> bool {
-        !self.changes.0.is_empty()
+        !self.changes.0.is_empty() || !self.rtc.sdp_queue.changes.is_empty()
     }
 
-    /// Add audio or video media and get the `mid` that will be used.
+    /// Add audio, video or application media and get the `mid` that will be used.
     ///
     /// Each call will result in a new m-line in the offer identified by the [`Mid`].
     ///
+    /// [`MediaKind::Application`] requires an application payload type in the codec config,
+    /// see [`CodecConfig::add_application()`][crate::format::CodecConfig::add_application].
+    ///
     /// The mid is not valid to use until the SDP offer-answer dance is complete and
     /// the mid been advertised via [`Event::MediaAdded`][crate::Event::MediaAdded].
     ///
     /// * `stream_id` is used to synchronize media. It is `a=msid-semantic: WMS <streamId>` line in SDP.
     /// * `track_id` is becomes both the track id in `a=msid <streamId> <trackId>` as well as the
-    ///   CNAME in the RTP SDES.
+    ///   CNAME in the RTP SDES (unless a CNAME is set via [`RtcConfig::set_cname()`][crate::RtcConfig::set_cname]).
+    ///
+    /// If [`RtcConfig::set_data_channel_only()`][crate::RtcConfig::set_data_channel_only]
+    /// is set, the m-line is offered rejected (port 0) and the media is inactive.
     ///
     /// ```
     /// # use str0m::{Rtc, media::MediaKind, media::Direction};
@@ -262,22 +285,43 @@ impl<'a> SdpApi<'a> {
             Id::<20>::random().to_string()
         };
 
-        let rtx = kind.is_video().then(|| self.rtc.session.streams.new_ssrc());
-        let ssrcs = vec![(self.rtc.session.streams.new_ssrc(), rtx)];
+        // A data channel only session never sends, so there are no SSRCs to offer.
+        let ssrcs = if self.rtc.session.data_channel_only {
+            vec![]
+        } else {
+            let rtx = kind.is_video().then(|| self.rtc.session.streams.new_ssrc());
+            vec![(self.rtc.session.streams.new_ssrc(), rtx)]
+        };
+
+        // The FlexFEC SSRC goes in the offer, like RTX, in case the remote accepts FlexFEC.
+        let has_flexfec = self
+            .rtc
+            .session
+            .codec_config
+            .iter()
+            .any(|p| p.spec().codec == Codec::FlexFec);
+        let flexfec_ssrc =
+            (kind.is_video() && has_flexfec).then(|| self.rtc.session.streams.new_ssrc());
+
+        let cname = match self.rtc.session.cname() {
+            Some(v) => v.to_string(),
+            None => track_id.clone(),
+        };
 
         // TODO: let user configure stream/track name.
         let msid = Msid {
             stream_id,
-            track_id: track_id.clone(),
+            track_id,
         };
 
         let add = AddMedia {
             mid,
-            cname: track_id,
+            cname,
             msid,
             kind,
             dir,
             ssrcs,
+            flexfec_ssrc,
 
             // Added later
             pts: vec![],
@@ -330,7 +374,8 @@ impl<'a> SdpApi<'a> {
     /// ```
     pub fn add_channel(&mut self, label: String) -> ChannelId {
         let has_media = self.rtc.session.app().is_some();
-        let changes_contains_add_app = self.changes.contains_add_app();
+        let changes_contains_add_app =
+            self.changes.contains_add_app() || self.rtc.sdp_queue.changes.contains_add_app();
 
         if !has_media && !changes_contains_add_app {
             let mid = self.rtc.new_mid();
@@ -380,9 +425,14 @@ impl<'a> SdpApi<'a> {
     /// without doing a negotiation. Specifically for additional [`SdpApi::add_channel()`]
     /// after the first, there is no negotiation needed.
     ///
-    /// The [`SdpPendingOffer`] is valid until the next time we call this function, at which
-    /// point using it will raise an error. Using [`SdpApi::accept_offer()`] will also invalidate
-    /// the current [`SdpPendingOffer`].
+    /// While an [`SdpPendingOffer`] is outstanding, changes that require a negotiation are
+    /// queued instead, and this returns `None`. Once the answer is accepted with
+    /// [`SdpApi::accept_answer()`], the next `apply()` batches all queued changes into a
+    /// single offer. [`SdpApi::has_changes()`] tells whether there is anything queued.
+    ///
+    /// The offer is no longer outstanding when the [`SdpPendingOffer`] is dropped (rolled back),
+    /// merged using [`SdpApi::merge()`], or when a remote offer is accepted using
+    /// [`SdpApi::accept_offer()`]. The latter also invalidates the [`SdpPendingOffer`].
     ///
     /// ```
     /// # use str0m::Rtc;
@@ -392,25 +442,46 @@ impl<'a> SdpApi<'a> {
     /// assert!(changes.apply().is_none());
     /// ```
     pub fn apply(self) -> Option<(SdpOffer, SdpPendingOffer)> {
-        if self.changes.is_empty() {
+        let mut changes = self.changes;
+        let requires_negotiation = changes.0.iter().any(requires_negotiation);
+
+        if requires_negotiation && self.rtc.sdp_queue.is_offer_outstanding(self.rtc) {
+            debug!("Queue changes while offer is outstanding");
+            self.rtc.sdp_queue.changes.extend(changes.drain(..));
             return None;
         }
 
-        let change_id = self.rtc.next_change_id();
+        if !self.rtc.sdp_queue.is_offer_outstanding(self.rtc) {
+            // Queued changes go first, they were made before the current ones.
+            let mut queued = std::mem::take(&mut self.rtc.sdp_queue.changes);
+            queued.retain_queued(self.rtc);
+            queued.extend(changes.drain(..));
+            changes = queued;
+        }
 
-        let requires_negotiation = self.changes.0.iter().any(requires_negotiation);
+        if changes.is_empty() {
+            return None;
+        }
+
+        changes.keep_last_ice_restart();
+
+        let requires_negotiation = changes.0.iter().any(requires_negotiation);
 
         if requires_negotiation {
-            let offer = create_offer(self.rtc, &self.changes);
+            let change_id = self.rtc.next_change_id();
+            let offer = create_offer(self.rtc, &changes);
+            let outstanding = Arc::new(());
+            self.rtc.sdp_queue.outstanding = Some((change_id, Arc::downgrade(&outstanding)));
             let pending = SdpPendingOffer {
                 change_id,
-                changes: self.changes,
+                changes,
+                _outstanding: outstanding,
             };
             debug!("Create offer");
             Some((offer, pending))
         } else {
             debug!("Apply direct changes");
-            apply_direct_changes(self.rtc, self.changes);
+            apply_direct_changes(self.rtc, changes);
             None
         }
     }
@@ -469,6 +540,8 @@ impl<'a> SdpApi<'a> {
 pub struct SdpPendingOffer {
     change_id: usize,
     changes: Changes,
+    /// Keeps the offer outstanding for the [`SdpQueue`] until dropped.
+    _outstanding: Arc<()>,
 }
 
 impl SdpPendingOffer {
@@ -494,10 +567,55 @@ impl SdpPendingOffer {
     }
 }
 
+/// Changes waiting for an outstanding offer to complete.
+#[derive(Default)]
+pub(crate) struct SdpQueue {
+    changes: Changes,
+    /// Change id of the last offer, alive as long as the [`SdpPendingOffer`] is.
+    outstanding: Option<(usize, Weak<()>)>,
+}
+
+impl SdpQueue {
+    pub(crate) fn is_offer_outstanding(&self, rtc: &Rtc) -> bool {
+        let Some((change_id, alive)) = &self.outstanding else {
+            return false;
+        };
+        alive.strong_count() > 0 && rtc.is_correct_change_id(*change_id)
+    }
+}
+
 #[derive(Default)]
 pub(crate) struct Changes(pub Vec<Change>);
 
 impl Changes {
+    /// Drop queued changes that are no longer needed after negotiations that happened
+    /// while they were queued.
+    fn retain_queued(&mut self, rtc: &Rtc) {
+        self.retain(|c| match c {
+            Change::AddApp(_) => rtc.session.app().is_none(),
+            Change::AddChannel(v) => rtc.chan.stream_id_by_channel_id(v.0).is_none(),
+            // The session direction is set straight away, this only triggers the negotiation.
+            Change::Direction(m, _) => rtc.media(*m).is_some(),
+            Change::AddMedia(_) | Change::IceRestart(_, _) => true,
+        });
+    }
+
+    /// Only the last ICE restart is kept, see [`SdpApi::ice_restart()`].
+    fn keep_last_ice_restart(&mut self) {
+        let Some(last) = self
+            .iter()
+            .rposition(|c| matches!(c, Change::IceRestart(_, _)))
+        else {
+            return;
+        };
+        let mut i = 0;
+        self.retain(|c| {
+            let keep = i == last || !matches!(c, Change::IceRestart(_, _));
+            i += 1;
+            keep
+        });
+    }
+
     /// Details of the active ICE restart, if any.
     ///
     /// Returns the new local ICE credentials and the whether to keep local ICE candidates if an
@@ -530,6 +648,7 @@ pub(crate) struct AddMedia {
     pub kind: MediaKind,
     pub dir: Direction,
     pub ssrcs: Vec<(Ssrc, Option<Ssrc>)>,
+    pub flexfec_ssrc: Option<Ssrc>,
 
     // pts and index are filled in when creating the SDP OFFER.
     // The default PT order is set by the Session (BUNDLE).
@@ -585,6 +704,52 @@ fn create_offer(rtc: &mut Rtc, changes: &Changes) -> SdpOffer {
     sdp.into()
 }
 
+/// Refuse a remote SDP that would take the session above the configured limits.
+fn check_limits(session: &Session, sdp: &Sdp) -> Result<(), RtcError> {
+    let known_mid = |mid: Mid| {
+        session.medias.iter().any(|m| m.mid() == mid) || session.app().map(|(m, _)| m) == Some(mid)
+    };
+
+    let new_mids = sdp
+        .media_lines
+        .iter()
+        .filter(|m| !known_mid(m.mid()))
+        .count();
+
+    if !session
+        .limits
+        .allows(Limit::Mids, session.mid_count() + new_mids)
+    {
+        return Err(RtcError::LimitReached(Limit::Mids));
+    }
+
+    let mut new_ssrcs: Vec<Ssrc> = sdp
+        .media_lines
+        .iter()
+        .flat_map(|m| m.ssrc_info())
+        .filter(|i| i.repairs.is_none() && !session.streams.has_stream_rx(i.ssrc))
+        .map(|i| i.ssrc)
+        .collect();
+    new_ssrcs.sort();
+    new_ssrcs.dedup();
+
+    let count = session.streams.stream_count() + new_ssrcs.len();
+    if !session.limits.allows(Limit::Ssrcs, count) {
+        return Err(RtcError::LimitReached(Limit::Ssrcs));
+    }
+
+    Ok(())
+}
+
+fn warn_unbundled(rtc: &mut Rtc, sdp: &Sdp) {
+    // All media runs over one ICE/DTLS transport. Multiple transports are not supported.
+    let mids = sdp.unbundled_mids();
+    if !mids.is_empty() {
+        warn!("Remote SDP has m-lines outside BUNDLE: {:?}", mids);
+        rtc.session.push_warning(Warning::BundleRejected(mids));
+    }
+}
+
 fn add_ice_details(
     rtc: &mut Rtc,
     sdp: &Sdp,
@@ -621,6 +786,11 @@ fn add_ice_details(
 
         rtc.ice
             .ice_restart(new_local_creds.clone(), keep_local_candidates);
+
+        // The server reflexive candidates are gone with the rest, gather them again.
+        if !keep_local_candidates {
+            rtc.stun_gatherer.reset();
+        }
     }
 
     rtc.ice.set_remote_credentials(creds);
@@ -629,6 +799,10 @@ fn add_ice_details(
         rtc.ice.add_remote_candidate(r.clone());
     }
 
+    if sdp.end_of_candidates() {
+        rtc.ice.set_remote_end_of_candidates();
+    }
+
     Ok(())
 }
 
@@ -647,6 +821,18 @@ fn init_dtls(rtc: &mut Rtc, remote_sdp: &Sdp) -> Result<(), RtcError> {
     };
 
     let active = setup == Setup::Active;
+
+    // The role is settled by the first negotiation. The remote can still say actpass
+    // in later OFFERs, but not ask for the opposite of what we have.
+    if let Some(role) = rtc.dtls.role() {
+        let is_fixed = matches!(remote_sdp.setup(), Some(Setup::Active | Setup::Passive));
+        if is_fixed && role != DtlsRole::from_active(active) {
+            warn!("Remote SDP attempts to change DTLS role, keep: {:?}", role);
+            rtc.session.push_warning(Warning::DtlsRoleChange(role));
+        }
+        return Ok(());
+    }
+
     rtc.init_dtls(active)?;
 
     Ok(())
@@ -688,13 +874,29 @@ fn as_sdp(session: &Session, params: AsSdpParams) -> Sdp {
                     ssrcs.extend(pending.ssrcs_for_mid(m.mid()))
                 }
 
+                let mut ssrcs_fec = session.streams.flexfec_ssrcs_tx(m.mid());
+                if let Some(pending) = params.pending {
+                    ssrcs_fec.extend(pending.flexfec_ssrcs_for_mid(m.mid()))
+                }
+
                 let params: Vec<_> = session
                     .codec_config
                     .all_for_kind(m.kind())
                     .cloned()
                     .collect();
 
-                m.as_media_line(attrs, &ssrcs, &session.exts, &params)
+                let mut line = m.as_media_line(attrs, &ssrcs, &ssrcs_fec, &session.exts, &params);
+
+                if line.is_media() && session.rtcp_rsize_in_sdp() {
+                    line.attrs.push(MediaAttribute::RtcpRsize);
+                }
+
+                if line.proto == Proto::Sctp {
+                    let max = session.local_max_message_size;
+                    line.attrs.push(MediaAttribute::MaxMessageSize(max));
+                }
+
+                line
             })
             .collect::<Vec<_>>();
 
@@ -702,11 +904,21 @@ fn as_sdp(session: &Session, params: AsSdpParams) -> Sdp {
             pending.apply_to(&mut lines);
         }
 
-        // Mids go into the session part of the SDP.
-        let mids = v.iter().map(|m| m.mid()).collect();
+        if session.data_channel_only {
+            reject_media_lines(&mut lines);
+        }
+
+        // Mids go into the session part of the SDP. Rejected m-lines are not bundled.
+        let mids = lines
+            .iter()
+            .filter(|l| !l.disabled)
+            .map(|l| l.mid())
+            .collect();
+
+        let enabled = v.iter().zip(&lines).filter(|(_, l)| !l.disabled);
 
         let mut stream_ids = vec![];
-        for msid in v.iter().filter_map(|v| v.msid()) {
+        for msid in enabled.filter_map(|(v, _)| v.msid()) {
             if !stream_ids.contains(&msid.stream_id) {
                 stream_ids.push(msid.stream_id.clone());
             }
@@ -730,6 +942,10 @@ fn as_sdp(session: &Session, params: AsSdpParams) -> Sdp {
         attrs.push(SessionAttribute::IceLite);
     }
 
+    if session.extmap_allow_mixed_in_sdp() {
+        attrs.push(SessionAttribute::ExtmapAllowMixed);
+    }
+
     Sdp {
         session: sdp::Session {
             id: session.id(),
@@ -740,6 +956,32 @@ fn as_sdp(session: &Session, params: AsSdpParams) -> Sdp {
     }
 }
 
+/// Rejects all audio/video m-lines with port 0 (RFC 3264), keeping only the mid.
+///
+/// The candidates are in the first m-line, and move to the first one that is not rejected.
+fn reject_media_lines(lines: &mut [MediaLine]) {
+    use MediaAttribute::*;
+
+    let is_candidate = |a: &MediaAttribute| matches!(a, Candidate(_) | EndOfCandidates);
+    let mut candidates = vec![];
+
+    for line in lines.iter_mut().filter(|l| l.is_media()) {
+        let mid = line.mid();
+
+        candidates.extend(line.attrs.drain(..).filter(is_candidate));
+
+        line.disabled = true;
+        // The format list is ignored in a rejected m-line, but can't be empty.
+        line.pts = vec![0.into()];
+        line.bw = None;
+        line.attrs = vec![Mid(mid), Direction::Inactive.into()];
+    }
+
+    if let Some(line) = lines.iter_mut().find(|l| !l.disabled) {
+        line.attrs.splice(0..0, candidates);
+    }
+}
+
 fn apply_offer(session: &mut Session, offer: SdpOffer) -> Result<(), RtcError> {
     offer.assert_consistency()?;
 
@@ -806,6 +1048,9 @@ fn ensure_stream_tx(session: &mut Session) {
             .filter(|p| media.remote_pts().contains(&p.pt))
             .any(|p| p.resend().is_some());
 
+        // Likewise the a=ssrc-group:FEC-FR needs the FlexFEC SSRC.
+        let has_flexfec = media.flexfec_pt(&session.codec_config).is_some();
+
         for rid in rids {
             // If we already have the stream, we don't make any new one.
             let has_stream = session
@@ -825,10 +1070,16 @@ fn ensure_stream_tx(session: &mut Session) {
                 (ssrc, None)
             };
 
+            let flexfec_ssrc = has_flexfec.then(|| session.streams.new_ssrc());
+
             let stream = session
                 .streams
                 .declare_stream_tx(ssrc, rtx, media.mid(), rid);
 
+            if let Some(flexfec_ssrc) = flexfec_ssrc {
+                stream.set_flexfec_ssrc(flexfec_ssrc);
+            }
+
             // Configure cache size
             let size = if media.kind().is_audio() {
                 session.send_buffer_audio
@@ -866,6 +1117,10 @@ fn add_pending_changes(session: &mut Session, pending: Changes) {
                 .streams
                 .declare_stream_tx(ssrc, rtx, add_media.mid, None);
 
+            if let Some(flexfec_ssrc) = add_media.flexfec_ssrc {
+                stream.set_flexfec_ssrc(flexfec_ssrc);
+            }
+
             let size = if media.kind().is_audio() {
                 session.send_buffer_audio
             } else {
@@ -886,35 +1141,35 @@ fn sync_medias<'a>(session: &mut Session, sdp: &'a Sdp) -> Result<Vec<&'a MediaL
 
     for (idx, m) in sdp.media_lines.iter().enumerate() {
         // First, match existing m-lines.
-        match m.typ {
-            MediaType::Application => {
-                if let Some((_, index)) = session.app() {
-                    if idx != *index {
-                        return index_err(m.mid());
-                    }
-                    continue;
+        if m.is_channel() {
+            if let Some((_, index)) = session.app() {
+                if idx != *index {
+                    return index_err(m.mid());
                 }
+                continue;
             }
-            MediaType::Audio | MediaType::Video => {
-                if let Some(media) = session.medias.iter_mut().find(|l| l.mid() == m.mid()) {
-                    if idx != media.index() {
-                        return index_err(m.mid());
-                    }
-
-                    update_media(
-                        media,
-                        m,
-                        &mut session.codec_config,
-                        &session.exts,
-                        &mut session.streams,
-                    );
+        } else if m.is_media() {
+            if let Some(media) = session.medias.iter_mut().find(|l| l.mid() == m.mid()) {
+                if idx != media.index() {
+                    return index_err(m.mid());
+                }
 
+                if media.is_disabled() {
                     continue;
                 }
-            }
-            _ => {
+
+                update_media(
+                    media,
+                    m,
+                    &mut session.codec_config,
+                    &session.exts,
+                    &mut session.streams,
+                );
+
                 continue;
             }
+        } else {
+            continue;
         }
 
         // Second, discover new m-lines.
@@ -937,17 +1192,46 @@ fn add_new_lines(
     for m in new_lines {
         let idx = session.line_count();
 
-        if m.typ.is_media() {
+        if m.is_media() {
             let mut media = Media::from_remote_media_line(m, idx, is_offer);
             media.need_open_event = is_offer;
 
+            // The m-line is rejected, none of its codecs or extensions are used.
+            if session.data_channel_only {
+                session.add_media(media);
+                continue;
+            }
+
             // Match/remap remote params.
             session
                 .codec_config
                 .update_params(&m.rtp_params(), m.direction());
 
             // Remap the extension to that of the answer.
-            session.exts.remap(&m.extmaps());
+            let audio = m.typ == MediaType::Audio;
+            for conflict in session.exts.remap(&m.extmaps(), audio) {
+                let warning = match conflict {
+                    RemapConflict::Locked {
+                        extension,
+                        locked_id,
+                        requested_id,
+                    } => Warning::ExtensionRemapConflict {
+                        extension,
+                        locked_id,
+                        requested_id,
+                    },
+                    RemapConflict::IdInUse {
+                        extension,
+                        id,
+                        existing,
+                    } => Warning::ExtensionIdInUse {
+                        extension,
+                        id,
+                        existing,
+                    },
+                };
+                session.push_warning(warning);
+            }
 
             update_media(
                 &mut media,
@@ -958,7 +1242,7 @@ fn add_new_lines(
             );
 
             session.add_media(media);
-        } else if m.typ.is_channel() {
+        } else if m.is_channel() {
             session.set_app(m.mid(), idx)?;
         } else {
             return Err(format!(
@@ -992,6 +1276,31 @@ fn update_session(session: &mut Session, sdp: &Sdp) {
     if has_transport_cc && has_twcc_header {
         session.enable_twcc_feedback();
     }
+
+    // Whether we can use the two-byte RTP header extension form when sending.
+    session.set_extmap_allow_mixed_remote(sdp.session.extmap_allow_mixed());
+
+    // RTCP is sent on the BUNDLE transport, which means reduced-size is also session wide.
+    let has_rtcp_rsize = sdp
+        .media_lines
+        .iter()
+        .filter(|m| m.is_media())
+        .any(|m| m.rtcp_rsize());
+    session.set_rtcp_rsize_remote(has_rtcp_rsize);
+
+    let remote_sctp = sdp
+        .media_lines
+        .iter()
+        .find(|m| m.typ == MediaType::Application && m.proto == Proto::Sctp && !m.disabled)
+        .map(|m| {
+            let port = m.sctp_port().unwrap_or(SCTP_PORT);
+            // RFC 8841: if the attribute is absent, the default is 64K.
+            let max_message_size = m.max_message_size().unwrap_or(65536);
+            (port, max_message_size)
+        });
+    if remote_sctp.is_some() {
+        session.remote_sctp = remote_sctp;
+    }
 }
 
 /// Returns all media/channels as `AsMediaLine` trait.
@@ -1115,6 +1424,7 @@ trait AsSdpMediaLine {
         &self,
         attrs: Vec<MediaAttribute>,
         ssrcs_tx: &[(Ssrc, Option<Ssrc>)],
+        ssrcs_fec: &[(Ssrc, Ssrc)],
         exts: &ExtensionMap,
         params: &[PayloadParams],
     ) -> MediaLine;
@@ -1137,12 +1447,12 @@ impl AsSdpMediaLine for (Mid, usize) {
         &self,
         mut attrs: Vec<MediaAttribute>,
         _ssrcs_tx: &[(Ssrc, Option<Ssrc>)],
+        _ssrcs_fec: &[(Ssrc, Ssrc)],
         _exts: &ExtensionMap,
         _params: &[PayloadParams],
     ) -> MediaLine {
         attrs.push(MediaAttribute::Mid(self.0));
-        attrs.push(MediaAttribute::SctpPort(5000));
-        attrs.push(MediaAttribute::MaxMessageSize(262144));
+        attrs.push(MediaAttribute::SctpPort(SCTP_PORT));
 
         MediaLine {
             typ: sdp::MediaType::Application,
@@ -1172,12 +1482,13 @@ impl AsSdpMediaLine for Media {
         &self,
         mut attrs: Vec<MediaAttribute>,
         ssrcs_tx: &[(Ssrc, Option<Ssrc>)],
+        ssrcs_fec: &[(Ssrc, Ssrc)],
         exts: &ExtensionMap,
         params: &[PayloadParams],
     ) -> MediaLine {
         if self.app_tmp {
             let app = (self.mid(), self.index());
-            return app.as_media_line(attrs, ssrcs_tx, exts, params);
+            return app.as_media_line(attrs, ssrcs_tx, ssrcs_fec, exts, params);
         }
 
         attrs.push(MediaAttribute::Mid(self.mid()));
@@ -1199,10 +1510,25 @@ impl AsSdpMediaLine for Media {
         // those the remote peer wants.
         let effective_params = params.iter().filter(|p| self.remote_pts().contains(&p.pt));
 
+        // Audio RED names the PT of its blocks, video RED wraps any of the video PTs.
+        let red_blocks = effective_params
+            .clone()
+            .find(|p| p.spec().codec == Codec::Opus)
+            .map(|p| p.pt());
+
         let mut pts = vec![];
+        let mut has_flexfec = false;
 
         for p in effective_params {
             p.as_media_attrs(&mut attrs);
+            has_flexfec |= p.spec().codec == Codec::FlexFec;
+
+            if let Some(blocks) = red_blocks.filter(|_| p.spec().is_audio_red()) {
+                attrs.push(MediaAttribute::Fmtp {
+                    pt: p.pt(),
+                    values: vec![FormatParam::Red(blocks)],
+                });
+            }
 
             // The pts that will be advertised in the SDP
             pts.push(p.pt());
@@ -1253,6 +1579,19 @@ impl AsSdpMediaLine for Media {
                     value: msid.clone(),
                 });
             }
+            let ssrc_fec = ssrcs_fec.iter().find(|(s, _)| s == ssrc).map(|(_, f)| f);
+            if let Some(ssrc_fec) = ssrc_fec.filter(|_| has_flexfec) {
+                attrs.push(MediaAttribute::Ssrc {
+                    ssrc: *ssrc_fec,
+                    attr: "cname".to_string(),
+                    value: self.cname().to_string(),
+                });
+                attrs.push(MediaAttribute::Ssrc {
+                    ssrc: *ssrc_fec,
+                    attr: "msid".to_string(),
+                    value: msid.clone(),
+                });
+            }
         }
 
         let count = ssrcs_tx.len();
@@ -1265,6 +1604,14 @@ impl AsSdpMediaLine for Media {
                     ssrcs: vec![*ssrc, *ssrc_rtx],
                 });
             }
+            // a=ssrc-group:FEC-FR <media ssrc> <fec ssrc> (RFC 5956)
+            let ssrc_fec = ssrcs_fec.iter().find(|(s, _)| s == ssrc).map(|(_, f)| f);
+            if let Some(ssrc_fec) = ssrc_fec.filter(|_| has_flexfec) {
+                attrs.push(MediaAttribute::SsrcGroup {
+                    semantics: "FEC-FR".to_string(),
+                    ssrcs: vec![*ssrc, *ssrc_fec],
+                });
+            }
         } else {
             // TODO: handle simulcast
         }
@@ -1285,12 +1632,14 @@ impl From<MediaKind> for MediaType {
         match value {
             MediaKind::Audio => MediaType::Audio,
             MediaKind::Video => MediaType::Video,
+            MediaKind::Application => MediaType::Application,
         }
     }
 }
 
 struct AsSdpParams<'a, 'b> {
     pub candidates: Vec<Candidate>,
+    pub end_of_candidates: bool,
     pub creds: IceCreds,
     pub fingerprint: &'a Fingerprint,
     pub setup: Setup,
@@ -1299,31 +1648,34 @@ struct AsSdpParams<'a, 'b> {
 
 impl<'a, 'b> AsSdpParams<'a, 'b> {
     pub fn new(rtc: &'a Rtc, pending: Option<&'b Changes>) -> Self {
-        let (creds, candidates) = if let Some((new_creds, keep_local_candidates)) =
-            pending.and_then(|p| p.ice_restart())
-        {
-            if keep_local_candidates {
-                // If we are performing an ICE restart and we are keeping the same
-                // candidates we need to use ufrag from the new ICE credentials
-                // in our offer.
-                let mut new_candidates = rtc.ice.local_candidates().to_vec();
-                for c in &mut new_candidates {
-                    c.set_ufrag(&new_creds.ufrag);
-                }
+        // An ICE restart starts over, the new session has not ended its candidates.
+        let (creds, candidates, end_of_candidates) =
+            if let Some((new_creds, keep_local_candidates)) = pending.and_then(|p| p.ice_restart())
+            {
+                if keep_local_candidates {
+                    // If we are performing an ICE restart and we are keeping the same
+                    // candidates we need to use ufrag from the new ICE credentials
+                    // in our offer.
+                    let mut new_candidates = rtc.ice.local_candidates().to_vec();
+                    for c in &mut new_candidates {
+                        c.set_ufrag(&new_creds.ufrag);
+                    }
 
-                (new_creds, new_candidates)
+                    (new_creds, new_candidates, false)
+                } else {
+                    (new_creds, vec![], false)
+                }
             } else {
-                (new_creds, vec![])
-            }
-        } else {
-            (
-                rtc.ice.local_credentials().clone(),
-                rtc.ice.local_candidates().to_vec(),
-            )
-        };
+                (
+                    rtc.ice.local_credentials().clone(),
+                    rtc.ice.local_candidates().to_vec(),
+                    rtc.ice.local_end_of_candidates(),
+                )
+            };
 
         AsSdpParams {
             candidates,
+            end_of_candidates,
             creds,
             fingerprint: rtc.dtls.local_fingerprint(),
             setup: match rtc.dtls.is_active() {
@@ -1338,7 +1690,7 @@ impl<'a, 'b> AsSdpParams<'a, 'b> {
     fn media_attributes(&self, include_candidates: bool) -> Vec<MediaAttribute> {
         use MediaAttribute::*;
 
-        let mut v = if include_candidates {
+        let mut v: Vec<_> = if include_candidates {
             self.candidates
                 .iter()
                 .map(|c| Candidate(c.clone()))
@@ -1347,6 +1699,10 @@ impl<'a, 'b> AsSdpParams<'a, 'b> {
             vec![]
         };
 
+        if include_candidates && self.end_of_candidates {
+            v.push(EndOfCandidates);
+        }
+
         v.push(IceUfrag(self.creds.ufrag.clone()));
         v.push(IcePwd(self.creds.pass.clone()));
         v.push(IceOptions("trickle".into()));
@@ -1408,7 +1764,7 @@ impl Changes {
                 use Change::*;
                 match m {
                     AddMedia(v) if v.mid == mid => {
-                        if !l.typ.is_media() {
+                        if !l.is_media() {
                             return Some(format!(
                                 "Answer m-line for mid ({}) is not of media type: {:?}",
                                 mid, l.typ
@@ -1417,7 +1773,7 @@ impl Changes {
                         continue 'next;
                     }
                     AddApp(v) if *v == mid => {
-                        if !l.typ.is_channel() {
+                        if !l.is_channel() {
                             return Some(format!(
                                 "Answer m-line for mid ({}) is not a data channel: {:?}",
                                 mid, l.typ
@@ -1466,6 +1822,23 @@ impl Changes {
         }
     }
 
+    fn flexfec_ssrcs_for_mid(&self, mid: Mid) -> Vec<(Ssrc, Ssrc)> {
+        let maybe_add_media = self.0.iter().find_map(|c| match c {
+            Change::AddMedia(m) if m.mid == mid => Some(m),
+            _ => None,
+        });
+
+        let Some(m) = maybe_add_media else {
+            return vec![];
+        };
+
+        m.ssrcs
+            .iter()
+            .zip(m.flexfec_ssrc)
+            .map(|((ssrc, _), fec)| (*ssrc, fec))
+            .collect()
+    }
+
     fn ssrcs_for_mid(&self, mid: Mid) -> &[(Ssrc, Option<Ssrc>)] {
         let maybe_add_media = self
             .0
@@ -1551,6 +1924,25 @@ mod test {
         assert!(matches!(r, Err(RtcError::ChangesOutOfOrder)));
     }
 
+    #[test]
+    fn end_of_candidates_in_sdp() {
+        let mut rtc1 = Rtc::new();
+        let mut rtc2 = Rtc::new();
+
+        let a = "1.2.3.4:5000".parse().unwrap();
+        rtc1.add_local_candidate(crate::Candidate::host(a, "udp").unwrap());
+        rtc1.set_local_end_of_candidates();
+
+        let mut change = rtc1.sdp_api();
+        change.add_channel("ch1".into());
+        let (offer, _) = change.apply().unwrap();
+
+        assert!(offer.to_sdp_string().contains("a=end-of-candidates"));
+
+        rtc2.sdp_api().accept_offer(offer).unwrap();
+        assert!(rtc2.ice.remote_end_of_candidates());
+    }
+
     #[test]
     fn sdp_api_merge_works() {
         let mut rtc = Rtc::new();
@@ -1567,6 +1959,158 @@ mod test {
         assert_eq!(new_offer.media_lines.len(), 2);
     }
 
+    #[test]
+    fn sdp_api_queues_while_offer_outstanding() {
+        let mut rtc1 = Rtc::new();
+        let mut rtc2 = Rtc::new();
+
+        let mut changes = rtc1.sdp_api();
+        changes.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+        let (offer1, pending1) = changes.apply().unwrap();
+
+        // Changes while the offer is outstanding are queued.
+        let mut changes = rtc1.sdp_api();
+        changes.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+        assert!(changes.apply().is_none());
+
+        let mut changes = rtc1.sdp_api();
+        changes.add_channel("ch1".into());
+        changes.add_channel("ch2".into());
+        assert!(changes.apply().is_none());
+
+        let mut changes = rtc1.sdp_api();
+        changes.add_channel("ch3".into());
+        assert!(changes.apply().is_none());
+
+        assert!(rtc1.sdp_api().has_changes());
+
+        let answer1 = rtc2.sdp_api().accept_offer(offer1).unwrap();
+        rtc1.sdp_api().accept_answer(pending1, answer1).unwrap();
+
+        // All queued changes are batched into the next offer.
+        let changes = rtc1.sdp_api();
+        assert!(changes.has_changes());
+        let (offer2, _) = changes.apply().unwrap();
+
+        let kinds: Vec<_> = offer2.media_lines.iter().map(|m| m.typ.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![MediaType::Audio, MediaType::Video, MediaType::Application]
+        );
+
+        assert!(!rtc1.sdp_api().has_changes());
+    }
+
+    #[test]
+    fn sdp_api_dropped_pending_is_not_outstanding() {
+        let mut rtc = Rtc::new();
+
+        let mut changes = rtc.sdp_api();
+        changes.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+        let (_, pending) = changes.apply().unwrap();
+
+        // Rolling back by dropping the pending offer.
+        drop(pending);
+
+        let mut changes = rtc.sdp_api();
+        changes.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+        let (offer, _) = changes.apply().unwrap();
+
+        assert_eq!(offer.media_lines.len(), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "openssl")]
+    fn dtls_cert_not_replaced_while_offer_outstanding() {
+        use crate::dtls::{DtlsCert, DtlsError};
+
+        let mut rtc = Rtc::new();
+
+        let mut changes = rtc.sdp_api();
+        changes.add_channel("ch".into());
+        let (_, pending) = changes.apply().unwrap();
+
+        // The offer carries the fingerprint of the current certificate.
+        let err = rtc.set_dtls_cert(DtlsCert::new_openssl());
+        assert!(matches!(
+            err,
+            Err(RtcError::Dtls(DtlsError::CertificateInUse))
+        ));
+
+        drop(pending);
+
+        rtc.set_dtls_cert(DtlsCert::new_openssl()).unwrap();
+    }
+
+    #[test]
+    fn configured_cname_used_for_all_media() {
+        let mut rtc = Rtc::builder().set_cname(Some("stable".into())).build();
+
+        let mut changes = rtc.sdp_api();
+        changes.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+        changes.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+        let (offer, _) = changes.apply().unwrap();
+
+        let cnames: Vec<_> = offer
+            .media_lines
+            .iter()
+            .flat_map(|m| m.attrs.iter())
+            .filter_map(|a| match a {
+                MediaAttribute::Ssrc { attr, value, .. } if attr == "cname" => Some(value),
+                _ => None,
+            })
+            .collect();
+
+        assert!(!cnames.is_empty());
+        assert!(cnames.iter().all(|c| *c == "stable"));
+    }
+
+    #[test]
+    fn warn_when_bundle_rejected() {
+        let mut rtc1 = Rtc::new();
+        let mut rtc2 = Rtc::new();
+
+        let mut changes = rtc1.sdp_api();
+        changes.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+        changes.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+        let (offer, _) = changes.apply().unwrap();
+
+        let unbundled: String = offer
+            .to_sdp_string()
+            .split_inclusive("\r\n")
+            .filter(|l| !l.starts_with("a=group:BUNDLE"))
+            .collect();
+        let offer = SdpOffer::from_sdp_string(&unbundled).unwrap();
+
+        rtc2.sdp_api().accept_offer(offer).unwrap();
+
+        let warning = std::iter::from_fn(|| rtc2.session.poll_event()).find_map(|e| match e {
+            crate::Event::Warning(Warning::BundleRejected(mids)) => Some(mids),
+            _ => None,
+        });
+        assert_eq!(warning.map(|m| m.len()), Some(2));
+    }
+
+    #[test]
+    fn rtcp_rsize_negotiation() {
+        let mut rtc1 = Rtc::builder().build();
+        let mut rtc2 = Rtc::builder().set_rtcp_rsize(false).build();
+
+        let mut changes = rtc1.sdp_api();
+        changes.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+        let (offer, pending) = changes.apply().unwrap();
+
+        assert!(offer.media_lines.iter().all(|m| m.rtcp_rsize()));
+
+        let answer = rtc2.sdp_api().accept_offer(offer).unwrap();
+        assert!(answer.media_lines.iter().all(|m| !m.rtcp_rsize()));
+
+        rtc1.sdp_api().accept_answer(pending, answer).unwrap();
+
+        assert!(!rtc1.session.is_rtcp_rsize());
+        assert!(!rtc2.session.is_rtcp_rsize());
+    }
+
     #[test]
     fn test_rtp_payload_priority() {
         let mut rtc1 = Rtc::builder()
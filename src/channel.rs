@@ -2,12 +2,13 @@
 
 use std::{fmt, str, time::Instant};
 
-use crate::sctp::RtcSctp;
+use crate::sctp::{RtcSctp, SctpError};
 use crate::util::already_happened;
 use crate::{Rtc, RtcError};
 
-pub use crate::sctp::ChannelConfig;
 pub use crate::sctp::Reliability;
+pub use crate::sctp::{ChannelCloseReason, SctpCloseReason, SctpRto};
+pub use crate::sctp::{ChannelConfig, ChannelPriority};
 
 /// Identifier of a data channel.
 ///
@@ -34,6 +35,49 @@ pub struct ChannelData {
     pub data: Vec<u8>,
 }
 
+/// SCTP parameters negotiated in the SDP.
+///
+/// This is obtained via [`Rtc::sctp_params()`][crate::Rtc::sctp_params()] once the
+/// SCTP association is established.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SctpParams {
+    /// The sctp-port in the local SDP.
+    pub local_port: u16,
+
+    /// The sctp-port in the remote SDP.
+    pub remote_port: u16,
+
+    /// The max-message-size in the local SDP.
+    pub local_max_message_size: usize,
+
+    /// The max-message-size in the remote SDP, which is the largest message we can
+    /// send in one [`Channel::write()`].
+    ///
+    /// `None` means the remote peer accepts messages of any size. Defaults to 65536
+    /// when the remote SDP doesn't say (RFC 8841).
+    pub remote_max_message_size: Option<usize>,
+}
+
+/// Transport statistics of the SCTP association carrying the data channels.
+///
+/// This is obtained via [`Rtc::sctp_stats()`][crate::Rtc::sctp_stats()], to diagnose data
+/// channel throughput problems. Counts are totals since the association started.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SctpStats {
+    /// SCTP packets sent.
+    pub packets_sent: u64,
+    /// SCTP packets received.
+    pub packets_received: u64,
+    /// DATA chunks sent, including retransmissions.
+    pub data_chunks_sent: u64,
+    /// DATA chunks sent again, because they were lost or acknowledged late.
+    pub data_chunks_retransmitted: u64,
+    /// Bytes written on all channels, but not yet sent and acknowledged.
+    pub buffered_amount: usize,
+    /// The [buffered amount][Channel::buffered_amount()] of each open channel.
+    pub channels: Vec<(ChannelId, usize)>,
+}
+
 /// Channel for sending data to the remote peer.
 ///
 /// Get this handle from [`Rtc::channel()`][crate::Rtc::channel()].
@@ -51,9 +95,112 @@ impl<'a> Channel<'a> {
     }
 
     /// Write data to the remote peer and indicate whether it's text or binary.
+    ///
+    /// Fails with [`SctpError::MessageTooLarge`] if `buf` is larger than the
+    /// [max-message-size][SctpParams::remote_max_message_size] of the remote peer.
+    ///
+    /// While a channel of higher [priority][ChannelPriority] has data to send, the message
+    /// is held back instead. If that backlog is full, this fails with
+    /// [`SctpError::WouldBlock`]. A held back message that fails once written is reported
+    /// with [`Event::ChannelWriteFailed`][crate::Event::ChannelWriteFailed].
     pub fn write(&mut self, binary: bool, buf: &[u8]) -> Result<usize, RtcError> {
+        self.check_message_size(buf.len())?;
         Ok(self.rtc.sctp.write(self.sctp_stream_id, binary, buf)?)
     }
+
+    fn check_message_size(&self, size: usize) -> Result<(), SctpError> {
+        // A max-message-size of 0 means the remote accepts messages of any size.
+        let max = match self.rtc.session.remote_sctp {
+            Some((_, max)) if max > 0 => max,
+            _ => return Ok(()),
+        };
+
+        if size > max {
+            return Err(SctpError::MessageTooLarge { size, max });
+        }
+
+        Ok(())
+    }
+
+    /// Number of bytes written, but not yet sent and acknowledged by the remote peer.
+    ///
+    /// Like `RTCDataChannel.bufferedAmount` in the browser API, to apply backpressure
+    /// on the sender. See [`Channel::set_buffered_amount_low_threshold()`].
+    pub fn buffered_amount(&mut self) -> usize {
+        self.rtc.sctp.buffered_amount(self.sctp_stream_id)
+    }
+
+    /// The threshold for [`Event::ChannelBufferedAmountLow`][crate::Event::ChannelBufferedAmountLow].
+    ///
+    /// Defaults to 0.
+    pub fn buffered_amount_low_threshold(&self) -> usize {
+        self.rtc
+            .sctp
+            .buffered_amount_low_threshold(self.sctp_stream_id)
+    }
+
+    /// Set the threshold for [`Event::ChannelBufferedAmountLow`][crate::Event::ChannelBufferedAmountLow].
+    ///
+    /// The event is emitted when the [buffered amount][Channel::buffered_amount()] drops
+    /// to or below the threshold, like `bufferedamountlow` in the browser API.
+    ///
+    /// ```no_run
+    /// # use str0m::Rtc;
+    /// # use str0m::channel::ChannelId;
+    /// # let mut rtc = Rtc::new();
+    /// # let cid: ChannelId = todo!();
+    /// let mut channel = rtc.channel(cid).unwrap();
+    /// channel.set_buffered_amount_low_threshold(64 * 1024);
+    ///
+    /// // Stop writing when much is buffered, and resume on Event::ChannelBufferedAmountLow.
+    /// if channel.buffered_amount() > 1024 * 1024 {
+    ///     // pause the sender
+    /// }
+    /// ```
+    pub fn set_buffered_amount_low_threshold(&mut self, threshold: usize) {
+        self.rtc
+            .sctp
+            .set_buffered_amount_low_threshold(self.sctp_stream_id, threshold);
+    }
+
+    /// Write data with a [`Reliability`] for this message only, overriding the channel
+    /// setting (PR-SCTP, RFC 3758).
+    ///
+    /// This allows mixing reliable and lossy messages on one channel, like game state
+    /// updates that may be dropped in between reliable events. The ordering of the
+    /// channel is not affected.
+    ///
+    /// The SCTP stack applies the reliability per stream, also to messages that are
+    /// buffered but not yet acknowledged. A message is therefore never sent less reliably
+    /// than asked for, but a lossy message written while more reliable messages are
+    /// buffered is sent with the stronger setting. Once the buffered messages drain, the
+    /// stream goes back to the channel setting.
+    ///
+    /// ```no_run
+    /// # use str0m::Rtc;
+    /// # use str0m::channel::{ChannelId, Reliability};
+    /// # let mut rtc = Rtc::new();
+    /// # let cid: ChannelId = todo!();
+    /// let mut channel = rtc.channel(cid).unwrap();
+    ///
+    /// // Give up on this position update after 100ms.
+    /// let lossy = Reliability::MaxPacketLifetime { lifetime: 100 };
+    /// channel.write_with_reliability(true, b"pos", lossy).unwrap();
+    /// ```
+    pub fn write_with_reliability(
+        &mut self,
+        binary: bool,
+        buf: &[u8],
+        reliability: Reliability,
+    ) -> Result<usize, RtcError> {
+        self.check_message_size(buf.len())?;
+        Ok(self.rtc.sctp.write_with_reliability(
+            self.sctp_stream_id,
+            binary,
+            buf,
+            Some(reliability),
+        )?)
+    }
 }
 
 impl fmt::Debug for ChannelData {
@@ -210,10 +357,12 @@ impl ChannelHandler {
         // stream identifiers, and the server picks odd stream identifiers).
         let base = if sctp.is_client() { 0 } else { 1 };
 
+        // Ids of closed channels are taken until their stream reset is done.
         let mut taken: Vec<u16> = self
             .allocations
             .iter()
             .filter_map(|a| a.sctp_stream_id)
+            .chain(sctp.resetting_ids().iter().copied())
             .collect();
 
         for a in &mut self.allocations {
@@ -285,6 +434,27 @@ impl ChannelHandler {
         }
     }
 
+    pub fn close_channel_with_reason(
+        &mut self,
+        id: ChannelId,
+        reason: ChannelCloseReason,
+        sctp: &mut RtcSctp,
+    ) {
+        if let Some(sctp_stream_id) = self
+            .allocations
+            .iter()
+            .find(|a| a.id == id)
+            .and_then(|s| s.sctp_stream_id)
+        {
+            sctp.close_stream_with_reason(sctp_stream_id, reason);
+        }
+    }
+
+    /// Number of channels, open or about to be.
+    pub fn len(&self) -> usize {
+        self.allocations.len()
+    }
+
     pub fn remove_channel(&mut self, id: ChannelId) {
         self.allocations.retain(|a| a.id != id)
     }
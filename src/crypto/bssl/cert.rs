@@ -0,0 +1,154 @@
+use std::time::{Duration, SystemTime};
+
+use boring::asn1::{Asn1Integer, Asn1Time, Asn1Type};
+use boring::bn::BigNum;
+use boring::error::ErrorStack;
+use boring::hash::MessageDigest;
+use boring::nid::Nid;
+use boring::pkey::{PKey, Private};
+use boring::rsa::Rsa;
+use boring::x509::{X509Name, X509Ref, X509};
+
+use crate::crypto::dtls::DTLS_CERT_IDENTITY;
+use crate::crypto::{Fingerprint, FingerprintHash};
+
+use super::CryptoError;
+
+const RSA_F4: u32 = 0x10001;
+
+/// Certificate used for DTLS, backed by BoringSSL.
+#[derive(Debug, Clone)]
+pub struct BsslDtlsCert {
+    pub(crate) pkey: PKey<Private>,
+    pub(crate) x509: X509,
+}
+
+impl BsslDtlsCert {
+    /// Creates a new (self signed) DTLS certificate.
+    pub fn new() -> Self {
+        Self::self_signed().expect("create dtls cert")
+    }
+
+    /// Creates a DTLS certificate from an existing PEM encoded certificate and private key.
+    pub fn from_pem(cert: &[u8], key: &[u8]) -> Result<Self, CryptoError> {
+        let x509 = X509::from_pem(cert)?;
+        let pkey = PKey::private_key_from_pem(key)?;
+        Self::from_parts(pkey, x509)
+    }
+
+    /// Creates a DTLS certificate from an existing DER encoded certificate and private key.
+    pub fn from_der(cert: &[u8], key: &[u8]) -> Result<Self, CryptoError> {
+        let x509 = X509::from_der(cert)?;
+        let pkey = PKey::private_key_from_der(key)?;
+        Self::from_parts(pkey, x509)
+    }
+
+    fn from_parts(pkey: PKey<Private>, x509: X509) -> Result<Self, CryptoError> {
+        // The private key must be the one the certificate was issued for, or
+        // the DTLS handshake will fail in confusing ways later.
+        if !x509.public_key()?.public_eq(&pkey) {
+            return Err(CryptoError::CertificateKeyMismatch);
+        }
+
+        Ok(Self { pkey, x509 })
+    }
+
+    // The libWebRTC code we try to match is at:
+    // https://webrtc.googlesource.com/src/+/1568f1b1330f94494197696fe235094e6293b258/rtc_base/openssl_certificate.cc#58
+    fn self_signed() -> Result<Self, CryptoError> {
+        let f4 = BigNum::from_u32(RSA_F4).unwrap();
+        let key = Rsa::generate_with_e(2048, &f4)?;
+        let pkey = PKey::from_rsa(key)?;
+
+        let mut x509b = X509::builder()?;
+        x509b.set_version(2)?; // X509.V3 (zero indexed)
+
+        // For Firefox, the serial number must be unique across all certificates, including those of other
+        // processes/machines! See https://github.com/versatica/mediasoup/issues/127#issuecomment-474460153
+        // and https://github.com/algesten/str0m/issues/517
+        let mut serial_buf = [0u8; 16];
+        boring::rand::rand_bytes(&mut serial_buf)?;
+
+        let serial_bn = BigNum::from_slice(&serial_buf)?;
+        let serial = Asn1Integer::from_bn(&serial_bn)?;
+        x509b.set_serial_number(&serial)?;
+        let before = Asn1Time::from_unix(unix_time() - 3600)?;
+        x509b.set_not_before(&before)?;
+        let after = Asn1Time::days_from_now(7)?;
+        x509b.set_not_after(&after)?;
+        x509b.set_pubkey(&pkey)?;
+
+        // The libWebRTC code for this is:
+        //
+        // !X509_NAME_add_entry_by_NID(name.get(), NID_commonName, MBSTRING_UTF8,
+        // (unsigned char*)params.common_name.c_str(), -1, -1, 0) ||
+        //
+        // libWebRTC allows this name to be configured by the user of the library.
+        // That's a future TODO for str0m.
+        let mut nameb = X509Name::builder()?;
+        nameb.append_entry_by_nid_with_type(
+            Nid::COMMONNAME,
+            DTLS_CERT_IDENTITY,
+            Asn1Type::UTF8STRING,
+        )?;
+
+        let name = nameb.build();
+
+        x509b.set_subject_name(&name)?;
+        x509b.set_issuer_name(&name)?;
+
+        x509b.sign(&pkey, MessageDigest::sha1())?;
+        let x509 = x509b.build();
+
+        Ok(BsslDtlsCert { pkey, x509 })
+    }
+
+    /// When the certificate expires.
+    pub fn not_after(&self) -> SystemTime {
+        let epoch = Asn1Time::from_unix(0).expect("unix epoch as asn1 time");
+        let diff = epoch
+            .diff(self.x509.not_after())
+            .expect("diff of asn1 times");
+
+        let secs = diff.days as i64 * 86_400 + diff.secs as i64;
+
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+    }
+
+    /// Produce a (public) fingerprint of the cert.
+    ///
+    /// This is sent via SDP to the other peer to lock down the DTLS
+    /// to this specific certificate.
+    pub fn fingerprint(&self) -> Fingerprint {
+        self.fingerprint_with_hash(FingerprintHash::Sha256)
+    }
+
+    /// Produce a fingerprint of the cert using the given hash function.
+    pub fn fingerprint_with_hash(&self, hash: FingerprintHash) -> Fingerprint {
+        fingerprint_of(&self.x509, hash).expect("digest to fingerprint")
+    }
+}
+
+/// Fingerprint of a certificate using the given hash function.
+pub fn fingerprint_of(x509: &X509Ref, hash: FingerprintHash) -> Result<Fingerprint, ErrorStack> {
+    let md = match hash {
+        FingerprintHash::Sha256 => MessageDigest::sha256(),
+        FingerprintHash::Sha384 => MessageDigest::sha384(),
+        FingerprintHash::Sha512 => MessageDigest::sha512(),
+    };
+
+    let digest: &[u8] = &x509.digest(md)?;
+
+    Ok(Fingerprint {
+        hash_func: hash.as_str().into(),
+        bytes: digest.to_vec(),
+    })
+}
+
+// See the TODO for the same function in the OpenSSL implementation.
+fn unix_time() -> i64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use boring::ssl::{Ssl, SslContext, SslContextBuilder, SslMethod, SslOptions, SslVerifyMode};
+
+use crate::crypto::dtls::DtlsInner;
+use crate::crypto::dtls::DtlsOptions;
+use crate::crypto::io_buf::IoBuffer;
+use crate::crypto::{DtlsEvent, SrtpProfile};
+use crate::io::DATAGRAM_MTU;
+
+use super::cert::BsslDtlsCert;
+use super::stream::TlsStream;
+use super::CryptoError;
+
+// BoringSSL has no DHE, and only ECDHE suites are relevant for WebRTC.
+const DTLS_CIPHERS: &str = "ECDHE+AESGCM:ECDHE+AES256";
+const DTLS_CURVES: &str = "P-256";
+
+pub struct BsslDtlsImpl {
+    /// Certificate for the DTLS session.
+    _cert: BsslDtlsCert,
+
+    /// Context belongs together with Fingerprint.
+    ///
+    /// This just needs to be kept alive since it pins the entire BoringSSL context
+    /// from which `Ssl` is created.
+    _context: SslContext,
+
+    /// The actual BoringSSL TLS stream.
+    tls: TlsStream<IoBuffer>,
+}
+
+impl BsslDtlsImpl {
+    pub fn new(cert: BsslDtlsCert, options: &DtlsOptions) -> Result<Self, super::CryptoError> {
+        let context = dtls_create_ctx(&cert, options)?;
+        let ssl = dtls_ssl_create(&context)?;
+        Ok(BsslDtlsImpl {
+            _cert: cert,
+            _context: context,
+            tls: TlsStream::new(ssl, IoBuffer::default()),
+        })
+    }
+}
+
+impl DtlsInner for BsslDtlsImpl {
+    fn set_active(&mut self, active: bool) {
+        self.tls.set_active(active);
+    }
+
+    fn is_active(&self) -> Option<bool> {
+        self.tls.is_active()
+    }
+
+    fn handle_receive(&mut self, m: &[u8], o: &mut VecDeque<DtlsEvent>) -> Result<(), CryptoError> {
+        self.tls.inner_mut().set_incoming(m);
+
+        if self.handle_handshake(o)? {
+            // early return as long as we're handshaking
+            return Ok(());
+        }
+
+        // Unlike OpenSSL, BoringSSL rejects renegotiation, so there is never any
+        // new keying material after the first handshake.
+        let mut buf = vec![0; 2000];
+        let n = match self.tls.read(&mut buf) {
+            Ok(v) => v,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        buf.truncate(n);
+
+        o.push_back(DtlsEvent::Data(buf));
+
+        Ok(())
+    }
+
+    fn poll_datagram(&mut self) -> Option<crate::net::DatagramSend> {
+        let x = self.tls.inner_mut().pop_outgoing();
+        if let Some(x) = &x {
+            trace!("Poll datagram: {}", x.len());
+        }
+        x
+    }
+
+    fn handle_input(&mut self, data: &[u8]) -> Result<(), CryptoError> {
+        Ok(self.tls.write_all(data)?)
+    }
+
+    fn is_connected(&self) -> bool {
+        self.tls.is_connected()
+    }
+
+    fn rekey(&mut self) -> Result<(), CryptoError> {
+        warn!("DTLS re-key is not possible, BoringSSL doesn't support renegotiation");
+        Ok(())
+    }
+
+    fn handle_handshake(&mut self, output: &mut VecDeque<DtlsEvent>) -> Result<bool, CryptoError> {
+        if self.tls.is_handshaken() {
+            // Nice. Nothing to do.
+            Ok(false)
+        } else if self.tls.complete_handshake_until_block()? {
+            output.push_back(DtlsEvent::Connected);
+
+            let (keying_material, srtp_profile, fingerprint) = self
+                .tls
+                .take_srtp_keying_material()
+                .expect("Exported keying material");
+
+            output.push_back(DtlsEvent::RemoteFingerprint(fingerprint));
+
+            output.push_back(DtlsEvent::SrtpKeyingMaterial(keying_material, srtp_profile));
+            Ok(false)
+        } else {
+            Ok(true)
+        }
+    }
+}
+
+pub fn dtls_create_ctx(
+    cert: &BsslDtlsCert,
+    options: &DtlsOptions,
+) -> Result<SslContext, CryptoError> {
+    if options.fips && !super::fips_enabled() {
+        return Err(CryptoError::FipsNotEnabled);
+    }
+
+    let mut ctx = SslContextBuilder::new(SslMethod::dtls())?;
+
+    // Unlike OpenSSL, BoringSSL lets us set the minimum version directly.
+    ctx.set_min_proto_version(Some(boring::ssl::SslVersion::DTLS1_2))?;
+
+    let cipher_list = options.cipher_list();
+    ctx.set_cipher_list(cipher_list.as_deref().unwrap_or(DTLS_CIPHERS))?;
+
+    let curves_list = options.groups_list();
+    ctx.set_curves_list(curves_list.as_deref().unwrap_or(DTLS_CURVES))?;
+
+    let srtp_profiles = {
+        let all: Vec<_> = options
+            .srtp_profiles()
+            .map(SrtpProfile::boringssl_name)
+            .collect();
+
+        all.join(":")
+    };
+    ctx.set_tlsext_use_srtp(&srtp_profiles)?;
+
+    let mut mode = SslVerifyMode::empty();
+    mode.insert(SslVerifyMode::PEER);
+    mode.insert(SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+    ctx.set_verify_callback(mode, |_ok, _ctx| true);
+
+    ctx.set_private_key(&cert.pkey)?;
+    ctx.set_certificate(&cert.x509)?;
+
+    let mut ssl_options = SslOptions::empty();
+    ssl_options.insert(SslOptions::NO_DTLSV1);
+    ctx.set_options(ssl_options);
+
+    if let Some(keylog) = options.keylog.clone() {
+        ctx.set_keylog_callback(move |_ssl, line| keylog.log(line));
+    }
+
+    let ctx = ctx.build();
+
+    Ok(ctx)
+}
+
+pub fn dtls_ssl_create(ctx: &SslContext) -> Result<Ssl, CryptoError> {
+    let mut ssl = Ssl::new(ctx)?;
+    ssl.set_mtu(DATAGRAM_MTU as u32)?;
+
+    Ok(ssl)
+}
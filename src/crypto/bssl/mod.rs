@@ -0,0 +1,33 @@
+//! BoringSSL implementation of cryptographic functions.
+
+use super::{CryptoError, SrtpProfile};
+
+mod cert;
+pub use cert::BsslDtlsCert;
+
+mod stream;
+
+mod dtls;
+pub use dtls::BsslDtlsImpl;
+
+// Unused when the pure Rust SRTP ciphers take over.
+#[cfg_attr(feature = "rust-crypto-srtp", allow(dead_code))]
+mod srtp;
+pub use srtp::BsslSrtpCryptoImpl;
+
+/// Whether BoringSSL is built and running as BoringCrypto in FIPS mode.
+pub fn fips_enabled() -> bool {
+    boring::fips::enabled()
+}
+
+impl SrtpProfile {
+    /// What this profile is called in BoringSSL parlance.
+    pub(crate) fn boringssl_name(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "_internal_test_exports")]
+            SrtpProfile::PassThrough => "NULL",
+            SrtpProfile::Aes128CmSha1_80 => "SRTP_AES128_CM_SHA1_80",
+            SrtpProfile::AeadAes128Gcm => "SRTP_AEAD_AES_128_GCM",
+        }
+    }
+}
@@ -2,10 +2,15 @@
 
 use std::collections::VecDeque;
 use std::fmt;
+use std::panic::UnwindSafe;
+use std::sync::Arc;
+use std::time::SystemTime;
 
+use crate::dtls::DtlsError;
 use crate::net::DatagramSend;
 
-use super::{CryptoError, Fingerprint, KeyingMaterial, SrtpProfile};
+use super::{CryptoError, Fingerprint, FingerprintHash, KeyingMaterial, SrtpProfile};
+use super::{DefaultSrtpCrypto, SrtpCrypto};
 
 // libWebRTC says "WebRTC" here when doing OpenSSL, for BoringSSL they seem
 // to generate a random 8 characters.
@@ -15,6 +20,200 @@ use super::{CryptoError, Fingerprint, KeyingMaterial, SrtpProfile};
 // https://github.com/pion/webrtc/blob/eed2bb2d3b9f204f9de1cd7e1046ca5d652778d2/constants.go#L31
 pub const DTLS_CERT_IDENTITY: &str = "WebRTC";
 
+/// Callback receiving the DTLS key material in NSS key log format.
+///
+/// Each invocation is one line such as `CLIENT_RANDOM <random> <master secret>`.
+/// Written to a file, it can be used as the "(Pre)-Master-Secret log filename" in
+/// Wireshark to decrypt captured DTLS traffic, including the SRTP key export.
+///
+/// This leaks the session secrets and is only meant for debugging.
+///
+/// ```
+/// # use str0m::change::DtlsKeyLog;
+/// # use str0m::RtcConfig;
+/// let keylog = DtlsKeyLog::new(|line| eprintln!("{}", line));
+///
+/// let rtc = RtcConfig::new()
+///     .set_dtls_keylog(Some(keylog))
+///     .build();
+/// ```
+#[derive(Clone)]
+pub struct DtlsKeyLog(Arc<dyn Fn(&str) + Send + Sync + 'static>);
+
+impl DtlsKeyLog {
+    /// Create a key log from a callback that is invoked once per key log line.
+    pub fn new(f: impl Fn(&str) + Send + Sync + 'static) -> Self {
+        DtlsKeyLog(Arc::new(f))
+    }
+
+    pub(crate) fn log(&self, line: &str) {
+        (self.0)(line)
+    }
+}
+
+// The callback is only ever invoked with a line, there is no state of ours it could
+// observe broken after a panic.
+impl UnwindSafe for DtlsKeyLog {}
+
+impl fmt::Debug for DtlsKeyLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DtlsKeyLog").finish_non_exhaustive()
+    }
+}
+
+/// A DTLS 1.2 cipher suite.
+///
+/// Used with [`RtcConfig::set_dtls_cipher_suites`][crate::RtcConfig::set_dtls_cipher_suites]
+/// to restrict what is offered and accepted in the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DtlsCipherSuite {
+    /// TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256
+    EcdheEcdsaAes128GcmSha256,
+    /// TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384
+    EcdheEcdsaAes256GcmSha384,
+    /// TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256
+    EcdheEcdsaChacha20Poly1305,
+    /// TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256
+    EcdheRsaAes128GcmSha256,
+    /// TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384
+    EcdheRsaAes256GcmSha384,
+    /// TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256
+    EcdheRsaChacha20Poly1305,
+}
+
+impl DtlsCipherSuite {
+    pub(crate) const ALL: &'static [DtlsCipherSuite] = &[
+        DtlsCipherSuite::EcdheEcdsaAes128GcmSha256,
+        DtlsCipherSuite::EcdheEcdsaAes256GcmSha384,
+        DtlsCipherSuite::EcdheEcdsaChacha20Poly1305,
+        DtlsCipherSuite::EcdheRsaAes128GcmSha256,
+        DtlsCipherSuite::EcdheRsaAes256GcmSha384,
+        DtlsCipherSuite::EcdheRsaChacha20Poly1305,
+    ];
+
+    /// Whether the suite is approved for use in FIPS mode. ChaCha20-Poly1305 is not.
+    pub fn is_fips_approved(&self) -> bool {
+        use DtlsCipherSuite::*;
+        matches!(
+            self,
+            EcdheEcdsaAes128GcmSha256
+                | EcdheEcdsaAes256GcmSha384
+                | EcdheRsaAes128GcmSha256
+                | EcdheRsaAes256GcmSha384
+        )
+    }
+
+    /// The OpenSSL name, which BoringSSL shares.
+    pub(crate) fn openssl_name(&self) -> &'static str {
+        use DtlsCipherSuite::*;
+        match self {
+            EcdheEcdsaAes128GcmSha256 => "ECDHE-ECDSA-AES128-GCM-SHA256",
+            EcdheEcdsaAes256GcmSha384 => "ECDHE-ECDSA-AES256-GCM-SHA384",
+            EcdheEcdsaChacha20Poly1305 => "ECDHE-ECDSA-CHACHA20-POLY1305",
+            EcdheRsaAes128GcmSha256 => "ECDHE-RSA-AES128-GCM-SHA256",
+            EcdheRsaAes256GcmSha384 => "ECDHE-RSA-AES256-GCM-SHA384",
+            EcdheRsaChacha20Poly1305 => "ECDHE-RSA-CHACHA20-POLY1305",
+        }
+    }
+}
+
+/// An elliptic curve group for the DTLS key exchange.
+///
+/// Used with [`RtcConfig::set_dtls_groups`][crate::RtcConfig::set_dtls_groups].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DtlsGroup {
+    /// NIST P-256, aka secp256r1/prime256v1.
+    P256,
+    /// NIST P-384, aka secp384r1.
+    P384,
+    /// Curve25519.
+    X25519,
+}
+
+impl DtlsGroup {
+    pub(crate) const ALL: &'static [DtlsGroup] =
+        &[DtlsGroup::P256, DtlsGroup::P384, DtlsGroup::X25519];
+
+    /// Whether the group is approved for use in FIPS mode. X25519 is not.
+    pub fn is_fips_approved(&self) -> bool {
+        matches!(self, DtlsGroup::P256 | DtlsGroup::P384)
+    }
+
+    /// The OpenSSL name, which BoringSSL shares.
+    pub(crate) fn openssl_name(&self) -> &'static str {
+        match self {
+            DtlsGroup::P256 => "P-256",
+            DtlsGroup::P384 => "P-384",
+            DtlsGroup::X25519 => "X25519",
+        }
+    }
+}
+
+/// Options applied to the DTLS context, regardless of crypto backend.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DtlsOptions {
+    pub keylog: Option<DtlsKeyLog>,
+    pub fingerprint_hash: FingerprintHash,
+    pub cipher_suites: Option<Vec<DtlsCipherSuite>>,
+    pub groups: Option<Vec<DtlsGroup>>,
+    /// Restrict to FIPS approved algorithms, and require the backend in FIPS mode.
+    pub fips: bool,
+}
+
+impl DtlsOptions {
+    /// Checks that the restrictions leave something to negotiate.
+    pub fn validate(&self) -> Result<(), CryptoError> {
+        if self.cipher_list().is_some_and(|l| l.is_empty()) {
+            return Err(CryptoError::NoCipherSuites);
+        }
+        if self.groups_list().is_some_and(|l| l.is_empty()) {
+            return Err(CryptoError::NoGroups);
+        }
+        // The pure Rust SRTP ciphers are not FIPS validated.
+        if self.fips && cfg!(feature = "rust-crypto-srtp") {
+            return Err(CryptoError::FipsNotEnabled);
+        }
+        Ok(())
+    }
+
+    pub fn cipher_list(&self) -> Option<String> {
+        let suites = match &self.cipher_suites {
+            Some(v) => v.as_slice(),
+            None if self.fips => DtlsCipherSuite::ALL,
+            None => return None,
+        };
+        let all: Vec<_> = suites
+            .iter()
+            .filter(|s| !self.fips || s.is_fips_approved())
+            .map(DtlsCipherSuite::openssl_name)
+            .collect();
+        Some(all.join(":"))
+    }
+
+    pub fn groups_list(&self) -> Option<String> {
+        let groups = match &self.groups {
+            Some(v) => v.as_slice(),
+            None if self.fips => DtlsGroup::ALL,
+            None => return None,
+        };
+        let all: Vec<_> = groups
+            .iter()
+            .filter(|g| !self.fips || g.is_fips_approved())
+            .map(DtlsGroup::openssl_name)
+            .collect();
+        Some(all.join(":"))
+    }
+
+    /// The SRTP profiles to offer, most preferred first.
+    pub fn srtp_profiles(&self) -> impl Iterator<Item = &'static SrtpProfile> + '_ {
+        SrtpProfile::ALL
+            .iter()
+            .filter(|p| !self.fips || p.is_fips_approved())
+    }
+}
+
 /// Events arising from a [`Dtls`] instance.
 pub enum DtlsEvent {
     /// When the DTLS has finished handshaking.
@@ -23,10 +222,11 @@ pub enum DtlsEvent {
     /// Keying material for SRTP encryption master key and the selected SRTP profile.
     SrtpKeyingMaterial(KeyingMaterial, SrtpProfile),
 
-    /// The fingerprint of the remote peer.
+    /// The fingerprints of the remote peer, one per supported hash function.
     ///
     /// This should be checked against the fingerprint communicated in the SDP.
-    RemoteFingerprint(Fingerprint),
+    /// The first is always `sha-256`.
+    RemoteFingerprint(Vec<Fingerprint>),
 
     /// Decrypted data from incoming DTLS traffic.
     Data(Vec<u8>),
@@ -40,6 +240,8 @@ pub struct DtlsCert(DtlsCertInner);
 enum DtlsCertInner {
     #[cfg(feature = "openssl")]
     OpenSsl(super::ossl::OsslDtlsCert),
+    #[cfg(feature = "boringssl")]
+    BoringSsl(super::bssl::BsslDtlsCert),
 }
 
 impl DtlsCert {
@@ -50,6 +252,62 @@ impl DtlsCert {
         DtlsCert(DtlsCertInner::OpenSsl(cert))
     }
 
+    /// Create a new BoringSSL variant of the certificate.
+    ///
+    /// Use this with [`RtcConfig::set_dtls_cert`][crate::RtcConfig::set_dtls_cert] to
+    /// run DTLS and SRTP on BoringSSL, which is the crypto stack used by libWebRTC.
+    #[cfg(feature = "boringssl")]
+    pub fn new_boringssl() -> Self {
+        let cert = super::bssl::BsslDtlsCert::new();
+        DtlsCert(DtlsCertInner::BoringSsl(cert))
+    }
+
+    /// Load an existing OpenSSL certificate and private key, PEM encoded.
+    ///
+    /// This allows pinning a long-lived identity, or using certificates issued by
+    /// an organization, instead of generating a new self-signed one. Fails if the
+    /// certificate or key can't be parsed, or if the key doesn't match the certificate.
+    ///
+    /// ```no_run
+    /// # use str0m::change::DtlsCert;
+    /// let cert = std::fs::read("cert.pem").unwrap();
+    /// let key = std::fs::read("key.pem").unwrap();
+    ///
+    /// let dtls_cert = DtlsCert::openssl_from_pem(&cert, &key).unwrap();
+    /// ```
+    #[cfg(feature = "openssl")]
+    pub fn openssl_from_pem(cert: &[u8], key: &[u8]) -> Result<Self, DtlsError> {
+        let cert = super::ossl::OsslDtlsCert::from_pem(cert, key)?;
+        Ok(DtlsCert(DtlsCertInner::OpenSsl(cert)))
+    }
+
+    /// Load an existing OpenSSL certificate and private key, DER encoded.
+    ///
+    /// See [`DtlsCert::openssl_from_pem`].
+    #[cfg(feature = "openssl")]
+    pub fn openssl_from_der(cert: &[u8], key: &[u8]) -> Result<Self, DtlsError> {
+        let cert = super::ossl::OsslDtlsCert::from_der(cert, key)?;
+        Ok(DtlsCert(DtlsCertInner::OpenSsl(cert)))
+    }
+
+    /// Load an existing BoringSSL certificate and private key, PEM encoded.
+    ///
+    /// See [`DtlsCert::openssl_from_pem`].
+    #[cfg(feature = "boringssl")]
+    pub fn boringssl_from_pem(cert: &[u8], key: &[u8]) -> Result<Self, DtlsError> {
+        let cert = super::bssl::BsslDtlsCert::from_pem(cert, key)?;
+        Ok(DtlsCert(DtlsCertInner::BoringSsl(cert)))
+    }
+
+    /// Load an existing BoringSSL certificate and private key, DER encoded.
+    ///
+    /// See [`DtlsCert::openssl_from_pem`].
+    #[cfg(feature = "boringssl")]
+    pub fn boringssl_from_der(cert: &[u8], key: &[u8]) -> Result<Self, DtlsError> {
+        let cert = super::bssl::BsslDtlsCert::from_der(cert, key)?;
+        Ok(DtlsCert(DtlsCertInner::BoringSsl(cert)))
+    }
+
     /// Creates a fingerprint for this certificate.
     ///
     /// Fingerprints are used to verify a remote peer's certificate.
@@ -57,15 +315,98 @@ impl DtlsCert {
         match &self.0 {
             #[cfg(feature = "openssl")]
             DtlsCertInner::OpenSsl(v) => v.fingerprint(),
+            #[cfg(feature = "boringssl")]
+            DtlsCertInner::BoringSsl(v) => v.fingerprint(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Creates a fingerprint for this certificate using the given hash function.
+    ///
+    /// ```
+    /// # use str0m::change::{DtlsCert, FingerprintHash};
+    /// let cert = DtlsCert::new_openssl();
+    /// let fingerprint = cert.fingerprint_with_hash(FingerprintHash::Sha384);
+    ///
+    /// assert_eq!(fingerprint.hash_func, "sha-384");
+    /// assert_eq!(fingerprint.bytes.len(), 48);
+    /// ```
+    pub fn fingerprint_with_hash(&self, hash: FingerprintHash) -> Fingerprint {
+        match &self.0 {
+            #[cfg(feature = "openssl")]
+            DtlsCertInner::OpenSsl(v) => v.fingerprint_with_hash(hash),
+            #[cfg(feature = "boringssl")]
+            DtlsCertInner::BoringSsl(v) => v.fingerprint_with_hash(hash),
+            _ => unreachable!(),
+        }
+    }
+
+    /// The SRTP ciphers of the same crypto backend as this certificate.
+    ///
+    /// Only differs from the default when both `openssl` and `boringssl` are enabled.
+    pub(crate) fn srtp_crypto(&self) -> Arc<dyn SrtpCrypto> {
+        match &self.0 {
+            #[cfg(all(
+                feature = "openssl",
+                feature = "boringssl",
+                not(feature = "rust-crypto-srtp")
+            ))]
+            DtlsCertInner::BoringSsl(_) => Arc::new(super::srtp::BoringSslSrtpCrypto),
+            _ => Arc::new(DefaultSrtpCrypto),
+        }
+    }
+
+    /// Whether the crypto backend of this certificate is operating in FIPS mode.
+    ///
+    /// For OpenSSL this means the FIPS provider is loaded and used by default, for
+    /// BoringSSL that it is built as BoringCrypto. Check this up front when using
+    /// [`RtcConfig::set_fips_mode`][crate::RtcConfig::set_fips_mode].
+    pub fn is_fips_mode(&self) -> bool {
+        match &self.0 {
+            #[cfg(feature = "openssl")]
+            DtlsCertInner::OpenSsl(_) => super::ossl::fips_enabled(),
+            #[cfg(feature = "boringssl")]
+            DtlsCertInner::BoringSsl(_) => super::bssl::fips_enabled(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// When this certificate expires.
+    ///
+    /// Certificates generated by str0m are valid for 7 days. Long running servers
+    /// sharing one certificate across sessions should rotate it before that, using
+    /// [`RtcConfig::set_dtls_cert`][crate::RtcConfig::set_dtls_cert] for new sessions
+    /// and [`Rtc::set_dtls_cert`][crate::Rtc::set_dtls_cert] for existing ones.
+    ///
+    /// ```
+    /// # use std::time::{Duration, SystemTime};
+    /// # use str0m::change::DtlsCert;
+    /// let cert = DtlsCert::new_openssl();
+    ///
+    /// let rotate_at = cert.not_after() - Duration::from_secs(24 * 3600);
+    /// assert!(SystemTime::now() < rotate_at);
+    /// ```
+    pub fn not_after(&self) -> SystemTime {
+        match &self.0 {
+            #[cfg(feature = "openssl")]
+            DtlsCertInner::OpenSsl(v) => v.not_after(),
+            #[cfg(feature = "boringssl")]
+            DtlsCertInner::BoringSsl(v) => v.not_after(),
             _ => unreachable!(),
         }
     }
 
-    pub(crate) fn create_dtls_impl(&self) -> Result<DtlsImpl, CryptoError> {
+    pub(crate) fn create_dtls_impl(&self, options: &DtlsOptions) -> Result<DtlsImpl, CryptoError> {
         match &self.0 {
             #[cfg(feature = "openssl")]
             DtlsCertInner::OpenSsl(c) => Ok(DtlsImpl::OpenSsl(super::ossl::OsslDtlsImpl::new(
                 c.clone(),
+                options,
+            )?)),
+            #[cfg(feature = "boringssl")]
+            DtlsCertInner::BoringSsl(c) => Ok(DtlsImpl::BoringSsl(super::bssl::BsslDtlsImpl::new(
+                c.clone(),
+                options,
             )?)),
             _ => unreachable!(),
         }
@@ -77,6 +418,8 @@ impl fmt::Debug for DtlsCert {
         match &self.0 {
             #[cfg(feature = "openssl")]
             DtlsCertInner::OpenSsl(c) => c.fmt(f),
+            #[cfg(feature = "boringssl")]
+            DtlsCertInner::BoringSsl(c) => c.fmt(f),
             _ => unreachable!(),
         }
     }
@@ -106,11 +449,18 @@ pub trait DtlsInner: Sized {
 
     /// Whether the DTLS connection is established.
     fn is_connected(&self) -> bool;
+
+    /// Start a re-handshake to get fresh SRTP keying material.
+    ///
+    /// The new keying material is emitted as an event when the handshake completes.
+    fn rekey(&mut self) -> Result<(), CryptoError>;
 }
 
 pub enum DtlsImpl {
     #[cfg(feature = "openssl")]
     OpenSsl(super::ossl::OsslDtlsImpl),
+    #[cfg(feature = "boringssl")]
+    BoringSsl(super::bssl::BsslDtlsImpl),
 }
 
 impl DtlsImpl {
@@ -118,6 +468,8 @@ impl DtlsImpl {
         match self {
             #[cfg(feature = "openssl")]
             DtlsImpl::OpenSsl(i) => i.set_active(active),
+            #[cfg(feature = "boringssl")]
+            DtlsImpl::BoringSsl(i) => i.set_active(active),
             _ => unreachable!(),
         }
     }
@@ -126,6 +478,8 @@ impl DtlsImpl {
         match self {
             #[cfg(feature = "openssl")]
             DtlsImpl::OpenSsl(i) => i.handle_handshake(o),
+            #[cfg(feature = "boringssl")]
+            DtlsImpl::BoringSsl(i) => i.handle_handshake(o),
             _ => unreachable!(),
         }
     }
@@ -134,6 +488,8 @@ impl DtlsImpl {
         match self {
             #[cfg(feature = "openssl")]
             DtlsImpl::OpenSsl(i) => i.is_active(),
+            #[cfg(feature = "boringssl")]
+            DtlsImpl::BoringSsl(i) => i.is_active(),
             _ => unreachable!(),
         }
     }
@@ -146,6 +502,8 @@ impl DtlsImpl {
         match self {
             #[cfg(feature = "openssl")]
             DtlsImpl::OpenSsl(i) => i.handle_receive(m, o),
+            #[cfg(feature = "boringssl")]
+            DtlsImpl::BoringSsl(i) => i.handle_receive(m, o),
             _ => unreachable!(),
         }
     }
@@ -154,6 +512,8 @@ impl DtlsImpl {
         match self {
             #[cfg(feature = "openssl")]
             DtlsImpl::OpenSsl(i) => i.poll_datagram(),
+            #[cfg(feature = "boringssl")]
+            DtlsImpl::BoringSsl(i) => i.poll_datagram(),
             _ => unreachable!(),
         }
     }
@@ -162,6 +522,8 @@ impl DtlsImpl {
         match self {
             #[cfg(feature = "openssl")]
             DtlsImpl::OpenSsl(i) => i.handle_input(data),
+            #[cfg(feature = "boringssl")]
+            DtlsImpl::BoringSsl(i) => i.handle_input(data),
             _ => unreachable!(),
         }
     }
@@ -170,7 +532,38 @@ impl DtlsImpl {
         match self {
             #[cfg(feature = "openssl")]
             DtlsImpl::OpenSsl(i) => i.is_connected(),
+            #[cfg(feature = "boringssl")]
+            DtlsImpl::BoringSsl(i) => i.is_connected(),
             _ => unreachable!(),
         }
     }
+
+    pub fn rekey(&mut self) -> Result<(), CryptoError> {
+        match self {
+            #[cfg(feature = "openssl")]
+            DtlsImpl::OpenSsl(i) => i.rekey(),
+            #[cfg(feature = "boringssl")]
+            DtlsImpl::BoringSsl(i) => i.rekey(),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "openssl",
+    feature = "boringssl",
+    not(feature = "rust-crypto-srtp")
+))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn srtp_crypto_follows_cert() {
+        let bssl = DtlsCert::new_boringssl().srtp_crypto();
+        assert_eq!(format!("{bssl:?}"), "BoringSslSrtpCrypto");
+
+        let ossl = DtlsCert::new_openssl().srtp_crypto();
+        assert_eq!(format!("{ossl:?}"), "DefaultSrtpCrypto");
+    }
 }
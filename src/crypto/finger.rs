@@ -15,6 +15,52 @@ pub struct Fingerprint {
     pub bytes: Vec<u8>,
 }
 
+/// Hash function used for a [`Fingerprint`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum FingerprintHash {
+    /// `sha-256`, which every WebRTC implementation supports.
+    #[default]
+    Sha256,
+    /// `sha-384`
+    Sha384,
+    /// `sha-512`
+    Sha512,
+}
+
+impl FingerprintHash {
+    /// All supported hash functions.
+    pub const ALL: [FingerprintHash; 3] = [
+        FingerprintHash::Sha256,
+        FingerprintHash::Sha384,
+        FingerprintHash::Sha512,
+    ];
+
+    /// The name as used in the SDP `a=fingerprint` attribute.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FingerprintHash::Sha256 => "sha-256",
+            FingerprintHash::Sha384 => "sha-384",
+            FingerprintHash::Sha512 => "sha-512",
+        }
+    }
+
+    /// Look up a hash function by its SDP name. The name is case insensitive.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|h| h.as_str().eq_ignore_ascii_case(name))
+    }
+}
+
+impl Fingerprint {
+    /// Whether this fingerprint is the same as the other, ignoring the case of the
+    /// hash function name.
+    pub(crate) fn matches(&self, other: &Fingerprint) -> bool {
+        self.hash_func.eq_ignore_ascii_case(&other.hash_func) && self.bytes == other.bytes
+    }
+}
+
 // DO NOT CHANGE!
 // This format is exactly what's needed in n SDP.
 impl fmt::Display for Fingerprint {
@@ -56,6 +102,29 @@ impl std::str::FromStr for Fingerprint {
 mod test {
     use super::*;
 
+    #[test]
+    fn fingerprint_hash_from_name() {
+        assert_eq!(
+            FingerprintHash::from_name("sha-384"),
+            Some(FingerprintHash::Sha384)
+        );
+        assert_eq!(
+            FingerprintHash::from_name("SHA-512"),
+            Some(FingerprintHash::Sha512)
+        );
+        assert_eq!(FingerprintHash::from_name("sha-1"), None);
+    }
+
+    #[test]
+    fn fingerprint_matches_ignores_case() {
+        let a: Fingerprint = "sha-256 00:01:02".parse().unwrap();
+        let b: Fingerprint = "SHA-256 00:01:02".parse().unwrap();
+        let c: Fingerprint = "sha-384 00:01:02".parse().unwrap();
+
+        assert!(a.matches(&b));
+        assert!(!a.matches(&c));
+    }
+
     #[test]
     fn fingerprint_format() {
         let f = Fingerprint {
@@ -6,16 +6,27 @@ use thiserror::Error;
 #[cfg(feature = "openssl")]
 mod ossl;
 
-mod dtls;
-pub use dtls::{DtlsCert, DtlsEvent, DtlsImpl};
+#[cfg(feature = "boringssl")]
+mod bssl;
+
+#[cfg(feature = "rust-crypto-srtp")]
+mod rust_crypto;
+
+#[cfg(any(feature = "openssl", feature = "boringssl"))]
+mod io_buf;
+
+pub(crate) mod dtls;
+pub use dtls::{DtlsCert, DtlsCipherSuite, DtlsEvent, DtlsGroup, DtlsImpl, DtlsKeyLog};
 
 mod finger;
-pub use finger::Fingerprint;
+pub use finger::{Fingerprint, FingerprintHash};
 
 mod keying;
 pub use keying::KeyingMaterial;
 
 mod srtp;
+pub(crate) use srtp::DefaultSrtpCrypto;
+pub use srtp::SrtpCrypto;
 pub use srtp::{aead_aes_128_gcm, aes_128_cm_sha1_80, new_aead_aes_128_gcm};
 pub use srtp::{new_aes_128_cm_sha1_80, srtp_aes_128_ecb_round, SrtpProfile};
 
@@ -34,15 +45,52 @@ pub fn sha1_hmac(key: &[u8], payloads: &[&[u8]]) -> [u8; 20] {
     hmac.finalize().into_bytes().into()
 }
 
-/// Errors that can arise in DTLS.
+/// MD5 digest as used for the TURN long-term credential key.
+pub fn md5(payloads: &[&[u8]]) -> [u8; 16] {
+    use md5::{Digest, Md5};
+
+    let mut hasher = Md5::new();
+
+    for payload in payloads {
+        hasher.update(payload);
+    }
+
+    hasher.finalize().into()
+}
+
+/// Errors that can arise in DTLS and SRTP crypto.
+///
+/// Some variants depend on the enabled crypto features.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum CryptoError {
     /// Some error from OpenSSL layer (used for DTLS).
     #[error("{0}")]
     #[cfg(feature = "openssl")]
     OpenSsl(#[from] openssl::error::ErrorStack),
 
+    /// Some error from BoringSSL layer (used for DTLS).
+    #[error("{0}")]
+    #[cfg(feature = "boringssl")]
+    BoringSsl(#[from] ::boring::error::ErrorStack),
+
     /// Other IO errors.
     #[error("{0}")]
     Io(#[from] io::Error),
+
+    /// The private key does not belong to the certificate.
+    #[error("Private key does not match certificate")]
+    CertificateKeyMismatch,
+
+    /// FIPS mode is required, but the crypto backend is not operating in FIPS mode.
+    #[error("Crypto backend is not in FIPS mode")]
+    FipsNotEnabled,
+
+    /// The configured DTLS cipher suites leave none to negotiate.
+    #[error("No DTLS cipher suites to negotiate")]
+    NoCipherSuites,
+
+    /// The configured DTLS key exchange groups leave none to negotiate.
+    #[error("No DTLS key exchange groups to negotiate")]
+    NoGroups,
 }
@@ -1,15 +1,16 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 use openssl::asn1::{Asn1Integer, Asn1Time, Asn1Type};
 use openssl::bn::BigNum;
+use openssl::error::ErrorStack;
 use openssl::hash::MessageDigest;
 use openssl::nid::Nid;
 use openssl::pkey::{PKey, Private};
 use openssl::rsa::Rsa;
-use openssl::x509::{X509Name, X509};
+use openssl::x509::{X509Name, X509Ref, X509};
 
 use crate::crypto::dtls::DTLS_CERT_IDENTITY;
-use crate::crypto::Fingerprint;
+use crate::crypto::{Fingerprint, FingerprintHash};
 
 use super::CryptoError;
 
@@ -28,6 +29,30 @@ impl OsslDtlsCert {
         Self::self_signed().expect("create dtls cert")
     }
 
+    /// Creates a DTLS certificate from an existing PEM encoded certificate and private key.
+    pub fn from_pem(cert: &[u8], key: &[u8]) -> Result<Self, CryptoError> {
+        let x509 = X509::from_pem(cert)?;
+        let pkey = PKey::private_key_from_pem(key)?;
+        Self::from_parts(pkey, x509)
+    }
+
+    /// Creates a DTLS certificate from an existing DER encoded certificate and private key.
+    pub fn from_der(cert: &[u8], key: &[u8]) -> Result<Self, CryptoError> {
+        let x509 = X509::from_der(cert)?;
+        let pkey = PKey::private_key_from_der(key)?;
+        Self::from_parts(pkey, x509)
+    }
+
+    fn from_parts(pkey: PKey<Private>, x509: X509) -> Result<Self, CryptoError> {
+        // The private key must be the one the certificate was issued for, or
+        // the DTLS handshake will fail in confusing ways later.
+        if !x509.public_key()?.public_eq(&pkey) {
+            return Err(CryptoError::CertificateKeyMismatch);
+        }
+
+        Ok(Self { pkey, x509 })
+    }
+
     // The libWebRTC code we try to match is at:
     // https://webrtc.googlesource.com/src/+/1568f1b1330f94494197696fe235094e6293b258/rtc_base/openssl_certificate.cc#58
     fn self_signed() -> Result<Self, CryptoError> {
@@ -78,23 +103,48 @@ impl OsslDtlsCert {
         Ok(OsslDtlsCert { pkey, x509 })
     }
 
+    /// When the certificate expires.
+    pub fn not_after(&self) -> SystemTime {
+        let epoch = Asn1Time::from_unix(0).expect("unix epoch as asn1 time");
+        let diff = epoch
+            .diff(self.x509.not_after())
+            .expect("diff of asn1 times");
+
+        let secs = diff.days as i64 * 86_400 + diff.secs as i64;
+
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+    }
+
     /// Produce a (public) fingerprint of the cert.
     ///
     /// This is sent via SDP to the other peer to lock down the DTLS
     /// to this specific certificate.
     pub fn fingerprint(&self) -> Fingerprint {
-        let digest: &[u8] = &self
-            .x509
-            .digest(MessageDigest::sha256())
-            .expect("digest to fingerprint");
-
-        Fingerprint {
-            hash_func: "sha-256".into(),
-            bytes: digest.to_vec(),
-        }
+        self.fingerprint_with_hash(FingerprintHash::Sha256)
+    }
+
+    /// Produce a fingerprint of the cert using the given hash function.
+    pub fn fingerprint_with_hash(&self, hash: FingerprintHash) -> Fingerprint {
+        fingerprint_of(&self.x509, hash).expect("digest to fingerprint")
     }
 }
 
+/// Fingerprint of a certificate using the given hash function.
+pub fn fingerprint_of(x509: &X509Ref, hash: FingerprintHash) -> Result<Fingerprint, ErrorStack> {
+    let md = match hash {
+        FingerprintHash::Sha256 => MessageDigest::sha256(),
+        FingerprintHash::Sha384 => MessageDigest::sha384(),
+        FingerprintHash::Sha512 => MessageDigest::sha512(),
+    };
+
+    let digest: &[u8] = &x509.digest(md)?;
+
+    Ok(Fingerprint {
+        hash_func: hash.as_str().into(),
+        bytes: digest.to_vec(),
+    })
+}
+
 // TODO: Refactor away this use of System::now, to instead go via InstantExt
 // and base the time on the first Instant. This would require lazy init of
 // Dtls, or that we pass a first ever Instant into the creation of Rtc.
@@ -107,3 +157,49 @@ pub fn unix_time() -> i64 {
         .unwrap()
         .as_secs() as i64
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pem_roundtrip() {
+        let cert = OsslDtlsCert::new();
+        let pem = cert.x509.to_pem().unwrap();
+        let key = cert.pkey.private_key_to_pem_pkcs8().unwrap();
+
+        let loaded = OsslDtlsCert::from_pem(&pem, &key).unwrap();
+        assert_eq!(loaded.fingerprint(), cert.fingerprint());
+    }
+
+    #[test]
+    fn der_roundtrip() {
+        let cert = OsslDtlsCert::new();
+        let der = cert.x509.to_der().unwrap();
+        let key = cert.pkey.private_key_to_der().unwrap();
+
+        let loaded = OsslDtlsCert::from_der(&der, &key).unwrap();
+        assert_eq!(loaded.fingerprint(), cert.fingerprint());
+    }
+
+    #[test]
+    fn self_signed_not_after() {
+        let cert = OsslDtlsCert::new();
+        let expires_in = cert.not_after().duration_since(SystemTime::now()).unwrap();
+
+        // Generated certificates are valid for 7 days.
+        assert!(expires_in > Duration::from_secs(6 * 86_400));
+        assert!(expires_in <= Duration::from_secs(7 * 86_400));
+    }
+
+    #[test]
+    fn key_mismatch() {
+        let cert = OsslDtlsCert::new();
+        let other = OsslDtlsCert::new();
+        let pem = cert.x509.to_pem().unwrap();
+        let key = other.pkey.private_key_to_pem_pkcs8().unwrap();
+
+        let err = OsslDtlsCert::from_pem(&pem, &key).unwrap_err();
+        assert!(matches!(err, CryptoError::CertificateKeyMismatch));
+    }
+}
@@ -6,11 +6,12 @@ use openssl::nid::Nid;
 use openssl::ssl::{Ssl, SslContext, SslContextBuilder, SslMethod, SslOptions, SslVerifyMode};
 
 use crate::crypto::dtls::DtlsInner;
+use crate::crypto::dtls::DtlsOptions;
+use crate::crypto::io_buf::IoBuffer;
 use crate::crypto::{DtlsEvent, SrtpProfile};
-use crate::io::{DATAGRAM_MTU, DATAGRAM_MTU_WARN};
+use crate::io::DATAGRAM_MTU;
 
 use super::cert::OsslDtlsCert;
-use super::io_buf::IoBuffer;
 use super::stream::TlsStream;
 use super::CryptoError;
 
@@ -32,9 +33,9 @@ pub struct OsslDtlsImpl {
 }
 
 impl OsslDtlsImpl {
-    pub fn new(cert: OsslDtlsCert) -> Result<Self, super::CryptoError> {
-        let context = dtls_create_ctx(&cert)?;
-        let ssl = dtls_ssl_create(&context)?;
+    pub fn new(cert: OsslDtlsCert, options: &DtlsOptions) -> Result<Self, super::CryptoError> {
+        let context = dtls_create_ctx(&cert, options)?;
+        let ssl = dtls_ssl_create(&context, options)?;
         Ok(OsslDtlsImpl {
             _cert: cert,
             _context: context,
@@ -61,7 +62,18 @@ impl DtlsInner for OsslDtlsImpl {
         }
 
         let mut buf = vec![0; 2000];
-        let n = match self.tls.read(&mut buf) {
+        let result = self.tls.read(&mut buf);
+
+        // A re-handshake started by the remote peer is processed while reading.
+        self.tls.check_rehandshake()?;
+        if let Some((keying_material, srtp_profile, fingerprint)) =
+            self.tls.take_srtp_keying_material()
+        {
+            o.push_back(DtlsEvent::RemoteFingerprint(fingerprint));
+            o.push_back(DtlsEvent::SrtpKeyingMaterial(keying_material, srtp_profile));
+        }
+
+        let n = match result {
             Ok(v) => v,
             Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
                 return Ok(());
@@ -78,9 +90,6 @@ impl DtlsInner for OsslDtlsImpl {
     fn poll_datagram(&mut self) -> Option<crate::net::DatagramSend> {
         let x = self.tls.inner_mut().pop_outgoing();
         if let Some(x) = &x {
-            if x.len() > DATAGRAM_MTU_WARN {
-                warn!("DTLS above MTU {}: {}", DATAGRAM_MTU_WARN, x.len());
-            }
             trace!("Poll datagram: {}", x.len());
         }
         x
@@ -94,6 +103,10 @@ impl DtlsInner for OsslDtlsImpl {
         self.tls.is_connected()
     }
 
+    fn rekey(&mut self) -> Result<(), CryptoError> {
+        Ok(self.tls.rekey()?)
+    }
+
     fn handle_handshake(&mut self, output: &mut VecDeque<DtlsEvent>) -> Result<bool, CryptoError> {
         if self.tls.is_handshaken() {
             // Nice. Nothing to do.
@@ -116,19 +129,31 @@ impl DtlsInner for OsslDtlsImpl {
     }
 }
 
-pub fn dtls_create_ctx(cert: &OsslDtlsCert) -> Result<SslContext, CryptoError> {
+pub fn dtls_create_ctx(
+    cert: &OsslDtlsCert,
+    options: &DtlsOptions,
+) -> Result<SslContext, CryptoError> {
+    if options.fips && !super::fips_enabled() {
+        return Err(CryptoError::FipsNotEnabled);
+    }
+
     // TODO: Technically we want to disallow DTLS < 1.2, but that requires
     // us to use this commented out unsafe. We depend on browsers disallowing
     // it instead.
     // let method = unsafe { SslMethod::from_ptr(DTLSv1_2_method()) };
     let mut ctx = SslContextBuilder::new(SslMethod::dtls())?;
 
-    ctx.set_cipher_list(DTLS_CIPHERS)?;
+    let cipher_list = options.cipher_list();
+    ctx.set_cipher_list(cipher_list.as_deref().unwrap_or(DTLS_CIPHERS))?;
+
+    if let Some(groups) = options.groups_list() {
+        ctx.set_groups_list(&groups)?;
+    }
     let srtp_profiles = {
         // Rust can't join directly to a string, need to allocate a vec first :(
         // This happens very rarely so the extra allocations don't matter
-        let all: Vec<_> = SrtpProfile::ALL
-            .iter()
+        let all: Vec<_> = options
+            .srtp_profiles()
             .map(SrtpProfile::openssl_name)
             .collect();
 
@@ -144,22 +169,173 @@ pub fn dtls_create_ctx(cert: &OsslDtlsCert) -> Result<SslContext, CryptoError> {
     ctx.set_private_key(&cert.pkey)?;
     ctx.set_certificate(&cert.x509)?;
 
-    let mut options = SslOptions::empty();
-    options.insert(SslOptions::SINGLE_ECDH_USE);
-    options.insert(SslOptions::NO_DTLSV1);
-    ctx.set_options(options);
+    let mut ssl_options = SslOptions::empty();
+    ssl_options.insert(SslOptions::SINGLE_ECDH_USE);
+    ssl_options.insert(SslOptions::NO_DTLSV1);
+    ctx.set_options(ssl_options);
+
+    if let Some(keylog) = options.keylog.clone() {
+        ctx.set_keylog_callback(move |_ssl, line| keylog.log(line));
+    }
 
     let ctx = ctx.build();
 
     Ok(ctx)
 }
 
-pub fn dtls_ssl_create(ctx: &SslContext) -> Result<Ssl, CryptoError> {
+pub fn dtls_ssl_create(ctx: &SslContext, options: &DtlsOptions) -> Result<Ssl, CryptoError> {
     let mut ssl = Ssl::new(ctx)?;
     ssl.set_mtu(DATAGRAM_MTU as u32)?;
 
-    let eckey = EcKey::from_curve_name(DTLS_EC_CURVE)?;
-    ssl.set_tmp_ecdh(&eckey)?;
+    // A configured groups list on the context takes precedence.
+    if options.groups.is_none() {
+        let eckey = EcKey::from_curve_name(DTLS_EC_CURVE)?;
+        ssl.set_tmp_ecdh(&eckey)?;
+    }
 
     Ok(ssl)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::crypto::{DtlsCipherSuite, DtlsGroup};
+
+    #[test]
+    fn restricted_suites_and_groups() {
+        let cert = OsslDtlsCert::new();
+        let options = DtlsOptions {
+            cipher_suites: Some(vec![DtlsCipherSuite::EcdheEcdsaAes256GcmSha384]),
+            groups: Some(vec![DtlsGroup::P384, DtlsGroup::X25519]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            options.cipher_list().as_deref(),
+            Some("ECDHE-ECDSA-AES256-GCM-SHA384")
+        );
+        assert_eq!(options.groups_list().as_deref(), Some("P-384:X25519"));
+
+        OsslDtlsImpl::new(cert, &options).unwrap();
+    }
+
+    #[test]
+    fn empty_suites_fail() {
+        let cert = OsslDtlsCert::new();
+        let options = DtlsOptions {
+            cipher_suites: Some(vec![]),
+            ..Default::default()
+        };
+
+        assert!(OsslDtlsImpl::new(cert, &options).is_err());
+    }
+
+    #[test]
+    fn fips_restricts_algorithms() {
+        let options = DtlsOptions {
+            groups: Some(vec![DtlsGroup::X25519, DtlsGroup::P256]),
+            fips: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            options.cipher_list().as_deref(),
+            Some(
+                "ECDHE-ECDSA-AES128-GCM-SHA256:ECDHE-ECDSA-AES256-GCM-SHA384:\
+                 ECDHE-RSA-AES128-GCM-SHA256:ECDHE-RSA-AES256-GCM-SHA384"
+            )
+        );
+        assert_eq!(options.groups_list().as_deref(), Some("P-256"));
+        assert_eq!(
+            options.srtp_profiles().collect::<Vec<_>>(),
+            [&SrtpProfile::AeadAes128Gcm]
+        );
+
+        let cert = OsslDtlsCert::new();
+        let result = OsslDtlsImpl::new(cert, &options);
+
+        if super::super::fips_enabled() {
+            result.unwrap();
+        } else {
+            assert!(matches!(result, Err(CryptoError::FipsNotEnabled)));
+        }
+    }
+
+    #[test]
+    fn local_rekey() {
+        let options = DtlsOptions::default();
+        let mut client = OsslDtlsImpl::new(OsslDtlsCert::new(), &options).unwrap();
+        let mut server = OsslDtlsImpl::new(OsslDtlsCert::new(), &options).unwrap();
+        client.set_active(true);
+        server.set_active(false);
+
+        let mut client_events = VecDeque::new();
+        let mut server_events = VecDeque::new();
+
+        assert!(client.handle_handshake(&mut client_events).unwrap());
+        exchange(
+            &mut client,
+            &mut server,
+            &mut client_events,
+            &mut server_events,
+        );
+        assert!(client.is_connected() && server.is_connected());
+
+        let mut keys = keying_material(&mut client_events);
+        assert_eq!(keys, keying_material(&mut server_events));
+
+        // Either side can start the re-key.
+        for rekey_client in [true, false] {
+            if rekey_client {
+                client.rekey().unwrap();
+            } else {
+                server.rekey().unwrap();
+            }
+
+            exchange(
+                &mut client,
+                &mut server,
+                &mut client_events,
+                &mut server_events,
+            );
+
+            let new_keys = keying_material(&mut client_events);
+            assert_eq!(new_keys, keying_material(&mut server_events));
+            assert_ne!(new_keys, keys);
+            keys = new_keys;
+        }
+    }
+
+    fn exchange(
+        client: &mut OsslDtlsImpl,
+        server: &mut OsslDtlsImpl,
+        client_events: &mut VecDeque<DtlsEvent>,
+        server_events: &mut VecDeque<DtlsEvent>,
+    ) {
+        loop {
+            let mut progress = false;
+            while let Some(d) = client.poll_datagram() {
+                server.handle_receive(&d, server_events).unwrap();
+                progress = true;
+            }
+            while let Some(d) = server.poll_datagram() {
+                client.handle_receive(&d, client_events).unwrap();
+                progress = true;
+            }
+            if !progress {
+                break;
+            }
+        }
+    }
+
+    fn keying_material(events: &mut VecDeque<DtlsEvent>) -> Vec<u8> {
+        let keys = events
+            .drain(..)
+            .filter_map(|e| match e {
+                DtlsEvent::SrtpKeyingMaterial(m, _) => Some(m.to_vec()),
+                _ => None,
+            })
+            .last();
+        keys.expect("keying material")
+    }
+}
@@ -5,15 +5,37 @@ use super::{CryptoError, SrtpProfile};
 mod cert;
 pub use cert::OsslDtlsCert;
 
-mod io_buf;
 mod stream;
 
 mod dtls;
 pub use dtls::OsslDtlsImpl;
 
+// Unused when the pure Rust SRTP ciphers take over.
+#[cfg_attr(feature = "rust-crypto-srtp", allow(dead_code))]
 mod srtp;
 pub use srtp::OsslSrtpCryptoImpl;
 
+/// Whether OpenSSL is operating in FIPS mode.
+///
+/// For OpenSSL 3.x this means the default properties select the FIPS provider,
+/// for 1.x that the FIPS object module is enabled. LibreSSL has no FIPS mode.
+pub fn fips_enabled() -> bool {
+    #[cfg(ossl300)]
+    {
+        openssl::init();
+        // SAFETY: A null library context is the default one.
+        unsafe { openssl_sys::EVP_default_properties_is_fips_enabled(std::ptr::null_mut()) == 1 }
+    }
+    #[cfg(not(any(ossl300, libressl)))]
+    {
+        openssl::fips::enabled()
+    }
+    #[cfg(libressl)]
+    {
+        false
+    }
+}
+
 impl SrtpProfile {
     /// What this profile is called in OpenSSL parlance.
     pub(crate) fn openssl_name(&self) -> &'static str {
@@ -12,6 +12,42 @@ impl SrtpCryptoImpl for OsslSrtpCryptoImpl {
     type Aes128CmSha1_80 = OsslAes128CmSha1_80;
     type AeadAes128Gcm = OsslAeadAes128Gcm;
 
+    fn new_aes_128_cm_sha1_80(
+        key: aes_128_cm_sha1_80::AesKey,
+        encrypt: bool,
+    ) -> Self::Aes128CmSha1_80 {
+        let t = cipher::Cipher::aes_128_ctr();
+        let mut ctx = CipherCtx::new().expect("a reusable cipher context");
+
+        if encrypt {
+            ctx.encrypt_init(Some(t), Some(&key[..]), None)
+                .expect("enc init");
+        } else {
+            ctx.decrypt_init(Some(t), Some(&key[..]), None)
+                .expect("enc init");
+        }
+
+        OsslAes128CmSha1_80(ctx)
+    }
+
+    fn new_aead_aes_128_gcm(key: aead_aes_128_gcm::AeadKey, encrypt: bool) -> Self::AeadAes128Gcm {
+        let t = cipher::Cipher::aes_128_gcm();
+        let mut ctx = CipherCtx::new().expect("a reusable cipher context");
+
+        if encrypt {
+            ctx.encrypt_init(Some(t), Some(&key), None)
+                .expect("enc init");
+            ctx.set_iv_length(aead_aes_128_gcm::IV_LEN)
+                .expect("IV length");
+            ctx.set_padding(false);
+        } else {
+            ctx.decrypt_init(Some(t), Some(&key), None)
+                .expect("dec init");
+        }
+
+        OsslAeadAes128Gcm(ctx)
+    }
+
     fn srtp_aes_128_ecb_round(key: &[u8], input: &[u8], output: &mut [u8]) {
         let mut aes =
             Crypter::new(Cipher::aes_128_ecb(), Mode::Encrypt, key, None).expect("AES deriver");
@@ -27,24 +63,6 @@ impl SrtpCryptoImpl for OsslSrtpCryptoImpl {
 pub struct OsslAes128CmSha1_80(CipherCtx);
 
 impl aes_128_cm_sha1_80::CipherCtx for OsslAes128CmSha1_80 {
-    fn new(key: aes_128_cm_sha1_80::AesKey, encrypt: bool) -> Self
-    where
-        Self: Sized,
-    {
-        let t = cipher::Cipher::aes_128_ctr();
-        let mut ctx = CipherCtx::new().expect("a reusable cipher context");
-
-        if encrypt {
-            ctx.encrypt_init(Some(t), Some(&key[..]), None)
-                .expect("enc init");
-        } else {
-            ctx.decrypt_init(Some(t), Some(&key[..]), None)
-                .expect("enc init");
-        }
-
-        OsslAes128CmSha1_80(ctx)
-    }
-
     fn encrypt(
         &mut self,
         iv: &aes_128_cm_sha1_80::RtpIv,
@@ -73,27 +91,6 @@ impl aes_128_cm_sha1_80::CipherCtx for OsslAes128CmSha1_80 {
 pub struct OsslAeadAes128Gcm(CipherCtx);
 
 impl aead_aes_128_gcm::CipherCtx for OsslAeadAes128Gcm {
-    fn new(key: aead_aes_128_gcm::AeadKey, encrypt: bool) -> Self
-    where
-        Self: Sized,
-    {
-        let t = cipher::Cipher::aes_128_gcm();
-        let mut ctx = CipherCtx::new().expect("a reusable cipher context");
-
-        if encrypt {
-            ctx.encrypt_init(Some(t), Some(&key), None)
-                .expect("enc init");
-            ctx.set_iv_length(aead_aes_128_gcm::IV_LEN)
-                .expect("IV length");
-            ctx.set_padding(false);
-        } else {
-            ctx.decrypt_init(Some(t), Some(&key), None)
-                .expect("dec init");
-        }
-
-        OsslAeadAes128Gcm(ctx)
-    }
-
     fn encrypt(
         &mut self,
         iv: &[u8; aead_aes_128_gcm::IV_LEN],
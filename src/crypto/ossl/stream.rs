@@ -1,22 +1,32 @@
+use std::os::raw::c_int;
 use std::panic::UnwindSafe;
 use std::{io, mem};
 
-use openssl::hash::MessageDigest;
+use openssl::error::ErrorStack;
 use openssl::srtp::SrtpProfileId;
-use openssl::ssl::{HandshakeError, MidHandshakeSslStream, Ssl, SslStream};
+use openssl::ssl::{HandshakeError, MidHandshakeSslStream, Ssl, SslRef, SslStream};
 
-use crate::change::Fingerprint;
+use crate::change::{Fingerprint, FingerprintHash};
 use crate::crypto::{KeyingMaterial, SrtpProfile};
 
+use super::cert::fingerprint_of;
+
 use super::CryptoError;
 
 const DTLS_KEY_LABEL: &str = "EXTRACTOR-dtls_srtp";
 
+extern "C" {
+    // Not exposed by openssl-sys.
+    fn SSL_renegotiate(s: *mut openssl_sys::SSL) -> c_int;
+}
+
 pub struct TlsStream<S> {
     active: Option<bool>,
     state: State<S>,
-    keying_mat: Option<(KeyingMaterial, SrtpProfile, Fingerprint)>,
+    keying_mat: Option<(KeyingMaterial, SrtpProfile, Vec<Fingerprint>)>,
     exported: bool,
+    /// Client random of the handshake the keying material was exported for.
+    client_random: [u8; 32],
 }
 
 pub enum State<S> {
@@ -40,6 +50,7 @@ where
             state: State::Init(ssl, stream),
             keying_mat: None,
             exported: false,
+            client_random: [0; 32],
         }
     }
 
@@ -84,14 +95,79 @@ where
             let keying_mat = export_srtp_keying_material(v)?;
             self.exported = true;
             self.keying_mat = Some(keying_mat);
+            v.ssl().client_random(&mut self.client_random);
         }
 
         Ok(v)
     }
 
+    /// Check whether the remote peer has completed a re-handshake (renegotiation).
+    ///
+    /// The new handshake has a new client random. If so, new keying material for SRTP
+    /// is exported, to be taken with `take_srtp_keying_material`.
+    pub fn check_rehandshake(&mut self) -> Result<(), io::Error> {
+        let State::Established(v) = &mut self.state else {
+            return Ok(());
+        };
+
+        if !self.exported || !v.ssl().is_init_finished() {
+            return Ok(());
+        }
+
+        let mut client_random = [0; 32];
+        v.ssl().client_random(&mut client_random);
+
+        if client_random == self.client_random {
+            return Ok(());
+        }
+
+        debug!("DTLS re-handshake, exporting new keying material");
+        let keying_mat = export_srtp_keying_material(v)?;
+        self.keying_mat = Some(keying_mat);
+        self.client_random = client_random;
+
+        Ok(())
+    }
+
+    /// Start a re-handshake (renegotiation) to get fresh SRTP keying material.
+    ///
+    /// Only the first flight is sent here, the rest of the handshake is processed while
+    /// reading. Once complete, `check_rehandshake` exports the new keying material.
+    pub fn rekey(&mut self) -> Result<(), io::Error> {
+        let State::Established(v) = &mut self.state else {
+            return Ok(());
+        };
+
+        if !v.ssl().is_init_finished() {
+            debug!("DTLS re-handshake already in progress");
+            return Ok(());
+        }
+
+        debug!("DTLS start re-handshake");
+        let ssl = v.ssl() as *const SslRef as *mut openssl_sys::SSL;
+
+        // SAFETY: The pointer is the SSL of the established stream, which we hold
+        // exclusively via &mut self for the duration of these calls.
+        unsafe {
+            if SSL_renegotiate(ssl) != 1 {
+                return Err(io::Error::new(io::ErrorKind::Other, ErrorStack::get()));
+            }
+
+            let ret = openssl_sys::SSL_do_handshake(ssl);
+            if ret <= 0 {
+                let err = openssl_sys::SSL_get_error(ssl, ret);
+                if err != openssl_sys::SSL_ERROR_WANT_READ {
+                    return Err(io::Error::new(io::ErrorKind::Other, ErrorStack::get()));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     pub fn take_srtp_keying_material(
         &mut self,
-    ) -> Option<(KeyingMaterial, SrtpProfile, Fingerprint)> {
+    ) -> Option<(KeyingMaterial, SrtpProfile, Vec<Fingerprint>)> {
         self.keying_mat.take()
     }
 
@@ -160,19 +236,18 @@ where
 
 fn export_srtp_keying_material<S>(
     stream: &mut SslStream<S>,
-) -> Result<(KeyingMaterial, SrtpProfile, Fingerprint), io::Error> {
+) -> Result<(KeyingMaterial, SrtpProfile, Vec<Fingerprint>), io::Error> {
     let ssl = stream.ssl();
 
     // remote peer certificate fingerprint
     let x509 = ssl
         .peer_certificate()
         .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "No remote X509 cert"))?;
-    let digest: &[u8] = &x509.digest(MessageDigest::sha256())?;
-
-    let fp = Fingerprint {
-        hash_func: "sha-256".into(),
-        bytes: digest.to_vec(),
-    };
+    // All supported hashes, since the one to verify against is up to the remote SDP.
+    let fp = FingerprintHash::ALL
+        .into_iter()
+        .map(|hash| fingerprint_of(&x509, hash))
+        .collect::<Result<Vec<_>, _>>()?;
 
     let srtp_profile_id = ssl
         .selected_srtp_profile()
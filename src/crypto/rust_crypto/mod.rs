@@ -0,0 +1,8 @@
+//! Pure Rust (RustCrypto) implementation of cryptographic functions.
+//!
+//! This only covers the SRTP ciphers. SHA1 HMAC is always pure Rust. There is no pure
+//! Rust DTLS 1.2 to build on (rustls has no DTLS), so DTLS and certificate generation
+//! still require OpenSSL or BoringSSL, and so does building for musl or cross targets.
+
+mod srtp;
+pub use srtp::RustCryptoSrtpCryptoImpl;
@@ -0,0 +1,243 @@
+use std::io;
+
+use aes::cipher::{BlockEncrypt, InnerIvInit, KeyInit, StreamCipher};
+use aes::Aes128;
+use aes_gcm::aead::AeadInPlace;
+use aes_gcm::{Aes128Gcm, Nonce, Tag};
+
+use crate::crypto::srtp::SrtpCryptoImpl;
+use crate::crypto::srtp::{aead_aes_128_gcm, aes_128_cm_sha1_80};
+use crate::crypto::CryptoError;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+
+pub struct RustCryptoSrtpCryptoImpl;
+
+impl SrtpCryptoImpl for RustCryptoSrtpCryptoImpl {
+    type Aes128CmSha1_80 = RustCryptoAes128CmSha1_80;
+    type AeadAes128Gcm = RustCryptoAeadAes128Gcm;
+
+    fn new_aes_128_cm_sha1_80(
+        key: aes_128_cm_sha1_80::AesKey,
+        _encrypt: bool,
+    ) -> Self::Aes128CmSha1_80 {
+        // Counter mode is symmetric, encrypt and decrypt are the same operation.
+        RustCryptoAes128CmSha1_80(Aes128::new(&key.into()))
+    }
+
+    fn new_aead_aes_128_gcm(key: aead_aes_128_gcm::AeadKey, _encrypt: bool) -> Self::AeadAes128Gcm {
+        RustCryptoAeadAes128Gcm(Aes128Gcm::new(&key.into()))
+    }
+
+    fn srtp_aes_128_ecb_round(key: &[u8], input: &[u8], output: &mut [u8]) {
+        let aes = Aes128::new_from_slice(key).expect("AES deriver");
+
+        // A single block. The caller only uses the first 16 bytes of the output.
+        let mut block = aes::Block::clone_from_slice(&input[..16]);
+        aes.encrypt_block(&mut block);
+
+        output[..16].copy_from_slice(&block);
+    }
+}
+
+pub struct RustCryptoAes128CmSha1_80(Aes128);
+
+impl aes_128_cm_sha1_80::CipherCtx for RustCryptoAes128CmSha1_80 {
+    fn encrypt(
+        &mut self,
+        iv: &aes_128_cm_sha1_80::RtpIv,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), CryptoError> {
+        let mut ctr = Aes128Ctr::inner_iv_init(self.0.clone(), iv.into());
+        ctr.apply_keystream_b2b(input, &mut output[..input.len()])
+            .map_err(|_| invalid_data("AES-CM output buffer too small"))?;
+        Ok(())
+    }
+
+    fn decrypt(
+        &mut self,
+        iv: &aes_128_cm_sha1_80::RtpIv,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), CryptoError> {
+        self.encrypt(iv, input, output)
+    }
+}
+
+pub struct RustCryptoAeadAes128Gcm(Aes128Gcm);
+
+impl aead_aes_128_gcm::CipherCtx for RustCryptoAeadAes128Gcm {
+    fn encrypt(
+        &mut self,
+        iv: &[u8; aead_aes_128_gcm::IV_LEN],
+        aad: &[u8],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), CryptoError> {
+        assert!(
+            aad.len() >= 12,
+            "Associated data length MUST be at least 12 octets"
+        );
+
+        let (text, rest) = output.split_at_mut(input.len());
+        text.copy_from_slice(input);
+
+        let tag = self
+            .0
+            .encrypt_in_place_detached(Nonce::from_slice(iv), aad, text)
+            .map_err(|_| invalid_data("AES-GCM encrypt failed"))?;
+
+        rest[..aead_aes_128_gcm::TAG_LEN].copy_from_slice(&tag);
+
+        Ok(())
+    }
+
+    fn decrypt(
+        &mut self,
+        iv: &[u8; aead_aes_128_gcm::IV_LEN],
+        aads: &[&[u8]],
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, CryptoError> {
+        if input.len() < aead_aes_128_gcm::TAG_LEN {
+            return Err(invalid_data("AES-GCM input shorter than tag"));
+        }
+
+        let (cipher_text, tag) = input.split_at(input.len() - aead_aes_128_gcm::TAG_LEN);
+
+        // The RustCrypto API takes the associated data as one slice.
+        let aad = aads.concat();
+
+        let text = &mut output[..cipher_text.len()];
+        text.copy_from_slice(cipher_text);
+
+        self.0
+            .decrypt_in_place_detached(Nonce::from_slice(iv), &aad, text, Tag::from_slice(tag))
+            .map_err(|_| invalid_data("AES-GCM authentication failed"))?;
+
+        Ok(cipher_text.len())
+    }
+}
+
+fn invalid_data(msg: &'static str) -> CryptoError {
+    io::Error::new(io::ErrorKind::InvalidData, msg).into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use aead_aes_128_gcm::CipherCtx as _;
+    use aes_128_cm_sha1_80::CipherCtx as _;
+
+    use crate::crypto::{KeyingMaterial, SrtpCrypto, SrtpProfile};
+    use crate::rtp_::{ExtensionMap, RtpHeader, SrtpContext};
+
+    #[test]
+    fn aes_cm_roundtrip() {
+        let key = [7; 16];
+        let iv = [3; 16];
+        let input = [42; 32];
+
+        let mut enc = RustCryptoSrtpCryptoImpl::new_aes_128_cm_sha1_80(key, true);
+        let mut dec = RustCryptoSrtpCryptoImpl::new_aes_128_cm_sha1_80(key, false);
+
+        let mut encrypted = [0; 32];
+        enc.encrypt(&iv, &input, &mut encrypted).unwrap();
+        assert_ne!(encrypted, input);
+
+        let mut decrypted = [0; 32];
+        dec.decrypt(&iv, &encrypted, &mut decrypted).unwrap();
+        assert_eq!(decrypted, input);
+    }
+
+    #[test]
+    fn aead_gcm_roundtrip() {
+        let key = [7; 16];
+        let iv = [3; 12];
+        let aad = [1; 12];
+        let input = [42; 20];
+
+        let mut enc = RustCryptoSrtpCryptoImpl::new_aead_aes_128_gcm(key, true);
+        let mut dec = RustCryptoSrtpCryptoImpl::new_aead_aes_128_gcm(key, false);
+
+        let mut encrypted = [0; 20 + aead_aes_128_gcm::TAG_LEN];
+        enc.encrypt(&iv, &aad, &input, &mut encrypted).unwrap();
+
+        let mut decrypted = [0; 20];
+        let n = dec
+            .decrypt(&iv, &[&aad[..6], &aad[6..]], &encrypted, &mut decrypted)
+            .unwrap();
+        assert_eq!(n, 20);
+        assert_eq!(decrypted, input);
+
+        // Tampering fails the authentication.
+        encrypted[0] ^= 1;
+        assert!(dec
+            .decrypt(&iv, &[&aad], &encrypted, &mut decrypted)
+            .is_err());
+    }
+
+    /// The RustCrypto ciphers, regardless of which backend is the default.
+    #[derive(Debug)]
+    struct RustCrypto;
+
+    impl SrtpCrypto for RustCrypto {
+        fn new_aes_128_cm_sha1_80(
+            &self,
+            key: aes_128_cm_sha1_80::AesKey,
+            encrypt: bool,
+        ) -> Box<dyn aes_128_cm_sha1_80::CipherCtx> {
+            Box::new(RustCryptoSrtpCryptoImpl::new_aes_128_cm_sha1_80(
+                key, encrypt,
+            ))
+        }
+
+        fn new_aead_aes_128_gcm(
+            &self,
+            key: aead_aes_128_gcm::AeadKey,
+            encrypt: bool,
+        ) -> Box<dyn aead_aes_128_gcm::CipherCtx> {
+            Box::new(RustCryptoSrtpCryptoImpl::new_aead_aes_128_gcm(key, encrypt))
+        }
+
+        fn srtp_aes_128_ecb_round(&self, key: &[u8], input: &[u8], output: &mut [u8]) {
+            RustCryptoSrtpCryptoImpl::srtp_aes_128_ecb_round(key, input, output)
+        }
+    }
+
+    #[test]
+    fn srtp_roundtrip() {
+        let mat = KeyingMaterial::new((0..60).collect());
+
+        let rtp = [
+            0x80, 0x60, 0x00, 0x01, // seq 1
+            0x00, 0x00, 0x00, 0x2a, // timestamp
+            0x12, 0x34, 0x56, 0x78, // ssrc
+            0xde, 0xad, 0xbe, 0xef, 0x01, 0x02, 0x03, 0x04,
+        ];
+        let header = RtpHeader::parse(&rtp, &ExtensionMap::empty()).unwrap();
+        let rr = [0x80, 201, 0x00, 0x01, 0, 0, 0, 42];
+
+        for profile in [SrtpProfile::Aes128CmSha1_80, SrtpProfile::AeadAes128Gcm] {
+            // Both ends derive the same keys for one direction.
+            let mut tx = SrtpContext::with_crypto(&RustCrypto, profile, &mat, true);
+            let mut rx = SrtpContext::with_crypto(&RustCrypto, profile, &mat, true);
+
+            let encrypted = tx.protect_rtp(&rtp, &header, 1);
+            assert_ne!(encrypted[12..rtp.len()], rtp[12..]);
+
+            let decrypted = rx.unprotect_rtp(&encrypted, &header, 1).unwrap();
+            assert_eq!(decrypted, rtp[12..], "{profile:?}");
+
+            // A broken packet is refused.
+            let mut broken = encrypted.clone();
+            broken[12] ^= 1;
+            assert!(rx.unprotect_rtp(&broken, &header, 1).is_none());
+
+            let encrypted = tx.protect_rtcp(&rr);
+            let decrypted = rx.unprotect_rtcp(&encrypted).unwrap();
+            assert_eq!(decrypted, rr, "{profile:?}");
+        }
+    }
+}
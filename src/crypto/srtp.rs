@@ -1,4 +1,5 @@
 use std::fmt;
+use std::panic::RefUnwindSafe;
 
 use self::aead_aes_128_gcm::AeadKey;
 use self::aes_128_cm_sha1_80::AesKey;
@@ -17,6 +18,18 @@ impl SrtpProfile {
     pub(crate) const ALL: &'static [SrtpProfile] =
         &[SrtpProfile::AeadAes128Gcm, SrtpProfile::Aes128CmSha1_80];
 
+    /// Whether the profile only uses FIPS approved algorithms.
+    pub(crate) fn is_fips_approved(&self) -> bool {
+        match self {
+            #[cfg(feature = "_internal_test_exports")]
+            SrtpProfile::PassThrough => false,
+            // AES-CM and HMAC-SHA1 are approved algorithms, but the HMAC is
+            // computed with the pure Rust `hmac` crate, outside the validated module.
+            SrtpProfile::Aes128CmSha1_80 => false,
+            SrtpProfile::AeadAes128Gcm => true,
+        }
+    }
+
     /// The length of keying material to extract from the DTLS session in bytes.
     #[rustfmt::skip]
     pub(crate) fn keying_material_len(&self) -> usize {
@@ -41,14 +54,32 @@ pub fn new_aes_128_cm_sha1_80(
     key: AesKey,
     encrypt: bool,
 ) -> Box<dyn aes_128_cm_sha1_80::CipherCtx> {
-    #[cfg(feature = "openssl")]
+    #[cfg(all(feature = "openssl", not(feature = "rust-crypto-srtp")))]
     {
         let ctx = super::ossl::OsslSrtpCryptoImpl::new_aes_128_cm_sha1_80(key, encrypt);
         Box::new(ctx)
     }
-    #[cfg(not(feature = "openssl"))]
+    #[cfg(all(
+        feature = "boringssl",
+        not(any(feature = "openssl", feature = "rust-crypto-srtp"))
+    ))]
+    {
+        let ctx = super::bssl::BsslSrtpCryptoImpl::new_aes_128_cm_sha1_80(key, encrypt);
+        Box::new(ctx)
+    }
+    #[cfg(feature = "rust-crypto-srtp")]
+    {
+        let ctx =
+            super::rust_crypto::RustCryptoSrtpCryptoImpl::new_aes_128_cm_sha1_80(key, encrypt);
+        Box::new(ctx)
+    }
+    #[cfg(not(any(
+        feature = "openssl",
+        feature = "boringssl",
+        feature = "rust-crypto-srtp"
+    )))]
     {
-        panic!("No SRTP implementation. Enable openssl feature");
+        panic!("No SRTP implementation. Enable openssl, boringssl or rust-crypto-srtp feature");
     }
 }
 
@@ -61,14 +92,31 @@ pub fn new_aead_aes_128_gcm(key: AeadKey, encrypt: bool) -> Box<dyn aead_aes_128
     /// TODO: The exact mechanism for passing which crypto to use from
     ///       RtcConfig to here. We're not going to instantiate openssl
     ///       automatically.
-    #[cfg(feature = "openssl")]
+    #[cfg(all(feature = "openssl", not(feature = "rust-crypto-srtp")))]
     {
         let ctx = super::ossl::OsslSrtpCryptoImpl::new_aead_aes_128_gcm(key, encrypt);
         Box::new(ctx)
     }
-    #[cfg(not(feature = "openssl"))]
+    #[cfg(all(
+        feature = "boringssl",
+        not(any(feature = "openssl", feature = "rust-crypto-srtp"))
+    ))]
     {
-        panic!("No SRTP implementation. Enable openssl feature");
+        let ctx = super::bssl::BsslSrtpCryptoImpl::new_aead_aes_128_gcm(key, encrypt);
+        Box::new(ctx)
+    }
+    #[cfg(feature = "rust-crypto-srtp")]
+    {
+        let ctx = super::rust_crypto::RustCryptoSrtpCryptoImpl::new_aead_aes_128_gcm(key, encrypt);
+        Box::new(ctx)
+    }
+    #[cfg(not(any(
+        feature = "openssl",
+        feature = "boringssl",
+        feature = "rust-crypto-srtp"
+    )))]
+    {
+        panic!("No SRTP implementation. Enable openssl, boringssl or rust-crypto-srtp feature");
     }
 }
 
@@ -78,13 +126,126 @@ pub fn srtp_aes_128_ecb_round(key: &[u8], input: &[u8], output: &mut [u8]) {
     /// TODO: The exact mechanism for passing which crypto to use from
     ///       RtcConfig to here. We're not going to instantiate openssl
     ///       automatically.
-    #[cfg(feature = "openssl")]
+    #[cfg(all(feature = "openssl", not(feature = "rust-crypto-srtp")))]
     {
         super::ossl::OsslSrtpCryptoImpl::srtp_aes_128_ecb_round(key, input, output)
     }
-    #[cfg(not(feature = "openssl"))]
+    #[cfg(all(
+        feature = "boringssl",
+        not(any(feature = "openssl", feature = "rust-crypto-srtp"))
+    ))]
     {
-        panic!("No SRTP implementation. Enable openssl feature");
+        super::bssl::BsslSrtpCryptoImpl::srtp_aes_128_ecb_round(key, input, output)
+    }
+    #[cfg(feature = "rust-crypto-srtp")]
+    {
+        super::rust_crypto::RustCryptoSrtpCryptoImpl::srtp_aes_128_ecb_round(key, input, output)
+    }
+    #[cfg(not(any(
+        feature = "openssl",
+        feature = "boringssl",
+        feature = "rust-crypto-srtp"
+    )))]
+    {
+        panic!("No SRTP implementation. Enable openssl, boringssl or rust-crypto-srtp feature");
+    }
+}
+
+/// Provider of the ciphers used for SRTP.
+///
+/// By default, SRTP uses the crypto backend enabled by feature flags (`openssl` or
+/// `boringssl`), or the pure Rust ciphers with `rust-crypto-srtp`. Implement this to plug in another cipher
+/// implementation, such as a hardware-backed one, and set it with
+/// [`RtcConfig::set_srtp_crypto`][crate::RtcConfig::set_srtp_crypto].
+///
+/// Only the ciphers are pluggable. Key derivation, the SHA1 HMAC of
+/// `SRTP_AES128_CM_SHA1_80` and the packet handling remain in str0m.
+pub trait SrtpCrypto: fmt::Debug + Send + Sync + RefUnwindSafe + 'static {
+    /// Create an AES-128 counter mode cipher for `SRTP_AES128_CM_SHA1_80`.
+    fn new_aes_128_cm_sha1_80(
+        &self,
+        key: AesKey,
+        encrypt: bool,
+    ) -> Box<dyn aes_128_cm_sha1_80::CipherCtx>;
+
+    /// Create an AES-128 GCM cipher for `SRTP_AEAD_AES_128_GCM`.
+    fn new_aead_aes_128_gcm(
+        &self,
+        key: AeadKey,
+        encrypt: bool,
+    ) -> Box<dyn aead_aes_128_gcm::CipherCtx>;
+
+    /// Encrypt one 16 byte block with AES-128 in ECB mode, as used by the SRTP
+    /// key derivation.
+    ///
+    /// The `output` is 32 bytes, of which only the first 16 are used.
+    fn srtp_aes_128_ecb_round(&self, key: &[u8], input: &[u8], output: &mut [u8]);
+}
+
+/// The SRTP ciphers of the crypto backend enabled by feature flags.
+#[derive(Debug)]
+pub(crate) struct DefaultSrtpCrypto;
+
+impl SrtpCrypto for DefaultSrtpCrypto {
+    fn new_aes_128_cm_sha1_80(
+        &self,
+        key: AesKey,
+        encrypt: bool,
+    ) -> Box<dyn aes_128_cm_sha1_80::CipherCtx> {
+        new_aes_128_cm_sha1_80(key, encrypt)
+    }
+
+    fn new_aead_aes_128_gcm(
+        &self,
+        key: AeadKey,
+        encrypt: bool,
+    ) -> Box<dyn aead_aes_128_gcm::CipherCtx> {
+        new_aead_aes_128_gcm(key, encrypt)
+    }
+
+    fn srtp_aes_128_ecb_round(&self, key: &[u8], input: &[u8], output: &mut [u8]) {
+        srtp_aes_128_ecb_round(key, input, output)
+    }
+}
+
+/// The SRTP ciphers of BoringSSL.
+///
+/// With both `openssl` and `boringssl` enabled, [`DefaultSrtpCrypto`] is OpenSSL. This
+/// is used instead when the DTLS certificate is from BoringSSL.
+#[cfg(all(
+    feature = "openssl",
+    feature = "boringssl",
+    not(feature = "rust-crypto-srtp")
+))]
+#[derive(Debug)]
+pub(crate) struct BoringSslSrtpCrypto;
+
+#[cfg(all(
+    feature = "openssl",
+    feature = "boringssl",
+    not(feature = "rust-crypto-srtp")
+))]
+impl SrtpCrypto for BoringSslSrtpCrypto {
+    fn new_aes_128_cm_sha1_80(
+        &self,
+        key: AesKey,
+        encrypt: bool,
+    ) -> Box<dyn aes_128_cm_sha1_80::CipherCtx> {
+        let ctx = super::bssl::BsslSrtpCryptoImpl::new_aes_128_cm_sha1_80(key, encrypt);
+        Box::new(ctx)
+    }
+
+    fn new_aead_aes_128_gcm(
+        &self,
+        key: AeadKey,
+        encrypt: bool,
+    ) -> Box<dyn aead_aes_128_gcm::CipherCtx> {
+        let ctx = super::bssl::BsslSrtpCryptoImpl::new_aead_aes_128_gcm(key, encrypt);
+        Box::new(ctx)
+    }
+
+    fn srtp_aes_128_ecb_round(&self, key: &[u8], input: &[u8], output: &mut [u8]) {
+        super::bssl::BsslSrtpCryptoImpl::srtp_aes_128_ecb_round(key, input, output)
     }
 }
 
@@ -92,13 +253,9 @@ pub trait SrtpCryptoImpl {
     type Aes128CmSha1_80: aes_128_cm_sha1_80::CipherCtx;
     type AeadAes128Gcm: aead_aes_128_gcm::CipherCtx;
 
-    fn new_aes_128_cm_sha1_80(key: AesKey, encrypt: bool) -> Self::Aes128CmSha1_80 {
-        <Self::Aes128CmSha1_80 as aes_128_cm_sha1_80::CipherCtx>::new(key, encrypt)
-    }
+    fn new_aes_128_cm_sha1_80(key: AesKey, encrypt: bool) -> Self::Aes128CmSha1_80;
 
-    fn new_aead_aes_128_gcm(key: AeadKey, encrypt: bool) -> Self::AeadAes128Gcm {
-        <Self::AeadAes128Gcm as aead_aes_128_gcm::CipherCtx>::new(key, encrypt)
-    }
+    fn new_aead_aes_128_gcm(key: AeadKey, encrypt: bool) -> Self::AeadAes128Gcm;
 
     fn srtp_aes_128_ecb_round(key: &[u8], input: &[u8], output: &mut [u8]);
 }
@@ -116,11 +273,11 @@ pub mod aes_128_cm_sha1_80 {
     pub type RtpSalt = [u8; 14];
     pub type RtpIv = [u8; 16];
 
+    /// AES-128 counter mode cipher for `SRTP_AES128_CM_SHA1_80`.
+    ///
+    /// See [`SrtpCrypto`][super::SrtpCrypto].
     pub trait CipherCtx: UnwindSafe + Send + Sync {
-        fn new(key: AesKey, encrypt: bool) -> Self
-        where
-            Self: Sized;
-
+        /// Encrypt `input` into `output`, which are the same length.
         fn encrypt(
             &mut self,
             iv: &RtpIv,
@@ -128,6 +285,7 @@ pub mod aes_128_cm_sha1_80 {
             output: &mut [u8],
         ) -> Result<(), CryptoError>;
 
+        /// Decrypt `input` into `output`, which are the same length.
         fn decrypt(
             &mut self,
             iv: &RtpIv,
@@ -189,11 +347,13 @@ pub mod aead_aes_128_gcm {
     pub type RtpSalt = [u8; SALT_LEN];
     pub type RtpIv = [u8; SALT_LEN];
 
+    /// AES-128 GCM cipher for `SRTP_AEAD_AES_128_GCM`.
+    ///
+    /// See [`SrtpCrypto`][super::SrtpCrypto].
     pub trait CipherCtx: UnwindSafe + Send + Sync {
-        fn new(key: AeadKey, encrypt: bool) -> Self
-        where
-            Self: Sized;
-
+        /// Encrypt `input` into `output` with additional authenticated data `aad`.
+        ///
+        /// The `output` is the length of `input` plus the 16 byte authentication tag.
         fn encrypt(
             &mut self,
             iv: &[u8; IV_LEN],
@@ -202,6 +362,10 @@ pub mod aead_aes_128_gcm {
             output: &mut [u8],
         ) -> Result<(), CryptoError>;
 
+        /// Decrypt and authenticate `input`, which ends with the authentication tag,
+        /// into `output`. The additional authenticated data is the concatenation of `aads`.
+        ///
+        /// Returns the number of decrypted bytes.
         fn decrypt(
             &mut self,
             iv: &[u8; IV_LEN],
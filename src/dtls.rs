@@ -2,22 +2,74 @@ use std::collections::VecDeque;
 use std::{fmt, io};
 use thiserror::Error;
 
+use crate::crypto::dtls::DtlsOptions;
 use crate::crypto::{CryptoError, DtlsImpl, Fingerprint};
 
-pub use crate::crypto::{DtlsCert, DtlsEvent};
+pub use crate::crypto::{DtlsCert, DtlsCipherSuite, DtlsEvent, DtlsGroup, DtlsKeyLog};
 use crate::net::DatagramSend;
 
 /// Errors that can arise in DTLS.
+///
+/// Some variants depend on the enabled crypto features.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum DtlsError {
     /// Some error from OpenSSL layer (used for DTLS).
     #[error("{0}")]
     #[cfg(feature = "openssl")]
     OpenSsl(#[from] openssl::error::ErrorStack),
 
+    /// Some error from BoringSSL layer (used for DTLS).
+    #[error("{0}")]
+    #[cfg(feature = "boringssl")]
+    BoringSsl(#[from] boring::error::ErrorStack),
+
     /// Other IO errors.
     #[error("{0}")]
     Io(#[from] io::Error),
+
+    /// The private key does not belong to the certificate.
+    #[error("Private key does not match certificate")]
+    CertificateKeyMismatch,
+
+    /// The certificate can't be replaced once the DTLS handshake has started,
+    /// or while an SDP offer with its fingerprint is outstanding.
+    #[error("DTLS started or offer outstanding, certificate can't be replaced")]
+    CertificateInUse,
+
+    /// FIPS mode is required, but the crypto backend is not operating in FIPS mode.
+    #[error("Crypto backend is not in FIPS mode")]
+    FipsNotEnabled,
+
+    /// The configured DTLS cipher suites leave none to negotiate.
+    #[error("No DTLS cipher suites to negotiate")]
+    NoCipherSuites,
+
+    /// The configured DTLS key exchange groups leave none to negotiate.
+    #[error("No DTLS key exchange groups to negotiate")]
+    NoGroups,
+}
+
+/// Our role in the DTLS handshake.
+///
+/// Resolved from the `a=setup` attributes in the SDP negotiation, or given to
+/// [`DirectApi::start_dtls`][crate::change::DirectApi::start_dtls].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DtlsRole {
+    /// We initiate the handshake, `a=setup:active`.
+    Client,
+    /// We wait for the remote to initiate the handshake, `a=setup:passive`.
+    Server,
+}
+
+impl DtlsRole {
+    pub(crate) fn from_active(active: bool) -> Self {
+        if active {
+            DtlsRole::Client
+        } else {
+            DtlsRole::Server
+        }
+    }
 }
 
 impl DtlsError {
@@ -36,7 +88,13 @@ impl From<CryptoError> for DtlsError {
         match value {
             #[cfg(feature = "openssl")]
             CryptoError::OpenSsl(e) => DtlsError::OpenSsl(e),
+            #[cfg(feature = "boringssl")]
+            CryptoError::BoringSsl(e) => DtlsError::BoringSsl(e),
             CryptoError::Io(e) => DtlsError::Io(e),
+            CryptoError::CertificateKeyMismatch => DtlsError::CertificateKeyMismatch,
+            CryptoError::FipsNotEnabled => DtlsError::FipsNotEnabled,
+            CryptoError::NoCipherSuites => DtlsError::NoCipherSuites,
+            CryptoError::NoGroups => DtlsError::NoGroups,
         }
     }
 }
@@ -53,6 +111,9 @@ pub struct Dtls {
 
     /// Events ready to be polled.
     events: VecDeque<DtlsEvent>,
+
+    /// Context options, kept to recreate the impl on a new certificate.
+    options: DtlsOptions,
 }
 
 impl Dtls {
@@ -60,18 +121,35 @@ impl Dtls {
     ///
     /// `active` indicates whether this side should initiate the handshake or not.
     /// This in turn is governed by the `a=setup` SDP attribute.
-    pub fn new(cert: DtlsCert) -> Result<Self, DtlsError> {
-        let dtls_impl = cert.create_dtls_impl()?;
-        let fingerprint = cert.fingerprint();
+    pub(crate) fn new(cert: DtlsCert, options: DtlsOptions) -> Result<Self, DtlsError> {
+        options.validate()?;
+
+        let dtls_impl = cert.create_dtls_impl(&options)?;
+        let fingerprint = cert.fingerprint_with_hash(options.fingerprint_hash);
 
         Ok(Self {
             dtls_impl,
             fingerprint,
             remote_fingerprint: None,
             events: VecDeque::new(),
+            options,
         })
     }
 
+    /// Replace the certificate.
+    ///
+    /// Only possible before the handshake has started, i.e. before `set_active`.
+    pub fn set_cert(&mut self, cert: DtlsCert) -> Result<(), DtlsError> {
+        if self.is_inited() {
+            return Err(DtlsError::CertificateInUse);
+        }
+
+        self.dtls_impl = cert.create_dtls_impl(&self.options)?;
+        self.fingerprint = cert.fingerprint_with_hash(self.options.fingerprint_hash);
+
+        Ok(())
+    }
+
     /// Tells if this instance has been inited.
     ///
     /// Once true, we cannot do `set_active` anymore.
@@ -92,6 +170,11 @@ impl Dtls {
         self.dtls_impl.is_active()
     }
 
+    /// The role, once set_active.
+    pub fn role(&self) -> Option<DtlsRole> {
+        self.is_active().map(DtlsRole::from_active)
+    }
+
     /// The local fingerprint.
     ///
     /// To be communicated in SDP offers sent to the remote peer.
@@ -142,8 +225,8 @@ impl Dtls {
 
         if self.remote_fingerprint.is_none() && self.events.len() > len_before {
             for ev in &self.events {
-                if let DtlsEvent::RemoteFingerprint(fingerprint) = ev {
-                    self.remote_fingerprint = Some(fingerprint.clone());
+                if let DtlsEvent::RemoteFingerprint(fingerprints) = ev {
+                    self.remote_fingerprint = fingerprints.first().cloned();
                 }
             }
         }
@@ -154,6 +237,15 @@ impl Dtls {
     pub(crate) fn is_connected(&self) -> bool {
         self.dtls_impl.is_connected()
     }
+
+    /// Start a re-handshake to get fresh SRTP keying material.
+    pub(crate) fn rekey(&mut self) -> Result<(), DtlsError> {
+        if !self.is_connected() {
+            return Ok(());
+        }
+
+        Ok(self.dtls_impl.rekey()?)
+    }
 }
 
 impl fmt::Debug for DtlsEvent {
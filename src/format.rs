@@ -11,7 +11,10 @@ use crate::sdp::FormatParam;
 
 // These really don't belong anywhere, but I guess they're kind of related
 // to codecs etc.
-pub use crate::packet::{CodecExtra, H264CodecExtra, Vp8CodecExtra, Vp9CodecExtra};
+pub use crate::packet::OpusCodecExtra;
+pub use crate::packet::{Av1CodecExtra, CodecExtra, H264CodecExtra, H265CodecExtra};
+pub use crate::packet::{Vp8CodecExtra, Vp9CodecExtra, Vp9LayerFrame};
+pub use crate::packet::{Vp9PictureGroupEntry, Vp9ScalabilityStructure};
 
 /// Session config for all codecs.
 #[derive(Debug, Clone, Default)]
@@ -99,32 +102,134 @@ pub struct CodecSpec {
     pub format: FormatParams,
 }
 
+impl CodecSpec {
+    /// RED wraps both audio and video, and is told apart by the clock rate.
+    pub(crate) fn is_audio_red(&self) -> bool {
+        self.codec == Codec::Red && self.clock_rate != Frequency::NINETY_KHZ
+    }
+}
+
 /// Known codecs.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[non_exhaustive]
 #[allow(missing_docs)]
 pub enum Codec {
     Opus,
+    /// G.711 µ-law (RFC 3551). Static payload type 0.
+    Pcmu,
+    /// G.711 A-law (RFC 3551). Static payload type 8.
+    Pcma,
+    /// G.722 (RFC 3551). Static payload type 9. The RTP clock rate is 8kHz,
+    /// even though the audio is sampled at 16kHz.
+    G722,
     H264,
-    // TODO show this when we support h265.
-    #[doc(hidden)]
     H265,
     Vp8,
     Vp9,
-    // TODO show this when we support Av1.
-    #[doc(hidden)]
     Av1,
+    /// Comfort noise (RFC 3389). Sent by audio senders during silence instead of
+    /// the audio codec, for the receiver to generate matching background noise.
+    Cn,
+    /// DTMF tones and other telephone events (RFC 4733). Sent alongside the audio
+    /// codec, see [`Writer::write_dtmf()`][crate::media::Writer::write_dtmf].
+    TelephoneEvent,
     /// Technically not a codec, but used in places where codecs go
     /// in `a=rtpmap` lines.
     #[doc(hidden)]
     Rtx,
+    /// Redundancy encoding (RFC 2198). Like [`Codec::Rtx`], this wraps
+    /// another codec.
+    #[doc(hidden)]
+    Red,
+    /// Flexible forward error correction (RFC 8627), in the flexfec-03 draft
+    /// format used by libWebRTC. Not a codec, but protects the video codecs.
+    #[doc(hidden)]
+    FlexFec,
+    /// Forward error correction (RFC 5109). Not a codec, but sent wrapped in
+    /// [`Codec::Red`] alongside the video codecs it protects.
+    #[doc(hidden)]
+    Ulpfec,
     /// For RTP mode. No codec.
     #[doc(hidden)]
     Null,
+    /// Generic application data for [`MediaKind::Application`], negotiated with
+    /// a custom encoding name such as `x-haptics`.
+    ///
+    /// The payload is passed through as is, one RTP packet per write.
+    Application(AppCodecName),
     #[doc(hidden)]
     Unknown,
 }
 
+/// Encoding name of a [`Codec::Application`].
+///
+/// This is the `x-haptics` in `a=rtpmap:120 x-haptics/90000`. Encoding names are
+/// case insensitive and kept in lower case. At most 32 bytes to keep [`Codec`] `Copy`.
+///
+/// ```
+/// # use str0m::format::AppCodecName;
+/// let name = AppCodecName::new("X-Haptics").unwrap();
+/// assert_eq!(name.as_str(), "x-haptics");
+///
+/// assert!(AppCodecName::new("has/slash").is_none());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AppCodecName {
+    len: u8,
+    buf: [u8; 32],
+}
+
+impl AppCodecName {
+    /// Creates a new name, if it is a valid encoding name of at most 32 bytes.
+    pub fn new(name: &str) -> Option<Self> {
+        let valid = |c: u8| c.is_ascii_graphic() && c != b'/';
+        if name.is_empty() || name.len() > 32 || !name.bytes().all(valid) {
+            return None;
+        }
+
+        let mut buf = [0; 32];
+        buf[..name.len()].copy_from_slice(name.as_bytes());
+        buf.make_ascii_lowercase();
+
+        Some(AppCodecName {
+            len: name.len() as u8,
+            buf,
+        })
+    }
+
+    /// The name as string.
+    pub fn as_str(&self) -> &str {
+        // Only ASCII goes in, see new().
+        std::str::from_utf8(&self.buf[..self.len as usize]).expect("ascii name")
+    }
+}
+
+impl fmt::Debug for AppCodecName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AppCodecName({})", self.as_str())
+    }
+}
+
+impl fmt::Display for AppCodecName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Serialize for AppCodecName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for AppCodecName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        AppCodecName::new(&s)
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid encoding name: {s}")))
+    }
+}
+
 /// Codec specific format parameters.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct FormatParams {
@@ -162,8 +267,22 @@ pub struct FormatParams {
     /// * 64 00 1f - 6400=high (H)                  1f=level 3.1
     pub profile_level_id: Option<u32>,
 
-    /// VP9 profile id.
+    /// VP9 and H265 profile id.
     pub profile_id: Option<u32>,
+
+    /// H265 tier flag.
+    ///
+    /// * 0 - main tier.
+    /// * 1 - high tier.
+    pub tier_flag: Option<u8>,
+
+    /// H265 level, 30 times the level number, i.e. 93 for level 3.1.
+    pub level_id: Option<u8>,
+
+    /// FlexFEC specific parameter.
+    ///
+    /// The time window in microseconds a FEC packet can be used for recovery.
+    pub repair_window: Option<u32>,
 }
 
 impl PayloadParams {
@@ -281,8 +400,8 @@ impl PayloadParams {
             return None;
         }
 
-        if c0.channels != c1.channels {
-            // Channels must match
+        if c0.channels.unwrap_or(1) != c1.channels.unwrap_or(1) {
+            // Channels must match, where a missing value means one channel.
             return None;
         }
 
@@ -290,6 +409,11 @@ impl PayloadParams {
             return Some(Self::match_opus_score(c0, c1));
         }
 
+        if matches!(c0.codec, Codec::Pcmu | Codec::Pcma | Codec::G722) {
+            // The G.7xx codecs have no format parameters to match.
+            return Some(100);
+        }
+
         if c0.codec == Codec::H264 {
             return Self::match_h264_score(c0, c1);
         }
@@ -298,6 +422,20 @@ impl PayloadParams {
             return Self::match_vp9_score(c0, c1);
         }
 
+        if c0.codec == Codec::H265 {
+            return Self::match_h265_score(c0, c1);
+        }
+
+        if c0.codec == Codec::FlexFec {
+            // The repair window is a hint and doesn't need to match.
+            return Some(100);
+        }
+
+        if c0.codec == Codec::Red || c0.codec == Codec::Ulpfec {
+            // Video RED and ULPFEC have no format parameters to match.
+            return Some(100);
+        }
+
         // TODO: Fuzzy matching for any other audio codecs
         // TODO: Fuzzy matching for video
 
@@ -345,6 +483,28 @@ impl PayloadParams {
         Some(100)
     }
 
+    fn match_h265_score(c0: CodecSpec, c1: CodecSpec) -> Option<usize> {
+        // Default profile_id is 1 (Main) and tier_flag 0 (Main tier).
+        // https://www.rfc-editor.org/rfc/rfc7798#section-7.1
+        let c0_profile_id = c0.format.profile_id.unwrap_or(1);
+        let c1_profile_id = c1.format.profile_id.unwrap_or(1);
+
+        if c0_profile_id != c1_profile_id {
+            return None;
+        }
+
+        let c0_tier_flag = c0.format.tier_flag.unwrap_or(0);
+        let c1_tier_flag = c1.format.tier_flag.unwrap_or(0);
+
+        if c0_tier_flag != c1_tier_flag {
+            return None;
+        }
+
+        // level_id is the highest level the receiver supports, and the two directions
+        // are allowed to differ, so it doesn't need to match.
+        Some(100)
+    }
+
     fn match_h264_score(c0: CodecSpec, c1: CodecSpec) -> Option<usize> {
         // Default packetization mode is 0. https://www.rfc-editor.org/rfc/rfc6184#section-6.2
         let c0_packetization_mode = c0.format.packetization_mode.unwrap_or(0);
@@ -438,10 +598,12 @@ impl CodecConfig {
     pub fn new_with_defaults() -> Self {
         let mut c = Self::empty();
         c.enable_opus(true);
+        c.enable_g722(true);
+        c.enable_pcmu(true);
+        c.enable_pcma(true);
 
         c.enable_vp8(true);
         c.enable_h264(true);
-        // c.add_default_av1();
         c.enable_vp9(true);
 
         c
@@ -516,6 +678,29 @@ impl CodecConfig {
         )
     }
 
+    /// Convenience for adding an application data payload type.
+    ///
+    /// Used with [`MediaKind::Application`] m-lines. The `name` is the encoding name
+    /// negotiated in the SDP, and is matched against that of the remote peer.
+    ///
+    /// ```
+    /// # use str0m::format::{AppCodecName, CodecConfig};
+    /// # use str0m::media::Frequency;
+    /// let mut config = CodecConfig::empty();
+    /// let name = AppCodecName::new("x-haptics").unwrap();
+    /// config.add_application(120.into(), name, Frequency::NINETY_KHZ);
+    /// ```
+    pub fn add_application(&mut self, pt: Pt, name: AppCodecName, clock_rate: Frequency) {
+        self.add_config(
+            pt,
+            None,
+            Codec::Application(name),
+            clock_rate,
+            None,
+            FormatParams::default(),
+        )
+    }
+
     /// Add a default OPUS payload type.
     pub fn enable_opus(&mut self, enabled: bool) {
         self.params.retain(|c| c.spec.codec != Codec::Opus);
@@ -536,6 +721,22 @@ impl CodecConfig {
         )
     }
 
+    /// Add a default RED (RFC 2198) payload type for redundancy encoding of Opus.
+    pub fn enable_opus_red(&mut self, enabled: bool) {
+        self.params.retain(|c| !c.spec.is_audio_red());
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            63.into(),
+            None,
+            Codec::Red,
+            Frequency::FORTY_EIGHT_KHZ,
+            Some(2),
+            FormatParams::default(),
+        );
+    }
+
     /// Add a default VP8 payload type.
     pub fn enable_vp8(&mut self, enabled: bool) {
         self.params.retain(|c| c.spec.codec != Codec::Vp8);
@@ -573,19 +774,42 @@ impl CodecConfig {
         }
     }
 
-    // TODO: AV1 depacketizer/packetizer.
-    //
-    // /// Add a default AV1 payload type.
-    // pub fn add_default_av1(&mut self) {
-    //     self.add_config(
-    //         41.into(),
-    //         Some(42.into()),
-    //         Codec::Av1,
-    //         90_000,
-    //         None,
-    //         FormatParams::default(),
-    //     )
-    // }
+    /// Add a default H265 payload type.
+    pub fn enable_h265(&mut self, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != Codec::H265);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            49.into(),
+            Some(50.into()),
+            Codec::H265,
+            Frequency::NINETY_KHZ,
+            None,
+            FormatParams {
+                profile_id: Some(1),
+                tier_flag: Some(0),
+                level_id: Some(93),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Add a default AV1 payload type.
+    pub fn enable_av1(&mut self, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != Codec::Av1);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            41.into(),
+            Some(42.into()),
+            Codec::Av1,
+            Frequency::NINETY_KHZ,
+            None,
+            FormatParams::default(),
+        )
+    }
 
     /// Add a default VP9 payload type.
     pub fn enable_vp9(&mut self, enabled: bool) {
@@ -617,6 +841,131 @@ impl CodecConfig {
         );
     }
 
+    /// Add a default FlexFEC payload type, protecting video.
+    ///
+    /// This uses the flexfec-03 draft format of libWebRTC.
+    pub fn enable_flexfec(&mut self, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != Codec::FlexFec);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            118.into(),
+            None,
+            Codec::FlexFec,
+            Frequency::NINETY_KHZ,
+            None,
+            FormatParams {
+                repair_window: Some(10_000_000),
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Add default RED and ULPFEC payload types, protecting video.
+    ///
+    /// The video is sent wrapped in RED to share the SSRC with the ULPFEC packets.
+    pub fn enable_ulpfec(&mut self, enabled: bool) {
+        self.params.retain(|c| {
+            let video_red = c.spec.codec == Codec::Red && !c.spec.is_audio_red();
+            !(video_red || c.spec.codec == Codec::Ulpfec)
+        });
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            116.into(),
+            None,
+            Codec::Red,
+            Frequency::NINETY_KHZ,
+            None,
+            FormatParams::default(),
+        );
+        self.add_config(
+            117.into(),
+            None,
+            Codec::Ulpfec,
+            Frequency::NINETY_KHZ,
+            None,
+            FormatParams::default(),
+        );
+    }
+
+    /// Add the static G.711 µ-law (PCMU) payload type 0.
+    pub fn enable_pcmu(&mut self, enabled: bool) {
+        self.enable_g7xx(0, Codec::Pcmu, enabled);
+    }
+
+    /// Add the static G.711 A-law (PCMA) payload type 8.
+    pub fn enable_pcma(&mut self, enabled: bool) {
+        self.enable_g7xx(8, Codec::Pcma, enabled);
+    }
+
+    /// Add the static G.722 payload type 9.
+    pub fn enable_g722(&mut self, enabled: bool) {
+        self.enable_g7xx(9, Codec::G722, enabled);
+    }
+
+    fn enable_g7xx(&mut self, pt: u8, codec: Codec, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != codec);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            pt.into(),
+            None,
+            codec,
+            Frequency::EIGHT_KHZ,
+            None,
+            FormatParams::default(),
+        )
+    }
+
+    /// Add a default comfort noise (CN) payload type for 8kHz audio.
+    pub fn enable_cn(&mut self, enabled: bool) {
+        self.params.retain(|c| c.spec.codec != Codec::Cn);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            13.into(),
+            None,
+            Codec::Cn,
+            Frequency::EIGHT_KHZ,
+            None,
+            FormatParams::default(),
+        )
+    }
+
+    /// Add default telephone-event payload types for DTMF.
+    ///
+    /// The events must use the clock rate of the audio they are sent with, so there is one
+    /// at 48kHz to pair with Opus, and one at 8kHz to pair with the G.7xx codecs, which is
+    /// what SIP gateways offer.
+    pub fn enable_dtmf(&mut self, enabled: bool) {
+        self.params
+            .retain(|c| c.spec.codec != Codec::TelephoneEvent);
+        if !enabled {
+            return;
+        }
+        self.add_config(
+            110.into(),
+            None,
+            Codec::TelephoneEvent,
+            Frequency::FORTY_EIGHT_KHZ,
+            None,
+            FormatParams::default(),
+        );
+        self.add_config(
+            126.into(),
+            None,
+            Codec::TelephoneEvent,
+            Frequency::EIGHT_KHZ,
+            None,
+            FormatParams::default(),
+        );
+    }
+
     /// Match the given parameters to the configured parameters.
     ///
     /// In a server scenario, a certain codec configuration might not have the same
@@ -656,12 +1005,13 @@ impl CodecConfig {
     }
 
     pub(crate) fn all_for_kind(&self, kind: MediaKind) -> impl Iterator<Item = &PayloadParams> {
-        self.params.iter().filter(move |params| {
-            if kind == MediaKind::Video {
+        self.params.iter().filter(move |params| match kind {
+            MediaKind::Audio => params.spec.codec.is_audio() || params.spec.is_audio_red(),
+            MediaKind::Video => {
                 params.spec.codec.is_video()
-            } else {
-                params.spec.codec.is_audio()
+                    || (params.spec.codec.is_video_protection() && !params.spec.is_audio_red())
             }
+            MediaKind::Application => params.spec.codec.is_application(),
         })
     }
 
@@ -812,7 +1162,11 @@ impl FormatParams {
             PacketizationMode(v) => self.packetization_mode = Some(*v),
             ProfileLevelId(v) => self.profile_level_id = Some(*v),
             ProfileId(v) => self.profile_id = Some(*v),
+            TierFlag(v) => self.tier_flag = Some(*v),
+            LevelId(v) => self.level_id = Some(*v),
+            RepairWindow(v) => self.repair_window = Some(*v),
             Apt(_) => {}
+            Red(_) => {}
             Unknown => {}
         }
     }
@@ -842,6 +1196,15 @@ impl FormatParams {
         if let Some(v) = self.profile_id {
             r.push(ProfileId(v));
         }
+        if let Some(v) = self.tier_flag {
+            r.push(TierFlag(v));
+        }
+        if let Some(v) = self.level_id {
+            r.push(LevelId(v));
+        }
+        if let Some(v) = self.repair_window {
+            r.push(RepairWindow(v));
+        }
 
         r
     }
@@ -863,7 +1226,7 @@ impl Codec {
     /// Tells if codec is audio.
     pub fn is_audio(&self) -> bool {
         use Codec::*;
-        matches!(self, Opus)
+        matches!(self, Opus | Pcmu | Pcma | G722 | Cn | TelephoneEvent)
     }
 
     /// Tells if codec is video.
@@ -872,10 +1235,24 @@ impl Codec {
         matches!(self, H265 | H264 | Vp8 | Vp9 | Av1)
     }
 
-    /// Audio/Video.
+    /// Tells if codec is application data.
+    pub fn is_application(&self) -> bool {
+        matches!(self, Codec::Application(_))
+    }
+
+    /// Tells if this is FlexFEC, ULPFEC or RED, when negotiated as separate PTs
+    /// alongside the video codecs.
+    pub(crate) fn is_video_protection(&self) -> bool {
+        use Codec::*;
+        matches!(self, FlexFec | Ulpfec | Red)
+    }
+
+    /// Audio/Video/Application.
     pub fn kind(&self) -> MediaKind {
         if self.is_audio() {
             MediaKind::Audio
+        } else if self.is_application() {
+            MediaKind::Application
         } else {
             MediaKind::Video
         }
@@ -887,12 +1264,20 @@ impl<'a> From<&'a str> for Codec {
         let lc = v.to_ascii_lowercase();
         match &lc[..] {
             "opus" => Codec::Opus,
+            "pcmu" => Codec::Pcmu,
+            "pcma" => Codec::Pcma,
+            "g722" => Codec::G722,
             "h264" => Codec::H264,
             "h265" => Codec::H265,
             "vp8" => Codec::Vp8,
             "vp9" => Codec::Vp9,
             "av1" => Codec::Av1,
+            "cn" => Codec::Cn,
+            "telephone-event" => Codec::TelephoneEvent,
             "rtx" => Codec::Rtx, // resends
+            "red" => Codec::Red, // redundancy
+            "flexfec-03" => Codec::FlexFec,
+            "ulpfec" => Codec::Ulpfec,
             _ => Codec::Unknown,
         }
     }
@@ -902,13 +1287,22 @@ impl fmt::Display for Codec {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Codec::Opus => write!(f, "opus"),
+            Codec::Pcmu => write!(f, "PCMU"),
+            Codec::Pcma => write!(f, "PCMA"),
+            Codec::G722 => write!(f, "G722"),
             Codec::H264 => write!(f, "H264"),
             Codec::H265 => write!(f, "H265"),
             Codec::Vp8 => write!(f, "VP8"),
             Codec::Vp9 => write!(f, "VP9"),
             Codec::Av1 => write!(f, "AV1"),
+            Codec::Cn => write!(f, "CN"),
+            Codec::TelephoneEvent => write!(f, "telephone-event"),
             Codec::Rtx => write!(f, "rtx"),
+            Codec::Red => write!(f, "red"),
+            Codec::FlexFec => write!(f, "flexfec-03"),
+            Codec::Ulpfec => write!(f, "ulpfec"),
             Codec::Null => write!(f, "null"),
+            Codec::Application(v) => write!(f, "{v}"),
             Codec::Unknown => write!(f, "unknown"),
         }
     }
@@ -951,6 +1345,9 @@ mod test {
                 packetization_mode,
                 profile_level_id,
                 profile_id: None, // VP8
+                tier_flag: None,
+                level_id: None,
+                repair_window: None,
             },
         }
     }
@@ -995,4 +1392,109 @@ mod test {
             assert_eq!(matched, must_match, "{msg}\nc0: {c0:#?}\nc1: {c1:#?}");
         }
     }
+
+    #[test]
+    fn test_h265_profile_matching() {
+        fn spec(profile_id: Option<u32>, tier_flag: Option<u8>, level_id: Option<u8>) -> CodecSpec {
+            CodecSpec {
+                codec: Codec::H265,
+                clock_rate: Frequency::NINETY_KHZ,
+                channels: None,
+                format: FormatParams {
+                    profile_id,
+                    tier_flag,
+                    level_id,
+                    ..Default::default()
+                },
+            }
+        }
+
+        // Unspecified means profile 1, tier 0.
+        assert!(PayloadParams::match_h265_score(
+            spec(None, None, None),
+            spec(Some(1), Some(0), None)
+        )
+        .is_some());
+
+        // Levels are allowed to differ.
+        assert!(PayloadParams::match_h265_score(
+            spec(Some(1), None, Some(93)),
+            spec(Some(1), None, Some(120))
+        )
+        .is_some());
+
+        assert!(PayloadParams::match_h265_score(
+            spec(Some(1), None, None),
+            spec(Some(2), None, None)
+        )
+        .is_none());
+        assert!(PayloadParams::match_h265_score(
+            spec(None, Some(0), None),
+            spec(None, Some(1), None)
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn test_g7xx_defaults() {
+        let config = CodecConfig::new_with_defaults();
+
+        let g7xx: Vec<_> = config
+            .params()
+            .iter()
+            .filter(|p| matches!(p.spec.codec, Codec::Pcmu | Codec::Pcma | Codec::G722))
+            .map(|p| (*p.pt, p.spec.codec, p.spec.clock_rate))
+            .collect();
+
+        assert_eq!(
+            g7xx,
+            [
+                (9, Codec::G722, Frequency::EIGHT_KHZ),
+                (0, Codec::Pcmu, Frequency::EIGHT_KHZ),
+                (8, Codec::Pcma, Frequency::EIGHT_KHZ),
+            ]
+        );
+
+        let spec = |codec, channels| CodecSpec {
+            codec,
+            clock_rate: Frequency::EIGHT_KHZ,
+            channels,
+            format: FormatParams::default(),
+        };
+
+        // A missing channel count is one channel, like Firefox G722/8000/1.
+        let local = PayloadParams::new(9.into(), None, spec(Codec::G722, None));
+        let remote = PayloadParams::new(9.into(), None, spec(Codec::G722, Some(1)));
+        assert_eq!(local.match_score(&remote), Some(100));
+
+        let remote = PayloadParams::new(9.into(), None, spec(Codec::G722, Some(2)));
+        assert_eq!(local.match_score(&remote), None);
+
+        let remote = PayloadParams::new(0.into(), None, spec(Codec::Pcma, None));
+        assert_eq!(local.match_score(&remote), None);
+    }
+
+    #[test]
+    fn test_red_per_kind() {
+        let mut config = CodecConfig::new_with_defaults();
+        config.enable_opus_red(true);
+        config.enable_ulpfec(true);
+
+        let red = |kind| -> Vec<_> {
+            config
+                .all_for_kind(kind)
+                .filter(|p| p.spec.codec == Codec::Red)
+                .map(|p| (*p.pt, p.spec.clock_rate))
+                .collect()
+        };
+
+        // Audio and video RED are separate PTs, each with its own clock rate.
+        assert_eq!(red(MediaKind::Audio), [(63, Frequency::FORTY_EIGHT_KHZ)]);
+        assert_eq!(red(MediaKind::Video), [(116, Frequency::NINETY_KHZ)]);
+
+        // Turning off one leaves the other.
+        config.enable_ulpfec(false);
+        assert_eq!(red(MediaKind::Audio), [(63, Frequency::FORTY_EIGHT_KHZ)]);
+        assert!(red(MediaKind::Video).is_empty());
+    }
 }
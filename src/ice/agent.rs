@@ -1,16 +1,18 @@
 use std::collections::{HashSet, VecDeque};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 
 use crate::io::{Id, StunClass, StunMethod, DATAGRAM_MTU_WARN};
 use crate::io::{Protocol, StunPacket};
-use crate::io::{StunMessage, TransId, STUN_TIMEOUT};
+use crate::io::{StunMessage, TransId, ROLE_CONFLICT, STUN_TIMEOUT};
 use crate::io::{Transmit, DATAGRAM_MTU};
+use crate::stats::{CandidatePairState, CandidatePairStats, StatsSnapshot};
 use crate::util::NonCryptographicRng;
+use crate::Limits;
 
-use super::candidate::{Candidate, CandidateKind};
+use super::candidate::{Candidate, CandidateKind, MAX_NETWORK_COST};
 use super::pair::{CandidatePair, CheckState, PairId};
 
 /// Handles the ICE protocol for a given peer.
@@ -40,7 +42,10 @@ pub struct IceAgent {
 
     // The default limit of candidate pairs for the checklist set is 100,
     // but the value MUST be configurable.
-    max_candidate_pairs: Option<usize>,
+    limits: Limits,
+
+    /// Preference between IPv6 and IPv4.
+    family_policy: IceFamilyPolicy,
 
     /// Credentials for this side. Set on init and ice-restart.
     local_credentials: IceCreds,
@@ -67,6 +72,16 @@ pub struct IceAgent {
     /// All remote candidates, in the order we get to know them.
     remote_candidates: Vec<Candidate>,
 
+    /// Remote mDNS candidates waiting for the app to resolve the hostname, with when to
+    /// give up on them.
+    pending_mdns: Vec<(Candidate, Option<Instant>)>,
+
+    /// No more local candidates will be added (until an ICE restart).
+    local_end_of_candidates: bool,
+
+    /// The remote peer signalled it has no more candidates (until an ICE restart).
+    remote_end_of_candidates: bool,
+
     /// The candidate pairs.
     candidate_pairs: Vec<CandidatePair>,
 
@@ -88,8 +103,21 @@ pub struct IceAgent {
     /// if we get a better candidate for [`IceAgentEvent::NominatedSend`].
     nominated_send: Option<PairId>,
 
+    /// Index of the pair last found for byte accounting. Media goes over the same
+    /// pair, so this saves searching all pairs on every datagram.
+    counted_pair: Option<usize>,
+
     /// Statistics counter for the agent.
     stats: IceAgentStats,
+
+    /// Consent freshness settings (RFC 7675).
+    consent: IceConsentConfig,
+
+    /// Whether we emitted [`IceConsent::Expiring`] for the nominated pair.
+    consent_expiring: bool,
+
+    /// How the controlling agent nominates.
+    nomination: IceNomination,
 }
 
 #[derive(Debug)]
@@ -101,11 +129,22 @@ struct StunRequest {
     trans_id: TransId,
     prio: u32,
     use_candidate: bool,
+    ice_controlling: Option<u64>,
+    ice_controlled: Option<u64>,
     remote_ufrag: String,
 }
 
 const REMOTE_PEER_REFLEXIVE_TEMP_FOUNDATION: &str = "tmp_prflx";
 
+/// Cap on remote mDNS candidates waiting for resolution.
+const MAX_PENDING_MDNS: usize = 20;
+
+/// How long to wait for an mDNS hostname to be resolved before dropping its candidates.
+const MDNS_RESOLVE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lowest allowed Ta, see [RFC 8445 section 14.2](https://www.rfc-editor.org/rfc/rfc8445#section-14.2).
+pub(crate) const MIN_TIMING_ADVANCE: Duration = Duration::from_millis(5);
+
 /// States the ICE connection can be in.
 ///
 /// More details on connection states can be found in the [ICE RFC][1].
@@ -137,14 +176,17 @@ pub enum IceConnectionState {
     /// or during temporary disconnections. When the problem resolves, the connection
     /// may return to the connected state.
     Disconnected,
+
+    /// The ICE agent has checked all candidate pairs against one another and has
+    /// failed to find compatible matches.
+    ///
+    /// This only happens after end-of-candidates from both the local and remote side,
+    /// since until then, more candidates can bring the connection back. An ICE
+    /// restart leaves this state.
+    Failed,
     //
-    // NB: The failed and closed state doesn't really have a mapping in this implementation.
-    //     We never end trickle ice and it's always possible to "come back" if more remote
-    //     candidates are added.
+    // NB: The closed state doesn't really have a mapping in this implementation.
     //
-    // The ICE candidate has checked all candidates pairs against one another and has
-    // failed to find compatible matches.
-    // Failed,
     // The ICE agent has shut down and is no longer handling requests.
     // Closed,
 }
@@ -158,7 +200,8 @@ impl IceConnectionState {
 
     /// Tells if this state is a disconnected state.
     pub fn is_disconnected(&self) -> bool {
-        *self == IceConnectionState::Disconnected
+        use IceConnectionState::*;
+        matches!(self, Disconnected | Failed)
     }
 }
 
@@ -224,6 +267,176 @@ pub enum IceAgentEvent {
         /// The remote address to send datagrams to.
         destination: SocketAddr,
     },
+
+    /// A remote candidate uses an obfuscated mDNS hostname.
+    ///
+    /// The application resolves the hostname and provides the result with
+    /// [`IceAgent::resolve_mdns`]. The candidate doesn't form pairs until then.
+    MdnsResolveRequest(String),
+
+    /// Consent freshness of the nominated pair changed.
+    Consent(IceConsent),
+
+    /// The pair used for sending changed. Follows the [`IceAgentEvent::NominatedSend`]
+    /// it corresponds to.
+    SelectedPair {
+        /// The local candidate of the pair.
+        local: Candidate,
+        /// The remote candidate of the pair.
+        remote: Candidate,
+    },
+
+    /// The agent switched role to resolve a role conflict with the peer.
+    RoleChange {
+        /// Whether the agent is now controlling.
+        controlling: bool,
+    },
+
+    /// A peer reflexive candidate was discovered from a connectivity check.
+    PeerReflexive {
+        /// The discovered candidate.
+        candidate: Candidate,
+        /// Whether it's our address as seen by the peer (from a binding response), or
+        /// the peer's address (from a binding request).
+        local: bool,
+    },
+
+    /// A remote peer reflexive candidate was signaled by the peer, which replaces the
+    /// temporary one with the actual kind, priority and foundation.
+    PeerReflexivePromoted {
+        /// The peer reflexive candidate that was replaced.
+        previous: Candidate,
+        /// The signaled candidate.
+        current: Candidate,
+    },
+}
+
+/// Consent freshness of the nominated pair, see [RFC 7675][1].
+///
+/// The remote peer consents to receive traffic by answering our STUN binding requests.
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc7675
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IceConsent {
+    /// The remote peer hasn't answered for half the consent timeout.
+    Expiring,
+    /// The remote peer answered again after [`IceConsent::Expiring`].
+    Refreshed,
+    /// The remote peer hasn't answered for the consent timeout. The pair is removed,
+    /// which typically leads to a disconnect unless there are other working pairs.
+    Lost,
+}
+
+/// Settings for consent freshness, see [`IceConsent`].
+///
+/// The defaults keep the regular cadence of STUN binding requests (at most 3 seconds
+/// apart), and consider consent lost after a number of unanswered requests (about
+/// 19 seconds).
+///
+/// ```
+/// # use str0m::IceConsentConfig;
+/// # use std::time::Duration;
+/// // The values suggested in RFC 7675.
+/// let consent = IceConsentConfig {
+///     interval: Some(Duration::from_secs(5)),
+///     timeout: Some(Duration::from_secs(30)),
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IceConsentConfig {
+    /// Time between consent checks on pairs that are answered. Randomized between 0.8
+    /// and 1.2 of the value.
+    pub interval: Option<Duration>,
+
+    /// Time without answers after which consent is lost.
+    pub timeout: Option<Duration>,
+}
+
+/// An IP address family.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum IpFamily {
+    /// IPv6
+    #[default]
+    Ipv6,
+    /// IPv4
+    Ipv4,
+}
+
+impl IpFamily {
+    fn is(&self, ip: IpAddr) -> bool {
+        match self {
+            IpFamily::Ipv6 => ip.is_ipv6(),
+            IpFamily::Ipv4 => ip.is_ipv4(),
+        }
+    }
+}
+
+/// How the agent treats IPv6 and IPv4 on dual-stack hosts, see [RFC 8421][1].
+///
+/// The default prefers IPv6 and interleaves the checks, which is what RFC 8421 recommends.
+///
+/// ```
+/// # use str0m::{IceFamilyPolicy, IpFamily};
+/// let policy = IceFamilyPolicy {
+///     prefer: IpFamily::Ipv4,
+///     ..Default::default()
+/// };
+/// ```
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc8421
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IceFamilyPolicy {
+    /// The family that ranks first among local candidates of the same type. This decides
+    /// which family is nominated when both work. Defaults to IPv6.
+    pub prefer: IpFamily,
+
+    /// Alternate the connectivity checks between the families, starting with the preferred.
+    ///
+    /// This way a broken path in one family doesn't hold up the checks in the other. When
+    /// `false`, all local candidates of the preferred family rank above those of the other
+    /// family of the same type, and the checks follow the pair priority. Defaults to `true`.
+    pub interleave: bool,
+}
+
+impl Default for IceFamilyPolicy {
+    fn default() -> Self {
+        Self {
+            prefer: IpFamily::Ipv6,
+            interleave: true,
+        }
+    }
+}
+
+/// How the controlling agent nominates candidate pairs.
+///
+/// This has no effect on the controlled agent, which follows the nominations of the
+/// controlling agent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IceNomination {
+    /// Wait until all candidate pairs are checked, then nominate the best one. The
+    /// nomination is kept unless the pair fails.
+    ///
+    /// This is regular nomination from [RFC 8445][1].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc8445#section-8.1.1
+    Regular,
+
+    /// Send USE-CANDIDATE in every check, so the controlled agent can start using a
+    /// pair as soon as it succeeds. Switches to better pairs as they succeed.
+    ///
+    /// This is aggressive nomination from RFC 5245.
+    Aggressive,
+
+    /// Nominate the first pair that succeeds, and nominate again whenever a better
+    /// pair succeeds, also after the connection is established.
+    #[default]
+    Renomination,
+}
+
+impl IceConsentConfig {
+    fn effective_timeout(&self) -> Duration {
+        self.timeout.unwrap_or(STUN_TIMEOUT)
+    }
 }
 
 impl IceCreds {
@@ -253,7 +466,8 @@ impl IceAgent {
         IceAgent {
             last_now: None,
             ice_lite: false,
-            max_candidate_pairs: None,
+            limits: Limits::default(),
+            family_policy: IceFamilyPolicy::default(),
             local_credentials,
             remote_credentials: None,
             controlling: false,
@@ -261,13 +475,20 @@ impl IceAgent {
             state: IceConnectionState::New,
             local_candidates: vec![],
             remote_candidates: vec![],
+            pending_mdns: vec![],
+            local_end_of_candidates: false,
+            remote_end_of_candidates: false,
             candidate_pairs: vec![],
             transmit: VecDeque::new(),
             events: VecDeque::new(),
             stun_server_queue: VecDeque::new(),
             discovered_recv: HashSet::new(),
             nominated_send: None,
+            counted_pair: None,
             stats: IceAgentStats::default(),
+            consent: IceConsentConfig::default(),
+            consent_expiring: false,
+            nomination: IceNomination::default(),
             timing_advance: Duration::from_millis(50),
         }
     }
@@ -276,7 +497,21 @@ impl IceAgent {
     ///
     /// Any pairs above this limit will be dropped (worst priority first).
     pub fn set_max_candidate_pairs(&mut self, max: usize) {
-        self.max_candidate_pairs = Some(max);
+        self.limits.max_candidate_pairs = Some(max);
+    }
+
+    /// Set limits for candidates and candidate pairs.
+    ///
+    /// Only the ICE fields of [`Limits`] are used by the agent.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    /// Set the preference between IPv6 and IPv4.
+    ///
+    /// Only affects candidates added after this call.
+    pub fn set_family_policy(&mut self, policy: IceFamilyPolicy) {
+        self.family_policy = policy;
     }
 
     /// Whether ice_lite is enabled.
@@ -298,9 +533,9 @@ impl IceAgent {
     /// Ta specifies the minimum increment of time that has to pass between calls to
     /// [`IceAgent::handle_timeout`]s (guided via [`IceAgent::poll_timeout`]).
     ///
-    /// Defaults to 50ms.
+    /// Defaults to 50ms. Values below 5ms are raised to 5ms, as RFC 8445 requires.
     pub fn set_timing_advance(&mut self, duration: Duration) {
-        self.timing_advance = duration
+        self.timing_advance = duration.max(MIN_TIMING_ADVANCE)
     }
 
     /// Local ice credentials.
@@ -328,6 +563,38 @@ impl IceAgent {
         &self.remote_candidates
     }
 
+    /// Signal that no more local candidates will be added.
+    ///
+    /// Together with [`IceAgent::set_remote_end_of_candidates`], this lets the agent
+    /// conclude [`IceConnectionState::Failed`] once all pairs failed.
+    pub fn set_local_end_of_candidates(&mut self) {
+        if !self.local_end_of_candidates {
+            debug!("Local end-of-candidates");
+            self.local_end_of_candidates = true;
+        }
+    }
+
+    /// Whether [`IceAgent::set_local_end_of_candidates`] has been called since the last
+    /// ICE restart.
+    pub fn local_end_of_candidates(&self) -> bool {
+        self.local_end_of_candidates
+    }
+
+    /// Signal that the remote peer will not send any more candidates.
+    ///
+    /// Remote candidates added after this are ignored, until the next ICE restart.
+    pub fn set_remote_end_of_candidates(&mut self) {
+        if !self.remote_end_of_candidates {
+            debug!("Remote end-of-candidates");
+            self.remote_end_of_candidates = true;
+        }
+    }
+
+    /// Whether the remote peer signalled end-of-candidates since the last ICE restart.
+    pub fn remote_end_of_candidates(&self) -> bool {
+        self.remote_end_of_candidates
+    }
+
     /// Determines whether any remote candidates match the specified address and
     /// have been verified with a STUN request/response.
     pub fn has_viable_remote_candidate(&self, addr: SocketAddr) -> bool {
@@ -345,6 +612,16 @@ impl IceAgent {
         }
     }
 
+    /// Set consent freshness settings.
+    pub fn set_consent(&mut self, consent: IceConsentConfig) {
+        self.consent = consent;
+    }
+
+    /// Set how to nominate when controlling.
+    pub fn set_nomination(&mut self, nomination: IceNomination) {
+        self.nomination = nomination;
+    }
+
     /// Credentials for STUN.
     ///
     /// The username for the credential is formed by concatenating the
@@ -396,6 +673,47 @@ impl IceAgent {
         self.controlling = v;
     }
 
+    /// The tie-breaker sent in ICE-CONTROLLING/ICE-CONTROLLED.
+    ///
+    /// When both agents claim the same role, the one with the larger
+    /// tie-breaker ends up controlling.
+    pub fn tie_breaker(&self) -> u64 {
+        self.control_tie_breaker
+    }
+
+    /// Set the tie-breaker. Defaults to a random value.
+    pub fn set_tie_breaker(&mut self, v: u64) {
+        self.control_tie_breaker = v;
+    }
+
+    /// Flip between controlling and controlled to resolve a role conflict.
+    ///
+    /// The pair priorities depend on the role and are recalculated.
+    fn switch_role(&mut self) {
+        self.controlling = !self.controlling;
+
+        debug!(
+            "Resolve ICE role conflict, switch to {}",
+            if self.controlling {
+                "controlling"
+            } else {
+                "controlled"
+            }
+        );
+
+        for pair in &mut self.candidate_pairs {
+            let local = pair.local_candidate(&self.local_candidates);
+            let remote = pair.remote_candidate(&self.remote_candidates);
+            let prio = CandidatePair::calculate_prio(self.controlling, remote.prio(), local.prio());
+            pair.set_prio(prio);
+        }
+        self.sort_pairs();
+
+        self.emit_event(IceAgentEvent::RoleChange {
+            controlling: self.controlling,
+        });
+    }
+
     /// Current ice agent state.
     pub fn state(&self) -> IceConnectionState {
         self.state
@@ -456,13 +774,16 @@ impl IceAgent {
         // 49152 - 65536 => host
         //
         // And furthermore we subdivide these to interleave IPv6 with IPv4
-        // so that odd numbers are ipv6 and even are ipv4.
+        // so that odd numbers are ipv6 and even are ipv4 (with the default
+        // IceFamilyPolicy).
         //
         // For host candidates this means:
         // 65535 - first ipv6
         // 65534 - first ipv4
         // 65533 - second ipv6
         // 65432 - second ipv4
+        //
+        // Without interleaving, the other family starts halfway down the interval.
         let counter_start: u32 = {
             use CandidateKind::*;
             let x = match c.kind() {
@@ -471,7 +792,12 @@ impl IceAgent {
                 ServerReflexive => 32_767,
                 Relayed => 16_383,
             };
-            x - if ip.is_ipv6() { 0 } else { 1 }
+            let policy = self.family_policy;
+            x - match (policy.prefer.is(ip), policy.interleave) {
+                (true, _) => 0,
+                (false, true) => 1,
+                (false, false) => 8_192,
+            }
         };
 
         // Count the number of existing candidates of the same kind.
@@ -482,7 +808,11 @@ impl IceAgent {
             .filter(|v| v.addr().is_ipv6() == ip.is_ipv6())
             .count() as u32;
 
-        let pref = counter_start - same_kind * 2;
+        // Candidates on costly networks, like cellular, go below those of the same kind on
+        // cheaper networks. The penalty is even to keep the IPv6/IPv4 interleaving.
+        let network_penalty = c.network_cost().min(MAX_NETWORK_COST) as u32 * 8;
+
+        let pref = counter_start - network_penalty - same_kind * 2;
         trace!("Calculated local preference: {}", pref);
 
         c.set_local_preference(pref);
@@ -529,6 +859,16 @@ impl IceAgent {
                 self.local_candidates.len() - 1
             }
         } else {
+            let count = self
+                .local_candidates
+                .iter()
+                .filter(|v| !v.discarded())
+                .count();
+            if count >= self.limits.max_local_candidates.unwrap_or(usize::MAX) {
+                debug!("Reject local candidate, max candidates reached: {:?}", c);
+                return false;
+            }
+
             info!("Add local candidate: {:?}", c);
             self.local_candidates.push(c);
             self.local_candidates.len() - 1
@@ -582,10 +922,26 @@ impl IceAgent {
             }
         }
 
+        // https://www.rfc-editor.org/rfc/rfc8838#section-14
+        // Once an agent has received an end-of-candidates indication, it MUST also
+        // ignore any newly received candidates for that data session.
+        if self.remote_end_of_candidates {
+            let known = self.remote_candidates.iter().any(|v| v.addr() == c.addr());
+            if !known {
+                debug!("Ignore candidate after end-of-candidates: {:?}", c);
+            }
+            return;
+        }
+
         // After we accepted the ufrag, don't keep this around since it will look
         // confusing inspecting the state.
         c.clear_ufrag();
 
+        if c.is_unresolved() {
+            self.add_pending_mdns(c);
+            return;
+        }
+
         let existing_prflx = self
             .remote_candidates
             .iter_mut()
@@ -605,24 +961,44 @@ impl IceAgent {
                 "Replace peer reflexive candidate, current: {:?} replaced with: {:?}",
                 existing, c
             );
-            *existing = c;
+            let previous = std::mem::replace(existing, c.clone());
+            self.emit_event(IceAgentEvent::PeerReflexivePromoted {
+                previous,
+                current: c,
+            });
             idx
         } else {
-            let maybe_discarded = self.remote_candidates.iter().position(|o| {
-                o.discarded()
-                    && c.addr() == o.addr()
+            let maybe_existing = self.remote_candidates.iter().position(|o| {
+                c.addr() == o.addr()
                     && c.base() == o.base()
                     && c.proto() == o.proto()
                     && c.kind() == o.kind()
                     && c.raddr() == o.raddr()
             });
 
-            if let Some(idx) = maybe_discarded {
+            if let Some(idx) = maybe_existing {
                 let other = &mut self.remote_candidates[idx];
+                if !other.discarded() {
+                    // Renegotiations repeat the candidates of the previous SDP. The pairs
+                    // are already formed, and must be left alone to not glitch the
+                    // connection.
+                    trace!("Remote candidate already known: {:?}", other);
+                    return;
+                }
                 debug!("Re-enable previously discarded remote: {:?}", other);
                 other.set_discarded(false);
                 idx
             } else {
+                let count = self
+                    .remote_candidates
+                    .iter()
+                    .filter(|v| !v.discarded())
+                    .count();
+                if count >= self.limits.max_remote_candidates.unwrap_or(usize::MAX) {
+                    debug!("Ignore remote candidate, max candidates reached: {:?}", c);
+                    return;
+                }
+
                 info!("Add remote candidate: {:?}", c);
                 self.remote_candidates.push(c);
                 self.remote_candidates.len() - 1
@@ -642,6 +1018,61 @@ impl IceAgent {
         self.form_pairs(&local_idxs, &remote_idxs);
     }
 
+    fn add_pending_mdns(&mut self, c: Candidate) {
+        if self.pending_mdns.iter().any(|(p, _)| *p == c) {
+            trace!("Remote mDNS candidate already pending: {:?}", c);
+            return;
+        }
+
+        if self.pending_mdns.len() >= MAX_PENDING_MDNS {
+            debug!("Reject mDNS candidate, too many pending: {:?}", c);
+            return;
+        }
+
+        let hostname = c.mdns_hostname().expect("mDNS candidate").to_string();
+        let already_requested = self
+            .pending_mdns
+            .iter()
+            .any(|(p, _)| p.mdns_hostname() == Some(&hostname));
+
+        info!("Add remote mDNS candidate pending resolution: {:?}", c);
+        let expires = self.last_now.map(|n| n + MDNS_RESOLVE_TIMEOUT);
+        self.pending_mdns.push((c, expires));
+
+        if !already_requested {
+            self.emit_event(IceAgentEvent::MdnsResolveRequest(hostname));
+        }
+    }
+
+    /// Provide the ip for an mDNS hostname previously requested via
+    /// [`IceAgentEvent::MdnsResolveRequest`].
+    ///
+    /// Candidates waiting for the hostname are added as remote candidates, and pairs
+    /// are formed for them.
+    pub fn resolve_mdns(&mut self, hostname: &str, ip: IpAddr) {
+        if ip.is_unspecified() || ip.is_multicast() {
+            debug!("Ignore invalid mDNS resolution {} -> {}", hostname, ip);
+            return;
+        }
+
+        let (resolved, pending): (Vec<_>, Vec<_>) = self
+            .pending_mdns
+            .drain(..)
+            .partition(|(c, _)| c.mdns_hostname() == Some(hostname));
+        self.pending_mdns = pending;
+
+        if resolved.is_empty() {
+            debug!("No pending mDNS candidate for: {}", hostname);
+            return;
+        }
+
+        for (mut c, _) in resolved {
+            c.set_resolved_ip(ip);
+            info!("Resolved mDNS candidate: {:?}", c);
+            self.add_remote_candidate(c);
+        }
+    }
+
     /// Form pairs given two slices of indexes into the local_candidates and remote_candidates.
     fn form_pairs(&mut self, local_idxs: &[usize], remote_idxs: &[usize]) {
         for local_idx in local_idxs {
@@ -654,6 +1085,14 @@ impl IceAgent {
                     continue 'outer;
                 }
 
+                // For TCP we only check pairs where we can open the connection. Pairs
+                // where the remote connects to us are formed from their binding requests.
+                if let (Some(l), Some(r)) = (local.tcp_type(), remote.tcp_type()) {
+                    if !l.can_connect_to(r) {
+                        continue 'outer;
+                    }
+                }
+
                 let prio =
                     CandidatePair::calculate_prio(self.controlling, remote.prio(), local.prio());
                 let mut pair = CandidatePair::new(*local_idx, *remote_idx, prio);
@@ -726,11 +1165,59 @@ impl IceAgent {
         // set is 100, but the value MUST be configurable.
         //
         // TODO: How does this work with trickle ice?
-        let max = self.max_candidate_pairs.unwrap_or(100);
+        let max = self.limits.max_candidate_pairs.unwrap_or(100);
         while self.candidate_pairs.len() > max {
             let pair = self.candidate_pairs.pop();
             debug!("Remove overflow pair {:?}", pair);
         }
+
+        if let Some(max) = self.limits.max_pairs_per_remote_candidate {
+            // The pairs are sorted, highest prio first.
+            let mut counts = vec![0; self.remote_candidates.len()];
+            self.candidate_pairs.retain(|p| {
+                let count = &mut counts[p.remote_idx()];
+                *count += 1;
+                let keep = *count <= max || p.is_nominated();
+                if !keep {
+                    debug!("Prune pair above max per remote candidate {:?}", p);
+                }
+                keep
+            });
+        }
+
+        self.sort_pairs();
+    }
+
+    /// Sort the candidate pairs in the order they are checked.
+    ///
+    /// This is priority order, and if the family policy says so, alternating between
+    /// the preferred and the other IP family (RFC 8421 section 4).
+    fn sort_pairs(&mut self) {
+        self.candidate_pairs.sort();
+
+        if !self.family_policy.interleave {
+            return;
+        }
+
+        let prefer = self.family_policy.prefer;
+        let (mut preferred, mut other): (Vec<_>, Vec<_>) =
+            self.candidate_pairs.drain(..).partition(|p| {
+                let local = p.local_candidate(&self.local_candidates);
+                prefer.is(local.addr().ip())
+            });
+
+        let mut preferred = preferred.drain(..);
+        let mut other = other.drain(..);
+
+        loop {
+            let a = preferred.next();
+            let b = other.next();
+            if a.is_none() && b.is_none() {
+                break;
+            }
+            self.candidate_pairs.extend(a);
+            self.candidate_pairs.extend(b);
+        }
     }
 
     /// Invalidate a candidate and remove it from the connection.
@@ -798,6 +1285,9 @@ impl IceAgent {
 
         self.remote_credentials = None;
         self.remote_candidates.clear();
+        self.pending_mdns.clear();
+        self.local_end_of_candidates = false;
+        self.remote_end_of_candidates = false;
         self.candidate_pairs.clear();
         self.transmit.clear();
         self.events.clear();
@@ -929,6 +1419,8 @@ impl IceAgent {
             self.stun_server_handle_message(now, &packet);
         } else if packet.message.is_successful_binding_response() {
             self.stun_client_handle_response(now, packet.message);
+        } else if packet.message.is_failed_binding_response() {
+            self.stun_client_handle_failure(now, packet.message);
         }
 
         self.emit_event(IceAgentEvent::DiscoveredRecv {
@@ -936,8 +1428,6 @@ impl IceAgent {
             source: packet.source,
         });
 
-        // TODO handle unsuccessful responses.
-
         true
     }
 
@@ -952,6 +1442,15 @@ impl IceAgent {
             self.emit_event(IceAgentEvent::IceRestart(self.local_credentials.clone()));
         }
 
+        // Unresolved mDNS candidates must not keep the agent from failing.
+        self.pending_mdns.retain_mut(|(c, expires)| {
+            let expires = *expires.get_or_insert(now + MDNS_RESOLVE_TIMEOUT);
+            if now >= expires {
+                debug!("Drop mDNS candidate that was never resolved: {:?}", c);
+            }
+            now < expires
+        });
+
         self.evaluate_state(now);
 
         // First we try to empty the queue of saved STUN requests.
@@ -981,18 +1480,27 @@ impl IceAgent {
 
         // prune failed candidates.
         let mut any_pruned = false;
+        let mut consent_lost = false;
+        let consent_timeout = self.consent.timeout;
+        let nominated_send = self.nominated_send;
         self.candidate_pairs.retain(|p| {
             let keep = if self.ice_lite {
                 p.has_recent_remote_binding_request(now)
             } else {
-                p.is_still_possible(now)
+                p.is_still_possible(now, consent_timeout)
             };
             if !keep {
                 debug!("Remove failed pair: {:?}", p);
                 any_pruned = true;
+                consent_lost |= Some(p.id()) == nominated_send;
             }
             keep
         });
+        if consent_lost && !self.ice_lite {
+            info!("Consent lost for nominated pair");
+            self.consent_expiring = false;
+            self.emit_event(IceAgentEvent::Consent(IceConsent::Lost));
+        }
         if any_pruned {
             self.evaluate_nomination();
             self.evaluate_state(now);
@@ -1003,6 +1511,8 @@ impl IceAgent {
             return;
         }
 
+        self.evaluate_consent(now);
+
         if self.ice_lite {
             // Remote binding request time is the timestamp in the CandidatePair that
             // is used to decide whether something is timed out or not. We need all
@@ -1019,11 +1529,12 @@ impl IceAgent {
         }
 
         // when do we need to handle the next candidate pair?
+        let interval = self.consent.interval;
         let next = self
             .candidate_pairs
             .iter_mut()
             .enumerate()
-            .map(|(i, c)| (i, c.next_binding_attempt(now)))
+            .map(|(i, c)| (i, c.next_binding_attempt(now, interval)))
             .min_by_key(|(_, t)| *t);
 
         if let Some((idx, deadline)) = next {
@@ -1072,9 +1583,19 @@ impl IceAgent {
             // ice-lite doesn't do checks.
             None
         } else {
-            self.candidate_pairs
+            let interval = self.consent.interval;
+            let next_attempt = self
+                .candidate_pairs
                 .iter_mut()
-                .map(|c| c.next_binding_attempt(last_now))
+                .map(|c| c.next_binding_attempt(last_now, interval))
+                .min();
+
+            // Wake up to tell when consent is about to expire, or mDNS resolution is
+            // given up on.
+            next_attempt
+                .into_iter()
+                .chain(self.consent_expiring_at())
+                .chain(self.pending_mdns.iter().filter_map(|(_, e)| *e))
                 .min()
         };
 
@@ -1144,6 +1665,8 @@ impl IceAgent {
             trans_id,
             prio,
             use_candidate,
+            ice_controlling: message.ice_controlling(),
+            ice_controlled: message.ice_controlled(),
             remote_ufrag: remote_ufrag.into(),
         };
 
@@ -1191,6 +1714,12 @@ impl IceAgent {
             return;
         }
 
+        if self.resolve_role_conflict(&req) {
+            // We keep our role, the other side must switch.
+            self.stun_server_reply_role_conflict(&req);
+            return;
+        }
+
         if req.use_candidate && self.controlling {
             // the other side is not controlling, and it sent USE-CANDIDATE. that's wrong.
             debug!("STUN request rejected, USE-CANDIDATE when local is controlling");
@@ -1250,6 +1779,11 @@ impl IceAgent {
                 c
             );
 
+            self.emit_event(IceAgentEvent::PeerReflexive {
+                candidate: c.clone(),
+                local: false,
+            });
+
             // This candidate is added to the list of remote candidates.  However,
             // the ICE agent does not pair this candidate with any local candidates.
             self.remote_candidates.push(c);
@@ -1315,7 +1849,7 @@ impl IceAgent {
             debug!("Created new pair for STUN request: {:?}", pair);
 
             self.candidate_pairs.push(pair);
-            self.candidate_pairs.sort();
+            self.sort_pairs();
         }
 
         let pair = self
@@ -1374,6 +1908,67 @@ impl IceAgent {
         self.transmit.push_back(trans);
     }
 
+    /// Detect a role conflict in an incoming binding request, see
+    /// [RFC 8445 §7.3.1.1][1].
+    ///
+    /// Either switches our role, or returns `true` if the request must be answered
+    /// with a 487 (Role Conflict) error.
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc8445#section-7.3.1.1
+    fn resolve_role_conflict(&mut self, req: &StunRequest) -> bool {
+        // An ice-lite agent is always controlled.
+        if self.ice_lite {
+            return false;
+        }
+
+        // Controlling with the larger tie-breaker, or controlled with the smaller,
+        // means we keep our role.
+        let (remote_tie_breaker, keep_role) = match (req.ice_controlling, req.ice_controlled) {
+            (Some(v), _) if self.controlling => (v, self.control_tie_breaker >= v),
+            (_, Some(v)) if !self.controlling => (v, self.control_tie_breaker < v),
+            _ => return false,
+        };
+
+        debug!(
+            "ICE role conflict, local tie-breaker {} remote {}",
+            self.control_tie_breaker, remote_tie_breaker
+        );
+
+        if keep_role {
+            true
+        } else {
+            self.switch_role();
+            false
+        }
+    }
+
+    fn stun_server_reply_role_conflict(&mut self, req: &StunRequest) {
+        let (_, password) = self.stun_credentials(true);
+
+        let reply = StunMessage::role_conflict_reply(req.trans_id);
+
+        trace!(
+            "Send STUN role conflict: {} -> {} {:?}",
+            req.destination,
+            req.source,
+            reply
+        );
+
+        let mut buf = vec![0_u8; DATAGRAM_MTU];
+
+        let n = reply
+            .to_bytes(&password, &mut buf)
+            .expect("IO error writing STUN reply");
+        buf.truncate(n);
+
+        self.transmit.push_back(Transmit {
+            proto: req.proto,
+            source: req.destination,
+            destination: req.source,
+            contents: buf.into(),
+        });
+    }
+
     fn stun_client_binding_request(&mut self, now: Instant, pair_idx: usize) {
         let (username, password) = self.stun_credentials(false);
 
@@ -1382,9 +1977,10 @@ impl IceAgent {
         let remote = pair.remote_candidate(&self.remote_candidates);
         let prio = local.prio_prflx();
         // Only the controlling side sends USE-CANDIDATE.
-        let use_candidate = self.controlling && pair.is_nominated();
+        let use_candidate = self.controlling
+            && (pair.is_nominated() || self.nomination == IceNomination::Aggressive);
 
-        let trans_id = pair.new_attempt(now);
+        let trans_id = pair.new_attempt(now, self.controlling);
 
         self.stats.bind_request_sent += 1;
 
@@ -1487,7 +2083,7 @@ impl IceAgent {
             let base = local_sent_from.base();
 
             // o  The type is peer reflexive.
-            let candidate = Candidate::peer_reflexive(
+            let mut candidate = Candidate::peer_reflexive(
                 local_sent_from.proto(),
                 mapped_address,
                 base,
@@ -1495,6 +2091,7 @@ impl IceAgent {
                 None,
                 self.local_credentials.ufrag.clone(),
             );
+            candidate.set_network_from(local_sent_from);
 
             debug!(
                 "Created local peer reflexive candidate for mapped address: {}",
@@ -1511,6 +2108,10 @@ impl IceAgent {
 
             // For now we do not tell the other side about discovered peer-reflexive candidates.
             // We just include it in our list of local candidates and use it for the "valid pair".
+            self.emit_event(IceAgentEvent::PeerReflexive {
+                candidate: candidate.clone(),
+                local: true,
+            });
             self.local_candidates.push(candidate);
 
             let idx = self.local_candidates.len() - 1;
@@ -1528,7 +2129,57 @@ impl IceAgent {
         self.evaluate_state(now);
     }
 
+    fn stun_client_handle_failure(&mut self, now: Instant, message: StunMessage<'_>) {
+        let trans_id = message.trans_id();
+
+        let code = message.error_code().map(|(code, _)| code);
+        if code != Some(ROLE_CONFLICT) {
+            debug!("Ignore STUN error response: {:?}", message.error_code());
+            return;
+        }
+
+        let Some(pair_idx) = self
+            .candidate_pairs
+            .iter()
+            .position(|p| p.has_binding_attempt(trans_id))
+        else {
+            return;
+        };
+
+        // Only switch if we still have the role the request was sent with, the
+        // responses to retransmits would otherwise flip us back.
+        let sent_controlling = self.candidate_pairs[pair_idx].attempt_controlling(trans_id);
+        if sent_controlling == Some(self.controlling) {
+            self.switch_role();
+        }
+
+        // The role is part of the request, so the check is redone straight away.
+        if let Some(pair) = self
+            .candidate_pairs
+            .iter_mut()
+            .find(|p| p.has_binding_attempt(trans_id))
+        {
+            pair.trigger_check(now);
+        }
+    }
+
     fn evaluate_nomination(&mut self) {
+        if self.controlling && self.nomination == IceNomination::Regular {
+            // Keep the nomination as long as the pair is around.
+            if self.nominated_pair_priority().is_some() {
+                return;
+            }
+
+            // Wait until all pairs are checked, failed pairs are pruned.
+            let checking = self
+                .candidate_pairs
+                .iter()
+                .any(|p| p.state() != CheckState::Succeeded);
+            if checking {
+                return;
+            }
+        }
+
         let nominated_pair_priority = self.nominated_pair_priority();
 
         let best_prio = if self.controlling {
@@ -1563,15 +2214,133 @@ impl IceAgent {
                 best_prio.nominate(self.ice_lite);
             }
 
-            let local = best_prio.local_candidate(&self.local_candidates);
-            let remote = best_prio.remote_candidate(&self.remote_candidates);
+            let local = best_prio.local_candidate(&self.local_candidates).clone();
+            let remote = best_prio.remote_candidate(&self.remote_candidates).clone();
 
             self.nominated_send = Some(best_prio.id());
             self.emit_event(IceAgentEvent::NominatedSend {
                 proto: local.proto(),
                 source: local.base(),
                 destination: remote.addr(),
-            })
+            });
+            self.emit_event(IceAgentEvent::SelectedPair { local, remote });
+        }
+    }
+
+    /// Emit [`IceConsent::Expiring`] and [`IceConsent::Refreshed`] for the nominated pair.
+    fn evaluate_consent(&mut self, now: Instant) {
+        if self.ice_lite {
+            // ice-lite doesn't send checks, hence gets no consent.
+            return;
+        }
+
+        let last_response = self
+            .nominated_send
+            .and_then(|id| self.candidate_pairs.iter().find(|p| p.id() == id))
+            .and_then(|p| p.last_response());
+
+        let Some(last_response) = last_response else {
+            return;
+        };
+
+        let expiring = now - last_response >= self.consent.effective_timeout() / 2;
+
+        if expiring != self.consent_expiring {
+            self.consent_expiring = expiring;
+            let consent = if expiring {
+                IceConsent::Expiring
+            } else {
+                IceConsent::Refreshed
+            };
+            info!("Consent for nominated pair: {:?}", consent);
+            self.emit_event(IceAgentEvent::Consent(consent));
+        }
+    }
+
+    /// When the consent for the nominated pair is going to be considered expiring.
+    fn consent_expiring_at(&self) -> Option<Instant> {
+        if self.ice_lite || self.consent_expiring {
+            return None;
+        }
+
+        let id = self.nominated_send?;
+        let pair = self.candidate_pairs.iter().find(|p| p.id() == id)?;
+
+        Some(pair.last_response()? + self.consent.effective_timeout() / 2)
+    }
+
+    /// Count bytes of a datagram, other than STUN, sent from `source` to `destination`.
+    pub(crate) fn count_sent(&mut self, source: SocketAddr, destination: SocketAddr, n: usize) {
+        if let Some(pair) = self.pair_by_addrs(source, destination) {
+            pair.add_bytes_sent(n);
+        }
+    }
+
+    /// Count bytes of a datagram, other than STUN, received on `destination` from `source`.
+    pub(crate) fn count_received(&mut self, source: SocketAddr, destination: SocketAddr, n: usize) {
+        if let Some(pair) = self.pair_by_addrs(destination, source) {
+            pair.add_bytes_received(n);
+        }
+    }
+
+    fn pair_by_addrs(
+        &mut self,
+        local: SocketAddr,
+        remote: SocketAddr,
+    ) -> Option<&mut CandidatePair> {
+        let local_candidates = &self.local_candidates;
+        let remote_candidates = &self.remote_candidates;
+
+        let is_match = |p: &CandidatePair| {
+            p.local_candidate(local_candidates).base() == local
+                && p.remote_candidate(remote_candidates).addr() == remote
+        };
+
+        // Pairs are sorted and pruned, so the cached index is checked before use.
+        let cached = self
+            .counted_pair
+            .filter(|i| self.candidate_pairs.get(*i).is_some_and(is_match));
+
+        let idx = match cached {
+            Some(i) => i,
+            None => {
+                let i = self.candidate_pairs.iter().position(is_match)?;
+                self.counted_pair = Some(i);
+                i
+            }
+        };
+
+        Some(&mut self.candidate_pairs[idx])
+    }
+
+    /// Add stats for every candidate pair to the snapshot.
+    pub(crate) fn visit_stats(&self, now: Instant, snapshot: &mut StatsSnapshot) {
+        for p in &self.candidate_pairs {
+            let state = match p.state() {
+                CheckState::Waiting => CandidatePairState::Waiting,
+                CheckState::InProgress => CandidatePairState::InProgress,
+                CheckState::Succeeded => CandidatePairState::Succeeded,
+            };
+
+            snapshot.candidate_pairs.push(CandidatePairStats {
+                local: p.local_candidate(&self.local_candidates).clone(),
+                remote: p.remote_candidate(&self.remote_candidates).clone(),
+                state,
+                nominated: p.is_nominated(),
+                selected: self.nominated_send == Some(p.id()),
+                requests_sent: p.requests_sent(),
+                responses_received: p.responses_received(),
+                requests_received: p.remote_binding_requests,
+                // Every binding request that reaches a pair is answered.
+                responses_sent: p.remote_binding_requests,
+                bytes_sent: p.bytes_sent(),
+                bytes_received: p.bytes_received(),
+                rtt: p.rtt(),
+                min_rtt: p.min_rtt(),
+                smoothed_rtt: p.smoothed_rtt(),
+                total_rtt: p.total_rtt(),
+                timestamp: now,
+            });
         }
     }
 
@@ -1600,7 +2369,7 @@ impl IceAgent {
         for p in &self.candidate_pairs {
             if p.is_nominated() {
                 any_nomination = true;
-            } else if p.is_still_possible(now) {
+            } else if p.is_still_possible(now, self.consent.timeout) {
                 any_still_possible = true;
             }
         }
@@ -1608,15 +2377,25 @@ impl IceAgent {
         // As a special case, before the ice agent has received any add_remote_candidate() or
         // discovered a peer reflexive via a STUN message, the agent is still viable. This is
         // also the case for ice_restart.
-        if self.remote_candidates.is_empty() {
+        if self.remote_candidates.is_empty() && !self.remote_end_of_candidates {
+            any_still_possible = true;
+        }
+
+        // mDNS candidates waiting for resolution might still work out.
+        if !self.pending_mdns.is_empty() {
             any_still_possible = true;
         }
 
+        // With end-of-candidates on both sides, no new pairs can be formed. When none of
+        // the current pairs are possible, the failure is definitive.
+        let all_candidates = self.local_end_of_candidates && self.remote_end_of_candidates;
+        let no_pairs_state = if all_candidates { Failed } else { Disconnected };
+
         match self.state {
             New => {
                 self.set_connection_state(Checking, "new connection");
             }
-            Checking | Disconnected => {
+            Checking | Disconnected | Failed => {
                 if any_nomination {
                     if self.ice_lite {
                         self.set_connection_state(Completed, "got nomination in ice lite");
@@ -1628,7 +2407,7 @@ impl IceAgent {
                         self.set_connection_state(Completed, "got nomination, no others to try");
                     }
                 } else if !any_still_possible {
-                    self.set_connection_state(Disconnected, "no possible pairs");
+                    self.set_connection_state(no_pairs_state, "no possible pairs");
                 }
             }
             Connected => {
@@ -1636,6 +2415,8 @@ impl IceAgent {
                     if !any_still_possible {
                         self.set_connection_state(Completed, "no more possible to try");
                     }
+                } else if !any_still_possible {
+                    self.set_connection_state(no_pairs_state, "none nominated");
                 } else {
                     self.set_connection_state(Disconnected, "none nominated");
                 }
@@ -1645,6 +2426,8 @@ impl IceAgent {
                     if any_still_possible && !self.ice_lite {
                         self.set_connection_state(Connected, "got new possible");
                     }
+                } else if !any_still_possible {
+                    self.set_connection_state(no_pairs_state, "none nominated");
                 } else {
                     self.set_connection_state(Disconnected, "none nominated");
                 }
@@ -1660,6 +2443,7 @@ impl IceAgent {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ice_::{NetworkType, TcpType};
     use std::net::SocketAddr;
 
     impl IceAgent {
@@ -1708,6 +2492,100 @@ mod test {
         assert_eq!(v, vec![65534, 65535, 65533, 65532]);
     }
 
+    #[test]
+    fn local_preference_network_cost() {
+        let mut agent = IceAgent::new();
+
+        let mut cellular = Candidate::host(ipv4_1(), "udp").unwrap();
+        cellular.set_network_type(NetworkType::Cellular);
+        let mut wifi = Candidate::host(ipv4_2(), "udp").unwrap();
+        wifi.set_network_type(NetworkType::Wifi);
+
+        agent.add_local_candidate(cellular);
+        agent.add_local_candidate(wifi);
+
+        let v: Vec<_> = agent
+            .local_candidates
+            .iter()
+            .map(|c| c.local_preference())
+            .collect();
+
+        // The cheaper wifi goes above cellular, even though it was added later.
+        assert_eq!(v, vec![65534 - 900 * 8, 65534 - 10 * 8 - 2]);
+
+        // Pairs with the wifi candidate are preferred.
+        agent.add_remote_candidate(Candidate::host(ipv4_3(), "udp").unwrap());
+        let best = agent
+            .candidate_pairs
+            .iter()
+            .max_by_key(|p| p.prio())
+            .unwrap();
+        assert_eq!(
+            best.local_candidate(&agent.local_candidates).addr(),
+            ipv4_2()
+        );
+    }
+
+    #[test]
+    fn mdns_candidate_pairs_after_resolve() {
+        let mut agent = IceAgent::new();
+
+        agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+
+        let mdns = |port: u16| {
+            Candidate::from_sdp_string(&format!(
+                "candidate:1 1 udp 2113937151 abc.local {port} typ host"
+            ))
+            .unwrap()
+        };
+
+        agent.add_remote_candidate(mdns(5000));
+        agent.add_remote_candidate(mdns(5001));
+        assert!(agent.remote_candidates.is_empty());
+        assert!(agent.candidate_pairs.is_empty());
+
+        // Only one request per hostname.
+        let requests: Vec<_> = std::iter::from_fn(|| agent.poll_event())
+            .filter_map(|e| match e {
+                IceAgentEvent::MdnsResolveRequest(h) => Some(h),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(requests, vec!["abc.local".to_string()]);
+
+        agent.resolve_mdns("other.local", ipv4_2().ip());
+        assert!(agent.remote_candidates.is_empty());
+
+        agent.resolve_mdns("abc.local", ipv4_2().ip());
+        assert_eq!(agent.remote_candidates.len(), 2);
+        assert_eq!(agent.remote_candidates[0].addr(), ipv4_2());
+        assert_eq!(agent.pair_indexes(), [(0, 0), (0, 1)]);
+        assert!(agent.pending_mdns.is_empty());
+    }
+
+    #[test]
+    fn mdns_unresolved_candidate_expires() {
+        let mut agent = IceAgent::new();
+        let now = Instant::now();
+
+        agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+        agent.set_local_end_of_candidates();
+
+        let mdns =
+            Candidate::from_sdp_string("candidate:1 1 udp 2113937151 abc.local 5000 typ host")
+                .unwrap();
+        agent.add_remote_candidate(mdns);
+        agent.set_remote_end_of_candidates();
+
+        agent.handle_timeout(now);
+        assert_eq!(agent.pending_mdns.len(), 1);
+        assert_ne!(agent.state(), IceConnectionState::Failed);
+
+        agent.handle_timeout(now + MDNS_RESOLVE_TIMEOUT);
+        assert!(agent.pending_mdns.is_empty());
+        assert_eq!(agent.state(), IceConnectionState::Failed);
+    }
+
     #[test]
     fn discard_adding_redundant() {
         let mut agent = IceAgent::new();
@@ -1793,6 +2671,58 @@ mod test {
         assert_eq!(v, vec![true, false]);
     }
 
+    #[test]
+    fn local_preference_prefer_ipv4() {
+        let mut agent = IceAgent::new();
+        agent.set_family_policy(IceFamilyPolicy {
+            prefer: IpFamily::Ipv4,
+            interleave: false,
+        });
+
+        agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+        agent.add_local_candidate(Candidate::host(ipv6_1(), "udp").unwrap());
+        agent.add_local_candidate(Candidate::host(ipv6_2(), "udp").unwrap());
+        agent.add_local_candidate(Candidate::host(ipv4_2(), "udp").unwrap());
+
+        let v: Vec<_> = agent
+            .local_candidates
+            .iter()
+            .map(|c| c.local_preference())
+            .collect();
+
+        assert_eq!(v, vec![65535, 57343, 57341, 65533]);
+    }
+
+    #[test]
+    fn form_pairs_interleave_families() {
+        fn agent_with(policy: IceFamilyPolicy) -> IceAgent {
+            let mut agent = IceAgent::new();
+            agent.set_family_policy(policy);
+
+            // local 0, 1
+            agent.add_local_candidate(Candidate::host(ipv6_1(), "udp").unwrap());
+            agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+
+            // remote 0, 1 ipv6 host, remote 2 ipv4 relay
+            agent.add_remote_candidate(Candidate::host(ipv6_1(), "udp").unwrap());
+            agent.add_remote_candidate(Candidate::host(ipv6_2(), "udp").unwrap());
+            agent.add_remote_candidate(Candidate::relayed(ipv4_3(), "udp").unwrap());
+
+            agent
+        }
+
+        // Priority alone checks both ipv6 pairs before the ipv4 pair.
+        let agent = agent_with(IceFamilyPolicy {
+            interleave: false,
+            ..Default::default()
+        });
+        assert_eq!(agent.pair_indexes(), [(0, 0), (0, 1), (1, 2)]);
+
+        // Interleaving alternates the families, preferred first.
+        let agent = agent_with(IceFamilyPolicy::default());
+        assert_eq!(agent.pair_indexes(), [(0, 0), (1, 2), (0, 1)]);
+    }
+
     #[test]
     fn form_pairs() {
         let mut agent = IceAgent::new();
@@ -1898,6 +2828,56 @@ mod test {
         assert_eq!(pair.remote_binding_request_time, Some(now));
     }
 
+    #[test]
+    fn limits_candidates() {
+        let mut agent = IceAgent::new();
+        agent.set_limits(Limits {
+            max_local_candidates: Some(2),
+            max_remote_candidates: Some(1),
+            ..Default::default()
+        });
+
+        assert!(agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap()));
+        assert!(agent.add_local_candidate(Candidate::host(ipv4_2(), "udp").unwrap()));
+        assert!(!agent.add_local_candidate(Candidate::host(ipv4_3(), "udp").unwrap()));
+
+        agent.add_remote_candidate(Candidate::host(ipv4_4(), "udp").unwrap());
+        agent
+            .add_remote_candidate(Candidate::host("5.6.7.8:5000".parse().unwrap(), "udp").unwrap());
+
+        assert_eq!(agent.local_candidates.len(), 2);
+        assert_eq!(agent.remote_candidates.len(), 1);
+        assert_eq!(agent.candidate_pairs.len(), 2);
+    }
+
+    #[test]
+    fn limits_pairs_per_remote_candidate() {
+        let mut agent = IceAgent::new();
+        agent.set_limits(Limits {
+            max_pairs_per_remote_candidate: Some(2),
+            ..Default::default()
+        });
+
+        agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+        agent.add_local_candidate(Candidate::host(ipv4_2(), "udp").unwrap());
+        agent.add_local_candidate(Candidate::host("5.6.7.8:5000".parse().unwrap(), "udp").unwrap());
+
+        agent.add_remote_candidate(Candidate::host(ipv4_3(), "udp").unwrap());
+        agent.add_remote_candidate(Candidate::host(ipv4_4(), "udp").unwrap());
+
+        let pairs = agent.pair_indexes();
+        assert_eq!(pairs.len(), 4);
+        for remote in 0..2 {
+            assert_eq!(pairs.iter().filter(|(_, r)| *r == remote).count(), 2);
+        }
+
+        // The lowest prio local candidate is the one pruned.
+        let worst = (0..3)
+            .min_by_key(|i| agent.local_candidates[*i].prio())
+            .unwrap();
+        assert!(pairs.iter().all(|(l, _)| *l != worst));
+    }
+
     #[test]
     fn form_pairs_skip_invalidated_local() {
         let mut agent = IceAgent::new();
@@ -1928,6 +2908,40 @@ mod test {
         assert_eq!(agent.pair_indexes(), []);
     }
 
+    #[test]
+    fn ignore_already_known_remote() {
+        let mut agent = IceAgent::new();
+
+        agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+        agent.add_remote_candidate(Candidate::host(ipv4_3(), "udp").unwrap());
+
+        assert_eq!(agent.pair_indexes(), [(0, 0)]);
+
+        // A renegotiation with the same candidate.
+        agent.add_remote_candidate(Candidate::host(ipv4_3(), "udp").unwrap());
+
+        assert_eq!(agent.remote_candidates.len(), 1);
+        assert_eq!(agent.pair_indexes(), [(0, 0)]);
+    }
+
+    #[test]
+    fn form_pairs_tcp_types() {
+        let mut agent = IceAgent::new();
+
+        let mut active = Candidate::host(ipv4_1(), "tcp").unwrap();
+        active.set_tcp_type(TcpType::Active);
+        agent.add_local_candidate(active);
+        agent.add_local_candidate(Candidate::host(ipv4_2(), "tcp").unwrap());
+
+        let mut remote_active = Candidate::host(ipv4_3(), "tcp").unwrap();
+        remote_active.set_tcp_type(TcpType::Active);
+        agent.add_remote_candidate(remote_active);
+        agent.add_remote_candidate(Candidate::host(ipv4_4(), "tcp").unwrap());
+
+        // Only local active to remote passive.
+        assert_eq!(agent.pair_indexes(), [(0, 1)]);
+    }
+
     #[test]
     fn poll_time_must_timing_advance() {
         let mut agent = IceAgent::new();
@@ -1941,6 +2955,55 @@ mod test {
         assert!(now2 - now1 == Duration::from_millis(50));
     }
 
+    #[test]
+    fn count_bytes_per_pair() {
+        let mut agent = IceAgent::new();
+        agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+        agent.add_remote_candidate(Candidate::host(ipv4_3(), "udp").unwrap());
+        agent.add_remote_candidate(Candidate::host(ipv4_4(), "udp").unwrap());
+        assert_eq!(agent.candidate_pairs.len(), 2);
+
+        let bytes = |agent: &IceAgent, remote: SocketAddr| {
+            let p = agent
+                .candidate_pairs
+                .iter()
+                .find(|p| p.remote_candidate(&agent.remote_candidates).addr() == remote)
+                .unwrap();
+            (p.bytes_sent(), p.bytes_received())
+        };
+
+        agent.count_sent(ipv4_1(), ipv4_3(), 100);
+        agent.count_sent(ipv4_1(), ipv4_3(), 100);
+        agent.count_received(ipv4_3(), ipv4_1(), 50);
+        agent.count_sent(ipv4_1(), ipv4_4(), 10);
+
+        assert_eq!(bytes(&agent, ipv4_3()), (200, 50));
+        assert_eq!(bytes(&agent, ipv4_4()), (10, 0));
+
+        // The cached pair is not trusted after the pairs move around.
+        agent.candidate_pairs.reverse();
+        agent.count_sent(ipv4_1(), ipv4_3(), 100);
+        assert_eq!(bytes(&agent, ipv4_3()), (300, 50));
+
+        // Unknown addresses are not counted.
+        agent.count_sent(ipv4_2(), ipv4_3(), 100);
+        assert_eq!(bytes(&agent, ipv4_3()), (300, 50));
+    }
+
+    #[test]
+    fn timing_advance_at_least_5ms() {
+        let mut agent = IceAgent::new();
+        agent.set_timing_advance(Duration::ZERO);
+        agent.add_local_candidate(Candidate::host(ipv4_1(), "udp").unwrap());
+        agent.add_remote_candidate(Candidate::host(ipv4_3(), "udp").unwrap());
+
+        let now1 = Instant::now();
+        agent.handle_timeout(now1);
+        let now2 = agent.poll_timeout().unwrap();
+
+        assert_eq!(now2 - now1, Duration::from_millis(5));
+    }
+
     #[test]
     fn no_disconnect_before_remote_candidates() {
         let mut agent = IceAgent::new();
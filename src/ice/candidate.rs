@@ -79,6 +79,20 @@ pub struct Candidate {
     /// If we discarded this candidate (for example due to being redundant
     /// against another candidate).
     discarded: bool,
+
+    /// For TCP candidates, how the connection is established (RFC 6544).
+    tcp_type: Option<TcpType>,
+
+    /// Obfuscated `.local` hostname for remote mDNS candidates.
+    ///
+    /// Until resolved, the ip of `addr` is unspecified.
+    mdns_hostname: Option<String>,
+
+    /// Type of network interface, for local candidates.
+    network_type: NetworkType,
+
+    /// Cost of using the network interface, overriding the one from `network_type`.
+    network_cost: Option<u16>,
 }
 
 impl fmt::Debug for Candidate {
@@ -92,6 +106,15 @@ impl fmt::Debug for Candidate {
         if let Some(raddr) = self.raddr {
             write!(f, " raddr={raddr}")?;
         }
+        if let Some(tcp_type) = self.tcp_type {
+            write!(f, " tcptype={tcp_type}")?;
+        }
+        if let Some(hostname) = &self.mdns_hostname {
+            write!(f, " mdns={hostname}")?;
+        }
+        if self.network_type != NetworkType::Unknown {
+            write!(f, " network={}", self.network_type)?;
+        }
         write!(f, " prio={}", self.prio())?;
         if self.discarded {
             write!(f, " discarded")?;
@@ -125,6 +148,12 @@ impl Candidate {
             ufrag,
             local_preference: None,
             discarded: false,
+            // Peer reflexive candidates are discovered on an already open connection.
+            tcp_type: (proto == Protocol::Tcp && kind != CandidateKind::PeerReflexive)
+                .then_some(TcpType::Passive),
+            mdns_hostname: None,
+            network_type: NetworkType::Unknown,
+            network_cost: None,
         }
     }
 
@@ -138,8 +167,9 @@ impl Candidate {
         kind: CandidateKind,
         raddr: Option<SocketAddr>,
         ufrag: Option<String>,
+        tcp_type: Option<TcpType>,
     ) -> Self {
-        Candidate::new(
+        let mut c = Candidate::new(
             Some(foundation),
             component_id,
             proto,
@@ -149,12 +179,19 @@ impl Candidate {
             kind,
             raddr,
             ufrag,
-        )
+        );
+        if let Some(tcp_type) = tcp_type {
+            c.set_tcp_type(tcp_type);
+        }
+        c
     }
 
     /// Creates a host ICE candidate.
     ///
     /// Host candidates are local sockets directly on the host.
+    ///
+    /// TCP candidates default to [`TcpType::Passive`], i.e. a listening socket. Use
+    /// [`Candidate::set_tcp_type()`] for the other kinds.
     pub fn host(addr: SocketAddr, proto: impl TryInto<Protocol>) -> Result<Self, IceError> {
         if !is_valid_ip(addr.ip()) {
             return Err(IceError::BadCandidate(format!("invalid ip {}", addr.ip())));
@@ -397,6 +434,112 @@ impl Candidate {
         self.kind
     }
 
+    /// For TCP candidates, how the connection is established.
+    ///
+    /// `None` for other protocols.
+    pub fn tcp_type(&self) -> Option<TcpType> {
+        self.tcp_type
+    }
+
+    /// Set how the connection is established for a TCP candidate.
+    ///
+    /// Ignored for other protocols than [`Protocol::Tcp`].
+    ///
+    /// ```
+    /// # use str0m::{Candidate, TcpType};
+    /// let mut c = Candidate::host("1.2.3.4:9".parse().unwrap(), "tcp").unwrap();
+    /// assert_eq!(c.tcp_type(), Some(TcpType::Passive));
+    ///
+    /// c.set_tcp_type(TcpType::Active);
+    /// assert!(c.to_sdp_string().ends_with("typ host tcptype active"));
+    /// ```
+    pub fn set_tcp_type(&mut self, tcp_type: TcpType) {
+        if self.proto == Protocol::Tcp {
+            self.tcp_type = Some(tcp_type);
+        }
+    }
+
+    /// The `.local` hostname of a remote mDNS candidate.
+    ///
+    /// Browsers hide the host ip behind an obfuscated hostname such as
+    /// `0f4cc0ff-5c2f-4e7a-8a3a-7d4f8b0e5b3a.local`. Such candidates don't form
+    /// pairs until resolved via [`Rtc::resolve_mdns()`][crate::Rtc::resolve_mdns].
+    ///
+    /// ```
+    /// # use str0m::Candidate;
+    /// let c = Candidate::from_sdp_string(
+    ///     "candidate:1 1 udp 2113937151 9b36eaac-bb2e-49bb-bb78-21c41c499900.local 61665 typ host",
+    /// )
+    /// .unwrap();
+    ///
+    /// assert_eq!(
+    ///     c.mdns_hostname(),
+    ///     Some("9b36eaac-bb2e-49bb-bb78-21c41c499900.local")
+    /// );
+    /// assert!(c.addr().ip().is_unspecified());
+    /// ```
+    pub fn mdns_hostname(&self) -> Option<&str> {
+        self.mdns_hostname.as_deref()
+    }
+
+    pub(crate) fn set_mdns_hostname(&mut self, hostname: String) {
+        self.mdns_hostname = Some(hostname);
+    }
+
+    /// Whether this is an mDNS candidate that still lacks an ip.
+    pub(crate) fn is_unresolved(&self) -> bool {
+        self.mdns_hostname.is_some() && self.addr.ip().is_unspecified()
+    }
+
+    /// Fill in the ip for the mDNS hostname.
+    pub(crate) fn set_resolved_ip(&mut self, ip: IpAddr) {
+        self.addr = SocketAddr::new(ip, self.addr.port());
+    }
+
+    /// The type of network interface of this (local) candidate.
+    pub fn network_type(&self) -> NetworkType {
+        self.network_type
+    }
+
+    /// Set the type of network interface of a local candidate.
+    ///
+    /// The ICE agent prefers candidates on cheap interfaces, such as ethernet over
+    /// cellular. This must be set before adding the candidate to the agent.
+    ///
+    /// ```
+    /// # use str0m::{Candidate, NetworkType};
+    /// let mut c = Candidate::host("1.2.3.4:9".parse().unwrap(), "udp").unwrap();
+    /// c.set_network_type(NetworkType::Cellular);
+    ///
+    /// assert_eq!(c.network_cost(), 900);
+    /// ```
+    pub fn set_network_type(&mut self, network_type: NetworkType) {
+        self.network_type = network_type;
+    }
+
+    /// The cost of using the network interface of this (local) candidate.
+    ///
+    /// This is the cost set by [`Candidate::set_network_cost()`], or the default
+    /// cost of the [`NetworkType`].
+    pub fn network_cost(&self) -> u16 {
+        self.network_cost
+            .unwrap_or_else(|| self.network_type.default_cost())
+    }
+
+    /// Set the cost of using the network interface of a local candidate.
+    ///
+    /// Lower is cheaper. The default costs of [`NetworkType`] range from 0 to 999,
+    /// and higher values are treated as 999.
+    pub fn set_network_cost(&mut self, cost: u16) {
+        self.network_cost = Some(cost);
+    }
+
+    /// Candidates derived from another, like reflexive ones, are on the same network.
+    pub(crate) fn set_network_from(&mut self, other: &Candidate) {
+        self.network_type = other.network_type;
+        self.network_cost = other.network_cost;
+    }
+
     pub(crate) fn set_local_preference(&mut self, v: u32) {
         self.local_preference = Some(v);
     }
@@ -423,19 +566,32 @@ impl Candidate {
 
     /// Generates a candidate attribute string.
     pub fn to_sdp_string(&self) -> String {
+        // Active TCP candidates don't listen, they use the discard port (RFC 6544).
+        let port = if self.tcp_type == Some(TcpType::Active) {
+            9
+        } else {
+            self.addr.port()
+        };
+        let host = match &self.mdns_hostname {
+            Some(hostname) => hostname.clone(),
+            None => self.addr.ip().to_string(),
+        };
         let mut s = format!(
             "candidate:{} {} {} {} {} {} typ {}",
             self.foundation(),
             self.component_id,
             self.proto,
             self.prio(),
-            self.addr.ip(),
-            self.addr.port(),
+            host,
+            port,
             self.kind
         );
         if let Some(raddr) = &self.raddr {
             s.push_str(&format!(" raddr {} rport {}", raddr.ip(), raddr.port()))
         }
+        if let Some(tcp_type) = self.tcp_type {
+            s.push_str(&format!(" tcptype {}", tcp_type));
+        }
         if let Some(ufrag) = &self.ufrag {
             s.push_str(&format!(" ufrag {}", ufrag));
         }
@@ -480,6 +636,104 @@ impl fmt::Display for CandidateKind {
     }
 }
 
+/// Type of network interface a local candidate is on.
+///
+/// The costs follow libwebrtc, and are used to prefer cheap interfaces. Unlike libwebrtc,
+/// [`NetworkType::Unknown`] costs nothing, to not change the priority of candidates
+/// that are not tagged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[non_exhaustive]
+pub enum NetworkType {
+    /// Not known. This is the default.
+    #[default]
+    Unknown,
+    /// Wired ethernet.
+    Ethernet,
+    /// Wifi.
+    Wifi,
+    /// Mobile data.
+    Cellular,
+    /// A VPN tunnel, on top of some other interface.
+    Vpn,
+    /// The loopback interface.
+    Loopback,
+}
+
+/// Highest network cost that affects candidate priority.
+pub(crate) const MAX_NETWORK_COST: u16 = 999;
+
+impl NetworkType {
+    /// Cost of the network type, unless set using [`Candidate::set_network_cost()`].
+    pub fn default_cost(&self) -> u16 {
+        match self {
+            NetworkType::Unknown | NetworkType::Ethernet | NetworkType::Loopback => 0,
+            NetworkType::Wifi => 10,
+            NetworkType::Vpn => 50,
+            NetworkType::Cellular => 900,
+        }
+    }
+}
+
+impl fmt::Display for NetworkType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let x = match self {
+            NetworkType::Unknown => "unknown",
+            NetworkType::Ethernet => "ethernet",
+            NetworkType::Wifi => "wifi",
+            NetworkType::Cellular => "cellular",
+            NetworkType::Vpn => "vpn",
+            NetworkType::Loopback => "loopback",
+        };
+        write!(f, "{x}")
+    }
+}
+
+/// How a TCP candidate establishes its connection, see RFC 6544.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TcpType {
+    /// Opens outgoing connections, but doesn't accept incoming.
+    Active,
+    /// Accepts incoming connections, but doesn't open outgoing.
+    Passive,
+    /// Simultaneous-open, both ends attempt to open connections at the same time.
+    So,
+}
+
+impl TcpType {
+    /// Whether a connection can be opened from a local candidate of this type to
+    /// a remote of the other type.
+    pub(crate) fn can_connect_to(&self, remote: TcpType) -> bool {
+        matches!(
+            (self, remote),
+            (TcpType::Active, TcpType::Passive) | (TcpType::So, TcpType::So)
+        )
+    }
+}
+
+impl fmt::Display for TcpType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let x = match self {
+            TcpType::Active => "active",
+            TcpType::Passive => "passive",
+            TcpType::So => "so",
+        };
+        write!(f, "{x}")
+    }
+}
+
+impl TryFrom<&str> for TcpType {
+    type Error = ();
+
+    fn try_from(v: &str) -> Result<Self, Self::Error> {
+        match v {
+            "active" => Ok(TcpType::Active),
+            "passive" => Ok(TcpType::Passive),
+            "so" => Ok(TcpType::So),
+            _ => Err(()),
+        }
+    }
+}
+
 fn is_valid_ip(ip: IpAddr) -> bool {
     match ip {
         IpAddr::V4(v) => {
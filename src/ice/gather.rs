@@ -0,0 +1,370 @@
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::time::Instant;
+
+use crate::io::{stun_resend_delay, Protocol, StunMessage, StunPacket};
+use crate::io::{TransId, Transmit, DATAGRAM_MTU};
+use crate::util::already_happened;
+
+use super::candidate::{Candidate, CandidateKind};
+
+/// How many times we send a BINDING request to a STUN server before giving up.
+///
+/// This is the `Rc` value from https://www.rfc-editor.org/rfc/rfc5389#section-7.2.1
+const MAX_REQUESTS: usize = 7;
+
+/// Gathers server reflexive candidates by sending BINDING requests to STUN servers.
+///
+/// For every local UDP host candidate, one request is sent to each configured STUN server
+/// of the same IP family. The request is sent from the host candidate socket, and the
+/// XOR-MAPPED-ADDRESS in the response becomes a server reflexive candidate with the host
+/// candidate as base.
+#[derive(Debug)]
+pub(crate) struct StunGatherer {
+    /// Addresses of the STUN servers to query.
+    servers: Vec<SocketAddr>,
+
+    /// One request per (base, server) combination.
+    requests: Vec<ServerRequest>,
+
+    /// Requests waiting to be sent.
+    transmit: VecDeque<Transmit>,
+
+    /// Candidates discovered, but not yet polled.
+    candidates: VecDeque<Candidate>,
+
+    /// (addr, base) of every candidate discovered so far.
+    gathered: Vec<(SocketAddr, SocketAddr)>,
+}
+
+#[derive(Debug)]
+struct ServerRequest {
+    /// The local host candidate the request is sent from.
+    host: Candidate,
+    /// The STUN server the request is sent to.
+    server: SocketAddr,
+    /// Retransmits reuse the same transaction id.
+    trans_id: TransId,
+    /// Number of times the request has been sent.
+    send_count: usize,
+    /// When the request was last sent.
+    last_sent: Option<Instant>,
+    /// Set when we got a response, or gave up.
+    done: bool,
+}
+
+impl StunGatherer {
+    pub fn new(servers: Vec<SocketAddr>) -> Self {
+        StunGatherer {
+            servers,
+            requests: vec![],
+            transmit: VecDeque::new(),
+            candidates: VecDeque::new(),
+            gathered: vec![],
+        }
+    }
+
+    /// Forget all requests and gathered candidates.
+    ///
+    /// Used when an ICE restart drops the local candidates. Host candidates added
+    /// after that are gathered for again.
+    pub fn reset(&mut self) {
+        self.requests.clear();
+        self.transmit.clear();
+        self.candidates.clear();
+        self.gathered.clear();
+    }
+
+    /// Start gathering for a local candidate.
+    ///
+    /// Only UDP host candidates are used as base.
+    pub fn add_local_candidate(&mut self, c: &Candidate) {
+        if c.kind() != CandidateKind::Host || c.proto() != Protocol::Udp {
+            return;
+        }
+
+        let base = c.addr();
+
+        for server in &self.servers {
+            if server.is_ipv4() != base.is_ipv4() {
+                continue;
+            }
+
+            let exists = self
+                .requests
+                .iter()
+                .any(|r| r.host.addr() == base && r.server == *server);
+
+            if exists {
+                continue;
+            }
+
+            debug!("Gather server reflexive from {} via {}", base, server);
+
+            self.requests.push(ServerRequest {
+                host: c.clone(),
+                server: *server,
+                trans_id: TransId::new(),
+                send_count: 0,
+                last_sent: None,
+                done: false,
+            });
+        }
+    }
+
+    pub fn handle_timeout(&mut self, now: Instant) {
+        for r in self.requests.iter_mut().filter(|r| !r.done) {
+            if let Some(last) = r.last_sent {
+                if now < last + stun_resend_delay(r.send_count) {
+                    continue;
+                }
+            }
+
+            if r.send_count >= MAX_REQUESTS {
+                debug!(
+                    "No STUN response from {} for {}, giving up",
+                    r.server,
+                    r.host.addr()
+                );
+                r.done = true;
+                continue;
+            }
+
+            let mut buf = vec![0_u8; DATAGRAM_MTU];
+
+            let n = StunMessage::server_binding_request(r.trans_id)
+                .to_bytes_unauthenticated(&mut buf)
+                .expect("IO error writing STUN message");
+            buf.truncate(n);
+
+            trace!(
+                "Send STUN binding request to {} from {}",
+                r.server,
+                r.host.addr()
+            );
+
+            self.transmit.push_back(Transmit {
+                proto: Protocol::Udp,
+                source: r.host.addr(),
+                destination: r.server,
+                contents: buf.into(),
+            });
+
+            r.send_count += 1;
+            r.last_sent = Some(now);
+        }
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<Transmit> {
+        self.transmit.pop_front()
+    }
+
+    pub fn poll_candidate(&mut self) -> Option<Candidate> {
+        self.candidates.pop_front()
+    }
+
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        self.requests
+            .iter()
+            .filter(|r| !r.done)
+            .map(|r| match r.last_sent {
+                Some(last) => last + stun_resend_delay(r.send_count),
+                None => already_happened(),
+            })
+            .min()
+    }
+
+    /// Whether the message is a response to one of our requests.
+    pub fn accepts_message(&self, source: SocketAddr, message: &StunMessage<'_>) -> bool {
+        message.is_successful_binding_response()
+            && self
+                .requests
+                .iter()
+                .any(|r| r.trans_id == message.trans_id() && r.server == source)
+    }
+
+    /// Handle a response from a STUN server.
+    ///
+    /// Returns `false` if the packet was not for the gatherer.
+    pub fn handle_packet(&mut self, packet: &StunPacket) -> bool {
+        if !self.accepts_message(packet.source, &packet.message) {
+            return false;
+        }
+
+        let trans_id = packet.message.trans_id();
+
+        let Some(r) = self.requests.iter_mut().find(|r| r.trans_id == trans_id) else {
+            return false;
+        };
+
+        if r.done {
+            // A late response to a retransmit.
+            return true;
+        }
+
+        r.done = true;
+        let host = r.host.clone();
+        let base = host.addr();
+
+        let Some(mapped) = packet.message.mapped_address() else {
+            debug!(
+                "STUN response from {} without mapped address",
+                packet.source
+            );
+            return true;
+        };
+
+        if mapped == base {
+            // Not behind a NAT, the host candidate covers this.
+            trace!("STUN mapped address equals base: {}", base);
+            return true;
+        }
+
+        if self.gathered.contains(&(mapped, base)) {
+            return true;
+        }
+
+        match Candidate::server_reflexive(mapped, base, Protocol::Udp) {
+            Ok(mut c) => {
+                c.set_network_from(&host);
+                debug!("Gathered server reflexive candidate: {:?}", c);
+                self.gathered.push((mapped, base));
+                self.candidates.push_back(c);
+            }
+            Err(e) => {
+                debug!("Bad server reflexive address {}: {:?}", mapped, e);
+            }
+        }
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn udp_host(s: &str) -> Candidate {
+        Candidate::host(s.parse().unwrap(), "udp").unwrap()
+    }
+
+    fn respond(t: &Transmit, mapped: SocketAddr) -> Vec<u8> {
+        // The request has no MESSAGE-INTEGRITY, so we don't parse it.
+        let trans_id = TransId::from_slice(&t.contents[8..20]);
+        let mut buf = vec![0_u8; DATAGRAM_MTU];
+        let n = StunMessage::reply(trans_id, mapped)
+            .to_bytes_unauthenticated(&mut buf)
+            .unwrap();
+        buf.truncate(n);
+        buf
+    }
+
+    #[test]
+    fn gathers_server_reflexive() {
+        let server: SocketAddr = "5.5.5.5:3478".parse().unwrap();
+        let base: SocketAddr = "10.0.0.1:1000".parse().unwrap();
+        let mapped: SocketAddr = "1.2.3.4:4000".parse().unwrap();
+
+        let mut g = StunGatherer::new(vec![server, "[::1]:3478".parse().unwrap()]);
+        g.add_local_candidate(&udp_host("10.0.0.1:1000"));
+        g.add_local_candidate(&udp_host("10.0.0.1:1000"));
+
+        let now = Instant::now();
+        g.handle_timeout(now);
+
+        // Only one request, the IPv6 server is not used for the IPv4 base.
+        let t = g.poll_transmit().unwrap();
+        assert!(g.poll_transmit().is_none());
+        assert_eq!(t.source, base);
+        assert_eq!(t.destination, server);
+
+        let buf = respond(&t, mapped);
+        let packet = StunPacket {
+            proto: Protocol::Udp,
+            source: server,
+            destination: base,
+            message: StunMessage::parse(&buf).unwrap(),
+        };
+
+        assert!(g.handle_packet(&packet));
+
+        let c = g.poll_candidate().unwrap();
+        assert_eq!(c.kind(), CandidateKind::ServerReflexive);
+        assert_eq!(c.addr(), mapped);
+        assert_eq!(c.base(), base);
+        assert!(g.poll_candidate().is_none());
+        assert!(g.poll_timeout().is_none());
+    }
+
+    #[test]
+    fn ignores_unknown_response() {
+        let server: SocketAddr = "5.5.5.5:3478".parse().unwrap();
+
+        let mut g = StunGatherer::new(vec![server]);
+        g.add_local_candidate(&udp_host("10.0.0.1:1000"));
+        g.handle_timeout(Instant::now());
+
+        let t = g.poll_transmit().unwrap();
+        let buf = respond(&t, "1.2.3.4:4000".parse().unwrap());
+
+        // Right transaction, wrong server.
+        let packet = StunPacket {
+            proto: Protocol::Udp,
+            source: "6.6.6.6:3478".parse().unwrap(),
+            destination: t.source,
+            message: StunMessage::parse(&buf).unwrap(),
+        };
+
+        assert!(!g.handle_packet(&packet));
+        assert!(g.poll_candidate().is_none());
+    }
+
+    #[test]
+    fn gives_up_after_max_requests() {
+        let mut g = StunGatherer::new(vec!["5.5.5.5:3478".parse().unwrap()]);
+        g.add_local_candidate(&udp_host("10.0.0.1:1000"));
+
+        let mut now = Instant::now();
+        let mut sent = 0;
+
+        while let Some(next) = g.poll_timeout() {
+            now = now.max(next);
+            g.handle_timeout(now);
+            while g.poll_transmit().is_some() {
+                sent += 1;
+            }
+            now += Duration::from_millis(1);
+        }
+
+        assert_eq!(sent, MAX_REQUESTS);
+    }
+
+    #[test]
+    fn gathers_again_after_reset() {
+        let server: SocketAddr = "5.5.5.5:3478".parse().unwrap();
+        let mapped: SocketAddr = "1.2.3.4:4000".parse().unwrap();
+
+        let mut g = StunGatherer::new(vec![server]);
+
+        for _ in 0..2 {
+            g.add_local_candidate(&udp_host("10.0.0.1:1000"));
+            g.handle_timeout(Instant::now());
+
+            let t = g.poll_transmit().unwrap();
+            let buf = respond(&t, mapped);
+            let packet = StunPacket {
+                proto: Protocol::Udp,
+                source: server,
+                destination: t.source,
+                message: StunMessage::parse(&buf).unwrap(),
+            };
+
+            assert!(g.handle_packet(&packet));
+            assert_eq!(g.poll_candidate().unwrap().addr(), mapped);
+
+            g.reset();
+        }
+    }
+}
@@ -5,9 +5,15 @@ use thiserror::Error;
 
 mod agent;
 pub use agent::{IceAgent, IceAgentEvent, IceConnectionState, IceCreds};
+pub use agent::{IceConsent, IceConsentConfig, IceFamilyPolicy, IceNomination, IpFamily};
+
+pub(crate) use agent::MIN_TIMING_ADVANCE;
 
 mod candidate;
-pub use candidate::{Candidate, CandidateKind};
+pub use candidate::{Candidate, CandidateKind, NetworkType, TcpType};
+
+mod gather;
+pub(crate) use gather::StunGatherer;
 
 mod pair;
 
@@ -68,6 +74,44 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn end_of_candidates_fails() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = host("1.1.1.1:9999", "udp"); // 9999 is just dropped by propagate
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000", "udp");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+
+        for a in [&mut a1, &mut a2] {
+            a.set_local_end_of_candidates();
+            a.set_remote_end_of_candidates();
+        }
+
+        // Ignored after end-of-candidates.
+        a1.add_remote_candidate(host("3.3.3.3:1000", "udp"));
+        assert_eq!(a1.remote_candidates().len(), 1);
+
+        loop {
+            if a1.state().is_disconnected() && a2.state().is_disconnected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        assert_eq!(a1.state(), IceConnectionState::Failed);
+        assert_eq!(a2.state(), IceConnectionState::Failed);
+        assert!(!a1.has_event(|e| matches!(
+            e,
+            IceAgentEvent::IceConnectionStateChange(IceConnectionState::Disconnected)
+        )));
+    }
+
     use std::net::IpAddr;
     use std::net::{Ipv4Addr, SocketAddr};
     use std::ops::{Deref, DerefMut};
@@ -217,6 +261,107 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn consent_lost() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = host("1.1.1.1:1000", "udp");
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000", "udp");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+
+        a1.set_consent(IceConsentConfig {
+            interval: Some(Duration::from_secs(1)),
+            timeout: Some(Duration::from_secs(6)),
+        });
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        // The remote stops answering.
+        a2.drop_sent_packets = true;
+
+        loop {
+            if !a1.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        let consent: Vec<_> = a1
+            .events
+            .iter()
+            .filter_map(|(_, e)| match e {
+                IceAgentEvent::Consent(c) => Some(*c),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(consent, vec![IceConsent::Expiring, IceConsent::Lost]);
+    }
+
+    #[test]
+    pub fn regular_nomination_keeps_pair() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = relay("1.1.1.1:1000", "udp");
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000", "udp");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+        a1.set_nomination(IceNomination::Regular);
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        // A better pair after connecting.
+        a1.add_local_candidate(host("1.1.1.1:1001", "udp"));
+        a2.add_remote_candidate(host("1.1.1.1:1001", "udp"));
+
+        loop {
+            if a2.has_event(|e| {
+                matches!(e, IceAgentEvent::DiscoveredRecv { source, .. } if source == &sock("1.1.1.1:1001"))
+            }) {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        for _ in 0..10 {
+            progress(&mut a1, &mut a2);
+        }
+
+        let nominated: Vec<_> = a1
+            .events
+            .iter()
+            .filter_map(|(_, e)| match e {
+                IceAgentEvent::NominatedSend { source, .. } => Some(*source),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(nominated, vec![sock("1.1.1.1:1000")]);
+    }
+
     #[test]
     pub fn host_host() {
         let mut a1 = TestAgent::new(info_span!("L"));
@@ -261,6 +406,58 @@ mod test {
         );
     }
 
+    fn role_conflict(controlling: bool) {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = host("1.1.1.1:1000", "udp");
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000", "udp");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+        a1.set_controlling(controlling);
+        a2.set_controlling(controlling);
+        a1.set_tie_breaker(1);
+        a2.set_tie_breaker(2);
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        // The larger tie-breaker ends up controlling.
+        assert!(!a1.controlling());
+        assert!(a2.controlling());
+
+        let role_changes = |a: &TestAgent| {
+            a.events
+                .iter()
+                .filter(|(_, e)| matches!(e, IceAgentEvent::RoleChange { .. }))
+                .count()
+        };
+
+        if controlling {
+            assert_eq!(role_changes(&a1), 1);
+            assert_eq!(role_changes(&a2), 0);
+        } else {
+            assert_eq!(role_changes(&a1), 0);
+            assert_eq!(role_changes(&a2), 1);
+        }
+    }
+
+    #[test]
+    pub fn role_conflict_both_controlling() {
+        role_conflict(true);
+    }
+
+    #[test]
+    pub fn role_conflict_both_controlled() {
+        role_conflict(false);
+    }
+
     #[test]
     pub fn no_respond_to_stun_request_on_invalidated_candidate() {
         let mut a1 = TestAgent::new(info_span!("L"));
@@ -547,6 +744,57 @@ mod test {
         );
     }
 
+    #[test]
+    pub fn prflx_events() {
+        let mut a1 = TestAgent::new(info_span!("L"));
+        let mut a2 = TestAgent::new(info_span!("R"));
+
+        let c1 = host("3.3.3.3:1000", "udp"); // will be rewritten to 4.4.4.4
+        a1.add_local_candidate(c1.clone());
+        a2.add_remote_candidate(c1);
+        let c2 = host("2.2.2.2:1000", "udp");
+        a2.add_local_candidate(c2.clone());
+        a1.add_remote_candidate(c2);
+
+        a1.set_controlling(true);
+        a2.set_controlling(false);
+
+        loop {
+            if a1.state().is_connected() && a2.state().is_connected() {
+                break;
+            }
+            progress(&mut a1, &mut a2);
+        }
+
+        let prflx = sock("4.4.4.4:1000");
+
+        // L learns its address as seen by R from the binding response.
+        assert!(a1.has_event(|e| matches!(
+            e,
+            IceAgentEvent::PeerReflexive { candidate, local: true } if candidate.addr() == prflx
+        )));
+
+        // R sees a binding request from an address it doesn't know.
+        assert!(a2.has_event(|e| matches!(
+            e,
+            IceAgentEvent::PeerReflexive { candidate, local: false } if candidate.addr() == prflx
+        )));
+
+        // L signals it late, which promotes the peer reflexive candidate at R.
+        a2.add_remote_candidate(host("4.4.4.4:1000", "udp"));
+        for _ in 0..5 {
+            progress(&mut a1, &mut a2);
+        }
+
+        assert!(a2.has_event(|e| matches!(
+            e,
+            IceAgentEvent::PeerReflexivePromoted { previous, current }
+                if previous.kind() == CandidateKind::PeerReflexive
+                    && current.kind() == CandidateKind::Host
+                    && current.addr() == prflx
+        )));
+    }
+
     // pub fn init_log() {
     //     use std::env;
     //     use tracing_subscriber::{fmt, prelude::*, EnvFilter};
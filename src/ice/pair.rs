@@ -4,6 +4,7 @@ use std::time::{Duration, Instant};
 
 use crate::io::{stun_resend_delay, STUN_MAX_RETRANS};
 use crate::io::{Id, TransId, STUN_MAX_RTO_MILLIS};
+use crate::util::NonCryptographicRng;
 use crate::Candidate;
 
 const MIN_TIMEOUT: Duration = Duration::from_millis(STUN_MAX_RTO_MILLIS);
@@ -50,6 +51,33 @@ pub struct CandidatePair {
 
     /// State of nomination for this candidate pair.
     nomination_state: NominationState,
+
+    /// The last time we got a binding response, i.e. the peer consented.
+    last_response: Option<Instant>,
+
+    /// Number of binding requests sent for this pair.
+    requests_sent: u64,
+
+    /// Number of binding responses received for this pair.
+    responses_received: u64,
+
+    /// Round trip time of the last answered binding request.
+    rtt: Option<Duration>,
+
+    /// Smallest round trip time of any answered binding request.
+    min_rtt: Option<Duration>,
+
+    /// Exponentially weighted round trip time, like SRTT in RFC 6298.
+    smoothed_rtt: Option<Duration>,
+
+    /// Sum of the round trip times of all answered binding requests.
+    total_rtt: Duration,
+
+    /// Bytes sent over this pair, not counting STUN.
+    bytes_sent: u64,
+
+    /// Bytes received over this pair, not counting STUN.
+    bytes_received: u64,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
@@ -104,6 +132,9 @@ pub struct BindingAttempt {
 
     /// Whether the binding attempt is nominated.
     nominated: bool,
+
+    /// Whether we were controlling when sending the binding request.
+    controlling: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -206,7 +237,7 @@ impl CandidatePair {
     /// Records a new binding request attempt.
     ///
     /// Returns the transaction id to use in the STUN message.
-    pub fn new_attempt(&mut self, now: Instant) -> TransId {
+    pub fn new_attempt(&mut self, now: Instant, controlling: bool) -> TransId {
         // calculate a new time
         self.cached_next_attempt_time = None;
 
@@ -220,9 +251,11 @@ impl CandidatePair {
             request_sent: now,
             respone_recv: None,
             nominated: self.is_nominated(),
+            controlling,
         };
 
         self.binding_attempts.push_back(attempt);
+        self.requests_sent += 1;
 
         // Never keep more than STUN_MAX_RETRANS attempts.
         while self.binding_attempts.len() > STUN_MAX_RETRANS {
@@ -248,6 +281,24 @@ impl CandidatePair {
         self.binding_attempts.iter().any(|b| b.trans_id == trans_id)
     }
 
+    /// The role we had when sending the binding request for a STUN transaction id.
+    pub fn attempt_controlling(&self, trans_id: TransId) -> Option<bool> {
+        self.binding_attempts
+            .iter()
+            .find(|b| b.trans_id == trans_id)
+            .map(|b| b.controlling)
+    }
+
+    /// Update the prio after the agent changed role.
+    pub fn set_prio(&mut self, prio: u64) {
+        self.prio = prio;
+    }
+
+    /// Do the next binding attempt straight away.
+    pub fn trigger_check(&mut self, now: Instant) {
+        self.cached_next_attempt_time = Some(now);
+    }
+
     /// Marks a binding request attempt as having a successful response.
     ///
     /// ### Panics
@@ -264,7 +315,20 @@ impl CandidatePair {
             .find(|b| b.trans_id == trans_id)
             .expect("Binding request attempt");
 
+        if attempt.respone_recv.is_none() {
+            let rtt = now - attempt.request_sent;
+            self.rtt = Some(rtt);
+            self.min_rtt = Some(self.min_rtt.map_or(rtt, |m| m.min(rtt)));
+            self.smoothed_rtt = Some(match self.smoothed_rtt {
+                Some(srtt) => (srtt * 7 + rtt) / 8,
+                None => rtt,
+            });
+            self.total_rtt += rtt;
+            self.responses_received += 1;
+        }
+
         attempt.respone_recv = Some(now);
+        self.last_response = Some(now);
 
         if attempt.nominated && self.nomination_state == NominationState::Attempt {
             self.nomination_state = NominationState::Success;
@@ -283,6 +347,52 @@ impl CandidatePair {
         trace!("Recorded binding response: {:?}", self);
     }
 
+    /// The last time the remote answered a binding request.
+    pub fn last_response(&self) -> Option<Instant> {
+        self.last_response
+    }
+
+    pub fn requests_sent(&self) -> u64 {
+        self.requests_sent
+    }
+
+    pub fn responses_received(&self) -> u64 {
+        self.responses_received
+    }
+
+    /// Round trip time of the last answered binding request.
+    pub fn rtt(&self) -> Option<Duration> {
+        self.rtt
+    }
+
+    pub fn min_rtt(&self) -> Option<Duration> {
+        self.min_rtt
+    }
+
+    pub fn smoothed_rtt(&self) -> Option<Duration> {
+        self.smoothed_rtt
+    }
+
+    pub fn total_rtt(&self) -> Duration {
+        self.total_rtt
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    pub fn add_bytes_sent(&mut self, n: usize) {
+        self.bytes_sent += n as u64;
+    }
+
+    pub fn add_bytes_received(&mut self, n: usize) {
+        self.bytes_received += n as u64;
+    }
+
     /// The time of the last binding request attempt.
     ///
     /// `None` means there has been no attempts.
@@ -306,12 +416,29 @@ impl CandidatePair {
 
     /// When we should do the next retry.
     ///
-    /// Returns `None` if we are not to attempt this pair anymore.
-    pub fn next_binding_attempt(&mut self, now: Instant) -> Instant {
+    /// With a `consent_interval`, a succeeded pair without unanswered attempts is checked
+    /// at that interval (randomized as per RFC 7675), instead of the default cadence.
+    pub fn next_binding_attempt(
+        &mut self,
+        now: Instant,
+        consent_interval: Option<Duration>,
+    ) -> Instant {
         if let Some(cached) = self.cached_next_attempt_time {
             return cached;
         }
 
+        let fresh = self.state == CheckState::Succeeded && self.unanswered().is_none();
+
+        if let (Some(interval), Some(last), true) =
+            (consent_interval, self.last_attempt_time(), fresh)
+        {
+            // RFC 7675: the interval is randomized between 0.8 and 1.2 of the base.
+            let factor = 0.8 + NonCryptographicRng::f32() * 0.4;
+            let next = last + interval.mul_f32(factor);
+            self.cached_next_attempt_time = Some(next);
+            return next;
+        }
+
         let next = if matches!(self.nomination_state, NominationState::Nominated) {
             // Cheating a bit to make the nomination "skip the queue".
             now.checked_sub(Duration::from_secs(60)).unwrap()
@@ -344,8 +471,15 @@ impl CandidatePair {
 
     /// Tells if this candidate pair is still possible to use for connectivity.
     ///
+    /// With a `consent_timeout`, a pair that has been answered fails when there has been no
+    /// response for that long. Otherwise it fails after a number of unanswered attempts.
+    ///
     /// Returns `false` if the candidate has failed.
-    pub fn is_still_possible(&self, now: Instant) -> bool {
+    pub fn is_still_possible(&self, now: Instant, consent_timeout: Option<Duration>) -> bool {
+        if let (Some(timeout), Some(last)) = (consent_timeout, self.last_response) {
+            return now - last < timeout;
+        }
+
         let attempts = self.binding_attempts.len();
         let unanswered = self.unanswered().map(|b| b.0).unwrap_or(0);
 
@@ -415,3 +549,31 @@ impl fmt::Debug for CandidatePair {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rtt_from_binding_responses() {
+        let mut pair = CandidatePair::new(0, 0, 0);
+        let start = Instant::now();
+
+        let t1 = pair.new_attempt(start, true);
+        pair.record_binding_response(start + Duration::from_millis(100), t1, 0);
+
+        assert_eq!(pair.rtt(), Some(Duration::from_millis(100)));
+        assert_eq!(pair.min_rtt(), Some(Duration::from_millis(100)));
+        assert_eq!(pair.smoothed_rtt(), Some(Duration::from_millis(100)));
+
+        // Keepalives on a succeeded pair keep updating the values.
+        let later = start + Duration::from_secs(1);
+        let t2 = pair.new_attempt(later, true);
+        pair.record_binding_response(later + Duration::from_millis(20), t2, 0);
+
+        assert_eq!(pair.rtt(), Some(Duration::from_millis(20)));
+        assert_eq!(pair.min_rtt(), Some(Duration::from_millis(20)));
+        assert_eq!(pair.smoothed_rtt(), Some(Duration::from_millis(90)));
+        assert_eq!(pair.total_rtt(), Duration::from_millis(120));
+    }
+}
@@ -0,0 +1,101 @@
+//! Framing of packets over stream transports, RFC 4571.
+
+use std::io;
+
+/// Frame a packet to be sent over a TCP connection.
+///
+/// RFC 4571 prefixes each packet with its length as a 16 bit big-endian number. Use this
+/// for the contents of a [`Transmit`][super::Transmit] with [`Protocol::Tcp`][super::Protocol::Tcp].
+///
+/// ```
+/// # use str0m::net::rfc4571_frame;
+/// let mut out = vec![];
+/// rfc4571_frame(&[1, 2, 3], &mut out).unwrap();
+/// assert_eq!(out, [0, 3, 1, 2, 3]);
+/// ```
+pub fn rfc4571_frame(packet: &[u8], out: &mut Vec<u8>) -> Result<(), io::Error> {
+    let len = u16::try_from(packet.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "packet too large to frame"))?;
+
+    out.reserve(2 + packet.len());
+    out.extend_from_slice(&len.to_be_bytes());
+    out.extend_from_slice(packet);
+
+    Ok(())
+}
+
+/// Splits the bytes read from a TCP connection into packets, RFC 4571.
+///
+/// Keep one instance per connection. Each packet can be passed on as the contents of a
+/// [`Receive`][super::Receive] with [`Protocol::Tcp`][super::Protocol::Tcp].
+///
+/// ```
+/// # use str0m::net::Rfc4571Decoder;
+/// let mut decoder = Rfc4571Decoder::new();
+///
+/// // A packet can arrive in several reads.
+/// decoder.push(&[0, 3, 1]);
+/// assert_eq!(decoder.pop(), None);
+///
+/// decoder.push(&[2, 3, 0]);
+/// assert_eq!(decoder.pop(), Some(vec![1, 2, 3]));
+/// assert_eq!(decoder.pop(), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct Rfc4571Decoder {
+    buf: Vec<u8>,
+}
+
+impl Rfc4571Decoder {
+    /// Creates a new decoder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add bytes read from the connection.
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    /// Take the next complete packet, if there is one.
+    pub fn pop(&mut self) -> Option<Vec<u8>> {
+        let len = u16::from_be_bytes([*self.buf.first()?, *self.buf.get(1)?]) as usize;
+
+        if self.buf.len() < 2 + len {
+            return None;
+        }
+
+        let packet = self.buf[2..2 + len].to_vec();
+        self.buf.drain(..2 + len);
+
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame_too_large() {
+        let mut out = vec![];
+        assert!(rfc4571_frame(&[0; 65_536], &mut out).is_err());
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn decode_many_in_one_read() {
+        let mut bytes = vec![];
+        rfc4571_frame(&[1], &mut bytes).unwrap();
+        rfc4571_frame(&[], &mut bytes).unwrap();
+        rfc4571_frame(&[2, 3], &mut bytes).unwrap();
+
+        let mut decoder = Rfc4571Decoder::new();
+        decoder.push(&bytes);
+
+        assert_eq!(decoder.pop(), Some(vec![1]));
+        assert_eq!(decoder.pop(), Some(vec![]));
+        assert_eq!(decoder.pop(), Some(vec![2, 3]));
+        assert_eq!(decoder.pop(), None);
+    }
+}
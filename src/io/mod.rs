@@ -13,8 +13,14 @@ use thiserror::Error;
 mod stun;
 pub(crate) use stun::stun_resend_delay;
 pub use stun::StunMessage;
+pub(crate) use stun::{decode_str, decode_xor, encode_xor, MAGIC as STUN_MAGIC};
 pub(crate) use stun::{Class as StunClass, Method as StunMethod, StunError};
-pub(crate) use stun::{TransId, STUN_MAX_RETRANS, STUN_MAX_RTO_MILLIS, STUN_TIMEOUT};
+pub(crate) use stun::{
+    TransId, ROLE_CONFLICT, STUN_MAX_RETRANS, STUN_MAX_RTO_MILLIS, STUN_TIMEOUT,
+};
+
+mod framing;
+pub use framing::{rfc4571_frame, Rfc4571Decoder};
 
 mod id;
 // this is only exported from this crate to avoid needing
@@ -51,6 +57,12 @@ pub enum Protocol {
     /// UDP
     Udp,
     /// TCP (See RFC 4571 for framing)
+    ///
+    /// The [`Transmit::source`] and [`Transmit::destination`] pair identifies the
+    /// connection. Frame the contents with [`rfc4571_frame()`] and split incoming bytes
+    /// with [`Rfc4571Decoder`]. For [`TcpType::Active`][crate::TcpType::Active] candidates,
+    /// open a connection from the candidate address when there is none, and report
+    /// received data with the candidate address as [`Receive::destination`].
     Tcp,
     /// TCP with fixed SSL Hello Exchange
     /// See AsyncSSLServerSocket implementation for exchange details:
@@ -115,6 +127,26 @@ pub struct Transmit {
     pub contents: DatagramSend,
 }
 
+/// Several outgoing datagrams with the same protocol, source and destination.
+///
+/// Obtained via [`Rtc::batch_transmit()`][crate::Rtc::batch_transmit]. The datagrams must be
+/// sent in order. They are suitable for a single vectored send, such as `sendmmsg`.
+pub struct TransmitBatch {
+    /// Protocol the transmission should use.
+    pub proto: Protocol,
+
+    /// The source IP the datagrams should be sent from.
+    ///
+    /// See [`Transmit::source`].
+    pub source: SocketAddr,
+
+    /// The destination address the datagrams should be sent to.
+    pub destination: SocketAddr,
+
+    /// Contents of the datagrams, in the order they should be sent.
+    pub contents: Vec<DatagramSend>,
+}
+
 /// A wrapper for some payload that is to be sent.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DatagramSend(Vec<u8>);
@@ -193,6 +225,7 @@ pub(crate) enum DatagramRecvInner<'a> {
     Dtls(&'a [u8]),
     Rtp(&'a [u8]),
     Rtcp(&'a [u8]),
+    Turn(&'a [u8]),
 }
 
 impl<'a> TryFrom<&'a [u8]> for DatagramRecv<'a> {
@@ -208,18 +241,75 @@ impl<'a> TryFrom<&'a [u8]> for DatagramRecv<'a> {
             MultiplexKind::Dtls => Dtls(value),
             MultiplexKind::Rtp => Rtp(value),
             MultiplexKind::Rtcp => Rtcp(value),
+            MultiplexKind::Turn => Turn(value),
         };
 
         Ok(DatagramRecv { inner })
     }
 }
 
+/// Kind of an incoming datagram, as found by [`classify_datagram()`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatagramKind<'a> {
+    /// A STUN message.
+    Stun {
+        /// The local ufrag from the USERNAME attribute.
+        ///
+        /// This is set for binding requests, which are sent with the USERNAME
+        /// `<local ufrag>:<remote ufrag>` as seen from the receiving side. Responses
+        /// have no USERNAME.
+        local_ufrag: Option<&'a str>,
+    },
+    /// A DTLS record.
+    Dtls,
+    /// An RTP packet.
+    Rtp,
+    /// An RTCP packet.
+    Rtcp,
+    /// A TURN message, either ChannelData or a STUN message with a TURN method.
+    ///
+    /// These come from a TURN server and belong to the [`Rtc`][crate::Rtc] that has the
+    /// server configured.
+    Turn,
+}
+
+/// Classify an incoming datagram without an [`Rtc`][crate::Rtc] instance.
+///
+/// This is for running many [`Rtc`][crate::Rtc] on a single socket. The first packets
+/// from a new remote address are STUN binding requests, and the local ufrag in them tells
+/// which `Rtc` (with those [`IceCreds`][crate::IceCreds]) they are for. Later packets can
+/// then be routed on the source address.
+///
+/// The STUN message is not validated. The `Rtc` does that when receiving it.
+///
+/// Returns `None` if the datagram isn't any of the kinds.
+///
+/// ```
+/// # use str0m::net::{classify_datagram, DatagramKind};
+/// assert_eq!(classify_datagram(&[23, 254, 253, 0, 0]), Some(DatagramKind::Dtls));
+/// assert_eq!(classify_datagram(&[]), None);
+/// ```
+pub fn classify_datagram(buf: &[u8]) -> Option<DatagramKind<'_>> {
+    let kind = MultiplexKind::try_from(buf).ok()?;
+
+    Some(match kind {
+        MultiplexKind::Stun => DatagramKind::Stun {
+            local_ufrag: stun::local_ufrag(buf),
+        },
+        MultiplexKind::Dtls => DatagramKind::Dtls,
+        MultiplexKind::Rtp => DatagramKind::Rtp,
+        MultiplexKind::Rtcp => DatagramKind::Rtcp,
+        MultiplexKind::Turn => DatagramKind::Turn,
+    })
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub(crate) enum MultiplexKind {
     Stun,
     Dtls,
     Rtp,
     Rtcp,
+    Turn,
 }
 
 impl<'a> TryFrom<&'a [u8]> for MultiplexKind {
@@ -234,9 +324,19 @@ impl<'a> TryFrom<&'a [u8]> for MultiplexKind {
         let len = value.len();
 
         if byte0 < 2 && len >= 20 {
-            Ok(MultiplexKind::Stun)
+            // ICE only uses binding, other methods are TURN (allocate, data indications...).
+            let method =
+                ((byte0 as u16 & 0b0011_1111) << 8 | value[1] as u16) & 0b0011_1110_1110_1111;
+            if method == 1 {
+                Ok(MultiplexKind::Stun)
+            } else {
+                Ok(MultiplexKind::Turn)
+            }
         } else if byte0 >= 20 && byte0 < 64 {
             Ok(MultiplexKind::Dtls)
+        } else if byte0 >= 64 && byte0 < 80 && len >= 4 {
+            // TURN ChannelData, RFC 7983.
+            Ok(MultiplexKind::Turn)
         } else if byte0 >= 128 && byte0 < 192 && len > 2 {
             let byte1 = value[1];
             let payload_type = byte1 & 0x7f;
@@ -306,6 +406,7 @@ impl fmt::Debug for DatagramRecvInner<'_> {
             Self::Dtls(v) => write!(f, "Dtls(len: {})", v.len()),
             Self::Rtp(v) => write!(f, "Rtp(len: {})", v.len()),
             Self::Rtcp(v) => write!(f, "Rtcp(len: {})", v.len()),
+            Self::Turn(v) => write!(f, "Turn(len: {})", v.len()),
         }
     }
     //
@@ -14,6 +14,9 @@ pub const STUN_MAX_RETRANS: usize = 9;
 pub const STUN_MAX_RTO_MILLIS: u64 = 3000;
 pub const STUN_TIMEOUT: Duration = Duration::from_millis(18_750); // See test for how this is calculated.
 
+/// ERROR-CODE sent when both ICE agents claim the same role.
+pub const ROLE_CONFLICT: u16 = 487;
+
 /// Calculate the send delay given how many times we tried.
 ///
 // Technically RTO should be calculated as per https://datatracker.ietf.org/doc/html/rfc2988, and
@@ -56,11 +59,15 @@ impl TransId {
         TransId(t)
     }
 
-    fn from_slice(s: &[u8]) -> Self {
+    pub(crate) fn from_slice(s: &[u8]) -> Self {
         let mut t = [0_u8; 12];
         t[..].copy_from_slice(s);
         TransId(t)
     }
+
+    pub(crate) fn as_bytes(&self) -> &[u8; 12] {
+        &self.0
+    }
 }
 
 /// Represents a STUN message as fit for our purposes.
@@ -108,18 +115,21 @@ impl<'a> StunMessage<'a> {
 
         let attrs = Attributes::parse(&buf[20..], trans_id, &mut message_integrity_offset)?;
 
-        // message-integrity only includes the length up until and including
-        // the message-integrity attribute.
-        if message_integrity_offset == 0 {
+        let (integrity, integrity_len) = if attrs.message_integrity.is_some() {
+            // message-integrity only includes the length up until and including
+            // the message-integrity attribute.
+            let integrity_len = (message_integrity_offset + 4 + 20) as u16;
+
+            // password as key is called "short-term credentials"
+            // buffer from beginning including header (+20) to where message-integrity starts.
+            (&buf[0..(message_integrity_offset + 20)], integrity_len)
+        } else if method == Method::Binding && class == Class::Success {
+            // Responses from STUN servers, when gathering server reflexive candidates,
+            // have no credentials. They fail check_integrity().
+            (&buf[0..0], 0)
+        } else {
             return Err(StunError::Parse("No message integrity in incoming".into()));
-        }
-
-        // length including message integrity attribute
-        let integrity_len = (message_integrity_offset + 4 + 20) as u16;
-
-        // password as key is called "short-term credentials"
-        // buffer from beginning including header (+20) to where message-integrity starts.
-        let integrity = &buf[0..(message_integrity_offset + 20)];
+        };
 
         if method == Method::Binding && class == Class::Success {
             if attrs.xor_mapped_address.is_none() {
@@ -160,11 +170,17 @@ impl<'a> StunMessage<'a> {
     /// Whether this STUN message is a _successful_ BINDING response.
     ///
     /// STUN binding requests are very simple, they just return the observed address.
-    /// As such, they cannot actually fail which is why we don't have `is_failed_binding_response`.
     pub(crate) fn is_successful_binding_response(&self) -> bool {
         self.method == Method::Binding && self.class == Class::Success
     }
 
+    /// Whether this STUN message is a BINDING error response.
+    ///
+    /// In ICE, the only error we act on is a role conflict.
+    pub(crate) fn is_failed_binding_response(&self) -> bool {
+        self.method == Method::Binding && self.class == Class::Failure
+    }
+
     /// The transaction ID of this STUN message.
     pub(crate) fn trans_id(&self) -> TransId {
         self.trans_id
@@ -196,6 +212,19 @@ impl<'a> StunMessage<'a> {
         }
     }
 
+    /// Constructs a BINDING request without ICE attributes, for asking a STUN server
+    /// about our server reflexive address.
+    pub(crate) fn server_binding_request(trans_id: TransId) -> Self {
+        StunMessage {
+            class: Class::Request,
+            method: Method::Binding,
+            trans_id,
+            attrs: Attributes::default(),
+            integrity: &[],
+            integrity_len: 0,
+        }
+    }
+
     /// Constructs a new STUN BINDING reply.
     pub(crate) fn reply(trans_id: TransId, mapped_address: SocketAddr) -> StunMessage<'a> {
         StunMessage {
@@ -211,6 +240,24 @@ impl<'a> StunMessage<'a> {
         }
     }
 
+    /// Constructs a BINDING error response telling the peer that both sides claim the
+    /// same ICE role, see [RFC 8445 §7.3.1.1][1].
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc8445#section-7.3.1.1
+    pub(crate) fn role_conflict_reply(trans_id: TransId) -> StunMessage<'a> {
+        StunMessage {
+            class: Class::Failure,
+            method: Method::Binding,
+            trans_id,
+            attrs: Attributes {
+                error_code: Some((ROLE_CONFLICT, "Role Conflict")),
+                ..Default::default()
+            },
+            integrity: &[],
+            integrity_len: 0,
+        }
+    }
+
     /// If present, splits the value of the USERNAME attribute into local and remote (separated by `:`).
     pub fn split_username(&self) -> Option<(&str, &str)> {
         self.attrs.split_username()
@@ -226,6 +273,21 @@ impl<'a> StunMessage<'a> {
         self.attrs.priority
     }
 
+    /// If present, returns the tie-breaker of the ICE-CONTROLLING attribute.
+    pub(crate) fn ice_controlling(&self) -> Option<u64> {
+        self.attrs.ice_controlling
+    }
+
+    /// If present, returns the tie-breaker of the ICE-CONTROLLED attribute.
+    pub(crate) fn ice_controlled(&self) -> Option<u64> {
+        self.attrs.ice_controlled
+    }
+
+    /// If present, returns the code and reason phrase of the ERROR-CODE attribute.
+    pub(crate) fn error_code(&self) -> Option<(u16, &str)> {
+        self.attrs.error_code
+    }
+
     /// Whether this message has the USE-CANDIDATE attribute.
     pub(crate) fn use_candidate(&self) -> bool {
         self.attrs.use_candidate
@@ -315,9 +377,44 @@ impl<'a> StunMessage<'a> {
 
         Ok(MSG_HEADER_LEN + attr_len)
     }
+
+    /// Serialize this message into the provided buffer without MESSAGE-INTEGRITY,
+    /// returning the final length of the message.
+    pub(crate) fn to_bytes_unauthenticated(self, buf: &mut [u8]) -> Result<usize, StunError> {
+        const MSG_HEADER_LEN: usize = 20;
+        const FPRINT_LEN: usize = 4;
+        const ATTR_TLV_LENGTH: usize = 4;
+
+        let attr_len = self.attrs.padded_len() + FPRINT_LEN + ATTR_TLV_LENGTH;
+
+        let mut buf = io::Cursor::new(buf);
+
+        let typ = self.class.to_u16() | self.method.to_u16();
+        buf.write_all(&typ.to_be_bytes())?;
+        buf.write_all(&(attr_len as u16).to_be_bytes())?;
+        buf.write_all(MAGIC)?;
+        buf.write_all(&self.trans_id.0)?;
+
+        self.attrs.to_bytes(&mut buf, &self.trans_id.0)?;
+
+        buf.write_all(&Attributes::FINGERPRINT.to_be_bytes())?;
+        buf.write_all(&(FPRINT_LEN as u16).to_be_bytes())?;
+        let fingerprint_value_offset = MSG_HEADER_LEN + self.attrs.padded_len() + ATTR_TLV_LENGTH;
+        buf.write_all(&[0; FPRINT_LEN])?; // placeholder
+
+        let buf = buf.into_inner();
+
+        let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC)
+            .checksum(&buf[0..(fingerprint_value_offset - ATTR_TLV_LENGTH)])
+            ^ 0x5354_554e;
+        buf[fingerprint_value_offset..(fingerprint_value_offset + FPRINT_LEN)]
+            .copy_from_slice(&crc.to_be_bytes());
+
+        Ok(MSG_HEADER_LEN + attr_len)
+    }
 }
 
-const MAGIC: &[u8] = &[0x21, 0x12, 0xA4, 0x42];
+pub(crate) const MAGIC: &[u8] = &[0x21, 0x12, 0xA4, 0x42];
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub(crate) enum Class {
@@ -329,7 +426,7 @@ pub(crate) enum Class {
 }
 
 impl Class {
-    fn from_typ(typ: u16) -> Self {
+    pub(crate) fn from_typ(typ: u16) -> Self {
         use Class::*;
         match typ & 0b0000_0001_0001_0000 {
             0b0000_0000_0000_0000 => Request,
@@ -340,7 +437,7 @@ impl Class {
         }
     }
 
-    fn to_u16(self) -> u16 {
+    pub(crate) fn to_u16(self) -> u16 {
         use Class::*;
         match self {
             Request => 0b0000_0000_0000_0000,
@@ -515,8 +612,21 @@ impl<'a> Attributes<'a> {
         } else {
             0
         };
+        let error_code = self
+            .error_code
+            .map(|(_, reason)| {
+                let len = 4 + reason.len();
+                ATTR_TLV_LENGTH + len + (4 - len % 4) % 4
+            })
+            .unwrap_or_default();
 
-        username + ice_controlled + ice_controlling + priority + address + use_candidate
+        username
+            + ice_controlled
+            + ice_controlling
+            + priority
+            + address
+            + use_candidate
+            + error_code
     }
 
     fn to_bytes(self, vec: &mut dyn Write, trans_id: &[u8]) -> io::Result<()> {
@@ -555,6 +665,16 @@ impl<'a> Attributes<'a> {
             vec.write_all(&Self::USE_CANDIDATE.to_be_bytes())?;
             vec.write_all(&0_u16.to_be_bytes())?;
         }
+        if let Some((code, reason)) = self.error_code {
+            let len = 4 + reason.len();
+            vec.write_all(&Self::ERROR_CODE.to_be_bytes())?;
+            vec.write_all(&(len as u16).to_be_bytes())?;
+            vec.write_all(&[0, 0, (code / 100) as u8, (code % 100) as u8])?;
+            vec.write_all(reason.as_bytes())?;
+            for _ in 0..(4 - len % 4) % 4 {
+                vec.write_all(&[0])?;
+            }
+        }
 
         Ok(())
     }
@@ -702,7 +822,38 @@ impl<'a> Attributes<'a> {
     }
 }
 
-fn decode_str(typ: u16, buf: &[u8], len: usize) -> Result<&str, StunError> {
+/// Find the local ufrag in the USERNAME of a STUN message, without a full parse.
+///
+/// No integrity check or other validation is done. This is only to find out which
+/// session a message is for.
+pub(crate) fn local_ufrag(buf: &[u8]) -> Option<&str> {
+    if buf.len() < 20 || &buf[4..8] != MAGIC {
+        return None;
+    }
+
+    let len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+    let mut attrs = buf.get(20..20 + len)?;
+
+    while attrs.len() >= 4 {
+        let typ = u16::from_be_bytes([attrs[0], attrs[1]]);
+        let len = u16::from_be_bytes([attrs[2], attrs[3]]) as usize;
+        let value = attrs.get(4..4 + len)?;
+
+        if typ == Attributes::USERNAME {
+            let username = std::str::from_utf8(value).ok()?;
+            let (local, _) = username.split_once(':')?;
+            return (!local.is_empty()).then_some(local);
+        }
+
+        // Attributes are padded to 4 bytes.
+        let pad = (4 - len % 4) % 4;
+        attrs = attrs.get(4 + len + pad..).unwrap_or_default();
+    }
+
+    None
+}
+
+pub(crate) fn decode_str(typ: u16, buf: &[u8], len: usize) -> Result<&str, StunError> {
     if len > 128 {
         return Err(StunError::Parse(format!(
             "0x{typ:04x?} too long str len: {len}"
@@ -714,7 +865,7 @@ fn decode_str(typ: u16, buf: &[u8], len: usize) -> Result<&str, StunError> {
     }
 }
 
-fn encode_xor(addr: SocketAddr, buf: &mut [u8; 20], trans_id: &[u8]) -> usize {
+pub(crate) fn encode_xor(addr: SocketAddr, buf: &mut [u8; 20], trans_id: &[u8]) -> usize {
     let port = addr.port() ^ 0x2112;
     buf[2..4].copy_from_slice(&port.to_be_bytes());
     buf[1] = if addr.is_ipv4() { 1 } else { 2 };
@@ -740,7 +891,7 @@ fn encode_xor(addr: SocketAddr, buf: &mut [u8; 20], trans_id: &[u8]) -> usize {
     }
 }
 
-fn decode_xor(buf: &[u8], trans_id: TransId) -> Result<SocketAddr, StunError> {
+pub(crate) fn decode_xor(buf: &[u8], trans_id: TransId) -> Result<SocketAddr, StunError> {
     let port = (((buf[2] as u16) << 8) | (buf[3] as u16)) ^ 0x2112;
     let ip_buf = &buf[4..];
     let ip = match buf[1] {
@@ -812,6 +963,9 @@ mod test {
         let packet = PACKET.to_vec();
         let message = StunMessage::parse(&packet).unwrap();
         assert!(message.check_integrity("xJcE9AQAR7kczUDVOXRUCl"));
+
+        assert_eq!(local_ufrag(&packet), Some("p9KA"));
+        assert_eq!(local_ufrag(&packet[..40]), None);
     }
 
     #[test]
@@ -853,6 +1007,41 @@ mod test {
         );
     }
 
+    #[test]
+    fn binding_response_without_integrity() {
+        let trans_id = TransId::new();
+        let addr: SocketAddr = "1.2.3.4:5000".parse().unwrap();
+
+        let mut buf = vec![0; 200];
+        let n = StunMessage::reply(trans_id, addr)
+            .to_bytes_unauthenticated(&mut buf)
+            .unwrap();
+        buf.truncate(n);
+
+        let message = StunMessage::parse(&buf).unwrap();
+        assert!(message.is_successful_binding_response());
+        assert_eq!(message.trans_id(), trans_id);
+        assert_eq!(message.mapped_address(), Some(addr));
+        assert!(!message.check_integrity("password"));
+    }
+
+    #[test]
+    fn role_conflict_reply_roundtrip() {
+        let trans_id = TransId::new();
+
+        let mut buf = vec![0; 200];
+        let n = StunMessage::role_conflict_reply(trans_id)
+            .to_bytes("password", &mut buf)
+            .unwrap();
+        buf.truncate(n);
+
+        let message = StunMessage::parse(&buf).unwrap();
+        assert_eq!(message.class(), Class::Failure);
+        assert_eq!(message.trans_id(), trans_id);
+        assert_eq!(message.error_code(), Some((ROLE_CONFLICT, "Role Conflict")));
+        assert!(message.check_integrity("password"));
+    }
+
     #[test]
     fn parse_zero_length_buffer() {
         let result = StunMessage::parse(&[]);
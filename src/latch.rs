@@ -0,0 +1,158 @@
+use std::net::SocketAddr;
+
+use crate::net::Protocol;
+use crate::SendAddr;
+
+/// Number of consecutive authenticated packets from a new address needed to move an
+/// already latched address.
+const RELATCH_PACKETS: usize = 3;
+
+/// Address latching for symmetric RTP.
+///
+/// See [`RtcConfig::set_address_latching()`][crate::RtcConfig::set_address_latching].
+#[derive(Debug)]
+pub(crate) struct AddressLatch {
+    enabled: bool,
+    /// Whether we latched since the last ICE nomination.
+    latched: bool,
+    /// A new address seen and the number of consecutive authenticated packets from it.
+    candidate: Option<(SocketAddr, usize)>,
+    /// Change to report as an event (previous, current).
+    pending: Option<(SocketAddr, SocketAddr)>,
+}
+
+impl AddressLatch {
+    pub fn new(enabled: bool) -> Self {
+        AddressLatch {
+            enabled,
+            latched: false,
+            candidate: None,
+            pending: None,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Called when ICE nominates a new pair, which takes precedence over any latch.
+    pub fn reset(&mut self) {
+        self.latched = false;
+        self.candidate = None;
+    }
+
+    /// Called for every SRTP/SRTCP packet that authenticated and was not replayed.
+    pub fn handle_authenticated(
+        &mut self,
+        send_addr: &mut SendAddr,
+        proto: Protocol,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        // Only latch for UDP arriving on the socket we are sending from.
+        if proto != Protocol::Udp || send_addr.proto != proto || send_addr.source != destination {
+            return;
+        }
+
+        if send_addr.destination == source {
+            // Traffic from the current address breaks any run from another address.
+            self.candidate = None;
+            self.latched = true;
+            return;
+        }
+
+        let count = match &mut self.candidate {
+            Some((addr, count)) if *addr == source => {
+                *count += 1;
+                *count
+            }
+            _ => {
+                self.candidate = Some((source, 1));
+                1
+            }
+        };
+
+        let needed = if self.latched { RELATCH_PACKETS } else { 1 };
+
+        if count < needed {
+            return;
+        }
+
+        let previous = send_addr.destination;
+        info!("Latch remote address: {} -> {}", previous, source);
+
+        send_addr.destination = source;
+        self.latched = true;
+        self.candidate = None;
+        self.pending = Some((previous, source));
+    }
+
+    pub fn poll_event(&mut self) -> Option<(SocketAddr, SocketAddr)> {
+        self.pending.take()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn addr(port: u16) -> SocketAddr {
+        ([10, 0, 0, 1], port).into()
+    }
+
+    fn send_addr() -> SendAddr {
+        SendAddr {
+            proto: Protocol::Udp,
+            source: addr(1000),
+            destination: addr(2000),
+        }
+    }
+
+    #[test]
+    fn latch_first_then_require_run() {
+        let mut latch = AddressLatch::new(true);
+        let mut send = send_addr();
+
+        // First authenticated packet from elsewhere latches straight away.
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(3000), addr(1000));
+        assert_eq!(send.destination, addr(3000));
+        assert_eq!(latch.poll_event(), Some((addr(2000), addr(3000))));
+        assert_eq!(latch.poll_event(), None);
+
+        // Interleaved packets from the current address prevent moving.
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(4000), addr(1000));
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(4000), addr(1000));
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(3000), addr(1000));
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(4000), addr(1000));
+        assert_eq!(send.destination, addr(3000));
+
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(4000), addr(1000));
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(4000), addr(1000));
+        assert_eq!(send.destination, addr(4000));
+        assert_eq!(latch.poll_event(), Some((addr(3000), addr(4000))));
+    }
+
+    #[test]
+    fn ignore_other_socket_or_tcp() {
+        let mut latch = AddressLatch::new(true);
+        let mut send = send_addr();
+
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(3000), addr(1001));
+        latch.handle_authenticated(&mut send, Protocol::Tcp, addr(3000), addr(1000));
+        assert_eq!(send.destination, addr(2000));
+        assert_eq!(latch.poll_event(), None);
+    }
+
+    #[test]
+    fn disabled() {
+        let mut latch = AddressLatch::new(false);
+        let mut send = send_addr();
+
+        latch.handle_authenticated(&mut send, Protocol::Udp, addr(3000), addr(1000));
+        assert_eq!(send.destination, addr(2000));
+    }
+}
@@ -112,7 +112,7 @@
 //! looks like this.
 //!
 //! ```no_run
-//! # use str0m::{Rtc, Output, IceConnectionState, Event, Input};
+//! # use str0m::{Rtc, Output, Event, Input};
 //! # use str0m::net::{Receive, Protocol};
 //! # use std::io::ErrorKind;
 //! # use std::net::UdpSocket;
@@ -143,9 +143,11 @@
 //!         // peer, but also data channel data and statistics.
 //!         Output::Event(v) => {
 //!
-//!             // Abort if we disconnect.
-//!             if v == Event::IceConnectionStateChange(IceConnectionState::Disconnected) {
-//!                 return;
+//!             // Abort if we disconnect or ICE fails.
+//!             if let Event::IceConnectionStateChange(state) = &v {
+//!                 if state.is_disconnected() {
+//!                     return;
+//!                 }
 //!             }
 //!
 //!             // TODO: handle more cases of v here, such as incoming media data.
@@ -341,7 +343,7 @@
 //!
 //! ### Sample level
 //!
-//! All codecs such as h264, vp8, vp9 and opus outputs what we call
+//! All codecs such as h264, h265, vp8, vp9, av1 and opus outputs what we call
 //! "Samples". A sample has a very specific meaning for audio, but this
 //! project uses it in a broader sense, where a sample is either a video
 //! or audio time stamped chunk of encoded data that typically represents
@@ -497,6 +499,7 @@
 //! | Audio render             | :x:                | :white_check_mark: |
 //! | Turn                     | :x:                | :white_check_mark: |
 //! | Network interface enum   | :x:                | :white_check_mark: |
+//! | Unbundled transports     | :x:                | :white_check_mark: |
 //!
 //! ### Platform Support
 //!
@@ -579,29 +582,33 @@
 #[macro_use]
 extern crate tracing;
 
-use bwe::{Bwe, BweKind};
-use change::{DirectApi, SdpApi};
+use bwe::{Bwe, BweKind, RemoteBitrateLimit};
+use change::{DirectApi, SdpApi, SdpQueue};
 use rtp::RawPacket;
 use std::fmt;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
-use streams::RtpPacket;
-use streams::StreamPaused;
+use streams::{RtcpPacing, RtcpSchedule, RtpPacket};
+use streams::{StreamLayersAllocation, StreamPaused};
 use thiserror::Error;
 use util::InstantExt;
 
 mod crypto;
-use crypto::Fingerprint;
+use crypto::{Fingerprint, FingerprintHash, SrtpCrypto};
 
 mod dtls;
-use dtls::DtlsCert;
+use crypto::dtls::DtlsOptions;
 use dtls::{Dtls, DtlsEvent};
+use dtls::{DtlsCert, DtlsCipherSuite, DtlsGroup, DtlsKeyLog, DtlsRole};
 
 #[path = "ice/mod.rs"]
 mod ice_;
 use ice_::IceAgent;
 use ice_::IceAgentEvent;
-pub use ice_::{Candidate, CandidateKind, IceConnectionState, IceCreds};
+use ice_::StunGatherer;
+pub use ice_::{Candidate, CandidateKind, IceConnectionState, IceCreds, NetworkType, TcpType};
+pub use ice_::{IceConsent, IceConsentConfig, IceFamilyPolicy, IceNomination, IpFamily};
 
 /// Low level ICE access.
 // The ICE API is not necessary to interact with directly for "regular"
@@ -616,13 +623,14 @@ pub mod ice {
 }
 
 mod io;
-use io::DatagramRecvInner;
+use io::{DatagramRecvInner, DATAGRAM_MTU_WARN};
 
 mod packet;
 
 #[path = "rtp/mod.rs"]
 mod rtp_;
 use rtp_::Bitrate;
+use rtp_::RtpLeniency;
 use rtp_::{Extension, ExtensionMap};
 
 /// Low level RTP access.
@@ -632,17 +640,35 @@ pub mod rtp {
         pub use crate::rtp_::{Descriptions, ExtendedReport, Fir, Goodbye, Nack, Pli};
         pub use crate::rtp_::{Dlrr, NackEntry, ReceptionReport, ReportBlock};
         pub use crate::rtp_::{FirEntry, ReceiverReport, SenderInfo, SenderReport, Twcc};
-        pub use crate::rtp_::{ReportList, Rrtr, Rtcp, Sdes, SdesType};
+        pub use crate::rtp_::{Remb, Tmmbr, TmmbrEntry};
+        pub use crate::rtp_::{ReportList, Rrtr, Rtcp, RtcpPacketSummary, Sdes, SdesType};
     }
     use self::rtcp::Rtcp;
 
     /// Video Layers Allocation RTP Header Extension
     pub mod vla;
+
+    /// Dependency Descriptor RTP Header Extension
+    pub mod dd;
     pub use crate::rtp_::{Extension, ExtensionMap, ExtensionSerializer};
     pub use crate::rtp_::{ExtensionValues, UserExtensionValues};
 
-    pub use crate::rtp_::{RtpHeader, SeqNo, Ssrc, VideoOrientation};
-    pub use crate::streams::{RtpPacket, StreamPaused, StreamRx, StreamTx};
+    pub use crate::rtp_::SrtpErrorCounters;
+    pub use crate::rtp_::{RocRecoveryCounters, RtpHeader, RtpLeniency, RtpLeniencyCounters};
+    pub use crate::rtp_::{SeqNo, Ssrc, VideoOrientation};
+    pub use crate::streams::{RtcpPacing, RtcpSchedule, RtpPacket};
+    pub use crate::streams::{StreamLayersAllocation, StreamPaused, StreamRx, StreamTx};
+
+    pub use crate::packet::{BridgedRtp, SimulcastBridge};
+
+    /// Pluggable SRTP ciphers.
+    ///
+    /// See [`SrtpCrypto`][crate::rtp::srtp::SrtpCrypto].
+    pub mod srtp {
+        pub use crate::crypto::aead_aes_128_gcm::CipherCtx as AeadAes128GcmCipher;
+        pub use crate::crypto::aes_128_cm_sha1_80::CipherCtx as Aes128CmSha1_80Cipher;
+        pub use crate::crypto::{CryptoError, SrtpCrypto};
+    }
 
     /// Debug output of the unencrypted RTP and RTCP packets.
     ///
@@ -659,6 +685,23 @@ pub mod rtp {
         RtpTx(RtpHeader, Vec<u8>),
         /// Incoming RTP.
         RtpRx(RtpHeader, Vec<u8>),
+        /// Sent compound RTCP packet, unencrypted.
+        RtcpCompoundTx(RawRtcp),
+        /// Incoming compound RTCP packet, unencrypted.
+        ///
+        /// Also contains packet types that str0m does not handle.
+        RtcpCompoundRx(RawRtcp),
+    }
+
+    /// An unencrypted compound RTCP packet with a summary of its parts.
+    ///
+    /// As found in [`RawPacket::RtcpCompoundTx`] and [`RawPacket::RtcpCompoundRx`].
+    #[derive(Debug, Clone)]
+    pub struct RawRtcp {
+        /// The entire compound packet.
+        pub data: Vec<u8>,
+        /// One entry per RTCP packet in `data`, in order.
+        pub packets: Vec<rtcp::RtcpPacketSummary>,
     }
 }
 
@@ -673,29 +716,46 @@ pub mod format;
 use format::CodecConfig;
 
 pub mod channel;
-use channel::{Channel, ChannelData, ChannelHandler, ChannelId};
+use channel::{Channel, ChannelCloseReason, ChannelData, ChannelHandler, ChannelId};
+use channel::{SctpCloseReason, SctpParams, SctpRto, SctpStats};
 
 pub mod media;
 use media::{Direction, Media, Mid, Pt, Rid, Writer};
-use media::{KeyframeRequest, KeyframeRequestKind};
-use media::{MediaAdded, MediaChanged, MediaData};
+use media::{DtmfEvent, MediaAdded, MediaChanged, MediaData};
+use media::{KeyframeRequest, KeyframeRequestKind, SendBackpressure};
 
 pub mod change;
 
 mod util;
-use util::{already_happened, not_happening, Soonest};
+use util::{already_happened, not_happening, InstanceRng, Soonest};
 
 mod session;
 use session::Session;
 
 pub mod stats;
-use stats::{MediaEgressStats, MediaIngressStats, PeerStats, Stats, StatsEvent, StatsSnapshot};
+use stats::{ActivityCounters, QualityScorer, QualityStats, Stats, StatsEvent, StatsSnapshot};
+use stats::{CandidatePairStats, ChannelStats, MediaEgressStats, MediaIngressStats, PeerStats};
 
 mod streams;
 
+pub mod turn;
+use turn::{TurnClient, TurnConfig, TurnEvent, TurnOutput};
+
+mod limits;
+use limits::LimitState;
+pub use limits::{Limit, Limits};
+
+mod latch;
+use latch::AddressLatch;
+
+mod warning;
+pub use warning::Warning;
+
 /// Network related types to get socket data in/out of [`Rtc`].
 pub mod net {
-    pub use crate::io::{DatagramRecv, DatagramSend, Protocol, Receive, Transmit};
+    pub use crate::io::{classify_datagram, DatagramKind};
+    pub use crate::io::{rfc4571_frame, Rfc4571Decoder};
+    pub use crate::io::{DatagramRecv, DatagramSend, Protocol, Receive, Transmit, TransmitBatch};
 }
 
 /// Various error types.
@@ -709,6 +769,28 @@ pub mod error {
     pub use crate::sdp::SdpError;
 }
 
+/// The most commonly used types, for glob import.
+///
+/// These re-exports stay put even if the modules they originate from are reorganized.
+/// None of them depend on cargo features. Only the crypto backends are feature gated,
+/// the [`rtp`], [`bwe`] and [`channel`] modules are always compiled.
+///
+/// ```
+/// use str0m::prelude::*;
+///
+/// let mut rtc: Rtc = RtcConfig::new().build();
+///
+/// let addr = "1.2.3.4:5000".parse().unwrap();
+/// rtc.add_local_candidate(Candidate::host(addr, "udp").unwrap());
+///
+/// let mid: Mid = "audio".into();
+/// ```
+pub mod prelude {
+    pub use crate::media::{Mid, Pt, Rid};
+    pub use crate::rtp::{ExtensionValues, SeqNo};
+    pub use crate::{Candidate, Event, Input, Output, Rtc, RtcConfig, RtcError};
+}
+
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Errors for the whole Rtc engine.
@@ -800,6 +882,10 @@ pub enum RtcError {
     /// is an incorrect usage pattern of the str0m API.
     #[error("Consecutive calls to write() without poll_output() in between")]
     WriteWithoutPoll,
+
+    /// The remote SDP would go above a limit configured with [`Limits`].
+    #[error("Remote SDP goes above limit of {0}")]
+    LimitReached(Limit),
 }
 
 /// Instance that does WebRTC. Main struct of the entire library.
@@ -834,6 +920,8 @@ pub enum RtcError {
 pub struct Rtc {
     alive: bool,
     ice: IceAgent,
+    stun_gatherer: StunGatherer,
+    turn: Vec<TurnClient>,
     dtls: Dtls,
     sctp: RtcSctp,
     chan: ChannelHandler,
@@ -842,12 +930,23 @@ pub struct Rtc {
     remote_fingerprint: Option<Fingerprint>,
     remote_addrs: Vec<SocketAddr>,
     send_addr: Option<SendAddr>,
+    latch: AddressLatch,
     need_init_time: bool,
     last_now: Instant,
     peer_bytes_rx: u64,
     peer_bytes_tx: u64,
     change_counter: usize,
+    sdp_queue: SdpQueue,
     last_timeout_reason: Reason,
+    max_transmit_batch: usize,
+    activity: ActivityCounters,
+    poll_budget: PollBudget,
+    poll_work: PollWork,
+    rng: Option<InstanceRng>,
+    dtls_rekey_interval: Option<Duration>,
+    dtls_rekey_at: Option<Instant>,
+    #[cfg(feature = "alloc-stats")]
+    allocation_counter: Option<fn() -> u64>,
 }
 
 struct SendAddr {
@@ -870,6 +969,82 @@ pub enum Event {
     /// connected to the peer or not.
     IceConnectionStateChange(IceConnectionState),
 
+    /// Consent freshness of the nominated pair changed.
+    ///
+    /// This tells apart a peer that stopped answering connectivity checks from other
+    /// reasons to disconnect. Configure using [`RtcConfig::set_ice_consent()`].
+    IceConsent(IceConsent),
+
+    /// A remote candidate hides its ip behind an mDNS hostname, typically from a browser.
+    ///
+    /// The hostname, such as `0f4cc0ff-5c2f-4e7a-8a3a-7d4f8b0e5b3a.local`, must be resolved
+    /// by the application using multicast DNS, and the result given to
+    /// [`Rtc::resolve_mdns()`]. The candidate is not used until then.
+    MdnsResolveRequest(String),
+
+    /// The remote media address changed due to address latching.
+    ///
+    /// Enable using [`RtcConfig::set_address_latching()`]. Outgoing traffic is sent to
+    /// `current` from here on.
+    RemoteAddressLatched {
+        /// The address we previously sent to.
+        previous: SocketAddr,
+        /// The address we now send to.
+        current: SocketAddr,
+    },
+
+    /// The candidate pair used for sending changed.
+    ///
+    /// Like the `selectedcandidatepairchange` event in the browser.
+    SelectedCandidatePairChange {
+        /// The local candidate of the pair.
+        local: Candidate,
+        /// The remote candidate of the pair.
+        remote: Candidate,
+    },
+
+    /// A candidate was gathered from a STUN or TURN server.
+    ///
+    /// Server reflexive candidates come from servers configured using
+    /// [`RtcConfig::set_stun_servers()`], and relayed candidates from allocations on
+    /// servers configured using [`RtcConfig::set_turn_servers()`]. The candidate is
+    /// already added as a local candidate, and should be communicated to the remote
+    /// peer like any other local candidate.
+    CandidateGathered(Candidate),
+
+    /// The ICE role changed to resolve a role conflict with the remote peer.
+    ///
+    /// Happens when both sides think they are controlling (or controlled). The side with
+    /// the larger tie-breaker, see [`DirectApi::set_ice_tie_breaker()`],
+    /// ends up controlling.
+    IceRoleChange {
+        /// Whether we are now the controlling side.
+        controlling: bool,
+    },
+
+    /// A peer reflexive candidate was discovered from a connectivity check.
+    ///
+    /// A remote one (`local: false`) is the source of a binding request from an address
+    /// the peer never signaled, typically the peer behind a NAT we haven't got its
+    /// candidates for yet. A local one is our address as seen by the peer, when it differs
+    /// from the candidate we sent from. The address and priority are in the candidate.
+    PeerReflexiveCandidate {
+        /// The discovered candidate.
+        candidate: Candidate,
+        /// Whether this is our address or the remote peer's.
+        local: bool,
+    },
+
+    /// A remote peer reflexive candidate was later signaled by the remote peer.
+    ///
+    /// The signaled candidate takes the place of the peer reflexive one, keeping its pairs.
+    PeerReflexivePromoted {
+        /// The peer reflexive candidate that was replaced.
+        previous: Candidate,
+        /// The candidate signaled by the remote peer.
+        current: Candidate,
+    },
+
     // =================== Media related events ==================
 
     /// Upon adding new media to the session. The lines are emitted.
@@ -880,6 +1055,11 @@ pub enum Event {
     /// Incoming media data sent by the remote peer.
     MediaData(MediaData),
 
+    /// A DTMF tone sent by the remote peer.
+    ///
+    /// Enable using [`RtcConfig::enable_dtmf()`].
+    Dtmf(DtmfEvent),
+
     /// Changes to the media may be emitted.
     ///
     ///. Currently only covers a change of direction.
@@ -905,8 +1085,34 @@ pub enum Event {
     ChannelData(ChannelData),
 
     /// A data channel has been closed.
+    ///
+    /// This is also emitted for a channel we opened in-band that the remote peer didn't
+    /// acknowledge within 30 seconds.
     ChannelClose(ChannelId),
 
+    /// The remote peer closed a data channel and told us why.
+    ///
+    /// Comes right before the [`Event::ChannelClose`] for the same channel. A remote
+    /// peer that closes without a reason, or isn't str0m, only results in the close.
+    ChannelCloseReason(ChannelId, ChannelCloseReason),
+
+    /// The buffered amount of a data channel dropped to or below the threshold.
+    ///
+    /// Set the threshold using [`Channel::set_buffered_amount_low_threshold()`].
+    ChannelBufferedAmountLow(ChannelId),
+
+    /// A message held back to leave room for other channels failed when it was finally written.
+    ///
+    /// [`Channel::write()`] returned `Ok` for the message, since it was only queued then.
+    ChannelWriteFailed(ChannelId, error::SctpError),
+
+    /// The SCTP association carrying all data channels has ended.
+    ///
+    /// This happens when the remote peer aborts the association, stops answering,
+    /// or violates the protocol. Every open channel is closed and will be followed
+    /// by a [`Event::ChannelClose`]. No new channels can be opened.
+    SctpAssociationClosed(SctpCloseReason),
+
     // =================== Statistics and BWE related events ===================
 
     /// Statistics event for the Rtc instance
@@ -920,9 +1126,21 @@ pub enum Event {
     /// Aggregated statistics for each media (mid, rid) in the egress direction
     MediaEgressStats(MediaEgressStats),
 
+    /// Statistics for each open data channel
+    ChannelStats(ChannelStats),
+
+    /// Statistics for each ICE candidate pair
+    CandidatePairStats(CandidatePairStats),
+
+    /// Connection quality score per direction, see [`RtcConfig::set_quality_scorer`]
+    QualityStats(QualityStats),
+
     /// A new estimate from the bandwidth estimation subsystem.
     EgressBitrateEstimate(BweKind),
 
+    /// The remote peer asks us to cap the bitrate of streams we are sending (REMB or TMMBR).
+    RemoteBitrateLimit(RemoteBitrateLimit),
+
     // =================== RTP related events ===================
 
     /// Incoming keyframe request for media that we are sending to the remote peer.
@@ -930,11 +1148,21 @@ pub enum Event {
     /// The request is either PLI (Picture Loss Indication) or FIR (Full Intra Request).
     KeyframeRequest(KeyframeRequest),
 
+    /// Writes for a media outpace what can be sent, or have stopped doing so.
+    ///
+    /// Enable using [`RtcConfig::set_send_backpressure()`].
+    SendBackpressure(SendBackpressure),
+
     /// Whether an incoming encoded stream is paused.
     ///
     /// This means the stream has not received any data for some time (default 1.5 seconds).
     StreamPaused(StreamPaused),
 
+    /// The remote sender signalled a new Video Layers Allocation for an incoming stream.
+    ///
+    /// Requires the VLA header extension to be configured, see [`rtp::vla`].
+    LayersAllocation(StreamLayersAllocation),
+
     /// Incoming RTP data.
     RtpPacket(RtpPacket),
 
@@ -944,6 +1172,13 @@ pub enum Event {
     /// This clones data, and is therefore expensive.
     /// Should not be enabled outside of tests and troubleshooting.
     RawPacket(Box<RawPacket>),
+
+    // =================== Diagnostics ===================
+
+    /// A non-fatal condition that is also logged as a warning.
+    ///
+    /// See [`Warning`] for the possible cases.
+    Warning(Warning),
 }
 
 impl Event {
@@ -1049,6 +1284,22 @@ pub enum Reason {
     ///
     /// Calculations regarding sender bandwidth using incoming TWCC.
     Bwe,
+
+    /// End of the grace period for the SRTP keys from before a re-key.
+    ///
+    /// Packets under the previous keys are accepted until then.
+    SrtpRekey,
+
+    /// Periodic DTLS re-handshake for fresh SRTP keys (if enabled).
+    ///
+    /// See [`RtcConfig::set_dtls_rekey_interval()`].
+    DtlsRekey,
+
+    /// The [`PollBudget`] is used up (if configured).
+    ///
+    /// There is more output pending. The timeout is the current time, and
+    /// [`Rtc::poll_output()`] should be called again after attending to other work.
+    PollAgain,
 }
 
 impl Default for Reason {
@@ -1057,6 +1308,37 @@ impl Default for Reason {
     }
 }
 
+/// Limits on the work done by consecutive [`Rtc::poll_output()`] calls.
+///
+/// The budget covers all output between two [`Output::Timeout`]. Once the limit is
+/// reached, the next poll yields an [`Output::Timeout`] with [`Reason::PollAgain`].
+///
+/// The work is counted in outputs rather than time, since str0m never reads the clock.
+///
+/// Set via [`RtcConfig::set_poll_budget()`]. The default has no limits.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PollBudget {
+    /// Max number of transmits and events. Datagrams appended by
+    /// [`Rtc::batch_transmit()`] count individually.
+    ///
+    /// `Some(0)` is treated as no limit, same as `None`.
+    pub max_outputs: Option<usize>,
+}
+
+#[derive(Debug, Default)]
+struct PollWork {
+    outputs: usize,
+}
+
+impl PollWork {
+    fn exceeded(&self, budget: &PollBudget) -> bool {
+        match budget.max_outputs {
+            Some(max) if max > 0 => self.outputs >= max,
+            _ => false,
+        }
+    }
+}
+
 impl Rtc {
     /// Creates a new instance with default settings.
     ///
@@ -1069,7 +1351,8 @@ impl Rtc {
     /// ```
     pub fn new() -> Self {
         let config = RtcConfig::default();
-        Self::new_from_config(config)
+        // The default config is always valid.
+        Self::new_from_config(config).expect("default RtcConfig to be valid")
     }
 
     /// Creates a config builder that configures an [`Rtc`] instance.
@@ -1084,8 +1367,12 @@ impl Rtc {
         RtcConfig::new()
     }
 
-    pub(crate) fn new_from_config(config: RtcConfig) -> Self {
-        let session = Session::new(&config);
+    pub(crate) fn new_from_config(config: RtcConfig) -> Result<Self, RtcError> {
+        // Must happen before anything below generates ids.
+        let rng = config.rng_seed.map(InstanceRng::with_seed);
+        let _rng = InstanceRng::enter(rng.as_ref());
+
+        let mut session = Session::new(&config);
 
         let local_creds = config.local_ice_credentials.unwrap_or_else(IceCreds::new);
         let mut ice = IceAgent::with_local_credentials(local_creds);
@@ -1093,6 +1380,19 @@ impl Rtc {
             ice.set_ice_lite(config.ice_lite);
         }
 
+        ice.set_consent(config.ice_consent);
+        ice.set_limits(config.limits);
+        ice.set_family_policy(config.ice_family_policy);
+        ice.set_timing_advance(config.ice_check_interval);
+        ice.set_nomination(config.ice_nomination);
+
+        // ice-lite only uses host candidates, there is no point in gathering.
+        let (stun_servers, turn_servers) = if config.ice_lite {
+            (vec![], vec![])
+        } else {
+            (config.stun_servers, config.turn_servers)
+        };
+
         let dtls_cert = if let Some(c) = config.dtls_cert {
             c
         } else {
@@ -1100,30 +1400,62 @@ impl Rtc {
             {
                 DtlsCert::new_openssl()
             }
-            #[cfg(not(feature = "openssl"))]
+            #[cfg(all(feature = "boringssl", not(feature = "openssl")))]
             {
-                panic!("No DTLS implementation. Enable openssl feature");
+                DtlsCert::new_boringssl()
             }
+            #[cfg(not(any(feature = "openssl", feature = "boringssl")))]
+            {
+                panic!("No DTLS implementation. Enable openssl or boringssl feature");
+            }
+        };
+
+        let dtls_options = DtlsOptions {
+            keylog: config.dtls_keylog,
+            fingerprint_hash: config.fingerprint_hash,
+            cipher_suites: config.dtls_cipher_suites,
+            groups: config.dtls_groups,
+            fips: config.fips_mode,
         };
 
-        Rtc {
+        // SRTP runs on the same crypto backend as DTLS.
+        session.set_dtls_srtp_crypto(dtls_cert.srtp_crypto());
+
+        let dtls = Dtls::new(dtls_cert, dtls_options)?;
+
+        Ok(Rtc {
             alive: true,
             ice,
-            dtls: Dtls::new(dtls_cert).expect("DTLS to init without problem"),
+            stun_gatherer: StunGatherer::new(stun_servers),
+            turn: turn_servers.into_iter().map(TurnClient::new).collect(),
+            dtls,
             session,
-            sctp: RtcSctp::new(),
+            sctp: RtcSctp::new(config.sctp_max_message_size, config.sctp_rto),
             chan: ChannelHandler::default(),
-            stats: config.stats_interval.map(Stats::new),
+            stats: config
+                .stats_interval
+                .map(|i| Stats::new(i, config.quality_scorer.clone())),
             remote_fingerprint: None,
             remote_addrs: vec![],
             send_addr: None,
+            latch: AddressLatch::new(config.address_latching),
             need_init_time: true,
             last_now: already_happened(),
             peer_bytes_rx: 0,
             peer_bytes_tx: 0,
+            max_transmit_batch: config.max_transmit_batch,
             change_counter: 0,
+            sdp_queue: SdpQueue::default(),
             last_timeout_reason: Reason::NotHappening,
-        }
+            activity: ActivityCounters::default(),
+            poll_budget: config.poll_budget,
+            poll_work: PollWork::default(),
+            rng,
+            dtls_rekey_interval: config.dtls_rekey_interval,
+            dtls_rekey_at: None,
+            #[cfg(feature = "alloc-stats")]
+            allocation_counter: config.allocation_counter,
+        })
     }
 
     /// Tests if this instance is still working.
@@ -1188,7 +1520,13 @@ impl Rtc {
     ///
     /// [1]: https://www.rfc-editor.org/rfc/rfc8838.txt
     pub fn add_local_candidate(&mut self, c: Candidate) {
-        self.ice.add_local_candidate(c);
+        let _rng = InstanceRng::enter(self.rng.as_ref());
+        let host = c.clone();
+        // Candidates rejected by the agent, e.g. above Limits::max_local_candidates,
+        // must not be used for gathering either.
+        if self.ice.add_local_candidate(c) {
+            self.stun_gatherer.add_local_candidate(&host);
+        }
     }
 
     /// Add a remote ICE candidate. Remote candidates are addresses of the peer.
@@ -1211,9 +1549,70 @@ impl Rtc {
     ///
     /// [1]: https://www.rfc-editor.org/rfc/rfc8838.txt
     pub fn add_remote_candidate(&mut self, c: Candidate) {
+        let _rng = InstanceRng::enter(self.rng.as_ref());
         self.ice.add_remote_candidate(c);
     }
 
+    /// Signal that all local candidates have been added.
+    ///
+    /// For [`SdpApi`]: Following offers and answers include `a=end-of-candidates`.
+    /// For trickle ice, the remote peer must be told by other means.
+    ///
+    /// With end-of-candidates from both sides, the ICE agent concludes
+    /// [`IceConnectionState::Failed`] when all candidate pairs failed, instead of
+    /// waiting for more candidates. An ICE restart clears this.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, Candidate};
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let a = "127.0.0.1:5000".parse().unwrap();
+    /// rtc.add_local_candidate(Candidate::host(a, "udp").unwrap());
+    ///
+    /// rtc.set_local_end_of_candidates();
+    /// ```
+    pub fn set_local_end_of_candidates(&mut self) {
+        self.ice.set_local_end_of_candidates();
+    }
+
+    /// Signal that the remote peer has no more candidates.
+    ///
+    /// For [`SdpApi`]: This is done when receiving `a=end-of-candidates` in a remote
+    /// [`SdpOffer`][change::SdpOffer] or [`SdpAnswer`][change::SdpAnswer].
+    ///
+    /// For [Trickle Ice][1], this is the equivalent of the end-of-candidates indication
+    /// from the other side. Remote candidates added after this are ignored, until an
+    /// ICE restart.
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc8838.txt
+    pub fn set_remote_end_of_candidates(&mut self) {
+        self.ice.set_remote_end_of_candidates();
+    }
+
+    /// Provide the ip of an mDNS hostname from [`Event::MdnsResolveRequest`].
+    ///
+    /// Remote candidates using the hostname are then used like any other. Resolutions
+    /// for hostnames that are not pending are ignored. Candidates that are not resolved
+    /// within 10 seconds are dropped, so they don't keep ICE from failing.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, Event, Output};
+    /// # use std::net::IpAddr;
+    /// # fn query_mdns(_hostname: &str) -> Option<IpAddr> { None }
+    /// let mut rtc = Rtc::new();
+    ///
+    /// if let Output::Event(Event::MdnsResolveRequest(hostname)) = rtc.poll_output().unwrap() {
+    ///     // Query multicast DNS for the hostname.
+    ///     if let Some(ip) = query_mdns(&hostname) {
+    ///         rtc.resolve_mdns(&hostname, ip);
+    ///     }
+    /// }
+    /// ```
+    pub fn resolve_mdns(&mut self, hostname: &str, ip: IpAddr) {
+        let _rng = InstanceRng::enter(self.rng.as_ref());
+        self.ice.resolve_mdns(hostname, ip);
+    }
+
     /// Checks if we are connected.
     ///
     /// This tests both if we have ICE connection and DTLS is ready.
@@ -1222,6 +1621,21 @@ impl Rtc {
         self.ice.state().is_connected() && self.dtls.is_connected()
     }
 
+    /// Our DTLS role, once it is resolved.
+    ///
+    /// With SDP, this is settled when the first OFFER/ANSWER exchange is applied. A remote
+    /// `a=setup:actpass` makes us the [`DtlsRole::Server`].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let rtc = Rtc::new();
+    ///
+    /// assert_eq!(rtc.dtls_role(), None);
+    /// ```
+    pub fn dtls_role(&self) -> Option<DtlsRole> {
+        self.dtls.role()
+    }
+
     /// Make changes to the Rtc session via SDP.
     ///
     /// ```no_run
@@ -1285,7 +1699,12 @@ impl Rtc {
             panic!("In rtp_mode use direct_api().stream_tx().write_rtp()");
         }
 
-        self.session.media_by_mid_mut(mid)?;
+        let media = self.session.media_by_mid_mut(mid)?;
+
+        // Data channel only, nothing is ever sent.
+        if media.is_disabled() {
+            return None;
+        }
 
         Some(Writer::new(&mut self.session, mid))
     }
@@ -1297,6 +1716,39 @@ impl Rtc {
         self.session.media_by_mid(mid)
     }
 
+    /// Replace the DTLS certificate of this instance.
+    ///
+    /// The new fingerprint is used in the next SDP offer or answer, and is what
+    /// [`DirectApi::local_dtls_fingerprint()`][crate::change::DirectApi::local_dtls_fingerprint]
+    /// returns from now on.
+    ///
+    /// The certificate can only be replaced before the DTLS handshake has started,
+    /// which happens when the first offer/answer is applied (or via the direct API).
+    /// It can also not be replaced while an offer created by [`Rtc::sdp_api()`] is
+    /// outstanding, since that offer carries the old fingerprint. In both cases this
+    /// returns [`DtlsError::CertificateInUse`][crate::error::DtlsError::CertificateInUse].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::change::DtlsCert;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let cert = DtlsCert::new_openssl();
+    /// let fingerprint = cert.fingerprint();
+    ///
+    /// rtc.set_dtls_cert(cert).unwrap();
+    /// assert_eq!(rtc.direct_api().local_dtls_fingerprint(), fingerprint);
+    /// ```
+    pub fn set_dtls_cert(&mut self, dtls_cert: DtlsCert) -> Result<(), RtcError> {
+        if self.sdp_queue.is_offer_outstanding(self) {
+            return Err(error::DtlsError::CertificateInUse.into());
+        }
+        let srtp_crypto = dtls_cert.srtp_crypto();
+        self.dtls.set_cert(dtls_cert)?;
+        self.session.set_dtls_srtp_crypto(srtp_crypto);
+        Ok(())
+    }
+
     fn init_dtls(&mut self, active: bool) -> Result<(), RtcError> {
         if self.dtls.is_inited() {
             return Ok(());
@@ -1347,21 +1799,43 @@ impl Rtc {
     /// 2. New network input.
     ///
     /// See [`Rtc`] instance documentation for how this is expected to be used in a loop.
+    ///
+    /// With a [`PollBudget`] configured, a timeout with [`Reason::PollAgain`] can be returned
+    /// while there is still output pending. The timeout is then the current time.
     pub fn poll_output(&mut self) -> Result<Output, RtcError> {
-        let o = self.do_poll_output()?;
+        let _rng = InstanceRng::enter(self.rng.as_ref());
+
+        if self.poll_work.exceeded(&self.poll_budget) {
+            self.poll_work = PollWork::default();
+            self.last_timeout_reason = Reason::PollAgain;
+            return Ok(Output::Timeout(self.last_now));
+        }
+
+        let allocations = self.allocation_count();
+        let o = self.do_poll_output();
+        self.count_allocations(allocations);
+        let o = o?;
 
         match &o {
-            Output::Event(e) => match e {
-                Event::ChannelData(_) | Event::MediaData(_) | Event::RtpPacket(_) => {
-                    trace!("{:?}", e)
+            Output::Event(e) => {
+                self.activity.events += 1;
+                self.poll_work.outputs += 1;
+                match e {
+                    Event::ChannelData(_) | Event::MediaData(_) | Event::RtpPacket(_) => {
+                        trace!("{:?}", e)
+                    }
+                    _ => debug!("{:?}", e),
                 }
-                _ => debug!("{:?}", e),
-            },
+            }
             Output::Transmit(t) => {
+                self.activity.packets_tx += 1;
+                self.poll_work.outputs += 1;
                 self.peer_bytes_tx += t.contents.len() as u64;
                 trace!("OUT {:?}", t)
             }
-            Output::Timeout(_t) => {}
+            Output::Timeout(_t) => {
+                self.poll_work = PollWork::default();
+            }
         }
 
         Ok(o)
@@ -1402,10 +1876,53 @@ impl Rtc {
                         source,
                         destination,
                     });
+                    self.latch.reset();
+                }
+                IceAgentEvent::MdnsResolveRequest(hostname) => {
+                    return Ok(Output::Event(Event::MdnsResolveRequest(hostname)));
+                }
+                IceAgentEvent::Consent(v) => return Ok(Output::Event(Event::IceConsent(v))),
+                IceAgentEvent::SelectedPair { local, remote } => {
+                    return Ok(Output::Event(Event::SelectedCandidatePairChange {
+                        local,
+                        remote,
+                    }));
+                }
+                IceAgentEvent::RoleChange { controlling } => {
+                    return Ok(Output::Event(Event::IceRoleChange { controlling }));
                 }
+                IceAgentEvent::PeerReflexive { candidate, local } => {
+                    return Ok(Output::Event(Event::PeerReflexiveCandidate {
+                        candidate,
+                        local,
+                    }));
+                }
+                IceAgentEvent::PeerReflexivePromoted { previous, current } => {
+                    return Ok(Output::Event(Event::PeerReflexivePromoted {
+                        previous,
+                        current,
+                    }));
+                }
+            }
+        }
+
+        if let Some((previous, current)) = self.latch.poll_event() {
+            return Ok(Output::Event(Event::RemoteAddressLatched {
+                previous,
+                current,
+            }));
+        }
+
+        while let Some(c) = self.stun_gatherer.poll_candidate() {
+            if self.ice.add_local_candidate(c.clone()) {
+                return Ok(Output::Event(Event::CandidateGathered(c)));
             }
         }
 
+        if let Some(o) = self.poll_turn()? {
+            return Ok(o);
+        }
+
         let mut dtls_connected = false;
 
         while let Some(e) = self.dtls.poll_event() {
@@ -1413,6 +1930,7 @@ impl Rtc {
                 DtlsEvent::Connected => {
                     debug!("DTLS connected");
                     dtls_connected = true;
+                    self.dtls_rekey_at = self.dtls_rekey_interval.map(|i| self.last_now + i);
                 }
                 DtlsEvent::SrtpKeyingMaterial(mat, srtp_profile) => {
                     info!(
@@ -1420,12 +1938,13 @@ impl Rtc {
                         srtp_profile
                     );
                     let active = self.dtls.is_active().expect("DTLS must be inited by now");
-                    self.session.set_keying_material(mat, srtp_profile, active);
+                    self.session
+                        .set_keying_material(self.last_now, mat, srtp_profile, active);
                 }
                 DtlsEvent::RemoteFingerprint(v1) => {
                     debug!("DTLS verify remote fingerprint");
                     if let Some(v2) = &self.remote_fingerprint {
-                        if v1 != *v2 {
+                        if !v1.iter().any(|v| v.matches(v2)) {
                             self.disconnect();
                             return Err(RtcError::RemoteSdp("remote fingerprint no match".into()));
                         }
@@ -1466,6 +1985,17 @@ impl Rtc {
                     }
                 }
                 SctpEvent::Open { id, label } => {
+                    let is_remote = self.chan.channel_id_by_stream_id(id).is_none();
+                    let count = self.chan.len() + 1;
+                    if is_remote && !self.session.limits.allows(Limit::Channels, count) {
+                        debug!("Refuse channel above limit: {}", id);
+                        self.sctp.close_stream(id);
+                        if let Some(w) = self.session.limits.refused(Limit::Channels) {
+                            warn!("{}", w);
+                            self.session.push_warning(w);
+                        }
+                        continue;
+                    }
                     self.chan.ensure_channel_id_for(id);
                     let id = self.chan.channel_id_by_stream_id(id).unwrap();
                     return Ok(Output::Event(Event::ChannelOpen(id, label)));
@@ -1473,22 +2003,53 @@ impl Rtc {
                 SctpEvent::Close { id } => {
                     let Some(id) = self.chan.channel_id_by_stream_id(id) else {
                         warn!("Drop ChannelClose event for id: {:?}", id);
+                        self.session.push_warning(Warning::ChannelEventDropped(id));
                         continue;
                     };
                     self.chan.remove_channel(id);
                     return Ok(Output::Event(Event::ChannelClose(id)));
                 }
+                SctpEvent::CloseReason { id, reason } => {
+                    let Some(id) = self.chan.channel_id_by_stream_id(id) else {
+                        warn!("Drop ChannelCloseReason event for id: {:?}", id);
+                        self.session.push_warning(Warning::ChannelEventDropped(id));
+                        continue;
+                    };
+                    return Ok(Output::Event(Event::ChannelCloseReason(id, reason)));
+                }
+                SctpEvent::BufferedAmountLow { id } => {
+                    let Some(id) = self.chan.channel_id_by_stream_id(id) else {
+                        warn!("Drop ChannelBufferedAmountLow event for id: {:?}", id);
+                        self.session.push_warning(Warning::ChannelEventDropped(id));
+                        continue;
+                    };
+                    return Ok(Output::Event(Event::ChannelBufferedAmountLow(id)));
+                }
                 SctpEvent::Data { id, binary, data } => {
                     let Some(id) = self.chan.channel_id_by_stream_id(id) else {
                         warn!("Drop ChannelData event for id: {:?}", id);
+                        self.session.push_warning(Warning::ChannelEventDropped(id));
                         continue;
                     };
                     let cd = ChannelData { id, binary, data };
                     return Ok(Output::Event(Event::ChannelData(cd)));
                 }
+                SctpEvent::AssociationLost { reason } => {
+                    return Ok(Output::Event(Event::SctpAssociationClosed(reason)));
+                }
+                SctpEvent::WriteFailed { id, error } => {
+                    let Some(id) = self.chan.channel_id_by_stream_id(id) else {
+                        warn!("Drop ChannelWriteFailed event for id: {:?}", id);
+                        self.session.push_warning(Warning::ChannelEventDropped(id));
+                        continue;
+                    };
+                    return Ok(Output::Event(Event::ChannelWriteFailed(id, error)));
+                }
             }
         }
 
+        self.session.update_limits(self.chan.len());
+
         if let Some(ev) = self.session.poll_event() {
             return Ok(Output::Event(ev));
         }
@@ -1503,37 +2064,52 @@ impl Rtc {
                 StatsEvent::Peer(s) => Output::Event(Event::PeerStats(s)),
                 StatsEvent::MediaIngress(s) => Output::Event(Event::MediaIngressStats(s)),
                 StatsEvent::MediaEgress(s) => Output::Event(Event::MediaEgressStats(s)),
+                StatsEvent::Channel(s) => Output::Event(Event::ChannelStats(s)),
+                StatsEvent::CandidatePair(s) => Output::Event(Event::CandidatePairStats(s)),
+                StatsEvent::Quality(s) => Output::Event(Event::QualityStats(s)),
             });
         }
 
         if let Some(v) = self.ice.poll_transmit() {
+            let Some(v) = self.relay(v) else {
+                return self.do_poll_output();
+            };
             return Ok(Output::Transmit(v));
         }
 
-        if let Some(send) = &self.send_addr {
-            // These can only be sent after we got an ICE connection.
-            let datagram = None
-                .or_else(|| self.dtls.poll_datagram())
-                .or_else(|| self.session.poll_datagram(self.last_now));
-
-            if let Some(contents) = datagram {
-                let t = net::Transmit {
-                    proto: send.proto,
-                    source: send.source,
-                    destination: send.destination,
-                    contents,
-                };
-                return Ok(Output::Transmit(t));
-            }
+        if let Some(v) = self.stun_gatherer.poll_transmit() {
+            return Ok(Output::Transmit(v));
+        }
+
+        if let Some(contents) = self.poll_datagram() {
+            // poll_datagram() only gives us something if there is a send_addr.
+            let send = self.send_addr.as_ref().unwrap();
+            self.ice
+                .count_sent(send.source, send.destination, contents.len());
+            let t = net::Transmit {
+                proto: send.proto,
+                source: send.source,
+                destination: send.destination,
+                contents,
+            };
+            let Some(t) = self.relay(t) else {
+                return self.do_poll_output();
+            };
+            return Ok(Output::Transmit(t));
         }
 
         let stats = self.stats.as_mut();
 
+        let turn_timeout = self.turn.iter().filter_map(|t| t.poll_timeout()).min();
+
         let time_and_reason = (None, Reason::NotHappening)
             .soonest((self.ice.poll_timeout(), Reason::Ice))
+            .soonest((self.stun_gatherer.poll_timeout(), Reason::Ice))
+            .soonest((turn_timeout, Reason::Ice))
             .soonest(self.session.poll_timeout())
             .soonest((self.sctp.poll_timeout(), Reason::Sctp))
             .soonest((self.chan.poll_timeout(&self.sctp), Reason::Channel))
+            .soonest((self.dtls_rekey_at, Reason::DtlsRekey))
             .soonest((stats.and_then(|s| s.poll_timeout()), Reason::Stats));
 
         // trace!("poll_output timeout reason: {}", time_and_reason.1);
@@ -1553,6 +2129,69 @@ impl Rtc {
         Ok(Output::Timeout(next))
     }
 
+    fn poll_datagram(&mut self) -> Option<net::DatagramSend> {
+        // These can only be sent after we got an ICE connection.
+        self.send_addr.as_ref()?;
+
+        let dtls_datagram = self.dtls.poll_datagram();
+
+        if let Some(v) = &dtls_datagram {
+            if v.len() > DATAGRAM_MTU_WARN {
+                warn!("DTLS above MTU {}: {}", DATAGRAM_MTU_WARN, v.len());
+                self.session.push_warning(Warning::DtlsAboveMtu(v.len()));
+            }
+        }
+
+        dtls_datagram.or_else(|| self.session.poll_datagram(self.last_now))
+    }
+
+    /// Extend an [`Output::Transmit`] into a batch of datagrams to the same destination.
+    ///
+    /// Call this with the transmit just obtained from [`Rtc::poll_output()`]. Any further
+    /// DTLS/SRTP datagrams that are ready for the same destination are appended, up to
+    /// [`RtcConfig::set_max_transmit_batch()`]. The batch can then be sent with a single
+    /// syscall like `sendmmsg`, before continuing to poll.
+    ///
+    /// ```no_run
+    /// # use str0m::{Rtc, Output};
+    /// # let mut rtc = Rtc::new();
+    /// if let Output::Transmit(t) = rtc.poll_output().unwrap() {
+    ///     let batch = rtc.batch_transmit(t);
+    ///     // send batch.contents from batch.source to batch.destination
+    /// }
+    /// ```
+    pub fn batch_transmit(&mut self, first: net::Transmit) -> net::TransmitBatch {
+        let mut batch = net::TransmitBatch {
+            proto: first.proto,
+            source: first.source,
+            destination: first.destination,
+            contents: vec![first.contents],
+        };
+
+        let same_destination = self.send_addr.as_ref().map(|s| {
+            s.proto == batch.proto && s.source == batch.source && s.destination == batch.destination
+        });
+
+        if !self.alive || same_destination != Some(true) {
+            return batch;
+        }
+
+        while batch.contents.len() < self.max_transmit_batch {
+            let Some(contents) = self.poll_datagram() else {
+                break;
+            };
+            self.activity.packets_tx += 1;
+            self.poll_work.outputs += 1;
+            self.peer_bytes_tx += contents.len() as u64;
+            self.ice
+                .count_sent(batch.source, batch.destination, contents.len());
+            trace!("OUT batched {:?}", contents.len());
+            batch.contents.push(contents);
+        }
+
+        batch
+    }
+
     /// The reason for the last [`Output::Timeout`]
     ///
     /// This is updated when calling [`Rtc::poll_output()`] and the next output
@@ -1586,6 +2225,8 @@ impl Rtc {
     ///
     /// In a server setup, the server would try to find an `Rtc` instances using [`Rtc::accepts()`].
     /// The first found instance would be given the input via [`Rtc::handle_input()`].
+    /// With many instances, [`net::classify_datagram()`] finds the local ufrag of a STUN
+    /// packet, which can be used to look up the instance directly.
     ///
     /// ```no_run
     /// # use str0m::{Rtc, Input};
@@ -1617,12 +2258,31 @@ impl Rtc {
             if r.source == send_addr.destination {
                 return true;
             }
+
+            // With address latching, the remote might show up from any address. Only
+            // authenticated SRTP/SRTCP will move the send address.
+            let is_media = matches!(
+                r.contents.inner,
+                DatagramRecvInner::Rtp(_) | DatagramRecvInner::Rtcp(_)
+            );
+            if self.latch.is_enabled()
+                && is_media
+                && r.proto == send_addr.proto
+                && r.destination == send_addr.source
+            {
+                return true;
+            }
+        }
+
+        // TURN messages are for the client talking to that server.
+        if let DatagramRecvInner::Turn(_) = &r.contents.inner {
+            return self.turn.iter().any(|t| t.accepts(r.source, r.destination));
         }
 
         // STUN can use the ufrag/password to identify that a message belongs
         // to this Rtc instance.
         if let DatagramRecvInner::Stun(v) = &r.contents.inner {
-            return self.ice.accepts_message(v);
+            return self.stun_gatherer.accepts_message(r.source, v) || self.ice.accepts_message(v);
         }
 
         // Slow path: Occasionally, traffic comes in on a socket address corresponding
@@ -1664,14 +2324,39 @@ impl Rtc {
             return Ok(());
         }
 
-        match input {
-            Input::Timeout(now) => self.do_handle_timeout(now)?,
+        let _rng = InstanceRng::enter(self.rng.as_ref());
+
+        let allocations = self.allocation_count();
+
+        let r = match input {
+            Input::Timeout(now) => self.do_handle_timeout(now),
             Input::Receive(now, r) => {
-                self.do_handle_receive(now, r)?;
-                self.do_handle_timeout(now)?;
+                self.activity.packets_rx += 1;
+                self.do_handle_receive(now, r)
+                    .and_then(|_| self.do_handle_timeout(now))
             }
-        }
-        Ok(())
+        };
+
+        self.count_allocations(allocations);
+
+        r
+    }
+
+    #[cfg(feature = "alloc-stats")]
+    fn allocation_count(&self) -> Option<u64> {
+        self.allocation_counter.map(|f| f())
+    }
+
+    #[cfg(not(feature = "alloc-stats"))]
+    fn allocation_count(&self) -> Option<u64> {
+        None
+    }
+
+    fn count_allocations(&mut self, start: Option<u64>) {
+        let (Some(start), Some(end)) = (start, self.allocation_count()) else {
+            return;
+        };
+        *self.activity.allocations.get_or_insert(0) += end.saturating_sub(start);
     }
 
     fn init_time(&mut self, now: Instant) {
@@ -1692,16 +2377,42 @@ impl Rtc {
 
         self.last_now = now;
         self.ice.handle_timeout(now);
+        self.stun_gatherer.handle_timeout(now);
+        for t in &mut self.turn {
+            t.handle_timeout(now);
+        }
         self.sctp.handle_timeout(now);
         self.chan.handle_timeout(now, &mut self.sctp);
         self.session.handle_timeout(now)?;
 
+        if let (Some(at), Some(interval)) = (self.dtls_rekey_at, self.dtls_rekey_interval) {
+            if now >= at {
+                self.dtls.rekey()?;
+                self.dtls_rekey_at = Some(now + interval);
+            }
+        }
+
         if let Some(stats) = &mut self.stats {
             if stats.wants_timeout(now) {
                 let mut snapshot = StatsSnapshot::new(now);
                 snapshot.peer_rx = self.peer_bytes_rx;
                 snapshot.peer_tx = self.peer_bytes_tx;
+                snapshot.activity = self.activity;
                 self.session.visit_stats(now, &mut snapshot);
+                self.ice.visit_stats(now, &mut snapshot);
+                for (stream_id, c) in self.sctp.stream_counters() {
+                    let Some(id) = self.chan.channel_id_by_stream_id(stream_id) else {
+                        continue;
+                    };
+                    snapshot.channels.push(ChannelStats {
+                        id,
+                        messages_sent: c.messages_sent,
+                        bytes_sent: c.bytes_sent,
+                        messages_received: c.messages_received,
+                        bytes_received: c.bytes_received,
+                        timestamp: now,
+                    });
+                }
                 stats.do_handle_timeout(&mut snapshot);
             }
         }
@@ -1719,11 +2430,17 @@ impl Rtc {
         let bytes_rx = match r.contents.inner {
             // TODO: stun is already parsed (depacketized) here
             Stun(_) => 0,
+            // Relayed data is counted when handled after unwrapping.
+            Turn(_) => 0,
             Dtls(v) | Rtp(v) | Rtcp(v) => v.len(),
         };
 
         self.peer_bytes_rx += bytes_rx as u64;
 
+        if bytes_rx > 0 {
+            self.ice.count_received(r.source, r.destination, bytes_rx);
+        }
+
         match r.contents.inner {
             Stun(stun) => {
                 let packet = io::StunPacket {
@@ -1732,45 +2449,249 @@ impl Rtc {
                     destination: r.destination,
                     message: stun,
                 };
-                self.ice.handle_packet(now, packet);
+                // Responses from STUN servers are for the gatherer, the rest is ICE.
+                if !self.stun_gatherer.handle_packet(&packet) {
+                    self.ice.handle_packet(now, packet);
+                }
             }
             Dtls(dtls) => self.dtls.handle_receive(dtls)?,
-            Rtp(rtp) => self.session.handle_rtp_receive(now, rtp),
-            Rtcp(rtcp) => self.session.handle_rtcp_receive(now, rtcp),
+            Rtp(rtp) => {
+                let before = self.session.rx_fresh();
+                self.session.handle_rtp_receive(now, rtp);
+                self.latch_if_authenticated(before, r.proto, r.source, r.destination);
+            }
+            Rtcp(rtcp) => {
+                let before = self.session.rx_fresh();
+                self.session.handle_rtcp_receive(now, rtcp);
+                self.latch_if_authenticated(before, r.proto, r.source, r.destination);
+            }
+            Turn(v) => {
+                let client = self
+                    .turn
+                    .iter_mut()
+                    .find(|t| t.accepts(r.source, r.destination));
+                if let Some(client) = client {
+                    if let Err(e) = client.handle_receive(now, v) {
+                        debug!("Failed to handle TURN message: {}", e);
+                    }
+                } else {
+                    trace!("Drop TURN message from unknown server: {}", r.source);
+                }
+            }
         }
 
         Ok(())
     }
 
-    /// Obtain handle for writing to a data channel.
-    ///
-    /// This is first available when a [`ChannelId`] is advertised via [`Event::ChannelOpen`].
-    /// The function returns `None` also for IDs from [`SdpApi::add_channel()`].
-    ///
-    /// Incoming channel data is via the [`Event::ChannelData`] event.
-    ///
-    /// ```no_run
-    /// # use str0m::{Rtc, channel::ChannelId};
-    /// let mut rtc = Rtc::new();
-    ///
-    /// let cid: ChannelId = todo!(); // obtain channel id from Event::ChannelOpen
-    /// let channel = rtc.channel(cid).unwrap();
-    /// // TODO write data channel data.
-    /// ```
-    pub fn channel(&mut self, id: ChannelId) -> Option<Channel<'_>> {
-        if !self.alive {
-            return None;
-        }
-
-        let sctp_stream_id = self.chan.stream_id_by_channel_id(id)?;
+    /// Poll the TURN clients. Data they relayed from peers is handled right away.
+    fn poll_turn(&mut self) -> Result<Option<Output>, RtcError> {
+        for i in 0..self.turn.len() {
+            // An ICE restart without keeping the local candidates clears out the relayed
+            // candidates of allocations that are still going.
+            if let Some(c) = self.turn[i].relayed_candidate() {
+                let known = self
+                    .ice
+                    .local_candidates()
+                    .iter()
+                    .any(|l| l.kind() == CandidateKind::Relayed && l.addr() == c.addr());
+                if !known && self.ice.add_local_candidate(c.clone()) {
+                    return Ok(Some(Output::Event(Event::CandidateGathered(c))));
+                }
+            }
 
-        if !self.sctp.is_open(sctp_stream_id) {
-            return None;
-        }
+            loop {
+                match self.turn[i].poll_output() {
+                    TurnOutput::Transmit(t) => return Ok(Some(Output::Transmit(t))),
+                    TurnOutput::Event(TurnEvent::Allocated(c)) => {
+                        if self.ice.add_local_candidate(c.clone()) {
+                            return Ok(Some(Output::Event(Event::CandidateGathered(c))));
+                        }
+                    }
+                    TurnOutput::Event(TurnEvent::Received {
+                        source,
+                        destination,
+                        contents,
+                    }) => {
+                        let proto = net::Protocol::Udp;
+                        match net::Receive::new(proto, source, destination, &contents) {
+                            Ok(r) => self.do_handle_receive(self.last_now, r)?,
+                            Err(e) => debug!("Drop relayed data from {}: {}", source, e),
+                        }
+                    }
+                    TurnOutput::Event(TurnEvent::Failed(e)) => {
+                        warn!(
+                            "TURN allocation on {} failed: {}",
+                            self.turn[i].config().server(),
+                            e
+                        );
+                        self.invalidate_relayed(i);
+                    }
+                    TurnOutput::Event(TurnEvent::Closed) => self.invalidate_relayed(i),
+                    TurnOutput::Event(_) => {}
+                    TurnOutput::Timeout(_) => break,
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Remove the relayed candidate of a TURN client that lost its allocation.
+    fn invalidate_relayed(&mut self, i: usize) {
+        let Some(addr) = self.turn[i].last_relayed_address() else {
+            return;
+        };
+
+        let candidate = self
+            .ice
+            .local_candidates()
+            .iter()
+            .find(|c| c.kind() == CandidateKind::Relayed && c.addr() == addr)
+            .cloned();
+
+        if let Some(c) = candidate {
+            self.ice.invalidate_candidate(&c);
+        }
+    }
+
+    /// Hand transmits from a relayed candidate to its TURN client.
+    ///
+    /// Returns the transmit if it is to be sent as is.
+    fn relay(&mut self, t: net::Transmit) -> Option<net::Transmit> {
+        let client = self
+            .turn
+            .iter_mut()
+            .find(|c| c.relayed_address() == Some(t.source));
+
+        let Some(client) = client else {
+            // A lost allocation can't be sent from.
+            if self
+                .turn
+                .iter()
+                .any(|c| c.last_relayed_address() == Some(t.source))
+            {
+                debug!("Drop transmit from lost TURN allocation: {}", t.source);
+                return None;
+            }
+            return Some(t);
+        };
+
+        if let Err(e) = client.send(self.last_now, t) {
+            debug!("Failed to relay transmit: {}", e);
+        }
+
+        None
+    }
+
+    fn latch_if_authenticated(
+        &mut self,
+        before: u64,
+        proto: net::Protocol,
+        source: SocketAddr,
+        destination: SocketAddr,
+    ) {
+        if self.session.rx_fresh() == before {
+            return;
+        }
+        if let Some(send_addr) = &mut self.send_addr {
+            self.latch
+                .handle_authenticated(send_addr, proto, source, destination);
+        }
+    }
+
+    /// Obtain handle for writing to a data channel.
+    ///
+    /// This is first available when a [`ChannelId`] is advertised via [`Event::ChannelOpen`].
+    /// The function returns `None` also for IDs from [`SdpApi::add_channel()`].
+    ///
+    /// Incoming channel data is via the [`Event::ChannelData`] event.
+    ///
+    /// ```no_run
+    /// # use str0m::{Rtc, channel::ChannelId};
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let cid: ChannelId = todo!(); // obtain channel id from Event::ChannelOpen
+    /// let channel = rtc.channel(cid).unwrap();
+    /// // TODO write data channel data.
+    /// ```
+    pub fn channel(&mut self, id: ChannelId) -> Option<Channel<'_>> {
+        if !self.alive {
+            return None;
+        }
+
+        let sctp_stream_id = self.chan.stream_id_by_channel_id(id)?;
+
+        if !self.sctp.is_open(sctp_stream_id) {
+            return None;
+        }
 
         Some(Channel::new(sctp_stream_id, self))
     }
 
+    /// SCTP parameters negotiated with the remote peer.
+    ///
+    /// Returns `None` until the SCTP association for data channels is established. Use
+    /// [`SctpParams::remote_max_message_size`]
+    /// to size the chunks written to a [`Channel`].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let rtc = Rtc::new();
+    ///
+    /// assert!(rtc.sctp_params().is_none());
+    /// ```
+    pub fn sctp_params(&self) -> Option<SctpParams> {
+        if !self.sctp.is_established() {
+            return None;
+        }
+
+        let (remote_port, remote_max) = self.session.remote_sctp?;
+
+        Some(SctpParams {
+            local_port: sctp::SCTP_PORT,
+            remote_port,
+            local_max_message_size: self.session.local_max_message_size,
+            remote_max_message_size: (remote_max > 0).then_some(remote_max),
+        })
+    }
+
+    /// Transport statistics of the SCTP association for data channels.
+    ///
+    /// Returns `None` until the SCTP association is established.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let mut rtc = Rtc::new();
+    ///
+    /// assert!(rtc.sctp_stats().is_none());
+    /// ```
+    pub fn sctp_stats(&mut self) -> Option<SctpStats> {
+        if !self.sctp.is_established() {
+            return None;
+        }
+
+        let counters = self.sctp.transport_counters();
+
+        let channels: Vec<_> = self
+            .sctp
+            .stream_buffered_amounts()
+            .into_iter()
+            .filter_map(|(stream_id, amount)| {
+                let id = self.chan.channel_id_by_stream_id(stream_id)?;
+                Some((id, amount))
+            })
+            .collect();
+
+        Some(SctpStats {
+            packets_sent: counters.packets_sent,
+            packets_received: counters.packets_received,
+            data_chunks_sent: counters.data_chunks_sent,
+            data_chunks_retransmitted: counters.data_chunks_retransmitted,
+            buffered_amount: channels.iter().map(|(_, a)| a).sum(),
+            channels,
+        })
+    }
+
     /// Configure the Bandwidth Estimate (BWE) subsystem.
     ///
     /// Only relevant if BWE was enabled in the [`RtcConfig::enable_bwe()`]
@@ -1825,6 +2746,43 @@ pub struct RtcConfig {
     send_buffer_video: usize,
     rtp_mode: bool,
     enable_raw_packets: bool,
+    cname: Option<String>,
+    rtcp_rsize: bool,
+    extmap_allow_mixed: bool,
+    rtcp_schedule: RtcpSchedule,
+    rtp_leniency: RtpLeniency,
+    send_backpressure: Option<Duration>,
+    congestion_window: Option<Duration>,
+    max_transmit_batch: usize,
+    roc_recovery: usize,
+    rtcp_pacing: Option<RtcpPacing>,
+    dtls_rekey_interval: Option<Duration>,
+    dtls_keylog: Option<DtlsKeyLog>,
+    dtls_cipher_suites: Option<Vec<DtlsCipherSuite>>,
+    dtls_groups: Option<Vec<DtlsGroup>>,
+    fingerprint_hash: FingerprintHash,
+    srtp_crypto: Option<Arc<dyn SrtpCrypto>>,
+    fips_mode: bool,
+    limits: Limits,
+    address_latching: bool,
+    ice_consent: IceConsentConfig,
+    ice_nomination: IceNomination,
+    stun_servers: Vec<SocketAddr>,
+    turn_servers: Vec<TurnConfig>,
+    initial_rtt: Option<Duration>,
+    rng_seed: Option<u64>,
+    ice_check_interval: Duration,
+    quality_scorer: Option<Arc<dyn QualityScorer>>,
+    ice_family_policy: IceFamilyPolicy,
+    #[cfg(feature = "alloc-stats")]
+    allocation_counter: Option<fn() -> u64>,
+    poll_budget: PollBudget,
+    sctp_max_message_size: usize,
+    sctp_rto: SctpRto,
+    data_channel_only: bool,
+    red_distance: usize,
+    flexfec_window: usize,
+    ulpfec_window: usize,
 }
 
 impl RtcConfig {
@@ -1958,6 +2916,43 @@ impl RtcConfig {
         self
     }
 
+    /// Enable G.711 µ-law (PCMU) audio codec on the static payload type 0.
+    ///
+    /// Enabled by default.
+    pub fn enable_pcmu(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_pcmu(enabled);
+        self
+    }
+
+    /// Enable G.711 A-law (PCMA) audio codec on the static payload type 8.
+    ///
+    /// Enabled by default.
+    pub fn enable_pcma(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_pcma(enabled);
+        self
+    }
+
+    /// Enable G.722 audio codec on the static payload type 9.
+    ///
+    /// Enabled by default.
+    pub fn enable_g722(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_g722(enabled);
+        self
+    }
+
+    /// Enable RED (RFC 2198) redundancy encoding for opus audio.
+    ///
+    /// When negotiated, outgoing opus frames carry copies of the previous
+    /// frames (see [`RtcConfig::set_red_distance()`]) and incoming RED packets
+    /// are unwrapped, recovering lost frames from the redundancy.
+    ///
+    /// RED is negotiated as its own payload type, like the video RED of
+    /// [`RtcConfig::enable_ulpfec()`]. Disabled by default.
+    pub fn enable_opus_red(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_opus_red(enabled);
+        self
+    }
+
     /// Enable VP8 video codec.
     ///
     /// Enabled by default.
@@ -1974,15 +2969,21 @@ impl RtcConfig {
         self
     }
 
-    // TODO: AV1 depacketizer/packetizer.
-    //
-    // /// Enable AV1 video codec.
-    // ///
-    // /// Enabled by default.
-    // pub fn enable_av1(mut self) -> Self {
-    //     self.codec_config.add_default_av1();
-    //     self
-    // }
+    /// Enable H265 video codec.
+    ///
+    /// Disabled by default.
+    pub fn enable_h265(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_h265(enabled);
+        self
+    }
+
+    /// Enable AV1 video codec.
+    ///
+    /// Disabled by default.
+    pub fn enable_av1(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_av1(enabled);
+        self
+    }
 
     /// Enable VP9 video codec.
     ///
@@ -1992,6 +2993,58 @@ impl RtcConfig {
         self
     }
 
+    /// Enable FlexFEC (forward error correction) for video.
+    ///
+    /// When negotiated, the sender adds FEC packets from which the receiver can
+    /// recover lost media packets without waiting for a resend. This uses the
+    /// flexfec-03 format of libWebRTC. Set how many packets each FEC packet
+    /// protects with [`RtcConfig::set_flexfec_window()`].
+    ///
+    /// Disabled by default.
+    pub fn enable_flexfec(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_flexfec(enabled);
+        self
+    }
+
+    /// Enable RED and ULPFEC (forward error correction) for video.
+    ///
+    /// This is the older FEC scheme offered by browsers, where the video and the
+    /// ULPFEC packets are wrapped in RED in the same SSRC. Received ULPFEC is used
+    /// to recover lost media packets. Sending is off unless a window is set with
+    /// [`RtcConfig::set_ulpfec_window()`].
+    ///
+    /// Disabled by default.
+    pub fn enable_ulpfec(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_ulpfec(enabled);
+        self
+    }
+
+    /// Enable comfort noise (RFC 3389) for 8kHz audio.
+    ///
+    /// During silence, a sender can send CN packets instead of the audio codec. These
+    /// arrive as [`MediaData`][crate::media::MediaData] with [`Codec::Cn`][crate::format::Codec::Cn].
+    /// Opus has its own DTX and doesn't use CN.
+    ///
+    /// Disabled by default.
+    pub fn enable_cn(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_cn(enabled);
+        self
+    }
+
+    /// Enable DTMF tones (RFC 4733 telephone events).
+    ///
+    /// This adds telephone-event at 48kHz for Opus, and at 8kHz for G.711/G.722. Use the
+    /// payload type with the same clock rate as the audio the tones are sent with.
+    ///
+    /// Send tones with [`Writer::write_dtmf()`][crate::media::Writer::write_dtmf]. Received
+    /// tones are [`Event::Dtmf`].
+    ///
+    /// Disabled by default.
+    pub fn enable_dtmf(mut self, enabled: bool) -> Self {
+        self.codec_config.enable_dtmf(enabled);
+        self
+    }
+
     /// Configure the RTP extension mappings.
     ///
     /// The default extension map is
@@ -2040,6 +3093,7 @@ impl RtcConfig {
     /// None turns off the stats events.
     ///
     /// This includes [`MediaEgressStats`], [`MediaIngressStats`], [`MediaEgressStats`]
+    /// and [`ChannelStats`]
     pub fn set_stats_interval(mut self, interval: Option<Duration>) -> Self {
         self.stats_interval = interval;
         self
@@ -2240,6 +3294,9 @@ impl RtcConfig {
 
     /// Enable the [`Event::RawPacket`] event.
     ///
+    /// Covers each sent and received RTP packet and parsed RTCP packet, as well as
+    /// every compound RTCP packet as a whole, including RTCP types str0m doesn't handle.
+    ///
     /// This clones data, and is therefore expensive.
     /// Should not be enabled outside of tests and troubleshooting.
     pub fn enable_raw_packets(mut self, enabled: bool) -> Self {
@@ -2247,97 +3304,1387 @@ impl RtcConfig {
         self
     }
 
-    /// Create a [`Rtc`] from the configuration.
-    pub fn build(self) -> Rtc {
-        Rtc::new_from_config(self)
+    /// Set a CNAME shared by all media in the session.
+    ///
+    /// By default every media gets its own CNAME. For [`SdpApi::add_media()`] this is the
+    /// track id, and for media created by the remote peer or the [`DirectApi`], it is a
+    /// random value.
+    ///
+    /// When set, this value is used for all outgoing RTCP SDES and `a=ssrc` cname lines,
+    /// and it stays the same across renegotiations and ICE restarts. Some receivers reset
+    /// their A/V sync state when the CNAME changes mid-call.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder()
+    ///     .set_cname(Some("my-endpoint".into()));
+    ///
+    /// assert_eq!(config.cname(), Some("my-endpoint"));
+    /// ```
+    pub fn set_cname(mut self, cname: Option<String>) -> Self {
+        self.cname = cname;
+        self
     }
-}
 
-impl Default for RtcConfig {
-    fn default() -> Self {
-        Self {
-            local_ice_credentials: None,
-            dtls_cert: None,
-            fingerprint_verification: true,
-            ice_lite: false,
-            codec_config: CodecConfig::new_with_defaults(),
-            exts: ExtensionMap::standard(),
-            stats_interval: None,
-            bwe_initial_bitrate: None,
-            reordering_size_audio: 15,
-            reordering_size_video: 30,
-            send_buffer_audio: 50,
-            send_buffer_video: 1000,
-            rtp_mode: false,
-            enable_raw_packets: false,
-        }
+    /// The CNAME shared by all media, if set.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to None.
+    /// assert_eq!(config.cname(), None);
+    /// ```
+    pub fn cname(&self) -> Option<&str> {
+        self.cname.as_deref()
     }
-}
 
-impl PartialEq for Event {
-    fn eq(&self, other: &Self) -> bool {
-        match (self, other) {
-            (Self::IceConnectionStateChange(l0), Self::IceConnectionStateChange(r0)) => l0 == r0,
-            (Self::MediaAdded(m0), Self::MediaAdded(m1)) => m0 == m1,
-            (Self::MediaData(m1), Self::MediaData(m2)) => m1 == m2,
-            (Self::ChannelOpen(l0, l1), Self::ChannelOpen(r0, r1)) => l0 == r0 && l1 == r1,
-            (Self::ChannelData(l0), Self::ChannelData(r0)) => l0 == r0,
-            (Self::ChannelClose(l0), Self::ChannelClose(r0)) => l0 == r0,
-            _ => false,
-        }
+    /// Offer and accept reduced-size RTCP (RFC 5506).
+    ///
+    /// When enabled, `a=rtcp-rsize` is included in the SDP. If the remote peer also
+    /// signals it, RTCP feedback such as NACK, PLI and TWCC is sent in non-compound
+    /// packets. Otherwise every RTCP packet is a compound packet starting with a SR/RR.
+    ///
+    /// Incoming non-compound packets are always accepted.
+    ///
+    /// For the [`DirectApi`] there is no negotiation, and this alone decides whether
+    /// non-compound packets are sent.
+    pub fn set_rtcp_rsize(mut self, enabled: bool) -> Self {
+        self.rtcp_rsize = enabled;
+        self
     }
-}
-
-impl Eq for Event {}
 
-impl fmt::Debug for Rtc {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.debug_struct("Rtc").finish()
+    /// Checks if reduced-size RTCP is offered/accepted.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to true.
+    /// assert_eq!(config.rtcp_rsize(), true);
+    /// ```
+    pub fn rtcp_rsize(&self) -> bool {
+        self.rtcp_rsize
     }
-}
-
-/// Log a CSV like stat to stdout.
-///
-/// ```ignore
-/// log_stat!("MY_STAT", 1, "hello", 3);
-/// ```
-///
-/// will result in the following being printed
-///
-/// ```text
-/// MY_STAT 1, hello, 3, {unix_timestamp_ms}
-/// ````
-///
-/// These logs can be easily grepped for, parsed and graphed, or otherwise analyzed.
-///
-/// This macro turns into a NO-OP if the `_internal_dont_use_log_stats` feature is not enabled
-macro_rules! log_stat {
-    ($name:expr, $($arg:expr),+) => {
-        #[cfg(feature = "_internal_dont_use_log_stats")]
-        {
-            use std::time::SystemTime;
-            use std::io::{self, Write};
 
-            let now = SystemTime::now();
-            let since_epoch = now.duration_since(SystemTime::UNIX_EPOCH).unwrap();
-            let unix_time_ms = since_epoch.as_millis();
-            let mut lock = io::stdout().lock();
-            write!(lock, "{} ", $name).expect("Failed to write to stdout");
+    /// Offer and accept the two-byte RTP header extension form (RFC 8285).
+    ///
+    /// When enabled, `a=extmap-allow-mixed` is included in the SDP. The two-byte form is
+    /// needed for extension ids above 14 and for values larger than 16 bytes. It is only
+    /// sent if the remote peer also signals `a=extmap-allow-mixed`, otherwise such
+    /// extensions are left out.
+    ///
+    /// For the [`DirectApi`] there is no negotiation, and this alone decides whether the
+    /// two-byte form is sent.
+    pub fn set_extmap_allow_mixed(mut self, enabled: bool) -> Self {
+        self.extmap_allow_mixed = enabled;
+        self
+    }
 
-            $(
-                write!(lock, "{},", $arg).expect("Failed to write to stdout");
-            )+
-            writeln!(lock, "{}", unix_time_ms).expect("Failed to write to stdout");
-        }
-    };
-}
-pub(crate) use log_stat;
+    /// Checks if the two-byte RTP header extension form is offered/accepted.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to true.
+    /// assert_eq!(config.extmap_allow_mixed(), true);
+    /// ```
+    pub fn extmap_allow_mixed(&self) -> bool {
+        self.extmap_allow_mixed
+    }
 
-#[cfg(test)]
-mod test {
-    use std::panic::UnwindSafe;
+    /// Set the scheduling of regular RTCP reports (SR/RR).
+    ///
+    /// See [`RtcpSchedule`][crate::rtp::RtcpSchedule] for how the session bandwidth
+    /// is used to limit report traffic in sessions with many streams.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::rtp::RtcpSchedule;
+    /// # use str0m::bwe::Bitrate;
+    /// let config = Rtc::builder().set_rtcp_schedule(RtcpSchedule {
+    ///     session_bandwidth: Some(Bitrate::mbps(10)),
+    ///     bandwidth_fraction: 0.025,
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn set_rtcp_schedule(mut self, schedule: RtcpSchedule) -> Self {
+        self.rtcp_schedule = schedule;
+        self
+    }
+
+    /// The scheduling of regular RTCP reports.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to fixed 1s (video) and 5s (audio) intervals.
+    /// let schedule = config.rtcp_schedule();
+    /// assert_eq!(schedule.interval_video, Duration::from_secs(1));
+    /// assert_eq!(schedule.interval_audio, Duration::from_secs(5));
+    /// assert_eq!(schedule.session_bandwidth, None);
+    /// ```
+    pub fn rtcp_schedule(&self) -> RtcpSchedule {
+        self.rtcp_schedule
+    }
+
+    /// Tolerate some kinds of invalid incoming RTP.
+    ///
+    /// Some devices, like IP cameras, produce RTP that is technically invalid, but still
+    /// decodable. By default such packets are dropped. Violations are counted in
+    /// [`PeerStats::rtp_leniency`][crate::stats::PeerStats::rtp_leniency] regardless
+    /// of this setting.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::rtp::RtpLeniency;
+    /// let config = Rtc::builder().set_rtp_leniency(RtpLeniency {
+    ///     padding_violation: true,
+    ///     extension_overrun: true,
+    /// });
+    /// ```
+    pub fn set_rtp_leniency(mut self, leniency: RtpLeniency) -> Self {
+        self.rtp_leniency = leniency;
+        self
+    }
+
+    /// Which kinds of invalid incoming RTP are tolerated.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::rtp::RtpLeniency;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to strict parsing.
+    /// assert_eq!(config.rtp_leniency(), RtpLeniency::default());
+    /// ```
+    pub fn rtp_leniency(&self) -> RtpLeniency {
+        self.rtp_leniency
+    }
+
+    /// Signal backpressure when media writes outpace what can be sent.
+    ///
+    /// When the estimated time to send what is queued for a media goes above this
+    /// threshold, [`Event::SendBackpressure`] is emitted and
+    /// [`Writer::is_backpressured()`] returns `true`. The estimate is based on the
+    /// oldest unsent packet, and the queue size relative to the BWE target (if
+    /// enabled). The backpressure ends once the queue time falls below half the threshold.
+    ///
+    /// This lets an application throttle its encoder instead of queuing seconds of video.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder()
+    ///     .enable_bwe(Some(str0m::bwe::Bitrate::kbps(300)))
+    ///     .set_send_backpressure(Some(Duration::from_millis(500)));
+    /// ```
+    pub fn set_send_backpressure(mut self, threshold: Option<Duration>) -> Self {
+        self.send_backpressure = threshold;
+        self
+    }
+
+    /// The send queue time above which media is backpressured.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to None, which disables backpressure signalling.
+    /// assert_eq!(config.send_backpressure(), None);
+    /// ```
+    pub fn send_backpressure(&self) -> Option<Duration> {
+        self.send_backpressure
+    }
+
+    /// Cap the amount of data in flight, based on TWCC feedback.
+    ///
+    /// When enabled, the congestion window is the BWE estimate times the RTT plus this
+    /// additional time. Once the bytes sent without TWCC feedback exceed the window, the
+    /// pacer holds back media (apart from one packet every 500ms) until feedback resumes.
+    /// This makes sending stall gracefully on a dead link instead of continuing at full rate.
+    ///
+    /// Unpaced media (audio by default) is not held back. Requires [`RtcConfig::enable_bwe()`].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder()
+    ///     .enable_bwe(Some(str0m::bwe::Bitrate::kbps(300)))
+    ///     .set_congestion_window(Some(Duration::from_millis(100)));
+    /// ```
+    pub fn set_congestion_window(mut self, additional: Option<Duration>) -> Self {
+        self.congestion_window = additional;
+        self
+    }
+
+    /// The time added to the RTT for the congestion window.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to None, which means no cap on data in flight.
+    /// assert_eq!(config.congestion_window(), None);
+    /// ```
+    pub fn congestion_window(&self) -> Option<Duration> {
+        self.congestion_window
+    }
+
+    /// Max number of datagrams in a [`net::TransmitBatch`].
+    ///
+    /// See [`Rtc::batch_transmit()`]. Larger batches mean fewer syscalls when the I/O
+    /// layer uses something like `sendmmsg`, but also hold back other output for longer.
+    /// Values below 1 are raised to 1, which turns batching off.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder()
+    ///     .set_max_transmit_batch(32);
+    /// ```
+    pub fn set_max_transmit_batch(mut self, max: usize) -> Self {
+        self.max_transmit_batch = max.max(1);
+        self
+    }
+
+    /// Max number of datagrams in a [`net::TransmitBatch`].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 16.
+    /// assert_eq!(config.max_transmit_batch(), 16);
+    /// ```
+    pub fn max_transmit_batch(&self) -> usize {
+        self.max_transmit_batch
+    }
+
+    /// Recover from a desynced SRTP rollover counter (ROC).
+    ///
+    /// After long gaps in reception, for instance when a mobile app has been in the
+    /// background, the sender might have wrapped the RTP sequence number more times
+    /// than the receiver can infer. All subsequent packets then fail SRTP authentication.
+    ///
+    /// When set above 0, a packet failing authentication is retried with up to this
+    /// many ROC values on either side of the inferred one. On success the stream is
+    /// resynced to the new ROC. Outcomes are counted in
+    /// [`PeerStats::roc_recovery`][crate::stats::PeerStats::roc_recovery].
+    ///
+    /// Each attempt is a full SRTP authentication, so keep this small.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder().set_roc_recovery(2);
+    /// ```
+    pub fn set_roc_recovery(mut self, max_attempts: usize) -> Self {
+        self.roc_recovery = max_attempts;
+        self
+    }
+
+    /// Max number of adjacent ROC values tried on SRTP authentication failure.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to off.
+    /// assert_eq!(config.roc_recovery(), 0);
+    /// ```
+    pub fn roc_recovery(&self) -> usize {
+        self.roc_recovery
+    }
+
+    /// Pace outgoing RTCP feedback.
+    ///
+    /// By default, feedback is sent as soon as it is generated. After burst loss across
+    /// many streams, that can be a large burst of NACK/TWCC datagrams. With pacing,
+    /// feedback is compounded up to the MTU and released under a size budget.
+    ///
+    /// panics if the [rate][RtcpPacing::rate] is zero.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::rtp::RtcpPacing;
+    /// let config = Rtc::builder().set_rtcp_pacing(Some(RtcpPacing::default()));
+    /// ```
+    pub fn set_rtcp_pacing(mut self, pacing: Option<RtcpPacing>) -> Self {
+        if let Some(pacing) = &pacing {
+            assert!(
+                pacing.rate.as_f64() > 0.0,
+                "RTCP pacing rate must be above zero"
+            );
+        }
+        self.rtcp_pacing = pacing;
+        self
+    }
+
+    /// The pacing of outgoing RTCP feedback, if any.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to no pacing.
+    /// assert_eq!(config.rtcp_pacing(), None);
+    /// ```
+    pub fn rtcp_pacing(&self) -> Option<RtcpPacing> {
+        self.rtcp_pacing
+    }
+
+    /// Periodically re-handshake DTLS to get fresh SRTP keys.
+    ///
+    /// Long-lived sessions can re-key this way. The first re-key happens this long after
+    /// DTLS connected. Both sides switch to the new SRTP keys as the handshake completes,
+    /// and packets under the previous keys are still accepted for a short while.
+    ///
+    /// The remote peer must accept DTLS renegotiation, which rules out browsers and
+    /// BoringSSL. Only the OpenSSL backend can start a re-key.
+    ///
+    /// Defaults to `None`, no re-keying.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use std::time::Duration;
+    /// let config = RtcConfig::new()
+    ///     .set_dtls_rekey_interval(Some(Duration::from_secs(3600)));
+    /// ```
+    pub fn set_dtls_rekey_interval(mut self, interval: Option<Duration>) -> Self {
+        self.dtls_rekey_interval = interval;
+        self
+    }
+
+    /// The interval of periodic DTLS re-keying, if any.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// assert_eq!(config.dtls_rekey_interval(), None);
+    /// ```
+    pub fn dtls_rekey_interval(&self) -> Option<Duration> {
+        self.dtls_rekey_interval
+    }
+
+    /// Set a callback receiving NSS key log lines during the DTLS handshake.
+    ///
+    /// Meant for debugging, it lets Wireshark decrypt captured DTLS traffic.
+    /// See [`DtlsKeyLog`] for details.
+    ///
+    /// Defaults to `None`.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use str0m::change::DtlsKeyLog;
+    /// let config = RtcConfig::new()
+    ///     .set_dtls_keylog(Some(DtlsKeyLog::new(|line| println!("{}", line))));
+    /// ```
+    pub fn set_dtls_keylog(mut self, keylog: Option<DtlsKeyLog>) -> Self {
+        self.dtls_keylog = keylog;
+        self
+    }
+
+    /// The DTLS key log callback, if set.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// assert!(config.dtls_keylog().is_none());
+    /// ```
+    pub fn dtls_keylog(&self) -> Option<&DtlsKeyLog> {
+        self.dtls_keylog.as_ref()
+    }
+
+    /// The allowed DTLS cipher suites, if restricted.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// assert!(config.dtls_cipher_suites().is_none());
+    /// ```
+    pub fn dtls_cipher_suites(&self) -> Option<&[DtlsCipherSuite]> {
+        self.dtls_cipher_suites.as_deref()
+    }
+
+    /// Restrict the DTLS cipher suites offered and accepted in the handshake.
+    ///
+    /// This applies to whichever crypto backend the [`DtlsCert`] belongs to. The
+    /// suites must be compatible with the certificate key type, the generated
+    /// certificates use ECDSA. A list that leaves no usable suite makes the
+    /// handshake fail, and an empty list makes [`RtcConfig::try_build`] fail.
+    ///
+    /// Defaults to `None`, which is the backend's own selection of ECDHE with AES-GCM.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use str0m::change::DtlsCipherSuite;
+    /// let config = RtcConfig::new()
+    ///     .set_dtls_cipher_suites(Some(vec![DtlsCipherSuite::EcdheEcdsaAes256GcmSha384]));
+    /// ```
+    pub fn set_dtls_cipher_suites(mut self, suites: Option<Vec<DtlsCipherSuite>>) -> Self {
+        self.dtls_cipher_suites = suites;
+        self
+    }
+
+    /// The allowed DTLS key exchange groups, if restricted.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// assert!(config.dtls_groups().is_none());
+    /// ```
+    pub fn dtls_groups(&self) -> Option<&[DtlsGroup]> {
+        self.dtls_groups.as_deref()
+    }
+
+    /// Restrict the elliptic curve groups used for the DTLS key exchange.
+    ///
+    /// Listed in order of preference. As with the cipher suites, an empty list makes
+    /// [`RtcConfig::try_build`] fail.
+    ///
+    /// Defaults to `None`, which is P-256 only, the curve every browser supports.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use str0m::change::DtlsGroup;
+    /// let config = RtcConfig::new()
+    ///     .set_dtls_groups(Some(vec![DtlsGroup::P384, DtlsGroup::P256]));
+    /// ```
+    pub fn set_dtls_groups(mut self, groups: Option<Vec<DtlsGroup>>) -> Self {
+        self.dtls_groups = groups;
+        self
+    }
+
+    /// The hash function used for the local DTLS fingerprint.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use str0m::change::FingerprintHash;
+    /// let config = RtcConfig::new();
+    ///
+    /// assert_eq!(config.fingerprint_hash(), FingerprintHash::Sha256);
+    /// ```
+    pub fn fingerprint_hash(&self) -> FingerprintHash {
+        self.fingerprint_hash
+    }
+
+    /// Set the hash function used for the local DTLS fingerprint.
+    ///
+    /// This is the `a=fingerprint` we put in the SDP. Remote fingerprints are verified
+    /// with whichever of the supported hash functions the remote peer used.
+    ///
+    /// Defaults to [`FingerprintHash::Sha256`], which all WebRTC implementations
+    /// support.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use str0m::change::FingerprintHash;
+    /// let mut rtc = RtcConfig::new()
+    ///     .set_fingerprint_hash(FingerprintHash::Sha512)
+    ///     .build();
+    ///
+    /// let fingerprint = rtc.direct_api().local_dtls_fingerprint();
+    /// assert_eq!(fingerprint.hash_func, "sha-512");
+    /// ```
+    pub fn set_fingerprint_hash(mut self, hash: FingerprintHash) -> Self {
+        self.fingerprint_hash = hash;
+        self
+    }
+
+    /// The custom SRTP ciphers, if set.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// assert!(config.srtp_crypto().is_none());
+    /// ```
+    pub fn srtp_crypto(&self) -> Option<&Arc<dyn SrtpCrypto>> {
+        self.srtp_crypto.as_ref()
+    }
+
+    /// Use custom ciphers for SRTP encryption and decryption.
+    ///
+    /// This replaces only the SRTP ciphers, DTLS still runs on the crypto backend
+    /// enabled by feature flags. See [`SrtpCrypto`] for what must be provided.
+    ///
+    /// Defaults to `None`, which uses the ciphers of the DTLS certificate's crypto backend.
+    pub fn set_srtp_crypto(mut self, crypto: Option<Arc<dyn SrtpCrypto>>) -> Self {
+        self.srtp_crypto = crypto;
+        self
+    }
+
+    /// Whether FIPS mode is required.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// assert!(!config.fips_mode());
+    /// ```
+    pub fn fips_mode(&self) -> bool {
+        self.fips_mode
+    }
+
+    /// Require FIPS mode for DTLS and SRTP.
+    ///
+    /// Restricts the negotiable DTLS cipher suites, key exchange groups and SRTP
+    /// profiles to FIPS approved algorithms. Anything set with
+    /// [`RtcConfig::set_dtls_cipher_suites`] and [`RtcConfig::set_dtls_groups`] is
+    /// filtered down to that subset. SRTP is limited to AEAD_AES_128_GCM, since the
+    /// HMAC of AES128_CM_SHA1_80 isn't computed by the crypto backend.
+    ///
+    /// The crypto backend itself must also operate in FIPS mode, otherwise
+    /// [`RtcConfig::try_build`] fails with
+    /// [`DtlsError::FipsNotEnabled`][crate::error::DtlsError::FipsNotEnabled]. Use
+    /// [`DtlsCert::is_fips_mode`] to check up front.
+    /// A custom [`RtcConfig::set_srtp_crypto`] is assumed to be compliant. The pure Rust
+    /// ciphers of the `rust-crypto-srtp` feature are not, and also fail `try_build`.
+    ///
+    /// Defaults to `false`.
+    pub fn set_fips_mode(mut self, enabled: bool) -> Self {
+        self.fips_mode = enabled;
+        self
+    }
+
+    /// Cap the number of mids, streams, data channels and ICE candidates.
+    ///
+    /// See [`Limits`] for how the limits are enforced.
+    ///
+    /// ```
+    /// # use str0m::{Limits, Rtc};
+    /// let config = Rtc::builder().set_limits(Limits {
+    ///     max_mids: Some(16),
+    ///     max_ssrcs: Some(64),
+    ///     max_channels: Some(8),
+    ///     max_pairs_per_remote_candidate: Some(2),
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn set_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The configured resource limits.
+    ///
+    /// ```
+    /// # use str0m::{Limits, Rtc};
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to no limits.
+    /// assert_eq!(config.limits(), Limits::default());
+    /// ```
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Toggle address latching for the remote media address.
+    ///
+    /// Some peers, typically legacy gateways or ICE lite endpoints behind NATs that
+    /// rewrite ports, send their media from a different address than the one we
+    /// nominated. With latching enabled, the address we send to is updated to the source
+    /// of the incoming SRTP/SRTCP. Only packets that authenticate against the negotiated
+    /// SRTP keys and are not replays of earlier packets are considered, and only over UDP
+    /// on the same local socket as the nominated pair.
+    ///
+    /// The first authenticated packet latches straight away. Subsequent changes require
+    /// a few consecutive authenticated packets from the new address. Each change is
+    /// reported as [`Event::RemoteAddressLatched`]. A new ICE nomination resets the latch.
+    ///
+    /// Note that with latching, [`Rtc::accepts()`] accepts all RTP/RTCP arriving on the
+    /// nominated local socket, since the new remote address isn't known up front.
+    ///
+    /// Defaults to `false`.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new()
+    ///     .set_address_latching(true);
+    /// ```
+    pub fn set_address_latching(mut self, enabled: bool) -> Self {
+        self.address_latching = enabled;
+        self
+    }
+
+    /// Whether address latching is enabled.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to false.
+    /// assert!(!config.address_latching());
+    /// ```
+    pub fn address_latching(&self) -> bool {
+        self.address_latching
+    }
+
+    /// Set consent freshness settings for ICE, see [RFC 7675][1].
+    ///
+    /// Consent is checked on the nominated pair, and changes are reported as
+    /// [`Event::IceConsent`].
+    ///
+    /// These are also the ICE timers for deployments to tune: `interval` is the STUN
+    /// keepalive interval on working pairs, and `timeout` the time without answers before
+    /// a pair fails, which leads to [`IceConnectionState::Disconnected`] when it was the
+    /// last one. Mobile clients typically want a longer timeout than servers in a datacenter.
+    ///
+    /// ```
+    /// # use str0m::{RtcConfig, IceConsentConfig};
+    /// # use std::time::Duration;
+    /// let config = RtcConfig::new().set_ice_consent(IceConsentConfig {
+    ///     interval: Some(Duration::from_secs(5)),
+    ///     timeout: Some(Duration::from_secs(30)),
+    /// });
+    /// ```
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc7675
+    pub fn set_ice_consent(mut self, consent: IceConsentConfig) -> Self {
+        self.ice_consent = consent;
+        self
+    }
+
+    /// Consent freshness settings for ICE.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to the regular cadence of STUN binding requests.
+    /// assert_eq!(config.ice_consent().interval, None);
+    /// assert_eq!(config.ice_consent().timeout, None);
+    /// ```
+    pub fn ice_consent(&self) -> IceConsentConfig {
+        self.ice_consent
+    }
+
+    /// Set how to nominate candidate pairs when we are the controlling ICE agent.
+    ///
+    /// Defaults to [`IceNomination::Renomination`], which switches to a better pair when
+    /// one succeeds after the connection is established.
+    ///
+    /// ```
+    /// # use str0m::{RtcConfig, IceNomination};
+    /// let config = RtcConfig::new().set_ice_nomination(IceNomination::Regular);
+    /// ```
+    pub fn set_ice_nomination(mut self, nomination: IceNomination) -> Self {
+        self.ice_nomination = nomination;
+        self
+    }
+
+    /// How candidate pairs are nominated when controlling.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, IceNomination};
+    /// let config = Rtc::builder();
+    ///
+    /// assert_eq!(config.ice_nomination(), IceNomination::Renomination);
+    /// ```
+    pub fn ice_nomination(&self) -> IceNomination {
+        self.ice_nomination
+    }
+
+    /// Set STUN servers used to gather server reflexive candidates.
+    ///
+    /// For every local UDP host candidate, a STUN binding request is sent to each server
+    /// of the same IP family via [`Output::Transmit`]. Discovered addresses are added as
+    /// local candidates and reported as [`Event::CandidateGathered`].
+    ///
+    /// Defaults to no servers, i.e. no gathering. Ignored in ice-lite mode.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let server = "1.2.3.4:3478".parse().unwrap();
+    /// let config = RtcConfig::new().set_stun_servers(vec![server]);
+    /// ```
+    pub fn set_stun_servers(mut self, servers: Vec<SocketAddr>) -> Self {
+        self.stun_servers = servers;
+        self
+    }
+
+    /// STUN servers used to gather server reflexive candidates.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// assert!(config.stun_servers().is_empty());
+    /// ```
+    pub fn stun_servers(&self) -> &[SocketAddr] {
+        &self.stun_servers
+    }
+
+    /// Set TURN servers used to allocate relayed candidates.
+    ///
+    /// An allocation is made on each server from the local socket given in the
+    /// [`TurnConfig`]. The relayed candidates are added as local candidates and reported
+    /// as [`Event::CandidateGathered`]. Data to and from peers over a relayed candidate
+    /// goes through the TURN server, which is taken care of by the `Rtc`. The messages
+    /// to the server are sent via [`Output::Transmit`], and what the server sends back
+    /// is passed in as [`Input::Receive`] like any other data.
+    ///
+    /// Over [`Protocol::Tcp`][net::Protocol::Tcp] and [`Protocol::Tls`][net::Protocol::Tls],
+    /// each [`Input::Receive`] from the server must start at a message boundary.
+    ///
+    /// Defaults to no servers. Ignored in ice-lite mode.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use str0m::net::Protocol;
+    /// # use str0m::turn::TurnConfig;
+    /// let local = "10.0.0.1:5000".parse().unwrap();
+    /// let server = "192.0.2.1:3478".parse().unwrap();
+    /// let turn = TurnConfig::new(Protocol::Udp, local, server, "user", "secret");
+    ///
+    /// let config = RtcConfig::new().set_turn_servers(vec![turn]);
+    /// ```
+    pub fn set_turn_servers(mut self, servers: Vec<TurnConfig>) -> Self {
+        self.turn_servers = servers;
+        self
+    }
+
+    /// TURN servers used to allocate relayed candidates.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// assert!(config.turn_servers().is_empty());
+    /// ```
+    pub fn turn_servers(&self) -> &[TurnConfig] {
+        &self.turn_servers
+    }
+
+    /// Set an initial round trip time estimate.
+    ///
+    /// Before a received stream has its own RTT measurement from RTCP reports, this
+    /// estimate is used to pace its NACK retries and keyframe requests. It could come from
+    /// a previous session with the same peer, or the RTT to a TURN server. Once the stream
+    /// has measured the RTT, the estimate is not used for it.
+    ///
+    /// Defaults to `None`, which means no pacing until the RTT is measured.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new().set_initial_rtt(Some(Duration::from_millis(80)));
+    /// ```
+    pub fn set_initial_rtt(mut self, rtt: Option<Duration>) -> Self {
+        self.initial_rtt = rtt;
+        self
+    }
+
+    /// The initial round trip time estimate.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// assert_eq!(config.initial_rtt(), None);
+    /// ```
+    pub fn initial_rtt(&self) -> Option<Duration> {
+        self.initial_rtt
+    }
+
+    /// Seed the generation of random identifiers, such as SSRC, mid and ICE credentials.
+    ///
+    /// Makes integration tests and fuzz reproductions produce the same identifiers from
+    /// run to run. The seeded generator is owned by the [`Rtc`] instance, other instances
+    /// and the generator of the thread are not affected. DTLS certificates are not affected
+    /// either, use [`RtcConfig::set_dtls_cert()`] for those.
+    ///
+    /// Predictable ICE credentials are not secure. Don't use this in production.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new().set_rng_seed(42);
+    /// ```
+    pub fn set_rng_seed(mut self, seed: u64) -> Self {
+        self.rng_seed = Some(seed);
+        self
+    }
+
+    /// The seed for random identifiers, if set.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// assert_eq!(config.rng_seed(), None);
+    /// ```
+    pub fn rng_seed(&self) -> Option<u64> {
+        self.rng_seed
+    }
+
+    /// Set the pacing of ICE connectivity checks.
+    ///
+    /// This is Ta in [RFC 8445][1], the minimum time between two STUN binding requests
+    /// for new candidate pairs. Lower values connect faster when there are many pairs,
+    /// at the cost of bursts of STUN traffic.
+    ///
+    /// The interval also paces the STUN traffic in general, so RFC 8445 forbids values
+    /// below 5ms. Lower values are raised to 5ms.
+    ///
+    /// For the keepalive interval and the time before declaring
+    /// [`IceConnectionState::Disconnected`], see [`RtcConfig::set_ice_consent()`].
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// # use std::time::Duration;
+    /// let config = RtcConfig::new().set_ice_check_interval(Duration::from_millis(20));
+    /// assert_eq!(config.ice_check_interval(), Duration::from_millis(20));
+    ///
+    /// let config = RtcConfig::new().set_ice_check_interval(Duration::ZERO);
+    /// assert_eq!(config.ice_check_interval(), Duration::from_millis(5));
+    /// ```
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc8445#section-14.2
+    pub fn set_ice_check_interval(mut self, interval: Duration) -> Self {
+        self.ice_check_interval = interval.max(ice::MIN_TIMING_ADVANCE);
+        self
+    }
+
+    /// The pacing of ICE connectivity checks.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 50ms.
+    /// assert_eq!(config.ice_check_interval(), Duration::from_millis(50));
+    /// ```
+    pub fn ice_check_interval(&self) -> Duration {
+        self.ice_check_interval
+    }
+
+    /// The pacing of ICE connectivity checks.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 50ms.
+    /// assert_eq!(config.ice_check_interval(), Duration::from_millis(50));
+    /// ```
+    pub fn ice_check_interval(&self) -> Duration {
+        self.ice_check_interval
+    }
+
+    /// Emit a connection quality score per direction at the stats interval.
+    ///
+    /// The score combines loss, RTT, jitter and bitrate versus target, see
+    /// [`QualityInputs`][crate::stats::QualityInputs]. Use
+    /// [`DefaultQualityScorer`][crate::stats::DefaultQualityScorer] for a MOS-like score, or
+    /// provide your own weighting. Requires [`RtcConfig::set_stats_interval`].
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use std::time::Duration;
+    /// # use str0m::RtcConfig;
+    /// # use str0m::stats::DefaultQualityScorer;
+    /// let config = RtcConfig::new()
+    ///     .set_stats_interval(Some(Duration::from_secs(1)))
+    ///     .set_quality_scorer(Some(Arc::new(DefaultQualityScorer::default())));
+    /// ```
+    ///
+    /// Defaults to `None`, which means no quality events.
+    pub fn set_quality_scorer(mut self, scorer: Option<Arc<dyn QualityScorer>>) -> Self {
+        self.quality_scorer = scorer;
+        self
+    }
+
+    /// The scorer for [`Event::QualityStats`], if set.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let config = RtcConfig::new();
+    ///
+    /// assert!(config.quality_scorer().is_none());
+    /// ```
+    pub fn quality_scorer(&self) -> Option<&Arc<dyn QualityScorer>> {
+        self.quality_scorer.as_ref()
+    }
+
+    /// Set the preference between IPv6 and IPv4 for dual-stack hosts.
+    ///
+    /// By default IPv6 is preferred and the connectivity checks alternate between the
+    /// families, so a broken IPv6 path doesn't stall the IPv4 checks, see [RFC 8421][1].
+    ///
+    /// ```
+    /// # use str0m::{RtcConfig, IceFamilyPolicy, IpFamily};
+    /// let config = RtcConfig::new().set_ice_family_policy(IceFamilyPolicy {
+    ///     prefer: IpFamily::Ipv4,
+    ///     interleave: true,
+    /// });
+    /// ```
+    ///
+    /// [1]: https://www.rfc-editor.org/rfc/rfc8421
+    pub fn set_ice_family_policy(mut self, policy: IceFamilyPolicy) -> Self {
+        self.ice_family_policy = policy;
+        self
+    }
+
+    /// The preference between IPv6 and IPv4 for dual-stack hosts.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, IpFamily};
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to preferring IPv6, interleaved with IPv4.
+    /// assert_eq!(config.ice_family_policy().prefer, IpFamily::Ipv6);
+    /// assert!(config.ice_family_policy().interleave);
+    /// ```
+    pub fn ice_family_policy(&self) -> IceFamilyPolicy {
+        self.ice_family_policy
+    }
+
+    /// Set a counter of heap allocations, to report them in
+    /// [`PeerStats::activity`][crate::stats::PeerStats::activity].
+    ///
+    /// The function must return a monotonically increasing count of allocations made on
+    /// the current thread, typically kept in a thread local by a counting
+    /// `#[global_allocator]`. The `Rtc` instance reads it before and after each call to
+    /// [`Rtc::handle_input()`] and [`Rtc::poll_output()`].
+    ///
+    /// Requires the `alloc-stats` feature.
+    #[cfg(feature = "alloc-stats")]
+    pub fn set_allocation_counter(mut self, counter: Option<fn() -> u64>) -> Self {
+        self.allocation_counter = counter;
+        self
+    }
+
+    /// The allocation counter, if set.
+    ///
+    /// Requires the `alloc-stats` feature.
+    #[cfg(feature = "alloc-stats")]
+    pub fn allocation_counter(&self) -> Option<fn() -> u64> {
+        self.allocation_counter
+    }
+
+    /// Bound the work done by consecutive [`Rtc::poll_output()`] calls.
+    ///
+    /// A session with a large backlog, say a burst of retransmissions, can otherwise
+    /// keep producing output for a long time before reaching an [`Output::Timeout`].
+    /// When the budget is used up, `poll_output` returns an [`Output::Timeout`] for
+    /// the current time with [`Reason::PollAgain`], which lets the event loop service
+    /// other sockets or sessions before coming back.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, PollBudget};
+    /// let config = Rtc::builder()
+    ///     .set_poll_budget(PollBudget {
+    ///         max_outputs: Some(64),
+    ///     });
+    /// ```
+    pub fn set_poll_budget(mut self, budget: PollBudget) -> Self {
+        self.poll_budget = budget;
+        self
+    }
+
+    /// Bound the work done by consecutive [`Rtc::poll_output()`] calls.
+    ///
+    /// ```
+    /// # use str0m::{Rtc, PollBudget};
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to no budget.
+    /// assert_eq!(config.poll_budget(), PollBudget::default());
+    /// ```
+    pub fn poll_budget(&self) -> PollBudget {
+        self.poll_budget
+    }
+
+    /// Largest data channel message we accept, advertised as `a=max-message-size` in the SDP.
+    ///
+    /// Incoming messages above this size are discarded. The SCTP receive window is sized to
+    /// fit one such message, which also bounds the memory used for reassembling incoming
+    /// messages, so a misbehaving remote peer can't make it grow without limit.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder()
+    ///     .set_sctp_max_message_size(64 * 1024);
+    /// ```
+    pub fn set_sctp_max_message_size(mut self, max: usize) -> Self {
+        assert!(max > 0, "sctp_max_message_size must be at least 1");
+        self.sctp_max_message_size = max;
+        self
+    }
+
+    /// Largest data channel message we accept.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 262144.
+    /// assert_eq!(config.sctp_max_message_size(), 262144);
+    /// ```
+    pub fn sctp_max_message_size(&self) -> usize {
+        self.sctp_max_message_size
+    }
+
+    /// Retransmission timeout (RTO) bounds for the SCTP association of the data channels.
+    ///
+    /// Low-latency use cases can tighten these to retransmit lost data sooner, and give up
+    /// on an unresponsive association faster. See [`SctpRto`] for the defaults.
+    ///
+    /// Panics unless `min <= initial <= max`.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::channel::SctpRto;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder()
+    ///     .set_sctp_rto(SctpRto {
+    ///         initial: Duration::from_millis(500),
+    ///         min: Duration::from_millis(200),
+    ///         max: Duration::from_secs(5),
+    ///     });
+    /// ```
+    pub fn set_sctp_rto(mut self, rto: SctpRto) -> Self {
+        assert!(
+            rto.min <= rto.initial && rto.initial <= rto.max,
+            "sctp rto must satisfy min <= initial <= max"
+        );
+        self.sctp_rto = rto;
+        self
+    }
+
+    /// Retransmission timeout (RTO) bounds for the SCTP association.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// # use str0m::channel::SctpRto;
+    /// # use std::time::Duration;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to the values in RFC 4960.
+    /// assert_eq!(config.sctp_rto().initial, Duration::from_secs(3));
+    /// assert_eq!(config.sctp_rto(), SctpRto::default());
+    /// ```
+    pub fn sctp_rto(&self) -> SctpRto {
+        self.sctp_rto
+    }
+
+    /// Use the [`Rtc`] instance for data channels only.
+    ///
+    /// This skips the media subsystems: no SRTP contexts are created when DTLS connects,
+    /// BWE is never enabled, and the RTP session isn't polled for timeouts or packets.
+    /// No codecs, FEC decoder or packet buffer are allocated.
+    /// Useful for signaling or file transfer servers with many connections.
+    ///
+    /// Audio and video m-lines in a remote offer are answered rejected (port 0), and so
+    /// are those added with [`SdpApi::add_media()`][change::SdpApi::add_media]. The
+    /// media still exists, but is [`Direction::Inactive`][media::Direction::Inactive].
+    /// Same goes for [`DirectApi::declare_media()`][change::DirectApi::declare_media].
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let rtc = Rtc::builder()
+    ///     .set_data_channel_only(true)
+    ///     .build();
+    /// ```
+    pub fn set_data_channel_only(mut self, enabled: bool) -> Self {
+        self.data_channel_only = enabled;
+        self
+    }
+
+    /// Whether the [`Rtc`] instance is for data channels only.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to false.
+    /// assert!(!config.data_channel_only());
+    /// ```
+    pub fn data_channel_only(&self) -> bool {
+        self.data_channel_only
+    }
+
+    /// Sets how many previous audio frames are sent in each RED packet.
+    ///
+    /// Only used when RED is negotiated, see [`RtcConfig::enable_opus_red()`].
+    /// Larger values survive longer bursts of loss at the cost of bandwidth.
+    /// 0 sends RED packets without redundancy.
+    pub fn set_red_distance(mut self, distance: usize) -> Self {
+        self.red_distance = distance;
+        self
+    }
+
+    /// Returns the setting for RED redundancy distance.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 1.
+    /// assert_eq!(config.red_distance(), 1);
+    /// ```
+    pub fn red_distance(&self) -> usize {
+        self.red_distance
+    }
+
+    /// Sets how many video packets are protected by each FlexFEC packet.
+    ///
+    /// Only used when FlexFEC is negotiated, see [`RtcConfig::enable_flexfec()`].
+    /// One lost packet in the window can be recovered. Smaller windows recover
+    /// more loss at the cost of bandwidth. Between 1 and 109.
+    pub fn set_flexfec_window(mut self, window: usize) -> Self {
+        self.flexfec_window = window.clamp(1, 109);
+        self
+    }
+
+    /// Returns the setting for the FlexFEC protection window.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 10.
+    /// assert_eq!(config.flexfec_window(), 10);
+    /// ```
+    pub fn flexfec_window(&self) -> usize {
+        self.flexfec_window
+    }
+
+    /// Sets how many video packets are protected by each ULPFEC packet.
+    ///
+    /// Only used when ULPFEC is negotiated, see [`RtcConfig::enable_ulpfec()`].
+    /// 0 means received ULPFEC is used to recover lost packets, but none is sent.
+    /// Otherwise one lost packet in the window can be recovered. Between 0 and 48.
+    ///
+    /// Not used in RTP mode, where the sequence numbers are set by the API user.
+    pub fn set_ulpfec_window(mut self, window: usize) -> Self {
+        self.ulpfec_window = window.min(48);
+        self
+    }
+
+    /// Returns the setting for the ULPFEC protection window.
+    ///
+    /// ```
+    /// # use str0m::Rtc;
+    /// let config = Rtc::builder();
+    ///
+    /// // Defaults to 0, which is not sending ULPFEC.
+    /// assert_eq!(config.ulpfec_window(), 0);
+    /// ```
+    pub fn ulpfec_window(&self) -> usize {
+        self.ulpfec_window
+    }
+
+    /// Create a [`Rtc`] from the configuration.
+    ///
+    /// Panics if the configuration can't be used, see [`RtcConfig::try_build()`].
+    pub fn build(self) -> Rtc {
+        self.try_build().expect("valid RtcConfig")
+    }
+
+    /// Create a [`Rtc`] from the configuration, or fail if it can't be used.
+    ///
+    /// Such as DTLS cipher suites or key exchange groups that leave nothing to negotiate.
+    ///
+    /// ```
+    /// # use str0m::RtcConfig;
+    /// let result = RtcConfig::new()
+    ///     .set_dtls_cipher_suites(Some(vec![]))
+    ///     .try_build();
+    ///
+    /// assert!(result.is_err());
+    /// ```
+    pub fn try_build(self) -> Result<Rtc, RtcError> {
+        Rtc::new_from_config(self)
+    }
+}
+
+impl Default for RtcConfig {
+    fn default() -> Self {
+        Self {
+            local_ice_credentials: None,
+            dtls_cert: None,
+            fingerprint_verification: true,
+            ice_lite: false,
+            codec_config: CodecConfig::new_with_defaults(),
+            exts: ExtensionMap::standard(),
+            stats_interval: None,
+            bwe_initial_bitrate: None,
+            reordering_size_audio: 15,
+            reordering_size_video: 30,
+            send_buffer_audio: 50,
+            send_buffer_video: 1000,
+            rtp_mode: false,
+            enable_raw_packets: false,
+            cname: None,
+            rtcp_rsize: true,
+            extmap_allow_mixed: true,
+            rtcp_schedule: RtcpSchedule::default(),
+            rtp_leniency: RtpLeniency::default(),
+            send_backpressure: None,
+            congestion_window: None,
+            max_transmit_batch: 16,
+            roc_recovery: 0,
+            rtcp_pacing: None,
+            dtls_rekey_interval: None,
+            dtls_keylog: None,
+            dtls_cipher_suites: None,
+            dtls_groups: None,
+            fingerprint_hash: FingerprintHash::Sha256,
+            srtp_crypto: None,
+            fips_mode: false,
+            limits: Limits::default(),
+            address_latching: false,
+            ice_consent: IceConsentConfig::default(),
+            ice_nomination: IceNomination::default(),
+            stun_servers: vec![],
+            turn_servers: vec![],
+            initial_rtt: None,
+            rng_seed: None,
+            ice_check_interval: Duration::from_millis(50),
+            quality_scorer: None,
+            ice_family_policy: IceFamilyPolicy::default(),
+            #[cfg(feature = "alloc-stats")]
+            allocation_counter: None,
+            poll_budget: PollBudget::default(),
+            sctp_max_message_size: sctp::DEFAULT_MAX_MESSAGE_SIZE,
+            sctp_rto: SctpRto::default(),
+            data_channel_only: false,
+            red_distance: 1,
+            flexfec_window: 10,
+            ulpfec_window: 0,
+        }
+    }
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::IceConnectionStateChange(l0), Self::IceConnectionStateChange(r0)) => l0 == r0,
+            (Self::MediaAdded(m0), Self::MediaAdded(m1)) => m0 == m1,
+            (Self::MediaData(m1), Self::MediaData(m2)) => m1 == m2,
+            (Self::Dtmf(d1), Self::Dtmf(d2)) => d1 == d2,
+            (Self::ChannelOpen(l0, l1), Self::ChannelOpen(r0, r1)) => l0 == r0 && l1 == r1,
+            (Self::ChannelData(l0), Self::ChannelData(r0)) => l0 == r0,
+            (Self::ChannelClose(l0), Self::ChannelClose(r0)) => l0 == r0,
+            (Self::ChannelCloseReason(l0, l1), Self::ChannelCloseReason(r0, r1)) => {
+                l0 == r0 && l1 == r1
+            }
+            (Self::ChannelBufferedAmountLow(l0), Self::ChannelBufferedAmountLow(r0)) => l0 == r0,
+            (Self::ChannelWriteFailed(l0, l1), Self::ChannelWriteFailed(r0, r1)) => {
+                l0 == r0 && l1 == r1
+            }
+            (Self::SctpAssociationClosed(l0), Self::SctpAssociationClosed(r0)) => l0 == r0,
+            (Self::MdnsResolveRequest(l0), Self::MdnsResolveRequest(r0)) => l0 == r0,
+            (Self::IceConsent(l0), Self::IceConsent(r0)) => l0 == r0,
+            (Self::CandidateGathered(l0), Self::CandidateGathered(r0)) => l0 == r0,
+            (Self::IceRoleChange { controlling: l0 }, Self::IceRoleChange { controlling: r0 }) => {
+                l0 == r0
+            }
+            (
+                Self::PeerReflexiveCandidate {
+                    candidate: l0,
+                    local: l1,
+                },
+                Self::PeerReflexiveCandidate {
+                    candidate: r0,
+                    local: r1,
+                },
+            ) => l0 == r0 && l1 == r1,
+            (
+                Self::PeerReflexivePromoted {
+                    previous: l0,
+                    current: l1,
+                },
+                Self::PeerReflexivePromoted {
+                    previous: r0,
+                    current: r1,
+                },
+            ) => l0 == r0 && l1 == r1,
+            (
+                Self::SelectedCandidatePairChange {
+                    local: l0,
+                    remote: l1,
+                },
+                Self::SelectedCandidatePairChange {
+                    local: r0,
+                    remote: r1,
+                },
+            ) => l0 == r0 && l1 == r1,
+            (
+                Self::RemoteAddressLatched {
+                    previous: l0,
+                    current: l1,
+                },
+                Self::RemoteAddressLatched {
+                    previous: r0,
+                    current: r1,
+                },
+            ) => l0 == r0 && l1 == r1,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Event {}
+
+impl fmt::Debug for Rtc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Rtc").finish()
+    }
+}
+
+/// Log a CSV like stat to stdout.
+///
+/// ```ignore
+/// log_stat!("MY_STAT", 1, "hello", 3);
+/// ```
+///
+/// will result in the following being printed
+///
+/// ```text
+/// MY_STAT 1, hello, 3, {unix_timestamp_ms}
+/// ````
+///
+/// These logs can be easily grepped for, parsed and graphed, or otherwise analyzed.
+///
+/// This macro turns into a NO-OP if the `_internal_dont_use_log_stats` feature is not enabled
+macro_rules! log_stat {
+    ($name:expr, $($arg:expr),+) => {
+        #[cfg(feature = "_internal_dont_use_log_stats")]
+        {
+            use std::time::SystemTime;
+            use std::io::{self, Write};
+
+            let now = SystemTime::now();
+            let since_epoch = now.duration_since(SystemTime::UNIX_EPOCH).unwrap();
+            let unix_time_ms = since_epoch.as_millis();
+            let mut lock = io::stdout().lock();
+            write!(lock, "{} ", $name).expect("Failed to write to stdout");
+
+            $(
+                write!(lock, "{},", $arg).expect("Failed to write to stdout");
+            )+
+            writeln!(lock, "{}", unix_time_ms).expect("Failed to write to stdout");
+        }
+    };
+}
+pub(crate) use log_stat;
+
+#[cfg(test)]
+mod test {
+    use std::panic::UnwindSafe;
 
     use super::*;
+    use crate::media::MediaKind;
 
     #[test]
     fn rtc_is_send() {
@@ -2358,6 +4705,31 @@ mod test {
         let n = std::mem::size_of::<Event>();
         assert!(n < 450);
     }
+
+    #[test]
+    fn rng_seed_reproduces_ids() {
+        let ids = || {
+            let mut rtc = RtcConfig::new().set_rng_seed(42).build();
+            let mid = rtc
+                .sdp_api()
+                .add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+            (mid, rtc.ice.local_credentials().clone())
+        };
+
+        assert_eq!(ids(), ids());
+    }
+
+    #[test]
+    fn rng_seed_is_per_instance() {
+        let creds = || {
+            let _seeded = RtcConfig::new().set_rng_seed(42).build();
+            let rtc = Rtc::new();
+            rtc.ice.local_credentials().clone()
+        };
+
+        // Instances without a seed stay random next to a seeded one.
+        assert_ne!(creds(), creds());
+    }
 }
 
 #[cfg(feature = "_internal_test_exports")]
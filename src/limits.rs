@@ -0,0 +1,182 @@
+use std::fmt;
+
+use crate::Warning;
+
+/// Caps on the resources a single [`Rtc`][crate::Rtc] instance uses.
+///
+/// Set using [`RtcConfig::set_limits()`][crate::RtcConfig::set_limits]. This lets servers
+/// with many sessions bound what each remote peer can make them allocate.
+///
+/// A remote SDP that would go above a limit is refused with
+/// [`RtcError::LimitReached`][crate::RtcError::LimitReached]. Data channels opened by the
+/// remote peer and incoming RTP for new streams are refused with a
+/// [`Warning::LimitReached`]. Media, streams and channels added through the API count
+/// towards the limits, but are not refused.
+///
+/// [`Warning::LimitApproached`] is emitted when the usage reaches 80% of a limit.
+///
+/// The ICE limits bound the STUN traffic and memory for servers with many host
+/// addresses, since every remote candidate is paired with every local candidate of
+/// the same IP family. str0m only does `rtcp-mux`, meaning there is one component,
+/// and the ICE limits apply to it as a whole. They don't emit warnings.
+///
+/// ```
+/// # use str0m::Limits;
+/// let limits = Limits {
+///     max_mids: Some(16),
+///     max_channels: Some(8),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Max number of m-lines (mids), including the one for data channels.
+    pub max_mids: Option<usize>,
+
+    /// Max number of streams, incoming and outgoing. An RTX SSRC is counted together
+    /// with its main SSRC.
+    pub max_ssrcs: Option<usize>,
+
+    /// Max number of data channels.
+    pub max_channels: Option<usize>,
+
+    /// Max number of ICE candidate pairs. The pairs with the lowest priority are dropped
+    /// when going above. Defaults to 100 as suggested by RFC 8445.
+    pub max_candidate_pairs: Option<usize>,
+
+    /// Max number of local ICE candidates. Candidates added above this are rejected.
+    pub max_local_candidates: Option<usize>,
+
+    /// Max number of remote ICE candidates. Candidates added above this are ignored.
+    pub max_remote_candidates: Option<usize>,
+
+    /// Max number of ICE candidate pairs for each remote candidate.
+    ///
+    /// Prunes the pairs with the lowest priority, i.e. a remote candidate is only checked
+    /// against the best local candidates. Nominated pairs are never pruned.
+    pub max_pairs_per_remote_candidate: Option<usize>,
+}
+
+/// The kind of resource capped by [`Limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Limit {
+    /// [`Limits::max_mids`]
+    Mids,
+    /// [`Limits::max_ssrcs`]
+    Ssrcs,
+    /// [`Limits::max_channels`]
+    Channels,
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Limit::Mids => write!(f, "mids"),
+            Limit::Ssrcs => write!(f, "SSRCs"),
+            Limit::Channels => write!(f, "channels"),
+        }
+    }
+}
+
+/// Keeps track of which warnings have been emitted per limit.
+#[derive(Debug)]
+pub(crate) struct LimitState {
+    limits: Limits,
+    approached: [bool; 3],
+    reached: [bool; 3],
+}
+
+impl LimitState {
+    pub fn new(limits: Limits) -> Self {
+        LimitState {
+            limits,
+            approached: [false; 3],
+            reached: [false; 3],
+        }
+    }
+
+    pub fn max(&self, limit: Limit) -> Option<usize> {
+        match limit {
+            Limit::Mids => self.limits.max_mids,
+            Limit::Ssrcs => self.limits.max_ssrcs,
+            Limit::Channels => self.limits.max_channels,
+        }
+    }
+
+    /// Whether a total of `count` is within the limit.
+    pub fn allows(&self, limit: Limit, count: usize) -> bool {
+        self.max(limit).map(|max| count <= max).unwrap_or(true)
+    }
+
+    /// Update with the current usage. Returns a warning when first approaching the limit.
+    pub fn update(&mut self, limit: Limit, count: usize) -> Option<Warning> {
+        let max = self.max(limit)?;
+        let idx = limit as usize;
+
+        if count * 5 < max * 4 {
+            // Below the threshold, warn again next time we get close.
+            self.approached[idx] = false;
+            self.reached[idx] = false;
+            return None;
+        }
+
+        if self.approached[idx] {
+            return None;
+        }
+        self.approached[idx] = true;
+
+        Some(Warning::LimitApproached { limit, count, max })
+    }
+
+    /// Something was refused. Returns a warning the first time since last being below
+    /// the threshold.
+    pub fn refused(&mut self, limit: Limit) -> Option<Warning> {
+        let idx = limit as usize;
+
+        if self.reached[idx] {
+            return None;
+        }
+        self.reached[idx] = true;
+
+        Some(Warning::LimitReached(limit))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn warn_once_until_below() {
+        let mut state = LimitState::new(Limits {
+            max_channels: Some(10),
+            ..Default::default()
+        });
+
+        assert!(state.allows(Limit::Mids, 1000));
+        assert!(state.allows(Limit::Channels, 10));
+        assert!(!state.allows(Limit::Channels, 11));
+
+        assert!(state.update(Limit::Channels, 7).is_none());
+        assert!(matches!(
+            state.update(Limit::Channels, 8),
+            Some(Warning::LimitApproached {
+                count: 8,
+                max: 10,
+                ..
+            })
+        ));
+        assert!(state.update(Limit::Channels, 9).is_none());
+
+        assert!(state.refused(Limit::Channels).is_some());
+        assert!(state.refused(Limit::Channels).is_none());
+
+        // Going below the threshold re-arms the warnings.
+        assert!(state.update(Limit::Channels, 2).is_none());
+        assert!(state.update(Limit::Channels, 8).is_some());
+        assert!(state.refused(Limit::Channels).is_some());
+
+        // No limit, no warnings.
+        assert!(state.update(Limit::Ssrcs, 1000).is_none());
+    }
+}
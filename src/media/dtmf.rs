@@ -0,0 +1,362 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::rtp_::{Frequency, MediaTime, Mid, Pt, Rid};
+use crate::util::already_happened;
+
+/// How often a tone in progress is updated (RFC 4733 section 2.5.1.2).
+const UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How many times the end of a tone is sent (RFC 4733 section 2.5.1.4).
+const END_PACKETS: usize = 3;
+
+/// Event codes of the DTMF digits in order.
+const DIGITS: &[u8; 16] = b"0123456789*#ABCD";
+
+/// A DTMF tone, sent and received as RFC 4733 telephone events.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use str0m::media::Dtmf;
+/// let dtmf = Dtmf::new('#', Duration::from_millis(100)).unwrap();
+/// assert_eq!(dtmf.event, 11);
+/// assert_eq!(dtmf.digit(), Some('#'));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Dtmf {
+    /// The event code. 0-9 are the digits, 10 is `*`, 11 is `#` and 12-15 are `A`-`D`.
+    pub event: u8,
+    /// The power level of the tone in -dBm0, from 0 to 63. Higher is quieter.
+    pub volume: u8,
+    /// How long the tone plays.
+    ///
+    /// The duration field is 16 bits of RTP time, which limits a tone to about 1.3
+    /// seconds at 48kHz.
+    pub duration: Duration,
+}
+
+impl Dtmf {
+    /// Creates a tone for a digit `0`-`9`, `*`, `#` or `A`-`D` at volume 10 (-10 dBm0).
+    pub fn new(digit: char, duration: Duration) -> Option<Self> {
+        let digit = digit.to_ascii_uppercase();
+        let event = DIGITS.iter().position(|d| *d as char == digit)?;
+
+        Some(Dtmf {
+            event: event as u8,
+            volume: 10,
+            duration,
+        })
+    }
+
+    /// The digit of the event code, if it is a DTMF digit.
+    pub fn digit(&self) -> Option<char> {
+        DIGITS.get(self.event as usize).map(|d| *d as char)
+    }
+}
+
+/// A DTMF tone received from the remote peer.
+///
+/// Emitted once per tone when it ends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DtmfEvent {
+    /// The media the tone came on.
+    pub mid: Mid,
+    /// The RTP time the tone started at.
+    pub time: MediaTime,
+    /// The tone.
+    pub dtmf: Dtmf,
+}
+
+/// Sends queued tones as telephone event packets, paced in time.
+#[derive(Debug, Default)]
+pub(crate) struct DtmfSender {
+    queue: VecDeque<DtmfTx>,
+}
+
+#[derive(Debug)]
+struct DtmfTx {
+    pt: Pt,
+    rid: Option<Rid>,
+    wallclock: Instant,
+    time: u32,
+    clock_rate: Frequency,
+    dtmf: Dtmf,
+    /// When to send the next packet. None before the first.
+    next_at: Option<Instant>,
+    /// Packets sent so far.
+    sent: usize,
+}
+
+/// A telephone event packet to send.
+pub(crate) struct DtmfPacket {
+    pub pt: Pt,
+    pub rid: Option<Rid>,
+    pub wallclock: Instant,
+    pub time: u32,
+    pub marker: bool,
+    pub payload: Vec<u8>,
+}
+
+impl DtmfSender {
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn push(
+        &mut self,
+        pt: Pt,
+        rid: Option<Rid>,
+        wallclock: Instant,
+        rtp_time: MediaTime,
+        clock_rate: Frequency,
+        dtmf: Dtmf,
+    ) {
+        self.queue.push_back(DtmfTx {
+            pt,
+            rid,
+            wallclock,
+            time: rtp_time.rebase(clock_rate).numer() as u32,
+            clock_rate,
+            dtmf,
+            next_at: None,
+            sent: 0,
+        });
+    }
+
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        let tx = self.queue.front()?;
+        Some(tx.next_at.unwrap_or_else(already_happened))
+    }
+
+    pub fn poll_packet(&mut self, now: Instant) -> Option<DtmfPacket> {
+        let tx = self.queue.front_mut()?;
+
+        if tx.next_at.is_some_and(|at| now < at) {
+            return None;
+        }
+
+        // Updates until the full duration, the last one is the end.
+        let updates = tx
+            .dtmf
+            .duration
+            .as_micros()
+            .div_ceil(UPDATE_INTERVAL.as_micros())
+            .max(1) as usize;
+
+        let index = tx.sent;
+        let end = index + 1 >= updates;
+
+        let elapsed = (UPDATE_INTERVAL * (index as u32 + 1)).min(tx.dtmf.duration);
+        let duration = MediaTime::from(elapsed)
+            .rebase(tx.clock_rate)
+            .numer()
+            .min(u16::MAX as u64) as u16;
+
+        let mut payload = Vec::with_capacity(4);
+        payload.push(tx.dtmf.event);
+        payload.push(((end as u8) << 7) | (tx.dtmf.volume & 0x3f));
+        payload.extend_from_slice(&duration.to_be_bytes());
+
+        let packet = DtmfPacket {
+            pt: tx.pt,
+            rid: tx.rid,
+            wallclock: tx.wallclock,
+            time: tx.time,
+            marker: index == 0,
+            payload,
+        };
+
+        tx.sent += 1;
+
+        if tx.sent >= updates + END_PACKETS - 1 {
+            self.queue.pop_front();
+        } else if end {
+            // The end packets are sent back to back.
+            tx.next_at = Some(now);
+        } else {
+            tx.next_at = Some(now + UPDATE_INTERVAL);
+        }
+
+        Some(packet)
+    }
+}
+
+/// Turns received telephone event packets into one event per tone.
+#[derive(Debug, Default)]
+pub(crate) struct DtmfReceiver {
+    current: Option<DtmfRx>,
+    events: VecDeque<(MediaTime, Dtmf)>,
+}
+
+#[derive(Debug)]
+struct DtmfRx {
+    time: MediaTime,
+    dtmf: Dtmf,
+    ended: bool,
+}
+
+impl DtmfReceiver {
+    //  0                   1                   2                   3
+    //  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    // |     event     |E|R| volume    |          duration             |
+    // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    pub fn handle(&mut self, time: MediaTime, payload: &[u8]) {
+        if payload.len() < 4 {
+            return;
+        }
+
+        let end = payload[1] & 0x80 != 0;
+        let units = u16::from_be_bytes([payload[2], payload[3]]) as u64;
+
+        let dtmf = Dtmf {
+            event: payload[0],
+            volume: payload[1] & 0x3f,
+            duration: MediaTime::new(units, time.frequency()).into(),
+        };
+
+        match &mut self.current {
+            // Update, or repeated end of the current tone.
+            Some(c) if c.time == time => {
+                if c.ended {
+                    return;
+                }
+                c.dtmf.duration = c.dtmf.duration.max(dtmf.duration);
+            }
+            // Late packet of an earlier tone.
+            Some(c) if time < c.time => return,
+            _ => {
+                // A tone that never got its end packets ends when the next starts.
+                if let Some(c) = self.current.take().filter(|c| !c.ended) {
+                    self.events.push_back((c.time, c.dtmf));
+                }
+                self.current = Some(DtmfRx {
+                    time,
+                    dtmf,
+                    ended: false,
+                });
+            }
+        }
+
+        if end {
+            // Unwrap is OK, we set it above.
+            let c = self.current.as_mut().unwrap();
+            c.ended = true;
+            self.events.push_back((c.time, c.dtmf));
+        }
+    }
+
+    pub fn poll_event(&mut self) -> Option<(MediaTime, Dtmf)> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tone(event: u8, ms: u64) -> Dtmf {
+        Dtmf {
+            event,
+            volume: 10,
+            duration: Duration::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn dtmf_digits() {
+        assert_eq!(Dtmf::new('0', Duration::ZERO).unwrap().event, 0);
+        assert_eq!(Dtmf::new('*', Duration::ZERO).unwrap().event, 10);
+        assert_eq!(Dtmf::new('d', Duration::ZERO).unwrap().event, 15);
+        assert!(Dtmf::new('x', Duration::ZERO).is_none());
+        assert_eq!(tone(16, 0).digit(), None);
+    }
+
+    #[test]
+    fn dtmf_send_updates_and_end() {
+        let now = Instant::now();
+        let mut sender = DtmfSender::default();
+        let freq = Frequency::FORTY_EIGHT_KHZ;
+
+        sender.push(
+            110.into(),
+            None,
+            now,
+            MediaTime::new(4800, freq),
+            freq,
+            tone(5, 120),
+        );
+
+        let mut packets = vec![];
+        let mut at = now;
+
+        while let Some(next) = sender.poll_timeout() {
+            at = at.max(next);
+            while let Some(p) = sender.poll_packet(at) {
+                packets.push((at - now, p));
+            }
+        }
+
+        // Updates at 0, 50 and 100ms. The end is sent three times.
+        assert_eq!(packets.len(), 5);
+
+        let durations: Vec<_> = packets
+            .iter()
+            .map(|(_, p)| u16::from_be_bytes([p.payload[2], p.payload[3]]))
+            .collect();
+        assert_eq!(durations, [2400, 4800, 5760, 5760, 5760]);
+
+        let ends: Vec<_> = packets.iter().map(|(_, p)| p.payload[1] >> 7).collect();
+        assert_eq!(ends, [0, 0, 1, 1, 1]);
+
+        let at: Vec<_> = packets.iter().map(|(at, _)| at.as_millis()).collect();
+        assert_eq!(at, [0, 50, 100, 100, 100]);
+
+        assert!(packets[0].1.marker);
+        assert!(packets[1..].iter().all(|(_, p)| !p.marker));
+        assert!(packets.iter().all(|(_, p)| p.time == 4800));
+        assert!(packets.iter().all(|(_, p)| p.payload[0] == 5));
+    }
+
+    #[test]
+    fn dtmf_receive_once_per_tone() {
+        let mut recv = DtmfReceiver::default();
+        let freq = Frequency::FORTY_EIGHT_KHZ;
+        let time = MediaTime::new(4800, freq);
+
+        recv.handle(time, &[5, 10, 0x09, 0x60]);
+        recv.handle(time, &[5, 10, 0x12, 0xc0]);
+        assert_eq!(recv.poll_event(), None);
+
+        // The end and its repeats.
+        for _ in 0..3 {
+            recv.handle(time, &[5, 0x80 | 10, 0x16, 0x80]);
+        }
+
+        assert_eq!(recv.poll_event(), Some((time, tone(5, 120))));
+        assert_eq!(recv.poll_event(), None);
+    }
+
+    #[test]
+    fn dtmf_receive_lost_end() {
+        let mut recv = DtmfReceiver::default();
+        let freq = Frequency::FORTY_EIGHT_KHZ;
+        let time1 = MediaTime::new(4800, freq);
+        let time2 = MediaTime::new(48000, freq);
+
+        recv.handle(time1, &[1, 10, 0x09, 0x60]);
+        recv.handle(time1, &[1, 10, 0x12, 0xc0]);
+
+        // The next tone starts without an end of the previous.
+        recv.handle(time2, &[2, 10, 0x09, 0x60]);
+        assert_eq!(recv.poll_event(), Some((time1, tone(1, 100))));
+
+        // Late packet of the first tone.
+        recv.handle(time1, &[1, 0x80 | 10, 0x16, 0x80]);
+        assert_eq!(recv.poll_event(), None);
+
+        // Too short.
+        recv.handle(time2, &[2, 0x80]);
+        assert_eq!(recv.poll_event(), None);
+    }
+}
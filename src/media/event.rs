@@ -1,6 +1,6 @@
 use std::fmt;
 use std::ops::RangeInclusive;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::packet::MediaKind;
 use crate::rtp_::{Direction, ExtensionValues, MediaTime, Mid, Pt, Rid, SenderInfo, SeqNo};
@@ -112,6 +112,13 @@ pub struct MediaData {
     /// For audio this flag most likely doesn't matter.
     pub contiguous: bool,
 
+    /// Whether the last RTP packet of this data ends a frame.
+    ///
+    /// This is normally `true`. It's `false` when the data is a part of a frame, such as
+    /// one VP9 spatial layer sent with the frame's remaining layers still to come and no
+    /// end of frame signal. See [`RtpPacket::end_of_frame`][crate::rtp::RtpPacket::end_of_frame].
+    pub end_of_frame: bool,
+
     /// The actual packet data a.k.a Sample.
     ///
     /// Bigger samples don't fit in one UDP packet, thus WebRTC RTP is chopping up codec
@@ -150,6 +157,27 @@ pub struct KeyframeRequest {
     pub kind: KeyframeRequestKind,
 }
 
+/// Change of send backpressure for a media.
+///
+/// This is obtained via the [`Event::SendBackpressure`][crate::Event::SendBackpressure]
+/// when [`RtcConfig::set_send_backpressure()`][crate::RtcConfig::set_send_backpressure]
+/// is configured. The current state is also available via
+/// [`Writer::is_backpressured()`][crate::media::Writer::is_backpressured].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SendBackpressure {
+    /// The media identifier the backpressure is for.
+    pub mid: Mid,
+
+    /// Whether the backpressure started (`true`) or ended (`false`).
+    ///
+    /// When active, the application should throttle the encoder (lower bitrate, drop
+    /// frames) rather than keep writing.
+    pub active: bool,
+
+    /// Estimated time to send what is currently queued for the media.
+    pub queued: Duration,
+}
+
 /// Type of keyframe request.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyframeRequestKind {
@@ -162,6 +190,66 @@ pub enum KeyframeRequestKind {
     Fir,
 }
 
+impl KeyframeRequestKind {
+    /// Combine two pending requests into one, keeping the most severe.
+    pub(crate) fn coalesce(self, other: KeyframeRequestKind) -> KeyframeRequestKind {
+        if self == KeyframeRequestKind::Fir || other == KeyframeRequestKind::Fir {
+            KeyframeRequestKind::Fir
+        } else {
+            KeyframeRequestKind::Pli
+        }
+    }
+}
+
+/// Policy for outgoing keyframe requests for a [`Media`][crate::media::Media].
+///
+/// Set via [`Writer::set_keyframe_request_policy()`][crate::media::Writer::set_keyframe_request_policy].
+///
+/// Keyframe requests that are made before a previous one has been sent are always
+/// coalesced into one. If any of them is a FIR, the coalesced request is a FIR.
+///
+/// ```
+/// # use std::time::Duration;
+/// # use str0m::media::{KeyframeRequestKind, KeyframeRequestPolicy};
+/// let policy = KeyframeRequestPolicy {
+///     force_kind: Some(KeyframeRequestKind::Pli),
+///     min_interval: Duration::from_millis(500),
+///     on_stream_start: Some(KeyframeRequestKind::Pli),
+/// };
+///
+/// assert_eq!(KeyframeRequestPolicy::default().min_interval, Duration::ZERO);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct KeyframeRequestPolicy {
+    /// Always send this kind of request, regardless of what was requested.
+    ///
+    /// The forced kind must be negotiated for the media (`a=rtcp-fb` with
+    /// `nack pli` or `ccm fir`), or the remote peer is likely to ignore it.
+    ///
+    /// Defaults to `None`, which sends the requested kind.
+    pub force_kind: Option<KeyframeRequestKind>,
+
+    /// Minimum time between two keyframe requests sent for the same stream.
+    ///
+    /// Requests made within this interval are held back, coalesced, and sent
+    /// once the interval has passed. Once the round trip time is known, either measured
+    /// or from [`RtcConfig::set_initial_rtt()`][crate::RtcConfig::set_initial_rtt],
+    /// requests are also held back for at least one round trip.
+    ///
+    /// Defaults to zero, which means requests are sent as soon as possible.
+    pub min_interval: Duration,
+
+    /// Automatically request a keyframe when an incoming stream starts.
+    ///
+    /// A stream starts when the first packet arrives, and again when packets arrive
+    /// after the stream was paused (see [`Event::StreamPaused`][crate::Event::StreamPaused]).
+    /// This avoids forwarding a stream that has no decodable point for a long time.
+    /// The request is subject to `min_interval` like any other.
+    ///
+    /// Defaults to `None`, which means no automatic requests.
+    pub on_stream_start: Option<KeyframeRequestKind>,
+}
+
 impl fmt::Debug for MediaData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("MediaData")
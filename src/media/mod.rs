@@ -1,15 +1,15 @@
 //! Media (audio/video) related content.
 
 use std::collections::{HashMap, VecDeque};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::change::AddMedia;
-use crate::format::CodecConfig;
+use crate::format::{Codec, CodecConfig};
 use crate::io::{Id, DATAGRAM_MTU};
-use crate::packet::{DepacketizingBuffer, Payloader, RtpMeta};
-use crate::rtp_::ExtensionMap;
+use crate::packet::{CodecDepacketizer, DepacketizingBuffer, Payloader, RedEncoder, RtpMeta};
 use crate::rtp_::SRTP_BLOCK_SIZE;
 use crate::rtp_::SRTP_OVERHEAD;
+use crate::rtp_::{Extension, ExtensionMap};
 use crate::RtcError;
 
 use crate::format::PayloadParams;
@@ -18,6 +18,10 @@ use crate::sdp::{MediaLine, Msid};
 use crate::streams::{RtpPacket, Streams};
 use crate::util::already_happened;
 
+mod dtmf;
+pub use dtmf::{Dtmf, DtmfEvent};
+use dtmf::{DtmfReceiver, DtmfSender};
+
 mod event;
 pub use event::*;
 
@@ -48,6 +52,11 @@ pub struct Media {
     /// RTP level.
     rids_rx: Rids,
 
+    /// How keyframe requests for incoming streams in this media are sent.
+    ///
+    /// RTP level.
+    keyframe_request_policy: KeyframeRequestPolicy,
+
     // ========================================= SDP level =========================================
     //
     /// The index of this media line in the Session::media Vec.
@@ -89,9 +98,21 @@ pub struct Media {
     /// These are 1-indexed to be exactly like in the SDP.
     remote_exts: ExtensionMap,
 
+    /// Extmaps for outgoing packets, when the TWCC id differs from `remote_exts`.
+    ///
+    /// Set via [`DirectApi::set_twcc_extension_id()`][crate::change::DirectApi::set_twcc_extension_id],
+    /// and cleared when the remote extmaps are negotiated again.
+    exts_tx: Option<ExtensionMap>,
+
     /// [`true`] if this media was created by the remote peer, [`false`] if it was created by us.
     remote_created: bool,
 
+    /// [`true`] if the session is data channel only. The m-line is rejected (port 0)
+    /// and the media stays inactive.
+    ///
+    /// SDP property.
+    disabled: bool,
+
     /// Simulcast configuration, if set.
     ///
     /// SDP property.
@@ -109,9 +130,19 @@ pub struct Media {
     /// Samples to payload. Should typically only be 0 or 1.
     to_payload: VecDeque<ToPayload>,
 
+    /// DTMF tones to send as telephone events.
+    dtmf_tx: DtmfSender,
+
+    /// Received telephone events.
+    dtmf_rx: DtmfReceiver,
+
     pub(crate) need_open_event: bool,
     pub(crate) need_changed_event: bool,
 
+    /// Whether the send queue is above the configured backpressure threshold.
+    backpressure: bool,
+    pub(crate) pending_backpressure: Option<SendBackpressure>,
+
     /// When converting media lines to SDP, it's easier to represent the app m-line
     /// as a Media. This field is true when we do that. No Session::medias will have
     /// this set to true – they only exist temporarily.
@@ -199,6 +230,80 @@ impl Media {
         &self.rids_rx
     }
 
+    /// Policy for outgoing keyframe requests (PLI/FIR).
+    ///
+    /// Change it using [`Writer::set_keyframe_request_policy()`].
+    ///
+    /// RTP level.
+    pub fn keyframe_request_policy(&self) -> KeyframeRequestPolicy {
+        self.keyframe_request_policy
+    }
+
+    pub(crate) fn set_keyframe_request_policy(&mut self, policy: KeyframeRequestPolicy) {
+        self.keyframe_request_policy = policy;
+    }
+
+    /// The RTP header extension id for transport-wide congestion control (TWCC).
+    ///
+    /// This is the id outgoing packets are sent with, `None` if TWCC isn't used for this
+    /// media. Change it using
+    /// [`DirectApi::set_twcc_extension_id()`][crate::change::DirectApi::set_twcc_extension_id].
+    ///
+    /// RTP level.
+    pub fn twcc_extension_id(&self) -> Option<u8> {
+        self.extmap_tx().id_of(Extension::TransportSequenceNumber)
+    }
+
+    /// Returns `false` if the id is used by another extension.
+    pub(crate) fn set_twcc_extension_id(&mut self, id: Option<u8>) -> bool {
+        let mut exts = self.extmap_tx().clone();
+
+        if let Some(id) = id {
+            let in_use = exts
+                .lookup(id)
+                .is_some_and(|e| *e != Extension::TransportSequenceNumber);
+            if in_use {
+                return false;
+            }
+        }
+
+        if let Some(old) = exts.id_of(Extension::TransportSequenceNumber) {
+            exts.remove(old);
+        }
+        if let Some(id) = id {
+            exts.set(id, Extension::TransportSequenceNumber);
+        }
+
+        self.exts_tx = Some(exts);
+        true
+    }
+
+    /// The extension map outgoing packets are written with.
+    pub(crate) fn extmap_tx(&self) -> &ExtensionMap {
+        self.exts_tx.as_ref().unwrap_or(&self.remote_exts)
+    }
+
+    /// Whether the send queue for this media is above the backpressure threshold.
+    ///
+    /// See [`RtcConfig::set_send_backpressure()`][crate::RtcConfig::set_send_backpressure].
+    ///
+    /// RTP level.
+    pub fn is_backpressured(&self) -> bool {
+        self.backpressure
+    }
+
+    pub(crate) fn set_backpressure(&mut self, active: bool, queued: Duration) {
+        if self.backpressure == active {
+            return;
+        }
+        self.backpressure = active;
+        self.pending_backpressure = Some(SendBackpressure {
+            mid: self.mid,
+            active,
+            queued,
+        });
+    }
+
     pub(crate) fn index(&self) -> usize {
         self.index
     }
@@ -255,6 +360,7 @@ impl Media {
                     network_time: dep.first_network_time(),
                     seq_range: dep.seq_range(),
                     contiguous: dep.contiguous,
+                    end_of_frame: dep.end_of_frame(),
                     ext_vals: dep.ext_vals().clone(),
                     codec_extra: dep.codec_extra,
                     last_sender_info: dep.first_sender_info(),
@@ -297,7 +403,12 @@ impl Media {
                 reordering_size_video
             };
 
-            let buffer = DepacketizingBuffer::new(codec.into(), hold_back);
+            let Some(depacketizer) = CodecDepacketizer::new(codec) else {
+                trace!("No depacketizer for {:?}", codec);
+                return;
+            };
+
+            let buffer = DepacketizingBuffer::new(depacketizer, hold_back);
 
             self.depayloaders.insert((pt, rid), buffer);
         }
@@ -311,9 +422,77 @@ impl Media {
             seq_no: packet.seq_no,
             header: packet.header.clone(),
             last_sender_info: packet.last_sender_info,
+            end_of_frame: packet.end_of_frame,
+        };
+
+        buffer.push(meta.clone(), packet.payload);
+
+        // Comfort noise takes sequence numbers in between the audio, which is not loss
+        // of the audio.
+        let is_cn = params
+            .iter()
+            .any(|p| p.pt == pt && p.spec.codec == Codec::Cn);
+
+        if is_cn {
+            self.skip_seq(rid, &meta);
+        }
+    }
+
+    /// Tells the buffers of other PTs about a packet in between theirs.
+    fn skip_seq(&mut self, rid: Option<Rid>, meta: &RtpMeta) {
+        for ((pt, r), buffer) in &mut self.depayloaders {
+            if *pt != meta.header.payload_type && *r == rid {
+                buffer.skip_seq(meta.clone());
+            }
+        }
+    }
+
+    pub(crate) fn handle_dtmf(&mut self, rid: Option<Rid>, packet: &RtpPacket) {
+        if !self.dir.is_receiving() {
+            return;
+        }
+
+        self.dtmf_rx.handle(packet.time, &packet.payload);
+
+        // Like comfort noise, the telephone events use sequence numbers in between
+        // the audio.
+        let meta = RtpMeta {
+            received: packet.timestamp,
+            time: packet.time,
+            seq_no: packet.seq_no,
+            header: packet.header.clone(),
+            last_sender_info: packet.last_sender_info,
+            end_of_frame: packet.end_of_frame,
         };
+        self.skip_seq(rid, &meta);
+    }
+
+    pub(crate) fn poll_dtmf(&mut self) -> Option<DtmfEvent> {
+        let (time, dtmf) = self.dtmf_rx.poll_event()?;
+        Some(DtmfEvent {
+            mid: self.mid,
+            time,
+            dtmf,
+        })
+    }
 
-        buffer.push(meta, packet.payload);
+    pub(crate) fn set_dtmf(
+        &mut self,
+        pt: Pt,
+        rid: Option<Rid>,
+        wallclock: Instant,
+        rtp_time: MediaTime,
+        clock_rate: Frequency,
+        dtmf: Dtmf,
+    ) -> Result<(), RtcError> {
+        if self.dtmf_tx.len() > 100 {
+            return Err(RtcError::WriteWithoutPoll);
+        }
+
+        self.dtmf_tx
+            .push(pt, rid, wallclock, rtp_time, clock_rate, dtmf);
+
+        Ok(())
     }
 
     pub(crate) fn set_cname(&mut self, cname: String) {
@@ -325,6 +504,9 @@ impl Media {
     }
 
     pub(crate) fn set_direction(&mut self, new_dir: Direction) {
+        if self.disabled {
+            return;
+        }
         self.need_changed_event = self.dir != new_dir;
         self.dir = new_dir;
     }
@@ -339,11 +521,16 @@ impl Media {
         pt: Pt,
         rid: Option<Rid>,
         params: &[PayloadParams],
+        red_distance: usize,
     ) -> &mut Payloader {
+        let red_pt = self.audio_red_pt(params);
         self.payloaders.entry((pt, rid)).or_insert_with(|| {
             // Unwrap is OK, the pt should be checked already when calling this function.
             let params = params.iter().find(|p| p.pt == pt).unwrap();
-            Payloader::new(params.spec)
+            let red = red_pt
+                .filter(|_| params.spec.codec == Codec::Opus)
+                .map(|red| RedEncoder::new(red, red_distance));
+            Payloader::new(params.spec, red)
         })
     }
 
@@ -361,7 +548,7 @@ impl Media {
         if !self.to_payload.is_empty() {
             Some(already_happened())
         } else {
-            None
+            self.dtmf_tx.poll_timeout()
         }
     }
 
@@ -370,7 +557,10 @@ impl Media {
         now: Instant,
         streams: &mut Streams,
         params: &[PayloadParams],
+        red_distance: usize,
     ) -> Result<(), RtcError> {
+        self.do_dtmf(now, streams)?;
+
         let Some(to_payload) = self.to_payload.pop_front() else {
             return Ok(());
         };
@@ -387,7 +577,7 @@ impl Media {
 
         let pt = *pt;
 
-        let payloader = self.payloader_for(pt, *rid, params);
+        let payloader = self.payloader_for(pt, *rid, params, red_distance);
 
         const RTP_SIZE: usize = DATAGRAM_MTU - SRTP_OVERHEAD;
         // align to SRTP block size to minimize padding needs
@@ -400,6 +590,29 @@ impl Media {
         Ok(())
     }
 
+    fn do_dtmf(&mut self, now: Instant, streams: &mut Streams) -> Result<(), RtcError> {
+        while let Some(p) = self.dtmf_tx.poll_packet(now) {
+            let Some(stream) = streams.stream_tx_by_mid_rid(self.mid, p.rid) else {
+                return Err(RtcError::NoSenderSource);
+            };
+
+            let seq_no = stream.next_seq_no();
+
+            stream.write_rtp(
+                p.pt,
+                seq_no,
+                p.time,
+                p.wallclock,
+                p.marker,
+                ExtensionValues::default(),
+                false,
+                p.payload,
+            )?;
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn set_remote_pts(&mut self, pts: Vec<Pt>) {
         // Have we already set PTs?
         if !self.remote_pts.is_empty() {
@@ -414,6 +627,7 @@ impl Media {
 
     pub(crate) fn set_remote_extmap(&mut self, exts: ExtensionMap) {
         self.remote_exts = exts;
+        self.exts_tx = None;
     }
 
     /// The remote PT (payload types) configured for this Media.
@@ -440,6 +654,15 @@ impl Media {
         self.remote_created
     }
 
+    pub(crate) fn is_disabled(&self) -> bool {
+        self.disabled
+    }
+
+    pub(crate) fn disable(&mut self) {
+        self.dir = Direction::Inactive;
+        self.disabled = true;
+    }
+
     pub(crate) fn first_pt_with_rtx(&self, config: &CodecConfig) -> Option<Pt> {
         config
             .all_for_kind(self.kind)
@@ -449,6 +672,45 @@ impl Media {
             .find_map(|p| p.resend().map(|_| p.pt))
     }
 
+    /// The FlexFEC PT, if negotiated for this media.
+    pub(crate) fn flexfec_pt(&self, config: &CodecConfig) -> Option<Pt> {
+        if !self.kind.is_video() {
+            return None;
+        }
+
+        config
+            .iter()
+            .filter(|p| p.spec().codec == Codec::FlexFec)
+            .map(|p| p.pt())
+            .find(|pt| self.remote_pts.contains(pt))
+    }
+
+    /// The RED PT for Opus, if negotiated for this media.
+    fn audio_red_pt(&self, params: &[PayloadParams]) -> Option<Pt> {
+        params
+            .iter()
+            .filter(|p| p.spec().is_audio_red())
+            .map(|p| p.pt())
+            .find(|pt| self.remote_pts.contains(pt))
+    }
+
+    /// The RED and ULPFEC PTs, if both are negotiated for this media.
+    pub(crate) fn ulpfec_pts(&self, config: &CodecConfig) -> Option<(Pt, Pt)> {
+        if !self.kind.is_video() {
+            return None;
+        }
+
+        let find = |codec: Codec| {
+            config
+                .iter()
+                .filter(|p| p.spec().codec == codec)
+                .map(|p| p.pt())
+                .find(|pt| self.remote_pts.contains(pt))
+        };
+
+        Some((find(Codec::Red)?, find(Codec::Ulpfec)?))
+    }
+
     pub(crate) fn reset_depayloader(&mut self, payload_type: Pt, rid: Option<Rid>) {
         // Simply remove the depayloader, it will be re-created on the next RTP packet.
         self.depayloaders.remove(&(payload_type, rid));
@@ -469,15 +731,22 @@ impl Default for Media {
             kind: MediaKind::Video,
             remote_pts: vec![],
             remote_exts: ExtensionMap::empty(),
+            exts_tx: None,
             remote_created: false,
+            disabled: false,
             dir: Direction::SendRecv,
             simulcast: None,
             rids_rx: Rids::Any,
+            keyframe_request_policy: KeyframeRequestPolicy::default(),
             payloaders: HashMap::new(),
             depayloaders: HashMap::new(),
             to_payload: VecDeque::default(),
+            dtmf_tx: DtmfSender::default(),
+            dtmf_rx: DtmfReceiver::default(),
             need_open_event: true,
             need_changed_event: false,
+            backpressure: false,
+            pending_backpressure: None,
         }
     }
 }
@@ -1,11 +1,12 @@
 use std::time::Instant;
 
-use crate::format::PayloadParams;
+use crate::format::{Codec, PayloadParams};
 use crate::rtp_::VideoOrientation;
 use crate::session::Session;
 use crate::RtcError;
 
-use super::{ExtensionValues, KeyframeRequestKind, Media, MediaTime, Mid, Pt, Rid, ToPayload};
+use super::{Dtmf, ExtensionValues, KeyframeRequestKind, KeyframeRequestPolicy};
+use super::{Media, MediaTime, Mid, Pt, Rid, ToPayload};
 
 /// Writer of sample level data.
 ///
@@ -150,6 +151,46 @@ impl<'a> Writer<'a> {
         Ok(())
     }
 
+    /// Send a DTMF tone as RFC 4733 telephone events.
+    ///
+    /// The `pt` must be for [`Codec::TelephoneEvent`], see
+    /// [`RtcConfig::enable_dtmf()`][crate::RtcConfig::enable_dtmf]. The `rtp_time` is when the
+    /// tone starts, in the same time line as the audio written with [`Writer::write()`].
+    ///
+    /// The tone is updated every 50ms until its duration has passed, and the end is sent
+    /// three times. Tones written back to back are sent one after the other.
+    pub fn write_dtmf(
+        self,
+        pt: Pt,
+        wallclock: Instant,
+        rtp_time: MediaTime,
+        dtmf: Dtmf,
+    ) -> Result<(), RtcError> {
+        let Some(params) = self
+            .session
+            .codec_config
+            .iter()
+            .find(|p| p.pt() == pt && p.spec().codec == Codec::TelephoneEvent)
+        else {
+            return Err(RtcError::UnknownPt(pt));
+        };
+        let clock_rate = params.spec().clock_rate;
+
+        // This (indirect) unwrap is OK due to the invariant of self.mid being resolvable
+        let media = media_by_mid_mut(&mut self.session.medias, self.mid);
+
+        trace!(
+            "write_dtmf {:?} {:?} {:?} time: {:?} {:?}",
+            self.mid,
+            self.rid,
+            pt,
+            rtp_time,
+            dtmf
+        );
+
+        media.set_dtmf(pt, self.rid, wallclock, rtp_time, clock_rate, dtmf)
+    }
+
     /// Test if the kind of keyframe request is possible.
     ///
     /// Sending a keyframe request requires the mechanic to be negotiated as a feedback mechanic
@@ -206,6 +247,45 @@ impl<'a> Writer<'a> {
 
         Ok(())
     }
+
+    /// Set the policy for how keyframe requests are sent for this media.
+    ///
+    /// Applies to all incoming streams of the media, both current and future ones,
+    /// and to requests made via [`StreamRx::request_keyframe()`][crate::rtp::StreamRx::request_keyframe].
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use str0m::Rtc;
+    /// # use str0m::media::{Mid, KeyframeRequestPolicy};
+    /// let mut rtc = Rtc::new();
+    ///
+    /// let mid: Mid = todo!(); // obtain mid from Event::MediaAdded.
+    ///
+    /// let writer = rtc.writer(mid).unwrap();
+    ///
+    /// writer.set_keyframe_request_policy(KeyframeRequestPolicy {
+    ///     min_interval: Duration::from_millis(300),
+    ///     ..Default::default()
+    /// });
+    /// ```
+    pub fn set_keyframe_request_policy(&mut self, policy: KeyframeRequestPolicy) {
+        let media = media_by_mid_mut(&mut self.session.medias, self.mid);
+        media.set_keyframe_request_policy(policy);
+    }
+
+    /// Whether writes for this media currently outpace what can be sent.
+    ///
+    /// Only ever `true` when [`RtcConfig::set_send_backpressure()`][crate::RtcConfig::set_send_backpressure]
+    /// is configured. Changes are also signalled via
+    /// [`Event::SendBackpressure`][crate::Event::SendBackpressure].
+    ///
+    /// Writing while backpressured is still possible, but the data is likely to be
+    /// queued for a long time.
+    pub fn is_backpressured(&self) -> bool {
+        // This unwrap is OK due to the invariant of self.mid being resolvable
+        let media = self.session.media_by_mid(self.mid).unwrap();
+        media.is_backpressured()
+    }
 }
 
 /// Get a &mut Media in a slice for a `mid`.
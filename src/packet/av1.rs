@@ -0,0 +1,437 @@
+use super::{CodecExtra, Depacketizer, PacketError, Packetizer};
+
+/// AV1 information describing the depacketized / packetized data
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Av1CodecExtra {
+    /// Flag which indicates that within [`MediaData`], there is an individual frame
+    /// containing complete and independent visual information. This frame serves
+    /// as a reference point for other frames in the video sequence.
+    ///
+    /// [`MediaData`]: crate::media::MediaData
+    pub is_keyframe: bool,
+}
+
+// https://aomediacodec.github.io/av1-rtp-spec/#44-av1-aggregation-header
+//
+//  0 1 2 3 4 5 6 7
+// +-+-+-+-+-+-+-+-+
+// |Z|Y| W |N|-|-|-|
+// +-+-+-+-+-+-+-+-+
+const AGGREGATION_HEADER_SIZE: usize = 1;
+const Z_BIT: u8 = 0x80;
+const Y_BIT: u8 = 0x40;
+const W_MASK: u8 = 0x30;
+const N_BIT: u8 = 0x08;
+
+// https://aomediacodec.github.io/av1-spec/#obu-header-syntax
+//
+//  0 1 2 3 4 5 6 7
+// +-+-+-+-+-+-+-+-+
+// |F| type  |X|S|-|
+// +-+-+-+-+-+-+-+-+
+const OBU_TYPE_MASK: u8 = 0x78;
+const OBU_EXTENSION_BIT: u8 = 0x04;
+const OBU_HAS_SIZE_BIT: u8 = 0x02;
+
+const OBU_SEQUENCE_HEADER: u8 = 1;
+const OBU_TEMPORAL_DELIMITER: u8 = 2;
+const OBU_TILE_LIST: u8 = 8;
+
+/// Packetizes AV1 RTP packets.
+///
+/// The input is a temporal unit in the low overhead bitstream format, i.e. OBUs with
+/// size fields. The OBUs are sent without size fields, each preceded by its length.
+#[derive(Default, Debug, Clone)]
+pub struct Av1Packetizer;
+
+impl Packetizer for Av1Packetizer {
+    fn packetize(&mut self, mtu: usize, payload: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
+        // Need room for the aggregation header, a length and at least one byte of OBU.
+        if payload.is_empty() || mtu <= AGGREGATION_HEADER_SIZE + 1 {
+            return Ok(vec![]);
+        }
+
+        let obus = parse_obus(payload)?;
+
+        // The N bit is set on the first packet of a coded video sequence, which is
+        // where the sequence header is sent.
+        let is_new_sequence = obus
+            .iter()
+            .any(|o| obu_type(o.header) == OBU_SEQUENCE_HEADER);
+
+        let mut packets = vec![];
+        let mut current = vec![0];
+
+        for obu in obus {
+            let t = obu_type(obu.header);
+            if t == OBU_TEMPORAL_DELIMITER || t == OBU_TILE_LIST {
+                // These must be removed from the RTP stream.
+                continue;
+            }
+
+            let mut element = Vec::with_capacity(obu.header_len() + obu.payload.len());
+            element.push(obu.header & !OBU_HAS_SIZE_BIT);
+            element.extend(obu.extension);
+            element.extend_from_slice(obu.payload);
+
+            let mut rest = &element[..];
+
+            while !rest.is_empty() {
+                let room = mtu - current.len();
+
+                if room >= leb128_size(rest.len()) + rest.len() {
+                    write_leb128(&mut current, rest.len());
+                    current.extend_from_slice(rest);
+                    break;
+                }
+
+                let fragment_len = room.saturating_sub(leb128_size(room));
+
+                if fragment_len > 0 {
+                    write_leb128(&mut current, fragment_len);
+                    current.extend_from_slice(&rest[..fragment_len]);
+                    rest = &rest[fragment_len..];
+
+                    // The last element continues in the next packet.
+                    current[0] |= Y_BIT;
+                    packets.push(current);
+                    current = vec![Z_BIT];
+                } else {
+                    packets.push(current);
+                    current = vec![0];
+                }
+            }
+        }
+
+        if current.len() > AGGREGATION_HEADER_SIZE {
+            packets.push(current);
+        }
+
+        if is_new_sequence {
+            if let Some(first) = packets.first_mut() {
+                first[0] |= N_BIT;
+            }
+        }
+
+        Ok(packets)
+    }
+
+    fn is_marker(&mut self, _data: &[u8], _previous: Option<&[u8]>, last: bool) -> bool {
+        last
+    }
+}
+
+/// Depacketizes AV1 RTP packets.
+///
+/// The output is in the low overhead bitstream format, where every OBU has a size field.
+#[derive(PartialEq, Eq, Debug, Default, Clone)]
+pub struct Av1Depacketizer {
+    fragment: Option<Vec<u8>>,
+}
+
+impl Depacketizer for Av1Depacketizer {
+    fn depacketize(
+        &mut self,
+        packet: &[u8],
+        out: &mut Vec<u8>,
+        extra: &mut CodecExtra,
+    ) -> Result<(), PacketError> {
+        if packet.len() <= AGGREGATION_HEADER_SIZE {
+            return Err(PacketError::ErrShortPacket);
+        }
+
+        let header = packet[0];
+        let z = header & Z_BIT > 0;
+        let y = header & Y_BIT > 0;
+        let w = ((header & W_MASK) >> 4) as usize;
+        let n = header & N_BIT > 0;
+
+        let is_keyframe = if let CodecExtra::Av1(e) = extra {
+            n | e.is_keyframe
+        } else {
+            n
+        };
+        *extra = CodecExtra::Av1(Av1CodecExtra { is_keyframe });
+
+        if !z {
+            // A previous fragment that was never completed is dropped.
+            self.fragment = None;
+        }
+
+        let mut buf = &packet[AGGREGATION_HEADER_SIZE..];
+        let mut index = 0;
+
+        while !buf.is_empty() {
+            index += 1;
+
+            // When W is set, the last element has no length field.
+            let len = if w > 0 && index == w {
+                buf.len()
+            } else {
+                let (len, n) = read_leb128(buf).ok_or(PacketError::ErrShortPacket)?;
+                buf = &buf[n..];
+                len
+            };
+
+            if len > buf.len() {
+                return Err(PacketError::ErrShortPacket);
+            }
+
+            let element = &buf[..len];
+            buf = &buf[len..];
+
+            let is_first = index == 1;
+            let is_last = buf.is_empty();
+
+            let obu = if is_first && z {
+                // Continuation of a fragment. If we never saw the start, drop it.
+                let Some(mut fragment) = self.fragment.take() else {
+                    continue;
+                };
+                fragment.extend_from_slice(element);
+                fragment
+            } else {
+                element.to_vec()
+            };
+
+            if is_last && y {
+                self.fragment = Some(obu);
+            } else {
+                write_obu(&obu, out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn is_partition_head(&self, packet: &[u8]) -> bool {
+        packet.first().is_some_and(|header| header & Z_BIT == 0)
+    }
+
+    fn is_partition_tail(&self, marker: bool, _packet: &[u8]) -> bool {
+        marker
+    }
+}
+
+/// Write a complete OBU from an RTP OBU element, adding the size field.
+fn write_obu(element: &[u8], out: &mut Vec<u8>) -> Result<(), PacketError> {
+    let Some(&header) = element.first() else {
+        return Err(PacketError::ErrShortPacket);
+    };
+
+    if header & OBU_HAS_SIZE_BIT > 0 {
+        // Already in the low overhead format.
+        out.extend_from_slice(element);
+        return Ok(());
+    }
+
+    let header_len = if header & OBU_EXTENSION_BIT > 0 { 2 } else { 1 };
+    if element.len() < header_len {
+        return Err(PacketError::ErrShortPacket);
+    }
+
+    out.push(header | OBU_HAS_SIZE_BIT);
+    out.extend_from_slice(&element[1..header_len]);
+    write_leb128(out, element.len() - header_len);
+    out.extend_from_slice(&element[header_len..]);
+
+    Ok(())
+}
+
+struct Obu<'a> {
+    header: u8,
+    extension: Option<u8>,
+    payload: &'a [u8],
+}
+
+impl Obu<'_> {
+    fn header_len(&self) -> usize {
+        1 + self.extension.is_some() as usize
+    }
+}
+
+fn obu_type(header: u8) -> u8 {
+    (header & OBU_TYPE_MASK) >> 3
+}
+
+fn parse_obus(mut buf: &[u8]) -> Result<Vec<Obu<'_>>, PacketError> {
+    let mut obus = vec![];
+
+    while !buf.is_empty() {
+        let header = buf[0];
+        buf = &buf[1..];
+
+        let extension = if header & OBU_EXTENSION_BIT > 0 {
+            let ext = *buf.first().ok_or(PacketError::ErrShortPacket)?;
+            buf = &buf[1..];
+            Some(ext)
+        } else {
+            None
+        };
+
+        let len = if header & OBU_HAS_SIZE_BIT > 0 {
+            let (len, n) = read_leb128(buf).ok_or(PacketError::ErrShortPacket)?;
+            buf = &buf[n..];
+            len
+        } else {
+            // Without size field, the OBU extends to the end.
+            buf.len()
+        };
+
+        if len > buf.len() {
+            return Err(PacketError::ErrShortPacket);
+        }
+
+        obus.push(Obu {
+            header,
+            extension,
+            payload: &buf[..len],
+        });
+        buf = &buf[len..];
+    }
+
+    Ok(obus)
+}
+
+fn leb128_size(mut v: usize) -> usize {
+    let mut n = 1;
+    while v >= 0x80 {
+        v >>= 7;
+        n += 1;
+    }
+    n
+}
+
+fn write_leb128(out: &mut Vec<u8>, mut v: usize) {
+    while v >= 0x80 {
+        out.push((v & 0x7f) as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+/// Returns the value and the number of bytes read.
+fn read_leb128(buf: &[u8]) -> Option<(usize, usize)> {
+    let mut v: usize = 0;
+
+    // The spec limits it to 8 bytes.
+    for (i, b) in buf.iter().take(8).enumerate() {
+        v |= ((b & 0x7f) as usize) << (i * 7);
+        if b & 0x80 == 0 {
+            return Some((v, i + 1));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn obu(t: u8, payload: &[u8]) -> Vec<u8> {
+        let mut v = vec![(t << 3) | OBU_HAS_SIZE_BIT];
+        write_leb128(&mut v, payload.len());
+        v.extend_from_slice(payload);
+        v
+    }
+
+    #[test]
+    fn leb128() {
+        for v in [0, 1, 127, 128, 300, 16383, 16384, 1 << 30] {
+            let mut buf = vec![];
+            write_leb128(&mut buf, v);
+            assert_eq!(buf.len(), leb128_size(v));
+            assert_eq!(read_leb128(&buf), Some((v, buf.len())));
+        }
+        assert_eq!(read_leb128(&[0x80, 0x80]), None);
+    }
+
+    #[test]
+    fn packetize_aggregates() -> Result<(), PacketError> {
+        let mut data = obu(OBU_TEMPORAL_DELIMITER, &[]);
+        data.extend(obu(OBU_SEQUENCE_HEADER, &[1, 2, 3]));
+        data.extend(obu(6, &[4, 5]));
+
+        let mut pck = Av1Packetizer;
+        let packets = pck.packetize(1200, &data)?;
+
+        // Temporal delimiter dropped, size fields removed, N set for the sequence header.
+        assert_eq!(packets, vec![vec![N_BIT, 4, 0x08, 1, 2, 3, 3, 0x30, 4, 5]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn packetize_fragments() -> Result<(), PacketError> {
+        let payload: Vec<u8> = (0..20).collect();
+        let data = obu(6, &payload);
+
+        let mut pck = Av1Packetizer;
+        let packets = pck.packetize(10, &data)?;
+
+        assert_eq!(packets.len(), 3);
+        assert_eq!(packets[0][0], Y_BIT);
+        assert_eq!(packets[1][0], Z_BIT | Y_BIT);
+        assert_eq!(packets[2][0], Z_BIT);
+        assert!(packets.iter().all(|p| p.len() <= 10));
+
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip() -> Result<(), PacketError> {
+        let mut data = obu(OBU_SEQUENCE_HEADER, &[1, 2, 3]);
+        data.extend(obu(6, &(0..=255).collect::<Vec<_>>()));
+        data.extend(obu(6, &[7, 8, 9]));
+
+        let mut pck = Av1Packetizer;
+        let packets = pck.packetize(100, &data)?;
+        assert!(packets.len() > 1);
+
+        let mut depack = Av1Depacketizer::default();
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+
+        for (i, p) in packets.iter().enumerate() {
+            assert_eq!(depack.is_partition_head(p), i == 0);
+            depack.depacketize(p, &mut out, &mut extra)?;
+        }
+
+        assert_eq!(out, data);
+        assert_eq!(extra, CodecExtra::Av1(Av1CodecExtra { is_keyframe: true }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn depacketize_w_field() -> Result<(), PacketError> {
+        // Two elements, W=2, the last without length.
+        let packet = [0x20, 2, 0x30, 1, 0x30, 2, 3];
+
+        let mut depack = Av1Depacketizer::default();
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+        depack.depacketize(&packet, &mut out, &mut extra)?;
+
+        assert_eq!(out, [0x32, 1, 1, 0x32, 2, 2, 3]);
+        assert_eq!(extra, CodecExtra::Av1(Av1CodecExtra { is_keyframe: false }));
+
+        Ok(())
+    }
+
+    #[test]
+    fn depacketize_drops_orphan_fragment() -> Result<(), PacketError> {
+        // Continuation without a preceding start, followed by a complete OBU.
+        let packet = [Z_BIT, 2, 9, 9, 2, 0x30, 1];
+
+        let mut depack = Av1Depacketizer::default();
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+        depack.depacketize(&packet, &mut out, &mut extra)?;
+
+        assert_eq!(out, [0x32, 1, 1]);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,606 @@
+use std::time::Instant;
+
+use crate::format::Codec;
+use crate::rtp_::{Rid, SeqNo};
+use crate::streams::RtpPacket;
+
+use super::h264::{FUA_NALU_TYPE, IDR_NALU_TYPE, SPS_NALU_TYPE, STAPA_NALU_TYPE};
+use super::{CodecDepacketizer, CodecExtra, Depacketizer};
+
+/// RTP clock rate of the video codecs handled by the bridge.
+const VIDEO_CLOCK_RATE: f64 = 90_000.0;
+
+/// Forwards one layer of a simulcast as a single outgoing stream.
+///
+/// An SFU receiving simulcast (one SSRC per rid) often has consumers that expect a single
+/// stream, like they would get with SVC. The bridge picks which incoming layer to forward
+/// and rewrites the packets so the layer switches are invisible to the consumer:
+///
+/// * Sequence numbers and RTP timestamps continue from the previously forwarded layer.
+/// * VP8 picture id and TL0PICIDX, and VP9 picture id and TL0PICIDX (non-flexible mode),
+///   are rewritten to continue as well.
+/// * H.264 needs no rewriting, since a switch happens on an IDR which restarts `frame_num`.
+/// * AV1 has no ids in the payload. The frame numbers of the dependency descriptor header
+///   extension are not rewritten, so that extension should not be forwarded as is.
+///
+/// Switching only happens at a keyframe of the target layer. Until then the current layer
+/// keeps being forwarded. Use [`SimulcastBridge::wants_keyframe()`] to know which layer to
+/// request a keyframe for.
+///
+/// The bridge doesn't transcode. It is the glue that lets a consumer of one stream, such as
+/// one expecting SVC with a single active spatial layer, be fed from simulcast. With the
+/// layers set using [`SimulcastBridge::set_layers()`], the forwarding decisions can be made
+/// in SVC terms, where spatial layer 0 is the lowest rid.
+///
+/// ```
+/// # use str0m::format::Codec;
+/// # use str0m::media::Rid;
+/// # use str0m::rtp::SimulcastBridge;
+/// let mut bridge = SimulcastBridge::new(Codec::Vp8).unwrap();
+/// bridge.set_layers(vec![Rid::from("l"), Rid::from("m"), Rid::from("h")]);
+/// bridge.set_target_spatial_layer(Some(2));
+///
+/// // Nothing is forwarded until the target layer has a keyframe.
+/// assert_eq!(bridge.wants_keyframe(), Some(Rid::from("h")));
+/// assert_eq!(bridge.spatial_layer(), None);
+/// ```
+#[derive(Debug)]
+pub struct SimulcastBridge {
+    codec: Codec,
+
+    /// The layers from lowest to highest, used as spatial layer ids.
+    layers: Vec<Rid>,
+
+    /// The layer we want to forward.
+    target: Option<Rid>,
+
+    /// The layer we are forwarding.
+    current: Option<Rid>,
+
+    /// The incoming sequence number the current layer was switched to on.
+    switch_seq_no: SeqNo,
+
+    /// Added to incoming sequence numbers.
+    seq_no_offset: u64,
+
+    /// Added to incoming RTP timestamps.
+    time_offset: u32,
+
+    /// Added to incoming picture ids (15 bit).
+    picture_id_offset: u16,
+
+    /// Added to incoming TL0PICIDX.
+    tl0_pic_idx_offset: u8,
+
+    /// The most recent forwarded packet.
+    last: Option<LastForwarded>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LastForwarded {
+    seq_no: SeqNo,
+    time: u32,
+    picture_id: Option<u16>,
+    tl0_pic_idx: Option<u8>,
+    timestamp: Instant,
+}
+
+/// A packet rewritten by [`SimulcastBridge::forward()`].
+///
+/// The values go straight into [`StreamTx::write_rtp()`][crate::rtp::StreamTx::write_rtp].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BridgedRtp {
+    /// Sequence number in the outgoing stream.
+    pub seq_no: SeqNo,
+    /// RTP timestamp in the outgoing stream.
+    pub time: u32,
+    /// RTP marker bit, unchanged.
+    pub marker: bool,
+    /// The payload, with codec specific ids rewritten.
+    pub payload: Vec<u8>,
+}
+
+impl SimulcastBridge {
+    /// Create a bridge for a video codec.
+    ///
+    /// Returns `None` if the codec is not VP8, VP9, H.264 or AV1.
+    pub fn new(codec: Codec) -> Option<Self> {
+        if !matches!(codec, Codec::Vp8 | Codec::Vp9 | Codec::H264 | Codec::Av1) {
+            return None;
+        }
+
+        Some(SimulcastBridge {
+            codec,
+            layers: vec![],
+            target: None,
+            current: None,
+            switch_seq_no: 0.into(),
+            seq_no_offset: 0,
+            time_offset: 0,
+            picture_id_offset: 0,
+            tl0_pic_idx_offset: 0,
+            last: None,
+        })
+    }
+
+    /// Set the simulcast layers from lowest to highest.
+    ///
+    /// The position of a rid in the list is its spatial layer id.
+    pub fn set_layers(&mut self, rids: Vec<Rid>) {
+        self.layers = rids;
+    }
+
+    /// Set the spatial layer to forward. `None` stops forwarding straight away.
+    ///
+    /// A layer above the highest one set with [`SimulcastBridge::set_layers()`] targets
+    /// the highest.
+    pub fn set_target_spatial_layer(&mut self, layer: Option<usize>) {
+        let rid = layer.and_then(|l| {
+            let l = l.min(self.layers.len().checked_sub(1)?);
+            Some(self.layers[l])
+        });
+        self.set_target(rid);
+    }
+
+    /// The spatial layer currently forwarded.
+    pub fn spatial_layer(&self) -> Option<usize> {
+        let current = self.current?;
+        self.layers.iter().position(|r| *r == current)
+    }
+
+    /// Set the layer to forward. `None` stops forwarding straight away.
+    pub fn set_target(&mut self, rid: Option<Rid>) {
+        self.target = rid;
+        if rid.is_none() {
+            self.current = None;
+        }
+    }
+
+    /// The layer we want to forward.
+    pub fn target(&self) -> Option<Rid> {
+        self.target
+    }
+
+    /// The layer currently forwarded.
+    pub fn current(&self) -> Option<Rid> {
+        self.current
+    }
+
+    /// The layer waiting for a keyframe to be switched to.
+    ///
+    /// The application should request a keyframe for it, e.g. using
+    /// [`StreamRx::request_keyframe()`][crate::rtp::StreamRx::request_keyframe].
+    pub fn wants_keyframe(&self) -> Option<Rid> {
+        self.target.filter(|t| Some(*t) != self.current)
+    }
+
+    /// Handle an incoming packet of the layer `rid`.
+    ///
+    /// Returns the rewritten packet to send, or `None` if the packet is not forwarded.
+    pub fn forward(&mut self, rid: Rid, packet: &RtpPacket) -> Option<BridgedRtp> {
+        if Some(rid) != self.current {
+            if Some(rid) != self.target || !self.is_keyframe_start(&packet.payload) {
+                return None;
+            }
+            self.switch_to(rid, packet);
+        } else if packet.seq_no < self.switch_seq_no {
+            // Reordered packet from before the switch.
+            return None;
+        }
+
+        let mut payload = packet.payload.clone();
+        let ids = self.rewrite_ids(&mut payload);
+
+        let seq_no: SeqNo = packet.seq_no.wrapping_add(self.seq_no_offset).into();
+        let time = packet.header.timestamp.wrapping_add(self.time_offset);
+
+        let newer = self.last.map(|l| seq_no > l.seq_no).unwrap_or(true);
+        if newer {
+            self.last = Some(LastForwarded {
+                seq_no,
+                time,
+                picture_id: ids.picture_id,
+                tl0_pic_idx: ids.tl0_pic_idx,
+                timestamp: packet.timestamp,
+            });
+        }
+
+        Some(BridgedRtp {
+            seq_no,
+            time,
+            marker: packet.header.marker,
+            payload,
+        })
+    }
+
+    fn switch_to(&mut self, rid: Rid, packet: &RtpPacket) {
+        debug!("Simulcast bridge switch {:?} -> {:?}", self.current, rid);
+
+        self.current = Some(rid);
+        self.switch_seq_no = packet.seq_no;
+
+        let Some(last) = self.last else {
+            // First layer forwarded, no need to offset anything.
+            return;
+        };
+
+        self.seq_no_offset = (*last.seq_no + 1).wrapping_sub(*packet.seq_no);
+
+        // Advance the RTP time by the wallclock time since the last forwarded packet.
+        let elapsed = packet.timestamp.saturating_duration_since(last.timestamp);
+        let ticks = ((elapsed.as_secs_f64() * VIDEO_CLOCK_RATE).round() as u32).max(1);
+        self.time_offset = last
+            .time
+            .wrapping_add(ticks)
+            .wrapping_sub(packet.header.timestamp);
+
+        let ids = self.read_ids(&packet.payload);
+
+        if let (Some(prev), Some(next)) = (last.picture_id, ids.picture_id) {
+            self.picture_id_offset = prev.wrapping_add(1).wrapping_sub(next) & 0x7fff;
+        }
+        if let (Some(prev), Some(next)) = (last.tl0_pic_idx, ids.tl0_pic_idx) {
+            self.tl0_pic_idx_offset = prev.wrapping_add(1).wrapping_sub(next);
+        }
+    }
+
+    fn is_keyframe_start(&self, payload: &[u8]) -> bool {
+        if self.codec == Codec::H264 {
+            return h264_is_keyframe_start(payload);
+        }
+
+        let Some(mut depacketizer) = CodecDepacketizer::new(self.codec) else {
+            return false;
+        };
+        if !depacketizer.is_partition_head(payload) {
+            return false;
+        }
+
+        let mut out = Vec::new();
+        let mut extra = CodecExtra::None;
+        if depacketizer
+            .depacketize(payload, &mut out, &mut extra)
+            .is_err()
+        {
+            return false;
+        }
+
+        match extra {
+            CodecExtra::Vp8(e) => e.is_keyframe,
+            CodecExtra::Vp9(e) => e.is_keyframe,
+            CodecExtra::Av1(e) => e.is_keyframe,
+            _ => false,
+        }
+    }
+
+    fn read_ids(&self, payload: &[u8]) -> Ids {
+        let pos = IdPositions::find(self.codec, payload);
+
+        Ids {
+            picture_id: pos.picture_id.map(|(i, long)| {
+                if long {
+                    ((payload[i] as u16 & 0x7f) << 8) | payload[i + 1] as u16
+                } else {
+                    payload[i] as u16 & 0x7f
+                }
+            }),
+            tl0_pic_idx: pos.tl0_pic_idx.map(|i| payload[i]),
+        }
+    }
+
+    /// Apply the offsets to the ids in the payload, returning the new values.
+    fn rewrite_ids(&self, payload: &mut [u8]) -> Ids {
+        let pos = IdPositions::find(self.codec, payload);
+        let ids = self.read_ids(payload);
+
+        let picture_id = ids.picture_id.zip(pos.picture_id).map(|(v, (i, long))| {
+            if long {
+                let v = v.wrapping_add(self.picture_id_offset) & 0x7fff;
+                payload[i] = 0x80 | (v >> 8) as u8;
+                payload[i + 1] = v as u8;
+                v
+            } else {
+                let v = v.wrapping_add(self.picture_id_offset) & 0x7f;
+                payload[i] = v as u8;
+                v
+            }
+        });
+
+        let tl0_pic_idx = ids.tl0_pic_idx.zip(pos.tl0_pic_idx).map(|(v, i)| {
+            let v = v.wrapping_add(self.tl0_pic_idx_offset);
+            payload[i] = v;
+            v
+        });
+
+        Ids {
+            picture_id,
+            tl0_pic_idx,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Ids {
+    picture_id: Option<u16>,
+    tl0_pic_idx: Option<u8>,
+}
+
+/// Where the ids are in the payload descriptor.
+#[derive(Debug, Default)]
+struct IdPositions {
+    /// Index and whether it's the 15 bit form.
+    picture_id: Option<(usize, bool)>,
+    tl0_pic_idx: Option<usize>,
+}
+
+impl IdPositions {
+    fn find(codec: Codec, p: &[u8]) -> Self {
+        match codec {
+            Codec::Vp8 => Self::vp8(p),
+            Codec::Vp9 => Self::vp9(p),
+            _ => Self::default(),
+        }
+        .within(p.len())
+    }
+
+    // https://datatracker.ietf.org/doc/html/rfc7741#section-4.2
+    //
+    //       0 1 2 3 4 5 6 7
+    //      +-+-+-+-+-+-+-+-+
+    //      |X|R|N|S|R| PID | (REQUIRED)
+    //      +-+-+-+-+-+-+-+-+
+    // X:   |I|L|T|K| RSV   | (OPTIONAL)
+    //      +-+-+-+-+-+-+-+-+
+    // I:   |M| PictureID   | (OPTIONAL)
+    //      +-+-+-+-+-+-+-+-+
+    // L:   |   tl0picidx   | (OPTIONAL)
+    //      +-+-+-+-+-+-+-+-+
+    fn vp8(p: &[u8]) -> Self {
+        let mut pos = Self::default();
+
+        if p.len() < 2 || p[0] & 0x80 == 0 {
+            return pos;
+        }
+
+        let i = p[1] & 0x80 != 0;
+        let l = p[1] & 0x40 != 0;
+        let mut idx = 2;
+
+        if i {
+            let long = p.get(idx).map(|b| b & 0x80 != 0).unwrap_or(false);
+            pos.picture_id = Some((idx, long));
+            idx += if long { 2 } else { 1 };
+        }
+
+        if l {
+            pos.tl0_pic_idx = Some(idx);
+        }
+
+        pos
+    }
+
+    // https://datatracker.ietf.org/doc/html/draft-ietf-payload-vp9-16#section-4.2
+    //
+    //       0 1 2 3 4 5 6 7
+    //      +-+-+-+-+-+-+-+-+
+    //      |I|P|L|F|B|E|V|Z| (REQUIRED)
+    //      +-+-+-+-+-+-+-+-+
+    // I:   |M| PICTURE ID  | (RECOMMENDED)
+    //      +-+-+-+-+-+-+-+-+
+    // L:   |  TID|U| SID |D| (Conditionally RECOMMENDED)
+    //      +-+-+-+-+-+-+-+-+
+    //      |   TL0PICIDX   | (Conditionally REQUIRED, non-flexible mode)
+    //      +-+-+-+-+-+-+-+-+
+    fn vp9(p: &[u8]) -> Self {
+        let mut pos = Self::default();
+
+        let Some(b0) = p.first() else {
+            return pos;
+        };
+
+        let i = b0 & 0x80 != 0;
+        let l = b0 & 0x20 != 0;
+        let f = b0 & 0x10 != 0;
+        let mut idx = 1;
+
+        if i {
+            let long = p.get(idx).map(|b| b & 0x80 != 0).unwrap_or(false);
+            pos.picture_id = Some((idx, long));
+            idx += if long { 2 } else { 1 };
+        }
+
+        if l && !f {
+            pos.tl0_pic_idx = Some(idx + 1);
+        }
+
+        pos
+    }
+
+    /// Drop positions that are outside a truncated payload.
+    fn within(mut self, len: usize) -> Self {
+        if let Some((i, long)) = self.picture_id {
+            if i + if long { 2 } else { 1 } > len {
+                self.picture_id = None;
+            }
+        }
+        if self.tl0_pic_idx.map(|i| i >= len).unwrap_or(false) {
+            self.tl0_pic_idx = None;
+        }
+        self
+    }
+}
+
+/// Whether the packet starts a keyframe, including the SPS preceding the IDR.
+fn h264_is_keyframe_start(p: &[u8]) -> bool {
+    let Some(b0) = p.first() else {
+        return false;
+    };
+
+    let is_key = |t: u8| t == SPS_NALU_TYPE || t == IDR_NALU_TYPE;
+
+    match b0 & 0x1f {
+        // First NALU after the STAP-A header and the 2 byte size.
+        STAPA_NALU_TYPE => p.get(3).map(|b| is_key(b & 0x1f)).unwrap_or(false),
+        // FU-A with the start bit set.
+        FUA_NALU_TYPE => p
+            .get(1)
+            .map(|b| b & 0x80 != 0 && b & 0x1f == IDR_NALU_TYPE)
+            .unwrap_or(false),
+        t => is_key(t),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    /// VP8 packet with a 15 bit picture id and TL0PICIDX.
+    fn vp8(seq_no: u64, time: u32, picture_id: u16, tl0: u8, keyframe: bool) -> RtpPacket {
+        let mut packet = RtpPacket::blank();
+        packet.seq_no = seq_no.into();
+        packet.header.timestamp = time;
+        packet.header.marker = true;
+        packet.timestamp = Instant::now();
+        // X, S, PID=0 | I, L | M, picture id | tl0 | VP8 payload header with P bit.
+        packet.payload = vec![
+            0x90,
+            0xc0,
+            0x80 | (picture_id >> 8) as u8,
+            picture_id as u8,
+            tl0,
+            if keyframe { 0x00 } else { 0x01 },
+            0,
+            0,
+        ];
+        packet
+    }
+
+    fn ids(p: &BridgedRtp) -> (u16, u8) {
+        let pid = ((p.payload[2] as u16 & 0x7f) << 8) | p.payload[3] as u16;
+        (pid, p.payload[4])
+    }
+
+    #[test]
+    fn waits_for_keyframe_on_target() {
+        let mut b = SimulcastBridge::new(Codec::Vp8).unwrap();
+        let low = Rid::from("l");
+
+        assert!(b.forward(low, &vp8(10, 1000, 5, 1, true)).is_none());
+
+        b.set_target(Some(low));
+        assert!(b.forward(low, &vp8(11, 2000, 6, 1, false)).is_none());
+        assert_eq!(b.wants_keyframe(), Some(low));
+
+        let p = b.forward(low, &vp8(12, 3000, 7, 2, true)).unwrap();
+        assert_eq!(*p.seq_no, 12);
+        assert_eq!(p.time, 3000);
+        assert_eq!(ids(&p), (7, 2));
+        assert_eq!(b.current(), Some(low));
+        assert_eq!(b.wants_keyframe(), None);
+    }
+
+    #[test]
+    fn switch_continues_numbering() {
+        let mut b = SimulcastBridge::new(Codec::Vp8).unwrap();
+        let low = Rid::from("l");
+        let high = Rid::from("h");
+
+        b.set_target(Some(low));
+        let mut p1 = vp8(100, 90_000, 300, 20, true);
+        let start = p1.timestamp;
+        b.forward(low, &p1).unwrap();
+
+        p1 = vp8(101, 93_000, 301, 21, false);
+        p1.timestamp = start + Duration::from_millis(33);
+        b.forward(low, &p1).unwrap();
+
+        b.set_target(Some(high));
+
+        // Delta frame on high is not forwarded, low keeps going.
+        let mut h = vp8(5000, 1_000_000, 32766, 200, false);
+        h.timestamp = start + Duration::from_millis(40);
+        assert!(b.forward(high, &h).is_none());
+        assert_eq!(b.current(), Some(low));
+
+        // Keyframe on high switches.
+        let mut h = vp8(5001, 1_003_000, 32767, 201, true);
+        h.timestamp = start + Duration::from_millis(66);
+        let p = b.forward(high, &h).unwrap();
+        assert_eq!(*p.seq_no, 102);
+        assert_eq!(p.time, 93_000 + 33 * 90);
+        assert_eq!(ids(&p), (302, 22));
+        assert_eq!(b.current(), Some(high));
+
+        // Low is now dropped.
+        assert!(b.forward(low, &vp8(102, 96_000, 302, 22, false)).is_none());
+
+        // High continues with offsets, picture id wraps at 15 bits.
+        let mut h = vp8(5002, 1_006_000, 0, 202, false);
+        h.timestamp = start + Duration::from_millis(100);
+        let p = b.forward(high, &h).unwrap();
+        assert_eq!(*p.seq_no, 103);
+        assert_eq!(p.time, 93_000 + 33 * 90 + 3000);
+        assert_eq!(ids(&p), (303, 23));
+    }
+
+    #[test]
+    fn h264_keyframe_start() {
+        // SPS
+        assert!(h264_is_keyframe_start(&[0x67, 0x42]));
+        // STAP-A with SPS first.
+        assert!(h264_is_keyframe_start(&[0x78, 0x00, 0x02, 0x67, 0x42]));
+        // FU-A start of IDR
+        assert!(h264_is_keyframe_start(&[0x7c, 0x85]));
+        // FU-A continuation of IDR
+        assert!(!h264_is_keyframe_start(&[0x7c, 0x05]));
+        // Non-IDR slice
+        assert!(!h264_is_keyframe_start(&[0x41, 0x9a]));
+    }
+
+    #[test]
+    fn unsupported_codec() {
+        assert!(SimulcastBridge::new(Codec::Opus).is_none());
+        assert!(SimulcastBridge::new(Codec::Av1).is_some());
+    }
+
+    #[test]
+    fn spatial_layers() {
+        let mut b = SimulcastBridge::new(Codec::Vp8).unwrap();
+        let low = Rid::from("l");
+        let high = Rid::from("h");
+
+        // Without layers there is nothing to target.
+        b.set_target_spatial_layer(Some(0));
+        assert_eq!(b.target(), None);
+
+        b.set_layers(vec![low, high]);
+        b.set_target_spatial_layer(Some(5));
+        assert_eq!(b.target(), Some(high));
+
+        b.set_target_spatial_layer(Some(0));
+        b.forward(low, &vp8(1, 1000, 1, 1, true)).unwrap();
+        assert_eq!(b.spatial_layer(), Some(0));
+    }
+
+    #[test]
+    fn av1_keyframe_start() {
+        let mut b = SimulcastBridge::new(Codec::Av1).unwrap();
+        let low = Rid::from("l");
+        b.set_target(Some(low));
+
+        let mut packet = RtpPacket::blank();
+        packet.timestamp = Instant::now();
+
+        // Delta frame: W=1, N=0, then a temporal delimiter OBU.
+        packet.payload = vec![0x10, 0x12, 0x00];
+        assert!(b.forward(low, &packet).is_none());
+
+        // N=1 starts a coded video sequence.
+        packet.seq_no = 1.into();
+        packet.payload = vec![0x18, 0x12, 0x00];
+        let p = b.forward(low, &packet).unwrap();
+        assert_eq!(p.payload, packet.payload);
+    }
+}
@@ -26,6 +26,8 @@ pub struct RtpMeta {
     ///
     /// If no Sender Report(SR) has been received this is [`None`].
     pub last_sender_info: Option<SenderInfo>,
+    /// Whether the packet ends a frame, see [`RtpPacket::end_of_frame`][crate::rtp::RtpPacket::end_of_frame].
+    pub end_of_frame: bool,
 }
 
 #[derive(Clone)]
@@ -60,6 +62,10 @@ impl Depacketized {
         first..=last
     }
 
+    pub fn end_of_frame(&self) -> bool {
+        self.meta.last().is_some_and(|m| m.end_of_frame)
+    }
+
     pub fn ext_vals(&self) -> &ExtensionValues {
         // We use the extensions from the last packet because certain extensions, such as video
         // orientation, are only added on the last packet to save bytes.
@@ -82,6 +88,7 @@ pub struct DepacketizingBuffer {
     queue: VecDeque<Entry>,
     segments: Vec<(usize, usize)>,
     last_emitted: Option<(SeqNo, CodecExtra)>,
+    last_emitted_time: Option<MediaTime>,
     max_time: Option<MediaTime>,
     depack_cache: Option<(Range<usize>, Depacketized)>,
     contiguity: Contiguity,
@@ -92,7 +99,8 @@ impl DepacketizingBuffer {
         let contiguity = match depack {
             CodecDepacketizer::Vp8(_) => Contiguity::Vp8(Vp8Contiguity::new()),
             CodecDepacketizer::Vp9(_) => Contiguity::Vp9(Vp9Contiguity::new()),
-            CodecDepacketizer::H264(_)
+            CodecDepacketizer::Av1(_)
+            | CodecDepacketizer::H264(_)
             | CodecDepacketizer::H265(_)
             | CodecDepacketizer::Boxed(_)
             | CodecDepacketizer::Opus(_)
@@ -105,6 +113,7 @@ impl DepacketizingBuffer {
             queue: VecDeque::new(),
             segments: Vec::new(),
             last_emitted: None,
+            last_emitted_time: None,
             max_time: None,
             depack_cache: None,
             contiguity,
@@ -215,7 +224,12 @@ impl DepacketizingBuffer {
             return None;
         }
 
+        if let CodecExtra::Opus(extra) = &mut dep.codec_extra {
+            extra.dtx_gap = self.dtx_gap(dep.contiguous, dep.time);
+        }
+
         self.last_emitted = Some((last, dep.codec_extra));
+        self.last_emitted_time = Some(dep.time);
 
         Some(Ok(dep))
     }
@@ -310,6 +324,47 @@ impl DepacketizingBuffer {
         None
     }
 
+    /// Marks a packet of another PT in the same stream, such as comfort noise in
+    /// between audio, to not wait for its sequence number as a lost packet.
+    pub fn skip_seq(&mut self, meta: RtpMeta) {
+        if let Some((last, _)) = self.last_emitted {
+            if meta.seq_no <= last {
+                return;
+            }
+        }
+
+        // An empty entry that is neither head nor tail is skipped like padding.
+        if let Err(i) = self
+            .queue
+            .binary_search_by_key(&meta.seq_no, |r| r.meta.seq_no)
+        {
+            let entry = Entry {
+                meta,
+                data: vec![],
+                head: false,
+                tail: false,
+            };
+            self.queue.insert(i, entry);
+        }
+    }
+
+    /// Opus samples the sender skipped in DTX between the end of the last emitted
+    /// packet and `time`.
+    fn dtx_gap(&self, contiguous: bool, time: MediaTime) -> u32 {
+        let (Some((_, CodecExtra::Opus(last))), Some(last_time)) =
+            (self.last_emitted, self.last_emitted_time)
+        else {
+            return 0;
+        };
+
+        if !contiguous {
+            return 0;
+        }
+
+        let end = last_time.numer() + last.samples as u64;
+        time.numer().saturating_sub(end) as u32
+    }
+
     fn is_following_last(&self, start: usize) -> bool {
         let Some((last, _)) = self.last_emitted else {
             // First time we emit something.
@@ -369,6 +424,7 @@ impl fmt::Debug for Depacketized {
 mod test {
     use super::*;
     use crate::{
+        packet::opus::OpusDepacketizer,
         packet::vp9::Vp9Depacketizer,
         rtp_::{Frequency, MediaTime, Pt, Ssrc},
     };
@@ -526,6 +582,7 @@ mod test {
                 seq_no: (*seq).into(),
                 time: MediaTime::from_90khz(*time),
                 last_sender_info: None,
+                end_of_frame: false,
                 header: RtpHeader {
                     sequence_number: *seq as u16,
                     timestamp: *time as u32,
@@ -621,6 +678,7 @@ mod test {
                             header_len: 28,
                         },
                         last_sender_info: None,
+                        end_of_frame: marker,
                     },
                     data,
                 )
@@ -778,4 +836,61 @@ mod test {
         assert_eq!(res0before.data, res0after.data);
         assert_eq!(res1before.data, res1after.data);
     }
+
+    #[test]
+    fn opus_dtx_gap() {
+        let mut buf = DepacketizingBuffer::new(CodecDepacketizer::Opus(OpusDepacketizer), 10);
+
+        let meta = |seq: u64, time: u64| RtpMeta {
+            received: Instant::now(),
+            time: MediaTime::new(time, Frequency::FORTY_EIGHT_KHZ),
+            seq_no: seq.into(),
+            header: RtpHeader {
+                sequence_number: seq as u16,
+                timestamp: time as u32,
+                ..Default::default()
+            },
+            last_sender_info: None,
+            end_of_frame: true,
+        };
+
+        let push = |buf: &mut DepacketizingBuffer, seq: u64, time: u64, data: &[u8]| {
+            buf.push(meta(seq, time), data.to_vec());
+        };
+
+        let dtx_gap = |dep: Depacketized| match dep.codec_extra {
+            CodecExtra::Opus(e) => e.dtx_gap,
+            _ => panic!("Expected opus codec extra"),
+        };
+
+        // Speech (CELT 20ms), followed by a DTX packet.
+        push(&mut buf, 1, 0, &[0xf8, 1, 2, 3]);
+        assert_eq!(dtx_gap(buf.pop().unwrap().unwrap()), 0);
+        push(&mut buf, 2, 960, &[0xf8]);
+        assert_eq!(dtx_gap(buf.pop().unwrap().unwrap()), 0);
+
+        // The next DTX packet 400ms later.
+        push(&mut buf, 3, 960 + 19_200, &[0xf8]);
+        let dep = buf.pop().unwrap().unwrap();
+        assert!(dep.contiguous);
+        assert_eq!(dtx_gap(dep), 18_240);
+
+        // Seq 4 and 5 are comfort noise on another PT, which is not loss.
+        push(&mut buf, 6, 40_000, &[0xf8, 1, 2, 3]);
+        buf.skip_seq(meta(5, 30_000));
+        assert!(buf.pop().is_none());
+        buf.skip_seq(meta(4, 25_000));
+        let dep = buf.pop().unwrap().unwrap();
+        assert!(dep.contiguous);
+        assert_eq!(dtx_gap(dep), 40_000 - 20_160 - 960);
+
+        // Loss is not a DTX gap.
+        push(&mut buf, 8, 60_000, &[0xf8, 1, 2, 3]);
+        for seq in 9..21 {
+            push(&mut buf, seq, 60_000 + (seq - 8) * 960, &[0xf8, 1, 2, 3]);
+        }
+        let dep = buf.pop().unwrap().unwrap();
+        assert!(!dep.contiguous);
+        assert_eq!(dtx_gap(dep), 0);
+    }
 }
@@ -1,7 +1,19 @@
 #![allow(clippy::all)]
 #![allow(unused)]
 
-use super::{CodecExtra, Depacketizer, PacketError};
+use super::h264::{H264Packetizer, ANNEXB_NALUSTART_CODE};
+use super::{CodecExtra, Depacketizer, PacketError, Packetizer};
+
+/// H265 information describing the depacketized / packetized data
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct H265CodecExtra {
+    /// Flag which indicates that within [`MediaData`], there is an individual frame
+    /// containing complete and independent visual information. This frame serves
+    /// as a reference point for other frames in the video sequence.
+    ///
+    /// [`MediaData`]: crate::media::MediaData
+    pub is_keyframe: bool,
+}
 
 ///
 /// Network Abstraction Unit Header implementation
@@ -15,6 +27,17 @@ const H265NALU_FRAGMENTATION_UNIT_TYPE: u8 = 49;
 /// https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.4
 const H265NALU_PACI_PACKET_TYPE: u8 = 50;
 
+/// IRAP (intra random access point) pictures, BLA_W_LP through CRA_NUT.
+const H265NALU_IRAP_TYPES: std::ops::RangeInclusive<u8> = 16..=21;
+const H265NALU_VPS_TYPE: u8 = 32;
+const H265NALU_SPS_TYPE: u8 = 33;
+const H265NALU_PPS_TYPE: u8 = 34;
+const H265NALU_AUD_TYPE: u8 = 35;
+const H265NALU_FD_TYPE: u8 = 38;
+
+/// Mask of the type bits in the 16 bit NAL unit header.
+const H265NALU_TYPE_MASK: u16 = 0x7e00;
+
 /// H265NALUHeader is a H265 NAL Unit Header
 /// https://datatracker.ietf.org/doc/html/rfc7798#section-1.1.4
 /// +---------------+---------------+
@@ -42,6 +65,18 @@ impl H265NALUHeader {
         ((self.0 & MASK) >> (8 + 1)) as u8
     }
 
+    /// with_nalu_type returns a copy of the header with the type replaced, keeping the
+    /// F, layer_id and tid fields.
+    pub fn with_nalu_type(&self, nalu_type: u8) -> Self {
+        H265NALUHeader((self.0 & !H265NALU_TYPE_MASK) | ((nalu_type as u16) << 9))
+    }
+
+    /// is_irap returns whether or not the NAL Unit type is an intra random access point,
+    /// i.e. the start of a keyframe.
+    pub fn is_irap(&self) -> bool {
+        H265NALU_IRAP_TYPES.contains(&self.nalu_type())
+    }
+
     /// is_type_vcl_unit returns whether or not the NAL Unit type is a VCL NAL unit.
     pub fn is_type_vcl_unit(&self) -> bool {
         // Type is coded on 6 bits
@@ -710,11 +745,169 @@ impl Default for H265Payload {
     }
 }
 
+/// Packetizes H265 RTP packets.
+///
+/// NAL units that fit the MTU are sent as single NAL unit packets, larger ones are split
+/// in fragmentation units. Parameter sets (VPS, SPS, PPS) are held back and sent together
+/// in an aggregation packet ahead of the NAL unit following them.
+#[derive(Default, Debug, Clone)]
+pub struct H265Packetizer {
+    parameter_sets: Vec<Vec<u8>>,
+}
+
+impl H265Packetizer {
+    fn emit(&mut self, nalu: &[u8], mtu: usize, payloads: &mut Vec<Vec<u8>>) {
+        if nalu.len() <= H265NALU_HEADER_SIZE {
+            return;
+        }
+
+        let header = H265NALUHeader::new(nalu[0], nalu[1]);
+
+        match header.nalu_type() {
+            H265NALU_AUD_TYPE | H265NALU_FD_TYPE => return,
+            H265NALU_VPS_TYPE | H265NALU_SPS_TYPE | H265NALU_PPS_TYPE => {
+                self.parameter_sets.push(nalu.to_vec());
+                return;
+            }
+            _ => {}
+        }
+
+        self.emit_parameter_sets(mtu, payloads);
+        Self::emit_nalu(header, nalu, mtu, payloads);
+    }
+
+    fn emit_parameter_sets(&mut self, mtu: usize, payloads: &mut Vec<Vec<u8>>) {
+        let sets = std::mem::take(&mut self.parameter_sets);
+
+        let ap_len = H265NALU_HEADER_SIZE + sets.iter().map(|s| 2 + s.len()).sum::<usize>();
+
+        if sets.len() < 2 || ap_len > mtu {
+            // An aggregation packet needs at least two units, and must fit the MTU.
+            for s in &sets {
+                let header = H265NALUHeader::new(s[0], s[1]);
+                Self::emit_nalu(header, s, mtu, payloads);
+            }
+            return;
+        }
+
+        // The F bit is set if any aggregated unit has it, layer_id and tid are the
+        // lowest of the aggregated units.
+        // https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.2
+        let headers = sets.iter().map(|s| H265NALUHeader::new(s[0], s[1]));
+        let f = headers.clone().any(|h| h.f());
+        let layer_id = headers.clone().map(|h| h.layer_id()).min().unwrap_or(0);
+        let tid = headers.map(|h| h.tid()).min().unwrap_or(0);
+
+        let ap_header = ((f as u16) << 15)
+            | ((H265NALU_AGGREGATION_PACKET_TYPE as u16) << 9)
+            | ((layer_id as u16) << 3)
+            | tid as u16;
+
+        let mut ap = Vec::with_capacity(ap_len);
+        ap.extend_from_slice(&ap_header.to_be_bytes());
+        for s in &sets {
+            ap.extend_from_slice(&(s.len() as u16).to_be_bytes());
+            ap.extend_from_slice(s);
+        }
+        payloads.push(ap);
+    }
+
+    fn emit_nalu(header: H265NALUHeader, nalu: &[u8], mtu: usize, payloads: &mut Vec<Vec<u8>>) {
+        // Single NAL unit packet
+        if nalu.len() <= mtu {
+            payloads.push(nalu.to_vec());
+            return;
+        }
+
+        // Fragmentation units. The NAL unit header is not part of the fragments, it is
+        // conveyed by the payload header and the type in the FU header.
+        // https://datatracker.ietf.org/doc/html/rfc7798#section-4.4.3
+        let max_fragment_size =
+            mtu.saturating_sub(H265NALU_HEADER_SIZE + H265FRAGMENTATION_UNIT_HEADER_SIZE);
+
+        if max_fragment_size == 0 {
+            return;
+        }
+
+        let payload_header = header.with_nalu_type(H265NALU_FRAGMENTATION_UNIT_TYPE);
+        let nalu_type = header.nalu_type();
+
+        let mut fragments = nalu[H265NALU_HEADER_SIZE..]
+            .chunks(max_fragment_size)
+            .peekable();
+        let mut first = true;
+
+        while let Some(fragment) = fragments.next() {
+            let mut fu_header = nalu_type;
+            if first {
+                // Set start bit
+                fu_header |= 1 << 7;
+            }
+            if fragments.peek().is_none() {
+                // Set end bit
+                fu_header |= 1 << 6;
+            }
+            first = false;
+
+            let mut out = Vec::with_capacity(
+                H265NALU_HEADER_SIZE + H265FRAGMENTATION_UNIT_HEADER_SIZE + fragment.len(),
+            );
+            out.extend_from_slice(&payload_header.0.to_be_bytes());
+            out.push(fu_header);
+            out.extend_from_slice(fragment);
+            payloads.push(out);
+        }
+    }
+}
+
+impl Packetizer for H265Packetizer {
+    /// Payload fragments a H265 packet across one or more byte arrays
+    fn packetize(&mut self, mtu: usize, payload: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
+        if payload.is_empty() || mtu == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut payloads = vec![];
+
+        let (mut next_ind_start, mut next_ind_len) = H264Packetizer::next_ind(payload, 0);
+        if next_ind_start == -1 {
+            self.emit(payload, mtu, &mut payloads);
+        } else {
+            while next_ind_start != -1 {
+                let prev_start = (next_ind_start + next_ind_len) as usize;
+                let (next_ind_start2, next_ind_len2) =
+                    H264Packetizer::next_ind(payload, prev_start);
+                next_ind_start = next_ind_start2;
+                next_ind_len = next_ind_len2;
+                if next_ind_start != -1 {
+                    self.emit(
+                        &payload[prev_start..next_ind_start as usize],
+                        mtu,
+                        &mut payloads,
+                    );
+                } else {
+                    // Emit until end of stream, no end indicator found
+                    self.emit(&payload[prev_start..], mtu, &mut payloads);
+                }
+            }
+        }
+
+        Ok(payloads)
+    }
+
+    fn is_marker(&mut self, _data: &[u8], _previous: Option<&[u8]>, last: bool) -> bool {
+        last
+    }
+}
+
 /// Depacketizes H265 RTP packets.
+///
+/// The output is in Annex B format, each NAL unit preceded by a start code.
 #[derive(Default, Debug, Clone, PartialEq, Eq)]
 pub struct H265Depacketizer {
     payload: H265Payload,
     might_need_donl: bool,
+    fu_buffer: Option<Vec<u8>>,
 }
 
 impl H265Depacketizer {
@@ -741,7 +934,7 @@ impl Depacketizer for H265Depacketizer {
         &mut self,
         packet: &[u8],
         out: &mut Vec<u8>,
-        _: &mut CodecExtra,
+        extra: &mut CodecExtra,
     ) -> Result<(), PacketError> {
         if packet.len() <= H265NALU_HEADER_SIZE {
             return Err(PacketError::ErrShortPacket);
@@ -756,6 +949,7 @@ impl Depacketizer for H265Depacketizer {
             let mut decoded = H265PACIPacket::default();
             decoded.depacketize(packet)?;
 
+            // PACI only carries header extensions we don't make use of.
             self.payload = H265Payload::H265PACIPacket(decoded);
         } else if header.is_fragmentation_unit() {
             let mut decoded = H265FragmentationUnitPacket::default();
@@ -763,6 +957,28 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            let fu_header = decoded.fu_header();
+
+            if fu_header.s() {
+                self.fu_buffer = Some(Vec::new());
+            }
+
+            // Fragments without a preceding start are dropped.
+            if let Some(fu_buffer) = &mut self.fu_buffer {
+                fu_buffer.extend_from_slice(&decoded.payload);
+            }
+
+            if fu_header.e() {
+                if let Some(fu_buffer) = self.fu_buffer.take() {
+                    let nalu_header = header.with_nalu_type(fu_header.fu_type());
+                    set_keyframe(extra, nalu_header);
+
+                    out.extend_from_slice(ANNEXB_NALUSTART_CODE);
+                    out.extend_from_slice(&nalu_header.0.to_be_bytes());
+                    out.extend_from_slice(&fu_buffer);
+                }
+            }
+
             self.payload = H265Payload::H265FragmentationUnitPacket(decoded);
         } else if header.is_aggregation_packet() {
             let mut decoded = H265AggregationPacket::default();
@@ -770,6 +986,18 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            let first = decoded.first_unit.iter().map(|u| &u.nal_unit);
+            let others = decoded.other_units.iter().map(|u| &u.nal_unit);
+
+            for nal_unit in first.chain(others) {
+                if let [b0, b1, ..] = nal_unit[..] {
+                    set_keyframe(extra, H265NALUHeader::new(b0, b1));
+                }
+
+                out.extend_from_slice(ANNEXB_NALUSTART_CODE);
+                out.extend_from_slice(nal_unit);
+            }
+
             self.payload = H265Payload::H265AggregationPacket(decoded);
         } else {
             let mut decoded = H265SingleNALUnitPacket::default();
@@ -777,18 +1005,31 @@ impl Depacketizer for H265Depacketizer {
 
             decoded.depacketize(packet)?;
 
+            set_keyframe(extra, header);
+
+            out.extend_from_slice(ANNEXB_NALUSTART_CODE);
+            out.extend_from_slice(&packet[..H265NALU_HEADER_SIZE]);
+            out.extend_from_slice(&decoded.payload);
+
             self.payload = H265Payload::H265SingleNALUnitPacket(decoded);
         }
 
-        out.extend_from_slice(packet);
-
         Ok(())
     }
 
     /// is_partition_head checks if this is the head of a packetized nalu stream.
-    fn is_partition_head(&self, _payload: &[u8]) -> bool {
-        //TODO:
-        true
+    fn is_partition_head(&self, packet: &[u8]) -> bool {
+        if packet.len() <= H265NALU_HEADER_SIZE {
+            return false;
+        }
+
+        let header = H265NALUHeader::new(packet[0], packet[1]);
+
+        if header.is_fragmentation_unit() {
+            H265FragmentationUnitHeader(packet[2]).s()
+        } else {
+            true
+        }
     }
 
     fn is_partition_tail(&self, marker: bool, _payload: &[u8]) -> bool {
@@ -796,6 +1037,15 @@ impl Depacketizer for H265Depacketizer {
     }
 }
 
+fn set_keyframe(extra: &mut CodecExtra, header: H265NALUHeader) {
+    let is_keyframe = if let CodecExtra::H265(e) = extra {
+        header.is_irap() | e.is_keyframe
+    } else {
+        header.is_irap()
+    };
+    *extra = CodecExtra::H265(H265CodecExtra { is_keyframe });
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1697,4 +1947,96 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn test_h265_packetize() -> Result<()> {
+        let mut pck = H265Packetizer::default();
+
+        // 0 MTU and empty payload
+        assert!(pck.packetize(0, &[0x02, 0x01, 0xaa])?.is_empty());
+        assert!(pck.packetize(10, &[])?.is_empty());
+
+        // AUD is dropped
+        assert!(pck.packetize(10, &[0x46, 0x01, 0x50])?.is_empty());
+
+        // Single NAL unit
+        let result = pck.packetize(10, &[0x00, 0x00, 0x01, 0x02, 0x01, 0xaa, 0xbb])?;
+        assert_eq!(result, vec![vec![0x02, 0x01, 0xaa, 0xbb]]);
+
+        // Fragmented IDR_W_RADL (type 19) keeps layer_id/tid in the payload header.
+        let result = pck.packetize(
+            5,
+            &[0x00, 0x00, 0x01, 0x26, 0x01, 0x01, 0x02, 0x03, 0x04, 0x05],
+        )?;
+        assert_eq!(
+            result,
+            vec![
+                vec![0x62, 0x01, 0x93, 0x01, 0x02],
+                vec![0x62, 0x01, 0x13, 0x03, 0x04],
+                vec![0x62, 0x01, 0x53, 0x05],
+            ]
+        );
+
+        // Parameter sets are aggregated ahead of the next NAL unit.
+        let vps = [0x40, 0x01, 0x0c];
+        let sps = [0x42, 0x01, 0x01, 0x60];
+        let pps = [0x44, 0x01, 0xc0];
+        let mut payload = vec![];
+        for nalu in [&vps[..], &sps[..], &pps[..], &[0x26, 0x01, 0xaf]] {
+            payload.extend_from_slice(ANNEXB_NALUSTART_CODE);
+            payload.extend_from_slice(nalu);
+        }
+        let result = pck.packetize(1200, &payload)?;
+        assert_eq!(
+            result,
+            vec![
+                vec![
+                    0x60, 0x01, 0x00, 0x03, 0x40, 0x01, 0x0c, 0x00, 0x04, 0x42, 0x01, 0x01, 0x60,
+                    0x00, 0x03, 0x44, 0x01, 0xc0
+                ],
+                vec![0x26, 0x01, 0xaf],
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_h265_roundtrip() -> Result<()> {
+        let idr: Vec<u8> = [0x26, 0x01].into_iter().chain(2..100).collect();
+
+        let mut payload = vec![];
+        for nalu in [
+            &[0x40, 0x01, 0x0c, 0x01][..],
+            &[0x42, 0x01, 0x01, 0x60, 0x0a],
+            &[0x44, 0x01, 0xc0, 0xf2],
+            &idr[..],
+            &[0x02, 0x01, 0xd0, 0x09],
+        ] {
+            payload.extend_from_slice(ANNEXB_NALUSTART_CODE);
+            payload.extend_from_slice(nalu);
+        }
+
+        let mut pck = H265Packetizer::default();
+        let packets = pck.packetize(30, &payload)?;
+
+        let mut depack = H265Depacketizer::default();
+        let mut out = vec![];
+        let mut extra = CodecExtra::None;
+
+        assert!(depack.is_partition_head(&packets[0]));
+        for (i, packet) in packets.iter().enumerate() {
+            let is_fu_middle = i > 1 && i < packets.len() - 2;
+            assert_eq!(depack.is_partition_head(packet), !is_fu_middle);
+            depack.depacketize(packet, &mut out, &mut extra)?;
+        }
+
+        assert_eq!(out, payload);
+        assert_eq!(
+            extra,
+            CodecExtra::H265(H265CodecExtra { is_keyframe: true })
+        );
+
+        Ok(())
+    }
 }
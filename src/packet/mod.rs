@@ -6,10 +6,16 @@ use std::panic::UnwindSafe;
 use thiserror::Error;
 
 use crate::format::Codec;
+use crate::rtp::dd::DependencyDescriptor;
+use crate::rtp_::RtpHeader;
 use crate::sdp::MediaType;
 
+mod av1;
+pub use av1::Av1CodecExtra;
+use av1::{Av1Depacketizer, Av1Packetizer};
+
 mod g7xx;
-use g7xx::{G711Packetizer, G722Packetizer};
+use g7xx::{G711Packetizer, G722Packetizer, G7xxPacketizer};
 
 mod h264;
 pub use h264::H264CodecExtra;
@@ -19,9 +25,12 @@ mod h264_profile;
 pub(crate) use h264_profile::H264ProfileLevel;
 
 mod h265;
-use h265::H265Depacketizer;
+pub use h265::H265CodecExtra;
+use h265::{H265Depacketizer, H265Packetizer};
 
 mod opus;
+pub(crate) use opus::is_dtx;
+pub use opus::OpusCodecExtra;
 use opus::{OpusDepacketizer, OpusPacketizer};
 
 mod vp8;
@@ -29,12 +38,15 @@ pub use vp8::Vp8CodecExtra;
 use vp8::{Vp8Depacketizer, Vp8Packetizer};
 
 mod vp9;
-pub use vp9::Vp9CodecExtra;
+pub use vp9::{Vp9CodecExtra, Vp9LayerFrame, Vp9PictureGroupEntry, Vp9ScalabilityStructure};
 use vp9::{Vp9Depacketizer, Vp9Packetizer};
 
 mod null;
 use null::{NullDepacketizer, NullPacketizer};
 
+mod bridge;
+pub use bridge::{BridgedRtp, SimulcastBridge};
+
 mod buffer_rx;
 pub(crate) use buffer_rx::{Depacketized, DepacketizingBuffer, RtpMeta};
 mod contiguity;
@@ -44,6 +56,9 @@ mod contiguity_vp9;
 mod payload;
 pub(crate) use payload::Payloader;
 
+mod red;
+pub(crate) use red::{RedBlock, RedEncoder};
+
 mod bwe;
 pub(crate) use bwe::SendSideBandwithEstimator;
 
@@ -58,6 +73,11 @@ pub enum MediaKind {
     Audio,
     /// Video media.
     Video,
+    /// Generic application data sent over RTP, such as haptics or metadata tracks.
+    ///
+    /// Not to be confused with data channels, which are an `m=application` line
+    /// over SCTP. These payloads use [`Codec::Application`][crate::format::Codec::Application].
+    Application,
 }
 
 impl MediaKind {
@@ -70,6 +90,11 @@ impl MediaKind {
     pub fn is_video(&self) -> bool {
         *self == MediaKind::Video
     }
+
+    /// Tests if this is `MediaKind::Application`
+    pub fn is_application(&self) -> bool {
+        *self == MediaKind::Application
+    }
 }
 
 /// Packetizes some bytes for use as RTP packet.
@@ -94,6 +119,33 @@ pub enum CodecExtra {
     Vp9(Vp9CodecExtra),
     /// Codec extra parameters for H264.
     H264(H264CodecExtra),
+    /// Codec extra parameters for H265.
+    H265(H265CodecExtra),
+    /// Codec extra parameters for AV1.
+    Av1(Av1CodecExtra),
+    /// Codec extra parameters for Opus.
+    Opus(OpusCodecExtra),
+}
+
+/// Tells whether an incoming RTP packet is the last one of a frame.
+///
+/// Normalizes the codec specific signals: the VP9 payload descriptor E bit ends a
+/// frame (per spatial layer), every audio and application packet is a frame of its
+/// own, and otherwise it's the RTP marker bit. A Dependency Descriptor on a video
+/// packet takes precedence over all of these.
+pub(crate) fn is_end_of_frame(codec: Codec, header: &RtpHeader, payload: &[u8]) -> bool {
+    if codec.is_video() {
+        if let Some(dd) = header.ext_vals.user_values.get::<DependencyDescriptor>() {
+            return dd.end_of_frame;
+        }
+    }
+
+    match codec {
+        Codec::Application(_) => true,
+        c if c.is_audio() => true,
+        Codec::Vp9 => header.marker || payload.first().is_some_and(|b| b & 0x04 != 0),
+        _ => header.marker,
+    }
 }
 
 /// Depacketizes an RTP payload.
@@ -184,10 +236,11 @@ impl BitRead for (&[u8], usize) {
 
 #[derive(Debug)]
 pub(crate) enum CodecPacketizer {
+    Av1(Av1Packetizer),
     G711(G711Packetizer),
     G722(G722Packetizer),
     H264(H264Packetizer),
-    // H265() TODO
+    H265(H265Packetizer),
     Opus(OpusPacketizer),
     Vp8(Vp8Packetizer),
     Vp9(Vp9Packetizer),
@@ -197,6 +250,7 @@ pub(crate) enum CodecPacketizer {
 
 #[derive(Debug)]
 pub(crate) enum CodecDepacketizer {
+    Av1(Av1Depacketizer),
     H264(H264Depacketizer),
     H265(H265Depacketizer),
     Opus(OpusDepacketizer),
@@ -209,34 +263,51 @@ pub(crate) enum CodecDepacketizer {
 impl From<Codec> for CodecPacketizer {
     fn from(c: Codec) -> Self {
         match c {
-            Codec::Opus => CodecPacketizer::Opus(OpusPacketizer),
+            Codec::Opus => CodecPacketizer::Opus(OpusPacketizer::default()),
+            Codec::Pcmu | Codec::Pcma => CodecPacketizer::G711(G7xxPacketizer),
+            Codec::G722 => CodecPacketizer::G722(G7xxPacketizer),
             Codec::H264 => CodecPacketizer::H264(H264Packetizer::default()),
-            Codec::H265 => unimplemented!("Missing packetizer for H265"),
+            Codec::H265 => CodecPacketizer::H265(H265Packetizer::default()),
             Codec::Vp8 => CodecPacketizer::Vp8(Vp8Packetizer::default()),
             Codec::Vp9 => CodecPacketizer::Vp9(Vp9Packetizer::default()),
-            Codec::Av1 => unimplemented!("Missing packetizer for AV1"),
-            Codec::Null => CodecPacketizer::Null(NullPacketizer),
+            Codec::Av1 => CodecPacketizer::Av1(Av1Packetizer),
+            Codec::Cn | Codec::TelephoneEvent | Codec::Null | Codec::Application(_) => {
+                CodecPacketizer::Null(NullPacketizer)
+            }
             Codec::Rtx => panic!("Cant instantiate packetizer for RTX codec"),
+            Codec::Red => panic!("Cant instantiate packetizer for RED codec"),
+            Codec::FlexFec => panic!("Cant instantiate packetizer for FlexFEC"),
+            Codec::Ulpfec => panic!("Cant instantiate packetizer for ULPFEC"),
             Codec::Unknown => panic!("Cant instantiate packetizer for unknown codec"),
             _ => panic!("Cant instantiate packetizer for unhandled codec"),
         }
     }
 }
 
-impl From<Codec> for CodecDepacketizer {
-    fn from(c: Codec) -> Self {
-        match c {
+impl CodecDepacketizer {
+    /// Depacketizer for a media codec.
+    ///
+    /// `None` for codecs that wrap or protect other codecs (RTX, RED, FEC), which are
+    /// unwrapped before depacketizing, and for unknown codecs.
+    pub(crate) fn new(c: Codec) -> Option<Self> {
+        let d = match c {
             Codec::Opus => CodecDepacketizer::Opus(OpusDepacketizer),
             Codec::H264 => CodecDepacketizer::H264(H264Depacketizer::default()),
             Codec::H265 => CodecDepacketizer::H265(H265Depacketizer::default()),
             Codec::Vp8 => CodecDepacketizer::Vp8(Vp8Depacketizer::default()),
             Codec::Vp9 => CodecDepacketizer::Vp9(Vp9Depacketizer::default()),
-            Codec::Av1 => unimplemented!("Missing depacketizer for AV1"),
-            Codec::Null => CodecDepacketizer::Null(NullDepacketizer),
-            Codec::Rtx => panic!("Cant instantiate depacketizer for RTX codec"),
-            Codec::Unknown => panic!("Cant instantiate depacketizer for unknown codec"),
-            _ => panic!("Cant instantiate packetizer for unhandled codec"),
-        }
+            Codec::Av1 => CodecDepacketizer::Av1(Av1Depacketizer::default()),
+            // G.711 and G.722 frames are the payload as is.
+            Codec::Pcmu
+            | Codec::Pcma
+            | Codec::G722
+            | Codec::Cn
+            | Codec::TelephoneEvent
+            | Codec::Null
+            | Codec::Application(_) => CodecDepacketizer::Null(NullDepacketizer),
+            _ => return None,
+        };
+        Some(d)
     }
 }
 
@@ -244,9 +315,11 @@ impl Packetizer for CodecPacketizer {
     fn packetize(&mut self, mtu: usize, b: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
         use CodecPacketizer::*;
         match self {
+            Av1(v) => v.packetize(mtu, b),
             G711(v) => v.packetize(mtu, b),
             G722(v) => v.packetize(mtu, b),
             H264(v) => v.packetize(mtu, b),
+            H265(v) => v.packetize(mtu, b),
             Opus(v) => v.packetize(mtu, b),
             Vp8(v) => v.packetize(mtu, b),
             Vp9(v) => v.packetize(mtu, b),
@@ -257,10 +330,12 @@ impl Packetizer for CodecPacketizer {
 
     fn is_marker(&mut self, data: &[u8], previous: Option<&[u8]>, last: bool) -> bool {
         match self {
+            CodecPacketizer::Av1(v) => v.is_marker(data, previous, last),
             CodecPacketizer::G711(v) => v.is_marker(data, previous, last),
             CodecPacketizer::G722(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Opus(v) => v.is_marker(data, previous, last),
             CodecPacketizer::H264(v) => v.is_marker(data, previous, last),
+            CodecPacketizer::H265(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Vp8(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Vp9(v) => v.is_marker(data, previous, last),
             CodecPacketizer::Null(v) => v.is_marker(data, previous, last),
@@ -278,6 +353,7 @@ impl Depacketizer for CodecDepacketizer {
     ) -> Result<(), PacketError> {
         use CodecDepacketizer::*;
         match self {
+            Av1(v) => v.depacketize(packet, out, extra),
             H264(v) => v.depacketize(packet, out, extra),
             H265(v) => v.depacketize(packet, out, extra),
             Opus(v) => v.depacketize(packet, out, extra),
@@ -291,6 +367,7 @@ impl Depacketizer for CodecDepacketizer {
     fn is_partition_head(&self, packet: &[u8]) -> bool {
         use CodecDepacketizer::*;
         match self {
+            Av1(v) => v.is_partition_head(packet),
             H264(v) => v.is_partition_head(packet),
             H265(v) => v.is_partition_head(packet),
             Opus(v) => v.is_partition_head(packet),
@@ -304,6 +381,7 @@ impl Depacketizer for CodecDepacketizer {
     fn is_partition_tail(&self, marker: bool, packet: &[u8]) -> bool {
         use CodecDepacketizer::*;
         match self {
+            Av1(v) => v.is_partition_tail(marker, packet),
             H264(v) => v.is_partition_tail(marker, packet),
             H265(v) => v.is_partition_tail(marker, packet),
             Opus(v) => v.is_partition_tail(marker, packet),
@@ -320,7 +398,8 @@ impl From<MediaType> for MediaKind {
         match v {
             MediaType::Audio => MediaKind::Audio,
             MediaType::Video => MediaKind::Video,
-            _ => panic!("Not MediaType::Audio or Video"),
+            MediaType::Application => MediaKind::Application,
+            _ => panic!("Not MediaType::Audio, Video or Application"),
         }
     }
 }
@@ -330,6 +409,7 @@ impl fmt::Display for MediaKind {
         match self {
             MediaKind::Audio => write!(f, "audio"),
             MediaKind::Video => write!(f, "video"),
+            MediaKind::Application => write!(f, "application"),
         }
     }
 }
@@ -1,8 +1,66 @@
 use super::{CodecExtra, Depacketizer, MediaKind, PacketError, Packetizer};
 
+/// Opus information describing the depacketized data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpusCodecExtra {
+    /// True if this is a DTX (discontinuous transmission) packet. The sender is
+    /// silent and sends these sparsely instead of a packet every frame.
+    pub dtx: bool,
+    /// The duration of the audio in the packet, in samples at 48kHz (same as the
+    /// RTP time). 0 if the TOC byte can't be read.
+    pub samples: u32,
+    /// Samples at 48kHz between the end of the previous packet and this one, that the
+    /// sender didn't send because of DTX. This is silence to fill with comfort noise,
+    /// not loss to conceal. Only set when there's no loss in between.
+    pub dtx_gap: u32,
+}
+
+impl OpusCodecExtra {
+    /// Reads the DTX state and duration from the TOC byte (RFC 6716 section 3.1).
+    pub(crate) fn parse(payload: &[u8]) -> Self {
+        OpusCodecExtra {
+            dtx: is_dtx(payload),
+            samples: toc_samples(payload).unwrap_or(0),
+            dtx_gap: 0,
+        }
+    }
+}
+
+/// Tells if the payload is an Opus DTX packet.
+///
+/// Same as libWebRTC, a packet of at most 2 bytes carries no audio.
+pub(crate) fn is_dtx(payload: &[u8]) -> bool {
+    payload.len() <= 2
+}
+
+fn toc_samples(payload: &[u8]) -> Option<u32> {
+    let toc = *payload.first()?;
+    let config = toc >> 3;
+
+    // Frame size in 48kHz samples, by the mode in the config.
+    let frame = match config {
+        // SILK: 10, 20, 40, 60ms
+        0..=11 => [480, 960, 1920, 2880][config as usize % 4],
+        // Hybrid: 10, 20ms
+        12..=15 => [480, 960][config as usize % 2],
+        // CELT: 2.5, 5, 10, 20ms
+        _ => [120, 240, 480, 960][config as usize % 4],
+    };
+
+    let frames = match toc & 0x03 {
+        0 => 1,
+        1 | 2 => 2,
+        _ => (*payload.get(1)? & 0x3f) as u32,
+    };
+
+    Some(frame * frames)
+}
+
 /// Packetizes Opus RTP packets.
 #[derive(Default, Debug, Copy, Clone)]
-pub struct OpusPacketizer;
+pub struct OpusPacketizer {
+    in_dtx: bool,
+}
 
 impl Packetizer for OpusPacketizer {
     fn packetize(&mut self, mtu: usize, payload: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
@@ -22,9 +80,11 @@ impl Packetizer for OpusPacketizer {
         Ok(out)
     }
 
-    fn is_marker(&mut self, data: &[u8], previous: Option<&[u8]>, last: bool) -> bool {
-        // TODO: dtx
-        false
+    fn is_marker(&mut self, data: &[u8], _previous: Option<&[u8]>, _last: bool) -> bool {
+        // The marker is set on the first packet of a talkspurt after DTX (RFC 3551).
+        let was_dtx = self.in_dtx;
+        self.in_dtx = is_dtx(data);
+        was_dtx && !self.in_dtx
     }
 }
 
@@ -37,12 +97,14 @@ impl Depacketizer for OpusDepacketizer {
         &mut self,
         packet: &[u8],
         out: &mut Vec<u8>,
-        _: &mut CodecExtra,
+        codec_extra: &mut CodecExtra,
     ) -> Result<(), PacketError> {
         if !packet.is_empty() {
             out.extend_from_slice(packet);
         }
 
+        *codec_extra = CodecExtra::Opus(OpusCodecExtra::parse(packet));
+
         Ok(())
     }
 
@@ -82,9 +144,50 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_opus_codec_extra() -> Result<(), PacketError> {
+        let mut pck = OpusDepacketizer;
+        let mut extra = CodecExtra::None;
+        let mut out = Vec::new();
+
+        // CELT FB 20ms, one frame.
+        pck.depacketize(&[0xf8, 0x11, 0x22, 0x33], &mut out, &mut extra)?;
+        let CodecExtra::Opus(e) = extra else {
+            panic!("Expected opus codec extra");
+        };
+        assert!(!e.dtx);
+        assert_eq!(e.samples, 960);
+
+        // SILK WB 60ms, arbitrary number of frames (2).
+        let e = OpusCodecExtra::parse(&[0x5b, 0x02, 0x11, 0x22]);
+        assert_eq!(e.samples, 5760);
+
+        // DTX packet of just the TOC byte.
+        let e = OpusCodecExtra::parse(&[0xf8]);
+        assert!(e.dtx);
+        assert_eq!(e.samples, 960);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_opus_marker_after_dtx() {
+        let mut pck = OpusPacketizer::default();
+        let speech = &[0xf8, 0x11, 0x22, 0x33];
+        let dtx = &[0xf8];
+
+        assert!(!pck.is_marker(speech, None, true));
+        assert!(!pck.is_marker(dtx, None, true));
+        assert!(!pck.is_marker(dtx, None, true));
+
+        // First packet of the talkspurt.
+        assert!(pck.is_marker(speech, None, true));
+        assert!(!pck.is_marker(speech, None, true));
+    }
+
     #[test]
     fn test_opus_payload() -> Result<(), PacketError> {
-        let mut pck = OpusPacketizer;
+        let mut pck = OpusPacketizer::default();
         let empty = &[];
         let payload = &[0x90, 0x90, 0x90];
 
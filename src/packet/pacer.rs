@@ -15,6 +15,7 @@ const MAX_DEBT_IN_TIME: Duration = Duration::from_millis(500);
 const MAX_PADDING_PACKET_SIZE: DataSize = DataSize::bytes(MAX_BLANK_PADDING_PAYLOAD_SIZE as u64);
 const PADDING_BURST_INTERVAL: Duration = Duration::from_millis(5);
 const PACING: Duration = Duration::from_millis(40);
+const CONGESTED_PACKET_INTERVAL: Duration = Duration::from_millis(500);
 
 pub enum PacerImpl {
     Null(NullPacer),
@@ -69,6 +70,34 @@ impl Pacer for PacerImpl {
     }
 }
 
+impl PacerImpl {
+    /// Poll for the average queue time when the queues have grown beyond the queue limit.
+    ///
+    /// This is edge triggered, i.e. it's only returned once per overflow.
+    pub fn poll_queue_overflow(&mut self) -> Option<Duration> {
+        match self {
+            PacerImpl::Null(_) => None,
+            PacerImpl::LeakyBucket(v) => v.queue_overflow.take(),
+        }
+    }
+
+    /// Set whether the congestion window is full.
+    ///
+    /// While congested, paced media is held back, apart from one packet every
+    /// 500ms to give the remote a chance to send feedback.
+    pub fn set_congested(&mut self, congested: bool) {
+        match self {
+            PacerImpl::Null(_) => {}
+            PacerImpl::LeakyBucket(v) => {
+                if v.congested != congested {
+                    trace!("LeakyBucketPacer congested: {}", congested);
+                }
+                v.congested = congested;
+            }
+        }
+    }
+}
+
 /// A packet Pacer.
 ///
 /// The pacer is responsible for ensuring correct pacing of packets onto the network at a given
@@ -278,6 +307,10 @@ pub struct LeakyBucketPacer {
     queue_states: Vec<QueueState>,
     /// The next return value for `poll_queue``
     next_poll_queue: Option<Mid>,
+    /// Whether the average queue time is currently above the queue limit.
+    overflowing: bool,
+    /// Average queue time when we last started overflowing. Taken by `poll_queue_overflow`.
+    queue_overflow: Option<Duration>,
 }
 
 impl Pacer for LeakyBucketPacer {
@@ -386,6 +419,9 @@ impl LeakyBucketPacer {
             queue_limit: DEFAULT_QUEUE_LIMIT,
             queue_states: vec![],
             next_poll_queue: None,
+            overflowing: false,
+            queue_overflow: None,
+            congested: false,
         }
     }
 
@@ -448,6 +484,16 @@ impl LeakyBucketPacer {
         };
 
         if let Some(queue) = non_empty_queue {
+            if self.congested {
+                // Hold back media while congested, but let a packet through now and then.
+                let poll_at = self
+                    .last_emitted
+                    .map(|e| e + CONGESTED_PACKET_INTERVAL)
+                    .unwrap_or(now);
+
+                return Some((poll_at, Some(queue)));
+            }
+
             if self.adjusted_bitrate > Bitrate::ZERO {
                 // If we have a non-empty queue, send on it as soon as possible, possibly waiting
                 // for the next pacing interval.
@@ -469,6 +515,11 @@ impl LeakyBucketPacer {
             }
         }
 
+        if self.congested {
+            // No padding while congested.
+            return None;
+        }
+
         let any_queue_for_padding = self.queue_states.iter().any(|q| q.use_for_padding);
         let padding_possible = self.padding_bitrate > Bitrate::ZERO && any_queue_for_padding;
 
@@ -509,11 +560,18 @@ impl LeakyBucketPacer {
                     )
                 });
         if queued_packets == 0 {
+            self.overflowing = false;
             return;
         }
 
         let avg_queue_time = queue_time / queued_packets;
 
+        let overflowing = avg_queue_time > self.queue_limit;
+        if overflowing && !self.overflowing {
+            self.queue_overflow = Some(avg_queue_time);
+        }
+        self.overflowing = overflowing;
+
         // The average time we want the packet in the queue to at most to wait to drain.
         let target_queue_wait =
             Duration::from_millis(1).max(self.queue_limit.saturating_sub(avg_queue_time));
@@ -6,23 +6,26 @@ use std::time::{Duration, Instant};
 
 use crate::format::CodecSpec;
 use crate::media::ToPayload;
+use crate::rtp::dd::DependencyDescriptor;
 use crate::rtp_::{ExtensionValues, Frequency, MediaTime, Rid, RtpHeader, SeqNo, Ssrc};
 use crate::streams::StreamTx;
 
-use super::{CodecPacketizer, PacketError, Packetizer, QueueSnapshot};
+use super::{CodecPacketizer, PacketError, Packetizer, QueueSnapshot, RedEncoder};
 use super::{MediaKind, QueuePriority};
 
 #[derive(Debug)]
 pub struct Payloader {
     pack: CodecPacketizer,
     clock_rate: Frequency,
+    red: Option<RedEncoder>,
 }
 
 impl Payloader {
-    pub(crate) fn new(spec: CodecSpec) -> Self {
+    pub(crate) fn new(spec: CodecSpec, red: Option<RedEncoder>) -> Self {
         Payloader {
             pack: spec.codec.into(),
             clock_rate: spec.clock_rate,
+            red,
         }
     }
 
@@ -64,15 +67,25 @@ impl Payloader {
             // TODO: delegate to self.pack to decide whether this packet is nackable.
             let nackable = !is_audio;
 
+            let mut ext_vals = ext_vals.clone();
+
+            let time = rtp_time.rebase(self.clock_rate).numer() as u32;
+
+            // Audio frames are a single packet that RED wraps with the previous frames.
+            let (pt, data) = match &mut self.red {
+                Some(red) if len == 1 => (red.pt(), red.encode(pt, time, data, mtu)),
+                _ => (pt, data),
+            };
+
+            // The dependency descriptor is given per frame, but marks the frame boundaries
+            // in each packet.
+            if let Some(dd) = ext_vals.user_values.get::<DependencyDescriptor>() {
+                let dd = dd.for_packet(first, last);
+                ext_vals.user_values.set(dd);
+            }
+
             stream.write_rtp(
-                pt,
-                seq_no,
-                rtp_time.rebase(self.clock_rate).numer() as u32,
-                wallclock,
-                marker,
-                ext_vals.clone(),
-                nackable,
-                data,
+                pt, seq_no, time, wallclock, marker, ext_vals, nackable, data,
             );
         }
 
@@ -0,0 +1,235 @@
+use std::collections::VecDeque;
+
+use crate::rtp_::Pt;
+
+use super::PacketError;
+
+/// Largest timestamp offset of a redundant block, 14 bits.
+const MAX_TIMESTAMP_OFFSET: u32 = (1 << 14) - 1;
+
+/// Largest length of a redundant block, 10 bits.
+const MAX_BLOCK_LENGTH: usize = (1 << 10) - 1;
+
+/// Wraps audio frames in RED (RFC 2198) with copies of previous frames.
+#[derive(Debug)]
+pub(crate) struct RedEncoder {
+    pt: Pt,
+    distance: usize,
+    history: VecDeque<(u32, Vec<u8>)>,
+}
+
+/// One block of a RED (RFC 2198) payload.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct RedBlock<'a> {
+    /// The PT of the encoding in the block.
+    pub pt: Pt,
+    /// How far back from the RTP timestamp this block is. 0 for the primary block.
+    pub timestamp_offset: u32,
+    /// The encoded data.
+    pub data: &'a [u8],
+}
+
+impl RedEncoder {
+    /// Creates a new encoder sending on `pt` with up to `distance` previous frames
+    /// in each packet.
+    pub fn new(pt: Pt, distance: usize) -> Self {
+        RedEncoder {
+            pt,
+            distance,
+            history: VecDeque::with_capacity(distance),
+        }
+    }
+
+    /// The RED payload type.
+    pub fn pt(&self) -> Pt {
+        self.pt
+    }
+
+    /// Encode the primary `data` together with the previous frames that fit in `mtu`.
+    //
+    //  0                   1                   2                   3
+    //  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    // |F|   block PT  |  timestamp offset         |   block length    |
+    // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    // |0|   block PT  |
+    // +-+-+-+-+-+-+-+-+
+    pub fn encode(&mut self, primary_pt: Pt, timestamp: u32, data: Vec<u8>, mtu: usize) -> Vec<u8> {
+        // Previous frames that can be represented in the header, oldest first.
+        let mut redundant: Vec<(u32, &[u8])> = self
+            .history
+            .iter()
+            .map(|(t, d)| (timestamp.wrapping_sub(*t), &d[..]))
+            .filter(|(offset, d)| {
+                *offset > 0
+                    && *offset <= MAX_TIMESTAMP_OFFSET
+                    && !d.is_empty()
+                    && d.len() <= MAX_BLOCK_LENGTH
+            })
+            .collect();
+
+        let size = |r: &[(u32, &[u8])]| -> usize {
+            r.iter().map(|(_, d)| 4 + d.len()).sum::<usize>() + 1 + data.len()
+        };
+
+        // Drop the oldest redundancy until it fits.
+        while !redundant.is_empty() && size(&redundant) > mtu {
+            redundant.remove(0);
+        }
+
+        let mut out = Vec::with_capacity(size(&redundant));
+
+        for (offset, d) in &redundant {
+            out.push(0x80 | *primary_pt);
+            let v = (offset << 10) | d.len() as u32;
+            out.extend_from_slice(&v.to_be_bytes()[1..]);
+        }
+        out.push(*primary_pt);
+
+        for (_, d) in &redundant {
+            out.extend_from_slice(d);
+        }
+        out.extend_from_slice(&data);
+
+        if self.distance > 0 {
+            if self.history.len() == self.distance {
+                self.history.pop_front();
+            }
+            self.history.push_back((timestamp, data));
+        }
+
+        out
+    }
+}
+
+impl<'a> RedBlock<'a> {
+    /// Split a RED payload into its blocks. The primary block is last.
+    pub fn parse(payload: &'a [u8]) -> Result<Vec<RedBlock<'a>>, PacketError> {
+        let mut headers = vec![];
+        let mut i = 0;
+
+        loop {
+            let b = *payload.get(i).ok_or(PacketError::ErrShortPacket)?;
+            let pt: Pt = (b & 0x7f).into();
+
+            if b & 0x80 == 0 {
+                // Primary block. The length is the rest of the payload.
+                headers.push((pt, 0, None));
+                i += 1;
+                break;
+            }
+
+            let h = payload
+                .get(i + 1..i + 4)
+                .ok_or(PacketError::ErrShortPacket)?;
+            let v = u32::from_be_bytes([0, h[0], h[1], h[2]]);
+            headers.push((pt, v >> 10, Some((v & 0x3ff) as usize)));
+            i += 4;
+        }
+
+        let mut blocks = Vec::with_capacity(headers.len());
+
+        for (pt, timestamp_offset, len) in headers {
+            let len = len.unwrap_or(payload.len().saturating_sub(i));
+            let data = payload.get(i..i + len).ok_or(PacketError::ErrShortPacket)?;
+            i += len;
+
+            blocks.push(RedBlock {
+                pt,
+                timestamp_offset,
+                data,
+            });
+        }
+
+        Ok(blocks)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn red_roundtrip() {
+        let opus: Pt = 111.into();
+        let mut enc = RedEncoder::new(63.into(), 2);
+
+        // First frame has no history.
+        let p = enc.encode(opus, 0, vec![1, 1, 1], 1200);
+        assert_eq!(p, &[111, 1, 1, 1]);
+
+        enc.encode(opus, 960, vec![2, 2], 1200);
+        let p = enc.encode(opus, 1920, vec![3], 1200);
+
+        let blocks = RedBlock::parse(&p).unwrap();
+        assert_eq!(
+            blocks,
+            vec![
+                RedBlock {
+                    pt: opus,
+                    timestamp_offset: 1920,
+                    data: &[1, 1, 1],
+                },
+                RedBlock {
+                    pt: opus,
+                    timestamp_offset: 960,
+                    data: &[2, 2],
+                },
+                RedBlock {
+                    pt: opus,
+                    timestamp_offset: 0,
+                    data: &[3],
+                },
+            ]
+        );
+
+        // Distance 2 means the first frame is gone now.
+        let p = enc.encode(opus, 2880, vec![4], 1200);
+        let blocks = RedBlock::parse(&p).unwrap();
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].data, &[2, 2]);
+    }
+
+    #[test]
+    fn red_drops_redundancy_over_mtu() {
+        let opus: Pt = 111.into();
+        let mut enc = RedEncoder::new(63.into(), 2);
+
+        enc.encode(opus, 0, vec![1; 100], 1200);
+        enc.encode(opus, 960, vec![2; 100], 1200);
+
+        // Room for the primary and one redundant block.
+        let p = enc.encode(opus, 1920, vec![3; 100], 210);
+        assert!(p.len() <= 210);
+
+        let blocks = RedBlock::parse(&p).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].timestamp_offset, 960);
+        assert_eq!(blocks[1].data, &[3; 100]);
+    }
+
+    #[test]
+    fn red_distance_zero() {
+        let opus: Pt = 111.into();
+        let mut enc = RedEncoder::new(63.into(), 0);
+
+        enc.encode(opus, 0, vec![1], 1200);
+        let p = enc.encode(opus, 960, vec![2], 1200);
+        assert_eq!(p, &[111, 2]);
+    }
+
+    #[test]
+    fn red_parse_short() {
+        assert_eq!(RedBlock::parse(&[]), Err(PacketError::ErrShortPacket));
+        // Redundant header cut short.
+        assert_eq!(
+            RedBlock::parse(&[0x80 | 111, 0x00]),
+            Err(PacketError::ErrShortPacket)
+        );
+        // Block length longer than the payload.
+        assert_eq!(
+            RedBlock::parse(&[0x80 | 111, 0x0f, 0x00, 0x05, 111, 1, 2]),
+            Err(PacketError::ErrShortPacket)
+        );
+    }
+}
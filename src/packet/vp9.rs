@@ -9,6 +9,7 @@ use std::sync::Arc;
 const VP9HEADER_SIZE: usize = 3;
 const MAX_SPATIAL_LAYERS: usize = 3;
 const MAX_VP9REF_PICS: usize = 3;
+const MAX_PICTURE_GROUP: usize = 16;
 
 /// Vp9 information describing the depacketized/packetized data.
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -101,6 +102,81 @@ pub struct Vp9CodecExtra {
     ///
     /// [`MediaData`]: crate::media::MediaData
     pub is_keyframe: bool,
+
+    /// Whether the stream is sent in flexible mode (F bit).
+    ///
+    /// In flexible mode, the references of every layer frame are signalled
+    /// in [`Vp9LayerFrame::pdiffs`]. In non-flexible mode, the references
+    /// follow the picture group of the [`Vp9ScalabilityStructure`].
+    pub flexible_mode: bool,
+
+    /// Payload descriptor fields for each spatial layer frame in the picture.
+    ///
+    /// Indexed by spatial layer id. Streams without layer indices are
+    /// reported as spatial layer 0.
+    pub layer_frames: [Option<Vp9LayerFrame>; MAX_SPATIAL_LAYERS],
+
+    /// The scalability structure (SS), if it was sent with this picture.
+    ///
+    /// Typically only present on keyframes.
+    pub scalability_structure: Option<Vp9ScalabilityStructure>,
+}
+
+/// Payload descriptor fields of a single VP9 spatial layer frame.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Vp9LayerFrame {
+    /// Temporal layer id (T).
+    pub tid: u8,
+
+    /// Switching up point (U).
+    ///
+    /// When set, the receiver can switch up to a higher temporal layer
+    /// from this frame onwards.
+    pub switching_up_point: bool,
+
+    /// Inter-picture predicted (P).
+    ///
+    /// The frame depends on previous pictures of the same spatial layer.
+    pub inter_picture_predicted: bool,
+
+    /// Inter-layer dependency used (D).
+    ///
+    /// The frame depends on the lower spatial layer of the same picture.
+    pub inter_layer_predicted: bool,
+
+    /// Not a reference for upper spatial layers (Z).
+    pub not_upper_layer_reference: bool,
+
+    /// Reference picture id differences (P_DIFF), flexible mode only.
+    pub pdiffs: [Option<u8>; MAX_VP9REF_PICS],
+}
+
+/// The VP9 scalability structure (SS) of the payload descriptor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Vp9ScalabilityStructure {
+    /// Number of spatial layers in the stream (N_S + 1).
+    pub spatial_layers: u8,
+
+    /// Width and height for each spatial layer, if signalled (Y bit).
+    pub resolutions: [Option<(u16, u16)>; MAX_SPATIAL_LAYERS],
+
+    /// The pictures in a picture group, if signalled (G bit).
+    ///
+    /// Up to 16 pictures are supported.
+    pub picture_group: [Option<Vp9PictureGroupEntry>; MAX_PICTURE_GROUP],
+}
+
+/// One picture of the picture group (PG) in a [`Vp9ScalabilityStructure`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Vp9PictureGroupEntry {
+    /// Temporal layer id (T).
+    pub tid: u8,
+
+    /// Switching up point (U).
+    pub switching_up_point: bool,
+
+    /// Reference picture id differences (P_DIFF).
+    pub pdiffs: [Option<u8>; MAX_VP9REF_PICS],
 }
 
 /// Packetizes VP9 RTP packets.
@@ -311,6 +387,8 @@ impl Depacketizer for Vp9Depacketizer {
 
         let mut payload_index = 1;
 
+        self.pdiff.clear();
+
         if self.i {
             payload_index = self.parse_picture_id(&mut reader, payload_index)?;
         }
@@ -388,12 +466,65 @@ impl Vp9Depacketizer {
             .layers_heights
             .copy_from_slice(&self.height[..MAX_SPATIAL_LAYERS]);
         vp9_extra.is_keyframe |= !self.p && (self.sid == 0 || !self.l) && self.b;
+        vp9_extra.flexible_mode = self.f;
+
+        let mut pdiffs = [None; MAX_VP9REF_PICS];
+        for (d, s) in pdiffs.iter_mut().zip(&self.pdiff) {
+            *d = Some(*s);
+        }
+
+        let sid = if self.l { self.sid as usize } else { 0 };
+        vp9_extra.layer_frames[sid] = Some(Vp9LayerFrame {
+            tid: self.tid,
+            switching_up_point: self.u,
+            inter_picture_predicted: self.p,
+            inter_layer_predicted: self.d,
+            not_upper_layer_reference: self.z,
+            pdiffs,
+        });
+
+        if self.v {
+            vp9_extra.scalability_structure = Some(self.scalability_structure());
+        }
 
         *extra = CodecExtra::Vp9(vp9_extra);
 
         Ok(())
     }
 
+    fn scalability_structure(&self) -> Vp9ScalabilityStructure {
+        let mut ss = Vp9ScalabilityStructure {
+            spatial_layers: self.ns + 1,
+            ..Default::default()
+        };
+
+        if self.y {
+            for (i, r) in ss.resolutions.iter_mut().enumerate() {
+                *r = self.width[i].zip(self.height[i]);
+            }
+        }
+
+        for (i, entry) in ss
+            .picture_group
+            .iter_mut()
+            .enumerate()
+            .take(self.ng as usize)
+        {
+            let mut pdiffs = [None; MAX_VP9REF_PICS];
+            for (d, s) in pdiffs.iter_mut().zip(&self.pgpdiff[i]) {
+                *d = Some(*s);
+            }
+
+            *entry = Some(Vp9PictureGroupEntry {
+                tid: self.pgtid[i],
+                switching_up_point: self.pgu[i],
+                pdiffs,
+            });
+        }
+
+        ss
+    }
+
     // Picture ID:
     //
     //      +-+-+-+-+-+-+-+-+
@@ -506,6 +637,9 @@ impl Vp9Depacketizer {
     ) -> Result<usize, PacketError> {
         let mut b = 1u8;
         while (b & 0x1) != 0 {
+            if self.pdiff.len() == MAX_VP9REF_PICS {
+                return Err(PacketError::ErrTooManyPDiff);
+            }
             if reader.remaining() == 0 {
                 return Err(PacketError::ErrShortPacket);
             }
@@ -513,9 +647,6 @@ impl Vp9Depacketizer {
             payload_index += 1;
 
             self.pdiff.push(b >> 1);
-            if self.pdiff.len() >= MAX_VP9REF_PICS {
-                return Err(PacketError::ErrTooManyPDiff);
-            }
         }
 
         Ok(payload_index)
@@ -559,8 +690,11 @@ impl Vp9Depacketizer {
 
         let ns = (self.ns + 1) as usize;
         self.ng = 0;
+        self.pgtid.clear();
+        self.pgu.clear();
+        self.pgpdiff.clear();
 
-        if ns >= MAX_SPATIAL_LAYERS {
+        if ns > MAX_SPATIAL_LAYERS {
             return Err(PacketError::ErrVP9CorruptedPacket);
         }
 
@@ -583,6 +717,10 @@ impl Vp9Depacketizer {
 
             self.ng = reader.get_u8().ok_or(PacketError::ErrShortPacket)?;
             payload_index += 1;
+
+            if self.ng as usize > MAX_PICTURE_GROUP {
+                return Err(PacketError::ErrVP9CorruptedPacket);
+            }
         }
 
         for i in 0..self.ng as usize {
@@ -865,6 +1003,108 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_vp9_flexible_svc_extra() -> Result<(), PacketError> {
+        let mut p = Vp9Depacketizer::default();
+        let mut out = Vec::new();
+        let mut extra = CodecExtra::None;
+
+        // S0: I=1 L=1 F=1 B=1 E=1 V=1, T:0 U:0 S:0 D:0, SS with NS:1 Y:1 G:1
+        p.depacketize(
+            &[
+                0xBE,
+                0x81,
+                0x00,
+                0x00, // descriptor, PID 0x100, layer info
+                (1 << 5) | (1 << 4) | (1 << 3),
+                0x01,
+                0x40,
+                0x00,
+                0xB4, // 320x180
+                0x02,
+                0x80,
+                0x01,
+                0x68,                // 640x360
+                2,                   // N_G
+                (1 << 4),            // T:0 U:1 R:0
+                (1 << 5) | (1 << 2), // T:1 U:0 R:1
+                1,                   // P_DIFF
+                0xAA,
+            ],
+            &mut out,
+            &mut extra,
+        )?;
+
+        // S1: I=1 P=1 L=1 F=1 B=1 E=1, T:0 U:1 S:1 D:1, P_DIFF 1 and 2 (N)
+        p.depacketize(
+            &[0xFC, 0x81, 0x00, 0x13, 0x03, 0x04, 0xBB],
+            &mut out,
+            &mut extra,
+        )?;
+
+        assert_eq!(out, &[0xAA, 0xBB]);
+
+        let CodecExtra::Vp9(extra) = extra else {
+            panic!("Expected Vp9 extra");
+        };
+
+        assert!(extra.flexible_mode);
+        assert_eq!(extra.pid, 0x100);
+        assert_eq!(extra.layers_scheme[..2], [Some(1), Some(2)]);
+
+        let s0 = extra.layer_frames[0].unwrap();
+        assert!(!s0.inter_picture_predicted);
+        assert!(!s0.inter_layer_predicted);
+        assert_eq!(s0.pdiffs, [None; MAX_VP9REF_PICS]);
+
+        let s1 = extra.layer_frames[1].unwrap();
+        assert_eq!(s1.tid, 0);
+        assert!(s1.switching_up_point);
+        assert!(s1.inter_picture_predicted);
+        assert!(s1.inter_layer_predicted);
+        assert_eq!(s1.pdiffs, [Some(1), Some(2), None]);
+
+        assert_eq!(extra.layer_frames[2], None);
+
+        let ss = extra.scalability_structure.unwrap();
+        assert_eq!(ss.spatial_layers, 2);
+        assert_eq!(ss.resolutions, [Some((320, 180)), Some((640, 360)), None]);
+        assert_eq!(
+            ss.picture_group[0],
+            Some(Vp9PictureGroupEntry {
+                tid: 0,
+                switching_up_point: true,
+                pdiffs: [None; MAX_VP9REF_PICS],
+            })
+        );
+        assert_eq!(
+            ss.picture_group[1],
+            Some(Vp9PictureGroupEntry {
+                tid: 1,
+                switching_up_point: false,
+                pdiffs: [Some(1), None, None],
+            })
+        );
+        assert_eq!(ss.picture_group[2], None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_vp9_pdiff_not_accumulated() -> Result<(), PacketError> {
+        let mut p = Vp9Depacketizer::default();
+
+        for _ in 0..5 {
+            let mut out = Vec::new();
+            let mut extra = CodecExtra::None;
+            // Three P_DIFFs is the maximum allowed.
+            p.depacketize(&[0xD0, 0x02, 0x03, 0x05, 0x06, 0xAA], &mut out, &mut extra)?;
+            assert_eq!(p.pdiff, vec![1, 2, 3]);
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_vp9_packetizer_payload() -> Result<(), PacketError> {
         let mut r0 = 8692;
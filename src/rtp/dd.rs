@@ -0,0 +1,658 @@
+use super::{ExtensionSerializer, ExtensionValues};
+
+/// URI for the Dependency Descriptor RTP Header Extension
+pub const URI: &str =
+    "https://aomediacodec.github.io/av1-rtp-spec/#dependency-descriptor-rtp-header-extension";
+
+/// The Dependency Descriptor of a frame.
+///
+/// Describes how a frame relates to the spatial and temporal layers of the stream, which
+/// lets an SFU forward layers without parsing the codec payload.
+///
+/// When sending, set this per frame with
+/// [`Writer::user_extension_value()`][crate::media::Writer::user_extension_value]. str0m fills
+/// in [`start_of_frame`][Self::start_of_frame] and [`end_of_frame`][Self::end_of_frame] for each
+/// RTP packet, and only sends the [`structure`][Self::structure] in the first packet of the frame.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DependencyDescriptor {
+    /// This is the first packet of the frame.
+    pub start_of_frame: bool,
+    /// This is the last packet of the frame.
+    pub end_of_frame: bool,
+    /// The template describing the frame, see [`DependencyStructure::template_id()`].
+    pub template_id: u8,
+    /// Frame number, increasing by one for each frame and wrapping at 2^16.
+    pub frame_number: u16,
+    /// The structure of the templates.
+    ///
+    /// Must be sent with every keyframe and whenever the structure changes.
+    pub structure: Option<DependencyStructure>,
+}
+
+/// Templates describing the possible frames of a stream.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DependencyStructure {
+    /// Template id of the first template, 0-63.
+    pub template_id_offset: u8,
+    /// Number of decode targets, 1-32.
+    pub decode_target_count: u8,
+    /// The templates, ordered by spatial and then temporal id.
+    pub templates: Vec<FrameTemplate>,
+    /// Max resolution for each spatial layer, or empty.
+    pub resolutions: Vec<Resolution>,
+}
+
+/// A frame template.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FrameTemplate {
+    /// Spatial layer of the frame.
+    pub spatial_id: u8,
+    /// Temporal layer of the frame.
+    pub temporal_id: u8,
+    /// How the frame relates to each decode target.
+    pub dtis: Vec<DecodeTargetIndication>,
+    /// Differences in frame number to the frames this frame depends on, 1-16.
+    pub fdiffs: Vec<u8>,
+}
+
+/// How a frame relates to a decode target.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeTargetIndication {
+    /// The frame is not part of the decode target.
+    NotPresent = 0,
+    /// No other frame of the decode target depends on the frame.
+    Discardable = 1,
+    /// The decode target can be switched to starting at this frame.
+    Switch = 2,
+    /// The frame is needed to decode the decode target.
+    Required = 3,
+}
+
+/// Max resolution of a spatial layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolution {
+    /// Width in number of pixels, 1 to 65536.
+    pub width: u32,
+    /// Height in number of pixels, 1 to 65536.
+    pub height: u32,
+}
+
+impl DependencyDescriptor {
+    /// Copy of the descriptor for one RTP packet of the frame.
+    pub(crate) fn for_packet(&self, first: bool, last: bool) -> Self {
+        DependencyDescriptor {
+            start_of_frame: first,
+            end_of_frame: last,
+            template_id: self.template_id,
+            frame_number: self.frame_number,
+            structure: if first { self.structure.clone() } else { None },
+        }
+    }
+
+    fn serialize(&self) -> Option<Vec<u8>> {
+        if self.template_id > 63 {
+            return None;
+        }
+
+        let mut w = BitWriter::default();
+
+        w.write(self.start_of_frame as u32, 1);
+        w.write(self.end_of_frame as u32, 1);
+        w.write(self.template_id as u32, 6);
+        w.write(self.frame_number as u32, 16);
+
+        if let Some(structure) = &self.structure {
+            // template_dependency_structure_present_flag, and no active decode targets,
+            // custom dtis, custom fdiffs or custom chains.
+            w.write(0b10000, 5);
+            structure.write(&mut w)?;
+        }
+
+        Some(w.finish())
+    }
+
+    fn parse(buf: &[u8]) -> Option<Self> {
+        let mut r = BitReader::new(buf);
+
+        let start_of_frame = r.read(1)? == 1;
+        let end_of_frame = r.read(1)? == 1;
+        let template_id = r.read(6)? as u8;
+        let frame_number = r.read(16)? as u16;
+
+        let mut structure = None;
+
+        if buf.len() > 3 {
+            let structure_present = r.read(1)? == 1;
+            // active_decode_targets, custom dtis, custom fdiffs and custom chains flags
+            // can't be interpreted without keeping state, so we stop at the structure.
+            r.read(4)?;
+
+            if structure_present {
+                structure = Some(DependencyStructure::read(&mut r)?);
+            }
+        }
+
+        Some(DependencyDescriptor {
+            start_of_frame,
+            end_of_frame,
+            template_id,
+            frame_number,
+            structure,
+        })
+    }
+}
+
+impl DependencyStructure {
+    /// The template id to use for a frame in the given layer.
+    ///
+    /// This is the first template matching the spatial and temporal id.
+    pub fn template_id(&self, spatial_id: u8, temporal_id: u8) -> Option<u8> {
+        let index = self
+            .templates
+            .iter()
+            .position(|t| t.spatial_id == spatial_id && t.temporal_id == temporal_id)?;
+
+        Some(((self.template_id_offset as usize + index) % 64) as u8)
+    }
+
+    fn max_spatial_id(&self) -> u8 {
+        self.templates.last().map(|t| t.spatial_id).unwrap_or(0)
+    }
+
+    fn write(&self, w: &mut BitWriter) -> Option<()> {
+        let dt_count = self.decode_target_count as u32;
+
+        if self.template_id_offset > 63
+            || !(1..=32).contains(&dt_count)
+            || self.templates.is_empty()
+            || self.templates.len() > 64
+        {
+            return None;
+        }
+
+        w.write(self.template_id_offset as u32, 6);
+        w.write(dt_count - 1, 5);
+
+        // template_layers
+        for (i, t) in self.templates.iter().enumerate() {
+            if i == 0 && (t.spatial_id, t.temporal_id) != (0, 0) {
+                return None;
+            }
+
+            let next_layer_idc = match self.templates.get(i + 1) {
+                None => 3,
+                Some(n) if (n.spatial_id, n.temporal_id) == (t.spatial_id, t.temporal_id) => 0,
+                Some(n) if n.spatial_id == t.spatial_id && n.temporal_id == t.temporal_id + 1 => 1,
+                Some(n) if n.spatial_id == t.spatial_id + 1 && n.temporal_id == 0 => 2,
+                // The templates are not in layer order.
+                Some(_) => return None,
+            };
+            w.write(next_layer_idc, 2);
+        }
+
+        // template_dtis
+        for t in &self.templates {
+            if t.dtis.len() != dt_count as usize {
+                return None;
+            }
+            for dti in &t.dtis {
+                w.write(*dti as u32, 2);
+            }
+        }
+
+        // template_fdiffs
+        for t in &self.templates {
+            for fdiff in &t.fdiffs {
+                if !(1..=16).contains(fdiff) {
+                    return None;
+                }
+                w.write(1, 1);
+                w.write(*fdiff as u32 - 1, 4);
+            }
+            w.write(0, 1);
+        }
+
+        // template_chains, we don't use chains.
+        w.write_ns(0, dt_count + 1);
+
+        // resolutions
+        if self.resolutions.is_empty() {
+            w.write(0, 1);
+        } else {
+            if self.resolutions.len() != self.max_spatial_id() as usize + 1 {
+                return None;
+            }
+            w.write(1, 1);
+            for r in &self.resolutions {
+                for v in [r.width, r.height] {
+                    // Sent as value minus one in 16 bits.
+                    let v = v.checked_sub(1).filter(|v| *v <= 0xffff)?;
+                    w.write(v, 16);
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    fn read(r: &mut BitReader) -> Option<Self> {
+        let template_id_offset = r.read(6)? as u8;
+        let dt_count = r.read(5)? + 1;
+
+        // template_layers
+        let mut templates = vec![];
+        let (mut spatial_id, mut temporal_id) = (0, 0);
+        loop {
+            if templates.len() == 64 {
+                return None;
+            }
+            templates.push(FrameTemplate {
+                spatial_id,
+                temporal_id,
+                ..Default::default()
+            });
+            match r.read(2)? {
+                1 => temporal_id += 1,
+                2 => {
+                    temporal_id = 0;
+                    spatial_id += 1;
+                }
+                3 => break,
+                _ => {}
+            }
+        }
+
+        // template_dtis
+        for t in &mut templates {
+            for _ in 0..dt_count {
+                t.dtis.push(match r.read(2)? {
+                    0 => DecodeTargetIndication::NotPresent,
+                    1 => DecodeTargetIndication::Discardable,
+                    2 => DecodeTargetIndication::Switch,
+                    _ => DecodeTargetIndication::Required,
+                });
+            }
+        }
+
+        // template_fdiffs
+        for t in &mut templates {
+            while r.read(1)? == 1 {
+                t.fdiffs.push(r.read(4)? as u8 + 1);
+            }
+        }
+
+        // template_chains, read past them.
+        let chain_count = r.read_ns(dt_count + 1)?;
+        if chain_count > 0 {
+            for _ in 0..dt_count {
+                r.read_ns(chain_count)?;
+            }
+            for _ in 0..templates.len() as u32 * chain_count {
+                r.read(4)?;
+            }
+        }
+
+        // resolutions
+        let mut resolutions = vec![];
+        if r.read(1)? == 1 {
+            for _ in 0..=spatial_id {
+                resolutions.push(Resolution {
+                    width: r.read(16)? + 1,
+                    height: r.read(16)? + 1,
+                });
+            }
+        }
+
+        Some(DependencyStructure {
+            template_id_offset,
+            decode_target_count: dt_count as u8,
+            templates,
+            resolutions,
+        })
+    }
+}
+
+/// Serializer of the Dependency Descriptor Header Extension
+#[derive(Debug)]
+pub struct Serializer;
+
+impl ExtensionSerializer for Serializer {
+    fn write_to(&self, buf: &mut [u8], ev: &ExtensionValues) -> usize {
+        let Some(dd) = ev.user_values.get::<DependencyDescriptor>() else {
+            return 0;
+        };
+        let Some(v) = dd.serialize() else {
+            warn!("Not writing invalid DependencyDescriptor: {:?}", dd);
+            return 0;
+        };
+        if buf.len() < v.len() {
+            return 0;
+        }
+        buf[..v.len()].copy_from_slice(&v);
+        v.len()
+    }
+
+    fn parse_value(&self, buf: &[u8], ev: &mut ExtensionValues) -> bool {
+        let Some(dd) = DependencyDescriptor::parse(buf) else {
+            return false;
+        };
+        ev.user_values.set(dd);
+        true
+    }
+
+    fn is_video(&self) -> bool {
+        true
+    }
+
+    fn is_audio(&self) -> bool {
+        false
+    }
+
+    fn requires_two_byte_form(&self, ev: &ExtensionValues) -> bool {
+        // Only the mandatory fields (3 bytes) are sure to fit the one byte form. A
+        // structure mostly does not.
+        ev.user_values
+            .get::<DependencyDescriptor>()
+            .filter(|dd| dd.structure.is_some())
+            .and_then(|dd| dd.serialize())
+            .is_some_and(|v| v.len() > 16)
+    }
+}
+
+#[derive(Default)]
+struct BitWriter {
+    buf: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, n: usize) {
+        for i in (0..n).rev() {
+            if self.bits % 8 == 0 {
+                self.buf.push(0);
+            }
+            if (value >> i) & 1 == 1 {
+                *self.buf.last_mut().unwrap() |= 0x80 >> (self.bits % 8);
+            }
+            self.bits += 1;
+        }
+    }
+
+    /// Non-symmetric unsigned value in the range 0..n.
+    fn write_ns(&mut self, value: u32, n: u32) {
+        let w = 32 - n.leading_zeros() as usize;
+        let m = (1 << w) - n;
+        if value < m {
+            self.write(value, w - 1);
+        } else {
+            let v = value + m;
+            self.write(v >> 1, w - 1);
+            self.write(v & 1, 1);
+        }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+struct BitReader<'a> {
+    buf: &'a [u8],
+    bits: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        BitReader { buf, bits: 0 }
+    }
+
+    fn read(&mut self, n: usize) -> Option<u32> {
+        let mut v = 0;
+        for _ in 0..n {
+            let byte = self.buf.get(self.bits / 8)?;
+            let bit = (byte >> (7 - self.bits % 8)) & 1;
+            v = (v << 1) | bit as u32;
+            self.bits += 1;
+        }
+        Some(v)
+    }
+
+    /// Non-symmetric unsigned value in the range 0..n.
+    fn read_ns(&mut self, n: u32) -> Option<u32> {
+        let w = 32 - n.leading_zeros() as usize;
+        let m = (1 << w) - n;
+        let v = self.read(w - 1)?;
+        if v < m {
+            return Some(v);
+        }
+        let extra_bit = self.read(1)?;
+        Some((v << 1) - m + extra_bit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use DecodeTargetIndication::*;
+
+    // L1T2, two decode targets: the base layer and the full frame rate.
+    fn l1t2() -> DependencyStructure {
+        DependencyStructure {
+            template_id_offset: 0,
+            decode_target_count: 2,
+            templates: vec![
+                FrameTemplate {
+                    spatial_id: 0,
+                    temporal_id: 0,
+                    dtis: vec![Switch, Switch],
+                    fdiffs: vec![],
+                },
+                FrameTemplate {
+                    spatial_id: 0,
+                    temporal_id: 0,
+                    dtis: vec![Switch, Switch],
+                    fdiffs: vec![2],
+                },
+                FrameTemplate {
+                    spatial_id: 0,
+                    temporal_id: 1,
+                    dtis: vec![NotPresent, Discardable],
+                    fdiffs: vec![1],
+                },
+            ],
+            resolutions: vec![Resolution {
+                width: 1280,
+                height: 720,
+            }],
+        }
+    }
+
+    // L2T3 with resolutions, six decode targets: each spatial layer at three frame rates.
+    fn l2t3() -> DependencyStructure {
+        let (n, d, s, r) = (NotPresent, Discardable, Switch, Required);
+        let t = |spatial_id, temporal_id, dtis: [DecodeTargetIndication; 6], fdiffs: &[u8]| {
+            FrameTemplate {
+                spatial_id,
+                temporal_id,
+                dtis: dtis.to_vec(),
+                fdiffs: fdiffs.to_vec(),
+            }
+        };
+
+        DependencyStructure {
+            template_id_offset: 0,
+            decode_target_count: 6,
+            templates: vec![
+                t(0, 0, [s, s, s, s, s, s], &[]),
+                t(0, 0, [s, s, s, r, r, r], &[4]),
+                t(0, 1, [n, d, r, n, r, r], &[2]),
+                t(0, 2, [n, n, d, n, n, r], &[1]),
+                t(1, 0, [n, n, n, s, s, s], &[1]),
+                t(1, 0, [n, n, n, s, s, s], &[4, 1]),
+                t(1, 1, [n, n, n, n, d, r], &[2, 1]),
+                t(1, 2, [n, n, n, n, n, d], &[1, 1]),
+            ],
+            resolutions: vec![
+                Resolution {
+                    width: 640,
+                    height: 360,
+                },
+                Resolution {
+                    width: 1280,
+                    height: 720,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn mandatory_fields() {
+        let dd = DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: false,
+            template_id: 2,
+            frame_number: 0x1234,
+            structure: None,
+        };
+
+        let buf = dd.serialize().unwrap();
+        assert_eq!(buf, [0x82, 0x12, 0x34]);
+        assert_eq!(DependencyDescriptor::parse(&buf), Some(dd));
+    }
+
+    #[test]
+    fn structure_roundtrip() {
+        let dd = DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: true,
+            template_id: 0,
+            frame_number: 1,
+            structure: Some(l1t2()),
+        };
+
+        let buf = dd.serialize().unwrap();
+        assert!(buf.len() > 3);
+        assert_eq!(DependencyDescriptor::parse(&buf), Some(dd));
+    }
+
+    #[test]
+    fn invalid_structure() {
+        let mut s = l1t2();
+        s.templates.swap(1, 2);
+
+        let dd = DependencyDescriptor {
+            structure: Some(s),
+            ..Default::default()
+        };
+
+        assert_eq!(dd.serialize(), None);
+    }
+
+    #[test]
+    fn max_resolution() {
+        let mut s = l1t2();
+        s.resolutions[0] = Resolution {
+            width: 65536,
+            height: 65536,
+        };
+
+        let dd = DependencyDescriptor {
+            structure: Some(s.clone()),
+            ..Default::default()
+        };
+
+        // 0xffff on the wire.
+        let buf = dd.serialize().unwrap();
+        assert_eq!(DependencyDescriptor::parse(&buf), Some(dd));
+
+        s.resolutions[0].width = 65537;
+        let dd = DependencyDescriptor {
+            structure: Some(s),
+            ..Default::default()
+        };
+        assert_eq!(dd.serialize(), None);
+    }
+
+    #[test]
+    fn structure_two_byte_form() {
+        let dd = DependencyDescriptor {
+            start_of_frame: true,
+            end_of_frame: true,
+            template_id: 0,
+            frame_number: 1,
+            structure: Some(l2t3()),
+        };
+
+        let mut ev = ExtensionValues::default();
+        ev.user_values.set(dd.clone());
+        assert!(Serializer.requires_two_byte_form(&ev));
+
+        let mut buf = [0_u8; 255];
+        let n = Serializer.write_to(&mut buf, &ev);
+        assert!(n > 16);
+
+        let mut parsed = ExtensionValues::default();
+        assert!(Serializer.parse_value(&buf[..n], &mut parsed));
+        assert_eq!(parsed.user_values.get::<DependencyDescriptor>(), Some(&dd));
+
+        // A small structure still fits the one byte form.
+        let mut s = l1t2();
+        s.resolutions.clear();
+        ev.user_values.set(DependencyDescriptor {
+            structure: Some(s),
+            ..dd.clone()
+        });
+        assert!(!Serializer.requires_two_byte_form(&ev));
+
+        ev.user_values.set(DependencyDescriptor {
+            structure: None,
+            ..dd
+        });
+        assert!(!Serializer.requires_two_byte_form(&ev));
+    }
+
+    #[test]
+    fn template_id() {
+        let mut s = l1t2();
+        assert_eq!(s.template_id(0, 1), Some(2));
+        assert_eq!(s.template_id(1, 0), None);
+
+        s.template_id_offset = 63;
+        assert_eq!(s.template_id(0, 0), Some(63));
+        assert_eq!(s.template_id(0, 1), Some(1));
+    }
+
+    #[test]
+    fn ns() {
+        for n in 1..20 {
+            for v in 0..n {
+                let mut w = BitWriter::default();
+                w.write_ns(v, n);
+                w.write(1, 1);
+                let buf = w.finish();
+                let mut r = BitReader::new(&buf);
+                assert_eq!(r.read_ns(n), Some(v));
+                assert_eq!(r.read(1), Some(1));
+            }
+        }
+    }
+
+    #[test]
+    fn for_packet() {
+        let dd = DependencyDescriptor {
+            template_id: 1,
+            frame_number: 7,
+            structure: Some(l1t2()),
+            ..Default::default()
+        };
+
+        let first = dd.for_packet(true, false);
+        assert!(first.start_of_frame && !first.end_of_frame);
+        assert!(first.structure.is_some());
+
+        let last = dd.for_packet(false, true);
+        assert!(!last.start_of_frame && last.end_of_frame);
+        assert_eq!(last.structure, None);
+        assert_eq!(last.frame_number, 7);
+    }
+}
@@ -115,6 +115,71 @@ impl ExtensionsForm {
 impl UnwindSafe for Extension {}
 
 /// Trait for parsing/writing user RTP header extensions.
+///
+/// This is how application metadata travels with the media. The example below
+/// passes the measured loudness of the audio through, for normalization on the
+/// receiving side. Both sides must register the same URI and serializer.
+///
+/// ```
+/// # use str0m::Rtc;
+/// # use str0m::rtp::{Extension, ExtensionSerializer, ExtensionValues};
+/// /// Integrated loudness in LUFS, in steps of 1/256 LU.
+/// #[derive(Debug, Clone, Copy, PartialEq)]
+/// struct Loudness(i16);
+///
+/// #[derive(Debug)]
+/// struct LoudnessSerializer;
+///
+/// impl ExtensionSerializer for LoudnessSerializer {
+///     fn write_to(&self, buf: &mut [u8], ev: &ExtensionValues) -> usize {
+///         let Some(v) = ev.user_values.get::<Loudness>() else {
+///             return 0;
+///         };
+///         if buf.len() < 2 {
+///             return 0;
+///         }
+///         buf[..2].copy_from_slice(&v.0.to_be_bytes());
+///         2
+///     }
+///
+///     fn parse_value(&self, buf: &[u8], ev: &mut ExtensionValues) -> bool {
+///         if buf.len() < 2 {
+///             return false;
+///         }
+///         ev.user_values.set(Loudness(i16::from_be_bytes([buf[0], buf[1]])));
+///         true
+///     }
+///
+///     fn is_audio(&self) -> bool {
+///         true
+///     }
+///
+///     fn is_video(&self) -> bool {
+///         false
+///     }
+/// }
+///
+/// let ext = Extension::with_serializer("urn:example:loudness", LoudnessSerializer);
+///
+/// let _rtc = Rtc::builder().set_extension(14, ext).build();
+///
+/// // When sending, the value is attached per write:
+/// // rtc.writer(mid).unwrap()
+/// //     .user_extension_value(Loudness(-23 * 256))
+/// //     .write(pt, wallclock, time, data)?;
+/// //
+/// // and is found on the receiving side in MediaData::ext_vals.user_values.
+///
+/// // What goes on the wire.
+/// let mut ev = ExtensionValues::default();
+/// ev.user_values.set(Loudness(-23 * 256));
+/// let mut buf = [0; 16];
+/// let n = LoudnessSerializer.write_to(&mut buf, &ev);
+///
+/// let mut parsed = ExtensionValues::default();
+/// assert!(LoudnessSerializer.parse_value(&buf[..n], &mut parsed));
+/// assert_eq!(parsed.user_values.get::<Loudness>(), Some(&Loudness(-23 * 256)));
+/// ```
 pub trait ExtensionSerializer: Debug + Send + Sync + 'static {
     /// Write the extension to the buffer of bytes. Must return the number
     /// of bytes written. This can be 0 if the extension could not be serialized.
@@ -140,12 +205,23 @@ pub trait ExtensionSerializer: Debug + Send + Sync + 'static {
 impl Extension {
     fn requires_two_byte_form(&self, ev: &ExtensionValues) -> bool {
         match self {
+            Extension::RtpStreamId => ev.rid.is_some_and(|v| v.len() > 16),
+            Extension::RepairedRtpStreamId => ev.rid_repair.is_some_and(|v| v.len() > 16),
             Extension::UnknownUri(_, serializer) => serializer.requires_two_byte_form(ev),
             _ => false,
         }
     }
 }
 
+/// Checks an RtpStreamId value before writing it.
+///
+/// RFC 8852 limits the value to 1-255 bytes, and RFC 8851 to alphanumeric, `-` and `_`.
+fn is_valid_stream_id(v: &[u8]) -> bool {
+    (1..=255).contains(&v.len())
+        && v.iter()
+            .all(|c| c.is_ascii_alphanumeric() || *c == b'-' || *c == b'_')
+}
+
 /// This is a placeholder value for when the Extension URI are parsed in an SDP OFFER/ANSWER.
 /// The trait write_to() and parse_value() should never be called (that would be a bug).
 #[derive(Debug)]
@@ -346,7 +422,57 @@ pub struct ExtensionMap([Option<MapEntry>; MAX_ID as usize]); // index 0 is extm
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct MapEntry {
     ext: Extension,
-    locked: bool,
+    /// Locked down by a negotiation of an audio m-line.
+    locked_audio: bool,
+    /// Locked down by a negotiation of a video m-line.
+    locked_video: bool,
+}
+
+impl MapEntry {
+    fn new(ext: Extension) -> Self {
+        MapEntry {
+            ext,
+            locked_audio: false,
+            locked_video: false,
+        }
+    }
+
+    fn is_locked(&self) -> bool {
+        self.locked_audio || self.locked_video
+    }
+
+    fn is_locked_for(&self, audio: bool) -> bool {
+        if audio {
+            self.locked_audio
+        } else {
+            self.locked_video
+        }
+    }
+
+    fn lock(&mut self, audio: bool) {
+        if audio {
+            self.locked_audio = true;
+        } else {
+            self.locked_video = true;
+        }
+    }
+}
+
+/// A remote extmap that could not be applied in [`ExtensionMap::remap`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RemapConflict {
+    /// The extension is locked to another id by a previous negotiation of the same media type.
+    Locked {
+        extension: Extension,
+        locked_id: u8,
+        requested_id: u8,
+    },
+    /// The id is locked to another extension.
+    IdInUse {
+        extension: Extension,
+        id: u8,
+        existing: Extension,
+    },
 }
 
 impl ExtensionMap {
@@ -389,9 +515,14 @@ impl ExtensionMap {
         }
         let idx = id as usize - 1;
 
-        let m = MapEntry { ext, locked: false };
+        self.0[idx] = Some(MapEntry::new(ext));
+    }
 
-        self.0[idx] = Some(m);
+    /// Remove the mapping for the id.
+    pub(crate) fn remove(&mut self, id: u8) {
+        if id >= 1 && id <= MAX_ID {
+            self.0[id as usize - 1] = None;
+        }
     }
 
     /// Look up the extension for the id.
@@ -427,13 +558,32 @@ impl ExtensionMap {
 
     /// Returns an iterator over the audio or video elements of the extension map
     pub fn iter_by_media_type(&self, audio: bool) -> impl Iterator<Item = (u8, &Extension)> + '_ {
-        self.iter().filter(move |(_id, ext)| {
-            if audio {
-                ext.is_audio()
-            } else {
-                ext.is_video()
-            }
-        })
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(i, e)| e.as_ref().map(|e| (i, e)))
+            .filter(move |(i, e)| {
+                let is_type = if audio {
+                    e.ext.is_audio()
+                } else {
+                    e.ext.is_video()
+                };
+                is_type && !self.is_shadowed(*i, e, audio)
+            })
+            .map(|(i, e)| ((i + 1) as u8, &e.ext))
+    }
+
+    /// Tells if an entry belongs to the other media type, because the remote side
+    /// uses different ids for audio and video, and there is another entry for this one.
+    fn is_shadowed(&self, index: usize, entry: &MapEntry, audio: bool) -> bool {
+        if entry.is_locked_for(audio) || !entry.is_locked() {
+            return false;
+        }
+
+        self.0
+            .iter()
+            .enumerate()
+            .any(|(i, e)| i != index && e.as_ref().map(|e| &e.ext) == Some(&entry.ext))
     }
 
     /// Returns an iterator over the audio elements of the extension map
@@ -540,15 +690,39 @@ impl ExtensionMap {
             if let Some(v) = x {
                 match form {
                     ExtensionsForm::OneByte => {
-                        if let Some(n) = v.ext.write_to(&mut b[1..], ev) {
-                            assert!(n <= 16);
+                        let id = idx as u8 + 1;
+                        if id > MAX_ID_ONE_BYTE_FORM {
+                            // Can't be written in this form.
+                            continue;
+                        }
+                        if v.ext.requires_two_byte_form(ev) {
+                            warn!(
+                                "Drop {:?} value that needs the two byte form, \
+                                which is not negotiated (extmap-allow-mixed)",
+                                v.ext
+                            );
+                            continue;
+                        }
+                        let Some(value_buf) = b.get_mut(1..) else {
+                            // No room left for even the header byte.
+                            break;
+                        };
+                        if let Some(n) = v.ext.write_to(value_buf, ev) {
                             assert!(n > 0);
+                            if n > 16 {
+                                warn!("Drop {:?} value of {} bytes in the one byte form", v.ext, n);
+                                continue;
+                            }
                             b[0] = (idx as u8 + 1) << 4 | (n as u8 - 1);
                             b = &mut b[1 + n..];
                         }
                     }
                     ExtensionsForm::TwoByte => {
-                        if let Some(n) = v.ext.write_to(&mut b[2..], ev) {
+                        let Some(value_buf) = b.get_mut(2..) else {
+                            break;
+                        };
+                        if let Some(n) = v.ext.write_to(value_buf, ev) {
+                            assert!(n <= 255);
                             b[0] = (idx + 1) as u8;
                             b[1] = n as u8;
                             b = &mut b[2 + n..];
@@ -561,57 +735,130 @@ impl ExtensionMap {
         orig_len - b.len()
     }
 
-    pub(crate) fn remap(&mut self, remote_exts: &[(u8, &Extension)]) {
+    /// Remap to the numbers used by the remote side for one audio or video m-line.
+    ///
+    /// The remote side is allowed to use different ids for the same extension in audio
+    /// and video. Each media type locks down its own id, and the map then holds the
+    /// extension under both. Returns the remote remaps that were ignored.
+    pub(crate) fn remap(
+        &mut self,
+        remote_exts: &[(u8, &Extension)],
+        audio: bool,
+    ) -> Vec<RemapConflict> {
+        let mut conflicts = vec![];
+
         // Match remote numbers and lock down those we see for the first time.
         for (id, ext) in remote_exts {
-            self.swap(*id, ext);
+            if let Some(conflict) = self.swap(*id, ext, audio) {
+                conflicts.push(conflict);
+            }
         }
+
+        conflicts
     }
 
-    fn swap(&mut self, id: u8, ext: &Extension) {
+    fn swap(&mut self, id: u8, ext: &Extension, audio: bool) -> Option<RemapConflict> {
         if id < 1 || id > MAX_ID {
-            return;
+            return None;
         }
 
-        // Mapping goes from 0 to 13.
+        // Mapping goes from 0 to 15.
         let new_index = id as usize - 1;
 
-        let Some(old_index) = self
+        // Already at the requested id, possibly from the other media type.
+        if self.0[new_index].as_ref().is_some_and(|m| &m.ext == ext) {
+            if let Some(locked_id) = self.locked_id(ext, audio).filter(|l| *l != id) {
+                return Some(self.locked_conflict(ext, locked_id, id));
+            }
+            // Locking must be done regardless of whether there was an actual change.
+            self.0[new_index].as_mut().unwrap().lock(audio);
+            return None;
+        }
+
+        // Prefer the entry locked for this media type, then one not locked at all.
+        let old_index = self
             .0
             .iter()
             .enumerate()
-            .find(|(_, m)| m.as_ref().map(|m| &m.ext) == Some(ext))
-            .map(|(i, _)| i)
-        else {
-            return;
-        };
+            .filter_map(|(i, m)| m.as_ref().filter(|m| &m.ext == ext).map(|m| (i, m)))
+            .min_by_key(|(_, m)| {
+                if m.is_locked_for(audio) {
+                    0
+                } else if !m.is_locked() {
+                    1
+                } else {
+                    2
+                }
+            })
+            .map(|(i, _)| i)?;
 
-        // Unwrap OK because index is checking just above.
-        let old = self.0[old_index].as_mut().unwrap();
+        // Unwrap OK because index is found just above.
+        let old = self.0[old_index].as_ref().unwrap();
 
-        let is_change = new_index != old_index;
+        if old.is_locked_for(audio) {
+            return Some(self.locked_conflict(ext, old_index as u8 + 1, id));
+        }
 
-        // If either audio or video is locked, we got a previous extmap negotiation.
-        if is_change && old.locked {
+        // Don't move an extension that is locked at the requested id.
+        if let Some(existing) = self.0[new_index].as_ref().filter(|m| m.is_locked()) {
             warn!(
-                "Extmap locked by previous negotiation. Ignore change: {} -> {}",
-                old_index, new_index
+                "Extmap id {} already locked to {}. Ignore change for {}",
+                id, existing.ext, ext
             );
-            return;
+            return Some(RemapConflict::IdInUse {
+                extension: ext.clone(),
+                id,
+                existing: existing.ext.clone(),
+            });
         }
 
-        // Locking must be done regardless of whether there was an actual change.
-        old.locked = true;
+        if old.is_locked() {
+            // The other media type uses the old id, keep it and add this one.
+            let mut entry = MapEntry::new(ext.clone());
+            entry.lock(audio);
 
-        if !is_change {
-            return;
+            if let Some(displaced) = self.0[new_index].replace(entry) {
+                if let Some(free) = self.0.iter().position(|m| m.is_none()) {
+                    self.0[free] = Some(displaced);
+                } else {
+                    debug!("No free extmap id for displaced: {}", displaced.ext);
+                }
+            }
+        } else {
+            self.0.swap(old_index, new_index);
+            self.0[new_index].as_mut().unwrap().lock(audio);
         }
 
-        self.0.swap(old_index, new_index);
+        None
+    }
+
+    fn locked_id(&self, ext: &Extension, audio: bool) -> Option<u8> {
+        self.0
+            .iter()
+            .position(|m| {
+                m.as_ref()
+                    .is_some_and(|m| &m.ext == ext && m.is_locked_for(audio))
+            })
+            .map(|i| i as u8 + 1)
+    }
+
+    fn locked_conflict(&self, ext: &Extension, locked_id: u8, requested_id: u8) -> RemapConflict {
+        warn!(
+            "Extmap locked by previous negotiation. Ignore change: {} -> {}",
+            locked_id, requested_id
+        );
+        RemapConflict::Locked {
+            extension: ext.clone(),
+            locked_id,
+            requested_id,
+        }
     }
 }
 
 impl Extension {
+    /// Write the extension value to `buf`.
+    ///
+    /// Returns `None` if there is no value to write, or if `buf` is too short for it.
     pub(crate) fn write_to(&self, buf: &mut [u8], ev: &ExtensionValues) -> Option<usize> {
         use Extension::*;
         match self {
@@ -630,28 +877,29 @@ impl Extension {
                     .rebase(Frequency::FIXED_POINT_6_18)
                     .numer() as u32;
 
-                buf[..3].copy_from_slice(&time_24.to_be_bytes()[1..]);
+                buf.get_mut(..3)?
+                    .copy_from_slice(&time_24.to_be_bytes()[1..]);
                 Some(3)
             }
             AudioLevel => {
                 let v1 = ev.audio_level?;
                 let v2 = ev.voice_activity?;
-                buf[0] = if v2 { 0x80 } else { 0 } | (-(0x7f & v1) as u8);
+                *buf.first_mut()? = if v2 { 0x80 } else { 0 } | (-(0x7f & v1) as u8);
                 Some(1)
             }
             TransmissionTimeOffset => {
                 let v = ev.tx_time_offs?;
-                buf[..4].copy_from_slice(&v.to_be_bytes());
+                buf.get_mut(..4)?.copy_from_slice(&v.to_be_bytes());
                 Some(4)
             }
             VideoOrientation => {
                 let v = ev.video_orientation?;
-                buf[0] = v as u8;
+                *buf.first_mut()? = v as u8;
                 Some(1)
             }
             TransportSequenceNumber => {
                 let v = ev.transport_cc?;
-                buf[..2].copy_from_slice(&v.to_be_bytes());
+                buf.get_mut(..2)?.copy_from_slice(&v.to_be_bytes());
                 Some(2)
             }
             PlayoutDelay => {
@@ -659,6 +907,7 @@ impl Extension {
                 let v2 = ev.play_delay_max?.rebase(Frequency::HUNDREDTHS);
                 let min = (v1.numer() & 0xfff) as u32;
                 let max = (v2.numer() & 0xfff) as u32;
+                let buf = buf.get_mut(..3)?;
                 buf[0] = (min >> 4) as u8;
                 buf[1] = (min << 4) as u8 | (max >> 8) as u8;
                 buf[2] = max as u8;
@@ -666,11 +915,12 @@ impl Extension {
             }
             VideoContentType => {
                 let v = ev.video_content_type?;
-                buf[0] = v;
+                *buf.first_mut()? = v;
                 Some(1)
             }
             VideoTiming => {
                 let v = ev.video_timing?;
+                let buf = buf.get_mut(..13)?;
                 buf[0] = v.flags;
                 buf[1..3].copy_from_slice(&v.encode_start.to_be_bytes());
                 buf[3..5].copy_from_slice(&v.encode_finish.to_be_bytes());
@@ -681,27 +931,29 @@ impl Extension {
                 buf[11..13].copy_from_slice(&0_u16.to_be_bytes());
                 Some(13)
             }
-            RtpStreamId => {
-                let v = ev.rid?;
-                let l = v.as_bytes().len();
-                buf[..l].copy_from_slice(v.as_bytes());
-                Some(l)
-            }
-            RepairedRtpStreamId => {
-                let v = ev.rid_repair?;
-                let l = v.as_bytes().len();
-                buf[..l].copy_from_slice(v.as_bytes());
-                Some(l)
+            RtpStreamId | RepairedRtpStreamId => {
+                let v = if *self == RtpStreamId {
+                    ev.rid?
+                } else {
+                    ev.rid_repair?
+                };
+                let b = v.as_bytes();
+                if !is_valid_stream_id(b) {
+                    warn!("Not writing invalid {:?}: {:?}", self, v);
+                    return None;
+                }
+                buf.get_mut(..b.len())?.copy_from_slice(b);
+                Some(b.len())
             }
             RtpMid => {
                 let v = ev.mid?;
                 let l = v.as_bytes().len();
-                buf[..l].copy_from_slice(v.as_bytes());
+                buf.get_mut(..l)?.copy_from_slice(v.as_bytes());
                 Some(l)
             }
             FrameMarking => {
                 let v = ev.frame_mark?;
-                buf[..4].copy_from_slice(&v.to_be_bytes());
+                buf.get_mut(..4)?.copy_from_slice(&v.to_be_bytes());
                 Some(4)
             }
             ColorSpace => {
@@ -713,6 +965,13 @@ impl Extension {
 
                 if n == 0 {
                     None
+                } else if n > buf.len() {
+                    warn!(
+                        "ExtensionSerializer wrote more than the buffer: {} > {}",
+                        n,
+                        buf.len()
+                    );
+                    None
                 } else {
                     Some(n)
                 }
@@ -912,6 +1171,57 @@ impl ExtensionValues {
 }
 
 /// Space for storing user extension values via [`ExtensionSerializer`].
+///
+/// This is a typed map where the type of the value is the key, i.e. it holds at most
+/// one value per type. Applications attach custom per-packet metadata by defining a
+/// wrapper type and reading/writing it in their own [`ExtensionSerializer`].
+///
+/// * Outgoing: set values using [`Writer::user_extension_value()`][crate::media::Writer::user_extension_value]
+///   or directly in the [`ExtensionValues`] for the RTP level API. The serializer reads
+///   them in [`ExtensionSerializer::write_to()`].
+/// * Incoming: the serializer puts them in place in [`ExtensionSerializer::parse_value()`],
+///   and they are found in the `ext_vals` of [`MediaData`][crate::media::MediaData] or
+///   [`RtpPacket`][crate::rtp::RtpPacket].
+///
+/// ```
+/// # use str0m::rtp::{ExtensionSerializer, ExtensionValues};
+/// #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// struct Loudness(u8);
+///
+/// #[derive(Debug)]
+/// struct LoudnessSerializer;
+///
+/// impl ExtensionSerializer for LoudnessSerializer {
+///     fn write_to(&self, buf: &mut [u8], ev: &ExtensionValues) -> usize {
+///         let Some(v) = ev.user_values.get::<Loudness>() else {
+///             return 0;
+///         };
+///         buf[0] = v.0;
+///         1
+///     }
+///
+///     fn parse_value(&self, buf: &[u8], ev: &mut ExtensionValues) -> bool {
+///         let Some(v) = buf.first() else {
+///             return false;
+///         };
+///         ev.user_values.set(Loudness(*v));
+///         true
+///     }
+///
+///     fn is_video(&self) -> bool { false }
+///     fn is_audio(&self) -> bool { true }
+/// }
+///
+/// let mut ev = ExtensionValues::default();
+/// ev.user_values.set(Loudness(12));
+///
+/// let mut buf = [0; 16];
+/// let n = LoudnessSerializer.write_to(&mut buf, &ev);
+///
+/// let mut parsed = ExtensionValues::default();
+/// assert!(LoudnessSerializer.parse_value(&buf[..n], &mut parsed));
+/// assert_eq!(parsed.user_values.get::<Loudness>(), Some(&Loudness(12)));
+/// ```
 #[derive(Clone, Default)]
 pub struct UserExtensionValues {
     map: Option<AnyMap>,
@@ -1028,6 +1338,63 @@ impl UserExtensionValues {
             .downcast()
             .ok()
     }
+
+    /// Remove a user extension value (by type).
+    ///
+    /// Returns the removed value, if there was one.
+    ///
+    /// ```
+    /// # use str0m::rtp::ExtensionValues;
+    /// let mut exts = ExtensionValues::default();
+    ///
+    /// #[derive(Debug, PartialEq, Eq)]
+    /// struct MySpecialType(u8);
+    ///
+    /// exts.user_values.set(MySpecialType(42));
+    ///
+    /// let v = exts.user_values.remove::<MySpecialType>();
+    ///
+    /// assert_eq!(v.as_deref(), Some(&MySpecialType(42)));
+    /// assert!(!exts.user_values.contains::<MySpecialType>());
+    /// ```
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<Arc<T>> {
+        self.map
+            .as_mut()?
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+    }
+
+    /// Tells if there is a user extension value of the given type.
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map
+            .as_ref()
+            .map(|map| map.contains_key(&TypeId::of::<T>()))
+            .unwrap_or(false)
+    }
+
+    /// Number of user extension values set.
+    pub fn len(&self) -> usize {
+        self.map.as_ref().map(|map| map.len()).unwrap_or(0)
+    }
+
+    /// Tells if no user extension values are set.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Remove all user extension values.
+    pub fn clear(&mut self) {
+        self.map = None;
+    }
+}
+
+impl fmt::Debug for UserExtensionValues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("UserExtensionValues")
+            .field("len", &self.len())
+            .finish()
+    }
 }
 
 impl UnwindSafe for UserExtensionValues {}
@@ -1217,6 +1584,54 @@ mod test {
         assert!(abs < Duration::from_millis(1));
     }
 
+    #[test]
+    fn rid_form_and_validation() {
+        let mut exts = ExtensionMap::empty();
+        exts.set(10, Extension::RtpStreamId);
+
+        let short = ExtensionValues {
+            rid: Some("hi".into()),
+            ..Default::default()
+        };
+        assert_eq!(ExtensionsForm::OneByte, exts.form(&short));
+
+        let long_rid: Rid = "a_very_long_rtp_stream_id".into();
+        let long = ExtensionValues {
+            rid: Some(long_rid),
+            ..Default::default()
+        };
+        assert_eq!(ExtensionsForm::TwoByte, exts.form(&long));
+
+        let mut buf = vec![0_u8; 64];
+        let n = exts.write_to(&mut buf[..], &long, ExtensionsForm::TwoByte);
+        assert_eq!(n, 2 + long_rid.len());
+
+        let mut ev = ExtensionValues::default();
+        exts.parse(&buf[..n], ExtensionsForm::TwoByte, &mut ev);
+        assert_eq!(ev.rid, Some(long_rid));
+
+        // Skipped in the one byte form rather than corrupting the block.
+        assert_eq!(
+            exts.write_to(&mut buf[..], &long, ExtensionsForm::OneByte),
+            0
+        );
+
+        let mut arr = [b' '; 32];
+        arr[..3].copy_from_slice(b"a!b");
+        let invalid = ExtensionValues {
+            rid: Some(Rid::from_array(arr)),
+            ..Default::default()
+        };
+        assert_eq!(
+            exts.write_to(&mut buf[..], &invalid, ExtensionsForm::OneByte),
+            0
+        );
+
+        // Capped at 32 characters.
+        let truncated: Rid = "a".repeat(40).as_str().into();
+        assert_eq!(truncated.len(), 32);
+    }
+
     #[test]
     fn abs_send_time_two_byte_form() {
         let now = Instant::now() + Duration::from_secs(1000);
@@ -1245,6 +1660,132 @@ mod test {
         assert!(abs < Duration::from_millis(1));
     }
 
+    #[test]
+    fn write_to_short_buffer() {
+        use Extension::*;
+
+        let ev = ExtensionValues {
+            audio_level: Some(-30),
+            voice_activity: Some(true),
+            video_orientation: Some(super::VideoOrientation::Deg90),
+            video_content_type: Some(1),
+            tx_time_offs: Some(12),
+            abs_send_time: Some(Instant::now()),
+            transport_cc: Some(42),
+            play_delay_min: Some(MediaTime::from_hundredths(100)),
+            play_delay_max: Some(MediaTime::from_hundredths(200)),
+            video_timing: Some(super::VideoTiming {
+                flags: 1,
+                encode_start: 2,
+                encode_finish: 3,
+                packetize_complete: 4,
+                last_left_pacer: 5,
+            }),
+            rid: Some("hi".into()),
+            rid_repair: Some("hi".into()),
+            mid: Some("video".into()),
+            frame_mark: Some(7),
+            ..Default::default()
+        };
+
+        let all = [
+            (AbsoluteSendTime, 3),
+            (AudioLevel, 1),
+            (TransmissionTimeOffset, 4),
+            (VideoOrientation, 1),
+            (TransportSequenceNumber, 2),
+            (PlayoutDelay, 3),
+            (VideoContentType, 1),
+            (VideoTiming, 13),
+            (RtpStreamId, 2),
+            (RepairedRtpStreamId, 2),
+            (RtpMid, 5),
+            (FrameMarking, 4),
+        ];
+
+        for (ext, len) in all {
+            let mut buf = [0_u8; 16];
+            assert_eq!(ext.write_to(&mut buf[..len], &ev), Some(len), "{ext}");
+
+            for short in 0..len {
+                assert_eq!(ext.write_to(&mut buf[..short], &ev), None, "{ext}");
+            }
+        }
+    }
+
+    #[test]
+    fn write_map_to_short_buffer() {
+        let exts = ExtensionMap::standard();
+        let ev = ExtensionValues {
+            audio_level: Some(-30),
+            voice_activity: Some(true),
+            abs_send_time: Some(Instant::now()),
+            transport_cc: Some(42),
+            mid: Some("video".into()),
+            ..Default::default()
+        };
+
+        for form in [ExtensionsForm::OneByte, ExtensionsForm::TwoByte] {
+            for len in 0..16 {
+                let mut buf = vec![0_u8; len];
+                let n = exts.write_to(&mut buf[..], &ev, form);
+                assert!(n <= len);
+            }
+        }
+    }
+
+    #[test]
+    fn oversized_value_one_byte_form() {
+        // Doesn't tell it needs the two byte form for its 20 bytes.
+        #[derive(Debug)]
+        struct Oversized;
+
+        impl ExtensionSerializer for Oversized {
+            fn write_to(&self, buf: &mut [u8], _ev: &ExtensionValues) -> usize {
+                let Some(buf) = buf.get_mut(..20) else {
+                    return 0;
+                };
+                buf.fill(1);
+                20
+            }
+
+            fn parse_value(&self, _buf: &[u8], _ev: &mut ExtensionValues) -> bool {
+                false
+            }
+
+            fn is_video(&self) -> bool {
+                true
+            }
+
+            fn is_audio(&self) -> bool {
+                true
+            }
+        }
+
+        let mut exts = ExtensionMap::empty();
+        exts.set(
+            1,
+            Extension::with_serializer("urn:example:oversized", Oversized),
+        );
+        exts.set(2, Extension::TransportSequenceNumber);
+        let ev = ExtensionValues {
+            transport_cc: Some(42),
+            ..Default::default()
+        };
+
+        // Dropped rather than corrupting the block.
+        let mut buf = vec![0_u8; 64];
+        let n = exts.write_to(&mut buf[..], &ev, ExtensionsForm::OneByte);
+        assert_eq!(n, 3);
+
+        let mut ev2 = ExtensionValues::default();
+        exts.parse(&buf[..n], ExtensionsForm::OneByte, &mut ev2);
+        assert_eq!(ev2.transport_cc, Some(42));
+
+        let n = exts.write_to(&mut buf[..], &ev, ExtensionsForm::TwoByte);
+        assert_eq!(n, 2 + 20 + 2 + 2);
+    }
+
     #[test]
     fn playout_delay() {
         let mut exts = ExtensionMap::empty();
@@ -1275,7 +1816,7 @@ mod test {
 
         println!("{:?}", e1.iter_video().collect::<Vec<_>>());
 
-        e1.remap(&e2.iter_audio().collect::<Vec<_>>());
+        e1.remap(&e2.iter_audio().collect::<Vec<_>>(), true);
 
         // e1 should have adjusted the TransportSequenceNumber for audio
         assert_eq!(
@@ -1316,7 +1857,7 @@ mod test {
         e2.set(14, TransportSequenceNumber);
         e2.set(12, VideoOrientation);
 
-        e1.remap(&e2.iter_video().collect::<Vec<_>>());
+        e1.remap(&e2.iter_video().collect::<Vec<_>>(), false);
 
         // e1 should have adjusted to e2.
         assert_eq!(
@@ -1340,7 +1881,7 @@ mod test {
         e2.set(14, TransportSequenceNumber);
         e2.set(12, VideoOrientation);
 
-        e1.remap(&e2.iter_video().collect::<Vec<_>>());
+        e1.remap(&e2.iter_video().collect::<Vec<_>>(), false);
 
         // just make sure the logic isn't wrong for 12-14 -> 14-12
         assert_eq!(
@@ -1367,7 +1908,7 @@ mod test {
         e3.set(12, AudioLevel); // change of type for existing.
 
         // First apply e2
-        e1.remap(&e2.iter_video().collect::<Vec<_>>());
+        e1.remap(&e2.iter_video().collect::<Vec<_>>(), false);
 
         println!("{:#?}", e1.0);
         assert_eq!(
@@ -1375,8 +1916,16 @@ mod test {
             vec![(12, &VideoOrientation), (14, &TransportSequenceNumber)]
         );
 
-        // Now attempt e3
-        e1.remap(&e3.iter_audio().collect::<Vec<_>>());
+        // Now attempt e3 for video again
+        let conflicts = e1.remap(&e3.iter_audio().collect::<Vec<_>>(), false);
+        assert_eq!(
+            conflicts,
+            vec![RemapConflict::Locked {
+                extension: TransportSequenceNumber,
+                locked_id: 14,
+                requested_id: 1
+            }]
+        );
 
         println!("{:#?}", e1.0);
         // At this point we should have not allowed the change, but remain as it was in first apply.
@@ -1385,4 +1934,78 @@ mod test {
             vec![(12, &VideoOrientation), (14, &TransportSequenceNumber)]
         );
     }
+
+    #[test]
+    fn remap_exts_audio_video_different_ids() {
+        use Extension::*;
+
+        let mut e1 = ExtensionMap::standard();
+
+        let mut audio = ExtensionMap::empty();
+        audio.set(1, AudioLevel);
+        audio.set(5, TransportSequenceNumber);
+
+        let mut video = ExtensionMap::empty();
+        video.set(4, RtpMid);
+        video.set(6, TransportSequenceNumber);
+
+        let conflicts = e1.remap(&audio.iter_audio().collect::<Vec<_>>(), true);
+        assert!(conflicts.is_empty());
+        let conflicts = e1.remap(&video.iter_video().collect::<Vec<_>>(), false);
+        assert!(conflicts.is_empty());
+
+        // Each media type keeps the id the remote uses for it.
+        assert_eq!(
+            e1.cloned_with_type(true).id_of(TransportSequenceNumber),
+            Some(5)
+        );
+        assert_eq!(
+            e1.cloned_with_type(false).id_of(TransportSequenceNumber),
+            Some(6)
+        );
+
+        // Incoming packets are parsed with either id.
+        assert_eq!(e1.lookup(5), Some(&TransportSequenceNumber));
+        assert_eq!(e1.lookup(6), Some(&TransportSequenceNumber));
+
+        // A later audio m-line can't move it again.
+        let mut audio2 = ExtensionMap::empty();
+        audio2.set(7, TransportSequenceNumber);
+        let conflicts = e1.remap(&audio2.iter_audio().collect::<Vec<_>>(), true);
+        assert_eq!(
+            conflicts,
+            vec![RemapConflict::Locked {
+                extension: TransportSequenceNumber,
+                locked_id: 5,
+                requested_id: 7
+            }]
+        );
+    }
+
+    #[test]
+    fn remap_exts_id_in_use() {
+        use Extension::*;
+
+        let mut e1 = ExtensionMap::standard();
+
+        let mut audio = ExtensionMap::empty();
+        audio.set(1, AudioLevel);
+        e1.remap(&audio.iter_audio().collect::<Vec<_>>(), true);
+
+        // Video wants the id audio locked for AudioLevel.
+        let mut video = ExtensionMap::empty();
+        video.set(1, VideoOrientation);
+        let conflicts = e1.remap(&video.iter_video().collect::<Vec<_>>(), false);
+        assert_eq!(
+            conflicts,
+            vec![RemapConflict::IdInUse {
+                extension: VideoOrientation,
+                id: 1,
+                existing: AudioLevel
+            }]
+        );
+
+        assert_eq!(e1.lookup(1), Some(&AudioLevel));
+        assert_eq!(e1.id_of(VideoOrientation), Some(13));
+    }
 }
@@ -32,8 +32,55 @@ pub struct RtpHeader {
     pub header_len: usize,
 }
 
+/// Opt-in leniency when parsing incoming RTP.
+///
+/// Some devices, such as IP cameras, produce RTP that is technically invalid
+/// but still decodable. By default str0m drops such packets. Each flag relaxes
+/// one specific check. Violations are counted in [`RtpLeniencyCounters`] whether
+/// the packet is accepted or not.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RtpLeniency {
+    /// Accept packets where the padding length is larger than the payload.
+    ///
+    /// The padding is then ignored and the payload is delivered as is.
+    pub padding_violation: bool,
+
+    /// Accept packets where the header extension length runs past the end of the packet.
+    ///
+    /// The header extension block is truncated to what is available, which leaves
+    /// the packet without payload.
+    pub extension_overrun: bool,
+}
+
+/// Counts of RTP parsing violations.
+///
+/// See [`RtpLeniency`] for which violations can be tolerated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RtpLeniencyCounters {
+    /// Packets where the padding length was larger than the payload.
+    pub padding_violations: u64,
+
+    /// Packets where the header extension length ran past the end of the packet.
+    pub extension_overruns: u64,
+
+    /// Packets with SSRC 0.
+    ///
+    /// SSRC 0 is valid, if unusual, and these packets are always accepted.
+    pub zero_ssrc: u64,
+}
+
 impl RtpHeader {
-    pub(crate) fn write_to(&self, buf: &mut [u8], exts: &ExtensionMap) -> usize {
+    /// Write the header to `buf`.
+    ///
+    /// `allow_two_byte` tells if the two-byte header extension form is negotiated
+    /// (`a=extmap-allow-mixed`). If not, extensions that can't be written in the
+    /// one-byte form are skipped.
+    pub(crate) fn write_to(
+        &self,
+        buf: &mut [u8],
+        exts: &ExtensionMap,
+        allow_two_byte: bool,
+    ) -> usize {
         buf[0] = 0b10_0_0_0000
             | if self.has_padding { 1 << 5 } else { 0 }
             | if self.has_extension { 1 << 4 } else { 0 };
@@ -45,7 +92,11 @@ impl RtpHeader {
         buf[4..8].copy_from_slice(&self.timestamp.to_be_bytes());
         buf[8..12].copy_from_slice(&self.ssrc.to_be_bytes());
 
-        let exts_form = exts.form(&self.ext_vals);
+        let exts_form = if allow_two_byte {
+            exts.form(&self.ext_vals)
+        } else {
+            ExtensionsForm::OneByte
+        };
         buf[12..14].copy_from_slice(&exts_form.serialize());
 
         let ext_buf = &mut buf[16..];
@@ -131,6 +182,20 @@ impl RtpHeader {
     }
 
     pub(crate) fn parse(buf: &[u8], exts: &ExtensionMap) -> Option<RtpHeader> {
+        Self::parse_lenient(
+            buf,
+            exts,
+            &RtpLeniency::default(),
+            &mut RtpLeniencyCounters::default(),
+        )
+    }
+
+    pub(crate) fn parse_lenient(
+        buf: &[u8],
+        exts: &ExtensionMap,
+        leniency: &RtpLeniency,
+        counters: &mut RtpLeniencyCounters,
+    ) -> Option<RtpHeader> {
         let orig_len = buf.len();
         if buf.len() < 12 {
             trace!("RTP header too short < 12: {}", buf.len());
@@ -153,6 +218,11 @@ impl RtpHeader {
 
         let ssrc = u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]);
 
+        if ssrc == 0 {
+            trace!("RTP header with SSRC 0");
+            counters.zero_ssrc += 1;
+        }
+
         let buf: &[u8] = &buf[12..];
 
         let csrc_len = 4 * csrc_count;
@@ -193,10 +263,17 @@ impl RtpHeader {
 
             let buf: &[u8] = &buf[4..];
 
-            if buf.len() < ext_len {
-                trace!("RTP ext len larger than header {} > {}", buf.len(), ext_len);
-                return None;
-            }
+            let ext_len = if buf.len() < ext_len {
+                counters.extension_overruns += 1;
+                if !leniency.extension_overrun {
+                    trace!("RTP ext len larger than header {} > {}", buf.len(), ext_len);
+                    return None;
+                }
+                trace!("Truncating RTP ext len {} > {}", ext_len, buf.len());
+                buf.len()
+            } else {
+                ext_len
+            };
 
             exts.parse(&buf[..ext_len], exts_form, &mut ext);
             &buf[ext_len..]
@@ -455,7 +532,7 @@ mod test {
                 ..Default::default()
             };
             let mut buf = vec![0; DATAGRAM_MAX_PACKET_SIZE];
-            let n = header.write_to(&mut buf[..], exts);
+            let n = header.write_to(&mut buf[..], exts, true);
             buf.truncate(n);
 
             buf
@@ -500,7 +577,7 @@ mod test {
                 ..Default::default()
             };
             let mut buf = vec![0; DATAGRAM_MAX_PACKET_SIZE];
-            let n = header.write_to(&mut buf[..], exts);
+            let n = header.write_to(&mut buf[..], exts, true);
             buf.truncate(n);
 
             buf
@@ -529,6 +606,36 @@ mod test {
         assert_eq!(&buf3, p3);
     }
 
+    #[test]
+    fn test_write_rtp_headers_two_byte_form_not_allowed() {
+        let header = RtpHeader {
+            payload_type: 33.into(),
+            sequence_number: 47_000,
+            timestamp: 10_000,
+            ssrc: 44.into(),
+            ext_vals: ExtensionValues {
+                audio_level: Some(-42),
+                voice_activity: Some(false),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut exts = ExtensionMap::empty();
+        // Needs the two-byte form, which is not allowed.
+        exts.set(15, Extension::AudioLevel);
+
+        let mut buf = vec![0; DATAGRAM_MAX_PACKET_SIZE];
+        let n = header.write_to(&mut buf[..], &exts, false);
+        buf.truncate(n);
+
+        // One-byte form without any extension written.
+        assert_eq!(
+            &buf,
+            &[144, 33, 183, 152, 0, 0, 39, 16, 0, 0, 0, 44, 0xBE, 0xDE, 0, 0]
+        );
+    }
+
     #[test]
     fn test_parse_rtp_headers() {
         let exts = ExtensionMap::standard();
@@ -751,4 +858,49 @@ mod test {
         assert_eq!(Ok(vec![]), truncate(vec![1]));
         assert_eq!(Ok(vec![]), truncate(vec![]));
     }
+
+    #[test]
+    fn parse_lenient() {
+        let exts = ExtensionMap::empty();
+
+        #[rustfmt::skip]
+        let zero_ssrc = [
+            0x80, 0x60, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0x01, 0x02, 0x03, 0x04,
+        ];
+
+        // Extension claims 5 words, but only one is present.
+        #[rustfmt::skip]
+        let ext_overrun = [
+            0x90, 0x60, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01,
+            0x00, 0x00, 0x00, 0x01, 0xbe, 0xde, 0x00, 0x05,
+            0x00, 0x00, 0x00, 0x00,
+        ];
+
+        let strict = RtpLeniency::default();
+        let mut counters = RtpLeniencyCounters::default();
+
+        // SSRC 0 is accepted also when strict, and only counted.
+        let h = RtpHeader::parse_lenient(&zero_ssrc, &exts, &strict, &mut counters).unwrap();
+        assert_eq!(h.ssrc, 0.into());
+        assert_eq!(h.header_len, 12);
+        assert!(RtpHeader::parse(&zero_ssrc, &exts).is_some());
+
+        assert!(RtpHeader::parse_lenient(&ext_overrun, &exts, &strict, &mut counters).is_none());
+        assert_eq!(counters.zero_ssrc, 1);
+        assert_eq!(counters.extension_overruns, 1);
+
+        let lenient = RtpLeniency {
+            extension_overrun: true,
+            ..Default::default()
+        };
+
+        RtpHeader::parse_lenient(&zero_ssrc, &exts, &lenient, &mut counters).unwrap();
+
+        let h = RtpHeader::parse_lenient(&ext_overrun, &exts, &lenient, &mut counters).unwrap();
+        assert_eq!(h.header_len, ext_overrun.len());
+
+        assert_eq!(counters.zero_ssrc, 2);
+        assert_eq!(counters.extension_overruns, 2);
+    }
 }
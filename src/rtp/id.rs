@@ -55,6 +55,12 @@ macro_rules! str_id {
                     .collect::<String>();
 
                 let bytes = v.as_bytes();
+                if bytes.len() > $num {
+                    warn!(
+                        "{} longer than {} characters is truncated: {}",
+                        $name, $num, v
+                    );
+                }
                 let bytes = &bytes[0..$num.min(bytes.len())];
 
                 // pad with space.
@@ -125,9 +131,21 @@ str_id!(Mid, "Mid", 16, 3);
 ///
 /// In SDP this is an optional value that will be seen in [`MediaData`][crate::media::MediaData]
 /// if the remote peer is configured for simulcast.
+///
+/// Holds up to 32 characters, longer values are truncated with a warning. Values over
+/// 16 characters are sent using the two-byte RTP header extension form ([RFC 8285][1]),
+/// and are dropped with a warning if that form is not negotiated (extmap-allow-mixed).
+///
+/// [RFC 8852][2] allows up to 255 characters, but the rid is `Copy` and part of every
+/// [`ExtensionValues`][crate::rtp::ExtensionValues], so str0m caps it at 32. Browsers
+/// use rids of a few characters. A longer rid is truncated the same way in the SDP and in
+/// the RTP header extension, so the two still match.
+///
+/// [1]: https://www.rfc-editor.org/rfc/rfc8285
+/// [2]: https://www.rfc-editor.org/rfc/rfc8852
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
-pub struct Rid([u8; 8]);
-str_id!(Rid, "Rid", 8, 3);
+pub struct Rid([u8; 32]);
+str_id!(Rid, "Rid", 32, 3);
 
 /// Synchronization source.
 ///
@@ -6,6 +6,7 @@ mod id;
 pub use id::{Mid, Pt, Rid, SeqNo, SessionId, Ssrc};
 
 mod ext;
+pub(crate) use ext::RemapConflict;
 pub use ext::{Extension, ExtensionMap, ExtensionSerializer, ExtensionValues};
 pub use ext::{UserExtensionValues, VideoOrientation};
 
@@ -17,11 +18,12 @@ pub use mtime::Frequency;
 pub use mtime::MediaTime;
 
 mod header;
-pub use header::RtpHeader;
 pub(crate) use header::{extend_u15, extend_u16, extend_u32, extend_u7, extend_u8};
+pub use header::{RtpHeader, RtpLeniency, RtpLeniencyCounters};
 
 mod srtp;
-pub(crate) use srtp::SrtpContext;
+pub use srtp::{RocRecoveryCounters, SrtpErrorCounters};
+pub(crate) use srtp::{SrtpContext, UnprotectError};
 pub(crate) use srtp::{SRTCP_OVERHEAD, SRTP_BLOCK_SIZE, SRTP_OVERHEAD};
 
 mod rtcp;
@@ -36,13 +38,21 @@ use crate::crypto::CryptoError;
 pub const MAX_BLANK_PADDING_PAYLOAD_SIZE: usize = 240;
 
 /// Errors that can arise in RTP.
+///
+/// Some variants depend on the enabled crypto features.
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum RtpError {
     /// Some error from OpenSSL layer (used for SRTP).
     #[error("{0}")]
     #[cfg(feature = "openssl")]
     OpenSsl(#[from] openssl::error::ErrorStack),
 
+    /// Some error from BoringSSL layer (used for SRTP).
+    #[error("{0}")]
+    #[cfg(feature = "boringssl")]
+    BoringSsl(#[from] boring::error::ErrorStack),
+
     /// Other IO errors.
     #[error("{0}")]
     Io(#[from] io::Error),
@@ -57,7 +67,15 @@ impl From<CryptoError> for RtpError {
         match value {
             #[cfg(feature = "openssl")]
             CryptoError::OpenSsl(e) => RtpError::OpenSsl(e),
+            #[cfg(feature = "boringssl")]
+            CryptoError::BoringSsl(e) => RtpError::BoringSsl(e),
             CryptoError::Io(e) => RtpError::Io(e),
+            e @ (CryptoError::CertificateKeyMismatch
+            | CryptoError::FipsNotEnabled
+            | CryptoError::NoCipherSuites
+            | CryptoError::NoGroups) => {
+                RtpError::Io(io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))
+            }
         }
     }
 }
@@ -61,6 +61,11 @@ pub enum TransportType {
     /// Definition: <https://www.rfc-editor.org/rfc/rfc4585#section-6.2.1>
     Nack = 1,
 
+    /// Temporary Maximum Media Stream Bit Rate Request.
+    ///
+    /// Definition: <https://www.rfc-editor.org/rfc/rfc5104#section-4.2.1>
+    Tmmbr = 3,
+
     /// Transportwide congestion control packet.
     ///
     /// Definition: <https://tools.ietf.org/html/draft-holmer-rmcat-transport-wide-cc-extensions-01>
@@ -74,6 +79,7 @@ impl TryFrom<u8> for TransportType {
         use TransportType::*;
         match v {
             1 => Ok(Nack),
+            3 => Ok(Tmmbr),
             15 => Ok(TransportWide),
             _ => {
                 trace!("Uknown TransportType: {}", v);
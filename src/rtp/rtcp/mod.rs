@@ -45,6 +45,9 @@ pub use rtcpfb::RtcpFb;
 mod remb;
 pub use remb::Remb;
 
+mod tmmbr;
+pub use tmmbr::{Tmmbr, TmmbrEntry};
+
 use super::extend_u16;
 use super::SeqNo;
 use super::Ssrc;
@@ -88,9 +91,64 @@ pub enum Rtcp {
     Twcc(Twcc),
     /// Receiver Estimated Maximum Bitrate. Feedback to the sender about the maximum bitrate.
     Remb(Remb),
+    /// Temporary Maximum Media Stream Bit Rate Request. Asks the sender to cap the bitrate.
+    Tmmbr(Tmmbr),
+}
+
+/// Summary of one packet in a compound RTCP packet.
+///
+/// Part of [`RawPacket::RtcpCompoundTx`][crate::rtp::RawPacket::RtcpCompoundTx] and
+/// [`RawPacket::RtcpCompoundRx`][crate::rtp::RawPacket::RtcpCompoundRx].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RtcpPacketSummary {
+    /// The RTCP packet type (PT), such as 200 for SR or 205 for transport layer feedback.
+    pub packet_type: u8,
+    /// The count or feedback message type (FMT) field.
+    pub fmt: u8,
+    /// Length in bytes, including header and padding.
+    pub length: usize,
+    /// Whether str0m parsed this packet into an [`Rtcp`].
+    pub parsed: bool,
 }
 
 impl Rtcp {
+    /// Summarize each packet in a (compound) RTCP buffer.
+    ///
+    /// Unlike [`Rtcp::read_packet`], this also covers packet types str0m doesn't handle.
+    pub(crate) fn summarize(buf: &[u8]) -> Vec<RtcpPacketSummary> {
+        let mut summary = vec![];
+        let mut buf = buf;
+
+        while buf.len() >= 4 {
+            let length_words = u16::from_be_bytes([buf[2], buf[3]]) as usize + 1;
+            let length = length_words * 4;
+
+            if length > buf.len() {
+                break;
+            }
+
+            let has_padding = buf[0] & 0b00_1_00000 > 0;
+            let pad = if has_padding {
+                buf[length - 1] as usize
+            } else {
+                0
+            };
+
+            let parsed = length >= pad && Rtcp::try_from(&buf[..length - pad]).is_ok();
+
+            summary.push(RtcpPacketSummary {
+                packet_type: buf[1],
+                fmt: buf[0] & 0b00_0_11111,
+                length,
+                parsed,
+            });
+
+            buf = &buf[length..];
+        }
+
+        summary
+    }
+
     pub(crate) fn read_packet(buf: &[u8], feedback: &mut VecDeque<Rtcp>) {
         let mut buf = buf;
         loop {
@@ -133,9 +191,15 @@ impl Rtcp {
         }
     }
 
+    /// Write queued feedback to `buf`.
+    ///
+    /// `compound` is the sender SSRC to use if the packet must be a compound RTCP
+    /// packet, i.e. when reduced-size RTCP (RFC 5506) is not negotiated. If the
+    /// packet doesn't start with a SR/RR, an empty RR is prepended.
     pub(crate) fn write_packet(
         feedback: &mut VecDeque<Rtcp>,
         buf: &mut [u8],
+        compound: Option<Ssrc>,
         mut output: impl FnMut(Rtcp),
     ) -> usize {
         if feedback.is_empty() {
@@ -145,13 +209,36 @@ impl Rtcp {
         // Total length, in bytes, shrunk to be on the pad_to boundary.
         let total_len = buf.len();
 
+        // An empty RR is the header plus the sender SSRC.
+        const EMPTY_RR_WORDS: usize = 2;
+
         // Capacity in words
-        let word_capacity = total_len / 4;
+        let word_capacity = if compound.is_some() {
+            total_len / 4 - EMPTY_RR_WORDS
+        } else {
+            total_len / 4
+        };
 
         // Pack RTCP feedback packets. Merge together ones of the same type.
         Rtcp::pack(feedback, word_capacity);
 
         let mut offset = 0;
+
+        if let Some(sender_ssrc) = compound {
+            let starts_with_report = matches!(
+                feedback.front(),
+                Some(Rtcp::SenderReport(_)) | Some(Rtcp::ReceiverReport(_))
+            );
+
+            if !starts_with_report {
+                let rr = Rtcp::ReceiverReport(ReceiverReport {
+                    sender_ssrc,
+                    reports: ReportList::new(),
+                });
+                offset += rr.write_to(buf);
+                output(rr);
+            }
+        }
         while let Some(fb) = feedback.front() {
             // Length of next item.
             let item_len = fb.length_words() * 4;
@@ -236,6 +323,7 @@ impl Rtcp {
             Rtcp::Fir(v) => v.reports.is_full(),
             Rtcp::Twcc(_) => true,
             Rtcp::Remb(_) => true,
+            Rtcp::Tmmbr(_) => true,
         }
     }
 
@@ -263,6 +351,8 @@ impl Rtcp {
             Rtcp::Twcc(_) => false,
             // A REMB report is never empty.
             Rtcp::Remb(_) => false,
+            // A TMMBR is never empty.
+            Rtcp::Tmmbr(_) => false,
         }
     }
 
@@ -361,6 +451,7 @@ impl RtcpPacket for Rtcp {
             Rtcp::Fir(v) => v.header(),
             Rtcp::Twcc(v) => v.header(),
             Rtcp::Remb(v) => v.header(),
+            Rtcp::Tmmbr(v) => v.header(),
         }
     }
 
@@ -376,6 +467,7 @@ impl RtcpPacket for Rtcp {
             Rtcp::Fir(v) => v.length_words(),
             Rtcp::Twcc(v) => v.length_words(),
             Rtcp::Remb(v) => v.length_words(),
+            Rtcp::Tmmbr(v) => v.length_words(),
         }
     }
 
@@ -391,6 +483,7 @@ impl RtcpPacket for Rtcp {
             Rtcp::Fir(v) => v.write_to(buf),
             Rtcp::Twcc(v) => v.write_to(buf),
             Rtcp::Remb(v) => v.write_to(buf),
+            Rtcp::Tmmbr(v) => v.write_to(buf),
         }
     }
 }
@@ -420,6 +513,7 @@ impl<'a> TryFrom<&'a [u8]> for Rtcp {
 
                 match tlfb {
                     TransportType::Nack => Rtcp::Nack(buf.try_into()?),
+                    TransportType::Tmmbr => Rtcp::Tmmbr(buf.try_into()?),
                     TransportType::TransportWide => Rtcp::Twcc(buf.try_into()?),
                 }
             }
@@ -496,7 +590,7 @@ mod test {
         twcc.delta.push_back(Delta::Small(0x84));
         queue.push_back(Rtcp::Twcc(twcc));
         let mut buf = vec![0; 1500];
-        let n = Rtcp::write_packet(&mut queue, &mut buf, |_| {});
+        let n = Rtcp::write_packet(&mut queue, &mut buf, None, |_| {});
         buf.truncate(n);
         println!("{buf:02x?}");
         assert_eq!(
@@ -578,7 +672,7 @@ mod test {
         feedback.push_back(rr(5));
 
         let mut buf = vec![0_u8; 1360];
-        let n = Rtcp::write_packet(&mut feedback, &mut buf, |_| {});
+        let n = Rtcp::write_packet(&mut feedback, &mut buf, None, |_| {});
         buf.truncate(n);
 
         let mut parsed = VecDeque::new();
@@ -603,6 +697,45 @@ mod test {
         assert!(abs < Duration::from_millis(1));
     }
 
+    #[test]
+    fn compound_prepends_empty_rr() {
+        let mut feedback = VecDeque::new();
+        feedback.push_back(Rtcp::Pli(Pli {
+            sender_ssrc: 1.into(),
+            ssrc: 2.into(),
+        }));
+
+        let mut buf = vec![0_u8; 1360];
+        let n = Rtcp::write_packet(&mut feedback, &mut buf, Some(1.into()), |_| {});
+        buf.truncate(n);
+
+        let mut parsed = VecDeque::new();
+        Rtcp::read_packet(&buf, &mut parsed);
+
+        assert_eq!(parsed.len(), 2);
+        assert!(matches!(&parsed[0], Rtcp::ReceiverReport(r) if r.reports.is_empty()));
+        assert!(matches!(&parsed[1], Rtcp::Pli(_)));
+    }
+
+    #[test]
+    fn reduced_size_is_not_compound() {
+        let mut feedback = VecDeque::new();
+        feedback.push_back(Rtcp::Pli(Pli {
+            sender_ssrc: 1.into(),
+            ssrc: 2.into(),
+        }));
+
+        let mut buf = vec![0_u8; 1360];
+        let n = Rtcp::write_packet(&mut feedback, &mut buf, None, |_| {});
+        buf.truncate(n);
+
+        let mut parsed = VecDeque::new();
+        Rtcp::read_packet(&buf, &mut parsed);
+
+        assert_eq!(parsed.len(), 1);
+        assert!(matches!(&parsed[0], Rtcp::Pli(_)));
+    }
+
     fn sr(ssrc: u32, ntp_time: Instant) -> Rtcp {
         Rtcp::SenderReport(SenderReport {
             sender_info: SenderInfo {
@@ -702,4 +835,29 @@ mod test {
             Rtcp::read_packet(t, &mut parsed);
         }
     }
+
+    #[test]
+    fn summarize_compound() {
+        let mut queue = VecDeque::new();
+        queue.push_back(rr(1));
+        queue.push_back(Rtcp::Pli(Pli {
+            sender_ssrc: 1.into(),
+            ssrc: 2.into(),
+        }));
+
+        let mut buf = vec![0; 1500];
+        let n = Rtcp::write_packet(&mut queue, &mut buf, None, |_| {});
+        buf.truncate(n);
+
+        // An APP packet that str0m doesn't handle.
+        buf.extend_from_slice(&[0x80, 204, 0, 2, 0, 0, 0, 1, b'n', b'a', b'm', b'e']);
+
+        let summary = Rtcp::summarize(&buf);
+
+        assert_eq!(summary.len(), 3);
+        assert_eq!(summary.iter().map(|s| s.length).sum::<usize>(), buf.len());
+        assert_eq!(summary[2].packet_type, 204);
+        assert!(!summary[2].parsed);
+        assert!(summary[..2].iter().all(|s| s.parsed));
+    }
 }
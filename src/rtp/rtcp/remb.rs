@@ -28,6 +28,7 @@ const UNIQUE_IDENTIFIER: [u8; 4] = [b'R', b'E', b'M', b'B'];
     |  ...                                                          |
 */
 
+/// Receiver Estimated Maximum Bitrate.
 #[derive(Debug, Clone)]
 pub struct Remb {
     /// SSRC of sender
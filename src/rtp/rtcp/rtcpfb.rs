@@ -72,6 +72,8 @@ impl RtcpFb {
                 Rtcp::Remb(v) => {
                     q.push(RtcpFb::Remb(v));
                 }
+                // Surfaced directly by the session, there is no per stream handling.
+                Rtcp::Tmmbr(_) => {}
             }
         }
         q.into_iter()
@@ -0,0 +1,169 @@
+use super::{FeedbackMessageType, RtcpHeader, RtcpPacket};
+use super::{RtcpType, Ssrc, TransportType};
+
+/*
+    https://www.rfc-editor.org/rfc/rfc5104#section-4.2.1.1
+
+    0                   1                   2                   3
+    0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+    +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    |                              SSRC                             |
+    +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+    | MxTBR Exp |  MxTBR Mantissa                 |Measured Overhead|
+    +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+*/
+
+const MANTISSA_MAX: u64 = (1 << 17) - 1;
+
+/// Temporary Maximum Media Stream Bit Rate Request.
+///
+/// Asks the sender of the media to cap the bitrate of the streams.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tmmbr {
+    /// Sender of this feedback. Mostly irrelevant, but part of RTCP packets.
+    pub sender_ssrc: Ssrc,
+    /// The requested caps, one per media SSRC.
+    pub entries: Vec<TmmbrEntry>,
+}
+
+/// One requested cap in a [`Tmmbr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TmmbrEntry {
+    /// The SSRC of the media the cap is for.
+    pub ssrc: Ssrc,
+    /// Maximum total media bitrate in bits per second.
+    pub bitrate: u64,
+    /// Measured per packet overhead in bytes.
+    pub overhead: u16,
+}
+
+impl RtcpPacket for Tmmbr {
+    fn header(&self) -> RtcpHeader {
+        RtcpHeader {
+            rtcp_type: RtcpType::TransportLayerFeedback,
+            feedback_message_type: FeedbackMessageType::TransportFeedback(TransportType::Tmmbr),
+            words_less_one: (self.length_words() - 1) as u16,
+        }
+    }
+
+    fn length_words(&self) -> usize {
+        // header
+        // sender SSRC
+        // media SSRC (always 0)
+        // 2 words per entry
+        3 + self.entries.len() * 2
+    }
+
+    fn write_to(&self, buf: &mut [u8]) -> usize {
+        self.header().write_to(&mut buf[..4]);
+        buf[4..8].copy_from_slice(&self.sender_ssrc.to_be_bytes());
+        buf[8..12].copy_from_slice(&[0; 4]);
+
+        let mut buf = &mut buf[12..];
+        for e in &self.entries {
+            let mut exp = 0_u32;
+            let mut mantissa = e.bitrate;
+            while mantissa > MANTISSA_MAX {
+                mantissa >>= 1;
+                exp += 1;
+            }
+            let word = (exp.min(63) << 26) | ((mantissa as u32) << 9) | (e.overhead as u32 & 0x1ff);
+
+            buf[..4].copy_from_slice(&e.ssrc.to_be_bytes());
+            buf[4..8].copy_from_slice(&word.to_be_bytes());
+            buf = &mut buf[8..];
+        }
+
+        12 + self.entries.len() * 8
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Tmmbr {
+    type Error = &'static str;
+
+    fn try_from(buf: &'a [u8]) -> Result<Self, Self::Error> {
+        if buf.len() < 8 {
+            return Err("Tmmbr less than 8 bytes");
+        }
+
+        let sender_ssrc = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]).into();
+
+        let mut entries = vec![];
+        let mut buf = &buf[8..];
+        while buf.len() >= 8 {
+            let ssrc = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]).into();
+            let word = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
+
+            let exp = word >> 26;
+            let mantissa = ((word >> 9) & MANTISSA_MAX as u32) as u64;
+            let overhead = (word & 0x1ff) as u16;
+
+            // The exponent is at most 63, but the shifted mantissa can still overflow.
+            let bitrate = mantissa.checked_mul(1 << exp).unwrap_or(u64::MAX);
+
+            entries.push(TmmbrEntry {
+                ssrc,
+                bitrate,
+                overhead,
+            });
+
+            buf = &buf[8..];
+        }
+
+        Ok(Tmmbr {
+            sender_ssrc,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn tmmbr_roundtrip() {
+        let tmmbr = Tmmbr {
+            sender_ssrc: 1.into(),
+            entries: vec![
+                TmmbrEntry {
+                    ssrc: 2.into(),
+                    bitrate: 64_000,
+                    overhead: 40,
+                },
+                TmmbrEntry {
+                    ssrc: 3.into(),
+                    // Needs an exponent, and loses precision.
+                    bitrate: 1_500_001,
+                    overhead: 0,
+                },
+            ],
+        };
+
+        let mut buf = vec![0; 100];
+        let n = tmmbr.write_to(&mut buf);
+        assert_eq!(n, tmmbr.length_words() * 4);
+
+        let parsed = Tmmbr::try_from(&buf[4..n]).unwrap();
+        assert_eq!(parsed.sender_ssrc, 1.into());
+        assert_eq!(parsed.entries[0], tmmbr.entries[0]);
+        assert_eq!(parsed.entries[1].ssrc, 3.into());
+        assert_eq!(parsed.entries[1].bitrate, 1_500_000);
+    }
+
+    #[test]
+    fn tmmbr_bitrate_saturates() {
+        let mut buf = vec![0; 16];
+        // Max exponent and mantissa.
+        buf[12..16].copy_from_slice(&(u32::MAX & !0x1ff).to_be_bytes());
+
+        let parsed = Tmmbr::try_from(&buf[..]).unwrap();
+        assert_eq!(parsed.entries[0].bitrate, u64::MAX);
+
+        // Exponent 47 and mantissa 1 fit.
+        buf[12..16].copy_from_slice(&((47_u32 << 26) | (1 << 9)).to_be_bytes());
+
+        let parsed = Tmmbr::try_from(&buf[..]).unwrap();
+        assert_eq!(parsed.entries[0].bitrate, 1 << 47);
+    }
+}
@@ -6,6 +6,7 @@ use std::time::{Duration, Instant};
 
 use super::{extend_u16, FeedbackMessageType, RtcpHeader, RtcpPacket};
 use super::{RtcpType, SeqNo, Ssrc, TransportType};
+use crate::rtp_::DataSize;
 
 /// Transport Wide Congestion Control.
 ///
@@ -998,6 +999,9 @@ pub struct TwccSendRegister {
 
     /// Last registered Twcc number.
     last_registered: SeqNo,
+
+    /// RTT of the most recent packet the remote reported as received.
+    last_rtt: Option<Duration>,
 }
 
 impl<'a> IntoIterator for &'a TwccSendRegister {
@@ -1067,6 +1071,7 @@ impl TwccSendRegister {
             queue: VecDeque::new(),
             time_zero: None,
             last_registered: 0.into(),
+            last_rtt: None,
         }
     }
 
@@ -1131,8 +1136,13 @@ impl TwccSendRegister {
 
         let mut problematic_seq = None;
 
+        // Lost packets have no RTT, only the ones the remote received.
+        let mut last_rtt = None;
+
         if !update(now, first_record, first_seq_no, first_instant) {
             problematic_seq = Some((first_record.seq, first_seq_no));
+        } else if first_instant.is_some() {
+            last_rtt = first_record.rtt();
         }
 
         let mut last_seq_no = first_seq_no;
@@ -1143,10 +1153,16 @@ impl TwccSendRegister {
 
             if !update(now, record, seq, instant) {
                 problematic_seq = Some((record.seq, seq));
+            } else if instant.is_some() {
+                last_rtt = record.rtt();
             }
             last_seq_no = seq;
         }
 
+        if last_rtt.is_some() {
+            self.last_rtt = last_rtt;
+        }
+
         if let Some((record_seq, report_seq)) = problematic_seq {
             let queue_tail: Vec<_> = self.queue.iter().rev().take(100).collect();
             panic!(
@@ -1159,6 +1175,27 @@ impl TwccSendRegister {
         Some(first_seq_no..=last_seq_no)
     }
 
+    /// Bytes sent after the last packet covered by TWCC feedback.
+    ///
+    /// Packets the feedback reports as lost are not in flight. Neither are those sent
+    /// before a reported packet, but missing from the feedback, since they are lost too.
+    pub fn in_flight(&self) -> DataSize {
+        let bytes = self
+            .queue
+            .iter()
+            .rev()
+            .take_while(|r| r.recv_report.is_none())
+            .map(|r| r.size as u64)
+            .sum();
+
+        DataSize::bytes(bytes)
+    }
+
+    /// The RTT of the most recent packet the remote reported as received.
+    pub fn last_rtt(&self) -> Option<Duration> {
+        self.last_rtt
+    }
+
     pub fn send_record(&self, seq: SeqNo) -> Option<&TwccSendRecord> {
         let index = self.queue.binary_search_by_key(&seq, |r| r.seq).ok()?;
 
@@ -2015,6 +2052,93 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_twcc_send_register_in_flight() {
+        let mut reg = TwccSendRegister::new(25);
+        let start = Instant::now();
+        for i in 0..4 {
+            reg.register_seq(i.into(), start, 100);
+        }
+
+        assert_eq!(reg.in_flight(), DataSize::bytes(400));
+        assert_eq!(reg.last_rtt(), None);
+
+        let now = start + Duration::from_millis(50);
+        reg.apply_report(
+            Twcc {
+                sender_ssrc: Ssrc::new(),
+                ssrc: Ssrc::new(),
+                base_seq: 0,
+                status_count: 2,
+                reference_time: 0,
+                feedback_count: 0,
+                chunks: [PacketChunk::Run(PacketStatus::ReceivedSmallDelta, 2)].into(),
+                delta: [Delta::Small(10), Delta::Small(10)].into(),
+            },
+            now,
+        )
+        .expect("apply_report to return Some(_)");
+
+        assert_eq!(reg.in_flight(), DataSize::bytes(200));
+        assert_eq!(reg.last_rtt(), Some(Duration::from_millis(50)));
+    }
+
+    #[test]
+    fn test_twcc_send_register_in_flight_lost() {
+        let mut reg = TwccSendRegister::new(25);
+        let start = Instant::now();
+        for i in 0..6 {
+            reg.register_seq(i.into(), start + Duration::from_millis(i * 10), 100);
+        }
+
+        // 0 and 3 received, 1, 2 and 4 lost. 5 not covered by the feedback.
+        let now = start + Duration::from_millis(100);
+        reg.apply_report(
+            Twcc {
+                sender_ssrc: Ssrc::new(),
+                ssrc: Ssrc::new(),
+                base_seq: 0,
+                status_count: 5,
+                reference_time: 0,
+                feedback_count: 0,
+                chunks: [
+                    PacketChunk::Run(PacketStatus::ReceivedSmallDelta, 1),
+                    PacketChunk::Run(PacketStatus::NotReceived, 2),
+                    PacketChunk::Run(PacketStatus::ReceivedSmallDelta, 1),
+                    PacketChunk::Run(PacketStatus::NotReceived, 1),
+                ]
+                .into(),
+                delta: [Delta::Small(10), Delta::Small(120)].into(),
+            },
+            now,
+        )
+        .expect("apply_report to return Some(_)");
+
+        assert_eq!(reg.in_flight(), DataSize::bytes(100));
+        // The RTT of 3, not of the lost 4.
+        assert_eq!(reg.last_rtt(), Some(Duration::from_millis(70)));
+
+        // A later report with only lost packets keeps the last RTT.
+        let now = now + Duration::from_millis(100);
+        reg.apply_report(
+            Twcc {
+                sender_ssrc: Ssrc::new(),
+                ssrc: Ssrc::new(),
+                base_seq: 5,
+                status_count: 1,
+                reference_time: 0,
+                feedback_count: 1,
+                chunks: [PacketChunk::Run(PacketStatus::NotReceived, 1)].into(),
+                delta: [].into(),
+            },
+            now,
+        )
+        .expect("apply_report to return Some(_)");
+
+        assert_eq!(reg.in_flight(), DataSize::bytes(0));
+        assert_eq!(reg.last_rtt(), Some(Duration::from_millis(70)));
+    }
+
     #[test]
     fn test_twcc_recv_register_loss() {
         let mut reg = TwccRecvRegister::new(25);
@@ -1,7 +1,7 @@
 use std::fmt;
 
-use crate::crypto::{self, new_aead_aes_128_gcm, new_aes_128_cm_sha1_80, KeyingMaterial};
 use crate::crypto::{aead_aes_128_gcm, aes_128_cm_sha1_80, SrtpProfile};
+use crate::crypto::{DefaultSrtpCrypto, KeyingMaterial, SrtpCrypto};
 
 use super::header::RtpHeader;
 
@@ -28,9 +28,68 @@ const MAX_TAG_LEN: usize = aead_aes_128_gcm::TAG_LEN;
 pub const SRTCP_OVERHEAD: usize = MAX_TAG_LEN + SRTCP_INDEX_LEN;
 pub const SRTP_OVERHEAD: usize = MAX_TAG_LEN;
 
+/// Counts of SRTP rollover counter (ROC) recovery.
+///
+/// See [`RtcConfig::set_roc_recovery()`][crate::RtcConfig::set_roc_recovery].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RocRecoveryCounters {
+    /// Packets that failed SRTP authentication, but were recovered with an adjacent ROC.
+    pub recovered: u64,
+
+    /// Packets that failed SRTP authentication also with the adjacent ROC values.
+    pub failed: u64,
+}
+
+/// Counts of incoming SRTP/SRTCP packets that were dropped or suspicious, by cause.
+///
+/// Reported in [`PeerStats::srtp_errors`][crate::stats::PeerStats::srtp_errors].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SrtpErrorCounters {
+    /// RTP packets with an SSRC that doesn't map to any stream. These are dropped
+    /// before decryption.
+    pub unknown_ssrc: u64,
+
+    /// Packets too short to hold the authentication tag.
+    pub too_short: u64,
+
+    /// Packets that failed authentication, while other packets authenticated with
+    /// the same keys. This points to corrupted or forged packets.
+    pub auth_failed: u64,
+
+    /// Packets that failed authentication before any packet authenticated with the
+    /// current keys. This points to a key mismatch with the remote peer.
+    pub wrong_key: u64,
+
+    /// Packets that authenticated, but could not be decrypted.
+    pub decrypt_failed: u64,
+
+    /// Packets that authenticated, but repeat an already received RTP sequence number
+    /// or SRTCP index. This happens for network duplicates as well as replay attacks.
+    pub replayed: u64,
+}
+
+/// Why [`SrtpContext`] could not unprotect a packet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum UnprotectError {
+    TooShort,
+    Auth,
+    Decrypt,
+}
+
 impl SrtpContext {
     /// Create an SRTP context for the relevant profile using the provided keying material.
+    #[cfg(test)]
     pub fn new(profile: SrtpProfile, mat: &KeyingMaterial, left: bool) -> Self {
+        Self::with_crypto(&DefaultSrtpCrypto, profile, mat, left)
+    }
+
+    /// Create an SRTP context using the ciphers of a specific [`SrtpCrypto`].
+    pub fn with_crypto(
+        crypto: &dyn SrtpCrypto,
+        profile: SrtpProfile,
+        mat: &KeyingMaterial,
+        left: bool,
+    ) -> Self {
         match profile {
             #[cfg(feature = "_internal_test_exports")]
             SrtpProfile::PassThrough => SrtpContext {
@@ -43,7 +102,7 @@ impl SrtpContext {
 
                 let key = SrtpKey::<KEY_LEN, SALT_LEN>::new(mat, left);
 
-                let (rtp, rtcp) = Derived::aes_128_cm_sha1_80(&key);
+                let (rtp, rtcp) = Derived::aes_128_cm_sha1_80(crypto, &key);
 
                 SrtpContext {
                     rtp,
@@ -56,7 +115,7 @@ impl SrtpContext {
 
                 let key = SrtpKey::<KEY_LEN, SALT_LEN>::new(mat, left);
 
-                let (rtp, rtcp) = Derived::aead_aes_128_gcm(&key);
+                let (rtp, rtcp) = Derived::aead_aes_128_gcm(crypto, &key);
 
                 SrtpContext {
                     rtp,
@@ -78,13 +137,13 @@ impl SrtpContext {
         Self {
             rtp: Derived::AeadAes128Gcm {
                 salt: rtp_salt,
-                enc: new_aead_aes_128_gcm(rtp_key, true),
-                dec: new_aead_aes_128_gcm(rtp_key, false),
+                enc: DefaultSrtpCrypto.new_aead_aes_128_gcm(rtp_key, true),
+                dec: DefaultSrtpCrypto.new_aead_aes_128_gcm(rtp_key, false),
             },
             rtcp: Derived::AeadAes128Gcm {
                 salt: rtcp_salt,
-                enc: new_aead_aes_128_gcm(rtcp_key, true),
-                dec: new_aead_aes_128_gcm(rtcp_key, false),
+                enc: DefaultSrtpCrypto.new_aead_aes_128_gcm(rtcp_key, true),
+                dec: DefaultSrtpCrypto.new_aead_aes_128_gcm(rtcp_key, false),
             },
             srtcp_index,
         }
@@ -186,14 +245,23 @@ impl SrtpContext {
         header: &RtpHeader,
         srtp_index: u64, // same as ext_seq
     ) -> Option<Vec<u8>> {
+        self.try_unprotect_rtp(buf, header, srtp_index).ok()
+    }
+
+    pub(crate) fn try_unprotect_rtp(
+        &mut self,
+        buf: &[u8],
+        header: &RtpHeader,
+        srtp_index: u64,
+    ) -> Result<Vec<u8>, UnprotectError> {
         match &mut self.rtp {
             #[cfg(feature = "_internal_test_exports")]
-            Derived::PassThrough => Some(buf.to_vec()),
+            Derived::PassThrough => Ok(buf.to_vec()),
             Derived::Aes128CmSha1_80 { key, salt, dec, .. } => {
                 use aes_128_cm_sha1_80::HMAC_TAG_LEN;
 
                 if buf.len() < HMAC_TAG_LEN {
-                    return None;
+                    return Err(UnprotectError::TooShort);
                 }
 
                 let hmac_start = buf.len() - HMAC_TAG_LEN;
@@ -205,7 +273,7 @@ impl SrtpContext {
                     &buf[hmac_start..],
                 ) {
                     trace!("unprotect_rtp hmac verify fail");
-                    return None;
+                    return Err(UnprotectError::Auth);
                 }
 
                 let iv = aes_128_cm_sha1_80::rtp_iv(*salt, *header.ssrc, srtp_index);
@@ -215,16 +283,16 @@ impl SrtpContext {
 
                 if let Err(e) = dec.decrypt(&iv, input, &mut output) {
                     warn!("Failed to decrypt SRTP ({}): {:?}", self.rtp.profile(), e);
-                    return None;
+                    return Err(UnprotectError::Decrypt);
                 };
 
-                Some(output)
+                Ok(output)
             }
             Derived::AeadAes128Gcm { salt, dec, .. } => {
                 use aead_aes_128_gcm::TAG_LEN;
 
-                if buf.len() < TAG_LEN {
-                    return None;
+                if buf.len() < header.header_len + TAG_LEN {
+                    return Err(UnprotectError::TooShort);
                 }
 
                 let roc: u32 = (srtp_index >> 16) as u32;
@@ -236,17 +304,53 @@ impl SrtpContext {
                 // Input and output lengths for decryption: https://www.rfc-editor.org/rfc/rfc7714#section-5.2.2
                 let mut output = vec![0; input.len() - TAG_LEN];
 
+                // AEAD decryption fails when the tag doesn't verify.
                 match dec.decrypt(&iv, &[aad], input, &mut output) {
                     Ok(v) => v,
                     Err(e) => {
-                        warn!("Failed to decrypt SRTP ({}): {:?}", self.rtp.profile(), e);
-                        return None;
+                        debug!("Failed to decrypt SRTP ({}): {:?}", self.rtp.profile(), e);
+                        return Err(UnprotectError::Auth);
                     }
                 };
 
-                Some(output)
+                Ok(output)
+            }
+        }
+    }
+
+    /// Unprotect RTP by trying rollover counters (ROC) adjacent to the one in `srtp_index`.
+    ///
+    /// After long gaps, the sender might have wrapped the sequence number more times
+    /// than we can infer. Up to `max_attempts` ROC values are tried on either side,
+    /// closest first. Returns the payload and the SRTP index that authenticated.
+    pub fn unprotect_rtp_recover_roc(
+        &mut self,
+        buf: &[u8],
+        header: &RtpHeader,
+        srtp_index: u64,
+        max_attempts: usize,
+    ) -> Option<(Vec<u8>, u64)> {
+        let roc = srtp_index >> 16;
+        let seq = srtp_index & 0xffff;
+
+        for d in 1..=max_attempts as u64 {
+            let candidates = [roc.checked_add(d), roc.checked_sub(d)];
+
+            for r in candidates.into_iter().flatten() {
+                // ROC is a 32 bit value.
+                if r > u32::MAX as u64 {
+                    continue;
+                }
+
+                let index = (r << 16) | seq;
+
+                if let Some(v) = self.unprotect_rtp(buf, header, index) {
+                    return Some((v, index));
+                }
             }
         }
+
+        None
     }
 
     pub fn protect_rtcp(&mut self, buf: &[u8]) -> Vec<u8> {
@@ -324,21 +428,25 @@ impl SrtpContext {
     //                  |--------------------------------------|
     //                              encrypted (aes)
     pub fn unprotect_rtcp(&mut self, buf: &[u8]) -> Option<Vec<u8>> {
+        self.try_unprotect_rtcp(buf).ok()
+    }
+
+    pub(crate) fn try_unprotect_rtcp(&mut self, buf: &[u8]) -> Result<Vec<u8>, UnprotectError> {
         match &mut self.rtcp {
             #[cfg(feature = "_internal_test_exports")]
-            Derived::PassThrough => Some(buf.to_vec()),
+            Derived::PassThrough => Ok(buf.to_vec()),
             Derived::Aes128CmSha1_80 { key, salt, dec, .. } => {
                 use aes_128_cm_sha1_80::HMAC_TAG_LEN;
 
                 if buf.len() < HMAC_TAG_LEN + SRTCP_INDEX_LEN {
-                    return None;
+                    return Err(UnprotectError::TooShort);
                 }
 
                 let hmac_start = buf.len() - HMAC_TAG_LEN;
 
                 if !aes_128_cm_sha1_80::rtcp_verify(key, &buf[..hmac_start], &buf[hmac_start..]) {
                     trace!("unprotect_rtcp hmac verify fail");
-                    return None;
+                    return Err(UnprotectError::Auth);
                 }
 
                 let idx_start = hmac_start - SRTCP_INDEX_LEN;
@@ -357,7 +465,7 @@ impl SrtpContext {
 
                 if !is_encrypted {
                     // Non-encrypted we can just return
-                    return Some(buf[0..idx_start].to_vec());
+                    return Ok(buf[0..idx_start].to_vec());
                 }
 
                 // The SRTCP index is a 31-bit counter for the SRTCP packet.
@@ -376,17 +484,17 @@ impl SrtpContext {
 
                 if let Err(e) = dec.decrypt(&iv, input, &mut output[8..]) {
                     warn!("Failed to decrypt SRTCP ({}): {:?}", self.rtcp.profile(), e);
-                    return None;
+                    return Err(UnprotectError::Decrypt);
                 }
 
-                Some(output)
+                Ok(output)
             }
             Derived::AeadAes128Gcm { salt, dec, .. } => {
                 use aead_aes_128_gcm::{RTCP_AAD_LEN, TAG_LEN};
 
                 if buf.len() < SRTCP_INDEX_LEN + TAG_LEN {
                     // Too short
-                    return None;
+                    return Err(UnprotectError::TooShort);
                 }
 
                 let idx_start = buf.len() - SRTCP_INDEX_LEN;
@@ -436,11 +544,12 @@ impl SrtpContext {
                 let mut output = vec![0_u8; buf.len() - TAG_LEN - SRTCP_INDEX_LEN];
                 output[0..8].copy_from_slice(&buf[0..8]);
 
+                // AEAD decryption fails when the tag doesn't verify.
                 let count = match dec.decrypt(&iv, &aads, input, &mut output[8..]) {
                     Ok(c) => c,
                     Err(e) => {
-                        warn!("Failed to decrypt SRTCP ({}): {:?}", self.rtcp.profile(), e);
-                        return None;
+                        debug!("Failed to decrypt SRTCP ({}): {:?}", self.rtcp.profile(), e);
+                        return Err(UnprotectError::Auth);
                     }
                 };
 
@@ -451,10 +560,26 @@ impl SrtpContext {
                     output.copy_from_slice(&buf[0..buf.len() - SRTCP_INDEX_LEN - TAG_LEN])
                 }
 
-                Some(output)
+                Ok(output)
             }
         }
     }
+
+    /// The SRTCP index of an incoming packet that already passed [`Self::try_unprotect_rtcp`].
+    pub(crate) fn srtcp_index(&self, buf: &[u8]) -> Option<u32> {
+        let idx_start = match &self.rtcp {
+            #[cfg(feature = "_internal_test_exports")]
+            Derived::PassThrough => return None,
+            Derived::Aes128CmSha1_80 { .. } => buf
+                .len()
+                .checked_sub(aes_128_cm_sha1_80::HMAC_TAG_LEN + SRTCP_INDEX_LEN)?,
+            Derived::AeadAes128Gcm { .. } => buf.len().checked_sub(SRTCP_INDEX_LEN)?,
+        };
+
+        let e_and_si = u32::from_be_bytes(buf[idx_start..idx_start + 4].try_into().ok()?);
+
+        Some(e_and_si & 0x7fff_ffff)
+    }
 }
 
 /// SrtpKeys created from DTLS SrtpKeyMaterial.
@@ -486,7 +611,7 @@ impl<const ML: usize, const SL: usize> SrtpKey<ML, SL> {
         SrtpKey { master, salt }
     }
 
-    fn derive(&self, label: u8, out: &mut [u8]) {
+    fn derive(&self, crypto: &dyn SrtpCrypto, label: u8, out: &mut [u8]) {
         // AEC-CM (128 bits) defined in RFC3711
         assert!(ML == 16, "Only valid for 128 bit master keys");
         assert!(SL <= 14, "Only valid for 128 bit master keys");
@@ -511,7 +636,7 @@ impl<const ML: usize, const SL: usize> SrtpKey<ML, SL> {
             input[14..].copy_from_slice(&round.to_be_bytes()[..]);
 
             // default key derivation function, which uses AES-128 in Counter Mode
-            crypto::srtp_aes_128_ecb_round(&self.master, &input[..], &mut buf[..]);
+            crypto.srtp_aes_128_ecb_round(&self.master, &input[..], &mut buf[..]);
 
             // Copy to output. Even if we get 32 bytes of output with AES 128 ECB, we
             // only use the first 16. That matches the tests in the RFC.
@@ -547,88 +672,90 @@ enum Derived {
 
 impl Derived {
     fn aes_128_cm_sha1_80(
+        crypto: &dyn SrtpCrypto,
         srtp_key: &SrtpKey<{ aes_128_cm_sha1_80::KEY_LEN }, { aes_128_cm_sha1_80::SALT_LEN }>,
     ) -> (Self, Self) {
         use aes_128_cm_sha1_80::*;
 
         // RTP AES Counter
         let mut rtp_aes = [0; KEY_LEN];
-        srtp_key.derive(LABEL_RTP_AES, &mut rtp_aes[..]);
+        srtp_key.derive(crypto, LABEL_RTP_AES, &mut rtp_aes[..]);
 
         // RTP SHA1 HMAC
         let rtp_hmac = {
             let mut hmac = [0; HMAC_KEY_LEN];
-            srtp_key.derive(LABEL_RTP_AUTHENTICATION_KEY, &mut hmac[..]);
+            srtp_key.derive(crypto, LABEL_RTP_AUTHENTICATION_KEY, &mut hmac[..]);
             hmac
         };
 
         // RTP IV SALT
         let mut rtp_salt = [0; SALT_LEN];
-        srtp_key.derive(LABEL_RTP_SALT, &mut rtp_salt[..]);
+        srtp_key.derive(crypto, LABEL_RTP_SALT, &mut rtp_salt[..]);
 
         // RTCP AES Counter
         let mut rtcp_aes = [0; KEY_LEN];
-        srtp_key.derive(LABEL_RTCP_AES, &mut rtcp_aes[..]);
+        srtp_key.derive(crypto, LABEL_RTCP_AES, &mut rtcp_aes[..]);
 
         // RTCP SHA1 HMAC
         let rtcp_hmac = {
             let mut hmac = [0; HMAC_KEY_LEN];
-            srtp_key.derive(LABEL_RTCP_AUTHENTICATION_KEY, &mut hmac[..]);
+            srtp_key.derive(crypto, LABEL_RTCP_AUTHENTICATION_KEY, &mut hmac[..]);
             hmac
         };
 
         // RTCP IV SALT
         let mut rtcp_salt = [0; SALT_LEN];
-        srtp_key.derive(LABEL_RTCP_SALT, &mut rtcp_salt[..]);
+        srtp_key.derive(crypto, LABEL_RTCP_SALT, &mut rtcp_salt[..]);
 
         let rtp = Derived::Aes128CmSha1_80 {
             key: rtp_hmac,
             salt: rtp_salt,
-            enc: new_aes_128_cm_sha1_80(rtp_aes, true),
-            dec: new_aes_128_cm_sha1_80(rtp_aes, false),
+            enc: crypto.new_aes_128_cm_sha1_80(rtp_aes, true),
+            dec: crypto.new_aes_128_cm_sha1_80(rtp_aes, false),
         };
 
         let rtcp = Derived::Aes128CmSha1_80 {
             key: rtcp_hmac,
             salt: rtcp_salt,
-            enc: new_aes_128_cm_sha1_80(rtcp_aes, true),
-            dec: new_aes_128_cm_sha1_80(rtcp_aes, false),
+            enc: crypto.new_aes_128_cm_sha1_80(rtcp_aes, true),
+            dec: crypto.new_aes_128_cm_sha1_80(rtcp_aes, false),
         };
 
         (rtp, rtcp)
     }
 
     fn aead_aes_128_gcm(
+        crypto: &dyn SrtpCrypto,
         srtp_key: &SrtpKey<{ aead_aes_128_gcm::KEY_LEN }, { aead_aes_128_gcm::SALT_LEN }>,
     ) -> (Derived, Derived) {
         use aead_aes_128_gcm::*;
 
         // RTP session key
         let mut rtp_aes = [0; KEY_LEN];
-        srtp_key.derive(LABEL_RTP_AES, &mut rtp_aes[..]);
+        srtp_key.derive(crypto, LABEL_RTP_AES, &mut rtp_aes[..]);
 
         // RTP session salt
         let mut rtp_salt = [0; SALT_LEN];
-        srtp_key.derive(LABEL_RTP_SALT, &mut rtp_salt[..]);
+        srtp_key.derive(crypto, LABEL_RTP_SALT, &mut rtp_salt[..]);
 
         // RTCP session key
         let mut rtcp_aes = [0; KEY_LEN];
-        srtp_key.derive(LABEL_RTCP_AES, &mut rtcp_aes[..]);
+        srtp_key.derive(crypto, LABEL_RTCP_AES, &mut rtcp_aes[..]);
 
         // RTCP session salt
         let mut rtcp_salt = [0; SALT_LEN];
-        srtp_key.derive(LABEL_RTCP_SALT, &mut rtcp_salt[..]);
+        srtp_key.derive(crypto, LABEL_RTCP_SALT, &mut rtcp_salt[..]);
 
         let rtp = Derived::AeadAes128Gcm {
             salt: rtp_salt,
-            enc: new_aead_aes_128_gcm(rtp_aes, true),
-            dec: new_aead_aes_128_gcm(rtp_aes, false),
+            enc: crypto.new_aead_aes_128_gcm(rtp_aes, true),
+            dec: crypto.new_aead_aes_128_gcm(rtp_aes, false),
         };
 
         let rtcp = Derived::AeadAes128Gcm {
             salt: rtcp_salt,
-            enc: new_aead_aes_128_gcm(rtcp_aes, true),
-            dec: new_aead_aes_128_gcm(rtcp_aes, false),
+            enc: crypto.new_aead_aes_128_gcm(rtcp_aes, true),
+            dec: crypto.new_aead_aes_128_gcm(rtcp_aes, false),
         };
 
         (rtp, rtcp)
@@ -674,7 +801,7 @@ mod test {
 
         // aes crypto key
         let mut out = [0_u8; 16];
-        sk.derive(0, &mut out[..]);
+        sk.derive(&DefaultSrtpCrypto, 0, &mut out[..]);
 
         assert_eq!(
             out,
@@ -686,7 +813,7 @@ mod test {
 
         // hmac
         let mut out = [0_u8; 20];
-        sk.derive(1, &mut out[..]);
+        sk.derive(&DefaultSrtpCrypto, 1, &mut out[..]);
 
         assert_eq!(
             out,
@@ -699,7 +826,7 @@ mod test {
 
         // salt
         let mut out = [0_u8; 14];
-        sk.derive(2, &mut out[..]);
+        sk.derive(&DefaultSrtpCrypto, 2, &mut out[..]);
 
         assert_eq!(
             out,
@@ -759,6 +886,50 @@ mod test {
             let encrypted = ctx_rx.protect_rtcp(&decrypted);
             assert_eq!(encrypted, SRTCP);
         }
+
+        #[test]
+        fn unprotect_rtcp_custom_crypto() {
+            use std::sync::atomic::{AtomicUsize, Ordering};
+
+            #[derive(Debug, Default)]
+            struct Counting(AtomicUsize);
+
+            impl SrtpCrypto for Counting {
+                fn new_aes_128_cm_sha1_80(
+                    &self,
+                    key: AesKey,
+                    encrypt: bool,
+                ) -> Box<dyn aes_128_cm_sha1_80::CipherCtx> {
+                    self.0.fetch_add(1, Ordering::Relaxed);
+                    DefaultSrtpCrypto.new_aes_128_cm_sha1_80(key, encrypt)
+                }
+
+                fn new_aead_aes_128_gcm(
+                    &self,
+                    key: aead_aes_128_gcm::AeadKey,
+                    encrypt: bool,
+                ) -> Box<dyn aead_aes_128_gcm::CipherCtx> {
+                    DefaultSrtpCrypto.new_aead_aes_128_gcm(key, encrypt)
+                }
+
+                fn srtp_aes_128_ecb_round(&self, key: &[u8], input: &[u8], output: &mut [u8]) {
+                    DefaultSrtpCrypto.srtp_aes_128_ecb_round(key, input, output)
+                }
+            }
+
+            let crypto = Counting::default();
+            let key_mat = KeyingMaterial::new(MAT.to_vec());
+            let mut ctx_rx =
+                SrtpContext::with_crypto(&crypto, SrtpProfile::Aes128CmSha1_80, &key_mat, true);
+            ctx_rx.srtcp_index = 1;
+
+            // Encrypt and decrypt for both RTP and RTCP.
+            assert_eq!(crypto.0.load(Ordering::Relaxed), 4);
+
+            let decrypted = ctx_rx.unprotect_rtcp(SRTCP).unwrap();
+            let encrypted = ctx_rx.protect_rtcp(&decrypted);
+            assert_eq!(encrypted, SRTCP);
+        }
     }
 
     mod test_aead_aes_128_gcm {
@@ -912,6 +1083,26 @@ mod test {
             assert!(result.is_none(), "Should fail to decrypt a SRTP packet that has mismatched authenicated additional data");
         }
 
+        #[test]
+        fn unprotect_rtp_error_kinds() {
+            let mut context = make_rtp_context();
+
+            let header =
+                RtpHeader::parse(&rfc7714::PROTECTED_RTP_PACKET[..12], &ExtensionMap::empty())
+                    .expect("header to parse");
+
+            // Header and less than a tag.
+            let short = &rfc7714::PROTECTED_RTP_PACKET[..12 + TAG_LEN - 1];
+            let result = context.try_unprotect_rtp(short, &header, 0);
+            assert_eq!(result, Err(UnprotectError::TooShort));
+
+            let mut broken = rfc7714::PROTECTED_RTP_PACKET.to_vec();
+            let len = broken.len();
+            broken[len - 1] ^= 0xFF;
+            let result = context.try_unprotect_rtp(&broken, &header, 0);
+            assert_eq!(result, Err(UnprotectError::Auth));
+        }
+
         #[test]
         fn unprotect_rtp_should_fail_with_broken_null_tag() {
             let mut context = make_rtp_context();
@@ -979,4 +1170,47 @@ mod test {
             )
         }
     }
+
+    mod test_roc {
+        use crate::rtp_::ExtensionMap;
+
+        use super::*;
+
+        fn context() -> SrtpContext {
+            let mat: Vec<u8> = (0..60).collect();
+            SrtpContext::new(
+                SrtpProfile::Aes128CmSha1_80,
+                &KeyingMaterial::new(mat),
+                true,
+            )
+        }
+
+        #[test]
+        fn recover_adjacent_roc() {
+            let mut tx = context();
+            let mut rx = context();
+
+            let mut packet = vec![0x80, 0x60, 0x00, 0x05, 0, 0, 0, 1, 0, 0, 0, 42];
+            packet.extend_from_slice(&[7; 16]);
+            let header = RtpHeader::parse(&packet[..12], &ExtensionMap::empty()).unwrap();
+
+            // Sender is two ROC ahead of what the receiver infers.
+            let actual = (3 << 16) | 5;
+            let inferred = (1 << 16) | 5;
+
+            let protected = tx.protect_rtp(&packet, &header, actual);
+            assert!(rx.unprotect_rtp(&protected, &header, inferred).is_none());
+
+            // One attempt is not enough.
+            assert!(rx
+                .unprotect_rtp_recover_roc(&protected, &header, inferred, 1)
+                .is_none());
+
+            let (payload, index) = rx
+                .unprotect_rtp_recover_roc(&protected, &header, inferred, 2)
+                .unwrap();
+            assert_eq!(payload, &packet[12..]);
+            assert_eq!(index, actual);
+        }
+    }
 }
@@ -1,6 +1,7 @@
 use std::collections::VecDeque;
 
 use super::{ExtensionSerializer, ExtensionValues};
+use crate::rtp_::Bitrate;
 
 #[allow(dead_code)]
 /// URI for the Video Layers Allocation RTP Header Extension
@@ -67,6 +68,30 @@ pub struct ResolutionAndFramerate {
 }
 
 impl VideoLayersAllocation {
+    /// The bitrate needed to receive each simulcast stream at its best quality.
+    ///
+    /// The index corresponds to the simulcast stream index, which is the order of the
+    /// rids negotiated for simulcast. Within a simulcast stream, the spatial layers
+    /// depend on each other, so the top temporal layer of each active spatial layer
+    /// is summed up. Inactive simulcast streams are 0.
+    ///
+    /// Useful as a hint for an application's bitrate allocator, to know which layers
+    /// the remote sender actually produces.
+    pub fn simulcast_stream_bitrates(&self) -> Vec<Bitrate> {
+        self.simulcast_streams
+            .iter()
+            .map(|s| {
+                let kbps: u64 = s
+                    .spatial_layers
+                    .iter()
+                    .filter_map(|l| l.temporal_layers.last())
+                    .map(|t| t.cumulative_kbps)
+                    .sum();
+                Bitrate::kbps(kbps)
+            })
+            .collect()
+    }
+
     #[allow(dead_code)]
     fn parse(buf: &[u8]) -> Option<Self> {
         // First byte
@@ -777,4 +802,38 @@ mod test {
             })
         );
     }
+
+    #[test]
+    fn test_simulcast_stream_bitrates() {
+        let layer = |kbps: &[u64]| SpatialLayerAllocation {
+            temporal_layers: kbps
+                .iter()
+                .map(|k| TemporalLayerAllocation {
+                    cumulative_kbps: *k,
+                })
+                .collect(),
+            resolution_and_framerate: None,
+        };
+
+        let vla = VideoLayersAllocation {
+            current_simulcast_stream_index: 1,
+            simulcast_streams: vec![
+                SimulcastStreamAllocation {
+                    spatial_layers: vec![layer(&[50, 100])],
+                },
+                SimulcastStreamAllocation {
+                    // Spatial layers add up, inactive ones don't count.
+                    spatial_layers: vec![layer(&[150, 300]), layer(&[]), layer(&[500, 900])],
+                },
+                SimulcastStreamAllocation {
+                    spatial_layers: vec![],
+                },
+            ],
+        };
+
+        assert_eq!(
+            vla.simulcast_stream_bitrates(),
+            vec![Bitrate::kbps(100), Bitrate::kbps(1200), Bitrate::kbps(0)]
+        );
+    }
 }
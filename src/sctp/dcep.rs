@@ -120,3 +120,62 @@ impl DcepAck {
         1
     }
 }
+
+// 0                   1                   2                   3
+// 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  Message Type |   Reserved    |          Close Code           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// \                                                               /
+// |                            Reason                             |
+// /                                                               \
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+
+/// Final message on a channel carrying the close reason.
+///
+/// This is not part of RFC 8832, but a convention between str0m peers. Other
+/// implementations discard the unknown message type, or close the channel, which
+/// is fine since it is closing anyway.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DcepClose {
+    pub code: u16,
+    pub reason: String,
+}
+
+/// Message type for [`DcepClose`], from the range unassigned by RFC 8832.
+const DCEP_CLOSE: u8 = 0x04;
+
+/// Same as the WebSocket close reason.
+pub const DCEP_CLOSE_MAX_REASON: usize = 123;
+
+impl TryFrom<&[u8]> for DcepClose {
+    type Error = Error;
+
+    fn try_from(buf: &[u8]) -> Result<Self, Self::Error> {
+        if buf.len() < 4 {
+            return Err(Error::DcepOpenTooSmall);
+        }
+
+        if buf[0] != DCEP_CLOSE {
+            return Err(Error::DcepIncorrectMessageType);
+        }
+
+        let code = u16::from_be_bytes([buf[2], buf[3]]);
+        let reason = from_utf8(&buf[4..])
+            .map_err(|_| Error::DcepBadUtf8)?
+            .to_string();
+
+        Ok(DcepClose { code, reason })
+    }
+}
+
+impl DcepClose {
+    pub fn marshal_to(&self, buf: &mut [u8]) -> usize {
+        buf[0] = DCEP_CLOSE;
+        buf[1] = 0;
+        buf[2..4].copy_from_slice(&self.code.to_be_bytes());
+        let bytes_reason = self.reason.as_bytes();
+        buf[4..4 + bytes_reason.len()].copy_from_slice(bytes_reason);
+        4 + bytes_reason.len()
+    }
+}
@@ -5,11 +5,11 @@ use std::fmt;
 use std::net::SocketAddr;
 use std::panic::UnwindSafe;
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use sctp_proto::{Association, AssociationHandle, ClientConfig, DatagramEvent};
+use sctp_proto::{Association, AssociationError, AssociationHandle, ClientConfig, DatagramEvent};
 use sctp_proto::{Endpoint, EndpointConfig, Stream, StreamEvent, Transmit};
-use sctp_proto::{Event, Payload, PayloadProtocolIdentifier, ServerConfig};
+use sctp_proto::{Event, Payload, PayloadProtocolIdentifier, ServerConfig, TransportConfig};
 use thiserror::Error;
 
 pub use sctp_proto::Error as ProtoError;
@@ -20,6 +20,32 @@ use dcep::DcepOpen;
 
 use dcep::DcepAck;
 
+use dcep::{DcepClose, DCEP_CLOSE_MAX_REASON};
+
+/// The sctp-port we put in the local SDP.
+pub(crate) const SCTP_PORT: u16 = 5000;
+
+/// The default max-message-size we put in the local SDP.
+pub(crate) const DEFAULT_MAX_MESSAGE_SIZE: usize = 262144;
+
+/// The default SCTP receive window in sctp-proto. Raised when the max-message-size is larger,
+/// since a message must fit the window to be reassembled.
+const DEFAULT_RECEIVE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Bytes the channels can have buffered in the association when there is more than one
+/// open channel. Further messages are held back, so that later messages on other channels
+/// don't queue up behind them. The window is shared by priority, see [`held_back`].
+const SEND_WINDOW: usize = 64 * 1024;
+
+/// Bytes a channel can have held back for priority. Writes beyond this are refused.
+const MAX_HELD_BYTES: usize = 1024 * 1024;
+
+/// Time we wait for a DATA_CHANNEL_ACK before giving up on the channel.
+///
+/// The DATA_CHANNEL_OPEN goes on a reliable stream, so SCTP does the retransmits and we
+/// never resend it ourselves. This only covers a peer that gets the open but never answers.
+const DCEP_ACK_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// Errors from the SCTP subsystem.
 #[derive(Debug, Error, Eq, Clone, PartialEq)]
 pub enum SctpError {
@@ -40,6 +66,76 @@ pub enum SctpError {
     /// The initial DCEP cant be read as utf-8.
     #[error("DCEP bad UTF-8 string")]
     DcepBadUtf8,
+
+    /// The message is larger than the max-message-size of the receiving side.
+    #[error("Message of {size} bytes exceeds max-message-size {max}")]
+    MessageTooLarge {
+        /// Size of the message.
+        size: usize,
+        /// The max-message-size.
+        max: usize,
+    },
+
+    /// Too much data is held back on the channel, waiting for its share of the send window.
+    ///
+    /// The message was not written. Try again once the buffered amount drops.
+    #[error("Too much data held back for other channels")]
+    WouldBlock,
+}
+
+/// Why the SCTP association carrying the data channels ended.
+///
+/// This is obtained via [`Event::SctpAssociationClosed`][crate::Event::SctpAssociationClosed].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SctpCloseReason {
+    /// The remote peer aborted the association.
+    Aborted,
+    /// The remote peer reset the association.
+    Reset,
+    /// The remote peer stopped answering and the retransmissions ran out.
+    TimedOut,
+    /// The association failed due to a protocol violation, such as a failed handshake.
+    Protocol(ProtoError),
+    /// Some other reason.
+    Other(String),
+}
+
+/// Application provided reason for closing a data channel.
+///
+/// Sent as a final message on the channel before it closes, and obtained on the remote
+/// side via [`Event::ChannelCloseReason`][crate::Event::ChannelCloseReason]. This is a
+/// str0m convention. Other WebRTC implementations ignore it and only see the channel close.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ChannelCloseReason {
+    /// Application defined code. `0` is used for a graceful close.
+    pub code: u16,
+    /// Human readable reason. Truncated to 123 bytes when sent.
+    pub reason: String,
+}
+
+impl From<AssociationError> for SctpCloseReason {
+    fn from(v: AssociationError) -> Self {
+        match v {
+            AssociationError::AssociationClosed => SctpCloseReason::Aborted,
+            AssociationError::Reset => SctpCloseReason::Reset,
+            AssociationError::TimedOut => SctpCloseReason::TimedOut,
+            AssociationError::HandshakeFailed(e) => SctpCloseReason::Protocol(e),
+            e => SctpCloseReason::Other(e.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for SctpCloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SctpCloseReason::Aborted => write!(f, "aborted by peer"),
+            SctpCloseReason::Reset => write!(f, "reset by peer"),
+            SctpCloseReason::TimedOut => write!(f, "timed out"),
+            SctpCloseReason::Protocol(e) => write!(f, "protocol error: {}", e),
+            SctpCloseReason::Other(s) => write!(f, "{}", s),
+        }
+    }
 }
 
 pub(crate) struct RtcSctp {
@@ -52,6 +148,15 @@ pub(crate) struct RtcSctp {
     pushed_back_transmit: Option<VecDeque<Vec<u8>>>,
     last_now: Instant,
     client: bool,
+    /// Stream ids of closed channels, reset but still known to the association.
+    /// These can't be reused for new channels yet.
+    resetting: Vec<u16>,
+    /// Largest incoming message we accept.
+    max_message_size: usize,
+    transport: Arc<TransportConfig>,
+    transport_counters: TransportCounters,
+    /// Held back messages that failed when finally written.
+    write_failures: VecDeque<(u16, SctpError)>,
 }
 
 /// This is okay because there is no way for a user of Rtc to interact with the Sctp subsystem
@@ -64,6 +169,8 @@ enum RtcSctpState {
     AwaitRemoteAssociation,
     AwaitAssociationEstablished,
     Established,
+    /// The association is gone, and will not come back.
+    Lost,
 }
 
 impl RtcSctpState {
@@ -86,6 +193,84 @@ struct StreamEntry {
     id: u16,
     /// If we are to close this entry.
     do_close: bool,
+    /// When to give up on the channel if the DATA_CHANNEL_OPEN is still not acked.
+    dcep_timeout_at: Option<Instant>,
+    /// Message and byte counts for stats.
+    counters: StreamCounters,
+    /// Close reason received from the remote peer, not yet polled.
+    remote_close_reason: Option<ChannelCloseReason>,
+    /// Reliability set on the stream for per-message overrides, if it differs from
+    /// the config. Reverted once the stream has nothing buffered.
+    reliability_override: Option<Reliability>,
+    /// Threshold for the buffered amount low event.
+    buffered_amount_low_threshold: usize,
+    /// If the threshold is not yet set on the sctp stream.
+    need_threshold: bool,
+    /// Messages held back to leave room for other channels.
+    held: VecDeque<HeldMessage>,
+    /// Sum of the held message sizes.
+    held_bytes: usize,
+}
+
+struct HeldMessage {
+    data: Vec<u8>,
+    ppi: PayloadProtocolIdentifier,
+    reliability: Option<Reliability>,
+}
+
+/// Counts of SCTP packets and chunks of the association.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct TransportCounters {
+    pub packets_sent: u64,
+    pub packets_received: u64,
+    pub data_chunks_sent: u64,
+    pub data_chunks_retransmitted: u64,
+    /// Highest TSN sent in a DATA chunk, to tell retransmissions apart.
+    highest_tsn: Option<u32>,
+}
+
+impl TransportCounters {
+    fn count_sent(&mut self, packet: &[u8]) {
+        self.packets_sent += 1;
+
+        // Chunks follow the 12 byte common header (RFC 4960 section 3).
+        let mut chunks = packet.get(12..).unwrap_or_default();
+
+        while chunks.len() >= 4 {
+            let typ = chunks[0];
+            let len = u16::from_be_bytes([chunks[2], chunks[3]]) as usize;
+            if len < 4 || len > chunks.len() {
+                break;
+            }
+
+            // DATA chunk, with the TSN following the chunk header.
+            if typ == 0 && len >= 8 {
+                let tsn = u32::from_be_bytes([chunks[4], chunks[5], chunks[6], chunks[7]]);
+                self.data_chunks_sent += 1;
+
+                match self.highest_tsn {
+                    // Serial number arithmetic (RFC 1982), since the TSN wraps.
+                    Some(h) if (tsn.wrapping_sub(h) as i32) <= 0 => {
+                        self.data_chunks_retransmitted += 1;
+                    }
+                    _ => self.highest_tsn = Some(tsn),
+                }
+            }
+
+            // Chunks are padded to 4 bytes.
+            let padded = (len + 3) & !3;
+            chunks = chunks.get(padded..).unwrap_or_default();
+        }
+    }
+}
+
+/// Counts of data channel messages, excluding DCEP.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct StreamCounters {
+    pub messages_sent: u64,
+    pub bytes_sent: u64,
+    pub messages_received: u64,
+    pub bytes_received: u64,
 }
 
 pub(crate) enum SctpEvent {
@@ -99,11 +284,25 @@ pub(crate) enum SctpEvent {
     Close {
         id: u16,
     },
+    CloseReason {
+        id: u16,
+        reason: ChannelCloseReason,
+    },
+    BufferedAmountLow {
+        id: u16,
+    },
     Data {
         id: u16,
         binary: bool,
         data: Vec<u8>,
     },
+    AssociationLost {
+        reason: SctpCloseReason,
+    },
+    WriteFailed {
+        id: u16,
+        error: SctpError,
+    },
 }
 
 /// These are the possible paths:
@@ -144,6 +343,86 @@ pub struct ChannelConfig {
     ///
     /// Defaults to ""
     pub protocol: String,
+    /// Priority when sending, relative to other channels.
+    ///
+    /// Sent to the remote peer in the DCEP open message (RFC 8832).
+    pub priority: ChannelPriority,
+}
+
+/// Priority of a data channel.
+///
+/// Channels with data to send share the send buffer in proportion to their DCEP priority
+/// (RFC 8831 section 6.4), so a bulk transfer doesn't delay messages on a control channel.
+/// A channel with nothing buffered can always send, which means a busy channel of higher
+/// priority never starves the others.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChannelPriority {
+    /// Priority 128 in DCEP.
+    VeryLow,
+    /// Priority 256 in DCEP. This is the default, like for `RTCDataChannel`.
+    #[default]
+    Low,
+    /// Priority 512 in DCEP.
+    Medium,
+    /// Priority 1024 in DCEP.
+    High,
+}
+
+impl ChannelPriority {
+    fn weight(self) -> usize {
+        self.to_dcep() as usize
+    }
+
+    fn to_dcep(self) -> u16 {
+        match self {
+            ChannelPriority::VeryLow => 128,
+            ChannelPriority::Low => 256,
+            ChannelPriority::Medium => 512,
+            ChannelPriority::High => 1024,
+        }
+    }
+
+    fn from_dcep(v: u16) -> Self {
+        match v {
+            // Unset by some implementations.
+            0 => ChannelPriority::default(),
+            1..=128 => ChannelPriority::VeryLow,
+            129..=256 => ChannelPriority::Low,
+            257..=512 => ChannelPriority::Medium,
+            _ => ChannelPriority::High,
+        }
+    }
+}
+
+/// Retransmission timeout (RTO) bounds of the SCTP association (RFC 4960 section 6.3).
+///
+/// The RTO is derived from the measured round trip time, clamped to `min` and `max`, and
+/// doubles on each retransmission of the same data. Set via
+/// [`RtcConfig::set_sctp_rto()`][crate::RtcConfig::set_sctp_rto()].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SctpRto {
+    /// RTO before any round trip time is measured.
+    ///
+    /// Defaults to 3 seconds.
+    pub initial: Duration,
+    /// Lower bound of the RTO.
+    ///
+    /// Defaults to 1 second.
+    pub min: Duration,
+    /// Upper bound of the RTO, also for the backed off retransmissions.
+    ///
+    /// Defaults to 60 seconds.
+    pub max: Duration,
+}
+
+impl Default for SctpRto {
+    fn default() -> Self {
+        SctpRto {
+            initial: Duration::from_secs(3),
+            min: Duration::from_secs(1),
+            max: Duration::from_secs(60),
+        }
+    }
 }
 
 /// Reliability setting of a data channel.
@@ -164,6 +443,26 @@ pub enum Reliability {
     },
 }
 
+impl Reliability {
+    /// The most reliable of two settings.
+    ///
+    /// Settings of different kinds can't be compared and combine to [`Reliability::Reliable`].
+    pub(crate) fn strongest(self, other: Reliability) -> Reliability {
+        use Reliability::*;
+        match (self, other) {
+            (MaxPacketLifetime { lifetime: a }, MaxPacketLifetime { lifetime: b }) => {
+                MaxPacketLifetime { lifetime: a.max(b) }
+            }
+            (MaxRetransmits { retransmits: a }, MaxRetransmits { retransmits: b }) => {
+                MaxRetransmits {
+                    retransmits: a.max(b),
+                }
+            }
+            _ => Reliable,
+        }
+    }
+}
+
 impl StreamEntry {
     fn set_state(&mut self, state: StreamEntryState) -> bool {
         if self.state == state {
@@ -174,6 +473,21 @@ impl StreamEntry {
         true
     }
 
+    fn write_dcep_open(&mut self, stream: &mut Stream, now: Instant) {
+        let config = self.config.as_ref().expect("config for DcepOpen");
+        let dcep: DcepOpen = config.into();
+        let mut buf = vec![0; 1500];
+        let n = dcep.marshal_to(&mut buf);
+        buf.truncate(n);
+
+        let l = stream
+            .write_with_ppi(&buf, PayloadProtocolIdentifier::Dcep)
+            .expect("writing dcep open");
+        assert!(n == l);
+
+        self.dcep_timeout_at = Some(now + DCEP_ACK_TIMEOUT);
+    }
+
     #[must_use]
     fn configure_reliability(&mut self, stream: &mut Stream) -> bool {
         let dcep: DcepOpen = self.config.as_ref().expect("config to be set").into();
@@ -195,16 +509,91 @@ impl StreamEntry {
 
         true
     }
+
+    fn priority(&self) -> ChannelPriority {
+        self.config.as_ref().map(|c| c.priority).unwrap_or_default()
+    }
+
+    fn write_now(
+        &mut self,
+        assoc: &mut Association,
+        buf: &[u8],
+        ppi: PayloadProtocolIdentifier,
+        reliability: Option<Reliability>,
+    ) -> Result<usize, SctpError> {
+        let mut stream = assoc.stream(self.id)?;
+
+        if reliability.is_some() || self.reliability_override.is_some() {
+            let base = self
+                .config
+                .as_ref()
+                .expect("config for open stream")
+                .reliability;
+            let wanted = reliability.unwrap_or(base);
+            self.apply_reliability(&mut stream, wanted)?;
+        }
+
+        let n = stream.write_with_ppi(buf, ppi)?;
+
+        self.counters.messages_sent += 1;
+        self.counters.bytes_sent += n as u64;
+
+        Ok(n)
+    }
+
+    /// Set the reliability used by the stream for the next message.
+    ///
+    /// sctp-proto decides on abandoning chunks using the current stream setting, so the
+    /// setting also applies to messages still buffered. To never make a buffered message
+    /// less reliable than asked for, the strongest setting is used until the stream drains.
+    fn apply_reliability(
+        &mut self,
+        stream: &mut Stream,
+        wanted: Reliability,
+    ) -> Result<(), SctpError> {
+        let config = self.config.as_ref().expect("config to be set");
+        let current = self.reliability_override.unwrap_or(config.reliability);
+
+        let buffered = stream.buffered_amount()? > 0;
+        let next = if buffered {
+            current.strongest(wanted)
+        } else {
+            wanted
+        };
+
+        if next != current {
+            let (channel_type, param) = (&next).into();
+            stream.set_reliability_params(!config.ordered, channel_type, param)?;
+        }
+
+        self.reliability_override = (next != config.reliability).then_some(next);
+
+        Ok(())
+    }
 }
 
 impl RtcSctp {
-    pub fn new() -> Self {
+    pub fn new(max_message_size: usize, rto: SctpRto) -> Self {
         let mut config = EndpointConfig::default();
         // Default here is 1200, I've seen warnings that are 77 over.
         // DTLS above MTU 1200: 1277
         // Let's try 1120, see if we can avoid warnings.
         config.max_payload_size(1120);
-        let server_config = ServerConfig::default();
+
+        let receive_buffer = max_message_size.max(DEFAULT_RECEIVE_BUFFER_SIZE);
+        let transport = Arc::new(
+            TransportConfig::default()
+                .with_max_receive_buffer_size(receive_buffer.min(u32::MAX as usize) as u32)
+                // The size of outgoing messages is checked against the remote
+                // max-message-size before writing.
+                .with_max_message_size(u32::MAX)
+                .with_rto_initial_ms(rto.initial.as_millis() as u64)
+                .with_rto_min_ms(rto.min.as_millis() as u64)
+                .with_rto_max_ms(rto.max.as_millis() as u64),
+        );
+
+        let mut server_config = ServerConfig::default();
+        server_config.transport = transport.clone();
         let endpoint = Endpoint::new(Arc::new(config), Some(Arc::new(server_config)));
         let fake_addr = "1.1.1.1:5000".parse().unwrap();
 
@@ -218,6 +607,11 @@ impl RtcSctp {
             pushed_back_transmit: None,
             last_now: Instant::now(), // placeholder until init()
             client: false,
+            resetting: vec![],
+            max_message_size,
+            transport,
+            transport_counters: TransportCounters::default(),
+            write_failures: VecDeque::new(),
         }
     }
 
@@ -225,6 +619,10 @@ impl RtcSctp {
         self.state != RtcSctpState::Uninited
     }
 
+    pub fn is_established(&self) -> bool {
+        self.state == RtcSctpState::Established
+    }
+
     pub fn init(&mut self, client: bool, now: Instant) {
         assert!(self.state == RtcSctpState::Uninited);
 
@@ -235,7 +633,12 @@ impl RtcSctp {
             info!("New local association");
             let (handle, assoc) = self
                 .endpoint
-                .connect(ClientConfig::default(), self.fake_addr)
+                .connect(
+                    ClientConfig {
+                        transport: self.transport.clone(),
+                    },
+                    self.fake_addr,
+                )
                 .expect("be able to create an association");
             self.handle = handle;
             self.assoc = Some(assoc);
@@ -308,6 +711,78 @@ impl RtcSctp {
         }
     }
 
+    /// Close stream, telling the remote peer why.
+    ///
+    /// The reason is only sent if the stream is open.
+    pub fn close_stream_with_reason(&mut self, id: u16, reason: ChannelCloseReason) {
+        if self.is_open(id) {
+            let mut reason = reason;
+            if reason.reason.len() > DCEP_CLOSE_MAX_REASON {
+                let mut n = DCEP_CLOSE_MAX_REASON;
+                while !reason.reason.is_char_boundary(n) {
+                    n -= 1;
+                }
+                reason.reason.truncate(n);
+            }
+
+            let close = DcepClose {
+                code: reason.code,
+                reason: reason.reason,
+            };
+
+            let mut buf = [0; 4 + DCEP_CLOSE_MAX_REASON];
+            let n = close.marshal_to(&mut buf);
+
+            let res = self
+                .assoc
+                .as_mut()
+                .expect("assoc when open")
+                .stream(id)
+                .and_then(|mut s| s.write_with_ppi(&buf[..n], PayloadProtocolIdentifier::Dcep));
+
+            if let Err(e) = res {
+                debug!("Failed to send close reason on stream {}: {:?}", id, e);
+            }
+        }
+
+        self.close_stream(id);
+    }
+
+    /// Bytes written to the stream that are not yet sent and acknowledged.
+    pub fn buffered_amount(&mut self, id: u16) -> usize {
+        let Some(assoc) = &mut self.assoc else {
+            return 0;
+        };
+        let held = self
+            .entries
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| e.held_bytes)
+            .unwrap_or(0);
+        stream_buffered_amount(assoc, id) + held
+    }
+
+    pub fn buffered_amount_low_threshold(&self, id: u16) -> usize {
+        self.entries
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| e.buffered_amount_low_threshold)
+            .unwrap_or(0)
+    }
+
+    /// The threshold is applied to the sctp stream on the next poll.
+    pub fn set_buffered_amount_low_threshold(&mut self, id: u16, threshold: usize) {
+        if let Some(entry) = self.entries.iter_mut().find(|e| e.id == id) {
+            entry.buffered_amount_low_threshold = threshold;
+            entry.need_threshold = true;
+        }
+    }
+
+    /// Stream ids that are being reset after a close, and can't be reused yet.
+    pub fn resetting_ids(&self) -> &[u16] {
+        &self.resetting
+    }
+
     pub fn is_open(&self, id: u16) -> bool {
         if self.state != RtcSctpState::Established {
             return false;
@@ -321,6 +796,17 @@ impl RtcSctp {
     }
 
     pub fn write(&mut self, id: u16, binary: bool, buf: &[u8]) -> Result<usize, SctpError> {
+        self.write_with_reliability(id, binary, buf, None)
+    }
+
+    /// Write with a reliability for this message only, overriding the channel setting.
+    pub fn write_with_reliability(
+        &mut self,
+        id: u16,
+        binary: bool,
+        buf: &[u8],
+        reliability: Option<Reliability>,
+    ) -> Result<usize, SctpError> {
         if self.state != RtcSctpState::Established {
             return Err(SctpError::WriteBeforeEstablished);
         }
@@ -330,18 +816,16 @@ impl RtcSctp {
             .as_mut()
             .ok_or(SctpError::WriteBeforeEstablished)?;
 
-        let rec = self
+        let idx = self
             .entries
             .iter()
-            .find(|e| e.id == id)
+            .position(|e| e.id == id)
             .expect("stream entry for write");
 
-        if rec.state != StreamEntryState::Open {
+        if self.entries[idx].state != StreamEntryState::Open {
             return Err(SctpError::WriteBeforeEstablished);
         }
 
-        let mut stream = assoc.stream(id)?;
-
         let ppi = if binary {
             if buf.is_empty() {
                 PayloadProtocolIdentifier::BinaryEmpty
@@ -354,11 +838,53 @@ impl RtcSctp {
             PayloadProtocolIdentifier::String
         };
 
-        Ok(stream.write_with_ppi(buf, ppi)?)
+        let load = channel_load(assoc, &self.entries);
+        let entry = &self.entries[idx];
+        if !entry.held.is_empty() || held_back(assoc, entry, load) {
+            let rec = &mut self.entries[idx];
+            if rec.held_bytes + buf.len() > MAX_HELD_BYTES {
+                return Err(SctpError::WouldBlock);
+            }
+            rec.held_bytes += buf.len();
+            rec.held.push_back(HeldMessage {
+                data: buf.to_vec(),
+                ppi,
+                reliability,
+            });
+            return Ok(buf.len());
+        }
+
+        self.entries[idx].write_now(assoc, buf, ppi, reliability)
+    }
+
+    /// Counters for each open stream.
+    pub fn stream_counters(&self) -> impl Iterator<Item = (u16, StreamCounters)> + '_ {
+        self.entries
+            .iter()
+            .filter(|e| e.state == StreamEntryState::Open)
+            .map(|e| (e.id, e.counters))
+    }
+
+    /// Counters of the association.
+    pub fn transport_counters(&self) -> TransportCounters {
+        self.transport_counters
+    }
+
+    /// Buffered amount of each open stream, including messages held back for priority.
+    pub fn stream_buffered_amounts(&mut self) -> Vec<(u16, usize)> {
+        let Some(assoc) = &mut self.assoc else {
+            return vec![];
+        };
+        self.entries
+            .iter()
+            .filter(|e| e.state == StreamEntryState::Open)
+            .map(|e| (e.id, stream_buffered_amount(assoc, e.id) + e.held_bytes))
+            .collect()
     }
 
     pub fn handle_input(&mut self, now: Instant, data: &[u8]) {
         trace!("Handle input: {}", data.len());
+        self.transport_counters.packets_received += 1;
 
         // TODO, remove Bytes in sctp and just use &[u8].
         let data = data.to_vec().into();
@@ -404,6 +930,29 @@ impl RtcSctp {
 
         assoc.handle_timeout(now);
 
+        // Stream ids are free for reuse once the reset removed them from the association.
+        self.resetting.retain(|id| assoc.stream(*id).is_ok());
+
+        // Revert per-message reliability overrides on drained streams.
+        for rec in &mut self.entries {
+            if rec.reliability_override.is_none() || rec.state != StreamEntryState::Open {
+                continue;
+            }
+            let Ok(mut stream) = assoc.stream(rec.id) else {
+                continue;
+            };
+            if matches!(stream.buffered_amount(), Ok(0)) {
+                let base = rec
+                    .config
+                    .as_ref()
+                    .expect("config for open stream")
+                    .reliability;
+                if let Err(e) = rec.apply_reliability(&mut stream, base) {
+                    warn!("Failed to revert reliability on stream {}: {:?}", rec.id, e);
+                }
+            }
+        }
+
         // propagate events between endpoint and association.
         while let Some(e) = assoc.poll_endpoint_event() {
             if let Some(ae) = self.endpoint.handle_event(self.handle, e) {
@@ -437,9 +986,28 @@ impl RtcSctp {
                 continue;
             };
 
+            for packet in &buf {
+                self.transport_counters.count_sent(packet);
+            }
+
             return Some(SctpEvent::Transmit { packets: buf });
         }
 
+        if let Some((id, error)) = self.write_failures.pop_front() {
+            return Some(SctpEvent::WriteFailed { id, error });
+        }
+
+        // All channels die with the association.
+        if self.state == RtcSctpState::Lost {
+            for entry in &mut self.entries {
+                if entry.state != StreamEntryState::Closed {
+                    entry.set_state(StreamEntryState::Closed);
+                    return Some(SctpEvent::Close { id: entry.id });
+                }
+            }
+            return None;
+        }
+
         // Don't progress to move data between association and endpoint until we have an
         // association we want to drive forward.
         if !self.state.propagate_endpoint_to_assoc() {
@@ -454,7 +1022,15 @@ impl RtcSctp {
                 return self.poll();
             }
 
-            // TODO: Do we need to handle AssociationLost?
+            if let Event::AssociationLost { reason } = e {
+                let reason: SctpCloseReason = reason.into();
+                warn!("SCTP association lost: {}", reason);
+
+                // The Close events for the channels are emitted on the following polls.
+                set_state(&mut self.state, RtcSctpState::Lost);
+
+                return Some(SctpEvent::AssociationLost { reason });
+            }
 
             if let Event::Stream(se) = e {
                 match se {
@@ -466,6 +1042,21 @@ impl RtcSctp {
                             "readable/writable",
                         );
                     }
+                    StreamEvent::BufferedAmountLow { id } => {
+                        let Some(entry) = self
+                            .entries
+                            .iter()
+                            .find(|e| e.id == id && e.state == StreamEntryState::Open)
+                        else {
+                            continue;
+                        };
+                        // Messages held back for priority count towards the amount. If those
+                        // keep it above the threshold, the event comes once they drain.
+                        let amount = stream_buffered_amount(assoc, id) + entry.held_bytes;
+                        if amount <= entry.buffered_amount_low_threshold {
+                            return Some(SctpEvent::BufferedAmountLow { id });
+                        }
+                    }
                     StreamEvent::Finished { id } | StreamEvent::Stopped { id, .. } => {
                         let entry = stream_entry(
                             &mut self.entries,
@@ -486,6 +1077,11 @@ impl RtcSctp {
             return None;
         }
 
+        if release_held(assoc, &mut self.entries, &mut self.write_failures) {
+            // Start over to send what was released.
+            return self.do_poll();
+        }
+
         for entry in &mut self.entries {
             let want_open = entry.state == StreamEntryState::AwaitOpen;
 
@@ -501,16 +1097,7 @@ impl RtcSctp {
                         let in_band = config.negotiated.is_none();
 
                         if in_band {
-                            let dcep: DcepOpen = config.into();
-                            let mut buf = vec![0; 1500];
-                            let n = dcep.marshal_to(&mut buf);
-                            buf.truncate(n);
-
-                            let l = s
-                                .write_with_ppi(&buf, PayloadProtocolIdentifier::Dcep)
-                                .expect("writing dcep open");
-                            assert!(n == l);
-
+                            entry.write_dcep_open(&mut s, self.last_now);
                             entry.set_state(StreamEntryState::AwaitDcepAck);
 
                             // Start over with polling, since we might have caused some network traffic by
@@ -557,7 +1144,45 @@ impl RtcSctp {
                 });
             }
 
+            let dcep_timeout = entry.state == StreamEntryState::AwaitDcepAck
+                && entry.dcep_timeout_at.map(|t| self.last_now >= t) == Some(true);
+
+            if dcep_timeout {
+                warn!("No DCEP ACK for stream {}", entry.id);
+                entry.dcep_timeout_at = None;
+                entry.do_close = true;
+            }
+
             if entry.do_close && entry.state != StreamEntryState::Closed {
+                // The reason goes out before the close.
+                if let Some(reason) = entry.remote_close_reason.take() {
+                    return Some(SctpEvent::CloseReason {
+                        id: entry.id,
+                        reason,
+                    });
+                }
+
+                // Messages written before the close go out regardless of priority.
+                while let Some(m) = entry.held.pop_front() {
+                    if let Err(e) = entry.write_now(assoc, &m.data, m.ppi, m.reliability) {
+                        debug!(
+                            "Failed to write held message on stream {}: {:?}",
+                            entry.id, e
+                        );
+                        self.write_failures.push_back((entry.id, e));
+                    }
+                }
+                entry.held_bytes = 0;
+
+                // Reset the stream, which closes the channel on the remote side (RFC 8831
+                // section 6.7). If the remote reset it first, this resets our direction.
+                if let Ok(mut stream) = assoc.stream(entry.id) {
+                    if let Err(e) = stream.close() {
+                        debug!("Resetting stream {} failed: {:?}", entry.id, e);
+                    }
+                    self.resetting.push(entry.id);
+                }
+
                 entry.set_state(StreamEntryState::Closed);
                 return Some(SctpEvent::Close { id: entry.id });
             }
@@ -572,7 +1197,15 @@ impl RtcSctp {
                 }
             };
 
-            match stream_read_data(&mut stream) {
+            if entry.need_threshold {
+                let t = entry.buffered_amount_low_threshold;
+                if let Err(e) = stream.set_buffered_amount_low_threshold(t) {
+                    warn!("Failed to set threshold on stream {}: {:?}", entry.id, e);
+                }
+                entry.need_threshold = false;
+            }
+
+            match stream_read_data(&mut stream, self.max_message_size) {
                 Ok(Some((buf, ppi))) => {
                     if ppi != PayloadProtocolIdentifier::Dcep {
                         // This is the normal path for incoming data.
@@ -582,6 +1215,8 @@ impl RtcSctp {
                             PayloadProtocolIdentifier::Binary
                                 | PayloadProtocolIdentifier::BinaryEmpty
                         );
+                        entry.counters.messages_received += 1;
+                        entry.counters.bytes_received += buf.len() as u64;
                         return Some(SctpEvent::Data {
                             id: entry.id,
                             binary,
@@ -634,6 +1269,7 @@ impl RtcSctp {
                             }
 
                             entry.set_state(StreamEntryState::Open);
+                            entry.dcep_timeout_at = None;
                             let config = entry.config.as_ref().expect("config when DcepAck");
 
                             return Some(SctpEvent::Open {
@@ -641,6 +1277,22 @@ impl RtcSctp {
                                 label: config.label.clone(),
                             });
                         }
+                        // The remote is closing the channel and tells us why.
+                        StreamEntryState::Open => {
+                            if let Ok(close) = DcepClose::try_from(buf.as_slice()) {
+                                debug!("Remote close reason for stream: {}", entry.id);
+                                entry.remote_close_reason = Some(ChannelCloseReason {
+                                    code: close.code,
+                                    reason: close.reason,
+                                });
+                                entry.do_close = true;
+                                return self.do_poll();
+                            }
+
+                            warn!("Unknown DCEP message for open stream: {}", entry.id);
+                            entry.do_close = true;
+                            continue;
+                        }
                         _ => {
                             warn!(
                                 "Stream {} in wrong state when receiving DCEP: {:?}",
@@ -652,6 +1304,14 @@ impl RtcSctp {
                     }
                 }
                 Ok(None) => continue,
+                Err(SctpError::MessageTooLarge { size, max }) => {
+                    warn!(
+                        "Drop message of {} bytes above max-message-size {} on stream {}",
+                        size, max, entry.id
+                    );
+                    // There might be more messages queued on the stream.
+                    return self.do_poll();
+                }
                 Err(_) => entry.do_close = true,
             }
         }
@@ -660,7 +1320,19 @@ impl RtcSctp {
     }
 
     pub fn poll_timeout(&mut self) -> Option<Instant> {
-        self.assoc.as_mut().and_then(|a| a.poll_timeout())
+        let assoc = self.assoc.as_mut().and_then(|a| a.poll_timeout());
+
+        let dcep = self
+            .entries
+            .iter()
+            .filter(|e| e.state == StreamEntryState::AwaitDcepAck)
+            .filter_map(|e| e.dcep_timeout_at)
+            .min();
+
+        match (assoc, dcep) {
+            (Some(a), Some(d)) => Some(a.min(d)),
+            (a, d) => a.or(d),
+        }
     }
 
     pub fn push_back_transmit(&mut self, data: VecDeque<Vec<u8>>) {
@@ -713,20 +1385,126 @@ fn stream_entry<'a>(
             state: initial_state,
             id,
             do_close: false,
+            dcep_timeout_at: None,
+            counters: StreamCounters::default(),
+            remote_close_reason: None,
+            reliability_override: None,
+            buffered_amount_low_threshold: 0,
+            need_threshold: false,
+            held: VecDeque::new(),
+            held_bytes: 0,
         };
         entries.push(e);
         entries.last_mut().unwrap()
     }
 }
 
+fn stream_buffered_amount(assoc: &mut Association, id: u16) -> usize {
+    assoc
+        .stream(id)
+        .and_then(|s| s.buffered_amount())
+        .unwrap_or(0)
+}
+
+/// Write held back messages that fit the share of the send window of their channel.
+///
+/// Returns true if anything was written. Failed writes are added to `failures`.
+fn release_held(
+    assoc: &mut Association,
+    entries: &mut [StreamEntry],
+    failures: &mut VecDeque<(u16, SctpError)>,
+) -> bool {
+    if entries.iter().all(|e| e.held.is_empty()) {
+        return false;
+    }
+
+    // Channels with held messages stay active while releasing, so the load doesn't change.
+    let load = channel_load(assoc, entries);
+
+    let mut released = false;
+
+    for entry in entries.iter_mut() {
+        while !entry.held.is_empty() && !held_back(assoc, entry, load) {
+            let m = entry.held.pop_front().expect("held message");
+            entry.held_bytes -= m.data.len();
+
+            if let Err(e) = entry.write_now(assoc, &m.data, m.ppi, m.reliability) {
+                warn!(
+                    "Failed to write held message on stream {}: {:?}",
+                    entry.id, e
+                );
+                failures.push_back((entry.id, e));
+            }
+            released = true;
+        }
+    }
+
+    released
+}
+
+/// Load of the open channels for [`held_back`].
+#[derive(Clone, Copy)]
+struct ChannelLoad {
+    /// Number of open channels.
+    open: usize,
+    /// Sum of the weights of open channels with data buffered or held back.
+    active_weight: usize,
+}
+
+fn channel_load(assoc: &mut Association, entries: &[StreamEntry]) -> ChannelLoad {
+    let mut load = ChannelLoad {
+        open: 0,
+        active_weight: 0,
+    };
+
+    for e in entries.iter().filter(|e| e.state == StreamEntryState::Open) {
+        load.open += 1;
+        if !e.held.is_empty() || stream_buffered_amount(assoc, e.id) > 0 {
+            load.active_weight += e.priority().weight();
+        }
+    }
+
+    load
+}
+
+/// Whether a message on the entry must be held back to leave room for other channels.
+///
+/// The channels with data to send share [`SEND_WINDOW`] weighted by priority, as suggested
+/// in RFC 8831 section 6.4. A channel with nothing buffered in the association can always
+/// send, which guarantees every channel a minimum share.
+fn held_back(assoc: &mut Association, entry: &StreamEntry, load: ChannelLoad) -> bool {
+    // A single channel has nobody to share with.
+    if load.open <= 1 {
+        return false;
+    }
+
+    let weight = entry.priority().weight();
+    let buffered = stream_buffered_amount(assoc, entry.id);
+
+    let is_active = buffered > 0 || !entry.held.is_empty();
+    let own = if is_active { weight } else { 0 };
+    let others = load.active_weight.saturating_sub(own);
+
+    let share = SEND_WINDOW * weight / (weight + others);
+
+    buffered >= share
+}
+
 fn stream_read_data(
     stream: &mut Stream,
+    max: usize,
 ) -> Result<Option<(Vec<u8>, PayloadProtocolIdentifier)>, SctpError> {
     let Some(chunks) = stream.read()? else {
         return Ok(None);
     };
 
     let n = chunks.len();
+
+    // Reading the chunks has removed them from the stream, so dropping them here discards
+    // the message without copying it.
+    if n > max {
+        return Err(SctpError::MessageTooLarge { size: n, max });
+    }
     let mut buf = vec![0; n];
 
     let l = chunks.read(&mut buf)?;
@@ -769,12 +1547,24 @@ impl fmt::Debug for SctpEvent {
                 .field("label", label)
                 .finish(),
             Self::Close { id } => f.debug_struct("Close").field("id", id).finish(),
+            Self::CloseReason { id, reason } => f
+                .debug_struct("CloseReason")
+                .field("id", id)
+                .field("reason", reason)
+                .finish(),
+            Self::BufferedAmountLow { id } => {
+                f.debug_struct("BufferedAmountLow").field("id", id).finish()
+            }
             Self::Data { id, binary, data } => f
                 .debug_struct("Data")
                 .field("id", id)
                 .field("binary", binary)
                 .field("data", &data.len())
                 .finish(),
+            Self::AssociationLost { reason } => f
+                .debug_struct("AssociationLost")
+                .field("reason", reason)
+                .finish(),
         }
     }
 }
@@ -786,7 +1576,7 @@ impl From<&ChannelConfig> for DcepOpen {
             unordered: !v.ordered,
             channel_type,
             reliability_parameter,
-            priority: 0,
+            priority: v.priority.to_dcep(),
             label: v.label.clone(),
             protocol: v.protocol.clone(),
         }
@@ -815,6 +1605,7 @@ impl From<&DcepOpen> for ChannelConfig {
             reliability: (v.channel_type, v.reliability_parameter).into(),
             negotiated: None,
             protocol: v.protocol.clone(),
+            priority: ChannelPriority::from_dcep(v.priority),
         }
     }
 }
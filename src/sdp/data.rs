@@ -51,6 +51,11 @@ impl Sdp {
             .or_else(|| self.media_lines.iter().find_map(|m| m.ice_creds()))
     }
 
+    /// Whether end-of-candidates is in the session or any m-line.
+    pub(crate) fn end_of_candidates(&self) -> bool {
+        self.session.end_of_candidates() || self.media_lines.iter().any(|m| m.end_of_candidates())
+    }
+
     pub(crate) fn ice_candidates(&self) -> impl Iterator<Item = &Candidate> {
         let mut candidates: HashSet<&Candidate> = HashSet::new();
 
@@ -65,6 +70,39 @@ impl Sdp {
         candidates.into_iter()
     }
 
+    /// Mids of enabled m-lines that are not part of a BUNDLE group.
+    ///
+    /// A single m-line doesn't need BUNDLE, so this is empty in that case.
+    pub(crate) fn unbundled_mids(&self) -> Vec<Mid> {
+        let enabled: Vec<Mid> = self
+            .media_lines
+            .iter()
+            .filter(|m| !m.disabled)
+            .map(|m| m.mid())
+            .collect();
+
+        if enabled.len() <= 1 {
+            return vec![];
+        }
+
+        let bundled: Vec<Mid> = self
+            .session
+            .attrs
+            .iter()
+            .filter_map(|a| match a {
+                SessionAttribute::Group { typ, mids } if typ == "BUNDLE" => Some(mids),
+                _ => None,
+            })
+            .flatten()
+            .copied()
+            .collect();
+
+        enabled
+            .into_iter()
+            .filter(|m| !bundled.contains(m))
+            .collect()
+    }
+
     pub(crate) fn setup(&self) -> Option<Setup> {
         self.session
             .setup()
@@ -91,8 +129,15 @@ impl Sdp {
                     self.media_lines.len()
                 ));
             }
-            for (media, mid) in self.media_lines.iter().zip(mids.iter()) {
+            for media in &self.media_lines {
                 media.check_consistent()?;
+            }
+            // Rejected m-lines are left out of the BUNDLE group.
+            let bundled = self
+                .media_lines
+                .iter()
+                .filter(|m| !m.disabled || mids.contains(&m.mid()));
+            for (media, mid) in bundled.zip(mids.iter()) {
                 let m = media.mid();
                 if m != *mid {
                     return Some(format!("Mid order not matching a=group {m} != {mid}"));
@@ -187,6 +232,12 @@ impl Session {
             .iter()
             .any(|a| matches!(a, SessionAttribute::EndOfCandidates))
     }
+
+    pub fn extmap_allow_mixed(&self) -> bool {
+        self.attrs
+            .iter()
+            .any(|a| matches!(a, SessionAttribute::ExtmapAllowMixed))
+    }
 }
 
 /// Attributes before the first m= line.
@@ -208,6 +259,7 @@ pub enum SessionAttribute {
     Setup(Setup), // active, passive, actpass, holdconn
     Candidate(Candidate),
     EndOfCandidates,
+    ExtmapAllowMixed,
     Unused(String),
 }
 
@@ -266,6 +318,16 @@ impl MediaLine {
         self.attrs[idx] = dir.into();
     }
 
+    /// Audio, video or application data over RTP.
+    pub fn is_media(&self) -> bool {
+        self.typ.is_media() || (self.typ == MediaType::Application && self.proto == Proto::Srtp)
+    }
+
+    /// Data channels over SCTP.
+    pub fn is_channel(&self) -> bool {
+        self.typ.is_channel() && self.proto == Proto::Sctp
+    }
+
     pub fn rtp_params(&self) -> Vec<PayloadParams> {
         let rtp_maps: Vec<_> = self
             .attrs
@@ -305,7 +367,12 @@ impl MediaLine {
 
         let mut params: Vec<_> = rtp_maps
             .iter()
-            .filter(|(_, c)| c.codec.is_audio() | c.codec.is_video())
+            .filter(|(_, c)| match self.typ {
+                MediaType::Application => c.codec.is_application(),
+                MediaType::Video if c.codec.is_video_protection() => true,
+                MediaType::Audio if c.codec == Codec::Red => true,
+                _ => c.codec.is_audio() | c.codec.is_video(),
+            })
             .map(|(pt, c)| PayloadParams::new(*pt, None, (*c).into()))
             .collect();
 
@@ -506,6 +573,32 @@ impl MediaLine {
         ret
     }
 
+    pub fn sctp_port(&self) -> Option<u16> {
+        self.attrs.iter().find_map(|a| {
+            if let MediaAttribute::SctpPort(v) = a {
+                Some(*v)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn max_message_size(&self) -> Option<usize> {
+        self.attrs.iter().find_map(|a| {
+            if let MediaAttribute::MaxMessageSize(v) = a {
+                Some(*v)
+            } else {
+                None
+            }
+        })
+    }
+
+    pub fn rtcp_rsize(&self) -> bool {
+        self.attrs
+            .iter()
+            .any(|a| matches!(a, MediaAttribute::RtcpRsize))
+    }
+
     pub fn rids(&self) -> Vec<Rid> {
         let mut ret = vec![];
         for a in &self.attrs {
@@ -599,6 +692,13 @@ impl MediaLine {
         for a in &self.attrs {
             match a {
                 MediaAttribute::SsrcGroup { semantics, ssrcs } => {
+                    // a=ssrc-group:FEC-FR 659652645 1293841245
+                    // The FlexFEC SSRC is found by PT, it's not a stream of its own.
+                    if semantics.eq_ignore_ascii_case("fec-fr") && ssrcs.len() == 2 {
+                        v.retain(|i| i.ssrc != ssrcs[1]);
+                        continue;
+                    }
+
                     if semantics.to_lowercase() != "fid" {
                         continue;
                     }
@@ -903,12 +1003,24 @@ pub enum FormatParam {
     /// * 64 00 1f - 6400=high (H)                  1f=level 3.1
     ProfileLevelId(u32),
 
-    /// VP9 profile id
+    /// VP9 and H265 profile id
     ProfileId(u32),
 
+    /// H265 tier flag, 0 for main and 1 for high tier.
+    TierFlag(u8),
+
+    /// H265 level id.
+    LevelId(u8),
+
+    /// FlexFEC repair window in microseconds.
+    RepairWindow(u32),
+
     /// RTX (resend) codecs, which PT it concerns.
     Apt(Pt),
 
+    /// RED (redundancy) codecs, which PT is used for the primary and redundant blocks.
+    Red(Pt),
+
     /// Unrecognized fmtp.
     Unknown,
 }
@@ -952,6 +1064,30 @@ impl FormatParam {
                     Unknown
                 }
             }
+            "tier-flag" => {
+                if let Ok(v) = v.parse() {
+                    TierFlag(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
+            "level-id" => {
+                if let Ok(v) = v.parse() {
+                    LevelId(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
+            "repair-window" => {
+                if let Ok(v) = v.parse() {
+                    RepairWindow(v)
+                } else {
+                    trace!("Failed to parse: {}", k);
+                    Unknown
+                }
+            }
             "apt" => {
                 if let Ok(v) = v.parse::<u8>() {
                     Apt(v.into())
@@ -963,6 +1099,22 @@ impl FormatParam {
             _ => Unknown,
         }
     }
+
+    /// Parses the RED fmtp, i.e. `111/111`, where the primary and redundant blocks
+    /// use the same PT.
+    pub fn parse_red(v: &str) -> Self {
+        let mut pts = v.split('/').map(|p| p.parse::<u8>());
+
+        let Some(Ok(first)) = pts.next() else {
+            return FormatParam::Unknown;
+        };
+
+        if pts.all(|p| p == Ok(first)) {
+            FormatParam::Red(first.into())
+        } else {
+            FormatParam::Unknown
+        }
+    }
 }
 
 impl fmt::Display for FormatParam {
@@ -978,7 +1130,11 @@ impl fmt::Display for FormatParam {
             PacketizationMode(v) => write!(f, "packetization-mode={}", *v),
             ProfileLevelId(v) => write!(f, "profile-level-id={:06x}", *v),
             ProfileId(v) => write!(f, "profile-id={}", *v),
+            TierFlag(v) => write!(f, "tier-flag={}", *v),
+            LevelId(v) => write!(f, "level-id={}", *v),
+            RepairWindow(v) => write!(f, "repair-window={}", *v),
             Apt(v) => write!(f, "apt={v}"),
+            Red(v) => write!(f, "{v}/{v}"),
             Unknown => Ok(()),
         }
     }
@@ -1190,6 +1346,7 @@ impl fmt::Display for SessionAttribute {
             Setup(v) => write!(f, "a=setup:{}\r\n", v.setup_line())?,
             Candidate(c) => write!(f, "a={}\r\n", c.to_sdp_string())?,
             EndOfCandidates => write!(f, "a=end-of-candidates\r\n")?,
+            ExtmapAllowMixed => write!(f, "a=extmap-allow-mixed\r\n")?,
             Unused(v) => write!(f, "a={v}\r\n")?,
         }
         Ok(())
@@ -1198,9 +1355,10 @@ impl fmt::Display for SessionAttribute {
 
 impl fmt::Display for MediaLine {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "m={} 9 {} ", self.typ, self.proto,)?;
+        let port = if self.disabled { 0 } else { 9 };
+        write!(f, "m={} {} {} ", self.typ, port, self.proto,)?;
         let len = self.pts.len();
-        if self.typ.is_channel() {
+        if self.is_channel() {
             write!(f, "webrtc-datachannel\r\n")?;
         } else {
             for (idx, m) in self.pts.iter().enumerate() {
@@ -1,4 +1,4 @@
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use {
     combine::error::*,
     combine::parser::char::*,
@@ -9,9 +9,10 @@ use {
 };
 
 use crate::crypto::Fingerprint;
+use crate::format::{AppCodecName, Codec};
 use crate::rtp_::{Direction, Extension, Frequency, Mid, Pt, SessionId, Ssrc};
 use crate::sdp::SdpError;
-use crate::{Candidate, CandidateKind};
+use crate::{Candidate, CandidateKind, TcpType};
 
 use super::data::*;
 
@@ -182,6 +183,10 @@ where
     // a=end-of-candidates
     let endof = attribute_line_flag("end-of-candidates").map(|_| SessionAttribute::EndOfCandidates);
 
+    // a=extmap-allow-mixed
+    let allow_mixed =
+        attribute_line_flag("extmap-allow-mixed").map(|_| SessionAttribute::ExtmapAllowMixed);
+
     // tls-id
     // identity
     // extmap
@@ -198,6 +203,7 @@ where
         attempt(setup),
         attempt(cand),
         attempt(endof),
+        attempt(allow_mixed),
         unused,
     ))
 }
@@ -232,6 +238,26 @@ where
         })
     };
 
+    // Either an ip, or an obfuscated mDNS hostname which is resolved later.
+    let connection_addr = || {
+        not_sp().and_then(|s| {
+            if s.ends_with(".local") {
+                return Ok((IpAddr::from(Ipv4Addr::UNSPECIFIED), Some(s)));
+            }
+            s.parse::<IpAddr>()
+                .map(|ip| (ip, None))
+                .map_err(StreamErrorFor::<Input>::message_format)
+        })
+    };
+
+    let tcp_type = || {
+        not_sp().and_then(|s| {
+            TcpType::try_from(s.as_str()).map_err(|_| {
+                StreamErrorFor::<Input>::message_format(format!("invalid tcptype: {}", s))
+            })
+        })
+    };
+
     let kind = choice((
         string("host").map(|_| CandidateKind::Host),
         string("prflx").map(|_| CandidateKind::PeerReflexive),
@@ -262,18 +288,22 @@ where
                 .map_err(StreamErrorFor::<Input>::message_format)
         }),
         token(' '),
-        ip_addr(),
+        connection_addr(),
         token(' '),
         port(),
         string(" typ "),
         kind,
-        optional((attempt(string(" tcptype ")), not_sp())),
-        optional((
-            attempt(string(" raddr ")),
-            ip_addr(),
-            string(" rport "),
-            port(),
-        )),
+        optional((attempt(string(" tcptype ")), tcp_type())),
+        (
+            optional((
+                attempt(string(" raddr ")),
+                ip_addr(),
+                string(" rport "),
+                port(),
+            )),
+            // RFC 6544 places tcptype after raddr/rport.
+            optional((attempt(string(" tcptype ")), tcp_type())),
+        ),
         optional((attempt(string(" generation ")), not_sp())),
         optional((attempt(string(" network-id ")), not_sp())),
         optional((attempt(string(" ufrag ")), not_sp())),
@@ -290,19 +320,19 @@ where
                 _,
                 prio,
                 _,
-                addr,
+                (addr, mdns_hostname),
                 _,
                 port,
                 _,
                 kind,
-                _,     // (" tcptype ", tcptype)
-                raddr, // (" raddr ", addr, " rport ", port)
-                _,     // (" generation ", generation)
-                _,     // (" network-id ", network_id)
-                ufrag, // (" ufrag ", ufrag)
-                _,     // ("network-cost", network_cost)
+                tcp1,          // (" tcptype ", tcptype)
+                (raddr, tcp2), // ((" raddr ", addr, " rport ", port), (" tcptype ", tcptype))
+                _,             // (" generation ", generation)
+                _,             // (" network-id ", network_id)
+                ufrag,         // (" ufrag ", ufrag)
+                _,             // ("network-cost", network_cost)
             )| {
-                Candidate::parsed(
+                let mut c = Candidate::parsed(
                     found,
                     comp_id,
                     proto,
@@ -311,7 +341,12 @@ where
                     kind,
                     raddr.map(|(_, addr, _, port)| SocketAddr::from((addr, port))),
                     ufrag.map(|(_, u)| u),
-                )
+                    tcp1.or(tcp2).map(|(_, t)| t),
+                );
+                if let Some(hostname) = mdns_hostname {
+                    c.set_mdns_hostname(hostname);
+                }
+                c
             },
         )
 }
@@ -367,7 +402,19 @@ where
         optional(bandwidth_line()),             // b=AS:2500
         many::<Vec<_>, _, _>(media_attribute_line()),
     )
-        .and_then(|((typ, port, proto, pts), _, bw, attrs)| {
+        .and_then(|((typ, port, proto, pts), _, bw, mut attrs)| {
+            // Encoding names we don't know are only application codecs on an
+            // m=application line over RTP.
+            if typ != MediaType::Application || proto != Proto::Srtp {
+                for a in &mut attrs {
+                    if let MediaAttribute::RtpMap { value, .. } = a {
+                        if value.codec.is_application() {
+                            value.codec = Codec::Unknown;
+                        }
+                    }
+                }
+            }
+
             let m = MediaLine {
                 typ,
                 disabled: port == "0",
@@ -583,7 +630,7 @@ where
         MediaAttribute::RtpMap {
             pt,
             value: RtpMap {
-                codec: codec.as_str().into(),
+                codec: parse_codec(&codec),
                 clock_rate,
                 channels,
             },
@@ -607,11 +654,12 @@ where
     let fmtp1 = attribute_line("fmtp", (pt(), token(' '), fmtp_param))
         .map(|(pt, _, values)| MediaAttribute::Fmtp { pt, values });
 
+    // a=fmtp:63 111/111
     // a=fmtp:101 0-15
-    let fmtp2 = attribute_line("fmtp", (pt(), token(' '), not_sp())).map(|(pt, _, _value)| {
+    let fmtp2 = attribute_line("fmtp", (pt(), token(' '), not_sp())).map(|(pt, _, value)| {
         MediaAttribute::Fmtp {
             pt,
-            values: vec![FormatParam::Unknown],
+            values: vec![FormatParam::parse_red(&value)],
         }
     });
 
@@ -781,6 +829,18 @@ where
         unused,
     ))
 }
+
+/// Codec of an a=rtpmap line. Names we don't know are kept for application data m-lines,
+/// and turned into [`Codec::Unknown`] by [`media_parser`] for any other m-line.
+fn parse_codec(name: &str) -> Codec {
+    match Codec::from(name) {
+        Codec::Unknown => AppCodecName::new(name)
+            .map(Codec::Application)
+            .unwrap_or(Codec::Unknown),
+        c => c,
+    }
+}
+
 /// /////////////////////////////////////////////////// Generic things below
 
 /// A specific line
@@ -1003,6 +1063,31 @@ mod test {
         );
     }
 
+    #[test]
+    fn rtpmap_unknown_codec() {
+        let rtpmap = |typ: &str| {
+            let m = format!(
+                "m={typ} 9 UDP/TLS/RTP/SAVPF 120\r\n\
+                a=mid:0\r\n\
+                a=sendrecv\r\n\
+                a=rtpmap:120 x-haptics/90000\r\n"
+            );
+            let (m, _) = media_parser().parse(m.as_str()).unwrap();
+            m.attrs
+                .into_iter()
+                .find_map(|a| match a {
+                    MediaAttribute::RtpMap { value, .. } => Some(value.codec),
+                    _ => None,
+                })
+                .unwrap()
+        };
+
+        let name = AppCodecName::new("x-haptics").unwrap();
+        assert_eq!(rtpmap("application"), Codec::Application(name));
+        assert_eq!(rtpmap("audio"), Codec::Unknown);
+        assert_eq!(rtpmap("video"), Codec::Unknown);
+    }
+
     #[test]
     fn session_parser_simple() {
         let sdp = "v=0\n\
@@ -1186,6 +1271,7 @@ mod test {
 
         assert!(!sdp.media_lines.is_empty());
         assert_eq!(sdp.media_lines[0].mid().to_string(), "0");
+        assert!(sdp.session.extmap_allow_mixed());
     }
 
     #[test]
@@ -1228,12 +1314,35 @@ mod test {
         let a = "a=candidate:2501718406 1 tcp 1518280447 10.217.229.219 9 typ host tcptype active generation 0 network-id 1 network-cost 900";
         let (c, _) = candidate_attribute().parse(a).unwrap();
         assert_eq!(c.addr(), "10.217.229.219:9".parse().unwrap());
+        assert_eq!(c.tcp_type(), Some(TcpType::Active));
+
+        let a = "a=candidate:1 1 tcp 1518280447 1.2.3.4 5000 typ srflx raddr 10.0.0.1 rport 8998 tcptype so";
+        let (c, _) = candidate_attribute().parse(a).unwrap();
+        assert_eq!(c.raddr(), Some("10.0.0.1:8998".parse().unwrap()));
+        assert_eq!(c.tcp_type(), Some(TcpType::So));
 
         let a = "a=candidate:387183333 1 udp 1686052607 113.185.55.72 41775 typ srflx raddr 10.217.229.219 rport 50028 generation 0 network-id 1 network-cost 900";
         let (c, _) = candidate_attribute().parse(a).unwrap();
         assert_eq!(c.addr(), "113.185.55.72:41775".parse().unwrap());
     }
 
+    #[test]
+    fn parse_candidate_mdns() {
+        let a = "a=candidate:1 1 udp 2113937151 9b36eaac-bb2e-49bb-bb78-21c41c499900.local 61665 typ host generation 0 network-cost 999\r\n";
+        let (c, _) = candidate_attribute().parse(a).unwrap();
+        assert_eq!(
+            c.mdns_hostname(),
+            Some("9b36eaac-bb2e-49bb-bb78-21c41c499900.local")
+        );
+        assert_eq!(c.addr(), "0.0.0.0:61665".parse().unwrap());
+        assert!(c
+            .to_sdp_string()
+            .contains(" 9b36eaac-bb2e-49bb-bb78-21c41c499900.local 61665 "));
+
+        let a = "a=candidate:1 1 udp 2113937151 example.com 61665 typ host\r\n";
+        assert!(candidate_attribute().parse(a).is_err());
+    }
+
     #[test]
     fn parse_firefox_missing_setup_on_mid1() {
         let sdp = "v=0\r\n\
@@ -1,28 +1,36 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
-use crate::bwe::BweKind;
+use crate::bwe::{BweKind, RemoteBitrateLimit, RemoteBitrateLimitKind};
 use crate::crypto::KeyingMaterial;
 use crate::crypto::SrtpProfile;
-use crate::format::CodecConfig;
+use crate::crypto::{DefaultSrtpCrypto, SrtpCrypto};
 use crate::format::PayloadParams;
+use crate::format::{Codec, CodecConfig};
 use crate::io::{DatagramSend, DATAGRAM_MTU, DATAGRAM_MTU_WARN};
+use crate::limits::{Limit, LimitState};
 use crate::media::KeyframeRequestKind;
 use crate::media::Media;
 use crate::media::{MediaAdded, MediaChanged};
+use crate::packet::is_dtx;
+use crate::packet::is_end_of_frame;
+use crate::packet::RedBlock;
 use crate::packet::SendSideBandwithEstimator;
 use crate::packet::{LeakyBucketPacer, NullPacer, Pacer, PacerImpl};
-use crate::rtp::RawPacket;
+use crate::rtp::{RawPacket, RawRtcp};
 use crate::rtp_::Direction;
 use crate::rtp_::Pt;
 use crate::rtp_::SeqNo;
 use crate::rtp_::SRTCP_OVERHEAD;
 use crate::rtp_::{extend_u16, RtpHeader, SessionId, TwccRecvRegister, TwccSendRegister};
-use crate::rtp_::{Bitrate, ExtensionMap, Mid, Rtcp, RtcpFb};
-use crate::rtp_::{SrtpContext, Ssrc};
+use crate::rtp_::{Bitrate, DataSize, ExtensionMap, Mid, Rtcp, RtcpFb};
+use crate::rtp_::{RocRecoveryCounters, RtpLeniency, RtpLeniencyCounters, SrtpContext, Ssrc};
+use crate::rtp_::{SrtpErrorCounters, UnprotectError};
 use crate::stats::StatsSnapshot;
-use crate::streams::{RtpPacket, Streams};
+use crate::streams::{FecDecoder, RtcpPacer, RtpPacket, Streams};
 use crate::util::{already_happened, not_happening, Soonest};
+use crate::warning::Warning;
 use crate::Event;
 use crate::{net, Reason};
 use crate::{RtcConfig, RtcError};
@@ -35,6 +43,9 @@ const NACK_MIN_INTERVAL: Duration = Duration::from_millis(33);
 /// Delay between reports of TWCC. This is deliberately very low.
 const TWCC_INTERVAL: Duration = Duration::from_millis(100);
 
+/// How long the previous SRTP receive context is kept after a re-key.
+const SRTP_REKEY_GRACE: Duration = Duration::from_secs(5);
+
 /// Amend to the current_bitrate value.
 const PACING_FACTOR: f64 = 1.1;
 
@@ -42,6 +53,13 @@ const PACING_FACTOR: f64 = 1.1;
 /// the total number BWE events to only fire when there is a substantial change.
 const ESTIMATE_TOLERANCE: f64 = 0.05;
 
+/// Smallest congestion window, to always allow a couple of packets in flight.
+const MIN_CONGESTION_WINDOW: DataSize = DataSize::bytes(2 * DATAGRAM_MTU as u64);
+
+/// Max RTCP packets waiting to be sent. Only reached when RTCP pacing holds back feedback
+/// faster than it is generated.
+const MAX_FEEDBACK_TX: usize = 1000;
+
 pub(crate) struct Session {
     id: SessionId,
 
@@ -58,6 +76,9 @@ pub(crate) struct Session {
 
     reordering_size_audio: usize,
     reordering_size_video: usize,
+    red_distance: usize,
+    flexfec_window: usize,
+    ulpfec_window: usize,
     pub send_buffer_audio: usize,
     pub send_buffer_video: usize,
 
@@ -68,8 +89,18 @@ pub(crate) struct Session {
     // Configuration of how we are sending/receiving media.
     pub codec_config: CodecConfig,
 
+    /// Ciphers used for the SRTP contexts.
+    srtp_crypto: Arc<dyn SrtpCrypto>,
+    /// Whether `srtp_crypto` is set via the config, rather than following the DTLS certificate.
+    srtp_crypto_configured: bool,
     srtp_rx: Option<SrtpContext>,
+    /// Receive context before a re-key, kept a while for packets still in flight.
+    srtp_rx_previous: Option<(SrtpContext, Instant)>,
     srtp_tx: Option<SrtpContext>,
+
+    /// Recovery of lost packets, if FlexFEC or ULPFEC is configured.
+    fec_rx: Option<FecDecoder>,
+
     last_nack: Instant,
     last_twcc: Instant,
     twcc: u64,
@@ -80,14 +111,19 @@ pub(crate) struct Session {
 
     enable_twcc_feedback: bool,
 
+    /// Set via the direct API to stop TWCC feedback, even if negotiated.
+    ///
+    /// The sequence numbers are transport wide, so this is for the whole session.
+    disable_twcc_feedback: bool,
+
     /// A pacer for sending RTP at specific rate.
     pacer: PacerImpl,
 
     // temporary buffer when getting the next (unencrypted) RTP packet from Media line.
     poll_packet_buf: Vec<u8>,
 
-    // Next packet for RtpPacket event.
-    pending_packet: Option<RtpPacket>,
+    // Next packets for RtpPacket event. More than one if packets are recovered with FEC.
+    pending_packets: VecDeque<RtpPacket>,
 
     pub ice_lite: bool,
 
@@ -95,9 +131,79 @@ pub(crate) struct Session {
     pub rtp_mode: bool,
 
     feedback_tx: VecDeque<Rtcp>,
+    rtcp_pacer: Option<RtcpPacer>,
     feedback_rx: VecDeque<Rtcp>,
 
     raw_packets: Option<VecDeque<Box<RawPacket>>>,
+
+    /// CNAME shared by all media, if configured.
+    cname: Option<String>,
+
+    /// Warnings to be emitted as events.
+    warnings: VecDeque<Warning>,
+
+    /// Configured resource limits.
+    pub limits: LimitState,
+
+    /// Whether we offer/accept reduced-size RTCP.
+    rtcp_rsize: bool,
+
+    /// Whether we offer/accept the two-byte RTP header extension form (`a=extmap-allow-mixed`).
+    extmap_allow_mixed: bool,
+
+    /// Whether the remote SDP has `a=extmap-allow-mixed`. None for the DirectApi.
+    extmap_allow_mixed_remote: Option<bool>,
+
+    /// The remote sctp-port and max-message-size from the application m-line.
+    ///
+    /// A max-message-size of 0 means the remote can receive messages of any size.
+    pub remote_sctp: Option<(u16, usize)>,
+
+    /// The max-message-size we put in the local SDP.
+    pub local_max_message_size: usize,
+
+    /// No media, only data channels. The media parts of the session are never driven.
+    pub data_channel_only: bool,
+
+    /// Whether the remote peer signalled reduced-size RTCP.
+    ///
+    /// None until we see a remote SDP (or the direct API enables it).
+    rtcp_rsize_remote: Option<bool>,
+
+    /// Which RTP parsing violations to tolerate.
+    rtp_leniency: RtpLeniency,
+
+    /// Counts of RTP parsing violations seen.
+    rtp_leniency_counters: RtpLeniencyCounters,
+    roc_recovery: usize,
+    roc_recovery_counters: RocRecoveryCounters,
+
+    /// Number of incoming SRTP/SRTCP packets that authenticated.
+    rx_authenticated: u64,
+
+    /// The value of `rx_authenticated` when the current SRTP keys were set.
+    rx_authenticated_at_keying: u64,
+
+    /// Number of incoming SRTP/SRTCP packets that authenticated and were not replayed.
+    rx_fresh: u64,
+
+    /// Highest SRTCP index per sender SSRC under the current SRTP keys.
+    srtcp_index_rx: HashMap<Ssrc, u32>,
+
+    /// Counts of incoming SRTP/SRTCP packets dropped, by cause.
+    srtp_error_counters: SrtpErrorCounters,
+
+    /// Estimated send queue time above which media is backpressured.
+    send_backpressure: Option<Duration>,
+
+    /// Reused buffer of estimated queue time per mid.
+    queued_per_mid: Vec<(Mid, Duration)>,
+
+    /// Time on top of the RTT for the congestion window, if enabled.
+    congestion_window: Option<Duration>,
+
+    /// Incoming REMB/TMMBR for our outgoing streams, to be emitted as events.
+    remote_bitrate_limits: VecDeque<RemoteBitrateLimit>,
 }
 
 impl Session {
@@ -108,7 +214,26 @@ impl Session {
         while *id > MAX_ID {
             id = (*id >> 1).into();
         }
-        let (pacer, bwe) = if let Some(rate) = config.bwe_initial_bitrate {
+        let bwe_initial_bitrate = config
+            .bwe_initial_bitrate
+            .filter(|_| !config.data_channel_only);
+
+        // Without media there is no need for codecs, FEC or a packet buffer.
+        let codec_config = if config.data_channel_only {
+            CodecConfig::empty()
+        } else {
+            config.codec_config.clone()
+        };
+        let has_fec = codec_config
+            .iter()
+            .any(|p| matches!(p.spec().codec, Codec::FlexFec | Codec::Ulpfec));
+        let poll_packet_buf = if config.data_channel_only {
+            vec![]
+        } else {
+            vec![0; 2000]
+        };
+
+        let (pacer, bwe) = if let Some(rate) = bwe_initial_bitrate {
             let pacer = PacerImpl::LeakyBucket(LeakyBucketPacer::new(rate * PACING_FACTOR * 2.0));
 
             let send_side_bwe = SendSideBandwithEstimator::new(rate);
@@ -128,20 +253,30 @@ impl Session {
         Session {
             id,
             medias: vec![],
-            streams: Streams::default(),
+            streams: Streams::new(config.rtcp_schedule, config.initial_rtt),
             app: None,
             reordering_size_audio: config.reordering_size_audio,
             reordering_size_video: config.reordering_size_video,
+            red_distance: config.red_distance,
+            flexfec_window: config.flexfec_window,
+            ulpfec_window: config.ulpfec_window,
             send_buffer_audio: config.send_buffer_audio,
             send_buffer_video: config.send_buffer_video,
             exts: config.exts.clone(),
 
             // Both sending and receiving starts from the configured codecs.
             // These can then be changed in the SDP OFFER/ANSWER dance.
-            codec_config: config.codec_config.clone(),
+            codec_config,
 
+            srtp_crypto: config
+                .srtp_crypto
+                .clone()
+                .unwrap_or_else(|| Arc::new(DefaultSrtpCrypto)),
+            srtp_crypto_configured: config.srtp_crypto.is_some(),
             srtp_rx: None,
+            srtp_rx_previous: None,
             srtp_tx: None,
+            fec_rx: has_fec.then(FecDecoder::new),
             last_nack: already_happened(),
             last_twcc: already_happened(),
             twcc: 0,
@@ -149,18 +284,43 @@ impl Session {
             twcc_tx_register: TwccSendRegister::new(1000),
             bwe,
             enable_twcc_feedback: false,
+            disable_twcc_feedback: false,
             pacer,
-            poll_packet_buf: vec![0; 2000],
-            pending_packet: None,
+            poll_packet_buf,
+            pending_packets: VecDeque::new(),
             ice_lite: config.ice_lite,
             rtp_mode: config.rtp_mode,
             feedback_tx: VecDeque::new(),
+            rtcp_pacer: config.rtcp_pacing.map(RtcpPacer::new),
             feedback_rx: VecDeque::new(),
             raw_packets: if config.enable_raw_packets {
                 Some(VecDeque::new())
             } else {
                 None
             },
+            cname: config.cname.clone(),
+            warnings: VecDeque::new(),
+            limits: LimitState::new(config.limits),
+            rtcp_rsize: config.rtcp_rsize,
+            rtcp_rsize_remote: None,
+            remote_sctp: None,
+            local_max_message_size: config.sctp_max_message_size,
+            data_channel_only: config.data_channel_only,
+            extmap_allow_mixed: config.extmap_allow_mixed,
+            extmap_allow_mixed_remote: None,
+            rtp_leniency: config.rtp_leniency,
+            rtp_leniency_counters: RtpLeniencyCounters::default(),
+            roc_recovery: config.roc_recovery,
+            roc_recovery_counters: RocRecoveryCounters::default(),
+            rx_authenticated: 0,
+            rx_authenticated_at_keying: 0,
+            rx_fresh: 0,
+            srtcp_index_rx: HashMap::new(),
+            srtp_error_counters: SrtpErrorCounters::default(),
+            send_backpressure: config.send_backpressure,
+            queued_per_mid: vec![],
+            congestion_window: config.congestion_window,
+            remote_bitrate_limits: VecDeque::new(),
         }
     }
 
@@ -168,6 +328,66 @@ impl Session {
         self.id
     }
 
+    /// Whether to put a=rtcp-rsize in the local SDP.
+    pub fn rtcp_rsize_in_sdp(&self) -> bool {
+        self.rtcp_rsize && self.rtcp_rsize_remote != Some(false)
+    }
+
+    pub fn set_rtcp_rsize_remote(&mut self, enabled: bool) {
+        self.rtcp_rsize_remote = Some(enabled);
+    }
+
+    /// Whether reduced-size RTCP is used.
+    ///
+    /// Without SDP there is no negotiation, and the local config decides.
+    pub fn is_rtcp_rsize(&self) -> bool {
+        self.rtcp_rsize && self.rtcp_rsize_remote != Some(false)
+    }
+
+    /// Whether to put a=extmap-allow-mixed in the local SDP.
+    ///
+    /// Not once the remote SDP left it out, which includes the answer to such an offer.
+    pub fn extmap_allow_mixed_in_sdp(&self) -> bool {
+        self.extmap_allow_mixed && self.extmap_allow_mixed_remote != Some(false)
+    }
+
+    pub fn set_extmap_allow_mixed_remote(&mut self, enabled: bool) {
+        self.extmap_allow_mixed_remote = Some(enabled);
+    }
+
+    /// Whether the two-byte RTP header extension form can be sent.
+    ///
+    /// Without SDP there is no negotiation, and the local config decides.
+    pub fn is_extmap_allow_mixed(&self) -> bool {
+        self.extmap_allow_mixed && self.extmap_allow_mixed_remote != Some(false)
+    }
+
+    /// Queue a warning to be emitted as [`Event::Warning`].
+    pub fn push_warning(&mut self, warning: Warning) {
+        self.warnings.push_back(warning);
+    }
+
+    /// Number of mids, including the one for data channels.
+    pub fn mid_count(&self) -> usize {
+        self.medias.len() + self.app.is_some() as usize
+    }
+
+    /// Check usage against the limits and warn when getting close.
+    pub fn update_limits(&mut self, channel_count: usize) {
+        let usage = [
+            (Limit::Mids, self.mid_count()),
+            (Limit::Ssrcs, self.streams.stream_count()),
+            (Limit::Channels, channel_count),
+        ];
+
+        for (limit, count) in usage {
+            if let Some(w) = self.limits.update(limit, count) {
+                warn!("{}", w);
+                self.warnings.push_back(w);
+            }
+        }
+    }
+
     pub fn set_app(&mut self, mid: Mid, index: usize) -> Result<(), String> {
         if let Some((mid_existing, index_existing)) = self.app {
             if mid_existing != mid {
@@ -188,20 +408,51 @@ impl Session {
 
     pub fn set_keying_material(
         &mut self,
+        now: Instant,
         mat: KeyingMaterial,
         srtp_profile: SrtpProfile,
         active: bool,
     ) {
         // TODO: rename this to `initialise_srtp_context`?
+        if self.data_channel_only {
+            return;
+        }
+
         // Whether we're active or passive determines if we use the left or right
         // hand side of the key material to derive input/output.
         let left = active;
 
-        self.srtp_rx = Some(SrtpContext::new(srtp_profile, &mat, !left));
-        self.srtp_tx = Some(SrtpContext::new(srtp_profile, &mat, left));
+        // On a re-key, the remote peer might still have packets in flight under the old keys.
+        if let Some(previous) = self.srtp_rx.take() {
+            debug!("Re-key SRTP");
+            self.srtp_rx_previous = Some((previous, now + SRTP_REKEY_GRACE));
+        }
+
+        self.rx_authenticated_at_keying = self.rx_authenticated;
+        self.srtcp_index_rx.clear();
+
+        let crypto = &*self.srtp_crypto;
+        self.srtp_rx = Some(SrtpContext::with_crypto(crypto, srtp_profile, &mat, !left));
+        self.srtp_tx = Some(SrtpContext::with_crypto(crypto, srtp_profile, &mat, left));
+    }
+
+    /// Use the SRTP ciphers of the DTLS certificate's crypto backend, unless configured.
+    pub fn set_dtls_srtp_crypto(&mut self, crypto: Arc<dyn SrtpCrypto>) {
+        if !self.srtp_crypto_configured {
+            self.srtp_crypto = crypto;
+        }
     }
 
     pub fn handle_timeout(&mut self, now: Instant) -> Result<(), RtcError> {
+        if self.data_channel_only {
+            return Ok(());
+        }
+
+        if matches!(&self.srtp_rx_previous, Some((_, until)) if now >= *until) {
+            debug!("Drop SRTP keys from before re-key");
+            self.srtp_rx_previous = None;
+        }
+
         // Payload any waiting samples
         self.do_payload(now)?;
 
@@ -215,6 +466,7 @@ impl Session {
             do_nack,
             &self.medias,
             &self.codec_config,
+            self.twcc_tx_register.last_rtt(),
             &mut self.feedback_tx,
         );
 
@@ -230,6 +482,16 @@ impl Session {
             }
         }
 
+        if self.feedback_tx.len() > MAX_FEEDBACK_TX {
+            // Dropping the newest is fine, since NACK is repeated from the receive
+            // registers and the reports are sent regularly.
+            debug!(
+                "Drop {} RTCP packets held back by pacing",
+                self.feedback_tx.len() - MAX_FEEDBACK_TX
+            );
+            self.feedback_tx.truncate(MAX_FEEDBACK_TX);
+        }
+
         if let Some(bwe) = self.bwe.as_mut() {
             bwe.handle_timeout(now);
         }
@@ -238,9 +500,74 @@ impl Session {
     }
 
     fn update_queue_state(&mut self, now: Instant) {
-        let iter = self.streams.streams_tx().map(|m| m.queue_state(now));
+        self.update_congestion();
+
+        let threshold = self.send_backpressure;
+        let target = self.bwe.as_ref().and_then(|b| b.last_estimate());
+
+        let mut queued_per_mid = std::mem::take(&mut self.queued_per_mid);
+        queued_per_mid.clear();
 
-        let Some(padding_request) = self.pacer.handle_timeout(now, iter) else {
+        let iter = self
+            .streams
+            .streams_tx()
+            .map(|m| m.queue_state(now))
+            .inspect(|s| {
+                if threshold.is_none() {
+                    return;
+                }
+
+                // How long until the queue is drained, either observed from the oldest
+                // unsent packet, or estimated from the queue size and the BWE target.
+                let waited = s
+                    .snapshot
+                    .first_unsent
+                    .map(|t| now.saturating_duration_since(t))
+                    .unwrap_or_default();
+                let drain = target
+                    .filter(|t| t.as_f64() > 0.0)
+                    .map(|t| DataSize::bytes(s.snapshot.size as u64) / t)
+                    .unwrap_or_default();
+                let queued = waited.max(drain);
+
+                match queued_per_mid.iter_mut().find(|(m, _)| *m == s.mid) {
+                    Some((_, q)) => *q = (*q).max(queued),
+                    None => queued_per_mid.push((s.mid, queued)),
+                }
+            });
+
+        let padding_request = self.pacer.handle_timeout(now, iter);
+
+        if let Some(threshold) = threshold {
+            for media in &mut self.medias {
+                let queued = queued_per_mid
+                    .iter()
+                    .find(|(m, _)| *m == media.mid())
+                    .map(|(_, q)| *q)
+                    .unwrap_or_default();
+
+                // Hysteresis to avoid flapping around the threshold.
+                let active = if media.is_backpressured() {
+                    queued > threshold / 2
+                } else {
+                    queued > threshold
+                };
+
+                media.set_backpressure(active, queued);
+            }
+        }
+
+        self.queued_per_mid = queued_per_mid;
+
+        if let Some(avg_queue_time) = self.pacer.poll_queue_overflow() {
+            warn!(
+                "Pacer queue overflow, average queue time: {:?}",
+                avg_queue_time
+            );
+            self.push_warning(Warning::PacerQueueOverflow { avg_queue_time });
+        }
+
+        let Some(padding_request) = padding_request else {
             return;
         };
 
@@ -252,6 +579,25 @@ impl Session {
         stream.generate_padding(padding_request.padding);
     }
 
+    fn update_congestion(&mut self) {
+        let Some(extra) = self.congestion_window else {
+            return;
+        };
+
+        // The window is derived from the BWE target and the RTT, both which we only have
+        // once TWCC feedback has started arriving.
+        let target = self.bwe.as_ref().and_then(|b| b.last_estimate());
+        let rtt = self.twcc_tx_register.last_rtt();
+        let (Some(target), Some(rtt)) = (target, rtt) else {
+            return;
+        };
+
+        let window = (target * (rtt + extra)).max(MIN_CONGESTION_WINDOW);
+        let congested = self.twcc_tx_register.in_flight() >= window;
+
+        self.pacer.set_congested(congested);
+    }
+
     fn create_twcc_feedback(&mut self, sender_ssrc: Ssrc, now: Instant) -> Option<()> {
         self.last_twcc = now;
         let mut twcc = self.twcc_rx_register.build_report(DATAGRAM_MTU - 100)?;
@@ -267,7 +613,12 @@ impl Session {
     }
 
     pub fn handle_rtp_receive(&mut self, now: Instant, message: &[u8]) {
-        let Some(header) = RtpHeader::parse(message, &self.exts) else {
+        let Some(header) = RtpHeader::parse_lenient(
+            message,
+            &self.exts,
+            &self.rtp_leniency,
+            &mut self.rtp_leniency_counters,
+        ) else {
             trace!("Failed to parse RTP header");
             return;
         };
@@ -282,6 +633,44 @@ impl Session {
         self.handle_rtcp(now, message);
     }
 
+    /// Count of incoming SRTP/SRTCP packets that passed authentication.
+    pub fn rx_authenticated(&self) -> u64 {
+        self.rx_authenticated
+    }
+
+    /// Count of incoming SRTP/SRTCP packets that passed authentication and were not
+    /// replayed. Only these may move a latched remote address.
+    pub fn rx_fresh(&self) -> u64 {
+        self.rx_fresh
+    }
+
+    fn count_unprotect_error(&mut self, e: UnprotectError, rtcp: bool) {
+        let kind = if rtcp { "SRTCP" } else { "SRTP" };
+        let c = &mut self.srtp_error_counters;
+
+        match e {
+            UnprotectError::TooShort => {
+                debug!("{} too short", kind);
+                c.too_short += 1;
+            }
+            UnprotectError::Auth if self.rx_authenticated == self.rx_authenticated_at_keying => {
+                debug!(
+                    "{} failed authentication, no packet authenticated yet",
+                    kind
+                );
+                c.wrong_key += 1;
+            }
+            UnprotectError::Auth => {
+                debug!("{} failed authentication", kind);
+                c.auth_failed += 1;
+            }
+            UnprotectError::Decrypt => {
+                debug!("{} failed decryption", kind);
+                c.decrypt_failed += 1;
+            }
+        }
+    }
+
     fn mid_and_ssrc_for_header(&mut self, now: Instant, header: &RtpHeader) -> Option<(Mid, Ssrc)> {
         let ssrc_header = header.ssrc;
 
@@ -316,11 +705,23 @@ impl Session {
             return;
         };
 
+        // A new stream must fit within the limits.
+        let is_new = !self.streams.has_stream_rx_by_mid_rid(mid, rid);
+        if is_new
+            && !self
+                .limits
+                .allows(Limit::Ssrcs, self.streams.stream_count() + 1)
+        {
+            trace!("Refuse new stream above limit: {} {:?}", mid, rid);
+            if let Some(w) = self.limits.refused(Limit::Ssrcs) {
+                warn!("{}", w);
+                self.warnings.push_back(w);
+            }
+            return;
+        }
+
         // Figure out which payload the PT maps to. Either main or RTX.
-        let maybe_payload = self
-            .codec_config
-            .iter()
-            .find(|p| p.pt() == header.payload_type || p.resend() == Some(header.payload_type));
+        let maybe_payload = main_payload_params(&self.codec_config, header.payload_type);
 
         // If we don't find it, bail out.
         let Some(payload) = maybe_payload else {
@@ -335,7 +736,7 @@ impl Session {
                 .map_dynamic_by_rid(header.ssrc, mid, rid, media, *payload, is_main);
         } else {
             // Case B - the payload type identifies RTX.
-            let is_main = payload.pt() == header.payload_type;
+            let is_main = payload.resend() != Some(header.payload_type);
 
             self.streams
                 .map_dynamic_by_pt(header.ssrc, mid, media, *payload, is_main);
@@ -348,9 +749,15 @@ impl Session {
 
         trace!("Handle RTP: {:?}", header);
 
+        if self.is_flexfec(header.payload_type) {
+            self.handle_flexfec(now, header, buf);
+            return;
+        }
+
         // The ssrc is the _main_ ssrc (no the rtx, that might be in the header).
         let Some((mid, ssrc)) = self.mid_and_ssrc_for_header(now, &header) else {
             debug!("No mid/SSRC for header: {:?}", header);
+            self.srtp_error_counters.unknown_ssrc += 1;
             return;
         };
 
@@ -362,8 +769,7 @@ impl Session {
             }
         };
 
-        // Both of these unwraps are fine because mid_and_ssrc_for_header guarantees it.
-        let media = self.medias.iter_mut().find(|m| m.mid() == mid).unwrap();
+        // This unwrap is fine because mid_and_ssrc_for_header guarantees it.
         let stream = self.streams.stream_rx(&ssrc).unwrap();
 
         let params = match main_payload_params(&self.codec_config, header.payload_type) {
@@ -376,34 +782,121 @@ impl Session {
                 return;
             }
         };
-        let clock_rate = params.spec().clock_rate;
-        let pt = params.pt();
-        let is_repair = pt != header.payload_type;
+        let is_repair = params.resend() == Some(header.payload_type);
+        let codec = params.spec().codec;
+
+        // FlexFEC protects the video media packets, ULPFEC the ones wrapped in RED.
+        let fec_protected = !is_repair && (codec.is_video() || codec == Codec::Red);
 
         // is_repair controls whether update is updating the main register or the RTX register.
         // Either way we get a seq_no_outer which is used to decrypt the SRTP.
         let mut seq_no = stream.extend_seq(&header, is_repair);
 
-        let mut data = match srtp.unprotect_rtp(buf, &header, *seq_no) {
-            Some(v) => v,
-            None => {
+        let decrypted = srtp.try_unprotect_rtp(buf, &header, *seq_no).or_else(|e| {
+            let Some((previous, _)) = self.srtp_rx_previous.as_mut().filter(|(_, u)| now < *u)
+            else {
+                return Err(e);
+            };
+            // Report the error for the current keys.
+            previous.unprotect_rtp(buf, &header, *seq_no).ok_or(e)
+        });
+
+        let data = match decrypted {
+            Ok(v) => v,
+            Err(UnprotectError::Auth) if self.roc_recovery > 0 => {
+                match srtp.unprotect_rtp_recover_roc(buf, &header, *seq_no, self.roc_recovery) {
+                    Some((v, index)) => {
+                        debug!(
+                            "Recovered SRTP ROC for {:?}: {} -> {}",
+                            header.ssrc,
+                            *seq_no >> 16,
+                            index >> 16
+                        );
+                        self.roc_recovery_counters.recovered += 1;
+                        seq_no = stream.resync_roc(index.into(), is_repair);
+                        v
+                    }
+                    None => {
+                        self.roc_recovery_counters.failed += 1;
+                        trace!("Failed to unprotect SRTP, also with adjacent ROC");
+                        self.count_unprotect_error(UnprotectError::Auth, false);
+                        return;
+                    }
+                }
+            }
+            Err(e) => {
                 trace!("Failed to unprotect SRTP");
+                self.count_unprotect_error(e, false);
                 return;
             }
         };
 
-        if header.has_padding && !RtpHeader::unpad_payload(&mut data) {
-            // Unpadding failed. Broken data?
-            trace!("unpadding of unprotected payload failed");
-            return;
+        self.rx_authenticated += 1;
+
+        let raw_header = &buf[..header.header_len];
+
+        // Keep the media for recovering packets lost later on. RED is kept when
+        // unwrapped further down.
+        if let Some(fec) = self
+            .fec_rx
+            .as_mut()
+            .filter(|_| codec.is_video() && !is_repair)
+        {
+            fec.insert_media(header.ssrc, raw_header, &data);
         }
 
-        if let Some(raw_packets) = &mut self.raw_packets {
-            raw_packets.push_back(Box::new(RawPacket::RtpRx(header.clone(), data.clone())));
+        self.handle_rtp_unprotected(now, mid, ssrc, header, data, seq_no, Some(raw_header));
+
+        if fec_protected {
+            self.recover_fec(now);
+        }
+    }
+
+    /// Handle an RTP packet after SRTP, either received or recovered with FEC.
+    ///
+    /// `raw_header` is the RTP header as received, and `None` for recovered packets.
+    fn handle_rtp_unprotected(
+        &mut self,
+        now: Instant,
+        mid: Mid,
+        ssrc: Ssrc,
+        mut header: RtpHeader,
+        mut data: Vec<u8>,
+        mut seq_no: SeqNo,
+        raw_header: Option<&[u8]>,
+    ) {
+        let recovered = raw_header.is_none();
+
+        // Both of these unwraps are fine because the caller found the mid and SSRC.
+        let media = self.medias.iter_mut().find(|m| m.mid() == mid).unwrap();
+        let stream = self.streams.stream_rx(&ssrc).unwrap();
+
+        let Some(params) = main_payload_params(&self.codec_config, header.payload_type) else {
+            return;
+        };
+        let clock_rate = stream
+            .clock_rate_override()
+            .unwrap_or(params.spec().clock_rate);
+        let pt = params.pt();
+        let mut codec = params.spec().codec;
+        let is_repair = params.resend() == Some(header.payload_type);
+
+        if header.has_padding && !RtpHeader::unpad_payload(&mut data) {
+            // Unpadding failed. Broken data?
+            self.rtp_leniency_counters.padding_violations += 1;
+            if !self.rtp_leniency.padding_violation {
+                trace!("unpadding of unprotected payload failed");
+                return;
+            }
+            trace!("Ignoring invalid padding of unprotected payload");
         }
 
-        // Mark as received for TWCC purposes
-        if let Some(transport_cc) = header.ext_vals.transport_cc {
+        // Mark as received for TWCC purposes. A recovered packet was not received.
+        let transport_cc = header
+            .ext_vals
+            .transport_cc
+            .filter(|_| !self.disable_twcc_feedback && !recovered);
+        if let Some(transport_cc) = transport_cc {
             let prev = self.twcc_rx_register.max_seq();
             let extended = extend_u16(Some(*prev), transport_cc);
             self.twcc_rx_register.update_seq(extended.into(), now);
@@ -412,6 +905,31 @@ impl Session {
         // Register reception in nack registers.
         let receipt_outer = stream.update_register(now, &header, clock_rate, is_repair, seq_no);
 
+        if !receipt_outer.is_new_packet {
+            if recovered {
+                // Arrived after all, or recovered already.
+                return;
+            }
+            debug!("SRTP replayed {:?} {:?}", header.ssrc, seq_no);
+            self.srtp_error_counters.replayed += 1;
+        } else if !recovered {
+            self.rx_fresh += 1;
+        }
+
+        if recovered {
+            stream.increase_packets_recovered();
+        }
+
+        // A blocked stream keeps the transport state going, but no media must leak further.
+        if stream.is_blocked() {
+            trace!("Drop RTP for blocked stream: {:?}", header.ssrc);
+            return;
+        }
+
+        if let Some(raw_packets) = &mut self.raw_packets {
+            raw_packets.push_back(Box::new(RawPacket::RtpRx(header.clone(), data.clone())));
+        }
+
         // RTX packets must be rewritten to be a normal packet. This only changes the
         // the seq_no, however MediaTime might be different when interpreted against the
         // the "main" register.
@@ -439,15 +957,114 @@ impl Session {
             receipt_outer
         };
 
-        let packet = stream.handle_rtp(now, header, data, seq_no, receipt.time);
+        // FEC is only valid where it is expected, in RED or as the FlexFEC stream.
+        if matches!(codec, Codec::Ulpfec | Codec::FlexFec) {
+            trace!("Drop bare {} packet: {:?}", codec, header);
+            return;
+        }
+
+        // RED wraps the media with copies of the packets right before it (audio), or
+        // wraps either the media or the ULPFEC protecting it (video).
+        let mut redundant = vec![];
+        if codec == Codec::Red {
+            let Some(blocks) = RedBlock::parse(&data).ok().filter(|b| !b.is_empty()) else {
+                trace!("Bad RED payload: {:?}", header);
+                return;
+            };
+
+            let primary = blocks.len() - 1;
+            let block_pt = blocks[primary].pt;
+
+            for (i, block) in blocks[..primary].iter().enumerate() {
+                if block.pt != block_pt || block.data.is_empty() {
+                    continue;
+                }
+
+                let back = (primary - i) as u64;
+                let Some(redundant_seq_no) = seq_no.checked_sub(back) else {
+                    continue;
+                };
+
+                let mut redundant_header = header.clone();
+                redundant_header.payload_type = block_pt;
+                redundant_header.has_padding = false;
+                redundant_header.sequence_number = redundant_seq_no as u16;
+                redundant_header.timestamp = header.timestamp.wrapping_sub(block.timestamp_offset);
+
+                redundant.push((
+                    redundant_header,
+                    block.data.to_vec(),
+                    redundant_seq_no.into(),
+                ));
+            }
+
+            let block = blocks[primary].data.to_vec();
+
+            header.payload_type = block_pt;
+            header.has_padding = false;
+            data = block;
+
+            let is_ulpfec = self
+                .codec_config
+                .iter()
+                .any(|p| p.spec().codec == Codec::Ulpfec && p.pt() == block_pt);
+
+            if is_ulpfec {
+                if let Some(fec) = self.fec_rx.as_mut() {
+                    if !fec.insert_ulpfec(header.ssrc, &data) {
+                        debug!("Bad ULPFEC packet: {:?}", header);
+                    }
+                }
+                return;
+            }
+
+            let Some(params) = main_payload_params(&self.codec_config, block_pt) else {
+                trace!("No payload params for PT in RED: {:?}", block_pt);
+                return;
+            };
+            codec = params.spec().codec;
+
+            if codec.is_video_protection() {
+                trace!("Drop {} packet wrapped in RED: {:?}", codec, header);
+                return;
+            }
+
+            // The media is protected as it was before wrapping in RED.
+            let raw_header = raw_header.filter(|_| !is_repair && codec.is_video());
+            if let (Some(fec), Some(raw_header)) = (self.fec_rx.as_mut(), raw_header) {
+                let mut unwrapped = raw_header.to_vec();
+                unwrapped[0] &= !0x20;
+                unwrapped[1] = (unwrapped[1] & 0x80) | *block_pt;
+                fec.insert_media(header.ssrc, &unwrapped, &data);
+            }
+        }
+
+        // An audio sender in DTX, or sending comfort noise, is silent rather than paused.
+        let is_silence = match codec {
+            Codec::Cn => true,
+            Codec::Opus => is_dtx(&data),
+            _ => false,
+        };
+        if is_silence && receipt.is_new_packet {
+            stream.set_silent();
+        }
+
+        let mut packet = stream.handle_rtp(now, header, data, seq_no, receipt.time);
+        packet.end_of_frame = is_end_of_frame(codec, &packet.header, &packet.payload);
+
+        // Telephone events become Event::Dtmf rather than MediaData.
+        let is_dtmf = codec == Codec::TelephoneEvent;
+        if is_dtmf {
+            media.handle_dtmf(stream.rid(), &packet);
+        }
 
         if self.rtp_mode {
             // In RTP mode, we store the packet temporarily here for the next poll_output().
             // However only if this is a packet not seen before. This filters out spurious resends for padding.
             if receipt.is_new_packet {
-                self.pending_packet = Some(packet);
+                self.pending_packets.push_back(packet);
             }
-        } else {
+        } else if !is_dtmf {
             // In non-RTP mode, we let the Media use a Depayloader.
             media.depayload(
                 stream.rid(),
@@ -457,21 +1074,179 @@ impl Session {
                 &self.codec_config,
             );
         }
+
+        // The redundant blocks are handled like packets recovered with FEC, which
+        // drops the ones already received.
+        for (header, data, seq_no) in redundant {
+            self.handle_rtp_unprotected(now, mid, ssrc, header, data, seq_no, None);
+        }
+    }
+
+    fn is_flexfec(&self, pt: Pt) -> bool {
+        self.fec_rx.is_some()
+            && self
+                .codec_config
+                .iter()
+                .any(|p| p.spec().codec == Codec::FlexFec && p.pt() == pt)
+    }
+
+    fn handle_flexfec(&mut self, now: Instant, header: RtpHeader, buf: &[u8]) {
+        let (Some(srtp), Some(fec)) = (self.srtp_rx.as_mut(), self.fec_rx.as_mut()) else {
+            return;
+        };
+
+        // The FEC packets are in their own sequence number series.
+        let seq_no = fec.extend_seq(&header);
+
+        let decrypted = srtp.try_unprotect_rtp(buf, &header, *seq_no).or_else(|e| {
+            let Some((previous, _)) = self.srtp_rx_previous.as_mut().filter(|(_, u)| now < *u)
+            else {
+                return Err(e);
+            };
+            previous.unprotect_rtp(buf, &header, *seq_no).ok_or(e)
+        });
+
+        let mut data = match decrypted {
+            Ok(v) => v,
+            Err(e) => {
+                trace!("Failed to unprotect SRTP FlexFEC");
+                self.count_unprotect_error(e, false);
+                return;
+            }
+        };
+
+        self.rx_authenticated += 1;
+
+        if header.has_padding && !RtpHeader::unpad_payload(&mut data) {
+            trace!("unpadding of FlexFEC payload failed");
+            return;
+        }
+
+        if let Some(raw_packets) = &mut self.raw_packets {
+            raw_packets.push_back(Box::new(RawPacket::RtpRx(header.clone(), data.clone())));
+        }
+
+        // Unwrap is fine, we checked it above.
+        let fec = self.fec_rx.as_mut().unwrap();
+
+        // The FEC packet points out the media SSRC it protects.
+        let Some(ssrc_media) = fec.insert_flexfec(&data) else {
+            debug!("Bad FlexFEC packet: {:?}", header);
+            return;
+        };
+
+        // The FEC packets count towards TWCC, like the media.
+        let transport_cc = header
+            .ext_vals
+            .transport_cc
+            .filter(|_| !self.disable_twcc_feedback);
+
+        if let Some(transport_cc) = transport_cc {
+            let prev = self.twcc_rx_register.max_seq();
+            let extended = extend_u16(Some(*prev), transport_cc);
+            self.twcc_rx_register.update_seq(extended.into(), now);
+        }
+
+        self.recover_fec(now);
+    }
+
+    fn recover_fec(&mut self, now: Instant) {
+        loop {
+            let Some(fec) = self.fec_rx.as_mut() else {
+                return;
+            };
+
+            let Some(packet) = fec.poll_recovered() else {
+                return;
+            };
+
+            let Some(mut header) = RtpHeader::parse(&packet, &self.exts) else {
+                continue;
+            };
+            header.ext_vals.update_absolute_send_time(now);
+
+            let Some((mid, ssrc)) = self.mid_and_ssrc_for_header(now, &header) else {
+                continue;
+            };
+
+            trace!("Recovered RTP with FEC: {:?}", header);
+
+            // Unwrap is fine because mid_and_ssrc_for_header guarantees it.
+            let stream = self.streams.stream_rx(&ssrc).unwrap();
+
+            // Recovering media for a blocked stream would leak it past the block.
+            if stream.is_blocked() {
+                trace!("Drop recovered RTP for blocked stream: {:?}", ssrc);
+                continue;
+            }
+
+            let seq_no = stream.extend_seq(&header, false);
+
+            let data = packet[header.header_len..].to_vec();
+
+            self.handle_rtp_unprotected(now, mid, ssrc, header, data, seq_no, None);
+        }
     }
 
     fn handle_rtcp(&mut self, now: Instant, buf: &[u8]) -> Option<()> {
         let srtp: &mut SrtpContext = self.srtp_rx.as_mut()?;
-        let unprotected = srtp.unprotect_rtcp(buf)?;
+        // The SRTCP index is only tracked for the current keys.
+        let mut srtcp_index = None;
+        let unprotected = match srtp.try_unprotect_rtcp(buf) {
+            Ok(v) => {
+                srtcp_index = srtp.srtcp_index(buf);
+                Ok(v)
+            }
+            Err(e) => match self.srtp_rx_previous.as_mut().filter(|(_, u)| now < *u) {
+                Some((previous, _)) => previous.unprotect_rtcp(buf).ok_or(e),
+                None => Err(e),
+            },
+        };
+
+        let unprotected = match unprotected {
+            Ok(v) => v,
+            Err(e) => {
+                self.count_unprotect_error(e, true);
+                return None;
+            }
+        };
+
+        self.rx_authenticated += 1;
+
+        // SRTCP has no replay protection of its own. A packet is only fresh if its
+        // index is higher than any seen before from the same sender.
+        if let (Some(index), Some(ssrc)) = (srtcp_index, unprotected.get(4..8)) {
+            let ssrc: Ssrc = u32::from_be_bytes(ssrc.try_into().unwrap()).into();
+            match self.srtcp_index_rx.get(&ssrc) {
+                Some(highest) if index <= *highest => {
+                    debug!("SRTCP replayed {:?} {}", ssrc, index);
+                    self.srtp_error_counters.replayed += 1;
+                }
+                _ => {
+                    self.srtcp_index_rx.insert(ssrc, index);
+                    self.rx_fresh += 1;
+                }
+            }
+        }
 
         Rtcp::read_packet(&unprotected, &mut self.feedback_rx);
         let mut need_configure_pacer = false;
 
         if let Some(raw_packets) = &mut self.raw_packets {
+            raw_packets.push_back(Box::new(RawPacket::RtcpCompoundRx(RawRtcp {
+                packets: Rtcp::summarize(&unprotected),
+                data: unprotected,
+            })));
             for fb in &self.feedback_rx {
                 raw_packets.push_back(Box::new(RawPacket::RtcpRx(fb.clone())));
             }
         }
 
+        for rtcp in &self.feedback_rx {
+            self.remote_bitrate_limits
+                .extend(remote_bitrate_limits(&self.streams, rtcp));
+        }
+
         for fb in RtcpFb::from_rtcp(self.feedback_rx.drain(..)) {
             if let RtcpFb::Twcc(twcc) = fb {
                 trace!("Handle TWCC: {:?}", twcc);
@@ -521,6 +1296,10 @@ impl Session {
             )));
         }
 
+        if let Some(warning) = self.warnings.pop_front() {
+            return Some(Event::Warning(warning));
+        }
+
         // If we're not ready to flow media, don't send any events.
         if !self.ready_for_srtp() {
             return None;
@@ -532,14 +1311,18 @@ impl Session {
             }
         }
 
-        // This must be before pending_packet.take() since we need to emit the unpaused event
+        // This must be before pending_packets.pop_front() since we need to emit the unpaused event
         // before the first packet causing the unpause.
         if let Some(paused) = self.streams.poll_stream_paused() {
             return Some(Event::StreamPaused(paused));
         }
 
+        if let Some(allocation) = self.streams.poll_layers_allocation() {
+            return Some(Event::LayersAllocation(allocation));
+        }
+
         if self.rtp_mode {
-            if let Some(packet) = self.pending_packet.take() {
+            if let Some(packet) = self.pending_packets.pop_front() {
                 return Some(Event::RtpPacket(packet));
             }
         }
@@ -552,6 +1335,10 @@ impl Session {
             return Some(Event::EgressBitrateEstimate(BweKind::Remb(mid, bitrate)));
         }
 
+        if let Some(limit) = self.remote_bitrate_limits.pop_front() {
+            return Some(Event::RemoteBitrateLimit(limit));
+        }
+
         for media in &mut self.medias {
             if media.need_open_event {
                 media.need_open_event = false;
@@ -564,6 +1351,14 @@ impl Session {
                 }));
             }
 
+            if let Some(dtmf) = media.poll_dtmf() {
+                return Some(Event::Dtmf(dtmf));
+            }
+
+            if let Some(backpressure) = media.pending_backpressure.take() {
+                return Some(Event::SendBackpressure(backpressure));
+            }
+
             if media.need_changed_event {
                 media.need_changed_event = false;
                 return Some(Event::MediaChanged(MediaChanged {
@@ -597,12 +1392,12 @@ impl Session {
 
     pub fn poll_datagram(&mut self, now: Instant) -> Option<net::DatagramSend> {
         // Time must have progressed forward from start value.
-        if now == already_happened() {
+        if now == already_happened() || self.data_channel_only {
             return None;
         }
 
         let x = None
-            .or_else(|| self.poll_feedback())
+            .or_else(|| self.poll_feedback(now))
             .or_else(|| self.poll_packet(now));
 
         if let Some(x) = &x {
@@ -611,17 +1406,24 @@ impl Session {
             // str0m does the RTP packetization.
             if !self.rtp_mode && x.len() > DATAGRAM_MTU_WARN {
                 warn!("RTP above MTU {}: {}", DATAGRAM_MTU_WARN, x.len());
+                self.push_warning(Warning::RtpAboveMtu(x.len()));
             }
         }
 
         x
     }
 
-    fn poll_feedback(&mut self) -> Option<net::DatagramSend> {
+    fn poll_feedback(&mut self, now: Instant) -> Option<net::DatagramSend> {
         if self.feedback_tx.is_empty() {
             return None;
         }
 
+        if let Some(pacer) = &mut self.rtcp_pacer {
+            if !pacer.can_send(now) {
+                return None;
+            }
+        }
+
         // Round to nearest multiple of 4 bytes.
         const ENCRYPTABLE_MTU: usize = (DATAGRAM_MTU - SRTCP_OVERHEAD) & !3;
         assert!(ENCRYPTABLE_MTU % 4 == 0);
@@ -635,7 +1437,14 @@ impl Session {
             }
         };
 
-        let len = Rtcp::write_packet(&mut self.feedback_tx, &mut data, output);
+        // Without reduced-size RTCP, every packet must be compound (start with SR/RR).
+        let compound = if self.is_rtcp_rsize() {
+            None
+        } else {
+            Some(self.streams.first_ssrc_local())
+        };
+
+        let len = Rtcp::write_packet(&mut self.feedback_tx, &mut data, compound, output);
 
         if len == 0 {
             return None;
@@ -643,9 +1452,23 @@ impl Session {
 
         data.truncate(len);
 
+        if let Some(raw_packets) = &mut self.raw_packets {
+            raw_packets.push_back(Box::new(RawPacket::RtcpCompoundTx(RawRtcp {
+                packets: Rtcp::summarize(&data),
+                data: data.clone(),
+            })));
+        }
+
         let srtp = self.srtp_tx.as_mut()?;
         let protected = srtp.protect_rtcp(&data);
 
+        // UDP/IPv4 header overhead is part of the RTCP bandwidth calculation.
+        self.streams.record_rtcp_size(protected.len() + 28);
+
+        if let Some(pacer) = &mut self.rtcp_pacer {
+            pacer.sent(protected.len());
+        }
+
         assert!(
             protected.len() < DATAGRAM_MTU,
             "Encrypted SRTCP should be less than MTU"
@@ -668,12 +1491,21 @@ impl Session {
         let buf = &mut self.poll_packet_buf;
         let twcc_seq = self.twcc;
 
+        let flexfec_pt = media.flexfec_pt(&self.codec_config);
+        let ulpfec_pts = media.ulpfec_pts(&self.codec_config);
+
+        // ULPFEC takes sequence numbers from the media, which are set by the user in RTP mode.
+        let ulpfec_window = if self.rtp_mode { 0 } else { self.ulpfec_window };
+
         // TODO: allow for sending simulcast
         let stream = self.streams.stream_tx_by_mid_rid(media.mid(), None)?;
+        stream.set_flexfec(flexfec_pt, self.flexfec_window);
+        stream.set_ulpfec(ulpfec_pts, ulpfec_window);
 
         let params = &self.codec_config;
-        let exts = media.remote_extmap();
-        let receipt = stream.poll_packet(now, exts, &mut self.twcc, params, buf)?;
+        let exts = media.extmap_tx();
+        let allow_two_byte = self.is_extmap_allow_mixed();
+        let receipt = stream.poll_packet(now, exts, allow_two_byte, &mut self.twcc, params, buf)?;
 
         let PacketReceipt {
             header,
@@ -710,16 +1542,26 @@ impl Session {
     }
 
     pub fn poll_timeout(&mut self) -> (Option<Instant>, Reason) {
+        if self.data_channel_only {
+            return (None, Reason::NotHappening);
+        }
+
         let feedback_at = self.regular_feedback_at();
+        let keyframe_request_at = self
+            .streams
+            .keyframe_request_at(&self.medias, self.twcc_tx_register.last_rtt());
         let nack_at = self.nack_at();
         let twcc_at = self.twcc_at();
         let pacing_at = self.pacer.poll_timeout();
-        let packetize_at = self.medias.iter().flat_map(|m| m.poll_timeout()).next();
+        let packetize_at = self.medias.iter().flat_map(|m| m.poll_timeout()).min();
         let bwe_at = self.bwe.as_ref().map(|bwe| bwe.poll_timeout());
         let paused_at = self.paused_at();
         let send_stream_at = self.streams.send_stream();
+        let rtcp_pacing_at = self.rtcp_pacing_at();
+        let srtp_rekey_at = self.srtp_rx_previous.as_ref().map(|(_, until)| *until);
 
         (feedback_at, Reason::Feedback)
+            .soonest((keyframe_request_at, Reason::Feedback))
             .soonest((nack_at, Reason::Nack))
             .soonest((twcc_at, Reason::Twcc))
             .soonest((pacing_at, Reason::Pacing))
@@ -727,6 +1569,8 @@ impl Session {
             .soonest((bwe_at, Reason::Bwe))
             .soonest((paused_at, Reason::PauseCheck))
             .soonest((send_stream_at, Reason::SendStream))
+            .soonest((rtcp_pacing_at, Reason::Feedback))
+            .soonest((srtp_rekey_at, Reason::SrtpRekey))
     }
 
     pub fn has_mid(&self, mid: Mid) -> bool {
@@ -737,6 +1581,14 @@ impl Session {
         self.streams.regular_feedback_at()
     }
 
+    fn rtcp_pacing_at(&self) -> Option<Instant> {
+        if self.feedback_tx.is_empty() {
+            return None;
+        }
+
+        self.rtcp_pacer.as_ref()?.poll_timeout()
+    }
+
     fn paused_at(&self) -> Option<Instant> {
         self.streams.paused_at()
     }
@@ -751,7 +1603,7 @@ impl Session {
 
     fn twcc_at(&self) -> Option<Instant> {
         let is_receiving = self.streams.is_receiving();
-        if is_receiving && self.enable_twcc_feedback && self.twcc_rx_register.has_unreported() {
+        if is_receiving && self.twcc_feedback() && self.twcc_rx_register.has_unreported() {
             Some(self.last_twcc + TWCC_INTERVAL)
         } else {
             None
@@ -765,21 +1617,40 @@ impl Session {
         }
     }
 
+    pub fn set_disable_twcc_feedback(&mut self, disabled: bool) {
+        debug!("Disable TWCC feedback: {}", disabled);
+        self.disable_twcc_feedback = disabled;
+    }
+
+    pub fn twcc_feedback(&self) -> bool {
+        self.enable_twcc_feedback && !self.disable_twcc_feedback
+    }
+
     pub fn visit_stats(&mut self, now: Instant, snapshot: &mut StatsSnapshot) {
         for stream in self.streams.streams_tx() {
             stream.visit_stats(snapshot, now);
+            snapshot.egress_jitter = snapshot.egress_jitter.max(stream.jitter());
         }
 
         for stream in self.streams.streams_rx() {
             stream.visit_stats(snapshot, now);
+            snapshot.ingress_jitter = snapshot.ingress_jitter.max(stream.jitter());
         }
 
         snapshot.tx = snapshot.egress.values().map(|s| s.bytes).sum();
         snapshot.rx = snapshot.ingress.values().map(|s| s.bytes).sum();
         snapshot.bwe_tx = self.bwe.as_ref().and_then(|bwe| bwe.last_estimate());
+        snapshot.bwe_desired = self
+            .bwe
+            .as_ref()
+            .map(|bwe| bwe.desired_bitrate)
+            .filter(|b| *b > Bitrate::ZERO);
 
         snapshot.egress_loss_fraction = self.twcc_tx_register.loss(Duration::from_secs(1), now);
         snapshot.ingress_loss_fraction = self.twcc_rx_register.loss();
+        snapshot.rtp_leniency = self.rtp_leniency_counters;
+        snapshot.roc_recovery = self.roc_recovery_counters;
+        snapshot.srtp_errors = self.srtp_error_counters;
     }
 
     pub fn set_bwe_current_bitrate(&mut self, current_bitrate: Bitrate) {
@@ -806,7 +1677,19 @@ impl Session {
         self.medias.len() + if self.app.is_some() { 1 } else { 0 }
     }
 
-    pub fn add_media(&mut self, media: Media) {
+    pub fn cname(&self) -> Option<&str> {
+        self.cname.as_deref()
+    }
+
+    pub fn add_media(&mut self, mut media: Media) {
+        // A configured session CNAME overrides whatever the media was created with.
+        if let Some(cname) = &self.cname {
+            media.set_cname(cname.clone());
+        }
+        // Media can be declared in a data channel only session, but it never sends or receives.
+        if self.data_channel_only {
+            media.disable();
+        }
         self.medias.push(media);
     }
 
@@ -851,7 +1734,12 @@ impl Session {
 
     fn do_payload(&mut self, now: Instant) -> Result<(), RtcError> {
         for m in &mut self.medias {
-            m.do_payload(now, &mut self.streams, &self.codec_config)?;
+            m.do_payload(
+                now,
+                &mut self.streams,
+                &self.codec_config,
+                self.red_distance,
+            )?;
         }
 
         Ok(())
@@ -940,6 +1828,38 @@ impl Bwe {
     }
 }
 
+fn remote_bitrate_limits(streams: &Streams, rtcp: &Rtcp) -> Vec<RemoteBitrateLimit> {
+    let our = |ssrc: Ssrc| streams.mid_by_ssrc_tx(ssrc).map(|mid| (mid, ssrc));
+
+    match rtcp {
+        Rtcp::Remb(v) => {
+            let ssrcs: Vec<_> = v.ssrcs.iter().filter_map(|s| our((*s).into())).collect();
+            if ssrcs.is_empty() {
+                return vec![];
+            }
+            vec![RemoteBitrateLimit {
+                kind: RemoteBitrateLimitKind::Remb,
+                bitrate: Bitrate::from(v.bitrate as f64),
+                ssrcs,
+            }]
+        }
+        Rtcp::Tmmbr(v) => v
+            .entries
+            .iter()
+            .filter_map(|e| {
+                Some(RemoteBitrateLimit {
+                    kind: RemoteBitrateLimitKind::Tmmbr {
+                        overhead: e.overhead,
+                    },
+                    bitrate: Bitrate::from(e.bitrate),
+                    ssrcs: vec![our(e.ssrc)?],
+                })
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
 pub struct PacketReceipt {
     pub header: RtpHeader,
     pub seq_no: SeqNo,
@@ -952,3 +1872,71 @@ pub struct PacketReceipt {
 fn main_payload_params(c: &CodecConfig, pt: Pt) -> Option<&PayloadParams> {
     c.iter().find(|p| (p.pt == pt || p.resend == Some(pt)))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn srtp_previous_keys_expire() {
+        let now = Instant::now();
+        let profile = SrtpProfile::Aes128CmSha1_80;
+        let mat = |v: u8| KeyingMaterial::new(vec![v; 60]);
+
+        let mut session = Session::new(&RtcConfig::new());
+        session.set_keying_material(now, mat(1), profile, true);
+        session.set_keying_material(now, mat(2), profile, true);
+
+        let (at, reason) = session.poll_timeout();
+        assert_eq!(at, Some(now + SRTP_REKEY_GRACE));
+        assert_eq!(reason, Reason::SrtpRekey);
+
+        // The remote peer still sending a receiver report under the old keys.
+        let mut remote = SrtpContext::new(profile, &mat(1), false);
+        let rr = [0x80, 201, 0x00, 0x01, 0, 0, 0, 42];
+
+        let authenticated = session.rx_authenticated;
+        let packet = remote.protect_rtcp(&rr);
+        assert!(session.handle_rtcp(now, &packet).is_some());
+        assert_eq!(session.rx_authenticated, authenticated + 1);
+
+        let after = now + SRTP_REKEY_GRACE;
+        session.handle_timeout(after).unwrap();
+        assert!(session.srtp_rx_previous.is_none());
+        assert_ne!(session.poll_timeout().1, Reason::SrtpRekey);
+
+        let packet = remote.protect_rtcp(&rr);
+        assert!(session.handle_rtcp(after, &packet).is_none());
+        assert_eq!(session.rx_authenticated, authenticated + 1);
+    }
+
+    #[test]
+    fn srtcp_replay_is_not_fresh() {
+        let now = Instant::now();
+        let profile = SrtpProfile::Aes128CmSha1_80;
+        let mat = || KeyingMaterial::new(vec![1; 60]);
+
+        let mut session = Session::new(&RtcConfig::new());
+        session.set_keying_material(now, mat(), profile, true);
+
+        let mut remote = SrtpContext::new(profile, &mat(), false);
+        let rr = [0x80, 201, 0x00, 0x01, 0, 0, 0, 42];
+
+        let first = remote.protect_rtcp(&rr);
+        let second = remote.protect_rtcp(&rr);
+
+        assert!(session.handle_rtcp(now, &first).is_some());
+        assert_eq!(session.rx_fresh(), 1);
+
+        // A replayed packet still authenticates, but must not count as fresh.
+        for _ in 0..3 {
+            assert!(session.handle_rtcp(now, &first).is_some());
+        }
+        assert_eq!(session.rx_authenticated(), 4);
+        assert_eq!(session.rx_fresh(), 1);
+        assert_eq!(session.srtp_error_counters.replayed, 3);
+
+        assert!(session.handle_rtcp(now, &second).is_some());
+        assert_eq!(session.rx_fresh(), 2);
+    }
+}
@@ -1,17 +1,28 @@
 //! Statistics events.
 
+use std::fmt;
+use std::panic::RefUnwindSafe;
+use std::sync::Arc;
 use std::{
     collections::{HashMap, VecDeque},
     time::{Duration, Instant},
 };
 
-use crate::rtp_::{Mid, Rid};
+use crate::channel::ChannelId;
+use crate::rtp_::{Mid, Rid, RocRecoveryCounters, RtpLeniencyCounters, SrtpErrorCounters};
 use crate::Bitrate;
+use crate::Candidate;
 
 pub(crate) struct Stats {
     last_now: Option<Instant>,
     events: VecDeque<StatsEvent>,
     interval: Duration,
+    quality_scorer: Option<Arc<dyn QualityScorer>>,
+    /// Media bytes sent/received at the last timeout, for the quality bitrate.
+    last_tx: u64,
+    last_rx: u64,
+    /// Activity at the last timeout, for the per second rates.
+    last_activity: ActivityCounters,
 }
 
 pub(crate) struct StatsSnapshot {
@@ -23,7 +34,16 @@ pub(crate) struct StatsSnapshot {
     pub ingress_loss_fraction: Option<f32>,
     pub ingress: HashMap<(Mid, Option<Rid>), MediaIngressStats>,
     pub egress: HashMap<(Mid, Option<Rid>), MediaEgressStats>,
+    pub channels: Vec<ChannelStats>,
+    pub candidate_pairs: Vec<CandidatePairStats>,
     pub bwe_tx: Option<Bitrate>,
+    pub bwe_desired: Option<Bitrate>,
+    pub egress_jitter: Option<Duration>,
+    pub ingress_jitter: Option<Duration>,
+    pub rtp_leniency: RtpLeniencyCounters,
+    pub roc_recovery: RocRecoveryCounters,
+    pub srtp_errors: SrtpErrorCounters,
+    pub activity: ActivityCounters,
     timestamp: Instant,
 }
 
@@ -38,7 +58,16 @@ impl StatsSnapshot {
             ingress_loss_fraction: None,
             ingress: HashMap::new(),
             egress: HashMap::new(),
+            channels: Vec::new(),
+            candidate_pairs: Vec::new(),
             bwe_tx: None,
+            bwe_desired: None,
+            egress_jitter: None,
+            ingress_jitter: None,
+            rtp_leniency: RtpLeniencyCounters::default(),
+            roc_recovery: RocRecoveryCounters::default(),
+            srtp_errors: SrtpErrorCounters::default(),
+            activity: ActivityCounters::default(),
             timestamp,
         }
     }
@@ -51,6 +80,9 @@ pub(crate) enum StatsEvent {
     Peer(PeerStats),
     MediaEgress(MediaEgressStats),
     MediaIngress(MediaIngressStats),
+    Channel(ChannelStats),
+    CandidatePair(CandidatePairStats),
+    Quality(QualityStats),
 }
 
 /// Peer statistics in [`Event::PeerStats`][crate::Event::PeerStats].
@@ -74,6 +106,71 @@ pub struct PeerStats {
     pub egress_loss_fraction: Option<f32>,
     /// The ingress loss since the last stats event.
     pub ingress_loss_fraction: Option<f32>,
+    /// Total counts of invalid incoming RTP.
+    ///
+    /// See [`RtcConfig::set_rtp_leniency()`][crate::RtcConfig::set_rtp_leniency].
+    pub rtp_leniency: RtpLeniencyCounters,
+    /// Total counts of SRTP rollover counter recovery.
+    ///
+    /// See [`RtcConfig::set_roc_recovery()`][crate::RtcConfig::set_roc_recovery].
+    pub roc_recovery: RocRecoveryCounters,
+    /// Total counts of incoming SRTP/SRTCP packets that were dropped or suspicious, by cause.
+    pub srtp_errors: SrtpErrorCounters,
+    /// Total counts of the work done by the [`Rtc`][crate::Rtc] instance.
+    pub activity: ActivityCounters,
+    /// The work done per second since the last stats event.
+    pub activity_rate: ActivityRate,
+}
+
+/// Counts of the work done by an [`Rtc`][crate::Rtc] instance.
+///
+/// Together with [`ActivityRate`] this helps sizing how many sessions fit on a core.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActivityCounters {
+    /// Events returned from [`Rtc::poll_output()`][crate::Rtc::poll_output].
+    pub events: u64,
+    /// Datagrams passed to [`Rtc::handle_input()`][crate::Rtc::handle_input].
+    pub packets_rx: u64,
+    /// Datagrams transmitted, including the ones added by
+    /// [`Rtc::batch_transmit()`][crate::Rtc::batch_transmit].
+    pub packets_tx: u64,
+    /// Heap allocations made inside calls to the [`Rtc`][crate::Rtc] instance.
+    ///
+    /// Only available with the `alloc-stats` feature and an allocation counter set
+    /// via `RtcConfig::set_allocation_counter()`.
+    pub allocations: Option<u64>,
+}
+
+/// [`ActivityCounters`] per second, over the last stats interval.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ActivityRate {
+    /// Events per second.
+    pub events: f32,
+    /// Received datagrams per second.
+    pub packets_rx: f32,
+    /// Transmitted datagrams per second.
+    pub packets_tx: f32,
+    /// Allocations per second.
+    pub allocations: Option<f32>,
+}
+
+impl ActivityCounters {
+    fn rate_since(&self, last: &ActivityCounters, elapsed: Duration) -> ActivityRate {
+        let secs = elapsed.as_secs_f32();
+        if secs == 0.0 {
+            return ActivityRate::default();
+        }
+        let rate = |now: u64, last: u64| now.saturating_sub(last) as f32 / secs;
+
+        ActivityRate {
+            events: rate(self.events, last.events),
+            packets_rx: rate(self.packets_rx, last.packets_rx),
+            packets_tx: rate(self.packets_tx, last.packets_tx),
+            allocations: self
+                .allocations
+                .map(|a| rate(a, last.allocations.unwrap_or(0))),
+        }
+    }
 }
 
 /// Outgoing media statistics in [`Event::MediaEgressStats`][crate::Event::MediaEgressStats].
@@ -114,6 +211,89 @@ pub struct MediaEgressStats {
     // pub remote: RemoteIngressStats,
 }
 
+/// Data channel statistics in [`Event::ChannelStats`][crate::Event::ChannelStats].
+///
+/// Counts are totals since the channel opened and do not include the DCEP messages
+/// used to open the channel.
+#[derive(Debug, Clone)]
+pub struct ChannelStats {
+    /// The identifier of the channel these stats are for.
+    pub id: ChannelId,
+    /// Total number of messages sent.
+    ///
+    /// Spec equivalent of [`RTCDataChannelStats.messagesSent`][1].
+    ///
+    /// [1]: https://www.w3.org/TR/webrtc-stats/#dom-rtcdatachannelstats-messagessent
+    pub messages_sent: u64,
+    /// Total bytes sent, only counting the message payload.
+    pub bytes_sent: u64,
+    /// Total number of messages received.
+    pub messages_received: u64,
+    /// Total bytes received, only counting the message payload.
+    pub bytes_received: u64,
+    /// Timestamp when this event was generated.
+    pub timestamp: Instant,
+}
+
+/// ICE candidate pair statistics in
+/// [`Event::CandidatePairStats`][crate::Event::CandidatePairStats].
+///
+/// Mirrors [`RTCIceCandidatePairStats`][1].
+///
+/// [1]: https://www.w3.org/TR/webrtc-stats/#candidatepair-dict*
+#[derive(Debug, Clone)]
+pub struct CandidatePairStats {
+    /// The local candidate of the pair.
+    pub local: Candidate,
+    /// The remote candidate of the pair.
+    pub remote: Candidate,
+    /// State of the connectivity checks.
+    pub state: CandidatePairState,
+    /// Whether the pair is nominated.
+    pub nominated: bool,
+    /// Whether this is the pair used for sending data.
+    pub selected: bool,
+    /// Total number of STUN binding requests sent.
+    pub requests_sent: u64,
+    /// Total number of STUN binding responses received.
+    pub responses_received: u64,
+    /// Total number of STUN binding requests received.
+    pub requests_received: u64,
+    /// Total number of STUN binding responses sent.
+    pub responses_sent: u64,
+    /// Total bytes sent, not counting STUN.
+    pub bytes_sent: u64,
+    /// Total bytes received, not counting STUN.
+    pub bytes_received: u64,
+    /// Round trip time of the last answered STUN binding request.
+    ///
+    /// Checks continue on succeeded pairs as keepalives/consent checks, which means
+    /// this tracks the path latency also when no media is flowing.
+    pub rtt: Option<Duration>,
+    /// Smallest round trip time of any answered STUN binding request.
+    pub min_rtt: Option<Duration>,
+    /// Smoothed round trip time, weighing each new measurement by 1/8 (as SRTT in RFC 6298).
+    pub smoothed_rtt: Option<Duration>,
+    /// Sum of the round trip times of all answered STUN binding requests. Divide by
+    /// `responses_received` for the average.
+    pub total_rtt: Duration,
+    /// Timestamp when this event was generated.
+    pub timestamp: Instant,
+}
+
+/// State of the connectivity checks for a candidate pair.
+///
+/// Pairs that fail are removed, which is why there is no failed state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidatePairState {
+    /// No check has been sent yet.
+    Waiting,
+    /// Checks are sent, but none was answered yet.
+    InProgress,
+    /// A check was answered.
+    Succeeded,
+}
+
 /// Stats as reported by the remote side (via RTCP ReceiverReports).
 #[derive(Debug, Clone)]
 pub struct RemoteIngressStats {
@@ -134,6 +314,8 @@ pub struct MediaIngressStats {
     pub bytes: u64,
     /// Total number of rtp packets received, including retransmissions.
     pub packets: u64,
+    /// Number of lost rtp packets recovered with FlexFEC, ULPFEC or RED redundancy.
+    pub packets_recovered: u64,
     /// Number of firs sent.
     pub firs: u64,
     /// Number of plis sent.
@@ -144,6 +326,22 @@ pub struct MediaIngressStats {
     pub rtt: Option<f32>,
     /// Fraction of packets lost extracted from the last RTCP receiver report.
     pub loss: Option<f32>,
+    /// Number of gaps in the incoming sequence numbers.
+    ///
+    /// Each gap is likely to cause a concealment event in the decoder, unless the
+    /// missing packets are recovered by retransmission in time.
+    pub concealment_events: u64,
+    /// Estimated number of samples missing due to the gaps, in the RTP clock rate.
+    ///
+    /// For audio this approximates the concealed samples.
+    pub concealed_samples: u64,
+    /// Number of times the interval between arriving frames was long enough to be
+    /// a freeze, i.e. more than max(3 * avg, avg + 150ms).
+    ///
+    /// This is based on the network arrival, not the rendering. Only meaningful for video.
+    pub freeze_count: u64,
+    /// Sum of the intervals counted in `freeze_count`.
+    pub total_freezes_duration: Duration,
     /// Timestamp when this event was generated.
     pub timestamp: Instant,
     // TODO
@@ -174,11 +372,16 @@ impl MediaIngressStats {
             rid: self.rid,
             bytes: self.bytes + other.bytes,
             packets: self.packets + other.packets,
+            packets_recovered: self.packets_recovered + other.packets_recovered,
             firs: self.firs + other.firs,
             plis: self.plis + other.plis,
             nacks: self.nacks + other.nacks,
             rtt,
             loss,
+            concealment_events: self.concealment_events + other.concealment_events,
+            concealed_samples: self.concealed_samples + other.concealed_samples,
+            freeze_count: self.freeze_count + other.freeze_count,
+            total_freezes_duration: self.total_freezes_duration + other.total_freezes_duration,
             timestamp: self.timestamp.max(other.timestamp),
         };
     }
@@ -191,17 +394,145 @@ pub struct RemoteEgressStats {
     pub bytes_tx: u64,
 }
 
+/// Direction of the media a [`QualityStats`] is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QualityDirection {
+    /// Media we send.
+    Egress,
+    /// Media we receive.
+    Ingress,
+}
+
+/// The measurements a [`QualityScorer`] combines into a score.
+///
+/// Any of them can be missing, for instance before the first RTCP report.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityInputs {
+    /// Fraction of packets lost, 0.0 to 1.0.
+    pub loss: Option<f32>,
+    /// Round trip time of the selected ICE candidate pair, or from RTCP if there is none.
+    pub rtt: Option<Duration>,
+    /// Largest interarrival jitter of the streams in this direction.
+    ///
+    /// For egress this is as reported by the remote peer.
+    pub jitter: Option<Duration>,
+    /// Media bitrate over the last stats interval.
+    pub bitrate: Option<Bitrate>,
+    /// The bitrate we want to be at.
+    ///
+    /// For egress this is the desired bitrate set via
+    /// [`Bwe::set_desired_bitrate`][crate::bwe::Bwe::set_desired_bitrate]. There is no
+    /// target for ingress.
+    pub target_bitrate: Option<Bitrate>,
+}
+
+/// Turns [`QualityInputs`] into a score.
+///
+/// Set with [`RtcConfig::set_quality_scorer`][crate::RtcConfig::set_quality_scorer]
+/// to replace how the score in [`QualityStats`] is calculated.
+/// [`DefaultQualityScorer`] is a MOS-like score with adjustable weights.
+pub trait QualityScorer: fmt::Debug + Send + Sync + RefUnwindSafe + 'static {
+    /// Score the inputs for one direction. Higher is better.
+    fn score(&self, direction: QualityDirection, inputs: &QualityInputs) -> f32;
+}
+
+/// MOS-like quality score from 1.0 (bad) to 4.5 (excellent).
+///
+/// This is a simplified ITU-T G.107 E-model. Delay and jitter reduce the score via the
+/// effective latency, loss via an equipment impairment and a bitrate below target via
+/// the shortfall. Each impairment is multiplied by its weight, so setting a weight to
+/// 0.0 ignores that input. Missing inputs are not counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DefaultQualityScorer {
+    /// Weight of the loss impairment. Defaults to 1.0.
+    pub loss_weight: f32,
+    /// Weight of the delay (RTT and jitter) impairment. Defaults to 1.0.
+    pub delay_weight: f32,
+    /// Weight of the bitrate below target impairment. Defaults to 1.0.
+    pub bitrate_weight: f32,
+}
+
+impl Default for DefaultQualityScorer {
+    fn default() -> Self {
+        Self {
+            loss_weight: 1.0,
+            delay_weight: 1.0,
+            bitrate_weight: 1.0,
+        }
+    }
+}
+
+impl QualityScorer for DefaultQualityScorer {
+    fn score(&self, _direction: QualityDirection, inputs: &QualityInputs) -> f32 {
+        // R-factor without impairments.
+        let mut r = 93.2;
+
+        let rtt = inputs.rtt.unwrap_or_default().as_secs_f32() * 1000.0;
+        let jitter = inputs.jitter.unwrap_or_default().as_secs_f32() * 1000.0;
+        let latency = rtt / 2.0 + jitter * 2.0 + 10.0;
+        let delay = if latency < 160.0 {
+            latency / 40.0
+        } else {
+            (latency - 120.0) / 10.0
+        };
+        r -= delay * self.delay_weight;
+
+        let loss = inputs.loss.unwrap_or_default().clamp(0.0, 1.0) * 100.0;
+        r -= loss * 2.5 * self.loss_weight;
+
+        if let (Some(bitrate), Some(target)) = (inputs.bitrate, inputs.target_bitrate) {
+            if target.as_f64() > 0.0 {
+                let shortfall = (1.0 - bitrate.as_f64() / target.as_f64()).clamp(0.0, 1.0);
+                r -= shortfall as f32 * 40.0 * self.bitrate_weight;
+            }
+        }
+
+        r_to_mos(r)
+    }
+}
+
+/// The R-factor to MOS mapping from ITU-T G.107.
+fn r_to_mos(r: f32) -> f32 {
+    if r <= 0.0 {
+        1.0
+    } else if r >= 100.0 {
+        4.5
+    } else {
+        (1.0 + 0.035 * r + 7.0e-6 * r * (r - 60.0) * (100.0 - r)).max(1.0)
+    }
+}
+
+/// Connection quality in [`Event::QualityStats`][crate::Event::QualityStats].
+///
+/// Emitted at the stats interval for each direction that has media, when a scorer is
+/// set via [`RtcConfig::set_quality_scorer`][crate::RtcConfig::set_quality_scorer].
+#[derive(Debug, Clone, PartialEq)]
+pub struct QualityStats {
+    /// Which direction this is for.
+    pub direction: QualityDirection,
+    /// The score from the [`QualityScorer`].
+    pub score: f32,
+    /// The measurements the score is based on.
+    pub inputs: QualityInputs,
+    /// Timestamp when this event was generated.
+    pub timestamp: Instant,
+}
+
 impl Stats {
     /// Create a new stats instance
     ///
     /// The internal state is market with the current `Instant::now()`.
     /// This allows us to emit stats right away at the first upcoming timeout
-    pub fn new(interval: Duration) -> Stats {
+    pub fn new(interval: Duration, quality_scorer: Option<Arc<dyn QualityScorer>>) -> Stats {
         Stats {
             // by starting with the current time we can generate stats right on first timeout
             last_now: None,
             events: VecDeque::new(),
             interval,
+            quality_scorer,
+            last_tx: 0,
+            last_rx: 0,
+            last_activity: ActivityCounters::default(),
         }
     }
 
@@ -223,6 +554,13 @@ impl Stats {
     pub fn do_handle_timeout(&mut self, snapshot: &mut StatsSnapshot) {
         // enqueue stats and timestamp them so they can be sent out
 
+        let elapsed = self
+            .last_now
+            .map(|l| snapshot.timestamp.saturating_duration_since(l))
+            .unwrap_or_default();
+        let activity_rate = snapshot.activity.rate_since(&self.last_activity, elapsed);
+        self.last_activity = snapshot.activity;
+
         let event = PeerStats {
             peer_bytes_rx: snapshot.peer_rx,
             peer_bytes_tx: snapshot.peer_tx,
@@ -232,10 +570,17 @@ impl Stats {
             bwe_tx: snapshot.bwe_tx,
             egress_loss_fraction: snapshot.egress_loss_fraction,
             ingress_loss_fraction: snapshot.ingress_loss_fraction,
+            rtp_leniency: snapshot.rtp_leniency,
+            roc_recovery: snapshot.roc_recovery,
+            srtp_errors: snapshot.srtp_errors,
+            activity: snapshot.activity,
+            activity_rate,
         };
 
         self.events.push_back(StatsEvent::Peer(event));
 
+        self.push_quality(snapshot);
+
         for (_, event) in snapshot.ingress.drain() {
             self.events.push_back(StatsEvent::MediaIngress(event));
         }
@@ -244,9 +589,94 @@ impl Stats {
             self.events.push_back(StatsEvent::MediaEgress(event));
         }
 
+        for event in snapshot.channels.drain(..) {
+            self.events.push_back(StatsEvent::Channel(event));
+        }
+
+        for event in snapshot.candidate_pairs.drain(..) {
+            self.events.push_back(StatsEvent::CandidatePair(event));
+        }
+
         self.last_now = Some(snapshot.timestamp);
     }
 
+    fn push_quality(&mut self, snapshot: &StatsSnapshot) {
+        let tx = snapshot.tx.saturating_sub(self.last_tx);
+        let rx = snapshot.rx.saturating_sub(self.last_rx);
+        self.last_tx = snapshot.tx;
+        self.last_rx = snapshot.rx;
+
+        let Some(scorer) = &self.quality_scorer else {
+            return;
+        };
+
+        let elapsed = self
+            .last_now
+            .map(|l| snapshot.timestamp.saturating_duration_since(l))
+            .filter(|e| !e.is_zero());
+        let bitrate =
+            |bytes: u64| elapsed.map(|e| Bitrate::from(bytes as f64 * 8.0 / e.as_secs_f64()));
+
+        // Prefer the ICE RTT since it's continuously measured, regardless of media.
+        let rtt = snapshot
+            .candidate_pairs
+            .iter()
+            .find(|p| p.selected)
+            .and_then(|p| p.smoothed_rtt.or(p.rtt))
+            .or_else(|| {
+                snapshot
+                    .egress
+                    .values()
+                    .filter_map(|s| s.rtt)
+                    .min_by(|a, b| a.total_cmp(b))
+                    .map(|ms| Duration::from_secs_f32(ms.max(0.0) / 1000.0))
+            });
+
+        let mut push = |direction, inputs: QualityInputs| {
+            let score = scorer.score(direction, &inputs);
+            self.events.push_back(StatsEvent::Quality(QualityStats {
+                direction,
+                score,
+                inputs,
+                timestamp: snapshot.timestamp,
+            }));
+        };
+
+        if !snapshot.egress.is_empty() {
+            let inputs = QualityInputs {
+                loss: snapshot.egress_loss_fraction.or_else(|| {
+                    snapshot
+                        .egress
+                        .values()
+                        .filter_map(|s| s.loss)
+                        .max_by(|a, b| a.total_cmp(b))
+                }),
+                rtt,
+                jitter: snapshot.egress_jitter,
+                bitrate: bitrate(tx),
+                target_bitrate: snapshot.bwe_desired,
+            };
+            push(QualityDirection::Egress, inputs);
+        }
+
+        if !snapshot.ingress.is_empty() {
+            let inputs = QualityInputs {
+                loss: snapshot.ingress_loss_fraction.or_else(|| {
+                    snapshot
+                        .ingress
+                        .values()
+                        .filter_map(|s| s.loss)
+                        .max_by(|a, b| a.total_cmp(b))
+                }),
+                rtt,
+                jitter: snapshot.ingress_jitter,
+                bitrate: bitrate(rx),
+                target_bitrate: None,
+            };
+            push(QualityDirection::Ingress, inputs);
+        }
+    }
+
     /// Poll for the next time to call [`Stats::wants_timeout`] and [`Stats::do_handle_timeout`].
     ///
     /// NOTE: we only need Option<_> to conform to .soonest() (see caller)
@@ -260,3 +690,71 @@ impl Stats {
         self.events.pop_front()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn inputs() -> QualityInputs {
+        QualityInputs {
+            loss: Some(0.0),
+            rtt: Some(Duration::from_millis(20)),
+            jitter: Some(Duration::from_millis(1)),
+            bitrate: Some(Bitrate::kbps(1000)),
+            target_bitrate: Some(Bitrate::kbps(1000)),
+        }
+    }
+
+    #[test]
+    fn default_quality_score() {
+        let s = DefaultQualityScorer::default();
+        let d = QualityDirection::Egress;
+
+        let good = s.score(d, &inputs());
+        assert!(good > 4.3 && good <= 4.5, "good: {}", good);
+
+        let lossy = s.score(
+            d,
+            &QualityInputs {
+                loss: Some(0.2),
+                ..inputs()
+            },
+        );
+        assert!(lossy < 3.5, "lossy: {}", lossy);
+
+        let slow = s.score(
+            d,
+            &QualityInputs {
+                rtt: Some(Duration::from_millis(800)),
+                ..inputs()
+            },
+        );
+        assert!(slow < good, "slow: {}", slow);
+
+        let starved = s.score(
+            d,
+            &QualityInputs {
+                bitrate: Some(Bitrate::kbps(250)),
+                ..inputs()
+            },
+        );
+        assert!(starved < 3.5, "starved: {}", starved);
+
+        // Weight 0.0 ignores the input.
+        let s = DefaultQualityScorer {
+            loss_weight: 0.0,
+            ..Default::default()
+        };
+        let ignored = s.score(
+            d,
+            &QualityInputs {
+                loss: Some(0.5),
+                ..inputs()
+            },
+        );
+        assert_eq!(ignored, good);
+
+        assert_eq!(r_to_mos(-10.0), 1.0);
+        assert_eq!(r_to_mos(120.0), 4.5);
+    }
+}
@@ -0,0 +1,745 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::rtp_::{RtpHeader, SeqNo, Ssrc};
+
+/// Size of the fixed RTP header, which is the part not covered by the FEC payload.
+const RTP_HEADER_LEN: usize = 12;
+
+/// Size of the FlexFEC header up until the first packet mask.
+const FEC_HEADER_LEN: usize = 18;
+
+/// Number of packets each packet mask chunk can cover (15, 46 and 109 in total).
+const MASK_CHUNK_BITS: [usize; 3] = [15, 31, 63];
+
+/// Most packets a single FlexFEC packet can protect.
+const MAX_PROTECTED: usize = 109;
+
+/// Size of the ULPFEC header up until the level 0 header.
+const ULPFEC_HEADER_LEN: usize = 10;
+
+/// Most packets a single ULPFEC packet can protect, with the long mask.
+const MAX_PROTECTED_ULPFEC: usize = 48;
+
+/// How many received media packets are kept for recovery.
+const MAX_MEDIA: usize = 1024;
+
+/// How many received FEC packets are kept while waiting for media.
+const MAX_FEC: usize = 64;
+
+/// FEC packets protecting media this far behind the latest received are discarded.
+const MAX_FEC_AGE: u16 = 256;
+
+/// The wire formats of the FEC packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FecFormat {
+    /// FlexFEC in the flexfec-03 draft format, sent in a separate SSRC.
+    FlexFec,
+    /// ULPFEC (RFC 5109), sent wrapped in RED in the same SSRC as the media.
+    Ulpfec,
+}
+
+/// Produces FEC payloads protecting a window of sent media packets.
+///
+/// Each FEC packet is the XOR of the last `window` consecutive media packets, which
+/// means any one of them can be recovered by the remote if it is lost.
+#[derive(Debug)]
+pub(crate) struct FecEncoder {
+    format: FecFormat,
+    window: usize,
+    packets: Vec<(u16, Vec<u8>)>,
+}
+
+/// Recovers lost media packets from received FlexFEC (flexfec-03) and ULPFEC packets.
+#[derive(Debug, Default)]
+pub(crate) struct FecDecoder {
+    /// Unprotected media packets by SSRC and sequence number.
+    media: HashMap<(Ssrc, u16), Vec<u8>>,
+    /// Insertion order of `media` for evicting old packets.
+    media_order: VecDeque<(Ssrc, u16)>,
+    /// Highest sequence number seen per protected SSRC.
+    max_seq: HashMap<Ssrc, u16>,
+    /// FEC packets not used for recovery yet.
+    fec: VecDeque<FecPacket>,
+    /// Extended sequence numbers of the FEC streams, for SRTP.
+    fec_seq_no: HashMap<Ssrc, SeqNo>,
+}
+
+#[derive(Debug)]
+struct FecPacket {
+    ssrc: Ssrc,
+    seqs: Vec<u16>,
+    /// The recovery fields: bits, PT, length and timestamp.
+    recovery: [u8; 8],
+    payload: Vec<u8>,
+}
+
+impl FecFormat {
+    /// Most packets a single FEC packet can protect.
+    pub fn max_protected(&self) -> usize {
+        match self {
+            FecFormat::FlexFec => MAX_PROTECTED,
+            FecFormat::Ulpfec => MAX_PROTECTED_ULPFEC,
+        }
+    }
+}
+
+impl FecEncoder {
+    /// Creates an encoder protecting every `window` packets with one FEC packet.
+    pub fn new(format: FecFormat, window: usize) -> Self {
+        let window = window.clamp(1, format.max_protected());
+
+        FecEncoder {
+            format,
+            window,
+            packets: Vec::with_capacity(window),
+        }
+    }
+
+    /// Add a sent (unprotected) RTP packet.
+    ///
+    /// Returns the FEC payload once the window is complete.
+    pub fn push(&mut self, packet: &[u8]) -> Option<Vec<u8>> {
+        if packet.len() < RTP_HEADER_LEN {
+            return None;
+        }
+
+        let seq = u16::from_be_bytes([packet[2], packet[3]]);
+
+        // A gap in sequence numbers restarts the window.
+        if let Some((last, _)) = self.packets.last() {
+            if seq != last.wrapping_add(1) {
+                self.packets.clear();
+            }
+        }
+
+        self.packets.push((seq, packet.to_vec()));
+
+        if self.packets.len() < self.window {
+            return None;
+        }
+
+        let fec = match self.format {
+            FecFormat::FlexFec => encode_flexfec(&self.packets),
+            FecFormat::Ulpfec => encode_ulpfec(&self.packets),
+        };
+        self.packets.clear();
+
+        Some(fec)
+    }
+}
+
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |R|F|P|X|  CC   |M| PT recovery |        length recovery        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          TS recovery                          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |   SSRCCount   |                    reserved                   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                             SSRC_i                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |           SN base_i           |k|          Mask [0-14]        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |k|                   Mask [15-45] (optional)                   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |k|                                                             |
+// +-+                   Mask [46-108] (optional)                  |
+// |                                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+fn encode_flexfec(packets: &[(u16, Vec<u8>)]) -> Vec<u8> {
+    let base = packets[0].0;
+    let ssrc = &packets[0].1[8..12];
+
+    let offsets: Vec<usize> = packets
+        .iter()
+        .map(|(seq, _)| seq.wrapping_sub(base) as usize)
+        .collect();
+
+    let max_offset = offsets.iter().copied().max().unwrap_or(0);
+    let mask = write_mask(&offsets, max_offset);
+    let header_len = FEC_HEADER_LEN + mask.len();
+
+    let max_len = packets
+        .iter()
+        .map(|(_, p)| p.len() - RTP_HEADER_LEN)
+        .max()
+        .unwrap_or(0);
+
+    let mut out = vec![0; header_len + max_len];
+
+    for (_, p) in packets {
+        xor_recovery(&mut out, p);
+        xor(&mut out[header_len..], &p[RTP_HEADER_LEN..]);
+    }
+
+    // R and F bits are 0, the version bits of the media are not recovered.
+    out[0] &= 0x3f;
+    out[8] = 1;
+    out[12..16].copy_from_slice(ssrc);
+    out[16..18].copy_from_slice(&base.to_be_bytes());
+    out[18..header_len].copy_from_slice(&mask);
+
+    out
+}
+
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |E|L|P|X|  CC   |M| PT recovery |            SN base            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          TS recovery                          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |        length recovery        |       Protection Length       |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |             mask              |  mask cont. (present if L=1)  |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                    mask cont. (present if L=1)                |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+fn encode_ulpfec(packets: &[(u16, Vec<u8>)]) -> Vec<u8> {
+    let base = packets[0].0;
+
+    let mut mask: u64 = 0;
+    for (seq, _) in packets {
+        let o = seq.wrapping_sub(base) as usize;
+        mask |= 1 << (MAX_PROTECTED_ULPFEC - 1 - o);
+    }
+
+    // The short mask covers 16 packets.
+    let long = mask & 0xffff_ffff > 0;
+    let header_len = ULPFEC_HEADER_LEN + if long { 8 } else { 4 };
+
+    let max_len = packets
+        .iter()
+        .map(|(_, p)| p.len() - RTP_HEADER_LEN)
+        .max()
+        .unwrap_or(0);
+
+    let mut recovery = [0; 8];
+    let mut out = vec![0; header_len + max_len];
+
+    for (_, p) in packets {
+        xor_recovery(&mut recovery, p);
+        xor(&mut out[header_len..], &p[RTP_HEADER_LEN..]);
+    }
+
+    // E bit is 0, the version bits of the media are not recovered.
+    out[0] = (recovery[0] & 0x3f) | if long { 0x40 } else { 0 };
+    out[1] = recovery[1];
+    out[2..4].copy_from_slice(&base.to_be_bytes());
+    out[4..8].copy_from_slice(&recovery[4..8]);
+    out[8..10].copy_from_slice(&recovery[2..4]);
+    out[10..12].copy_from_slice(&(max_len as u16).to_be_bytes());
+    out[12..header_len].copy_from_slice(&mask.to_be_bytes()[2..2 + header_len - 12]);
+
+    out
+}
+
+fn write_mask(offsets: &[usize], max_offset: usize) -> Vec<u8> {
+    let mut bits: u128 = 0;
+    for o in offsets {
+        bits |= 1 << (MAX_PROTECTED - 1 - o);
+    }
+
+    let mut out = Vec::with_capacity(14);
+    let mut covered = 0;
+
+    for (i, n) in MASK_CHUNK_BITS.into_iter().enumerate() {
+        // Bits for this chunk, shifted down from the top of the 109 bit mask.
+        let chunk = (bits >> (MAX_PROTECTED - covered - n)) & ((1 << n) - 1);
+        covered += n;
+
+        let last = covered > max_offset || i == MASK_CHUNK_BITS.len() - 1;
+        let k = if last { 1 << n } else { 0 };
+        let v = (chunk | k) as u64;
+
+        let len = (n + 1) / 8;
+        out.extend_from_slice(&v.to_be_bytes()[8 - len..]);
+
+        if last {
+            break;
+        }
+    }
+
+    out
+}
+
+/// Parse the packet mask. Returns the offsets from SN base and the header length.
+fn read_mask(buf: &[u8]) -> Option<(Vec<usize>, usize)> {
+    let mut offsets = vec![];
+    let mut i = FEC_HEADER_LEN;
+    let mut covered = 0;
+
+    for n in MASK_CHUNK_BITS {
+        let len = (n + 1) / 8;
+        let b = buf.get(i..i + len)?;
+        i += len;
+
+        let v = b.iter().fold(0_u64, |acc, x| (acc << 8) | *x as u64);
+
+        for bit in 0..n {
+            if v & (1 << (n - 1 - bit)) > 0 {
+                offsets.push(covered + bit);
+            }
+        }
+        covered += n;
+
+        if v & (1 << n) > 0 {
+            return Some((offsets, i));
+        }
+    }
+
+    // The last chunk must have k set.
+    None
+}
+
+/// XOR the bits, PT, length and timestamp of a media packet into the recovery fields.
+fn xor_recovery(out: &mut [u8], packet: &[u8]) {
+    let len = ((packet.len() - RTP_HEADER_LEN) as u16).to_be_bytes();
+
+    out[0] ^= packet[0];
+    out[1] ^= packet[1];
+    out[2] ^= len[0];
+    out[3] ^= len[1];
+    xor(&mut out[4..8], &packet[4..8]);
+}
+
+fn xor(out: &mut [u8], data: &[u8]) {
+    for (o, d) in out.iter_mut().zip(data.iter()) {
+        *o ^= d;
+    }
+}
+
+impl FecDecoder {
+    pub fn new() -> Self {
+        FecDecoder::default()
+    }
+
+    /// Extended sequence number of an incoming FlexFEC packet, used for SRTP.
+    pub fn extend_seq(&mut self, header: &RtpHeader) -> SeqNo {
+        let previous = self.fec_seq_no.get(&header.ssrc).copied();
+        let seq_no = header.sequence_number(previous);
+
+        if previous.map(|p| seq_no > p).unwrap_or(true) {
+            self.fec_seq_no.insert(header.ssrc, seq_no);
+        }
+
+        seq_no
+    }
+
+    /// Keep an unprotected media packet for recovery. The packet is the RTP header
+    /// as received followed by the decrypted payload (including any padding).
+    pub fn insert_media(&mut self, ssrc: Ssrc, header: &[u8], payload: &[u8]) {
+        if header.len() < RTP_HEADER_LEN {
+            return;
+        }
+
+        let seq = u16::from_be_bytes([header[2], header[3]]);
+
+        let mut packet = Vec::with_capacity(header.len() + payload.len());
+        packet.extend_from_slice(header);
+        packet.extend_from_slice(payload);
+
+        self.do_insert_media(ssrc, seq, packet);
+    }
+
+    fn do_insert_media(&mut self, ssrc: Ssrc, seq: u16, packet: Vec<u8>) {
+        if self.media.contains_key(&(ssrc, seq)) {
+            return;
+        }
+
+        let max = self.max_seq.entry(ssrc).or_insert(seq);
+        if seq.wrapping_sub(*max) < u16::MAX / 2 {
+            *max = seq;
+        }
+
+        self.media.insert((ssrc, seq), packet);
+        self.media_order.push_back((ssrc, seq));
+
+        while self.media_order.len() > MAX_MEDIA {
+            if let Some(k) = self.media_order.pop_front() {
+                self.media.remove(&k);
+            }
+        }
+    }
+
+    /// Add the (unprotected) payload of a FlexFEC packet.
+    ///
+    /// Returns the SSRC of the media it protects.
+    pub fn insert_flexfec(&mut self, payload: &[u8]) -> Option<Ssrc> {
+        if payload.len() < FEC_HEADER_LEN {
+            return None;
+        }
+
+        // The R bit is for retransmissions, and F for the flexible mask modes of
+        // RFC 8627 which flexfec-03 doesn't have.
+        if payload[0] & 0xc0 > 0 {
+            return None;
+        }
+
+        // Multiple protected streams are not supported.
+        if payload[8] != 1 {
+            return None;
+        }
+
+        let ssrc: Ssrc =
+            u32::from_be_bytes([payload[12], payload[13], payload[14], payload[15]]).into();
+        let base = u16::from_be_bytes([payload[16], payload[17]]);
+
+        let (offsets, header_len) = read_mask(payload)?;
+        if offsets.is_empty() {
+            return None;
+        }
+
+        let mut recovery = [0; 8];
+        recovery.copy_from_slice(&payload[..8]);
+
+        self.push_fec(FecPacket {
+            ssrc,
+            seqs: offsets
+                .into_iter()
+                .map(|o| base.wrapping_add(o as u16))
+                .collect(),
+            recovery,
+            payload: payload[header_len..].to_vec(),
+        });
+
+        Some(ssrc)
+    }
+
+    /// Add a ULPFEC payload, unwrapped from RED, protecting the media in `ssrc`.
+    ///
+    /// Returns false if the payload is broken.
+    pub fn insert_ulpfec(&mut self, ssrc: Ssrc, payload: &[u8]) -> bool {
+        if payload.len() < ULPFEC_HEADER_LEN + 4 {
+            return false;
+        }
+
+        // The E bit is reserved for extensions of RFC 5109.
+        if payload[0] & 0x80 > 0 {
+            return false;
+        }
+
+        let long = payload[0] & 0x40 > 0;
+        let header_len = ULPFEC_HEADER_LEN + if long { 8 } else { 4 };
+
+        let Some(mask) = payload.get(12..header_len) else {
+            return false;
+        };
+
+        let base = u16::from_be_bytes([payload[2], payload[3]]);
+        let bits = mask.len() * 8;
+        let v = mask.iter().fold(0_u64, |acc, x| (acc << 8) | *x as u64);
+
+        let seqs: Vec<u16> = (0..bits)
+            .filter(|i| v & (1 << (bits - 1 - i)) > 0)
+            .map(|i| base.wrapping_add(i as u16))
+            .collect();
+
+        if seqs.is_empty() {
+            return false;
+        }
+
+        // Same order as the FlexFEC recovery fields.
+        let mut recovery = [0; 8];
+        recovery[..2].copy_from_slice(&payload[..2]);
+        recovery[2..4].copy_from_slice(&payload[8..10]);
+        recovery[4..8].copy_from_slice(&payload[4..8]);
+
+        let protection_len = u16::from_be_bytes([payload[10], payload[11]]) as usize;
+        let end = (header_len + protection_len).min(payload.len());
+
+        self.push_fec(FecPacket {
+            ssrc,
+            seqs,
+            recovery,
+            payload: payload[header_len..end].to_vec(),
+        });
+
+        true
+    }
+
+    fn push_fec(&mut self, fec: FecPacket) {
+        self.fec.push_back(fec);
+
+        while self.fec.len() > MAX_FEC {
+            self.fec.pop_front();
+        }
+    }
+
+    /// Attempt to recover one lost media packet.
+    ///
+    /// Recovered packets are also kept for further recovery, so this should be
+    /// polled until it returns `None`.
+    pub fn poll_recovered(&mut self) -> Option<Vec<u8>> {
+        let mut i = 0;
+
+        while i < self.fec.len() {
+            let fec = &self.fec[i];
+
+            let max = self.max_seq.get(&fec.ssrc).copied();
+            let too_old = fec.seqs.iter().any(|s| {
+                let age = max.map(|m| m.wrapping_sub(*s)).unwrap_or(0);
+                age > MAX_FEC_AGE && age < u16::MAX / 2
+            });
+
+            let mut missing = fec
+                .seqs
+                .iter()
+                .filter(|s| !self.media.contains_key(&(fec.ssrc, **s)));
+
+            let first_missing = missing.next().copied();
+            let more_missing = missing.next().is_some();
+
+            match first_missing {
+                // Nothing to recover, or we can't tell what is lost anymore.
+                _ if too_old => {
+                    self.fec.remove(i);
+                }
+                None => {
+                    self.fec.remove(i);
+                }
+                // Maybe later, when more media arrives.
+                Some(_) if more_missing => {
+                    i += 1;
+                }
+                Some(seq) => {
+                    let fec = self.fec.remove(i).expect("fec at index");
+
+                    let Some(packet) = self.recover(&fec, seq) else {
+                        continue;
+                    };
+
+                    self.do_insert_media(fec.ssrc, seq, packet.clone());
+
+                    return Some(packet);
+                }
+            }
+        }
+
+        None
+    }
+
+    fn recover(&self, fec: &FecPacket, seq: u16) -> Option<Vec<u8>> {
+        let mut recovery = fec.recovery;
+        let mut payload = fec.payload.clone();
+
+        for s in fec.seqs.iter().filter(|s| **s != seq) {
+            let p = self.media.get(&(fec.ssrc, *s))?;
+            xor_recovery(&mut recovery, p);
+            xor(&mut payload, &p[RTP_HEADER_LEN..]);
+        }
+
+        let len = u16::from_be_bytes([recovery[2], recovery[3]]) as usize;
+        if len > payload.len() {
+            // Broken FEC or media.
+            return None;
+        }
+
+        let mut packet = Vec::with_capacity(RTP_HEADER_LEN + len);
+
+        // Set the version 2 bits.
+        packet.push((recovery[0] & 0x3f) | 0x80);
+        packet.push(recovery[1]);
+        packet.extend_from_slice(&seq.to_be_bytes());
+        packet.extend_from_slice(&recovery[4..8]);
+        packet.extend_from_slice(&(*fec.ssrc).to_be_bytes());
+        packet.extend_from_slice(&payload[..len]);
+
+        Some(packet)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn packet(seq: u16, ts: u32, marker: bool, payload: &[u8]) -> Vec<u8> {
+        let mut p = vec![0x80, 96 | if marker { 0x80 } else { 0 }];
+        p.extend_from_slice(&seq.to_be_bytes());
+        p.extend_from_slice(&ts.to_be_bytes());
+        p.extend_from_slice(&0x1234_5678_u32.to_be_bytes());
+        p.extend_from_slice(payload);
+        p
+    }
+
+    fn split(p: &[u8]) -> (&[u8], &[u8]) {
+        p.split_at(RTP_HEADER_LEN)
+    }
+
+    #[test]
+    fn recover_one_lost() {
+        let mut enc = FecEncoder::new(FecFormat::FlexFec, 3);
+        let mut dec = FecDecoder::new();
+
+        let p0 = packet(65534, 1000, false, &[1, 2, 3, 4]);
+        let p1 = packet(65535, 1000, true, &[5, 6]);
+        let p2 = packet(0, 4000, false, &[7, 8, 9, 10, 11, 12]);
+
+        assert_eq!(enc.push(&p0), None);
+        assert_eq!(enc.push(&p1), None);
+        let fec = enc.push(&p2).unwrap();
+
+        // Lose p1.
+        let (h, b) = split(&p0);
+        dec.insert_media(0x1234_5678.into(), h, b);
+        let (h, b) = split(&p2);
+        dec.insert_media(0x1234_5678.into(), h, b);
+
+        assert_eq!(dec.insert_flexfec(&fec), Some(0x1234_5678.into()));
+        assert_eq!(dec.poll_recovered(), Some(p1));
+        assert_eq!(dec.poll_recovered(), None);
+    }
+
+    #[test]
+    fn no_recovery_for_two_lost() {
+        let mut enc = FecEncoder::new(FecFormat::FlexFec, 3);
+        let mut dec = FecDecoder::new();
+
+        let p0 = packet(10, 1000, false, &[1, 2, 3]);
+        let p1 = packet(11, 1000, false, &[4, 5, 6]);
+        let p2 = packet(12, 1000, true, &[7, 8, 9]);
+
+        enc.push(&p0);
+        enc.push(&p1);
+        let fec = enc.push(&p2).unwrap();
+
+        let (h, b) = split(&p0);
+        dec.insert_media(0x1234_5678.into(), h, b);
+        dec.insert_flexfec(&fec);
+        assert_eq!(dec.poll_recovered(), None);
+
+        // When one of them arrives late, the other can be recovered.
+        let (h, b) = split(&p2);
+        dec.insert_media(0x1234_5678.into(), h, b);
+        assert_eq!(dec.poll_recovered(), Some(p1));
+    }
+
+    #[test]
+    fn nothing_lost() {
+        let mut enc = FecEncoder::new(FecFormat::FlexFec, 2);
+        let mut dec = FecDecoder::new();
+
+        let p0 = packet(10, 1000, false, &[1]);
+        let p1 = packet(11, 1000, false, &[2]);
+
+        enc.push(&p0);
+        let fec = enc.push(&p1).unwrap();
+
+        for p in [&p0, &p1] {
+            let (h, b) = split(p);
+            dec.insert_media(0x1234_5678.into(), h, b);
+        }
+
+        dec.insert_flexfec(&fec);
+        assert_eq!(dec.poll_recovered(), None);
+        assert!(dec.fec.is_empty());
+    }
+
+    #[test]
+    fn mask_sizes() {
+        for (window, header_len) in [(15, 20), (16, 24), (46, 24), (47, 32), (109, 32)] {
+            let mut enc = FecEncoder::new(FecFormat::FlexFec, window);
+            let mut fec = None;
+            for i in 0..window {
+                fec = enc.push(&packet(i as u16, 0, false, &[i as u8]));
+            }
+            let fec = fec.unwrap();
+
+            let (offsets, len) = read_mask(&fec).unwrap();
+            assert_eq!(len, header_len);
+            assert_eq!(offsets, (0..window).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn bad_fec() {
+        let mut dec = FecDecoder::new();
+        assert_eq!(dec.insert_flexfec(&[0; 10]), None);
+
+        // Last mask chunk without k bit.
+        let mut fec = vec![0; 32];
+        fec[8] = 1;
+        assert_eq!(dec.insert_flexfec(&fec), None);
+    }
+
+    #[test]
+    fn ulpfec_recover_one_lost() {
+        let mut enc = FecEncoder::new(FecFormat::Ulpfec, 3);
+        let mut dec = FecDecoder::new();
+
+        let p0 = packet(65534, 1000, false, &[1, 2, 3, 4]);
+        let p1 = packet(65535, 1000, true, &[5, 6]);
+        let p2 = packet(0, 4000, false, &[7, 8, 9, 10, 11, 12]);
+
+        enc.push(&p0);
+        enc.push(&p1);
+        let fec = enc.push(&p2).unwrap();
+
+        // Short mask, and the protection length is the longest payload.
+        assert_eq!(fec.len(), 14 + 6);
+        assert_eq!(fec[0] & 0x40, 0);
+        assert_eq!(&fec[2..4], &65534_u16.to_be_bytes());
+        assert_eq!(&fec[10..12], &[0, 6]);
+        assert_eq!(&fec[12..14], &[0b1110_0000, 0]);
+
+        // Lose p1.
+        let (h, b) = split(&p0);
+        dec.insert_media(0x1234_5678.into(), h, b);
+        let (h, b) = split(&p2);
+        dec.insert_media(0x1234_5678.into(), h, b);
+
+        assert!(dec.insert_ulpfec(0x1234_5678.into(), &fec));
+        assert_eq!(dec.poll_recovered(), Some(p1));
+        assert_eq!(dec.poll_recovered(), None);
+    }
+
+    #[test]
+    fn ulpfec_long_mask() {
+        let mut enc = FecEncoder::new(FecFormat::Ulpfec, 100);
+        let mut dec = FecDecoder::new();
+
+        let packets: Vec<_> = (0..48_u16)
+            .map(|i| packet(i, 0, false, &[i as u8; 3]))
+            .collect();
+
+        let mut fec = None;
+        for p in &packets {
+            fec = enc.push(p);
+        }
+        let fec = fec.unwrap();
+
+        // The window is capped to the 48 bits of the long mask.
+        assert_eq!(fec[0] & 0x40, 0x40);
+        assert_eq!(&fec[12..18], &[0xff; 6]);
+
+        for p in packets.iter().filter(|p| p[3] != 20) {
+            let (h, b) = split(p);
+            dec.insert_media(0x1234_5678.into(), h, b);
+        }
+
+        assert!(dec.insert_ulpfec(0x1234_5678.into(), &fec));
+        assert_eq!(dec.poll_recovered().as_ref(), Some(&packets[20]));
+    }
+
+    #[test]
+    fn bad_ulpfec() {
+        let mut dec = FecDecoder::new();
+        assert!(!dec.insert_ulpfec(0x1234_5678.into(), &[0; 10]));
+
+        // E bit set.
+        let mut fec = vec![0; 20];
+        fec[0] = 0x80;
+        fec[12] = 0x80;
+        assert!(!dec.insert_ulpfec(0x1234_5678.into(), &fec));
+
+        // L bit set, but too short for the long mask.
+        let mut fec = vec![0; 16];
+        fec[0] = 0x40;
+        fec[12] = 0x80;
+        assert!(!dec.insert_ulpfec(0x1234_5678.into(), &fec));
+
+        // Empty mask.
+        assert!(!dec.insert_ulpfec(0x1234_5678.into(), &[0; 20]));
+    }
+}
@@ -5,7 +5,8 @@ use std::time::Instant;
 
 use crate::format::CodecConfig;
 use crate::format::PayloadParams;
-use crate::media::{KeyframeRequest, Media};
+use crate::media::{KeyframeRequest, KeyframeRequestPolicy, Media};
+use crate::rtp::vla::VideoLayersAllocation;
 use crate::rtp_::Ssrc;
 use crate::rtp_::{Bitrate, Pt};
 use crate::rtp_::{MediaTime, SenderInfo};
@@ -16,9 +17,15 @@ use crate::util::{already_happened, NonCryptographicRng};
 pub use self::receive::StreamRx;
 pub use self::send::StreamTx;
 
+pub(crate) use self::fec::FecDecoder;
+pub(crate) use self::rtcp_pacer::RtcpPacer;
+pub use self::rtcp_pacer::RtcpPacing;
+
+mod fec;
 mod receive;
 pub(crate) mod register;
 pub(crate) mod register_nack;
+mod rtcp_pacer;
 mod rtx_cache;
 pub(crate) mod rtx_cache_buf;
 mod send;
@@ -33,11 +40,104 @@ pub(crate) use send::DEFAULT_RTX_CACHE_DURATION;
 const RR_INTERVAL_VIDEO: Duration = Duration::from_millis(1000);
 const RR_INTERVAL_AUDIO: Duration = Duration::from_millis(5000);
 
-fn rr_interval(audio: bool) -> Duration {
-    if audio {
-        RR_INTERVAL_AUDIO
-    } else {
-        RR_INTERVAL_VIDEO
+// Initial guess of the average compound RTCP packet size (including UDP/IP
+// overhead) before we have sent anything.
+const INITIAL_AVG_RTCP_SIZE: f64 = 128.0;
+
+/// Scheduling of regular RTCP reports (SR/RR).
+///
+/// By default, str0m sends reports every second for video and every 5 seconds for audio,
+/// regardless of the number of streams. For large sessions, such as an SFU with many
+/// SSRCs, this can be a lot of traffic. Setting a session bandwidth makes the interval
+/// scale with the number of streams, as described in
+/// [RFC 3550 6.2](https://www.rfc-editor.org/rfc/rfc3550#section-6.2), so that reports
+/// stay within a fraction of the bandwidth.
+///
+/// The fixed intervals are always the minimum, the scaled interval can only be longer.
+///
+/// ```
+/// # use str0m::rtp::RtcpSchedule;
+/// # use str0m::bwe::Bitrate;
+/// let schedule = RtcpSchedule {
+///     session_bandwidth: Some(Bitrate::mbps(2)),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RtcpSchedule {
+    /// Minimum interval between reports for audio streams.
+    ///
+    /// Defaults to 5 seconds.
+    pub interval_audio: Duration,
+
+    /// Minimum interval between reports for video streams.
+    ///
+    /// Defaults to 1 second.
+    pub interval_video: Duration,
+
+    /// The session bandwidth used to scale the report interval.
+    ///
+    /// Defaults to `None`, which means the fixed intervals are used.
+    pub session_bandwidth: Option<Bitrate>,
+
+    /// Fraction of the session bandwidth to use for RTCP reports.
+    ///
+    /// Only used if `session_bandwidth` is set. Defaults to 0.05 (5%) as per RFC 3550.
+    pub bandwidth_fraction: f64,
+}
+
+impl Default for RtcpSchedule {
+    fn default() -> Self {
+        Self {
+            interval_audio: RR_INTERVAL_AUDIO,
+            interval_video: RR_INTERVAL_VIDEO,
+            session_bandwidth: None,
+            bandwidth_fraction: 0.05,
+        }
+    }
+}
+
+impl RtcpSchedule {
+    /// The report interval given the number of streams and the average RTCP packet size.
+    fn interval(&self, audio: bool, members: usize, avg_rtcp_size: f64) -> Duration {
+        let min = if audio {
+            self.interval_audio
+        } else {
+            self.interval_video
+        };
+
+        let Some(bw) = self.session_bandwidth else {
+            return min;
+        };
+
+        // bytes per second available for RTCP.
+        let rtcp_bw = bw.as_f64() * self.bandwidth_fraction / 8.0;
+
+        if !rtcp_bw.is_finite() || rtcp_bw <= 0.0 {
+            return min;
+        }
+
+        // RFC 3550 6.3.1, deterministic interval without the randomization.
+        let secs = members.max(1) as f64 * avg_rtcp_size / rtcp_bw;
+
+        min.max(Duration::from_secs_f64(secs.min(3600.0)))
+    }
+}
+
+/// Report intervals for audio and video at a given point in time.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ReportInterval {
+    audio: Duration,
+    video: Duration,
+}
+
+impl ReportInterval {
+    fn get(&self, audio: bool) -> Duration {
+        if audio {
+            self.audio
+        } else {
+            self.video
+        }
     }
 }
 
@@ -73,6 +173,13 @@ pub struct RtpPacket {
     /// If no Sender Report(SR) has been received or this packet is being sent by str0m this is [`None`].
     pub last_sender_info: Option<SenderInfo>,
 
+    /// Whether this packet ends a frame.
+    ///
+    /// For incoming packets this normalizes the codec specific signals, such as the VP9
+    /// payload descriptor, on top of the RTP marker bit. Audio packets always end a frame.
+    /// For outgoing packets it's the marker bit.
+    pub end_of_frame: bool,
+
     /// Whether this packet can be nacked.
     ///
     /// This is often false for audio, but might also be false for discardable frames when
@@ -98,11 +205,31 @@ pub struct StreamPaused {
     pub paused: bool,
 }
 
+/// Event when the remote sender signals a new Video Layers Allocation for an encoded stream.
+///
+/// Only happens if the VLA header extension is configured, see [`crate::rtp::vla`].
+#[derive(Debug, Clone)]
+pub struct StreamLayersAllocation {
+    /// The main SSRC of the encoded stream the allocation came on.
+    pub ssrc: Ssrc,
+
+    /// The mid the encoded stream belongs to.
+    pub mid: Mid,
+
+    /// The rid, if the encoded stream has a rid.
+    pub rid: Option<Rid>,
+
+    /// The allocation for all the simulcast streams of the sender.
+    ///
+    /// [`VideoLayersAllocation::current_simulcast_stream_index`] tells which one is this stream.
+    pub allocation: VideoLayersAllocation,
+}
+
 /// 255 is out of range for a real PT, which is 7 bit.
 const BLANK_PACKET_DEFAULT_PT: Pt = Pt::new_with_value(255);
 
 impl RtpPacket {
-    fn blank() -> RtpPacket {
+    pub(crate) fn blank() -> RtpPacket {
         RtpPacket {
             seq_no: 0.into(),
             time: MediaTime::from_90khz(0),
@@ -111,6 +238,7 @@ impl RtpPacket {
                 ..Default::default()
             },
             payload: vec![], // This payload is never used. See RtpHeader::create_padding_packet
+            end_of_frame: false,
             nackable: false,
             last_sender_info: None,
             timestamp: already_happened(),
@@ -148,6 +276,18 @@ pub(crate) struct Streams {
     /// Whether nack reports are enabled. This is an optimization to avoid too frequent
     /// Session::nack_at() when we don't need to send nacks.
     any_nack_active: Option<bool>,
+
+    /// Configured scheduling of regular reports.
+    rtcp_schedule: RtcpSchedule,
+
+    /// Running average of sent RTCP packet sizes in bytes, including UDP/IP overhead.
+    avg_rtcp_size: f64,
+
+    /// RTT to use before any stream has measured one.
+    initial_rtt: Option<Duration>,
+
+    /// Incoming mid/rid blocked by the application. A `None` rid blocks the entire mid.
+    blocked_rx: Vec<(Mid, Option<Rid>)>,
 }
 
 /// Delay between cleaning up the RxLookup.
@@ -163,21 +303,23 @@ struct RxLookup {
     last_used: Instant,
 }
 
-impl Default for Streams {
-    fn default() -> Self {
+impl Streams {
+    pub(crate) fn new(rtcp_schedule: RtcpSchedule, initial_rtt: Option<Duration>) -> Self {
         Self {
             streams_rx: Default::default(),
             rx_lookup: Default::default(),
             last_rx_lookup_cleanup: already_happened(),
             streams_tx: Default::default(),
             default_ssrc_tx: 0.into(), // this will be changed
-            mids_to_report: Vec::with_capacity(10),
+            mids_to_report: Vec::new(),
             any_nack_active: None,
+            rtcp_schedule,
+            avg_rtcp_size: INITIAL_AVG_RTCP_SIZE,
+            initial_rtt,
+            blocked_rx: Vec::new(),
         }
     }
-}
 
-impl Streams {
     pub(crate) fn map_dynamic_by_rid(
         &mut self,
         ssrc: Ssrc,
@@ -296,10 +438,13 @@ impl Streams {
         // New stream might have enabled nacks.
         self.any_nack_active = None;
 
-        let stream = self
-            .streams_rx
-            .entry(ssrc)
-            .or_insert_with(|| StreamRx::new(ssrc, mid, rid, suppress_nack));
+        let blocked = self.is_blocked_rx(mid, rid);
+
+        let stream = self.streams_rx.entry(ssrc).or_insert_with(|| {
+            let mut stream = StreamRx::new(ssrc, mid, rid, suppress_nack);
+            stream.set_blocked(blocked);
+            stream
+        });
 
         if let Some(rtx) = rtx {
             stream.maybe_reset_rtx(rtx);
@@ -308,6 +453,12 @@ impl Streams {
         stream
     }
 
+    /// Make room for more streams, to avoid growing the maps one stream at a time.
+    pub fn reserve(&mut self, rx: usize, tx: usize) {
+        self.streams_rx.reserve(rx);
+        self.streams_tx.reserve(tx);
+    }
+
     pub fn remove_stream_rx(&mut self, ssrc: Ssrc) -> bool {
         let stream = self.streams_rx.remove(&ssrc);
         let existed = stream.is_some();
@@ -341,6 +492,11 @@ impl Streams {
         self.streams_tx.get_mut(ssrc)
     }
 
+    /// The mid of an outgoing stream, if we have it.
+    pub(crate) fn mid_by_ssrc_tx(&self, ssrc: Ssrc) -> Option<Mid> {
+        self.streams_tx.get(&ssrc).map(|s| s.mid())
+    }
+
     /// Lookup the "main" SSRC and mid for a given SSRC(main or RTX).
     pub(crate) fn mid_ssrc_rx_by_ssrc_or_rtx(
         &mut self,
@@ -375,11 +531,63 @@ impl Streams {
     }
 
     pub(crate) fn regular_feedback_at(&self) -> Option<Instant> {
-        let r = self.streams_rx.values().map(|s| s.receiver_report_at());
-        let s = self.streams_tx.values().map(|s| s.sender_report_at());
+        let interval = self.report_interval();
+        let r = self
+            .streams_rx
+            .values()
+            .map(|s| s.receiver_report_at(interval));
+        let s = self
+            .streams_tx
+            .values()
+            .map(|s| s.sender_report_at(interval));
         r.chain(s).min()
     }
 
+    fn report_interval(&self) -> ReportInterval {
+        let members = self.streams_rx.len() + self.streams_tx.len();
+        let s = &self.rtcp_schedule;
+        ReportInterval {
+            audio: s.interval(true, members, self.avg_rtcp_size),
+            video: s.interval(false, members, self.avg_rtcp_size),
+        }
+    }
+
+    /// Update the running average of sent RTCP packet sizes.
+    pub(crate) fn record_rtcp_size(&mut self, size: usize) {
+        // RFC 3550 6.3.3
+        self.avg_rtcp_size = size as f64 / 16.0 + self.avg_rtcp_size * 15.0 / 16.0;
+    }
+
+    pub(crate) fn keyframe_request_at(
+        &self,
+        medias: &[Media],
+        session_rtt: Option<Duration>,
+    ) -> Option<Instant> {
+        let fallback_rtt = self.fallback_rtt(session_rtt);
+
+        self.streams_rx
+            .values()
+            .filter_map(|s| {
+                let policy = keyframe_request_policy(medias, s.mid());
+                s.keyframe_request_at(&policy, s.rtt().or(fallback_rtt))
+            })
+            .min()
+    }
+
+    /// RTT for incoming streams that have not measured their own.
+    ///
+    /// The RTT of a StreamRx comes from DLRR, which browsers rarely send. Before falling
+    /// back on the configured initial RTT, we use the RTT from the receiver reports of our
+    /// outgoing streams, and then the session RTT from TWCC.
+    fn fallback_rtt(&self, session_rtt: Option<Duration>) -> Option<Duration> {
+        self.streams_tx
+            .values()
+            .filter_map(|s| s.rtt())
+            .max()
+            .or(session_rtt)
+            .or(self.initial_rtt)
+    }
+
     pub(crate) fn paused_at(&self) -> Option<Instant> {
         self.streams_rx.values().find_map(|s| s.paused_at())
     }
@@ -403,17 +611,25 @@ impl Streams {
         do_nack: bool,
         medias: &[Media],
         config: &CodecConfig,
+        session_rtt: Option<Duration>,
         feedback: &mut VecDeque<Rtcp>,
     ) {
+        let interval = self.report_interval();
+        let fallback_rtt = self.fallback_rtt(session_rtt);
+
         self.mids_to_report.clear(); // Clear for checking StreamRx.
         for stream in self.streams_rx.values() {
-            if stream.need_rr(now) {
+            if stream.need_rr(now, interval) {
                 self.mids_to_report.push(stream.mid());
             }
         }
 
         for stream in self.streams_rx.values_mut() {
-            stream.maybe_create_keyframe_request(sender_ssrc, feedback);
+            // Each stream is paced by its own RTT, a fallback until it has measured one.
+            let rtt = stream.rtt().or(fallback_rtt);
+
+            let policy = keyframe_request_policy(medias, stream.mid());
+            stream.maybe_create_keyframe_request(now, rtt, sender_ssrc, &policy, feedback);
             stream.maybe_create_remb_request(sender_ssrc, feedback);
 
             // All StreamRx belonging to the same Mid are reported together.
@@ -422,7 +638,7 @@ impl Streams {
             }
 
             if do_nack {
-                stream.maybe_create_nack(sender_ssrc, feedback);
+                stream.maybe_create_nack(now, rtt, sender_ssrc, feedback);
             }
 
             stream.handle_timeout(now);
@@ -430,7 +646,7 @@ impl Streams {
 
         self.mids_to_report.clear(); // start over for StreamTx.
         for stream in self.streams_tx.values() {
-            if stream.need_sr(now) {
+            if stream.need_sr(now, interval) {
                 self.mids_to_report.push(stream.mid());
             }
         }
@@ -478,6 +694,12 @@ impl Streams {
         self.streams_rx.values_mut().find_map(|s| s.poll_paused())
     }
 
+    pub(crate) fn poll_layers_allocation(&mut self) -> Option<StreamLayersAllocation> {
+        self.streams_rx
+            .values_mut()
+            .find_map(|s| s.poll_layers_allocation())
+    }
+
     pub(crate) fn has_stream_rx(&self, ssrc: Ssrc) -> bool {
         self.streams_rx.contains_key(&ssrc)
     }
@@ -502,6 +724,15 @@ impl Streams {
             .collect()
     }
 
+    /// The media and FlexFEC SSRC of the send streams with FlexFEC.
+    pub(crate) fn flexfec_ssrcs_tx(&self, mid: Mid) -> Vec<(Ssrc, Ssrc)> {
+        self.streams_tx
+            .values()
+            .filter(|s| s.mid() == mid)
+            .filter_map(|s| Some((s.ssrc(), s.flexfec_ssrc()?)))
+            .collect()
+    }
+
     pub(crate) fn new_ssrc(&self) -> Ssrc {
         loop {
             let ssrc: Ssrc = (NonCryptographicRng::u32()).into();
@@ -523,6 +754,14 @@ impl Streams {
                 continue;
             }
 
+            let has_fec_tx = self
+                .streams_tx
+                .values()
+                .any(|s| s.flexfec_ssrc() == Some(ssrc));
+            if has_fec_tx {
+                continue;
+            }
+
             // Not used
             break ssrc;
         }
@@ -584,7 +823,48 @@ impl Streams {
             .find(|s| s.mid() == mid && (rid.is_none() || s.rid() == rid))
     }
 
+    /// Whether there is an incoming stream for the mid/rid, as looked up by dynamic mapping.
+    pub(crate) fn has_stream_rx_by_mid_rid(&self, mid: Mid, rid: Option<Rid>) -> bool {
+        self.streams_rx
+            .values()
+            .any(|s| s.mid() == mid && (rid.is_none() || s.rid() == rid))
+    }
+
+    /// Block or unblock all incoming media for a mid/rid, including streams that
+    /// appear later on. A `None` rid applies to every rid of the mid.
+    pub(crate) fn set_blocked_rx(&mut self, mid: Mid, rid: Option<Rid>, blocked: bool) {
+        self.blocked_rx
+            .retain(|(m, r)| *m != mid || (rid.is_some() && *r != rid));
+        if blocked {
+            self.blocked_rx.push((mid, rid));
+        }
+
+        for stream in self.streams_rx.values_mut() {
+            if stream.mid() == mid && (rid.is_none() || stream.rid() == rid) {
+                stream.set_blocked(blocked);
+            }
+        }
+    }
+
+    /// Whether incoming media for the mid/rid is blocked.
+    pub(crate) fn is_blocked_rx(&self, mid: Mid, rid: Option<Rid>) -> bool {
+        self.blocked_rx
+            .iter()
+            .any(|(m, r)| *m == mid && (r.is_none() || *r == rid))
+    }
+
+    /// Whether there is an incoming stream for the (main) SSRC.
+    pub(crate) fn has_stream_rx(&self, ssrc: Ssrc) -> bool {
+        self.streams_rx.contains_key(&ssrc)
+    }
+
+    /// Total number of incoming and outgoing streams.
+    pub(crate) fn stream_count(&self) -> usize {
+        self.streams_rx.len() + self.streams_tx.len()
+    }
+
     pub(crate) fn remove_streams_by_mid(&mut self, mid: Mid) {
+        self.blocked_rx.retain(|(m, _)| *m != mid);
         self.streams_tx.retain(|_, s| s.mid() != mid);
         self.streams_rx.retain(|_, s| s.mid() != mid);
         self.rx_lookup.retain(|_, v| v.mid != mid);
@@ -672,6 +952,15 @@ impl Streams {
     }
 }
 
+/// The keyframe request policy for the media with the given mid.
+fn keyframe_request_policy(medias: &[Media], mid: Mid) -> KeyframeRequestPolicy {
+    medias
+        .iter()
+        .find(|m| m.mid() == mid)
+        .map(|m| m.keyframe_request_policy())
+        .unwrap_or_default()
+}
+
 impl fmt::Debug for RtpPacket {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("RtpPacket")
@@ -679,8 +968,143 @@ impl fmt::Debug for RtpPacket {
             .field("time", &self.time)
             .field("header", &self.header)
             .field("payload", &self.payload.len())
+            .field("end_of_frame", &self.end_of_frame)
             .field("nackable", &self.nackable)
             .field("timestamp", &self.timestamp)
             .finish()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn rtcp_schedule_fixed_by_default() {
+        let s = RtcpSchedule::default();
+        assert_eq!(s.interval(false, 1000, 200.0), RR_INTERVAL_VIDEO);
+        assert_eq!(s.interval(true, 1000, 200.0), RR_INTERVAL_AUDIO);
+    }
+
+    #[test]
+    fn rtcp_schedule_scales_with_members() {
+        let s = RtcpSchedule {
+            session_bandwidth: Some(Bitrate::kbps(1600)),
+            ..Default::default()
+        };
+
+        // 1600kbps * 5% = 10_000 bytes/s. Few members stays at the minimum.
+        assert_eq!(s.interval(false, 10, 100.0), RR_INTERVAL_VIDEO);
+
+        // 500 members * 100 bytes = 50_000 bytes => 5 seconds.
+        assert_eq!(s.interval(false, 500, 100.0), Duration::from_secs(5));
+        assert_eq!(s.interval(true, 500, 100.0), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn keyframe_requests_paced_by_own_rtt() {
+        use crate::media::KeyframeRequestKind;
+        use crate::rtp_::{DlrrItem, RtcpFb};
+        use crate::util::InstantExt;
+
+        let now = Instant::now();
+        let initial = Duration::from_millis(100);
+        let mut streams = Streams::new(RtcpSchedule::default(), Some(initial));
+        let mid = Mid::from("v");
+        let config = CodecConfig::default();
+        let mut feedback = VecDeque::new();
+
+        // Stream 1 measures 500ms, stream 2 has no measurement.
+        let last_rr_time = ((now - Duration::from_millis(600)).as_ntp_64() >> 16) as u32;
+        let dlrr = DlrrItem {
+            ssrc: 1.into(),
+            last_rr_time,
+            last_rr_delay: 65536 / 10,
+        };
+        streams
+            .expect_stream_rx(1.into(), None, mid, None, false)
+            .handle_rtcp(now, RtcpFb::DlrrItem(dlrr));
+        streams.expect_stream_rx(2.into(), None, mid, None, false);
+
+        let request = |streams: &mut Streams, ssrc: u32| {
+            let s = streams.stream_rx(&ssrc.into()).unwrap();
+            s.request_keyframe(KeyframeRequestKind::Pli);
+        };
+
+        request(&mut streams, 1);
+        request(&mut streams, 2);
+        streams.handle_timeout(now, 0.into(), false, &[], &config, None, &mut feedback);
+        let plis = feedback.iter().filter(|r| matches!(r, Rtcp::Pli(_)));
+        assert_eq!(plis.count(), 2);
+
+        request(&mut streams, 1);
+        let at = streams.keyframe_request_at(&[], None).unwrap();
+        let rtt = at - now;
+        assert!(
+            rtt > Duration::from_millis(490) && rtt < Duration::from_millis(510),
+            "{:?}",
+            rtt
+        );
+
+        request(&mut streams, 2);
+        assert_eq!(streams.keyframe_request_at(&[], None), Some(now + initial));
+    }
+
+    #[test]
+    fn keyframe_requests_fall_back_to_rr_and_session_rtt() {
+        use crate::media::KeyframeRequestKind;
+        use crate::rtp_::{ReceptionReport, RtcpFb};
+        use crate::util::InstantExt;
+
+        let now = Instant::now();
+        let initial = Duration::from_millis(100);
+        let session = Duration::from_millis(200);
+        let mut streams = Streams::new(RtcpSchedule::default(), Some(initial));
+        let mid = Mid::from("v");
+        let config = CodecConfig::default();
+        let mut feedback = VecDeque::new();
+
+        let request = |streams: &mut Streams| {
+            let s = streams.stream_rx(&1.into()).unwrap();
+            s.request_keyframe(KeyframeRequestKind::Pli);
+        };
+
+        // The first request goes out straight away, the next one is held back.
+        streams.expect_stream_rx(1.into(), None, mid, None, false);
+        request(&mut streams);
+        streams.handle_timeout(now, 0.into(), false, &[], &config, None, &mut feedback);
+        let plis = feedback.iter().filter(|r| matches!(r, Rtcp::Pli(_)));
+        assert_eq!(plis.count(), 1);
+        request(&mut streams);
+
+        // Without any measurement, the session RTT is preferred over the initial.
+        assert_eq!(streams.keyframe_request_at(&[], None), Some(now + initial));
+        assert_eq!(
+            streams.keyframe_request_at(&[], Some(session)),
+            Some(now + session)
+        );
+
+        // An RR for an outgoing stream measures 300ms, which is preferred over both.
+        let last_sr_time = ((now - Duration::from_millis(400)).as_ntp_64() >> 16) as u32;
+        let rr = ReceptionReport {
+            ssrc: 2.into(),
+            fraction_lost: 0,
+            packets_lost: 0,
+            max_seq: 0,
+            jitter: 0,
+            last_sr_time,
+            last_sr_delay: 65536 / 10,
+        };
+        streams
+            .declare_stream_tx(2.into(), None, mid, None)
+            .handle_rtcp(now, RtcpFb::ReceptionReport(rr));
+
+        let at = streams.keyframe_request_at(&[], Some(session)).unwrap();
+        let rtt = at - now;
+        assert!(
+            rtt > Duration::from_millis(290) && rtt < Duration::from_millis(310),
+            "{:?}",
+            rtt
+        );
+    }
+}
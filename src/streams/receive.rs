@@ -1,7 +1,8 @@
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
-use crate::media::KeyframeRequestKind;
+use crate::media::{KeyframeRequestKind, KeyframeRequestPolicy};
+use crate::rtp::vla::VideoLayersAllocation;
 use crate::rtp_::{
     extend_u32, Bitrate, DlrrItem, ExtendedReport, Fir, FirEntry, Frequency, MediaTime, Remb,
 };
@@ -13,8 +14,11 @@ use crate::util::InstantExt;
 use crate::util::{already_happened, calculate_rtt_ms};
 
 use super::register::ReceiverRegister;
-use super::StreamPaused;
-use super::{rr_interval, RtpPacket};
+use super::{ReportInterval, RtpPacket};
+use super::{StreamLayersAllocation, StreamPaused};
+
+/// Number of frames the average inter-frame interval is smoothed over.
+const FRAME_INTERVAL_WINDOW: u32 = 30;
 
 /// Incoming encoded stream.
 ///
@@ -58,6 +62,9 @@ pub struct StreamRx {
     /// Last seen pt and clock_rate in
     last_clock_rate: Option<(Pt, Frequency)>,
 
+    /// Clock rate to use regardless of PT, if set via [`StreamRx::set_clock_rate`].
+    clock_rate_override: Option<Frequency>,
+
     /// Last received sender info.
     sender_info: Option<(Instant, SenderInfo)>,
 
@@ -80,6 +87,13 @@ pub struct StreamRx {
     /// If we have a pending keyframe request to send.
     pending_request_keyframe: Option<KeyframeRequestKind>,
 
+    /// Last time we sent a keyframe request.
+    last_keyframe_request: Option<Instant>,
+
+    /// Whether the stream started (or resumed after pause) since we last checked
+    /// the keyframe request policy.
+    stream_started: bool,
+
     /// If we have a pending REMB request to send.
     pending_request_remb: Option<Bitrate>,
 
@@ -108,6 +122,15 @@ pub struct StreamRx {
 
     /// The configured threshold before considering the lack of packets as going into paused.
     pause_threshold: Duration,
+
+    /// Last Video Layers Allocation signalled by the remote sender.
+    layers_allocation: Option<VideoLayersAllocation>,
+
+    /// Whether we need to emit an event for a changed layers allocation.
+    need_layers_allocation_event: bool,
+
+    /// Whether incoming media is dropped before reaching the application.
+    blocked: bool,
 }
 
 /// Holder of stats.
@@ -117,6 +140,8 @@ pub(crate) struct StreamRxStats {
     bytes: u64,
     /// count of packets received, including retransmissions
     packets: u64,
+    /// count of packets recovered with FEC or RED
+    packets_recovered: u64,
     /// count of FIR requests sent
     firs: u64,
     /// count of PLI requests sent
@@ -127,6 +152,20 @@ pub(crate) struct StreamRxStats {
     rtt: Option<f32>,
     /// fraction of packets lost from the last RR, if any
     loss: Option<f32>,
+    /// count of sequence number gaps, each likely to cause a concealment event
+    concealment_events: u64,
+    /// estimated samples (in RTP clock) missing from the gaps
+    concealed_samples: u64,
+    /// count of inter-frame arrival gaps long enough to be a freeze
+    freeze_count: u64,
+    /// sum of the inter-frame arrival gaps counted as freezes
+    total_freezes_duration: Duration,
+    /// (seq_no, rtp time) of the last main packet
+    last_packet: Option<(SeqNo, u64)>,
+    /// arrival of the last new frame (RTP timestamp)
+    last_frame_at: Option<Instant>,
+    /// moving average of the inter-frame arrival interval
+    avg_frame_interval: Option<Duration>,
 }
 
 impl StreamRx {
@@ -143,12 +182,15 @@ impl StreamRx {
             suppress_nack,
             last_used: already_happened(),
             last_clock_rate: None,
+            clock_rate_override: None,
             sender_info: None,
             reset_roc: None,
             register: None,
             register_rtx: None,
             last_time: None,
             pending_request_keyframe: None,
+            last_keyframe_request: None,
+            stream_started: false,
             pending_request_remb: None,
             fir_seq_no: 0,
             last_receiver_report: already_happened(),
@@ -157,6 +199,9 @@ impl StreamRx {
             paused: true,
             need_paused_event: false,
             pause_threshold: Duration::from_millis(1500),
+            layers_allocation: None,
+            need_layers_allocation_event: false,
+            blocked: false,
         }
     }
 
@@ -165,6 +210,13 @@ impl StreamRx {
         self.ssrc
     }
 
+    /// The last Video Layers Allocation signalled by the remote sender on this stream.
+    ///
+    /// Only available if the VLA header extension is configured, see [`crate::rtp::vla`].
+    pub fn layers_allocation(&self) -> Option<&VideoLayersAllocation> {
+        self.layers_allocation.as_ref()
+    }
+
     /// The resend (RTX) SSRC of this encoded stream.
     pub fn rtx(&self) -> Option<Ssrc> {
         self.rtx
@@ -193,16 +245,56 @@ impl StreamRx {
 
     /// Set threshold duration for emitting the paused event.
     ///
-    /// This event is emitted when no packet have received for this duration.
+    /// This event is emitted when no packet have received for this duration. An audio
+    /// sender that went silent with Opus DTX or comfort noise is not paused.
     pub fn set_pause_threshold(&mut self, t: Duration) {
         self.pause_threshold = t;
     }
 
+    /// Set the RTP clock rate of this stream.
+    ///
+    /// Normally the clock rate is inferred from the payload type of each packet. This
+    /// overrides that, which keeps the media time, jitter and other stats correct when
+    /// forwarding payloads whose clock rate str0m doesn't know (RTP mode). `None` goes
+    /// back to inferring from the payload type.
+    pub fn set_clock_rate(&mut self, clock_rate: Option<Frequency>) {
+        self.clock_rate_override = clock_rate;
+    }
+
+    pub(crate) fn clock_rate_override(&self) -> Option<Frequency> {
+        self.clock_rate_override
+    }
+
+    /// Block all incoming media for this stream.
+    ///
+    /// While blocked, packets are decrypted and accounted for in the transport (TWCC,
+    /// NACK, receiver reports), but dropped before they become raw packets, RTP events
+    /// or depacketized media. This guarantees no media of the stream reaches the
+    /// application, e.g. when a participant's permission to publish has expired.
+    ///
+    /// After unblocking, the first frames are likely to be undecodable. Consider
+    /// [requesting a keyframe][StreamRx::request_keyframe].
+    pub fn set_blocked(&mut self, blocked: bool) {
+        self.blocked = blocked;
+    }
+
+    /// Whether incoming media for this stream is blocked.
+    pub fn is_blocked(&self) -> bool {
+        self.blocked
+    }
+
     /// Request a keyframe for an incoming encoded stream.
     ///
     /// * SSRC the identifier of the remote encoded stream to request a keyframe for.
     /// * kind PLI or FIR.
+    ///
+    /// Subject to the [`KeyframeRequestPolicy`] of the media. Requests made before a
+    /// previous one has been sent are coalesced, keeping the most severe kind.
     pub fn request_keyframe(&mut self, kind: KeyframeRequestKind) {
+        let kind = match self.pending_request_keyframe {
+            Some(pending) => pending.coalesce(kind),
+            None => kind,
+        };
         self.pending_request_keyframe = Some(kind);
     }
 
@@ -221,9 +313,9 @@ impl StreamRx {
         self.suppress_nack = suppress;
     }
 
-    pub(crate) fn receiver_report_at(&self) -> Instant {
+    pub(crate) fn receiver_report_at(&self, interval: ReportInterval) -> Instant {
         let is_audio = self.rtx.is_none(); // this is maybe not correct, but it's all we got.
-        self.last_receiver_report + rr_interval(is_audio)
+        self.last_receiver_report + interval.get(is_audio)
     }
 
     pub(crate) fn handle_rtcp(&mut self, now: Instant, fb: RtcpFb) {
@@ -283,6 +375,13 @@ impl StreamRx {
         self.sender_info = Some((now, info));
     }
 
+    /// Round trip time from the last DLRR, if any.
+    pub(crate) fn rtt(&self) -> Option<Duration> {
+        self.stats
+            .rtt
+            .map(|ms| Duration::from_secs_f32(ms.max(0.0) / 1000.0))
+    }
+
     fn set_dlrr_item(&mut self, now: Instant, dlrr: DlrrItem) {
         let ntp_time = now.to_ntp_duration();
         let rtt = calculate_rtt_ms(ntp_time, dlrr.last_rr_delay, dlrr.last_rr_time);
@@ -293,6 +392,12 @@ impl StreamRx {
         self.check_paused_at
     }
 
+    /// The last packet was silence, an Opus DTX or comfort noise packet. The sender
+    /// might not send anything until the audio resumes, which is not a pause.
+    pub(crate) fn set_silent(&mut self) {
+        self.check_paused_at = None;
+    }
+
     pub(crate) fn handle_timeout(&mut self, now: Instant) {
         // No scheduled paused check?
         if self.check_paused_at.is_none() {
@@ -340,6 +445,21 @@ impl StreamRx {
         }
     }
 
+    /// Resync the register to an SRTP index found by ROC recovery.
+    ///
+    /// The register is restarted so that following packets are extended from `seq_no`.
+    pub(crate) fn resync_roc(&mut self, seq_no: SeqNo, is_repair: bool) -> SeqNo {
+        let register_ref = if is_repair {
+            &mut self.register_rtx
+        } else {
+            &mut self.register
+        };
+
+        *register_ref = Some(ReceiverRegister::new());
+
+        seq_no
+    }
+
     pub(crate) fn update_register(
         &mut self,
         now: Instant,
@@ -353,6 +473,7 @@ impl StreamRx {
         if self.paused {
             self.paused = false;
             self.need_paused_event = true;
+            self.stream_started = true;
         }
         self.check_paused_at = Some(now + self.pause_threshold);
 
@@ -375,6 +496,10 @@ impl StreamRx {
             self.last_time = Some(time);
         }
 
+        if !is_repair && is_new_packet {
+            self.stats.update_quality(now, seq_no, time);
+        }
+
         RegisterUpdateReceipt {
             time,
             is_new_packet,
@@ -382,6 +507,11 @@ impl StreamRx {
     }
 
     #[allow(clippy::too_many_arguments)]
+    /// Count a packet that was lost, but recovered with FEC or RED.
+    pub(crate) fn increase_packets_recovered(&mut self) {
+        self.stats.packets_recovered += 1;
+    }
+
     pub(crate) fn handle_rtp(
         &mut self,
         now: Instant,
@@ -402,9 +532,17 @@ impl StreamRx {
             }
         }
 
+        if let Some(vla) = header.ext_vals.user_values.get::<VideoLayersAllocation>() {
+            if self.layers_allocation.as_ref() != Some(vla) {
+                self.layers_allocation = Some(vla.clone());
+                self.need_layers_allocation_event = true;
+            }
+        }
+
         let packet = RtpPacket {
             seq_no,
             time,
+            end_of_frame: header.marker,
             header,
             payload: data,
             nackable: false,
@@ -437,15 +575,53 @@ impl StreamRx {
         header.ext_vals.rid = header.ext_vals.rid_repair.take();
     }
 
+    /// When a held back keyframe request is allowed to be sent, if any.
+    ///
+    /// Requests are held back for at least one `rtt`, since a keyframe
+    /// answering the previous request can't arrive sooner.
+    pub(crate) fn keyframe_request_at(
+        &self,
+        policy: &KeyframeRequestPolicy,
+        rtt: Option<Duration>,
+    ) -> Option<Instant> {
+        if self.stream_started && policy.on_stream_start.is_some() {
+            return Some(already_happened());
+        }
+        self.pending_request_keyframe?;
+        let last = self.last_keyframe_request?;
+        let interval = policy.min_interval.max(rtt.unwrap_or(Duration::ZERO));
+        Some(last + interval)
+    }
+
     pub(crate) fn maybe_create_keyframe_request(
         &mut self,
+        now: Instant,
+        rtt: Option<Duration>,
         sender_ssrc: Ssrc,
+        policy: &KeyframeRequestPolicy,
         feedback: &mut VecDeque<Rtcp>,
     ) {
+        if self.stream_started {
+            self.stream_started = false;
+            if let Some(kind) = policy.on_stream_start {
+                self.request_keyframe(kind);
+            }
+        }
+
+        if let Some(at) = self.keyframe_request_at(policy, rtt) {
+            if now < at {
+                // Debounce. The request is sent once the interval has passed.
+                return;
+            }
+        }
+
         let Some(kind) = self.pending_request_keyframe.take() else {
             return;
         };
 
+        self.last_keyframe_request = Some(now);
+
+        let kind = policy.force_kind.unwrap_or(kind);
         let ssrc = self.ssrc;
 
         match kind {
@@ -490,8 +666,8 @@ impl StreamRx {
         x
     }
 
-    pub(crate) fn need_rr(&self, now: Instant) -> bool {
-        now >= self.receiver_report_at()
+    pub(crate) fn need_rr(&self, now: Instant, interval: ReportInterval) -> bool {
+        now >= self.receiver_report_at(interval)
     }
 
     pub(crate) fn create_rr_and_update(
@@ -581,6 +757,8 @@ impl StreamRx {
 
     pub(crate) fn maybe_create_nack(
         &mut self,
+        now: Instant,
+        rtt: Option<Duration>,
         sender_ssrc: Ssrc,
         feedback: &mut VecDeque<Rtcp>,
     ) -> Option<()> {
@@ -588,7 +766,10 @@ impl StreamRx {
             return None;
         }
 
-        let nacks = self.register.as_mut().and_then(|r| r.nack_report())?;
+        let nacks = self
+            .register
+            .as_mut()
+            .and_then(|r| r.nack_report(now, rtt))?;
 
         for mut nack in nacks {
             nack.sender_ssrc = sender_ssrc;
@@ -606,6 +787,11 @@ impl StreamRx {
         self.stats.fill(snapshot, self.mid, self.rid, now);
     }
 
+    /// Interarrival jitter of the main (non-RTX) packets, if any were received.
+    pub(crate) fn jitter(&self) -> Option<Duration> {
+        self.register.as_ref().map(|r| r.jitter())
+    }
+
     pub(crate) fn poll_paused(&mut self) -> Option<StreamPaused> {
         if !self.need_paused_event {
             return None;
@@ -629,6 +815,21 @@ impl StreamRx {
         })
     }
 
+    pub(crate) fn poll_layers_allocation(&mut self) -> Option<StreamLayersAllocation> {
+        if !self.need_layers_allocation_event {
+            return None;
+        }
+
+        self.need_layers_allocation_event = false;
+
+        Some(StreamLayersAllocation {
+            ssrc: self.ssrc,
+            mid: self.mid,
+            rid: self.rid,
+            allocation: self.layers_allocation.clone()?,
+        })
+    }
+
     pub(crate) fn reset_buffers(&mut self) {
         if let Some(r) = &mut self.register {
             r.clear();
@@ -704,6 +905,72 @@ impl StreamRxStats {
         self.loss = Some(fraction_lost as f32 / u8::MAX as f32)
     }
 
+    /// Estimate concealment and freezes from the main (non-RTX) packets arriving.
+    fn update_quality(&mut self, now: Instant, seq_no: SeqNo, time: MediaTime) {
+        let frequency = time.frequency();
+        let time = time.numer();
+
+        let Some((last_seq, last_time)) = self.last_packet else {
+            self.last_packet = Some((seq_no, time));
+            self.last_frame_at = Some(now);
+            return;
+        };
+
+        // Late or resent packets don't move the reference forward.
+        if *seq_no <= *last_seq {
+            return;
+        }
+        self.last_packet = Some((seq_no, time));
+
+        // A gap in sequence numbers means the receiver has to conceal. The missing
+        // duration is estimated from the time advanced per packet across the gap.
+        let packets = *seq_no - *last_seq;
+        if packets > 1 && time > last_time {
+            let per_packet = (time - last_time) / packets;
+            self.concealment_events += 1;
+            self.concealed_samples += per_packet * (packets - 1);
+        }
+
+        // Packets of the same frame share the RTP time.
+        if time == last_time {
+            return;
+        }
+
+        if let Some(last_frame_at) = self.last_frame_at {
+            let interval = now.saturating_duration_since(last_frame_at);
+
+            // Same definition as webrtc-stats: a freeze is an interval that is more
+            // than max(3 * avg, avg + 150ms).
+            if let Some(avg) = self.avg_frame_interval {
+                let threshold = (avg * 3).max(avg + Duration::from_millis(150));
+
+                // A sender that goes silent, like audio in DTX, advances the RTP time as
+                // much as the interval without losing packets. That is not a freeze, and
+                // not a frame interval.
+                let media =
+                    Duration::from(MediaTime::new(time.saturating_sub(last_time), frequency));
+                let silent = packets == 1 && interval.saturating_sub(media) <= threshold;
+
+                if interval > threshold && silent {
+                    self.last_frame_at = Some(now);
+                    return;
+                }
+
+                if interval > threshold {
+                    self.freeze_count += 1;
+                    self.total_freezes_duration += interval;
+                }
+            }
+
+            self.avg_frame_interval = Some(match self.avg_frame_interval {
+                Some(avg) => (avg * (FRAME_INTERVAL_WINDOW - 1) + interval) / FRAME_INTERVAL_WINDOW,
+                None => interval,
+            });
+        }
+
+        self.last_frame_at = Some(now);
+    }
+
     pub(crate) fn fill(
         &mut self,
         snapshot: &mut StatsSnapshot,
@@ -721,11 +988,16 @@ impl StreamRxStats {
             rid,
             bytes: self.bytes,
             packets: self.packets,
+            packets_recovered: self.packets_recovered,
             firs: self.firs,
             plis: self.plis,
             nacks: self.nacks,
             rtt: self.rtt,
             loss: self.loss,
+            concealment_events: self.concealment_events,
+            concealed_samples: self.concealed_samples,
+            freeze_count: self.freeze_count,
+            total_freezes_duration: self.total_freezes_duration,
             timestamp: now,
         };
 
@@ -746,3 +1018,84 @@ pub(crate) struct RegisterUpdateReceipt {
     pub time: MediaTime,
     pub is_new_packet: bool,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn concealment_and_freeze_estimate() {
+        let now = Instant::now();
+        let mut stats = StreamRxStats::default();
+        let freq = Frequency::NINETY_KHZ;
+
+        // 30 fps, one packet per frame.
+        let mut seq = 0_u64;
+        let mut at = now;
+        let frame = |stats: &mut StreamRxStats, seq: u64, at: Instant| {
+            stats.update_quality(at, seq.into(), MediaTime::new(seq * 3000, freq));
+        };
+
+        for _ in 0..60 {
+            frame(&mut stats, seq, at);
+            seq += 1;
+            at += Duration::from_millis(33);
+        }
+
+        assert_eq!(stats.concealment_events, 0);
+        assert_eq!(stats.freeze_count, 0);
+
+        // Two packets lost.
+        seq += 2;
+        frame(&mut stats, seq, at);
+        seq += 1;
+        at += Duration::from_millis(33);
+
+        assert_eq!(stats.concealment_events, 1);
+        assert_eq!(stats.concealed_samples, 6000);
+
+        // Half a second without frames.
+        at += Duration::from_millis(500);
+        frame(&mut stats, seq, at);
+
+        assert_eq!(stats.freeze_count, 1);
+        assert_eq!(stats.total_freezes_duration, Duration::from_millis(533));
+    }
+
+    #[test]
+    fn dtx_is_not_freeze() {
+        let now = Instant::now();
+        let mut stats = StreamRxStats::default();
+        let freq = Frequency::FORTY_EIGHT_KHZ;
+
+        let mut seq = 0_u64;
+        let mut time = 0_u64;
+        let mut at = now;
+        let mut packet = |stats: &mut StreamRxStats, ms: u64| {
+            at += Duration::from_millis(ms);
+            time += ms * 48;
+            stats.update_quality(at, seq.into(), MediaTime::new(time, freq));
+            seq += 1;
+        };
+
+        // 20ms audio packets.
+        for _ in 0..50 {
+            packet(&mut stats, 20);
+        }
+
+        // DTX packets every 400ms, without loss.
+        for _ in 0..10 {
+            packet(&mut stats, 400);
+        }
+
+        assert_eq!(stats.freeze_count, 0);
+
+        // Speech again.
+        for _ in 0..10 {
+            packet(&mut stats, 20);
+        }
+
+        assert_eq!(stats.freeze_count, 0);
+        assert_eq!(stats.concealment_events, 0);
+    }
+}
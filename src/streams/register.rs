@@ -1,4 +1,4 @@
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use crate::rtp_::{Nack, ReceptionReport, SeqNo};
 
@@ -90,8 +90,12 @@ impl ReceiverRegister {
     }
 
     /// Generates a NACK report
-    pub fn nack_report(&mut self) -> Option<impl Iterator<Item = Nack>> {
-        self.nack.nack_reports()
+    pub fn nack_report(
+        &mut self,
+        now: Instant,
+        rtt: Option<Duration>,
+    ) -> Option<impl Iterator<Item = Nack>> {
+        self.nack.nack_reports(now, rtt)
     }
 
     /// Create a new reception report.
@@ -115,6 +119,12 @@ impl ReceiverRegister {
         })
     }
 
+    /// Current jitter estimate.
+    pub fn jitter(&self) -> Duration {
+        // TimePoint::delta is in microseconds.
+        Duration::from_micros(self.jitter as u64)
+    }
+
     pub fn max_seq(&self) -> Option<SeqNo> {
         self.nack.max_seq()
     }
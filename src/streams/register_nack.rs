@@ -1,4 +1,5 @@
 use std::ops::Range;
+use std::time::{Duration, Instant};
 
 use crate::rtp_::{Nack, NackEntry, ReportList, SeqNo};
 
@@ -26,11 +27,25 @@ pub struct NackRegister {
 struct PacketStatus {
     received: bool,
     nack_count: u8,
+    last_nack: Option<Instant>,
 }
 
 impl PacketStatus {
-    fn needs_nack(&self) -> bool {
-        !self.received && self.nack_count < MAX_NACKS
+    /// Whether this packet should be in the next report.
+    ///
+    /// With a known `rtt`, a packet is not nacked again until the retransmission
+    /// of the previous NACK had a chance to arrive.
+    fn needs_nack(&self, now: Instant, rtt: Option<Duration>) -> bool {
+        let waiting = match (self.last_nack, rtt) {
+            (Some(last), Some(rtt)) => now < last + rtt,
+            _ => false,
+        };
+        !self.received && self.nack_count < MAX_NACKS && !waiting
+    }
+
+    fn mark_nacked(&mut self, now: Instant) {
+        self.nack_count += 1;
+        self.last_nack = Some(now);
     }
 
     fn mark_received(&mut self) -> bool {
@@ -42,6 +57,7 @@ impl PacketStatus {
     fn reset(&mut self) {
         self.received = false;
         self.nack_count = 0;
+        self.last_nack = None;
     }
 }
 
@@ -49,26 +65,31 @@ struct NackIterator<'a> {
     reg: &'a mut NackRegister,
     next: u64,
     end: u64,
+    now: Instant,
+    rtt: Option<Duration>,
 }
 
 impl<'a> Iterator for NackIterator<'a> {
     type Item = NackEntry;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.next = (self.next..=self.end).find(|s| self.reg.packet((*s).into()).needs_nack())?;
+        let (now, rtt) = (self.now, self.rtt);
+
+        self.next =
+            (self.next..=self.end).find(|s| self.reg.packet((*s).into()).needs_nack(now, rtt))?;
 
         let mut entry = NackEntry {
             pid: (self.next % U16_MAX) as u16,
             blp: 0,
         };
 
-        self.reg.packet(self.next.into()).nack_count += 1;
+        self.reg.packet(self.next.into()).mark_nacked(now);
         self.next += 1;
 
         for (i, s) in (self.next..self.end).take(16).enumerate() {
             let packet = self.reg.packet(s.into());
-            if packet.needs_nack() {
-                self.reg.packet(self.next.into()).nack_count += 1;
+            if packet.needs_nack(now, rtt) {
+                self.reg.packet(self.next.into()).mark_nacked(now);
                 entry.blp |= 1 << i
             }
             self.next += 1;
@@ -146,16 +167,23 @@ impl NackRegister {
 
     /// Create a new nack report
     ///
-    /// This modifies the state as it counts how many times packets have been nacked
-    pub fn nack_reports(&mut self) -> Option<impl Iterator<Item = Nack>> {
+    /// This modifies the state as it counts how many times packets have been nacked.
+    /// Packets nacked less than `rtt` ago are left out.
+    pub fn nack_reports(
+        &mut self,
+        now: Instant,
+        rtt: Option<Duration>,
+    ) -> Option<impl Iterator<Item = Nack>> {
         let Range { start, end } = self.active.clone()?;
-        let start = (*start..=*end).find(|s| self.packet((*s).into()).needs_nack())?;
+        let start = (*start..=*end).find(|s| self.packet((*s).into()).needs_nack(now, rtt))?;
 
         Some(
             ReportList::lists_from_iter(NackIterator {
                 reg: self,
                 next: start,
                 end: *end,
+                now,
+                rtt,
             })
             .into_iter()
             .map(|reports| {
@@ -181,6 +209,7 @@ impl NackRegister {
 #[cfg(test)]
 mod test {
     use std::ops::Range;
+    use std::time::{Duration, Instant};
 
     use crate::streams::register_nack::MAX_MISORDER;
 
@@ -271,13 +300,13 @@ mod test {
     #[test]
     fn nack_report_none() {
         let mut reg = NackRegister::new();
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
 
         reg.update(110.into());
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
 
         reg.update(111.into());
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
     }
 
     #[test]
@@ -291,13 +320,16 @@ mod test {
     #[test]
     fn nack_report_one() {
         let mut reg = NackRegister::new();
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
 
         reg.update(110.into());
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
 
         reg.update(112.into());
-        let report = reg.nack_reports().map(Vec::from_iter).expect("some report");
+        let report = reg
+            .nack_reports(Instant::now(), None)
+            .map(Vec::from_iter)
+            .expect("some report");
         assert!(report.len() == 1);
         assert_eq!(report[0].reports.len(), 1);
         assert_eq!(report[0].reports[0].pid, 111);
@@ -307,13 +339,16 @@ mod test {
     #[test]
     fn nack_report_two() {
         let mut reg = NackRegister::new();
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
 
         reg.update(110.into());
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
 
         reg.update(113.into());
-        let report = reg.nack_reports().map(Vec::from_iter).expect("some report");
+        let report = reg
+            .nack_reports(Instant::now(), None)
+            .map(Vec::from_iter)
+            .expect("some report");
         assert!(report.len() == 1);
         assert_eq!(report[0].reports.len(), 1);
         assert_eq!(report[0].reports[0].pid, 111);
@@ -328,7 +363,10 @@ mod test {
             reg.update((*i).into());
         }
 
-        let report = reg.nack_reports().map(Vec::from_iter).expect("some report");
+        let report = reg
+            .nack_reports(Instant::now(), None)
+            .map(Vec::from_iter)
+            .expect("some report");
         assert!(report.len() == 1);
         assert_eq!(report[0].reports.len(), 1);
         assert_eq!(report[0].reports[0].pid, 102);
@@ -349,7 +387,10 @@ mod test {
             reg.update((*i).into());
         }
 
-        let report = reg.nack_reports().map(Vec::from_iter).expect("some report");
+        let report = reg
+            .nack_reports(Instant::now(), None)
+            .map(Vec::from_iter)
+            .expect("some report");
         assert_eq!(report.len(), 1);
         assert_eq!(report[0].reports.len(), 2);
         assert_eq!(report[0].reports[0].pid, 102);
@@ -370,7 +411,10 @@ mod test {
             reg.update((*i).into());
         }
 
-        let report = reg.nack_reports().map(Vec::from_iter).expect("some report");
+        let report = reg
+            .nack_reports(Instant::now(), None)
+            .map(Vec::from_iter)
+            .expect("some report");
         assert_eq!(report.len(), 1);
         assert_eq!(report[0].reports.len(), 1);
         assert_eq!(report[0].reports[0].pid, 102);
@@ -391,7 +435,7 @@ mod test {
             reg.update((*i).into());
         }
 
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
     }
 
     #[test]
@@ -402,7 +446,7 @@ mod test {
         ] {
             reg.update((*i).into());
         }
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
         let active = reg.active.clone().expect("nack range");
         assert_eq!(*active.start, 105);
 
@@ -411,13 +455,13 @@ mod test {
         ] {
             reg.update((*i).into());
         }
-        assert!(reg.nack_reports().is_some());
+        assert!(reg.nack_reports(Instant::now(), None).is_some());
         let active = reg.active.clone().expect("nack range");
         assert_eq!(*active.start, 107);
 
         reg.update(107.into()); // Got 107 via RTX
 
-        let nacks = reg.nack_reports().map(Vec::from_iter);
+        let nacks = reg.nack_reports(Instant::now(), None).map(Vec::from_iter);
         assert!(
             nacks.is_none(),
             "Expected no NACKs to be generated after repairing the stream, got {nacks:?}"
@@ -470,7 +514,10 @@ mod test {
         reg.update(3000.into());
         reg.update(3001.into());
 
-        let reports = reg.nack_reports().map(Vec::from_iter).expect("some report");
+        let reports = reg
+            .nack_reports(Instant::now(), None)
+            .map(Vec::from_iter)
+            .expect("some report");
         assert_eq!(reports.len(), 1);
         assert_eq!(reports[0].reports[0].pid, 2999);
         assert_eq!(reports[0].reports[0].blp, 4);
@@ -496,7 +543,10 @@ mod test {
         reg.update(5996.into());
         reg.update(5997.into());
 
-        let reports = reg.nack_reports().map(Vec::from_iter).expect("some report");
+        let reports = reg
+            .nack_reports(Instant::now(), None)
+            .map(Vec::from_iter)
+            .expect("some report");
         assert_eq!(reports.len(), 1);
         assert_eq!(reports[0].reports[0].pid, 5995);
     }
@@ -516,7 +566,10 @@ mod test {
                 reg.update((*i).into());
             }
 
-            let reports = reg.nack_reports().map(Vec::from_iter).expect("some report");
+            let reports = reg
+                .nack_reports(Instant::now(), None)
+                .map(Vec::from_iter)
+                .expect("some report");
             let pid = reports[0].reports[0].pid;
             assert_eq!(pid, *expected);
         }
@@ -529,7 +582,7 @@ mod test {
             reg.update(i.into());
         }
 
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
         let active = reg.active.clone().expect("nack range");
         assert_eq!(*active.start, 3003);
 
@@ -537,7 +590,7 @@ mod test {
             reg.update(i.into());
         }
 
-        let report = reg.nack_reports().map(Vec::from_iter);
+        let report = reg.nack_reports(Instant::now(), None).map(Vec::from_iter);
         assert!(report.is_none(), "Expected empty NACKs got {:?}", report);
         let active = reg.active.clone().expect("nack range");
         assert_eq!(*active.start, 3008);
@@ -549,7 +602,7 @@ mod test {
         for i in 65500..=65534 {
             reg.update(i.into());
         }
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
         let active = reg.active.clone().expect("nack range");
         assert_eq!(*active.start, 65534);
 
@@ -557,7 +610,7 @@ mod test {
             reg.update(i.into());
         }
 
-        assert!(reg.nack_reports().is_some());
+        assert!(reg.nack_reports(Instant::now(), None).is_some());
         let active = reg.active.clone().expect("nack range");
         assert_eq!(*active.start, 65535);
 
@@ -567,8 +620,30 @@ mod test {
 
         reg.update(65535.into());
 
-        assert!(reg.nack_reports().is_none());
+        assert!(reg.nack_reports(Instant::now(), None).is_none());
         let active = reg.active.clone().expect("nack range");
         assert_eq!(*active.start, 65666);
     }
+
+    #[test]
+    fn nack_paced_by_rtt() {
+        let mut reg = NackRegister::new();
+        let now = Instant::now();
+        let rtt = Some(Duration::from_millis(100));
+
+        reg.update(110.into());
+        reg.update(112.into());
+
+        assert!(reg.nack_reports(now, rtt).is_some());
+
+        // Within one RTT of the previous NACK, the retransmission may still be on its way.
+        let at = now + Duration::from_millis(50);
+        assert!(reg.nack_reports(at, rtt).is_none());
+
+        // Without a known RTT, the packet is nacked again.
+        assert!(reg.nack_reports(at, None).is_some());
+
+        let at = now + Duration::from_millis(150);
+        assert!(reg.nack_reports(at, rtt).is_some());
+    }
 }
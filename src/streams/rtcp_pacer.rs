@@ -0,0 +1,142 @@
+use std::time::{Duration, Instant};
+
+use crate::rtp_::Bitrate;
+
+/// Pacing of outgoing RTCP feedback.
+///
+/// Burst loss across many streams can make str0m generate a lot of NACK and TWCC
+/// feedback at the same time. Without pacing all of it goes out as back-to-back
+/// datagrams. With pacing, the feedback is still compounded up to the MTU, but the
+/// datagrams are released under a token bucket of `burst` bytes refilled at `rate`.
+///
+/// ```
+/// # use str0m::rtp::RtcpPacing;
+/// # use str0m::bwe::Bitrate;
+/// let pacing = RtcpPacing {
+///     rate: Bitrate::kbps(500),
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RtcpPacing {
+    /// Bytes of RTCP that can be sent back to back before pacing kicks in.
+    ///
+    /// Defaults to 6000 bytes, which is about five full datagrams.
+    pub burst: usize,
+
+    /// The rate at which the burst budget refills. Must be above zero.
+    ///
+    /// Defaults to 1 Mbit/s.
+    pub rate: Bitrate,
+}
+
+impl Default for RtcpPacing {
+    fn default() -> Self {
+        Self {
+            burst: 6000,
+            rate: Bitrate::mbps(1),
+        }
+    }
+}
+
+pub(crate) struct RtcpPacer {
+    pacing: RtcpPacing,
+    /// Available budget in bytes. Goes negative by at most one datagram.
+    budget: f64,
+    last: Option<Instant>,
+}
+
+impl RtcpPacer {
+    pub fn new(pacing: RtcpPacing) -> Self {
+        RtcpPacer {
+            pacing,
+            budget: pacing.burst as f64,
+            last: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        if let Some(last) = self.last {
+            let elapsed = now.saturating_duration_since(last).as_secs_f64();
+            let refill = elapsed * self.pacing.rate.as_f64() / 8.0;
+            self.budget = (self.budget + refill).min(self.pacing.burst as f64);
+        }
+        self.last = Some(now);
+    }
+
+    /// Whether a datagram can be sent now.
+    pub fn can_send(&mut self, now: Instant) -> bool {
+        self.refill(now);
+        self.budget > 0.0
+    }
+
+    /// Account for a sent datagram.
+    pub fn sent(&mut self, bytes: usize) {
+        self.budget -= bytes as f64;
+    }
+
+    /// When the budget is positive again, if we are currently held back.
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        if self.budget > 0.0 {
+            return None;
+        }
+
+        let last = self.last?;
+        let rate = self.pacing.rate.as_f64();
+
+        // +1 byte to end up above zero.
+        let secs = (1.0 - self.budget) * 8.0 / rate;
+
+        Some(last + Duration::from_secs_f64(secs))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn burst_then_pace() {
+        let now = Instant::now();
+        let mut pacer = RtcpPacer::new(RtcpPacing {
+            burst: 2400,
+            rate: Bitrate::kbps(96),
+        });
+
+        // Burst goes out straight away.
+        assert!(pacer.can_send(now));
+        pacer.sent(1200);
+        assert!(pacer.can_send(now));
+        pacer.sent(1200);
+        assert!(!pacer.can_send(now));
+
+        // 96kbps is 12000 bytes/s, 1 byte takes ~83us.
+        let at = pacer.poll_timeout().unwrap();
+        assert!(at > now);
+        assert!(at - now < Duration::from_millis(1));
+
+        assert!(pacer.can_send(at));
+        pacer.sent(1200);
+
+        // 1200 bytes takes 100ms to refill.
+        let at2 = pacer.poll_timeout().unwrap();
+        assert!(at2 - at >= Duration::from_millis(99));
+    }
+
+    #[test]
+    fn budget_capped_at_burst() {
+        let now = Instant::now();
+        let mut pacer = RtcpPacer::new(RtcpPacing {
+            burst: 1200,
+            rate: Bitrate::kbps(96),
+        });
+
+        assert!(pacer.can_send(now));
+
+        // A long idle time doesn't accumulate more than the burst.
+        let later = now + Duration::from_secs(10);
+        assert!(pacer.can_send(later));
+        pacer.sent(1200);
+        assert!(!pacer.can_send(later));
+    }
+}
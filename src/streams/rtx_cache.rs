@@ -92,6 +92,7 @@ mod test {
             payload: millis.to_be_bytes().to_vec(),
             timestamp: after(now, millis),
             last_sender_info: None,
+            end_of_frame: false,
             nackable: true,
         }
     }
@@ -28,9 +28,10 @@ use crate::util::{already_happened, calculate_rtt_ms, not_happening};
 use crate::util::{InstantExt, NonCryptographicRng};
 use crate::RtcError;
 
+use super::fec::{FecEncoder, FecFormat};
 use super::rtx_cache::RtxCache;
 use super::send_queue::SendQueue;
-use super::{rr_interval, RtpPacket};
+use super::{ReportInterval, RtpPacket};
 
 /// The smallest size of padding for which we attempt to use a spurious resend. For padding
 /// requests smaller than this we use blank packets instead.
@@ -66,6 +67,9 @@ pub struct StreamTx {
     /// The last main payload clock rate that was sent.
     clock_rate: Option<Frequency>,
 
+    /// Clock rate to use regardless of PT, if set via [`StreamTx::set_clock_rate`].
+    clock_rate_override: Option<Frequency>,
+
     /// If we are doing seq_no ourselves (when writing sample mode).
     seq_no: SeqNo,
 
@@ -120,6 +124,32 @@ pub struct StreamTx {
     // The _main_ PT to use for padding. This is main PT, since the poll_packet() loop
     // figures out the param.resend() RTX PT using main.
     pt_for_padding: Option<Pt>,
+
+    /// SSRC for FlexFEC, announced in the SDP before FlexFEC is in use.
+    flexfec_ssrc: Option<Ssrc>,
+
+    /// FlexFEC protection of the sent packets, if negotiated.
+    flexfec: Option<FlexFecTx>,
+
+    /// ULPFEC protection of the sent packets, if negotiated and enabled.
+    ulpfec: Option<UlpfecTx>,
+}
+
+/// The FEC stream sent alongside the media.
+#[derive(Debug)]
+struct FlexFecTx {
+    ssrc: Ssrc,
+    pt: Pt,
+    seq_no: SeqNo,
+    encoder: FecEncoder,
+}
+
+/// The ULPFEC sent wrapped in RED, in the same SSRC as the media.
+#[derive(Debug)]
+struct UlpfecTx {
+    red_pt: Pt,
+    ulpfec_pt: Pt,
+    encoder: FecEncoder,
 }
 
 /// Holder of stats.
@@ -146,6 +176,8 @@ pub(crate) struct StreamTxStats {
     rtt: Option<f32>,
     /// losses collecter from RR (known packets, lost ratio)
     losses: Vec<(u64, f32)>,
+    /// interarrival jitter (RTP clock) from the last RR, if any
+    jitter: Option<u32>,
     bytes_transmitted: ValueHistory<u64>,
     bytes_retransmitted: ValueHistory<u64>,
 }
@@ -168,6 +200,7 @@ impl StreamTx {
             kind: None,
             cname: None,
             clock_rate: None,
+            clock_rate_override: None,
             seq_no,
             seq_no_rtx,
             last_used: already_happened(),
@@ -184,6 +217,9 @@ impl StreamTx {
             stats: StreamTxStats::default(),
             rtx_ratio: (0.0, already_happened()),
             pt_for_padding: None,
+            flexfec_ssrc: None,
+            flexfec: None,
+            ulpfec: None,
         }
     }
 
@@ -231,6 +267,16 @@ impl StreamTx {
         self.unpaced = Some(unpaced);
     }
 
+    /// Set the RTP clock rate of this stream.
+    ///
+    /// Normally the clock rate is taken from the payload type of each packet. This
+    /// overrides that, which keeps sender reports and stats correct when forwarding
+    /// payloads whose clock rate str0m doesn't know (RTP mode). `None` goes back to
+    /// using the payload type.
+    pub fn set_clock_rate(&mut self, clock_rate: Option<Frequency>) {
+        self.clock_rate_override = clock_rate;
+    }
+
     /// Write RTP packet to a send stream.
     ///
     /// The `payload` argument is expected to be only the RTP payload, not the RTP packet header.
@@ -291,6 +337,7 @@ impl StreamTx {
             time: media_time,
             header,
             payload,
+            end_of_frame: marker,
             nackable,
             // The overall idea for str0m is to only drive time forward from handle_input. If we
             // used a "now" argument to write_rtp(), we effectively get a second point that also need
@@ -310,6 +357,151 @@ impl StreamTx {
         Ok(())
     }
 
+    /// The SSRC of the FlexFEC packets, if set or in use.
+    pub(crate) fn flexfec_ssrc(&self) -> Option<Ssrc> {
+        self.flexfec_ssrc
+    }
+
+    /// Set the SSRC for FlexFEC packets, as announced in the SDP.
+    pub(crate) fn set_flexfec_ssrc(&mut self, ssrc: Ssrc) {
+        self.flexfec_ssrc = Some(ssrc);
+    }
+
+    /// Protect sent packets with FlexFEC on `pt`, one FEC packet every `window` packets.
+    pub(crate) fn set_flexfec(&mut self, pt: Option<Pt>, window: usize) {
+        let Some(pt) = pt else {
+            self.flexfec = None;
+            return;
+        };
+
+        if self.flexfec.as_ref().map(|f| f.pt) == Some(pt) {
+            return;
+        }
+
+        // The FEC packets are sent in a separate SSRC, identified by the receiver from the PT.
+        // Without an SSRC from the SDP, as in the direct API, one is made up.
+        let ssrc = *self
+            .flexfec_ssrc
+            .get_or_insert_with(|| NonCryptographicRng::u32().into());
+        let seq_no = (NonCryptographicRng::u16() as u64).into();
+
+        self.flexfec = Some(FlexFecTx {
+            ssrc,
+            pt,
+            seq_no,
+            encoder: FecEncoder::new(FecFormat::FlexFec, window),
+        });
+    }
+
+    /// Wrap sent packets in RED on the first PT, with one ULPFEC packet on the second
+    /// every `window` packets. A `window` of 0 sends neither.
+    pub(crate) fn set_ulpfec(&mut self, pts: Option<(Pt, Pt)>, window: usize) {
+        let Some((red_pt, ulpfec_pt)) = pts.filter(|_| window > 0) else {
+            self.ulpfec = None;
+            return;
+        };
+
+        if let Some(u) = &self.ulpfec {
+            if u.red_pt == red_pt && u.ulpfec_pt == ulpfec_pt {
+                return;
+            }
+        }
+
+        self.ulpfec = Some(UlpfecTx {
+            red_pt,
+            ulpfec_pt,
+            encoder: FecEncoder::new(FecFormat::Ulpfec, window),
+        });
+    }
+
+    /// Clock rate of the protected media, for the FEC packets sharing its timestamps.
+    fn fec_clock_rate(&self) -> Frequency {
+        self.clock_rate_override
+            .or(self.clock_rate)
+            .unwrap_or(Frequency::NINETY_KHZ)
+    }
+
+    /// Feed a sent packet to FlexFEC and queue the FEC packet when the window is complete.
+    fn protect_flexfec(&mut self, now: Instant, header: &RtpHeader, packet: &[u8]) {
+        let clock_rate = self.fec_clock_rate();
+
+        let Some(fec) = &mut self.flexfec else {
+            return;
+        };
+
+        let Some(payload) = fec.encoder.push(packet) else {
+            return;
+        };
+
+        let seq_no = fec.seq_no.inc();
+
+        let header = RtpHeader {
+            sequence_number: *seq_no as u16,
+            payload_type: fec.pt,
+            timestamp: header.timestamp,
+            ssrc: fec.ssrc,
+            ..Default::default()
+        };
+
+        let packet = RtpPacket {
+            seq_no,
+            time: MediaTime::new(header.timestamp as u64, clock_rate),
+            header,
+            payload,
+            end_of_frame: false,
+            nackable: false,
+            timestamp: not_happening(),
+            last_sender_info: None,
+        };
+
+        // Send right after the packets it protects.
+        self.send_queue.push(packet);
+        self.send_queue.handle_timeout(now);
+    }
+
+    /// Feed a sent media packet, as it is without RED, to ULPFEC and queue the
+    /// FEC packet when the window is complete.
+    fn protect_ulpfec(&mut self, now: Instant, header: &RtpHeader, packet: &[u8]) {
+        let clock_rate = self.fec_clock_rate();
+
+        let Some(fec) = &mut self.ulpfec else {
+            return;
+        };
+
+        let Some(fec_payload) = fec.encoder.push(packet) else {
+            return;
+        };
+
+        let mut payload = Vec::with_capacity(1 + fec_payload.len());
+        payload.push(*fec.ulpfec_pt);
+        payload.extend_from_slice(&fec_payload);
+
+        // ULPFEC shares the sequence numbers with the media.
+        let seq_no = self.seq_no.inc();
+
+        let header = RtpHeader {
+            sequence_number: *seq_no as u16,
+            payload_type: fec.red_pt,
+            timestamp: header.timestamp,
+            ssrc: self.ssrc,
+            ..Default::default()
+        };
+
+        let packet = RtpPacket {
+            seq_no,
+            time: MediaTime::new(header.timestamp as u64, clock_rate),
+            header,
+            payload,
+            end_of_frame: false,
+            nackable: false,
+            timestamp: not_happening(),
+            last_sender_info: None,
+        };
+
+        self.send_queue.push(packet);
+        self.send_queue.handle_timeout(now);
+    }
+
     fn padding_enabled(&self) -> bool {
         self.rtx.is_some() && self.pt_for_padding.is_some()
     }
@@ -318,6 +510,7 @@ impl StreamTx {
         &mut self,
         now: Instant,
         exts: &ExtensionMap,
+        allow_two_byte: bool,
         twcc: &mut u64,
         params: &[PayloadParams],
         buf: &mut Vec<u8>,
@@ -325,6 +518,8 @@ impl StreamTx {
         let mid = self.mid;
         let rid = self.rid;
         let ssrc_rtx = self.rtx;
+        let clock_rate_override = self.clock_rate_override;
+        let ulpfec_red_pt = self.ulpfec.as_ref().map(|u| u.red_pt);
 
         let (next, is_padding) = if let Some(next) = self.poll_packet_resend(now) {
             (next, false)
@@ -363,6 +558,10 @@ impl StreamTx {
         let mut set_pt_for_padding = None;
         let mut set_cr = None;
 
+        // With ULPFEC, the media is wrapped in RED. The FEC packets already are.
+        let red_pt = ulpfec_red_pt
+            .filter(|red_pt| next.kind == NextPacketKind::Regular && *red_pt != pt_main);
+
         let mut header = match next.kind {
             NextPacketKind::Regular => {
                 let rtx_possible = param.resend().is_some();
@@ -382,7 +581,7 @@ impl StreamTx {
                     next.pkt.nackable = false;
                 }
 
-                let clock_rate = param.spec().clock_rate;
+                let clock_rate = clock_rate_override.unwrap_or(param.spec().clock_rate);
                 set_cr = Some(clock_rate);
 
                 // Modify the cached packet time. This is so write_rtp can use u32 media time without
@@ -426,9 +625,13 @@ impl StreamTx {
         header.ext_vals.transport_cc = Some(*twcc as u16);
         *twcc += 1;
 
+        if let Some(red_pt) = red_pt {
+            header.payload_type = red_pt;
+        }
+
         buf.resize(DATAGRAM_MAX_PACKET_SIZE, 0);
 
-        let header_len = header.write_to(buf, exts);
+        let header_len = header.write_to(buf, exts, allow_two_byte);
         assert!(header_len % 4 == 0, "RTP header must be multiple of 4");
         header.header_len = header_len;
 
@@ -441,6 +644,15 @@ impl StreamTx {
             body_out = &mut body_out[original_seq_len..];
         }
 
+        // The RED header of the single (primary) block.
+        let red_len = if red_pt.is_some() {
+            body_out[0] = *pt_main;
+            body_out = &mut body_out[1..];
+            1
+        } else {
+            0
+        };
+
         let pkt = &next.pkt;
 
         let body_len = match next.kind {
@@ -452,11 +664,11 @@ impl StreamTx {
                 let pad_len = RtpHeader::pad_packet(
                     &mut buf[..],
                     header_len,
-                    body_len + original_seq_len,
+                    body_len + original_seq_len + red_len,
                     SRTP_BLOCK_SIZE,
                 );
 
-                body_len + original_seq_len + pad_len
+                body_len + original_seq_len + red_len + pad_len
             }
             NextPacketKind::Blank(len) => {
                 let len = RtpHeader::create_padding_packet(
@@ -515,9 +727,29 @@ impl StreamTx {
                 .send_queue
                 .pop(now)
                 .expect("head of send_queue to be there");
+
+            // The remote unwraps RED before recovery, so the media is protected
+            // without the RED header, and without padding.
+            let unwrapped = red_pt.map(|_| {
+                let mut packet = buf[..header_len].to_vec();
+                packet[0] &= !0x20;
+                packet[1] = (packet[1] & 0x80) | *pt_main;
+                packet.extend_from_slice(&pkt.payload);
+                packet
+            });
+
             if pkt.nackable {
                 self.rtx_cache.cache_sent_packet(pkt, now);
             }
+
+            // Only the media, not the FEC packets themselves.
+            if header.ssrc == self.ssrc {
+                self.protect_flexfec(now, &header, buf);
+            }
+
+            if let Some(packet) = unwrapped {
+                self.protect_ulpfec(now, &header, &packet);
+            }
         }
 
         Some(PacketReceipt {
@@ -666,12 +898,12 @@ impl StreamTx {
         })
     }
 
-    pub(crate) fn sender_report_at(&self) -> Instant {
+    pub(crate) fn sender_report_at(&self, interval: ReportInterval) -> Instant {
         let Some(kind) = self.kind else {
             // First handle_timeout sets the kind. No sender report until then.
             return not_happening();
         };
-        self.last_sender_report + rr_interval(kind.is_audio())
+        self.last_sender_report + interval.get(kind.is_audio())
     }
 
     pub(crate) fn poll_keyframe_request(&mut self) -> Option<KeyframeRequestKind> {
@@ -735,8 +967,8 @@ impl StreamTx {
         Some(())
     }
 
-    pub(crate) fn need_sr(&self, now: Instant) -> bool {
-        now >= self.sender_report_at()
+    pub(crate) fn need_sr(&self, now: Instant, interval: ReportInterval) -> bool {
+        now >= self.sender_report_at(interval)
     }
 
     pub(crate) fn create_sr_and_update(&mut self, now: Instant, feedback: &mut VecDeque<Rtcp>) {
@@ -828,6 +1060,22 @@ impl StreamTx {
         self.stats.fill(snapshot, self.mid, self.rid, now);
     }
 
+    /// Interarrival jitter from the last RR, if any.
+    pub(crate) fn jitter(&self) -> Option<Duration> {
+        let clock_rate = self.clock_rate_override.or(self.clock_rate)?;
+        let jitter = self.stats.jitter?;
+        Some(Duration::from_secs_f64(
+            jitter as f64 / clock_rate.get() as f64,
+        ))
+    }
+
+    /// Round trip time from the last RR, if any.
+    pub(crate) fn rtt(&self) -> Option<Duration> {
+        self.stats
+            .rtt
+            .map(|ms| Duration::from_secs_f32(ms.max(0.0) / 1000.0))
+    }
+
     pub(crate) fn queue_state(&mut self, now: Instant) -> QueueState {
         // The unpaced flag is set to a default value on first handle_timeout. The
         // default is to not pace audio. We unwrap default to "true" here to not
@@ -993,6 +1241,7 @@ impl StreamTxStats {
         let ntp_time = now.to_ntp_duration();
         let rtt = calculate_rtt_ms(ntp_time, r.last_sr_delay, r.last_sr_time);
         self.rtt = rtt;
+        self.jitter = Some(r.jitter);
 
         let ext_seq = {
             let prev = self.losses.last().map(|s| s.0).unwrap_or(r.max_seq as u64);
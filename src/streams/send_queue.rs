@@ -214,6 +214,7 @@ mod test {
             payload: vec![],
             timestamp: Instant::now(),
             last_sender_info: None,
+            end_of_frame: false,
             nackable: true,
         });
 
@@ -249,6 +250,7 @@ mod test {
             payload: vec![42, 42],
             timestamp: start,
             last_sender_info: None,
+            end_of_frame: false,
             nackable: true,
         });
 
@@ -0,0 +1,1323 @@
+//! TURN client.
+//!
+//! A sans-IO TURN client (RFC 8656) that allocates a relay on a TURN server and moves data
+//! between the relay and [`Rtc`][crate::Rtc]. Like `Rtc`, the client does no networking
+//! itself. It is driven by [`TurnClient::handle_timeout()`] and
+//! [`TurnClient::handle_receive()`], and everything that should happen as a result is
+//! obtained via [`TurnClient::poll_output()`].
+//!
+//! The client takes care of allocating, refreshing the allocation, installing permissions
+//! and binding channels for the peers it relays to. Data is sent in ChannelData messages
+//! once a channel is bound, and in SEND indications before that.
+//!
+//! Usually the servers are configured using
+//! [`RtcConfig::set_turn_servers()`][crate::RtcConfig::set_turn_servers], and the `Rtc`
+//! drives a client per server. A client can also be driven separately, for instance to
+//! share an allocation between `Rtc` instances:
+//!
+//! 1. On [`TurnEvent::Allocated`], add the relayed candidate to `Rtc` using
+//!    [`Rtc::add_local_candidate()`][crate::Rtc::add_local_candidate].
+//! 2. When `Rtc` outputs a [`Transmit`] with [`Transmit::source`] being the
+//!    [relayed address][TurnClient::relayed_address], hand it to [`TurnClient::send()`].
+//! 3. Data coming from the TURN server goes to [`TurnClient::handle_receive()`], and data
+//!    relayed from peers comes back as [`TurnEvent::Received`], ready to be fed to `Rtc`.
+//!
+//! ```no_run
+//! # use std::net::UdpSocket;
+//! # use std::time::Instant;
+//! # use str0m::{Input, Output, Rtc};
+//! # use str0m::net::{Protocol, Receive};
+//! # use str0m::turn::{TurnClient, TurnConfig, TurnEvent, TurnOutput};
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let mut rtc = Rtc::new();
+//! let socket = UdpSocket::bind("0.0.0.0:0")?;
+//! let server = "192.0.2.1:3478".parse()?;
+//!
+//! let config = TurnConfig::new(Protocol::Udp, socket.local_addr()?, server, "user", "secret");
+//! let mut turn = TurnClient::new(config);
+//!
+//! turn.handle_timeout(Instant::now());
+//!
+//! loop {
+//!     match turn.poll_output() {
+//!         TurnOutput::Transmit(t) => {
+//!             socket.send_to(&t.contents, t.destination)?;
+//!         }
+//!         TurnOutput::Event(TurnEvent::Allocated(candidate)) => {
+//!             rtc.add_local_candidate(candidate);
+//!         }
+//!         TurnOutput::Event(TurnEvent::Received { source, destination, contents }) => {
+//!             let receive = Receive::new(Protocol::Udp, source, destination, &contents)?;
+//!             rtc.handle_input(Input::Receive(Instant::now(), receive))?;
+//!         }
+//!         TurnOutput::Event(_) => {}
+//!         TurnOutput::Timeout(_) => break,
+//!     }
+//! }
+//!
+//! match rtc.poll_output()? {
+//!     Output::Transmit(t) if Some(t.source) == turn.relayed_address() => {
+//!         turn.send(Instant::now(), t)?;
+//!     }
+//!     // Datagrams from the TURN server go to turn.handle_receive(),
+//!     // everything else is handled as usual.
+//!     _ => {}
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Over [`Protocol::Tcp`] and [`Protocol::Tls`] the transmits are parts of a stream to the
+//! TURN server and should be written to the connection as is (TLS being the responsibility
+//! of the caller). Received stream data can be passed to `handle_receive()` in any chunks.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use thiserror::Error;
+
+use crate::io::{stun_resend_delay, Protocol, StunClass, TransId, Transmit, STUN_TIMEOUT};
+use crate::util::{already_happened, not_happening};
+use crate::Candidate;
+
+pub(crate) mod msg;
+use msg::{channel_data, is_channel_data, TurnMessage, TurnMethod};
+use msg::{long_term_key, parse_channel_data, stream_message_len};
+use msg::{CHANNEL_LIFETIME_SECS, CHANNEL_MAX, CHANNEL_MIN, PERMISSION_LIFETIME_SECS};
+
+/// Number of times a request is sent over UDP before giving up (Rc in RFC 8489).
+const MAX_SENDS: usize = 7;
+
+/// How long before expiry permissions and channels are refreshed.
+const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+/// Errors from the TURN client.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TurnError {
+    /// The TURN server answered with an error.
+    #[error("TURN server error {0}: {1}")]
+    Server(u16, String),
+
+    /// The TURN server did not answer.
+    #[error("TURN request timed out")]
+    Timeout,
+
+    /// A message from the TURN server could not be understood.
+    #[error("TURN bad message: {0}")]
+    BadMessage(String),
+
+    /// There is no allocation to send via.
+    #[error("TURN allocation is not ready")]
+    NotAllocated,
+}
+
+/// Configuration of a [`TurnClient`].
+#[derive(Clone)]
+pub struct TurnConfig {
+    proto: Protocol,
+    local: SocketAddr,
+    server: SocketAddr,
+    username: String,
+    password: String,
+    lifetime: Duration,
+}
+
+impl TurnConfig {
+    /// Creates a new configuration.
+    ///
+    /// * `proto` is how to reach the TURN server, [`Protocol::Udp`], [`Protocol::Tcp`]
+    ///   or [`Protocol::Tls`]. The relay itself is always UDP.
+    /// * `local` is the local socket used to talk to the server.
+    /// * `server` is the address of the TURN server.
+    /// * `username` and `password` are the long-term credentials.
+    pub fn new(
+        proto: Protocol,
+        local: SocketAddr,
+        server: SocketAddr,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        TurnConfig {
+            proto,
+            local,
+            server,
+            username: username.into(),
+            password: password.into(),
+            lifetime: Duration::from_secs(600),
+        }
+    }
+
+    /// The protocol used to reach the TURN server.
+    pub fn proto(&self) -> Protocol {
+        self.proto
+    }
+
+    /// The local socket used to talk to the TURN server.
+    pub fn local(&self) -> SocketAddr {
+        self.local
+    }
+
+    /// The address of the TURN server.
+    pub fn server(&self) -> SocketAddr {
+        self.server
+    }
+
+    /// Set the allocation lifetime to request.
+    ///
+    /// The server decides the actual lifetime, and the client refreshes the allocation
+    /// ahead of it expiring.
+    ///
+    /// Defaults to 600 seconds.
+    pub fn set_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// The allocation lifetime to request.
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use str0m::net::Protocol;
+    /// # use str0m::turn::TurnConfig;
+    /// let local = "10.0.0.1:5000".parse().unwrap();
+    /// let server = "192.0.2.1:3478".parse().unwrap();
+    /// let config = TurnConfig::new(Protocol::Udp, local, server, "user", "secret");
+    ///
+    /// assert_eq!(config.lifetime(), Duration::from_secs(600));
+    /// ```
+    pub fn lifetime(&self) -> Duration {
+        self.lifetime
+    }
+}
+
+impl fmt::Debug for TurnConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Leave out the password.
+        f.debug_struct("TurnConfig")
+            .field("proto", &self.proto)
+            .field("local", &self.local)
+            .field("server", &self.server)
+            .field("username", &self.username)
+            .field("lifetime", &self.lifetime)
+            .finish()
+    }
+}
+
+/// Output from [`TurnClient::poll_output()`].
+#[derive(Debug)]
+pub enum TurnOutput {
+    /// When the client needs to be driven by [`TurnClient::handle_timeout()`] next.
+    Timeout(Instant),
+
+    /// Data to send to the TURN server.
+    Transmit(Transmit),
+
+    /// Something happened with the allocation.
+    Event(TurnEvent),
+}
+
+/// Events from the [`TurnClient`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TurnEvent {
+    /// The allocation succeeded.
+    ///
+    /// The relayed candidate should be added using
+    /// [`Rtc::add_local_candidate()`][crate::Rtc::add_local_candidate].
+    Allocated(Candidate),
+
+    /// Data relayed from a peer.
+    ///
+    /// Feed this to [`Rtc`][crate::Rtc] as a [`Receive`][crate::net::Receive] over
+    /// [`Protocol::Udp`] with the given `source` and `destination`.
+    Received {
+        /// The peer that sent the data.
+        source: SocketAddr,
+        /// The relayed address the data was sent to.
+        destination: SocketAddr,
+        /// The data.
+        contents: Vec<u8>,
+    },
+
+    /// The allocation could not be made, or was lost.
+    ///
+    /// The client is closed after this.
+    Failed(TurnError),
+
+    /// The allocation was released after [`TurnClient::close()`].
+    Closed,
+}
+
+/// A sans-IO TURN client.
+///
+/// See the [module documentation][crate::turn] for how to use it together with
+/// [`Rtc`][crate::Rtc].
+pub struct TurnClient {
+    config: TurnConfig,
+    state: State,
+    /// The relayed address of the latest allocation, kept after it is lost.
+    last_relayed: Option<SocketAddr>,
+    last_now: Option<Instant>,
+    auth: Option<Auth>,
+    transactions: Vec<Transaction>,
+    permissions: HashMap<IpAddr, Permission>,
+    channels: Vec<Channel>,
+    next_channel: u16,
+    stream_buf: Vec<u8>,
+    transmits: VecDeque<Transmit>,
+    events: VecDeque<TurnEvent>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Idle,
+    Allocating,
+    Allocated {
+        relayed: SocketAddr,
+        mapped: Option<SocketAddr>,
+        refresh_at: Instant,
+    },
+    Closing,
+    Closed,
+}
+
+struct Auth {
+    realm: String,
+    nonce: String,
+    key: [u8; 16],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    Allocate,
+    Refresh(u32),
+    CreatePermission(IpAddr),
+    ChannelBind(u16, SocketAddr),
+}
+
+struct Transaction {
+    request: Request,
+    trans_id: TransId,
+    bytes: Vec<u8>,
+    first_sent: Instant,
+    send_count: usize,
+    auth_retries: usize,
+}
+
+struct Permission {
+    /// Set once the server has confirmed the permission.
+    expires: Option<Instant>,
+    /// Whether the permission has been used since last refresh.
+    used: bool,
+    in_flight: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChannelState {
+    Binding,
+    Bound { refresh_at: Instant },
+    Failed,
+}
+
+struct Channel {
+    number: u16,
+    peer: SocketAddr,
+    state: ChannelState,
+    in_flight: bool,
+}
+
+impl TurnClient {
+    /// Creates a new TURN client.
+    ///
+    /// Nothing happens until the first [`TurnClient::handle_timeout()`].
+    pub fn new(config: TurnConfig) -> Self {
+        TurnClient {
+            config,
+            state: State::Idle,
+            last_relayed: None,
+            last_now: None,
+            auth: None,
+            transactions: vec![],
+            permissions: HashMap::new(),
+            channels: vec![],
+            next_channel: CHANNEL_MIN,
+            stream_buf: vec![],
+            transmits: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// The configuration of this client.
+    pub fn config(&self) -> &TurnConfig {
+        &self.config
+    }
+
+    /// The relayed address, once allocated.
+    pub fn relayed_address(&self) -> Option<SocketAddr> {
+        match self.state {
+            State::Allocated { relayed, .. } => Some(relayed),
+            _ => None,
+        }
+    }
+
+    /// The relayed candidate, while allocated.
+    pub(crate) fn relayed_candidate(&self) -> Option<Candidate> {
+        Candidate::relayed(self.relayed_address()?, Protocol::Udp).ok()
+    }
+
+    /// The relayed address of the latest allocation, also after it was lost.
+    pub(crate) fn last_relayed_address(&self) -> Option<SocketAddr> {
+        self.last_relayed
+    }
+
+    /// Our address as seen by the TURN server, once allocated.
+    ///
+    /// This can be used as a server reflexive candidate.
+    pub fn mapped_address(&self) -> Option<SocketAddr> {
+        match self.state {
+            State::Allocated { mapped, .. } => mapped,
+            _ => None,
+        }
+    }
+
+    /// Whether the client has stopped, either by failing or being closed.
+    pub fn is_closed(&self) -> bool {
+        self.state == State::Closed
+    }
+
+    /// Whether data received from `source` to `destination` is for this client.
+    pub fn accepts(&self, source: SocketAddr, destination: SocketAddr) -> bool {
+        source == self.config.server && destination == self.config.local
+    }
+
+    /// Drive time forward in the client.
+    ///
+    /// The first call starts the allocation.
+    pub fn handle_timeout(&mut self, now: Instant) {
+        self.last_now = Some(now);
+
+        if self.state == State::Idle {
+            self.state = State::Allocating;
+            self.start_request(now, Request::Allocate, 0);
+        }
+
+        self.handle_transaction_timeouts(now);
+
+        let State::Allocated { refresh_at, .. } = self.state else {
+            return;
+        };
+
+        if now >= refresh_at && !self.is_in_flight(|r| matches!(r, Request::Refresh(_))) {
+            let lifetime = self.config.lifetime.as_secs() as u32;
+            self.start_request(now, Request::Refresh(lifetime), 0);
+        }
+
+        let mut to_refresh = vec![];
+        self.permissions.retain(|ip, p| {
+            let Some(expires) = p.expires else {
+                return true;
+            };
+            if p.in_flight || now + REFRESH_MARGIN < expires {
+                return true;
+            }
+            if p.used {
+                to_refresh.push(*ip);
+                return true;
+            }
+            // Unused permissions are left to expire.
+            now < expires
+        });
+        for ip in to_refresh {
+            self.request_permission(now, ip);
+        }
+
+        let mut to_rebind = vec![];
+        for c in &mut self.channels {
+            if let ChannelState::Bound { refresh_at } = c.state {
+                if !c.in_flight && now >= refresh_at {
+                    c.in_flight = true;
+                    to_rebind.push((c.number, c.peer));
+                }
+            }
+        }
+        for (number, peer) in to_rebind {
+            self.start_request(now, Request::ChannelBind(number, peer), 0);
+        }
+    }
+
+    /// Handle data received from the TURN server.
+    ///
+    /// Over stream protocols, `contents` can be any chunk of the stream.
+    pub fn handle_receive(&mut self, now: Instant, contents: &[u8]) -> Result<(), TurnError> {
+        self.last_now = Some(now);
+
+        if !matches!(self.config.proto, Protocol::Tcp | Protocol::Tls) {
+            return self.handle_message(now, contents);
+        }
+
+        self.stream_buf.extend_from_slice(contents);
+
+        let mut result = Ok(());
+        let mut off = 0;
+
+        while let Some(len) = stream_message_len(&self.stream_buf[off..]) {
+            if self.stream_buf.len() - off < len {
+                break;
+            }
+            let message = self.stream_buf[off..(off + len)].to_vec();
+            off += len;
+
+            if let Err(e) = self.handle_message(now, &message) {
+                result = Err(e);
+            }
+        }
+
+        self.stream_buf.drain(..off);
+
+        result
+    }
+
+    /// Send data via the relay.
+    ///
+    /// Used for [`Transmit`] from [`Rtc`][crate::Rtc] that have the
+    /// [relayed address][TurnClient::relayed_address] as source. The resulting
+    /// data to the TURN server is obtained via [`TurnClient::poll_output()`].
+    pub fn send(&mut self, now: Instant, transmit: Transmit) -> Result<(), TurnError> {
+        if self.relayed_address().is_none() {
+            return Err(TurnError::NotAllocated);
+        }
+
+        let peer = transmit.destination;
+
+        self.create_permission(now, peer.ip());
+
+        let idx = match self.channels.iter().position(|c| c.peer == peer) {
+            Some(idx) => Some(idx),
+            None if self.next_channel <= CHANNEL_MAX => {
+                let number = self.next_channel;
+                self.next_channel += 1;
+                self.channels.push(Channel {
+                    number,
+                    peer,
+                    state: ChannelState::Binding,
+                    in_flight: true,
+                });
+                self.start_request(now, Request::ChannelBind(number, peer), 0);
+                Some(self.channels.len() - 1)
+            }
+            // Out of channels, keep using SEND indications.
+            None => None,
+        };
+
+        let channel = idx
+            .map(|idx| &self.channels[idx])
+            .filter(|c| matches!(c.state, ChannelState::Bound { .. }));
+
+        let bytes = if let Some(channel) = channel {
+            channel_data(channel.number, &transmit.contents, self.is_stream())
+        } else {
+            TurnMessage::send_indication(peer, &transmit.contents).to_bytes(None)
+        };
+
+        self.enqueue(bytes);
+
+        Ok(())
+    }
+
+    /// Install a permission for a peer on the TURN server.
+    ///
+    /// The TURN server drops data from peers without a permission. Permissions are created
+    /// when sending, but incoming ICE checks from a remote candidate can arrive before
+    /// that, so it's a good idea to call this for every remote candidate.
+    ///
+    /// Permissions are refreshed for as long as they are used.
+    pub fn create_permission(&mut self, now: Instant, ip: IpAddr) {
+        if self.permissions.contains_key(&ip) {
+            self.touch_permission(now, ip);
+            return;
+        }
+
+        self.permissions.insert(
+            ip,
+            Permission {
+                expires: None,
+                used: true,
+                in_flight: false,
+            },
+        );
+
+        // Before allocation, the permission is requested once allocated.
+        if self.relayed_address().is_some() {
+            self.request_permission(now, ip);
+        }
+    }
+
+    /// Release the allocation.
+    ///
+    /// [`TurnEvent::Closed`] is emitted when the server confirms, or the request fails.
+    pub fn close(&mut self, now: Instant) {
+        if self.relayed_address().is_some() {
+            self.transactions.clear();
+            self.state = State::Closing;
+            self.start_request(now, Request::Refresh(0), 0);
+        } else if !matches!(self.state, State::Closing | State::Closed) {
+            self.shut_down(TurnEvent::Closed);
+        }
+    }
+
+    /// Poll the client for output.
+    pub fn poll_output(&mut self) -> TurnOutput {
+        if let Some(e) = self.events.pop_front() {
+            return TurnOutput::Event(e);
+        }
+
+        if let Some(t) = self.transmits.pop_front() {
+            return TurnOutput::Transmit(t);
+        }
+
+        TurnOutput::Timeout(self.poll_timeout().unwrap_or_else(not_happening))
+    }
+
+    pub(crate) fn poll_timeout(&self) -> Option<Instant> {
+        if self.state == State::Idle {
+            return Some(already_happened());
+        }
+
+        let mut timeout = not_happening();
+
+        for t in &self.transactions {
+            timeout = timeout.min(self.next_send(t));
+        }
+
+        if let State::Allocated { refresh_at, .. } = self.state {
+            timeout = timeout.min(refresh_at);
+        }
+
+        for p in self.permissions.values() {
+            if let Some(expires) = p.expires {
+                if !p.in_flight {
+                    // Unused permissions are not refreshed, only removed when expired.
+                    let at = if p.used {
+                        expires - REFRESH_MARGIN
+                    } else {
+                        expires
+                    };
+                    timeout = timeout.min(at);
+                }
+            }
+        }
+
+        for c in &self.channels {
+            if let ChannelState::Bound { refresh_at } = c.state {
+                if !c.in_flight {
+                    timeout = timeout.min(refresh_at);
+                }
+            }
+        }
+
+        (timeout != not_happening()).then_some(timeout)
+    }
+
+    fn is_stream(&self) -> bool {
+        matches!(self.config.proto, Protocol::Tcp | Protocol::Tls)
+    }
+
+    fn is_in_flight(&self, f: impl Fn(&Request) -> bool) -> bool {
+        self.transactions.iter().any(|t| f(&t.request))
+    }
+
+    fn enqueue(&mut self, bytes: Vec<u8>) {
+        self.transmits.push_back(Transmit {
+            proto: self.config.proto,
+            source: self.config.local,
+            destination: self.config.server,
+            contents: bytes.into(),
+        });
+    }
+
+    /// Mark a permission as used, and refresh it straight away if it's due.
+    fn touch_permission(&mut self, now: Instant, ip: IpAddr) {
+        let Some(p) = self.permissions.get_mut(&ip) else {
+            return;
+        };
+
+        p.used = true;
+
+        let due = p.expires.is_some_and(|e| now + REFRESH_MARGIN >= e);
+        if due && !p.in_flight {
+            self.request_permission(now, ip);
+        }
+    }
+
+    fn request_permission(&mut self, now: Instant, ip: IpAddr) {
+        if let Some(p) = self.permissions.get_mut(&ip) {
+            p.in_flight = true;
+            p.used = false;
+        }
+        self.start_request(now, Request::CreatePermission(ip), 0);
+    }
+
+    fn start_request(&mut self, now: Instant, request: Request, auth_retries: usize) {
+        let lifetime = self.config.lifetime.as_secs() as u32;
+
+        let mut msg = match request {
+            Request::Allocate => TurnMessage::allocate(lifetime),
+            Request::Refresh(lifetime) => TurnMessage::refresh(lifetime),
+            // The port is ignored for permissions.
+            Request::CreatePermission(ip) => TurnMessage::create_permission((ip, 0).into()),
+            Request::ChannelBind(number, peer) => TurnMessage::channel_bind(number, peer),
+        };
+
+        let key = self.auth.as_ref().map(|a| {
+            msg.attrs.username = Some(self.config.username.clone());
+            msg.attrs.realm = Some(a.realm.clone());
+            msg.attrs.nonce = Some(a.nonce.clone());
+            a.key
+        });
+
+        let bytes = msg.to_bytes(key.as_ref().map(|k| &k[..]));
+
+        trace!("Send TURN {:?} {:?}", request, msg.trans_id);
+        self.enqueue(bytes.clone());
+
+        self.transactions.push(Transaction {
+            request,
+            trans_id: msg.trans_id,
+            bytes,
+            first_sent: now,
+            send_count: 1,
+            auth_retries,
+        });
+    }
+
+    fn next_send(&self, t: &Transaction) -> Instant {
+        let deadline = t.first_sent + STUN_TIMEOUT;
+
+        // Over TCP and TLS, the transport takes care of retransmissions.
+        if self.is_stream() || t.send_count >= MAX_SENDS {
+            return deadline;
+        }
+
+        let mut at = t.first_sent;
+        for i in 1..=t.send_count {
+            at += stun_resend_delay(i);
+        }
+
+        at.min(deadline)
+    }
+
+    fn handle_transaction_timeouts(&mut self, now: Instant) {
+        let mut timed_out = vec![];
+        let mut resend = vec![];
+
+        for (i, t) in self.transactions.iter().enumerate() {
+            if now >= t.first_sent + STUN_TIMEOUT {
+                timed_out.push(i);
+            } else if now >= self.next_send(t) {
+                resend.push(i);
+            }
+        }
+
+        for i in resend {
+            let t = &mut self.transactions[i];
+            t.send_count += 1;
+            let bytes = t.bytes.clone();
+            self.enqueue(bytes);
+        }
+
+        for i in timed_out.into_iter().rev() {
+            let t = self.transactions.remove(i);
+            debug!("TURN {:?} timed out", t.request);
+            self.handle_request_failure(t.request, TurnError::Timeout);
+        }
+    }
+
+    fn handle_message(&mut self, now: Instant, buf: &[u8]) -> Result<(), TurnError> {
+        if is_channel_data(buf) {
+            let Some((number, data)) = parse_channel_data(buf) else {
+                return Err(TurnError::BadMessage("Truncated ChannelData".into()));
+            };
+            let Some(peer) = self
+                .channels
+                .iter()
+                .find(|c| c.number == number)
+                .map(|c| c.peer)
+            else {
+                trace!("ChannelData for unknown channel: {}", number);
+                return Ok(());
+            };
+            self.relay_received(now, peer, data.to_vec());
+            return Ok(());
+        }
+
+        let key = self.auth.as_ref().map(|a| a.key);
+        let msg = TurnMessage::parse(buf, key.as_ref().map(|k| &k[..]))
+            .map_err(|e| TurnError::BadMessage(e.to_string()))?;
+
+        if msg.class == StunClass::Indication {
+            if msg.method == TurnMethod::Data {
+                if let (Some(peer), Some(data)) = (msg.attrs.xor_peer_address, msg.attrs.data) {
+                    self.relay_received(now, peer, data);
+                }
+            }
+            return Ok(());
+        }
+
+        let Some(idx) = self
+            .transactions
+            .iter()
+            .position(|t| t.trans_id == msg.trans_id)
+        else {
+            trace!("TURN response for unknown transaction");
+            return Ok(());
+        };
+
+        // Responses to authenticated requests must be authenticated, except errors.
+        if msg.class == StunClass::Success && key.is_some() && !msg.authenticated {
+            debug!("TURN response failed integrity check");
+            return Ok(());
+        }
+
+        let t = self.transactions.remove(idx);
+
+        match msg.class {
+            StunClass::Success => self.handle_success(now, t.request, &msg),
+            StunClass::Failure => self.handle_error(now, t, &msg),
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn relay_received(&mut self, now: Instant, peer: SocketAddr, contents: Vec<u8>) {
+        let Some(relayed) = self.relayed_address() else {
+            return;
+        };
+
+        self.touch_permission(now, peer.ip());
+
+        self.events.push_back(TurnEvent::Received {
+            source: peer,
+            destination: relayed,
+            contents,
+        });
+    }
+
+    fn handle_success(&mut self, now: Instant, request: Request, msg: &TurnMessage) {
+        debug!("TURN {:?} succeeded", request);
+
+        match request {
+            Request::Allocate => {
+                let Some(relayed) = msg.attrs.xor_relayed_address else {
+                    let e = TurnError::BadMessage("Allocate without relayed address".into());
+                    self.shut_down(TurnEvent::Failed(e));
+                    return;
+                };
+
+                let candidate = match Candidate::relayed(relayed, Protocol::Udp) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        let e = TurnError::BadMessage(e.to_string());
+                        self.shut_down(TurnEvent::Failed(e));
+                        return;
+                    }
+                };
+
+                self.last_relayed = Some(relayed);
+                self.state = State::Allocated {
+                    relayed,
+                    mapped: msg.attrs.xor_mapped_address,
+                    refresh_at: now + self.refresh_delay(msg.attrs.lifetime),
+                };
+                info!("TURN allocated {}", relayed);
+                self.events.push_back(TurnEvent::Allocated(candidate));
+
+                // Permissions asked for before the allocation.
+                let pending: Vec<_> = self.permissions.keys().copied().collect();
+                for ip in pending {
+                    self.request_permission(now, ip);
+                }
+            }
+            Request::Refresh(0) => {
+                self.shut_down(TurnEvent::Closed);
+            }
+            Request::Refresh(_) => {
+                let delay = self.refresh_delay(msg.attrs.lifetime);
+                if let State::Allocated { refresh_at, .. } = &mut self.state {
+                    *refresh_at = now + delay;
+                }
+            }
+            Request::CreatePermission(ip) => {
+                if let Some(p) = self.permissions.get_mut(&ip) {
+                    p.expires = Some(now + Duration::from_secs(PERMISSION_LIFETIME_SECS));
+                    p.in_flight = false;
+                }
+            }
+            Request::ChannelBind(number, peer) => {
+                let refresh_at = now + Duration::from_secs(CHANNEL_LIFETIME_SECS) - REFRESH_MARGIN;
+                if let Some(c) = self.channels.iter_mut().find(|c| c.number == number) {
+                    c.state = ChannelState::Bound { refresh_at };
+                    c.in_flight = false;
+                }
+                // A channel binding also installs a permission.
+                if let Some(p) = self.permissions.get_mut(&peer.ip()) {
+                    if !p.in_flight {
+                        p.expires = Some(now + Duration::from_secs(PERMISSION_LIFETIME_SECS));
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_error(&mut self, now: Instant, t: Transaction, msg: &TurnMessage) {
+        let (code, reason) = msg
+            .attrs
+            .error_code
+            .clone()
+            .unwrap_or((0, "Missing error code".into()));
+
+        // 401 Unauthorized: the first response to a request without credentials.
+        // 438 Stale Nonce: the nonce needs replacing.
+        let is_challenge = (code == 401 && t.auth_retries == 0) || code == 438;
+
+        if is_challenge && t.auth_retries < 3 {
+            if let (Some(realm), Some(nonce)) = (&msg.attrs.realm, &msg.attrs.nonce) {
+                let key = long_term_key(&self.config.username, realm, &self.config.password);
+                self.auth = Some(Auth {
+                    realm: realm.clone(),
+                    nonce: nonce.clone(),
+                    key,
+                });
+                self.start_request(now, t.request, t.auth_retries + 1);
+                return;
+            }
+        }
+
+        debug!("TURN {:?} failed: {} {}", t.request, code, reason);
+        self.handle_request_failure(t.request, TurnError::Server(code, reason));
+    }
+
+    fn handle_request_failure(&mut self, request: Request, error: TurnError) {
+        match request {
+            Request::Allocate => self.shut_down(TurnEvent::Failed(error)),
+            Request::Refresh(0) => self.shut_down(TurnEvent::Closed),
+            Request::Refresh(_) => {
+                warn!("TURN allocation lost: {}", error);
+                self.shut_down(TurnEvent::Failed(error));
+            }
+            Request::CreatePermission(ip) => {
+                warn!("TURN permission for {} failed: {}", ip, error);
+                self.permissions.remove(&ip);
+            }
+            Request::ChannelBind(number, _) => {
+                warn!("TURN channel bind {} failed: {}", number, error);
+                // Keep sending SEND indications to this peer.
+                if let Some(c) = self.channels.iter_mut().find(|c| c.number == number) {
+                    c.state = ChannelState::Failed;
+                    c.in_flight = false;
+                }
+            }
+        }
+    }
+
+    fn shut_down(&mut self, event: TurnEvent) {
+        self.state = State::Closed;
+        self.transactions.clear();
+        self.permissions.clear();
+        self.channels.clear();
+        self.events.push_back(event);
+    }
+
+    fn refresh_delay(&self, lifetime: Option<u32>) -> Duration {
+        let lifetime = lifetime
+            .map(|l| Duration::from_secs(l as u64))
+            .unwrap_or(self.config.lifetime);
+
+        if lifetime > REFRESH_MARGIN * 2 {
+            lifetime - REFRESH_MARGIN
+        } else {
+            lifetime / 2
+        }
+    }
+}
+
+impl fmt::Debug for TurnClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TurnClient")
+            .field("config", &self.config)
+            .field("state", &self.state)
+            .field("permissions", &self.permissions.len())
+            .field("channels", &self.channels.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn server() -> SocketAddr {
+        "192.0.2.1:3478".parse().unwrap()
+    }
+
+    fn relayed() -> SocketAddr {
+        "192.0.2.1:50000".parse().unwrap()
+    }
+
+    fn peer() -> SocketAddr {
+        "198.51.100.7:4000".parse().unwrap()
+    }
+
+    fn client(proto: Protocol) -> TurnClient {
+        let local = "10.0.0.1:5000".parse().unwrap();
+        TurnClient::new(TurnConfig::new(proto, local, server(), "user", "pass"))
+    }
+
+    fn key() -> [u8; 16] {
+        long_term_key("user", "example.org", "pass")
+    }
+
+    fn transmits(c: &mut TurnClient) -> Vec<Vec<u8>> {
+        let mut v = vec![];
+        while let Some(t) = c.transmits.pop_front() {
+            v.push(t.contents.into());
+        }
+        v
+    }
+
+    fn events(c: &mut TurnClient) -> Vec<TurnEvent> {
+        c.events.drain(..).collect()
+    }
+
+    fn reply(req: &[u8], f: impl FnOnce(&mut TurnMessage)) -> Vec<u8> {
+        let req = TurnMessage::parse(req, None).unwrap();
+        let mut res = TurnMessage::new(req.method, StunClass::Success, Default::default());
+        res.trans_id = req.trans_id;
+        f(&mut res);
+        let key = key();
+        let key = (res.class == StunClass::Success).then_some(&key[..]);
+        res.to_bytes(key)
+    }
+
+    fn challenge(req: &[u8]) -> Vec<u8> {
+        reply(req, |m| {
+            m.class = StunClass::Failure;
+            m.attrs.error_code = Some((401, "Unauthorized".into()));
+            m.attrs.realm = Some("example.org".into());
+            m.attrs.nonce = Some("nonce1".into());
+        })
+    }
+
+    fn allocated(req: &[u8]) -> Vec<u8> {
+        reply(req, |m| {
+            m.attrs.xor_relayed_address = Some(relayed());
+            m.attrs.xor_mapped_address = Some("203.0.113.5:6000".parse().unwrap());
+            m.attrs.lifetime = Some(600);
+        })
+    }
+
+    fn allocate(c: &mut TurnClient, now: Instant) {
+        c.handle_timeout(now);
+        let req = transmits(c).remove(0);
+
+        // Unauthenticated first attempt is challenged.
+        c.handle_receive(now, &challenge(&req)).unwrap();
+
+        let req = transmits(c).remove(0);
+        let parsed = TurnMessage::parse(&req, Some(&key())).unwrap();
+        assert!(parsed.authenticated);
+        assert_eq!(parsed.method, TurnMethod::Allocate);
+        assert_eq!(parsed.attrs.username.as_deref(), Some("user"));
+
+        c.handle_receive(now, &allocated(&req)).unwrap();
+    }
+
+    #[test]
+    fn allocate_with_challenge() {
+        let now = Instant::now();
+        let mut c = client(Protocol::Udp);
+
+        allocate(&mut c, now);
+
+        let evs = events(&mut c);
+        assert!(matches!(&evs[..], [TurnEvent::Allocated(cand)] if cand.addr() == relayed()));
+        assert_eq!(c.relayed_address(), Some(relayed()));
+        assert_eq!(
+            c.mapped_address(),
+            Some("203.0.113.5:6000".parse().unwrap())
+        );
+
+        // Refresh is scheduled a minute ahead of expiry.
+        let TurnOutput::Timeout(at) = c.poll_output() else {
+            panic!("Expected timeout");
+        };
+        assert_eq!(at, now + Duration::from_secs(540));
+
+        c.handle_timeout(at);
+        let req = transmits(&mut c).remove(0);
+        let parsed = TurnMessage::parse(&req, Some(&key())).unwrap();
+        assert_eq!(parsed.method, TurnMethod::Refresh);
+        assert_eq!(parsed.attrs.lifetime, Some(600));
+    }
+
+    #[test]
+    fn wrong_password_fails() {
+        let now = Instant::now();
+        let mut c = client(Protocol::Udp);
+        c.handle_timeout(now);
+
+        for _ in 0..2 {
+            let req = transmits(&mut c).remove(0);
+            c.handle_receive(now, &challenge(&req)).unwrap();
+        }
+
+        let evs = events(&mut c);
+        assert!(matches!(
+            &evs[..],
+            [TurnEvent::Failed(TurnError::Server(401, _))]
+        ));
+        assert!(c.is_closed());
+    }
+
+    #[test]
+    fn allocate_timeout() {
+        let now = Instant::now();
+        let mut c = client(Protocol::Udp);
+        c.handle_timeout(now);
+
+        let mut sent = transmits(&mut c).len();
+        loop {
+            let TurnOutput::Timeout(at) = c.poll_output() else {
+                continue;
+            };
+            c.handle_timeout(at);
+            sent += transmits(&mut c).len();
+            if c.is_closed() {
+                break;
+            }
+        }
+
+        assert_eq!(sent, MAX_SENDS);
+        let evs = events(&mut c);
+        assert!(matches!(&evs[..], [TurnEvent::Failed(TurnError::Timeout)]));
+    }
+
+    #[test]
+    fn send_via_channel() {
+        let now = Instant::now();
+        let mut c = client(Protocol::Udp);
+        allocate(&mut c, now);
+        events(&mut c);
+
+        let t = Transmit {
+            proto: Protocol::Udp,
+            source: relayed(),
+            destination: peer(),
+            contents: vec![1, 2, 3].into(),
+        };
+        c.send(now, t).unwrap();
+
+        // Permission, channel bind and the data as SEND indication.
+        let out = transmits(&mut c);
+        assert_eq!(out.len(), 3);
+        let perm = TurnMessage::parse(&out[0], None).unwrap();
+        assert_eq!(perm.method, TurnMethod::CreatePermission);
+        assert_eq!(
+            perm.attrs.xor_peer_address.map(|a| a.ip()),
+            Some(peer().ip())
+        );
+        let bind = TurnMessage::parse(&out[1], None).unwrap();
+        assert_eq!(bind.method, TurnMethod::ChannelBind);
+        assert_eq!(bind.attrs.channel_number, Some(CHANNEL_MIN));
+        let send = TurnMessage::parse(&out[2], None).unwrap();
+        assert_eq!(send.method, TurnMethod::Send);
+        assert_eq!(send.attrs.data.as_deref(), Some(&[1, 2, 3][..]));
+
+        c.handle_receive(now, &reply(&out[0], |_| {})).unwrap();
+        c.handle_receive(now, &reply(&out[1], |_| {})).unwrap();
+
+        let t = Transmit {
+            proto: Protocol::Udp,
+            source: relayed(),
+            destination: peer(),
+            contents: vec![4, 5, 6].into(),
+        };
+        c.send(now, t).unwrap();
+
+        let out = transmits(&mut c);
+        assert_eq!(out, vec![channel_data(CHANNEL_MIN, &[4, 5, 6], false)]);
+
+        // Incoming on the channel.
+        let incoming = channel_data(CHANNEL_MIN, &[7, 8], false);
+        c.handle_receive(now, &incoming).unwrap();
+
+        let evs = events(&mut c);
+        assert!(matches!(
+            &evs[..],
+            [TurnEvent::Received { source, destination, contents }]
+            if *source == peer() && *destination == relayed() && contents == &[7, 8]
+        ));
+    }
+
+    #[test]
+    fn receive_data_indication() {
+        let now = Instant::now();
+        let mut c = client(Protocol::Udp);
+        allocate(&mut c, now);
+        events(&mut c);
+
+        let mut ind = TurnMessage::send_indication(peer(), &[9, 9]);
+        ind.method = TurnMethod::Data;
+        c.handle_receive(now, &ind.to_bytes(None)).unwrap();
+
+        let evs = events(&mut c);
+        assert!(matches!(
+            &evs[..],
+            [TurnEvent::Received { source, contents, .. }]
+            if *source == peer() && contents == &[9, 9]
+        ));
+    }
+
+    #[test]
+    fn permission_before_allocation() {
+        let now = Instant::now();
+        let mut c = client(Protocol::Udp);
+        c.create_permission(now, peer().ip());
+
+        allocate(&mut c, now);
+
+        let out = transmits(&mut c);
+        assert_eq!(out.len(), 1);
+        let perm = TurnMessage::parse(&out[0], None).unwrap();
+        assert_eq!(perm.method, TurnMethod::CreatePermission);
+    }
+
+    #[test]
+    fn stream_in_chunks() {
+        let now = Instant::now();
+        let mut c = client(Protocol::Tcp);
+        c.handle_timeout(now);
+
+        let req = transmits(&mut c).remove(0);
+        let challenge = reply(&req, |m| {
+            m.class = StunClass::Failure;
+            m.attrs.error_code = Some((401, "Unauthorized".into()));
+            m.attrs.realm = Some("example.org".into());
+            m.attrs.nonce = Some("nonce1".into());
+        });
+
+        // Split the stream in an awkward place.
+        c.handle_receive(now, &challenge[..7]).unwrap();
+        assert!(c.transmits.is_empty());
+        c.handle_receive(now, &challenge[7..]).unwrap();
+
+        let req = transmits(&mut c).remove(0);
+        let ok = reply(&req, |m| {
+            m.attrs.xor_relayed_address = Some(relayed());
+        });
+        let incoming = channel_data(CHANNEL_MIN, &[1], true);
+
+        // Two messages in one chunk, where the ChannelData is not bound (and ignored).
+        let mut both = ok.clone();
+        both.extend_from_slice(&incoming);
+        c.handle_receive(now, &both).unwrap();
+
+        assert_eq!(c.relayed_address(), Some(relayed()));
+        assert!(c.stream_buf.is_empty());
+    }
+
+    #[test]
+    fn close_releases_allocation() {
+        let now = Instant::now();
+        let mut c = client(Protocol::Udp);
+        allocate(&mut c, now);
+        events(&mut c);
+
+        c.close(now);
+        let req = transmits(&mut c).remove(0);
+        let parsed = TurnMessage::parse(&req, None).unwrap();
+        assert_eq!(parsed.method, TurnMethod::Refresh);
+        assert_eq!(parsed.attrs.lifetime, Some(0));
+
+        c.handle_receive(now, &reply(&req, |_| {})).unwrap();
+        let evs = events(&mut c);
+        assert!(matches!(&evs[..], [TurnEvent::Closed]));
+        assert!(c.is_closed());
+    }
+
+    #[test]
+    fn rtc_relays_via_allocation() {
+        use crate::net::Receive;
+        use crate::{CandidateKind, Event, IceCreds, Input, Output, Rtc, RtcConfig};
+
+        let now = Instant::now();
+        let local: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let config = TurnConfig::new(Protocol::Udp, local, server(), "user", "pass");
+        let mut rtc = RtcConfig::new().set_turn_servers(vec![config]).build();
+        rtc.handle_input(Input::Timeout(now)).unwrap();
+
+        fn to_server(rtc: &mut Rtc) -> Vec<u8> {
+            loop {
+                match rtc.poll_output().unwrap() {
+                    Output::Transmit(t) if t.destination == server() => return t.contents.into(),
+                    Output::Timeout(_) => panic!("Expected transmit to the TURN server"),
+                    _ => {}
+                }
+            }
+        }
+
+        let receive = |rtc: &mut Rtc, buf: &[u8]| {
+            let r = Receive::new(Protocol::Udp, server(), local, buf).unwrap();
+            let input = Input::Receive(now, r);
+            assert!(rtc.accepts(&input));
+            rtc.handle_input(input).unwrap();
+        };
+
+        let req = to_server(&mut rtc);
+        receive(&mut rtc, &challenge(&req));
+        let req = to_server(&mut rtc);
+        receive(&mut rtc, &allocated(&req));
+
+        let candidate = loop {
+            match rtc.poll_output().unwrap() {
+                Output::Event(Event::CandidateGathered(c)) => break c,
+                Output::Timeout(_) => panic!("Expected relayed candidate"),
+                _ => {}
+            }
+        };
+        assert_eq!(candidate.kind(), CandidateKind::Relayed);
+        assert_eq!(candidate.addr(), relayed());
+
+        // Connectivity checks from the relayed candidate go via the TURN server.
+        rtc.direct_api().set_remote_ice_credentials(IceCreds::new());
+        rtc.add_remote_candidate(Candidate::host(peer(), "udp").unwrap());
+
+        let mut methods = vec![];
+        for _ in 0..50 {
+            match rtc.poll_output().unwrap() {
+                Output::Transmit(t) => {
+                    assert_ne!(t.source, relayed());
+                    assert_eq!(t.destination, server());
+                    if let Ok(m) = TurnMessage::parse(&t.contents, None) {
+                        methods.push(m.method);
+                    }
+                }
+                Output::Timeout(at) => rtc.handle_input(Input::Timeout(at)).unwrap(),
+                Output::Event(_) => {}
+            }
+            if methods.contains(&TurnMethod::Send) {
+                break;
+            }
+        }
+        assert!(methods.contains(&TurnMethod::CreatePermission));
+        assert!(methods.contains(&TurnMethod::Send));
+
+        // Data relayed from the peer is accepted from the server.
+        let mut ind = TurnMessage::send_indication(peer(), &[0, 1, 0, 0]);
+        ind.method = TurnMethod::Data;
+        receive(&mut rtc, &ind.to_bytes(None));
+    }
+}
@@ -0,0 +1,557 @@
+use std::net::SocketAddr;
+
+use crc::{Crc, CRC_32_ISO_HDLC};
+
+use crate::io::{decode_str, decode_xor, encode_xor, StunClass, StunError, TransId, STUN_MAGIC};
+
+/// Lifetime of a permission on the TURN server.
+pub const PERMISSION_LIFETIME_SECS: u64 = 300;
+
+/// Lifetime of a channel binding on the TURN server.
+pub const CHANNEL_LIFETIME_SECS: u64 = 600;
+
+/// First channel number that can be bound.
+pub const CHANNEL_MIN: u16 = 0x4000;
+
+/// Last channel number that can be bound.
+pub const CHANNEL_MAX: u16 = 0x4fff;
+
+/// The IANA protocol number for UDP in REQUESTED-TRANSPORT.
+const TRANSPORT_UDP: u8 = 17;
+
+const MSG_HEADER_LEN: usize = 20;
+const ATTR_TLV_LENGTH: usize = 4;
+const MSG_INTEGRITY_LEN: usize = 20;
+const FPRINT_LEN: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TurnMethod {
+    Allocate,
+    Refresh,
+    Send,
+    Data,
+    CreatePermission,
+    ChannelBind,
+}
+
+impl TurnMethod {
+    fn from_typ(typ: u16) -> Option<Self> {
+        use TurnMethod::*;
+        Some(match typ & 0b0011_1110_1110_1111 {
+            0x003 => Allocate,
+            0x004 => Refresh,
+            0x006 => Send,
+            0x007 => Data,
+            0x008 => CreatePermission,
+            0x009 => ChannelBind,
+            _ => return None,
+        })
+    }
+
+    fn to_u16(self) -> u16 {
+        use TurnMethod::*;
+        match self {
+            Allocate => 0x003,
+            Refresh => 0x004,
+            Send => 0x006,
+            Data => 0x007,
+            CreatePermission => 0x008,
+            ChannelBind => 0x009,
+        }
+    }
+}
+
+/// The attributes we send or understand in TURN messages.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TurnAttrs {
+    pub username: Option<String>,
+    pub realm: Option<String>,
+    pub nonce: Option<String>,
+    pub error_code: Option<(u16, String)>,
+    pub lifetime: Option<u32>,
+    pub requested_transport: bool,
+    pub channel_number: Option<u16>,
+    pub xor_peer_address: Option<SocketAddr>,
+    pub xor_relayed_address: Option<SocketAddr>,
+    pub xor_mapped_address: Option<SocketAddr>,
+    pub data: Option<Vec<u8>>,
+}
+
+impl TurnAttrs {
+    const CHANNEL_NUMBER: u16 = 0x000c;
+    const LIFETIME: u16 = 0x000d;
+    const XOR_PEER_ADDRESS: u16 = 0x0012;
+    const DATA: u16 = 0x0013;
+    const XOR_RELAYED_ADDRESS: u16 = 0x0016;
+    const REQUESTED_TRANSPORT: u16 = 0x0019;
+    const USERNAME: u16 = 0x0006;
+    const MESSAGE_INTEGRITY: u16 = 0x0008;
+    const ERROR_CODE: u16 = 0x0009;
+    const REALM: u16 = 0x0014;
+    const NONCE: u16 = 0x0015;
+    const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+    const FINGERPRINT: u16 = 0x8028;
+}
+
+/// A TURN message (RFC 8656).
+///
+/// TURN reuses the STUN message format, but with methods and attributes that are of no
+/// interest to ICE. Unlike [`StunMessage`][crate::io::StunMessage] this owns its data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TurnMessage {
+    pub method: TurnMethod,
+    pub class: StunClass,
+    pub trans_id: TransId,
+    pub attrs: TurnAttrs,
+    /// Whether the message carried a MESSAGE-INTEGRITY that checked out against the key
+    /// provided to [`TurnMessage::parse`].
+    pub authenticated: bool,
+}
+
+impl TurnMessage {
+    pub fn new(method: TurnMethod, class: StunClass, attrs: TurnAttrs) -> Self {
+        TurnMessage {
+            method,
+            class,
+            trans_id: TransId::new(),
+            attrs,
+            authenticated: false,
+        }
+    }
+
+    /// An ALLOCATE request for a UDP relay.
+    pub fn allocate(lifetime: u32) -> Self {
+        let attrs = TurnAttrs {
+            lifetime: Some(lifetime),
+            requested_transport: true,
+            ..Default::default()
+        };
+        Self::new(TurnMethod::Allocate, StunClass::Request, attrs)
+    }
+
+    /// A REFRESH request. A lifetime of 0 deletes the allocation.
+    pub fn refresh(lifetime: u32) -> Self {
+        let attrs = TurnAttrs {
+            lifetime: Some(lifetime),
+            ..Default::default()
+        };
+        Self::new(TurnMethod::Refresh, StunClass::Request, attrs)
+    }
+
+    /// A CREATE-PERMISSION request for the IP of `peer`. The port is ignored by the server.
+    pub fn create_permission(peer: SocketAddr) -> Self {
+        let attrs = TurnAttrs {
+            xor_peer_address: Some(peer),
+            ..Default::default()
+        };
+        Self::new(TurnMethod::CreatePermission, StunClass::Request, attrs)
+    }
+
+    /// A CHANNEL-BIND request binding `channel` to `peer`.
+    pub fn channel_bind(channel: u16, peer: SocketAddr) -> Self {
+        let attrs = TurnAttrs {
+            channel_number: Some(channel),
+            xor_peer_address: Some(peer),
+            ..Default::default()
+        };
+        Self::new(TurnMethod::ChannelBind, StunClass::Request, attrs)
+    }
+
+    /// A SEND indication relaying `data` to `peer`.
+    pub fn send_indication(peer: SocketAddr, data: &[u8]) -> Self {
+        let attrs = TurnAttrs {
+            xor_peer_address: Some(peer),
+            data: Some(data.to_vec()),
+            ..Default::default()
+        };
+        Self::new(TurnMethod::Send, StunClass::Indication, attrs)
+    }
+
+    /// Parse a TURN message.
+    ///
+    /// If `key` is provided, a MESSAGE-INTEGRITY attribute is checked against it and the
+    /// result is available in [`TurnMessage::authenticated`].
+    pub fn parse(buf: &[u8], key: Option<&[u8]>) -> Result<TurnMessage, StunError> {
+        if buf.len() < MSG_HEADER_LEN {
+            return Err(StunError::Parse("Buffer too short".into()));
+        }
+
+        let typ = (buf[0] as u16 & 0b0011_1111) << 8 | buf[1] as u16;
+        let len = (buf[2] as u16) << 8 | buf[3] as u16;
+        if len & 0b0000_0011 > 0 {
+            return Err(StunError::Parse("len is not a multiple of 4".into()));
+        }
+        if len as usize != buf.len() - MSG_HEADER_LEN {
+            return Err(StunError::Parse("TURN length vs packet mismatch".into()));
+        }
+        if &buf[4..8] != STUN_MAGIC {
+            return Err(StunError::Parse("magic cookie mismatch".into()));
+        }
+
+        let class = StunClass::from_typ(typ);
+        let Some(method) = TurnMethod::from_typ(typ) else {
+            return Err(StunError::Parse(format!("Not a TURN method: 0x{typ:04x?}")));
+        };
+        let trans_id = TransId::from_slice(&buf[8..20]);
+
+        let mut attrs = TurnAttrs::default();
+        let mut authenticated = false;
+
+        let mut off = MSG_HEADER_LEN;
+        while off < buf.len() {
+            if buf.len() - off < ATTR_TLV_LENGTH {
+                return Err(StunError::Parse("Truncated TURN attribute".into()));
+            }
+            let typ = u16::from_be_bytes([buf[off], buf[off + 1]]);
+            let len = u16::from_be_bytes([buf[off + 2], buf[off + 3]]) as usize;
+            let value_off = off + ATTR_TLV_LENGTH;
+            if len > buf.len() - value_off {
+                return Err(StunError::Parse(format!(
+                    "Bad TURN attribute length: {} > {}",
+                    len,
+                    buf.len() - value_off,
+                )));
+            }
+            let v = &buf[value_off..(value_off + len)];
+
+            match typ {
+                TurnAttrs::USERNAME => {
+                    attrs.username = Some(decode_str(typ, v, len)?.to_string());
+                }
+                TurnAttrs::REALM => {
+                    attrs.realm = Some(decode_str(typ, v, len)?.to_string());
+                }
+                TurnAttrs::NONCE => {
+                    attrs.nonce = Some(decode_str(typ, v, len)?.to_string());
+                }
+                TurnAttrs::ERROR_CODE => {
+                    if len < 4 {
+                        return Err(StunError::Parse("Error code too short".into()));
+                    }
+                    let code = (v[2] & 0b0000_0111) as u16 * 100 + (v[3] % 100) as u16;
+                    let reason = decode_str(typ, &v[4..], len - 4).unwrap_or_default();
+                    attrs.error_code = Some((code, reason.to_string()));
+                }
+                TurnAttrs::LIFETIME => {
+                    if len != 4 {
+                        return Err(StunError::Parse("Lifetime that isnt 4 in length".into()));
+                    }
+                    attrs.lifetime = Some(u32::from_be_bytes([v[0], v[1], v[2], v[3]]));
+                }
+                TurnAttrs::CHANNEL_NUMBER => {
+                    if len != 4 {
+                        return Err(StunError::Parse(
+                            "ChannelNumber that isnt 4 in length".into(),
+                        ));
+                    }
+                    attrs.channel_number = Some(u16::from_be_bytes([v[0], v[1]]));
+                }
+                TurnAttrs::REQUESTED_TRANSPORT => {
+                    attrs.requested_transport = len == 4 && v[0] == TRANSPORT_UDP;
+                }
+                TurnAttrs::XOR_PEER_ADDRESS => {
+                    attrs.xor_peer_address = Some(decode_xor_checked(v, trans_id)?);
+                }
+                TurnAttrs::XOR_RELAYED_ADDRESS => {
+                    attrs.xor_relayed_address = Some(decode_xor_checked(v, trans_id)?);
+                }
+                TurnAttrs::XOR_MAPPED_ADDRESS => {
+                    attrs.xor_mapped_address = Some(decode_xor_checked(v, trans_id)?);
+                }
+                TurnAttrs::DATA => {
+                    attrs.data = Some(v.to_vec());
+                }
+                TurnAttrs::MESSAGE_INTEGRITY => {
+                    if len != MSG_INTEGRITY_LEN {
+                        return Err(StunError::Parse(
+                            "Expected message integrity to have length 20".into(),
+                        ));
+                    }
+                    if let Some(key) = key {
+                        // The length in the header is adjusted to end with the integrity.
+                        let integrity_len = (value_off + len - MSG_HEADER_LEN) as u16;
+                        let comp = crate::crypto::sha1_hmac(
+                            key,
+                            &[&buf[..2], &integrity_len.to_be_bytes(), &buf[4..off]],
+                        );
+                        authenticated = comp[..] == *v;
+                    }
+                    // Only FINGERPRINT is allowed after MESSAGE-INTEGRITY.
+                    break;
+                }
+                _ => {}
+            }
+
+            // attributes are on even 32 bit boundaries
+            let pad = (4 - (len % 4)) % 4;
+            off = value_off + len + pad;
+        }
+
+        Ok(TurnMessage {
+            method,
+            class,
+            trans_id,
+            attrs,
+            authenticated,
+        })
+    }
+
+    /// Serialize this message.
+    ///
+    /// With a `key` the message gets a MESSAGE-INTEGRITY as per the long-term credential
+    /// mechanism. All messages get a FINGERPRINT.
+    pub fn to_bytes(&self, key: Option<&[u8]>) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(128);
+
+        let typ = self.class.to_u16() | self.method.to_u16();
+        buf.extend_from_slice(&typ.to_be_bytes());
+        buf.extend_from_slice(&[0, 0]); // length placeholder
+        buf.extend_from_slice(STUN_MAGIC);
+        buf.extend_from_slice(self.trans_id.as_bytes());
+
+        let a = &self.attrs;
+        let trans_id = self.trans_id.as_bytes();
+
+        if let Some(v) = a.channel_number {
+            let mut value = [0; 4];
+            value[..2].copy_from_slice(&v.to_be_bytes());
+            write_attr(&mut buf, TurnAttrs::CHANNEL_NUMBER, &value);
+        }
+        if let Some(v) = a.lifetime {
+            write_attr(&mut buf, TurnAttrs::LIFETIME, &v.to_be_bytes());
+        }
+        if a.requested_transport {
+            write_attr(
+                &mut buf,
+                TurnAttrs::REQUESTED_TRANSPORT,
+                &[TRANSPORT_UDP, 0, 0, 0],
+            );
+        }
+        for (typ, addr) in [
+            (TurnAttrs::XOR_PEER_ADDRESS, a.xor_peer_address),
+            (TurnAttrs::XOR_RELAYED_ADDRESS, a.xor_relayed_address),
+            (TurnAttrs::XOR_MAPPED_ADDRESS, a.xor_mapped_address),
+        ] {
+            if let Some(addr) = addr {
+                let mut value = [0_u8; 20];
+                let len = encode_xor(addr, &mut value, trans_id);
+                write_attr(&mut buf, typ, &value[..len]);
+            }
+        }
+        if let Some((code, reason)) = &a.error_code {
+            let mut value = vec![0, 0, (code / 100) as u8, (code % 100) as u8];
+            value.extend_from_slice(reason.as_bytes());
+            write_attr(&mut buf, TurnAttrs::ERROR_CODE, &value);
+        }
+        if let Some(v) = &a.data {
+            write_attr(&mut buf, TurnAttrs::DATA, v);
+        }
+        if let Some(v) = &a.username {
+            write_attr(&mut buf, TurnAttrs::USERNAME, v.as_bytes());
+        }
+        if let Some(v) = &a.realm {
+            write_attr(&mut buf, TurnAttrs::REALM, v.as_bytes());
+        }
+        if let Some(v) = &a.nonce {
+            write_attr(&mut buf, TurnAttrs::NONCE, v.as_bytes());
+        }
+
+        if let Some(key) = key {
+            // The integrity is calculated with a length that includes the attribute itself.
+            set_len(&mut buf, ATTR_TLV_LENGTH + MSG_INTEGRITY_LEN);
+            let hmac = crate::crypto::sha1_hmac(key, &[&buf]);
+            write_attr(&mut buf, TurnAttrs::MESSAGE_INTEGRITY, &hmac);
+        }
+
+        set_len(&mut buf, ATTR_TLV_LENGTH + FPRINT_LEN);
+        let crc = Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&buf) ^ 0x5354_554e;
+        write_attr(&mut buf, TurnAttrs::FINGERPRINT, &crc.to_be_bytes());
+
+        buf
+    }
+}
+
+/// The long-term credential key.
+///
+/// `MD5(username ":" realm ":" password)`, as per RFC 8489 section 9.2.2.
+pub fn long_term_key(username: &str, realm: &str, password: &str) -> [u8; 16] {
+    crate::crypto::md5(&[
+        username.as_bytes(),
+        b":",
+        realm.as_bytes(),
+        b":",
+        password.as_bytes(),
+    ])
+}
+
+/// Whether the first byte indicates a ChannelData message.
+pub fn is_channel_data(buf: &[u8]) -> bool {
+    !buf.is_empty() && buf[0] & 0b1100_0000 == 0b0100_0000
+}
+
+/// Wrap `data` in a ChannelData message.
+///
+/// Over TCP and TLS, the message must be padded to a multiple of 4 bytes.
+pub fn channel_data(channel: u16, data: &[u8], pad: bool) -> Vec<u8> {
+    let padding = if pad { (4 - (data.len() % 4)) % 4 } else { 0 };
+
+    let mut buf = Vec::with_capacity(4 + data.len() + padding);
+    buf.extend_from_slice(&channel.to_be_bytes());
+    buf.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    buf.extend_from_slice(data);
+    buf.resize(buf.len() + padding, 0);
+
+    buf
+}
+
+/// Parse a ChannelData message into channel number and data.
+pub fn parse_channel_data(buf: &[u8]) -> Option<(u16, &[u8])> {
+    if buf.len() < 4 || !is_channel_data(buf) {
+        return None;
+    }
+
+    let channel = u16::from_be_bytes([buf[0], buf[1]]);
+    let len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+
+    // Trailing bytes are padding (TCP) or ignored (UDP).
+    buf.get(4..(4 + len)).map(|data| (channel, data))
+}
+
+/// For stream transports, the length of the first message in `buf`.
+///
+/// Returns `None` if there is not yet enough data to tell.
+pub fn stream_message_len(buf: &[u8]) -> Option<usize> {
+    if buf.len() < 4 {
+        return None;
+    }
+
+    let len = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+
+    if is_channel_data(buf) {
+        // Over streams, ChannelData is padded to 4 bytes.
+        Some(4 + len + (4 - (len % 4)) % 4)
+    } else {
+        Some(MSG_HEADER_LEN + len)
+    }
+}
+
+fn decode_xor_checked(v: &[u8], trans_id: TransId) -> Result<SocketAddr, StunError> {
+    // IPv4 is 8 bytes, IPv6 20.
+    if v.len() < 8 || (v[1] == 2 && v.len() < 20) {
+        return Err(StunError::Parse("Address attribute too short".into()));
+    }
+    decode_xor(v, trans_id)
+}
+
+fn write_attr(buf: &mut Vec<u8>, typ: u16, value: &[u8]) {
+    buf.extend_from_slice(&typ.to_be_bytes());
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buf.extend_from_slice(value);
+    let pad = (4 - (value.len() % 4)) % 4;
+    buf.resize(buf.len() + pad, 0);
+}
+
+/// Set the header length to the current attributes plus `extra`.
+fn set_len(buf: &mut [u8], extra: usize) {
+    let len = (buf.len() - MSG_HEADER_LEN + extra) as u16;
+    buf[2..4].copy_from_slice(&len.to_be_bytes());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn long_term_key_is_md5() {
+        let key = long_term_key("user", "realm", "pass");
+        // md5("user:realm:pass")
+        assert_eq!(
+            key,
+            [
+                0x84, 0x93, 0xfb, 0xc5, 0x3b, 0xa5, 0x82, 0xfb, 0x4c, 0x04, 0x4c, 0x45, 0x6b, 0xdc,
+                0x40, 0xeb
+            ]
+        );
+    }
+
+    #[test]
+    fn roundtrip_authenticated() {
+        let key = long_term_key("user", "realm", "pass");
+
+        let mut msg = TurnMessage::channel_bind(0x4001, "1.2.3.4:5000".parse().unwrap());
+        msg.attrs.username = Some("user".into());
+        msg.attrs.realm = Some("realm".into());
+        msg.attrs.nonce = Some("abcdef".into());
+
+        let buf = msg.to_bytes(Some(&key));
+        assert_eq!(buf.len() % 4, 0);
+
+        let parsed = TurnMessage::parse(&buf, Some(&key)).unwrap();
+        assert!(parsed.authenticated);
+        assert_eq!(parsed.method, TurnMethod::ChannelBind);
+        assert_eq!(parsed.class, StunClass::Request);
+        assert_eq!(parsed.trans_id, msg.trans_id);
+        assert_eq!(parsed.attrs, msg.attrs);
+
+        let wrong = long_term_key("user", "realm", "wrong");
+        let parsed = TurnMessage::parse(&buf, Some(&wrong)).unwrap();
+        assert!(!parsed.authenticated);
+    }
+
+    #[test]
+    fn roundtrip_data_indication() {
+        let mut msg = TurnMessage::send_indication("[::1]:5000".parse().unwrap(), &[1, 2, 3]);
+        msg.method = TurnMethod::Data;
+
+        let buf = msg.to_bytes(None);
+        let parsed = TurnMessage::parse(&buf, None).unwrap();
+
+        assert!(!parsed.authenticated);
+        assert_eq!(parsed.class, StunClass::Indication);
+        assert_eq!(parsed.attrs.data.as_deref(), Some(&[1, 2, 3][..]));
+        assert_eq!(
+            parsed.attrs.xor_peer_address,
+            Some("[::1]:5000".parse().unwrap())
+        );
+    }
+
+    #[test]
+    fn parse_error_response() {
+        let mut msg = TurnMessage::allocate(600);
+        msg.class = StunClass::Failure;
+        msg.attrs.error_code = Some((401, "Unauthorized".into()));
+        msg.attrs.realm = Some("example.org".into());
+        msg.attrs.nonce = Some("n0nce".into());
+
+        let parsed = TurnMessage::parse(&msg.to_bytes(None), None).unwrap();
+        assert_eq!(parsed.attrs.error_code, Some((401, "Unauthorized".into())));
+        assert_eq!(parsed.attrs.realm.as_deref(), Some("example.org"));
+    }
+
+    #[test]
+    fn channel_data_framing() {
+        let buf = channel_data(0x4000, &[1, 2, 3, 4, 5], false);
+        assert_eq!(buf, [0x40, 0x00, 0x00, 0x05, 1, 2, 3, 4, 5]);
+        assert_eq!(
+            parse_channel_data(&buf),
+            Some((0x4000, &[1, 2, 3, 4, 5][..]))
+        );
+
+        let padded = channel_data(0x4000, &[1, 2, 3, 4, 5], true);
+        assert_eq!(padded.len(), 12);
+        assert_eq!(stream_message_len(&padded), Some(12));
+        assert_eq!(
+            parse_channel_data(&padded),
+            Some((0x4000, &[1, 2, 3, 4, 5][..]))
+        );
+
+        assert_eq!(parse_channel_data(&buf[..6]), None);
+    }
+
+    #[test]
+    fn stream_len_of_stun() {
+        let buf = TurnMessage::refresh(0).to_bytes(None);
+        assert_eq!(stream_message_len(&buf), Some(buf.len()));
+        assert_eq!(stream_message_len(&buf[..3]), None);
+    }
+}
@@ -1,3 +1,5 @@
+use std::cell::RefCell;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 mod bit_pattern;
@@ -80,31 +82,76 @@ pub(crate) fn calculate_rtt_ms(ntp_time: Duration, delay: u32, last_report: u32)
     Some(rtt_seconds as f32 * 1000.0 + rtt_fraction * 1000.0)
 }
 
+thread_local! {
+    /// The generator of the seeded Rtc instance currently being called, if any.
+    static INSTANCE_RNG: RefCell<Option<InstanceRng>> = RefCell::new(None);
+}
+
 pub struct NonCryptographicRng;
 
 impl NonCryptographicRng {
     #[inline(always)]
     pub fn u8() -> u8 {
-        fastrand::u8(..)
+        Self::with(|r| r.u8(..))
     }
 
     #[inline(always)]
     pub fn u16() -> u16 {
-        fastrand::u16(..)
+        Self::with(|r| r.u16(..))
     }
 
     #[inline(always)]
     pub fn u32() -> u32 {
-        fastrand::u32(..)
+        Self::with(|r| r.u32(..))
     }
 
     #[inline(always)]
     pub fn u64() -> u64 {
-        fastrand::u64(..)
+        Self::with(|r| r.u64(..))
     }
 
     #[inline(always)]
     pub fn f32() -> f32 {
-        fastrand::f32()
+        Self::with(|r| r.f32())
+    }
+
+    /// Uses the generator of the current instance, or the one of the thread.
+    fn with<T>(f: impl FnOnce(&mut fastrand::Rng) -> T) -> T {
+        INSTANCE_RNG.with(|rng| match &*rng.borrow() {
+            Some(v) => f(&mut v.0.lock().unwrap_or_else(|e| e.into_inner())),
+            // Seeded from the generator of the thread.
+            None => f(&mut fastrand::Rng::new()),
+        })
+    }
+}
+
+/// Random generator owned by an Rtc instance configured with a seed.
+#[derive(Debug, Clone)]
+pub(crate) struct InstanceRng(Arc<Mutex<fastrand::Rng>>);
+
+impl InstanceRng {
+    pub fn with_seed(seed: u64) -> Self {
+        InstanceRng(Arc::new(Mutex::new(fastrand::Rng::with_seed(seed))))
+    }
+
+    /// Makes `rng` the generator of [`NonCryptographicRng`] until the guard is dropped.
+    ///
+    /// `None` is the generator of the thread, which stops an instance without a seed
+    /// from using the generator of another.
+    pub fn enter(rng: Option<&InstanceRng>) -> RngGuard {
+        let previous = INSTANCE_RNG.with(|r| r.replace(rng.cloned()));
+        RngGuard(previous)
+    }
+}
+
+/// Restores the previous generator when dropped.
+#[derive(Debug)]
+pub(crate) struct RngGuard(Option<InstanceRng>);
+
+impl Drop for RngGuard {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        // Fails only when the thread is shutting down.
+        let _ = INSTANCE_RNG.try_with(|r| *r.borrow_mut() = previous);
     }
 }
@@ -0,0 +1,147 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::dtls::DtlsRole;
+use crate::limits::Limit;
+use crate::rtp_::{Extension, Mid};
+
+/// Non-fatal conditions detected by the [`Rtc`][crate::Rtc] instance.
+///
+/// These are surfaced as [`Event::Warning`][crate::Event::Warning] so that applications
+/// can count and alert on them. Each warning is also logged at `warn` level when it
+/// happens. None of them stop the session, but they are usually a sign of a
+/// misconfiguration or a remote peer behaving unexpectedly.
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Warning {
+    /// A DTLS datagram was produced that is larger than the MTU we aim for.
+    ///
+    /// The value is the size of the datagram in bytes.
+    DtlsAboveMtu(usize),
+
+    /// An RTP/RTCP datagram was produced that is larger than the MTU we aim for.
+    ///
+    /// The value is the size of the datagram in bytes. Not emitted in RTP mode,
+    /// since then the packet sizes are in the hands of the API user.
+    RtpAboveMtu(usize),
+
+    /// A remote SDP tried to remap an RTP header extension that was already locked
+    /// down by a previous negotiation. The remap is ignored.
+    ExtensionRemapConflict {
+        /// The extension the remote tried to remap.
+        extension: Extension,
+        /// The id the extension is locked to.
+        locked_id: u8,
+        /// The id the remote peer asked for.
+        requested_id: u8,
+    },
+
+    /// A remote SDP tried to map an RTP header extension to an id that is already
+    /// locked to another extension by a previous negotiation. The mapping is ignored.
+    ///
+    /// Audio and video can use different ids for the same extension, but one id
+    /// can't mean different extensions.
+    ExtensionIdInUse {
+        /// The extension the remote tried to map.
+        extension: Extension,
+        /// The id the remote peer asked for.
+        id: u8,
+        /// The extension already locked to the id.
+        existing: Extension,
+    },
+
+    /// The pacer queue has grown so large that the average packet has been waiting
+    /// longer than the queue limit. The pacer will exceed the pacing rate to drain it.
+    ///
+    /// Emitted once when entering this state, and again only after it has recovered.
+    PacerQueueOverflow {
+        /// Average time a packet has spent in the queue.
+        avg_queue_time: Duration,
+    },
+
+    /// A data channel event was dropped since we don't know of the channel.
+    ///
+    /// The value is the SCTP stream id.
+    ChannelEventDropped(u16),
+
+    /// A remote SDP asked for the opposite DTLS role of the one settled by a previous
+    /// negotiation. The role can't change for an established transport and is kept.
+    ///
+    /// The value is the role we keep.
+    DtlsRoleChange(DtlsRole),
+
+    /// The remote SDP has m-lines outside of a BUNDLE group.
+    ///
+    /// str0m runs all media over a single ICE/DTLS transport. A peer that rejects
+    /// BUNDLE expects a separate transport per m-line, which means the listed media
+    /// will most likely not flow. Separate transports per m-line are not supported.
+    BundleRejected(Vec<Mid>),
+
+    /// The usage of a resource capped by [`Limits`][crate::Limits] has reached 80% of
+    /// the limit.
+    ///
+    /// Emitted once when getting close, and again only after usage has gone down.
+    LimitApproached {
+        /// The limit being approached.
+        limit: Limit,
+        /// The current usage.
+        count: usize,
+        /// The configured max.
+        max: usize,
+    },
+
+    /// Something the remote peer opened was refused since it would go above a limit
+    /// configured with [`Limits`][crate::Limits].
+    ///
+    /// This is incoming RTP for new streams or data channels opened by the remote peer.
+    LimitReached(Limit),
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Warning::DtlsAboveMtu(n) => write!(f, "DTLS above MTU: {}", n),
+            Warning::RtpAboveMtu(n) => write!(f, "RTP above MTU: {}", n),
+            Warning::ExtensionRemapConflict {
+                extension,
+                locked_id,
+                requested_id,
+            } => write!(
+                f,
+                "Extmap locked by previous negotiation: {} {} -> {}",
+                extension, locked_id, requested_id
+            ),
+            Warning::ExtensionIdInUse {
+                extension,
+                id,
+                existing,
+            } => write!(
+                f,
+                "Extmap id {} already locked to {}, ignore: {}",
+                id, existing, extension
+            ),
+            Warning::PacerQueueOverflow { avg_queue_time } => {
+                write!(f, "Pacer queue overflow: {:?}", avg_queue_time)
+            }
+            Warning::ChannelEventDropped(id) => {
+                write!(f, "Dropped channel event for stream id: {}", id)
+            }
+            Warning::DtlsRoleChange(role) => {
+                write!(
+                    f,
+                    "Remote SDP attempts to change DTLS role, keep: {:?}",
+                    role
+                )
+            }
+            Warning::BundleRejected(mids) => {
+                write!(f, "Remote SDP has m-lines outside BUNDLE: {:?}", mids)
+            }
+            Warning::LimitApproached { limit, count, max } => {
+                write!(f, "Approaching limit of {}: {}/{}", limit, count, max)
+            }
+            Warning::LimitReached(limit) => {
+                write!(f, "Refused above limit of {}", limit)
+            }
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::net::{DatagramRecv, Receive};
+use str0m::{Candidate, Event, Input, Output, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn latch_on_nat_rebinding() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let rtc = Rtc::builder().set_address_latching(true).build();
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let rebound: SocketAddr = (Ipv4Addr::new(3, 3, 3, 3), 3000).into();
+    let mut sent_to_rebound = 0;
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1_u8; 80])?;
+
+        let wallclock = r.start + r.duration();
+        let time = r.duration().into();
+        r.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![2_u8; 80])?;
+
+        // After 2 seconds, the NAT in front of L maps its media to a new port.
+        let rebind = l.duration() > Duration::from_secs(2);
+
+        if l.last < r.last {
+            forward(&mut l, &mut r, rebind.then_some(rebound))?;
+        } else {
+            sent_to_rebound += forward(&mut r, &mut l, None)?;
+        }
+
+        if l.duration() > Duration::from_secs(4) {
+            break;
+        }
+    }
+
+    let latched: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::RemoteAddressLatched { previous, current } => Some((*previous, *current)),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(
+        latched,
+        vec![(SocketAddr::from((Ipv4Addr::new(1, 1, 1, 1), 1000)), rebound)]
+    );
+    assert!(sent_to_rebound > 0, "R should send to the latched address");
+
+    Ok(())
+}
+
+/// Like `common::progress`, but rewrites the source of RTP/RTCP from `f`, and counts
+/// transmits to the rebound address (3.3.3.3).
+fn forward(
+    f: &mut TestRtc,
+    t: &mut TestRtc,
+    rewrite: Option<SocketAddr>,
+) -> Result<usize, RtcError> {
+    let mut to_rebound = 0;
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                if v.destination.ip() == Ipv4Addr::new(3, 3, 3, 3) {
+                    to_rebound += 1;
+                }
+
+                // RTP and RTCP, see RFC 7983. STUN and DTLS keep the original source.
+                let is_media = (128..=191).contains(&v.contents[0]);
+
+                let source = match rewrite {
+                    Some(addr) if is_media => addr,
+                    _ => v.source,
+                };
+
+                let contents: DatagramRecv = (&*v.contents).try_into()?;
+
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source,
+                        destination: v.destination,
+                        contents,
+                    },
+                );
+                t.span.in_scope(|| t.rtc.handle_input(input))?;
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+        }
+    }
+
+    Ok(to_rebound)
+}
@@ -0,0 +1,105 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::{AppCodecName, Codec};
+use str0m::media::{Direction, Frequency, MediaKind};
+use str0m::{Candidate, Event, RtcConfig, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn application_media() -> Result<(), RtcError> {
+    init_log();
+
+    let name = AppCodecName::new("x-haptics").unwrap();
+
+    let rtc = |pt: u8| {
+        let mut config = RtcConfig::new();
+        config
+            .codec_config()
+            .add_application(pt.into(), name, Frequency::NINETY_KHZ);
+        config.build()
+    };
+
+    // Different PT on each side, the encoding name is what matches.
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc(120));
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc(121));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Application, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let offer_str = offer.to_sdp_string();
+    assert!(offer_str.contains("m=application 9 UDP/TLS/RTP/SAVPF 120"));
+    assert!(offer_str.contains("a=rtpmap:120 x-haptics/90000"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = *l
+        .codec_config()
+        .find(|p| p.spec().codec.is_application())
+        .unwrap();
+    assert_eq!(params.spec().codec, Codec::Application(name));
+    let pt = params.pt();
+
+    let mut count = 0_u8;
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![count; 20])?;
+        count = count.wrapping_add(1);
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let added = r.events.iter().find_map(|(_, e)| match e {
+        Event::MediaAdded(v) => Some(v),
+        _ => None,
+    });
+    assert_eq!(added.unwrap().kind, MediaKind::Application);
+
+    let data: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    assert!(data.len() > 100, "Not enough MediaData: {}", data.len());
+
+    // Payloads are passed through untouched.
+    for d in data {
+        assert_eq!(d.data.len(), 20);
+        assert!(d.data.iter().all(|b| *b == d.data[0]));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,280 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::{Codec, CodecExtra};
+use str0m::media::{Direction, MediaKind};
+use str0m::rtp::dd::{self, DecodeTargetIndication, DependencyDescriptor};
+use str0m::rtp::dd::{DependencyStructure, FrameTemplate};
+use str0m::rtp::{Extension, ExtensionValues, Ssrc};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log, progress, TestRtc};
+
+/// An OBU with the size field set, as in the low overhead bitstream format.
+fn obu(obu_type: u8, len: usize, fill: u8) -> Vec<u8> {
+    let mut v = vec![(obu_type << 3) | 0x02];
+    let mut n = len;
+    while n >= 0x80 {
+        v.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    v.push(n as u8);
+    v.resize(v.len() + len, fill);
+    v
+}
+
+#[test]
+pub fn av1_with_dependency_descriptor() -> Result<(), RtcError> {
+    init_log();
+
+    let dd_ext = Extension::with_serializer(dd::URI, dd::Serializer);
+
+    let rtc_l = Rtc::builder()
+        .enable_av1(true)
+        .set_extension(12, dd_ext.clone())
+        .build();
+    let rtc_r = Rtc::builder()
+        .enable_av1(true)
+        .set_extension(12, dd_ext)
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l
+        .rtc
+        .codec_config()
+        .find(|p| p.spec().codec == Codec::Av1)
+        .map(|p| p.pt())
+        .unwrap();
+
+    let structure = DependencyStructure {
+        template_id_offset: 0,
+        decode_target_count: 1,
+        templates: vec![
+            FrameTemplate {
+                spatial_id: 0,
+                temporal_id: 0,
+                dtis: vec![DecodeTargetIndication::Switch],
+                fdiffs: vec![],
+            },
+            FrameTemplate {
+                spatial_id: 0,
+                temporal_id: 0,
+                dtis: vec![DecodeTargetIndication::Switch],
+                fdiffs: vec![1],
+            },
+        ],
+        resolutions: vec![],
+    };
+
+    let mut sent = vec![];
+    let mut frame_number = 0_u16;
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = l.duration().into();
+
+        let is_keyframe = frame_number == 0;
+
+        let mut data = vec![];
+        if is_keyframe {
+            // Sequence header
+            data.extend(obu(1, 10, 0x01));
+        }
+        data.extend(obu(6, 3000, frame_number as u8));
+
+        let dd = DependencyDescriptor {
+            template_id: if is_keyframe { 0 } else { 1 },
+            frame_number,
+            structure: is_keyframe.then(|| structure.clone()),
+            ..Default::default()
+        };
+
+        l.writer(mid)
+            .unwrap()
+            .user_extension_value(dd)
+            .write(pt, wallclock, time, data.clone())?;
+
+        sent.push(data);
+        frame_number += 1;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(2) {
+            break;
+        }
+    }
+
+    let received: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| {
+            if let Event::MediaData(d) = e {
+                Some(d)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    assert!(received.len() > 10);
+
+    for data in received {
+        let CodecExtra::Av1(extra) = data.codec_extra else {
+            panic!("Got non AV1 CodecExtra")
+        };
+
+        // The ext values are from the last packet of the frame.
+        let dd = data
+            .ext_vals
+            .user_values
+            .get::<DependencyDescriptor>()
+            .unwrap();
+        assert!(dd.end_of_frame);
+        assert!(!dd.start_of_frame);
+        assert_eq!(dd.structure, None);
+
+        let n = dd.frame_number as usize;
+        assert_eq!(data.data, sent[n]);
+        assert_eq!(extra.is_keyframe, n == 0);
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn av1_end_of_frame_from_dependency_descriptor() -> Result<(), RtcError> {
+    init_log();
+
+    let dd_ext = Extension::with_serializer(dd::URI, dd::Serializer);
+
+    let rtc_l = Rtc::builder()
+        .set_rtp_mode(true)
+        .enable_av1(true)
+        .set_extension(12, dd_ext.clone())
+        .build();
+    let rtc_r = Rtc::builder()
+        .set_rtp_mode(true)
+        .enable_av1(true)
+        .set_extension(12, dd_ext)
+        .build();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(rtc_l, rtc_r);
+
+    let mid = "vid".into();
+    let ssrc: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Video);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Video);
+    r.direct_api().expect_stream_rx(ssrc, None, mid, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l
+        .rtc
+        .codec_config()
+        .find(|p| p.spec().codec == Codec::Av1)
+        .map(|p| p.pt())
+        .unwrap();
+
+    let mut index: u64 = 0;
+    let mut write_at = l.last;
+
+    while l.duration() < Duration::from_secs(2) {
+        if l.last >= write_at {
+            write_at = l.last + Duration::from_millis(10);
+            let wallclock = l.start + l.duration();
+
+            // Three packets per frame, and never a marker bit.
+            let dd = DependencyDescriptor {
+                start_of_frame: index % 3 == 0,
+                end_of_frame: index % 3 == 2,
+                frame_number: (index / 3) as u16,
+                ..Default::default()
+            };
+
+            let mut exts = ExtensionValues::default();
+            exts.user_values.set(dd);
+
+            let time = ((index / 3) * 3000 + 47_000_000) as u32;
+            let seq_no = (47_000 + index).into();
+            index += 1;
+
+            l.direct_api()
+                .stream_tx(&ssrc)
+                .unwrap()
+                .write_rtp(
+                    pt,
+                    seq_no,
+                    time,
+                    wallclock,
+                    false,
+                    exts,
+                    true,
+                    vec![0x1, 0x2, 0x3, 0x4],
+                )
+                .expect("clean write");
+        }
+
+        progress(&mut l, &mut r)?;
+    }
+
+    let received: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| {
+            if let Event::RtpPacket(v) = e {
+                Some(v)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    assert!(received.len() > 30);
+
+    for packet in received {
+        assert!(!packet.header.marker);
+
+        let dd = packet
+            .header
+            .ext_vals
+            .user_values
+            .get::<DependencyDescriptor>()
+            .unwrap();
+        assert_eq!(packet.end_of_frame, dd.end_of_frame);
+        assert_eq!(packet.end_of_frame, (*packet.seq_no - 47_000) % 3 == 2);
+    }
+
+    Ok(())
+}
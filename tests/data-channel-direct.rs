@@ -1,7 +1,7 @@
 use std::net::Ipv4Addr;
 use std::time::Duration;
 
-use str0m::channel::ChannelConfig;
+use str0m::channel::{ChannelCloseReason, ChannelConfig};
 use str0m::{Candidate, Event, RtcConfig, RtcError};
 use tracing::info_span;
 
@@ -99,3 +99,98 @@ pub fn data_channel_direct() -> Result<(), RtcError> {
 
     Ok(())
 }
+
+#[test]
+pub fn data_channel_direct_close_reason() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+
+    let rtc_r = RtcConfig::new().set_ice_lite(true).build();
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1.clone());
+    l.add_remote_candidate(host2.clone());
+    r.add_local_candidate(host2);
+    r.add_remote_candidate(host1);
+
+    let finger_l = l.direct_api().local_dtls_fingerprint();
+    let finger_r = r.direct_api().local_dtls_fingerprint();
+
+    l.direct_api().set_remote_fingerprint(finger_r);
+    r.direct_api().set_remote_fingerprint(finger_l);
+
+    let creds_l = l.direct_api().local_ice_credentials();
+    let creds_r = r.direct_api().local_ice_credentials();
+
+    l.direct_api().set_remote_ice_credentials(creds_r);
+    r.direct_api().set_remote_ice_credentials(creds_l);
+
+    l.direct_api().set_ice_controlling(true);
+    r.direct_api().set_ice_controlling(false);
+
+    l.direct_api().start_dtls(true).unwrap();
+    r.direct_api().start_dtls(false).unwrap();
+
+    l.direct_api().start_sctp(true);
+    r.direct_api().start_sctp(false);
+
+    let config = ChannelConfig {
+        negotiated: Some(1),
+        label: "my-chan".into(),
+        ..Default::default()
+    };
+    let cid_l = l.direct_api().create_data_channel(config.clone());
+    let cid_r = r.direct_api().create_data_channel(config);
+
+    loop {
+        if l.channel(cid_l).is_some() && r.channel(cid_r).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let reason = ChannelCloseReason {
+        code: 4000,
+        reason: "going away".into(),
+    };
+    l.direct_api()
+        .close_data_channel_with_reason(cid_l, reason.clone());
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if r.events
+            .iter()
+            .any(|(_, event)| event == &Event::ChannelClose(cid_r))
+        {
+            break;
+        }
+
+        if l.duration() > Duration::from_secs(10) {
+            break;
+        }
+    }
+
+    let pos_reason = r
+        .events
+        .iter()
+        .position(|(_, event)| event == &Event::ChannelCloseReason(cid_r, reason.clone()))
+        .expect("close reason on remote side");
+    let pos_close = r
+        .events
+        .iter()
+        .position(|(_, event)| event == &Event::ChannelClose(cid_r))
+        .unwrap();
+    assert!(pos_reason < pos_close);
+
+    // The side closing doesn't get its own reason back.
+    assert!(!l
+        .events
+        .iter()
+        .any(|(_, event)| matches!(event, Event::ChannelCloseReason(..))));
+
+    Ok(())
+}
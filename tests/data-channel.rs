@@ -1,11 +1,15 @@
 use std::net::Ipv4Addr;
 use std::time::Duration;
 
-use str0m::{Candidate, RtcError};
+use str0m::channel::{ChannelConfig, ChannelPriority, Reliability, SctpParams, SctpRto};
+use str0m::error::SctpError;
+use str0m::media::{Direction, MediaKind};
+use str0m::net::Receive;
+use str0m::{Candidate, Event, Input, Output, Rtc, RtcError};
 use tracing::info_span;
 
 mod common;
-use common::{init_log, progress, TestRtc};
+use common::{init_log, progress, progress_with_loss, TestRtc};
 
 #[test]
 pub fn data_channel() -> Result<(), RtcError> {
@@ -55,3 +59,886 @@ pub fn data_channel() -> Result<(), RtcError> {
 
     Ok(())
 }
+
+#[test]
+pub fn data_channel_per_message_reliability() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("Game state".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::ChannelOpen(_, _)))
+        {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let lossy = Reliability::MaxRetransmits { retransmits: 0 };
+
+    let mut sent = 0;
+    for i in 0..20 {
+        let mut chan = l.channel(cid).expect("channel");
+        if i % 2 == 0 {
+            chan.write_with_reliability(true, b"position", lossy)?;
+        } else {
+            chan.write(true, b"event")?;
+        }
+        sent += 1;
+        progress(&mut l, &mut r)?;
+    }
+
+    let start = l.duration();
+    loop {
+        progress(&mut l, &mut r)?;
+        if l.duration() - start > Duration::from_secs(2) {
+            break;
+        }
+    }
+
+    // Nothing is lost in the test network, so all messages arrive.
+    let received = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::ChannelData(_)))
+        .count();
+    assert_eq!(received, sent);
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_close_and_reopen() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("first".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    let opened = |r: &TestRtc, label: &str| {
+        r.events.iter().find_map(|(_, e)| match e {
+            Event::ChannelOpen(id, name) if name == label => Some(*id),
+            _ => None,
+        })
+    };
+
+    let cid_r = loop {
+        if let Some(id) = opened(&r, "first") {
+            break id;
+        }
+        progress(&mut l, &mut r)?;
+    };
+
+    l.direct_api().close_data_channel(cid);
+
+    // The stream reset closes the channel on the remote side.
+    loop {
+        if r.events
+            .iter()
+            .any(|(_, e)| e == &Event::ChannelClose(cid_r))
+        {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(
+            l.duration() < Duration::from_secs(10),
+            "no ChannelClose at R"
+        );
+    }
+    assert!(l.events.iter().any(|(_, e)| e == &Event::ChannelClose(cid)));
+    assert!(l.channel(cid).is_none());
+
+    // A new channel opens once the reset is done, possibly on the same stream id.
+    let cid2 = l.direct_api().create_data_channel(ChannelConfig {
+        label: "second".into(),
+        ..Default::default()
+    });
+
+    let cid2_r = loop {
+        if let Some(id) = opened(&r, "second") {
+            break id;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(
+            l.duration() < Duration::from_secs(20),
+            "no second ChannelOpen"
+        );
+    };
+
+    l.channel(cid2)
+        .expect("second channel")
+        .write(false, b"hi")?;
+
+    loop {
+        let data = r.events.iter().any(|(_, e)| match e {
+            Event::ChannelData(d) => d.id == cid2_r,
+            _ => false,
+        });
+        if data {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(
+            l.duration() < Duration::from_secs(30),
+            "no data on second channel"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_open_with_loss() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("lossy".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    // SCTP retransmits the DCEP open and ack, the channel opens once on each side.
+    loop {
+        let open_l = l
+            .events
+            .iter()
+            .any(|(_, e)| e == &Event::ChannelOpen(cid, "lossy".into()));
+        let open_r = r
+            .events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::ChannelOpen(_, name) if name == "lossy"));
+        if open_l && open_r {
+            break;
+        }
+        progress_with_loss(&mut l, &mut r, 0.3)?;
+        assert!(l.duration() < Duration::from_secs(30), "channel not open");
+    }
+
+    for _ in 0..100 {
+        progress(&mut l, &mut r)?;
+    }
+
+    let opens = |t: &TestRtc| {
+        t.events
+            .iter()
+            .filter(|(_, e)| matches!(e, Event::ChannelOpen(_, _)))
+            .count()
+    };
+    assert_eq!(opens(&l), 1);
+    assert_eq!(opens(&r), 1);
+    assert!(!l
+        .events
+        .iter()
+        .any(|(_, e)| matches!(e, Event::ChannelClose(_))));
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_open_without_ack() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let first = change.add_channel("first".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.channel(first).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(l.duration() < Duration::from_secs(10), "channel not open");
+    }
+
+    let cid = l.direct_api().create_data_channel(ChannelConfig {
+        label: "unacked".into(),
+        ..Default::default()
+    });
+
+    // The remote goes silent, so the DCEP open is never acked.
+    let start = l.last;
+    loop {
+        l.rtc.handle_input(Input::Timeout(l.last))?;
+
+        match l.rtc.poll_output()? {
+            Output::Timeout(v) => {
+                let tick = l.last + Duration::from_millis(100);
+                l.last = if v <= l.last { tick } else { tick.min(v) };
+            }
+            Output::Transmit(_) => {}
+            Output::Event(Event::ChannelClose(id)) if id == cid => break,
+            Output::Event(e) => assert_ne!(e, Event::ChannelOpen(cid, "unacked".into())),
+        }
+
+        assert!(
+            l.last - start < Duration::from_secs(40),
+            "no ChannelClose for unacked channel"
+        );
+    }
+
+    // Not before the ack timeout.
+    assert!(l.last - start >= Duration::from_secs(30));
+
+    Ok(())
+}
+
+#[test]
+pub fn sctp_association_closed() -> Result<(), RtcError> {
+    init_log();
+
+    // Short retransmission timeouts to give up on the SCTP handshake quickly.
+    let config = || {
+        Rtc::builder().set_sctp_rto(SctpRto {
+            initial: Duration::from_millis(200),
+            min: Duration::from_millis(100),
+            max: Duration::from_millis(400),
+        })
+    };
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), config().build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), config().build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("Lost".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    let closed = |t: &TestRtc| {
+        t.events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::SctpAssociationClosed(_)))
+    };
+
+    // ICE and the DTLS handshake go through, but DTLS application data, which carries
+    // SCTP, is dropped. The SCTP handshake never completes.
+    loop {
+        if closed(&l) || closed(&r) {
+            break;
+        }
+
+        let (f, t) = if l.last < r.last {
+            (&mut l, &mut r)
+        } else {
+            (&mut r, &mut l)
+        };
+
+        f.rtc.handle_input(Input::Timeout(f.last))?;
+
+        match f.rtc.poll_output()? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+            }
+            Output::Transmit(v) if v.contents.first() == Some(&23) => {}
+            Output::Transmit(v) => {
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*v.contents).try_into()?,
+                    },
+                );
+                t.rtc.handle_input(input)?;
+            }
+            Output::Event(v) => f.events.push((f.last, v)),
+        }
+
+        assert!(
+            l.duration() < Duration::from_secs(60),
+            "association not closed"
+        );
+    }
+
+    assert!(l.events.iter().any(|(_, e)| matches!(e, Event::Connected)));
+    assert!(!l
+        .events
+        .iter()
+        .any(|(_, e)| e == &Event::ChannelOpen(cid, "Lost".into())));
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_buffered_amount_low() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("Bulk".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.channel(cid).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    {
+        let mut chan = l.channel(cid).unwrap();
+        assert_eq!(chan.buffered_amount_low_threshold(), 0);
+        chan.set_buffered_amount_low_threshold(1000);
+        assert_eq!(chan.buffered_amount_low_threshold(), 1000);
+
+        let data = vec![1_u8; 16_000];
+        for _ in 0..4 {
+            chan.write(true, &data)?;
+        }
+        assert!(chan.buffered_amount() > 1000);
+    }
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.events
+            .iter()
+            .any(|(_, e)| e == &Event::ChannelBufferedAmountLow(cid))
+        {
+            break;
+        }
+
+        assert!(
+            l.duration() < Duration::from_secs(10),
+            "no buffered amount low"
+        );
+    }
+
+    assert!(l.channel(cid).unwrap().buffered_amount() <= 1000);
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_priority() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let bulk = change.add_channel("Bulk".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.channel(bulk).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let control = l.direct_api().create_data_channel(ChannelConfig {
+        label: "Control".into(),
+        priority: ChannelPriority::High,
+        ..Default::default()
+    });
+
+    loop {
+        if l.channel(control).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(l.duration() < Duration::from_secs(10), "control not open");
+    }
+
+    const COUNT: usize = 200;
+    {
+        let mut chan = l.channel(bulk).unwrap();
+        for _ in 0..COUNT {
+            chan.write(true, &[1; 1000])?;
+        }
+        assert!(chan.buffered_amount() >= COUNT * 1000);
+    }
+    l.channel(control).unwrap().write(true, b"urgent")?;
+
+    let position = |r: &TestRtc, urgent: bool| {
+        r.events
+            .iter()
+            .filter_map(|(_, e)| match e {
+                Event::ChannelData(d) => Some(d),
+                _ => None,
+            })
+            .position(|d| (d.data == b"urgent") == urgent)
+    };
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        let bulk_received = r
+            .events
+            .iter()
+            .filter(|(_, e)| matches!(e, Event::ChannelData(d) if d.data.len() == 1000))
+            .count();
+
+        if bulk_received == COUNT {
+            break;
+        }
+        assert!(l.duration() < Duration::from_secs(30), "bulk not delivered");
+    }
+
+    // The urgent message overtakes most of the bulk transfer written before it.
+    let urgent = position(&r, true).expect("urgent message");
+    assert!(urgent < COUNT / 2, "urgent message at {}", urgent);
+    assert_eq!(position(&r, false), Some(0));
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_priority_no_starvation() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let low = change.add_channel("Low".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    let high = l.direct_api().create_data_channel(ChannelConfig {
+        label: "High".into(),
+        priority: ChannelPriority::High,
+        ..Default::default()
+    });
+
+    loop {
+        if l.channel(low).is_some() && l.channel(high).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(l.duration() < Duration::from_secs(10), "channels not open");
+    }
+
+    let start = l.duration();
+
+    // The high priority channel is kept busy the whole time.
+    let mut fill_high = |l: &mut TestRtc| -> Result<(), RtcError> {
+        let mut chan = l.channel(high).unwrap();
+        while chan.buffered_amount() < 128 * 1024 {
+            chan.write(true, &[1; 1000])?;
+        }
+        Ok(())
+    };
+
+    fill_high(&mut l)?;
+    l.channel(low).unwrap().write(true, b"low")?;
+
+    loop {
+        fill_high(&mut l)?;
+        progress(&mut l, &mut r)?;
+
+        let low_received = r
+            .events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::ChannelData(d) if d.data == b"low"));
+
+        if low_received {
+            break;
+        }
+        assert!(
+            l.duration() - start < Duration::from_secs(10),
+            "low priority channel starved"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_held_back_is_bounded() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let bulk = change.add_channel("Bulk".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    let control = l.direct_api().create_data_channel(ChannelConfig {
+        label: "Control".into(),
+        priority: ChannelPriority::High,
+        ..Default::default()
+    });
+
+    loop {
+        if l.channel(bulk).is_some() && l.channel(control).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(l.duration() < Duration::from_secs(10), "channels not open");
+    }
+
+    // While control has data buffered, bulk only gets its share of the send window
+    // and the rest is held back.
+    l.channel(control).unwrap().write(true, b"urgent")?;
+
+    let mut written = 0;
+    {
+        let mut chan = l.channel(bulk).unwrap();
+        let err = loop {
+            match chan.write(true, &[1; 1000]) {
+                Ok(_) => written += 1,
+                Err(e) => break e,
+            }
+            assert!(written < 10_000, "held back messages not bounded");
+        };
+        assert!(matches!(err, RtcError::Sctp(SctpError::WouldBlock)));
+    }
+    assert!(written > 0);
+
+    // What was accepted is still delivered.
+    loop {
+        progress(&mut l, &mut r)?;
+
+        let bulk_received = r
+            .events
+            .iter()
+            .filter(|(_, e)| matches!(e, Event::ChannelData(d) if d.data.len() == 1000))
+            .count();
+
+        if bulk_received == written {
+            break;
+        }
+        assert!(l.duration() < Duration::from_secs(60), "bulk not delivered");
+    }
+
+    assert!(!l
+        .events
+        .iter()
+        .any(|(_, e)| matches!(e, Event::ChannelWriteFailed(_, _))));
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_sctp_stats() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("Stats".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    assert!(l.rtc.sctp_stats().is_none());
+
+    loop {
+        if l.channel(cid).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    {
+        let mut chan = l.channel(cid).unwrap();
+        for _ in 0..50 {
+            chan.write(true, &[1; 1000])?;
+        }
+    }
+
+    let stats = l.rtc.sctp_stats().unwrap();
+    assert_eq!(stats.channels, vec![(cid, stats.buffered_amount)]);
+    assert!(stats.buffered_amount >= 50_000);
+
+    let start = l.duration();
+    while l.duration() - start < Duration::from_secs(5) {
+        progress_with_loss(&mut l, &mut r, 0.2)?;
+    }
+    while l.duration() - start < Duration::from_secs(30) {
+        progress(&mut l, &mut r)?;
+    }
+
+    let stats = l.rtc.sctp_stats().unwrap();
+    assert!(stats.packets_sent > 0);
+    assert!(stats.packets_received > 0);
+    assert!(stats.data_chunks_sent >= 50 + stats.data_chunks_retransmitted);
+    assert!(stats.data_chunks_retransmitted > 0);
+    assert_eq!(stats.buffered_amount, 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_only_mode() -> Result<(), RtcError> {
+    init_log();
+
+    let config = || Rtc::builder().set_data_channel_only(true);
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), config().build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), config().build());
+
+    // A browser style offer with audio, video and data gets the media rejected.
+    let mut b = TestRtc::new(info_span!("B"));
+    let mut change = b.sdp_api();
+    let audio = change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let video = change.add_media(MediaKind::Video, Direction::SendRecv, None, None);
+    change.add_channel("Signaling".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let mut s = TestRtc::new_with_rtc(info_span!("S"), config().build());
+    let answer = s.rtc.sdp_api().accept_offer(offer)?;
+    let sdp = answer.to_sdp_string();
+    assert!(sdp.contains("m=audio 0 "));
+    assert!(sdp.contains("m=video 0 "));
+    assert!(sdp.contains("m=application 9 "));
+    assert_eq!(s.media(audio).unwrap().direction(), Direction::Inactive);
+    assert_eq!(s.media(video).unwrap().direction(), Direction::Inactive);
+
+    b.rtc.sdp_api().accept_answer(pending, answer)?;
+    assert_eq!(b.media(audio).unwrap().direction(), Direction::Inactive);
+
+    // Declared media is inactive.
+    let media = l.direct_api().declare_media("a".into(), MediaKind::Audio);
+    assert_eq!(media.direction(), Direction::Inactive);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    // Added media is offered rejected.
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendRecv, None, None);
+    let cid = change.add_channel("Signaling".into());
+    let (offer, pending) = change.apply().unwrap();
+    assert!(offer.to_sdp_string().contains("m=video 0 "));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+    assert_eq!(l.media(mid).unwrap().direction(), Direction::Inactive);
+
+    loop {
+        if let Some(mut chan) = l.channel(cid) {
+            chan.write(false, "Hello".as_bytes())?;
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(l.duration() < Duration::from_secs(10), "channel not open");
+    }
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if r.events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::ChannelData(d) if d.data == b"Hello"))
+        {
+            break;
+        }
+        assert!(l.duration() < Duration::from_secs(10), "no data");
+    }
+
+    Ok(())
+}
+
+#[test]
+pub fn negotiated_sctp_params() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    change.add_channel("My little channel".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    assert_eq!(l.rtc.sctp_params(), None);
+
+    loop {
+        let open = r
+            .events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::ChannelOpen(..)));
+        if open {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(10) {
+            panic!("Channel did not open");
+        }
+    }
+
+    let expected = SctpParams {
+        local_port: 5000,
+        remote_port: 5000,
+        local_max_message_size: 262144,
+        remote_max_message_size: Some(262144),
+    };
+
+    assert_eq!(l.rtc.sctp_params(), Some(expected));
+    assert_eq!(r.rtc.sctp_params(), Some(expected));
+
+    Ok(())
+}
+
+#[test]
+pub fn data_channel_max_message_size() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let rtc = Rtc::builder().set_sctp_max_message_size(1000).build();
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("Small".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.rtc.channel(cid).is_some() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(10) {
+            panic!("Channel did not open");
+        }
+    }
+
+    let params = l.rtc.sctp_params().unwrap();
+    assert_eq!(params.local_max_message_size, 262144);
+    assert_eq!(params.remote_max_message_size, Some(1000));
+    assert_eq!(r.rtc.sctp_params().unwrap().local_max_message_size, 1000);
+
+    let mut chan = l.rtc.channel(cid).unwrap();
+    let err = chan.write(true, &[0; 1001]).unwrap_err();
+    assert!(matches!(
+        err,
+        RtcError::Sctp(SctpError::MessageTooLarge {
+            size: 1001,
+            max: 1000
+        })
+    ));
+    chan.write(true, &[1; 1000])
+        .expect("write at max-message-size");
+
+    loop {
+        let data = r.events.iter().find_map(|(_, e)| match e {
+            Event::ChannelData(d) => Some(d),
+            _ => None,
+        });
+        if let Some(d) = data {
+            assert_eq!(d.data, vec![1; 1000]);
+            break;
+        }
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(20) {
+            panic!("No data received");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,38 @@
+use std::sync::{Arc, Mutex};
+
+use str0m::change::DtlsKeyLog;
+use str0m::{Rtc, RtcError};
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log};
+
+#[test]
+pub fn dtls_keylog() -> Result<(), RtcError> {
+    init_log();
+
+    let lines = Arc::new(Mutex::new(Vec::<String>::new()));
+
+    let keylog = {
+        let lines = lines.clone();
+        DtlsKeyLog::new(move |line| lines.lock().unwrap().push(line.to_string()))
+    };
+
+    let rtc1 = Rtc::builder().set_dtls_keylog(Some(keylog)).build();
+    let rtc2 = Rtc::builder().build();
+
+    // L is the DTLS client, and has the keys before R completes the handshake.
+    let _ = connect_l_r_with_rtc(rtc1, rtc2);
+
+    // One line per DTLS 1.2 handshake, in the NSS key log format.
+    let lines = lines.lock().unwrap();
+    assert_eq!(lines.len(), 1, "{:?}", lines);
+
+    let parts: Vec<_> = lines[0].split(' ').collect();
+    assert_eq!(parts.len(), 3);
+    assert_eq!(parts[0], "CLIENT_RANDOM");
+    // 32 bytes client random, 48 bytes master secret.
+    assert_eq!(parts[1].len(), 64);
+    assert_eq!(parts[2].len(), 96);
+
+    Ok(())
+}
@@ -0,0 +1,62 @@
+use str0m::change::{DtlsRole, SdpOffer};
+use str0m::media::{Direction, MediaKind};
+use str0m::{Event, Output, Warning};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, TestRtc};
+
+#[test]
+pub fn dtls_role() {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    assert_eq!(l.dtls_role(), None);
+    assert_eq!(r.dtls_role(), None);
+
+    negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    });
+
+    // The offer is actpass, the answerer picks passive.
+    assert_eq!(l.dtls_role(), Some(DtlsRole::Client));
+    assert_eq!(r.dtls_role(), Some(DtlsRole::Server));
+
+    // A re-OFFER keeping the roles is fine.
+    negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendRecv, None, None);
+    });
+    assert!(warnings(&mut r).is_empty());
+
+    // A re-OFFER where L suddenly wants to be the server.
+    let mut change = l.sdp_api();
+    change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, _pending) = change.apply().unwrap();
+
+    let tampered = offer
+        .to_sdp_string()
+        .replace("a=setup:active", "a=setup:passive");
+    let offer = SdpOffer::from_sdp_string(&tampered).unwrap();
+    r.sdp_api().accept_offer(offer).unwrap();
+
+    // R keeps its role and tells about it.
+    assert_eq!(r.dtls_role(), Some(DtlsRole::Server));
+    let warnings = warnings(&mut r);
+    assert_eq!(warnings.len(), 1);
+    assert!(matches!(
+        warnings[0],
+        Warning::DtlsRoleChange(DtlsRole::Server)
+    ));
+}
+
+fn warnings(rtc: &mut TestRtc) -> Vec<Warning> {
+    let mut warnings = vec![];
+    while let Ok(Output::Event(e)) = rtc.poll_output() {
+        if let Event::Warning(w) = e {
+            warnings.push(w);
+        }
+    }
+    warnings
+}
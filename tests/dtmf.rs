@@ -0,0 +1,172 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::change::SdpOffer;
+use str0m::format::Codec;
+use str0m::media::{Direction, Dtmf, MediaKind, MediaTime};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn dtmf_tones_between_audio() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = Rtc::builder().enable_dtmf(true).build();
+    let rtc_r = Rtc::builder().enable_dtmf(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let sdp = offer.to_sdp_string();
+    assert!(sdp.contains("a=rtpmap:110 telephone-event/48000"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let dtmf_pt = l
+        .writer(mid)
+        .unwrap()
+        .payload_params()
+        .find(|p| p.spec().codec == Codec::TelephoneEvent)
+        .map(|p| p.pt())
+        .expect("telephone-event PT");
+
+    let tones = [
+        (10, Dtmf::new('1', Duration::from_millis(100)).unwrap()),
+        (60, Dtmf::new('#', Duration::from_millis(200)).unwrap()),
+    ];
+
+    let mut index = 0_u64;
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = MediaTime::from_hundredths(2 * index);
+
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![0xf8, 1, 2, 3])?;
+
+        for (at, dtmf) in &tones {
+            if *at == index {
+                l.writer(mid)
+                    .unwrap()
+                    .write_dtmf(dtmf_pt, wallclock, time, *dtmf)?;
+            }
+        }
+
+        index += 1;
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let received: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::Dtmf(v) => Some(v.clone()),
+            _ => None,
+        })
+        .collect();
+
+    // One event per tone, despite the updates and repeated ends.
+    assert_eq!(received.len(), 2);
+
+    for (e, (at, dtmf)) in received.iter().zip(tones.iter()) {
+        assert_eq!(e.mid, mid);
+        assert_eq!(e.dtmf, *dtmf);
+        assert_eq!(e.time.numer(), 960 * at);
+    }
+
+    let audio: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    // The telephone events are not audio, and their sequence numbers are not loss.
+    assert!(audio.len() > 100);
+    assert!(audio.iter().all(|d| d.params.spec().codec == Codec::Opus));
+    assert!(audio.iter().all(|d| d.contiguous));
+
+    Ok(())
+}
+
+/// An offer as sent by a SIP gateway bridging to WebRTC, G.711 only.
+const SIP_GATEWAY_OFFER: &str = "v=0\r\n\
+    o=- 1697051341 1697051342 IN IP4 203.0.113.10\r\n\
+    s=-\r\n\
+    t=0 0\r\n\
+    a=fingerprint:sha-256 0F:4D:21:8A:55:7C:9E:31:A2:6B:C0:DE:48:F2:19:7A:3C:E5:80:B4:61:2D:9F:07:CC:35:A8:1E:F6:42:B9:D3\r\n\
+    a=group:BUNDLE 0\r\n\
+    m=audio 20000 UDP/TLS/RTP/SAVPF 0 8 101\r\n\
+    c=IN IP4 203.0.113.10\r\n\
+    a=rtpmap:0 PCMU/8000\r\n\
+    a=rtpmap:8 PCMA/8000\r\n\
+    a=rtpmap:101 telephone-event/8000\r\n\
+    a=fmtp:101 0-16\r\n\
+    a=ptime:20\r\n\
+    a=mid:0\r\n\
+    a=sendrecv\r\n\
+    a=rtcp-mux\r\n\
+    a=setup:actpass\r\n\
+    a=ice-ufrag:Xk3f9a2B\r\n\
+    a=ice-pwd:a8f2c1e9d4b7a6c3e2f1d0b9a8c7e6f5\r\n\
+    a=candidate:1 1 udp 2130706431 203.0.113.10 20000 typ host\r\n\
+    a=ssrc:1834577410 cname:gw\r\n\
+    ";
+
+#[test]
+pub fn dtmf_negotiated_with_sip_gateway() {
+    init_log();
+
+    let mut rtc = Rtc::builder().enable_dtmf(true).build();
+
+    let offer = SdpOffer::from_sdp_string(SIP_GATEWAY_OFFER).unwrap();
+    let answer = rtc.sdp_api().accept_offer(offer).unwrap();
+
+    let sdp = answer.to_sdp_string();
+    assert!(sdp.contains("a=rtpmap:0 PCMU/8000"));
+    assert!(sdp.contains("a=rtpmap:101 telephone-event/8000"));
+    assert!(!sdp.contains("telephone-event/48000"));
+
+    // The 8kHz telephone-event is what the gateway's G.711 audio pairs with.
+    let mid = "0".into();
+    let dtmf_pt = rtc
+        .writer(mid)
+        .unwrap()
+        .payload_params()
+        .find(|p| p.spec().codec == Codec::TelephoneEvent)
+        .map(|p| (p.pt(), p.spec().clock_rate.get()));
+    assert_eq!(dtmf_pt, Some((101.into(), 8000)));
+}
@@ -0,0 +1,50 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::change::FingerprintHash;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn fingerprint_hash_sha384_sha512() -> Result<(), RtcError> {
+    init_log();
+
+    let l_rtc = Rtc::builder()
+        .set_fingerprint_hash(FingerprintHash::Sha384)
+        .build();
+    let r_rtc = Rtc::builder()
+        .set_fingerprint_hash(FingerprintHash::Sha512)
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    assert_eq!(l.direct_api().local_dtls_fingerprint().hash_func, "sha-384");
+    assert_eq!(r.direct_api().local_dtls_fingerprint().hash_func, "sha-512");
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            panic!("Failed to connect with sha-384/sha-512 fingerprints");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,286 @@
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::{Codec, FormatParams};
+use str0m::media::{Direction, Frequency, MediaKind, MediaTime, Pt};
+use str0m::net::Receive;
+use str0m::{Candidate, Event, Input, Output, Rtc, RtcConfig, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+fn config() -> RtcConfig {
+    let mut config = Rtc::builder().clear_codecs();
+
+    // VP8 without RTX, so lost packets can only come back with FlexFEC.
+    config.codec_config().add_config(
+        96.into(),
+        None,
+        Codec::Vp8,
+        Frequency::NINETY_KHZ,
+        None,
+        FormatParams::default(),
+    );
+
+    config.enable_flexfec(true)
+}
+
+#[test]
+pub fn flexfec_recovers_lost_packets() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = config().set_flexfec_window(3).build();
+    let rtc_r = config().set_rtp_mode(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let sdp = offer.to_sdp_string();
+    assert!(sdp.contains("a=rtpmap:118 flexfec-03/90000"));
+    assert!(sdp.contains("a=fmtp:118 repair-window=10000000"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    let mut sent = 0;
+    let mut dropped = 0;
+
+    for index in 0..200_u32 {
+        let wallclock = l.start + l.duration();
+        let time = MediaTime::from_90khz(3000 * index as u64);
+
+        // Small enough for one RTP packet per frame.
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1; 100])?;
+
+        // Drop every 4th media packet, which means at most one packet in
+        // each window of 3 is lost. Not too close to the end, to have the FEC
+        // packets for all losses.
+        let lossy = (10..190).contains(&index);
+        progress_drop_media(&mut l, &mut r, pt, &mut sent, &mut dropped, lossy, None)?;
+    }
+
+    let settle_time = l.duration() + Duration::from_secs(1);
+    while l.duration() < settle_time {
+        progress(&mut l, &mut r)?;
+    }
+
+    assert!(dropped > 40, "Dropped {dropped}");
+
+    let mut seen = HashSet::new();
+
+    for (_, e) in &r.events {
+        let Event::RtpPacket(p) = e else {
+            continue;
+        };
+
+        // The FEC packets themselves are not emitted.
+        assert_eq!(p.header.payload_type, pt);
+
+        assert!(
+            seen.insert(*p.seq_no),
+            "Packet {:?} emitted twice",
+            p.seq_no
+        );
+    }
+
+    // Every lost packet was recovered.
+    assert_eq!(seen.len(), sent);
+
+    let min = seen.iter().min().unwrap();
+    let max = seen.iter().max().unwrap();
+    assert_eq!(max - min + 1, sent as u64);
+
+    Ok(())
+}
+
+#[test]
+pub fn flexfec_sdp_round_trip() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = config().set_flexfec_window(3).build();
+    let rtc_r = config().set_rtp_mode(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    // Both sides announce their FlexFEC SSRC next to the media SSRC.
+    let (ssrc_l, ssrc_fec_l) = fec_fr_group(&offer.to_sdp_string()).expect("FEC-FR in offer");
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    let (ssrc_r, ssrc_fec_r) = fec_fr_group(&answer.to_sdp_string()).expect("FEC-FR in answer");
+    assert_ne!(ssrc_l, ssrc_fec_l);
+    assert_ne!(ssrc_r, ssrc_fec_r);
+
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    let mut sent = 0;
+    let mut dropped = 0;
+    let mut fec_ssrcs = HashSet::new();
+
+    for index in 0..100_u32 {
+        let wallclock = l.start + l.duration();
+        let time = MediaTime::from_90khz(3000 * index as u64);
+
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1; 100])?;
+
+        let lossy = (10..90).contains(&index);
+        progress_drop_media(
+            &mut l,
+            &mut r,
+            pt,
+            &mut sent,
+            &mut dropped,
+            lossy,
+            Some(&mut fec_ssrcs),
+        )?;
+    }
+
+    let settle_time = l.duration() + Duration::from_secs(1);
+    while l.duration() < settle_time {
+        progress(&mut l, &mut r)?;
+    }
+
+    // The FEC packets go out on the SSRC from the offer.
+    assert_eq!(fec_ssrcs, HashSet::from([ssrc_fec_l]));
+
+    let received: HashSet<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::RtpPacket(p) => Some(*p.seq_no),
+            _ => None,
+        })
+        .collect();
+
+    assert!(dropped > 15, "Dropped {dropped}");
+    assert_eq!(received.len(), sent);
+
+    Ok(())
+}
+
+/// The media and FEC SSRC of the `a=ssrc-group:FEC-FR` line.
+fn fec_fr_group(sdp: &str) -> Option<(u32, u32)> {
+    let line = sdp
+        .lines()
+        .find_map(|l| l.strip_prefix("a=ssrc-group:FEC-FR "))?;
+    let mut ssrcs = line.split(' ').map(|s| s.trim().parse().unwrap());
+    Some((ssrcs.next()?, ssrcs.next()?))
+}
+
+/// Like `progress`, but drops every 4th media packet from L to R when `lossy`.
+///
+/// The SSRCs of FlexFEC packets from L are added to `fec_ssrcs`, if given.
+fn progress_drop_media(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    pt: Pt,
+    sent: &mut usize,
+    dropped: &mut usize,
+    lossy: bool,
+    mut fec_ssrcs: Option<&mut HashSet<u32>>,
+) -> Result<(), RtcError> {
+    let from_l = l.last < r.last;
+    let (f, t) = if from_l { (l, r) } else { (r, l) };
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                let data = v.contents;
+
+                // The RTP header is not encrypted by SRTP.
+                let is_media =
+                    from_l && data.len() > 12 && data[0] >> 6 == 2 && data[1] & 0x7f == *pt;
+
+                // The FlexFEC PT from the default config.
+                let is_fec =
+                    from_l && data.len() > 12 && data[0] >> 6 == 2 && data[1] & 0x7f == 118;
+
+                if let Some(fec_ssrcs) = fec_ssrcs.as_mut().filter(|_| is_fec) {
+                    fec_ssrcs.insert(u32::from_be_bytes(data[8..12].try_into().unwrap()));
+                }
+
+                if is_media {
+                    *sent += 1;
+
+                    if lossy && *sent % 4 == 0 {
+                        *dropped += 1;
+                        continue;
+                    }
+                }
+
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*data).try_into()?,
+                    },
+                );
+                t.span.in_scope(|| t.rtc.handle_input(input))?;
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+        }
+    }
+
+    Ok(())
+}
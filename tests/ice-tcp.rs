@@ -0,0 +1,97 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::net::{rfc4571_frame, Protocol, Receive, Rfc4571Decoder};
+use str0m::{Candidate, Event, Input, Output, RtcError, TcpType};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, TestRtc};
+
+#[test]
+pub fn ice_tcp() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let mut active = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 9).into(), "tcp")?;
+    active.set_tcp_type(TcpType::Active);
+    let passive = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 443).into(), "tcp")?;
+    l.add_local_candidate(active);
+    r.add_local_candidate(passive);
+
+    negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    });
+
+    // One decoder per connection direction, as if reading from a TCP stream.
+    let mut l_to_r = Rfc4571Decoder::new();
+    let mut r_to_l = Rfc4571Decoder::new();
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        if l.duration() > Duration::from_secs(10) {
+            panic!("Failed to connect over TCP in 10 seconds");
+        }
+        progress_tcp(&mut l, &mut r, &mut l_to_r, &mut r_to_l)?;
+    }
+
+    let connected = l.events.iter().any(|(_, e)| matches!(e, Event::Connected));
+    assert!(connected);
+
+    Ok(())
+}
+
+fn progress_tcp(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    l_to_r: &mut Rfc4571Decoder,
+    r_to_l: &mut Rfc4571Decoder,
+) -> Result<(), RtcError> {
+    let (f, t, decoder) = if l.last < r.last {
+        (l, r, l_to_r)
+    } else {
+        (r, l, r_to_l)
+    };
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                assert_eq!(v.proto, Protocol::Tcp);
+
+                let mut stream = vec![];
+                rfc4571_frame(&v.contents, &mut stream).unwrap();
+
+                // Deliver the stream in two reads to exercise the decoder.
+                let (a, b) = stream.split_at(stream.len() / 2);
+                for part in [a, b] {
+                    decoder.push(part);
+                    while let Some(packet) = decoder.pop() {
+                        let input = Input::Receive(
+                            f.last,
+                            Receive::new(v.proto, v.source, v.destination, &packet)?,
+                        );
+                        t.span.in_scope(|| t.rtc.handle_input(input))?;
+                    }
+                }
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,192 @@
+//! Interop fixtures: offers in the shape the major browsers produce.
+//!
+//! The SDPs under `tests/data/interop` are written after offers from Chrome, Firefox
+//! and Safari, with their PT ranges, extension ids, simulcast, RED, ULPFEC/FlexFEC and
+//! the dependency descriptor extension. They are accepted as is, to catch regressions
+//! in the parser and the negotiation against what browsers send.
+//!
+//! Only offers are covered. There are no recorded browser answers, and no RTP traces
+//! with RED, FEC or the dependency descriptor.
+
+use str0m::change::{SdpAnswer, SdpOffer};
+use str0m::format::Codec;
+use str0m::media::{Direction, MediaKind, Mid, Rid, Rids};
+use str0m::rtp::Extension;
+use str0m::Rtc;
+
+mod common;
+use common::init_log;
+
+const CHROME_OFFER: &str = include_str!("data/interop/chrome-offer.sdp");
+const FIREFOX_OFFER: &str = include_str!("data/interop/firefox-offer.sdp");
+const SAFARI_OFFER: &str = include_str!("data/interop/safari-offer.sdp");
+
+fn accept(offer: &str) -> (Rtc, SdpAnswer) {
+    let mut rtc = Rtc::new();
+
+    let offer = SdpOffer::from_sdp_string(offer).expect("fixture to parse");
+    let answer = rtc
+        .sdp_api()
+        .accept_offer(offer)
+        .expect("fixture to be accepted");
+
+    // Whatever we answer must be parseable by ourselves.
+    SdpAnswer::from_sdp_string(&answer.to_sdp_string()).expect("answer to parse");
+
+    (rtc, answer)
+}
+
+/// Codecs negotiated for the mid, looked up via the PTs the remote sent.
+fn remote_codecs(rtc: &Rtc, mid: Mid) -> Vec<Codec> {
+    let media = rtc.media(mid).expect("media for mid");
+
+    media
+        .remote_pts()
+        .iter()
+        .map(|pt| {
+            rtc.codec_config()
+                .params()
+                .iter()
+                .find(|p| p.pt() == *pt)
+                .expect("remote pt to be in codec config")
+                .spec()
+                .codec
+        })
+        .collect()
+}
+
+fn assert_only_media_codecs(codecs: &[Codec]) {
+    // RED, ULPFEC and FlexFEC are offered, but are not media codecs. They must not
+    // end up as PTs we would try to depacketize.
+    assert!(!codecs.is_empty());
+    for c in codecs {
+        assert!(
+            matches!(
+                c,
+                Codec::Opus
+                    | Codec::Pcmu
+                    | Codec::Pcma
+                    | Codec::G722
+                    | Codec::Vp8
+                    | Codec::Vp9
+                    | Codec::H264
+            ),
+            "Unexpected negotiated codec: {:?}",
+            c
+        );
+    }
+}
+
+fn assert_rids(rids: &Rids, expected: &[&str]) {
+    let expected: Vec<Rid> = expected.iter().map(|r| Rid::from(*r)).collect();
+    match rids {
+        Rids::Specific(v) => assert_eq!(v, &expected),
+        Rids::Any => panic!("Expected specific rids: {:?}", expected),
+    }
+}
+
+#[test]
+pub fn interop_chrome_offer() {
+    init_log();
+
+    let (rtc, answer) = accept(CHROME_OFFER);
+    let answer = answer.to_sdp_string();
+
+    assert!(answer.contains("a=group:BUNDLE 0 1 2"));
+    assert!(answer.contains("a=extmap-allow-mixed"));
+
+    let audio = rtc.media("0".into()).unwrap();
+    assert_eq!(audio.kind(), MediaKind::Audio);
+    assert_eq!(audio.direction(), Direction::SendRecv);
+    let codecs = remote_codecs(&rtc, "0".into());
+    assert_eq!(codecs[0], Codec::Opus);
+    assert_only_media_codecs(&codecs);
+
+    let video = rtc.media("1".into()).unwrap();
+    assert_eq!(video.kind(), MediaKind::Video);
+    assert_eq!(video.direction(), Direction::RecvOnly);
+    assert_eq!(video.remote_pts()[0], 96.into());
+    assert_only_media_codecs(&remote_codecs(&rtc, "1".into()));
+
+    // Simulcast send from Chrome is received by us.
+    assert_rids(video.rids_rx(), &["q", "h", "f"]);
+    assert!(answer.contains("a=simulcast:recv q;h;f"));
+
+    // Extensions are remapped to the ids Chrome uses.
+    let exts = video.remote_extmap();
+    assert_eq!(exts.id_of(Extension::TransportSequenceNumber), Some(3));
+    assert_eq!(exts.id_of(Extension::RtpStreamId), Some(10));
+    assert_eq!(exts.id_of(Extension::RepairedRtpStreamId), Some(11));
+    assert_eq!(exts.id_of(Extension::VideoOrientation), Some(13));
+
+    // The dependency descriptor is not something we enable by default.
+    assert!(!answer.contains("dependency-descriptor"));
+
+    assert!(answer.contains("m=application 9 UDP/DTLS/SCTP webrtc-datachannel"));
+}
+
+#[test]
+pub fn interop_firefox_offer() {
+    init_log();
+
+    let (rtc, answer) = accept(FIREFOX_OFFER);
+    let answer = answer.to_sdp_string();
+
+    assert!(answer.contains("a=group:BUNDLE 0 1 2"));
+
+    // Firefox doesn't offer allow-mixed, so it's not in the answer either.
+    assert!(!answer.contains("a=extmap-allow-mixed"));
+
+    let audio = rtc.media("0".into()).unwrap();
+    assert_eq!(audio.direction(), Direction::SendRecv);
+    let codecs = remote_codecs(&rtc, "0".into());
+    assert_eq!(codecs[0], Codec::Opus);
+    assert_only_media_codecs(&codecs);
+
+    // G722/8000/1 matches our G722/8000 without channels.
+    assert_eq!(&codecs[1..], &[Codec::G722, Codec::Pcmu, Codec::Pcma]);
+    assert!(answer.contains("a=rtpmap:9 G722/8000"));
+
+    let video = rtc.media("1".into()).unwrap();
+    assert_eq!(video.direction(), Direction::RecvOnly);
+    // Firefox uses the 120-range for its video PTs.
+    assert_eq!(video.remote_pts()[0], 120.into());
+    assert_only_media_codecs(&remote_codecs(&rtc, "1".into()));
+
+    assert_rids(video.rids_rx(), &["h", "m", "l"]);
+    assert!(answer.contains("a=simulcast:recv h;m;l"));
+
+    let exts = video.remote_extmap();
+    assert_eq!(exts.id_of(Extension::RtpMid), Some(3));
+    assert_eq!(exts.id_of(Extension::AbsoluteSendTime), Some(4));
+    assert_eq!(exts.id_of(Extension::TransportSequenceNumber), Some(7));
+    assert_eq!(exts.id_of(Extension::RtpStreamId), Some(8));
+
+    assert!(answer.contains("m=application 9 UDP/DTLS/SCTP webrtc-datachannel"));
+}
+
+#[test]
+pub fn interop_safari_offer() {
+    init_log();
+
+    let (rtc, answer) = accept(SAFARI_OFFER);
+    let answer = answer.to_sdp_string();
+
+    assert!(answer.contains("a=group:BUNDLE 0 1"));
+
+    let audio = rtc.media("0".into()).unwrap();
+    assert_eq!(audio.direction(), Direction::SendRecv);
+    assert_only_media_codecs(&remote_codecs(&rtc, "0".into()));
+
+    let video = rtc.media("1".into()).unwrap();
+    assert_eq!(video.direction(), Direction::SendRecv);
+    // Safari prefers H264, and that order must be kept.
+    let codecs = remote_codecs(&rtc, "1".into());
+    assert_eq!(codecs[0], Codec::H264);
+    assert!(codecs.contains(&Codec::Vp8));
+    assert_only_media_codecs(&codecs);
+
+    // No simulcast from Safari.
+    assert!(matches!(video.rids_rx(), Rids::Any));
+    assert!(!answer.contains("a=simulcast"));
+}
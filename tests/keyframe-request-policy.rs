@@ -0,0 +1,308 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, KeyframeRequestKind, KeyframeRequestPolicy, MediaKind, MediaTime};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn keyframe_request_policy() -> Result<(), RtcError> {
+    init_log();
+    let l_rtc = Rtc::builder().build();
+    let r_rtc = Rtc::builder().build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    // wait for srtp success
+    let settle_time = l.duration() + Duration::from_millis(20);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    r.writer(mid)
+        .unwrap()
+        .set_keyframe_request_policy(KeyframeRequestPolicy {
+            force_kind: None,
+            min_interval: Duration::from_millis(500),
+            on_stream_start: None,
+        });
+
+    let count_requests = |l: &TestRtc| -> Vec<KeyframeRequestKind> {
+        l.events
+            .iter()
+            .filter_map(|(_, e)| {
+                if let Event::KeyframeRequest(r) = e {
+                    Some(r.kind)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+
+    let request = |r: &mut TestRtc, kind: KeyframeRequestKind| {
+        r.direct_api()
+            .stream_rx_by_mid(mid, None)
+            .expect("Should has rx")
+            .request_keyframe(kind);
+    };
+
+    // First request goes out straight away.
+    request(&mut r, KeyframeRequestKind::Pli);
+
+    let settle_time = l.duration() + Duration::from_millis(100);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    assert_eq!(count_requests(&l), vec![KeyframeRequestKind::Pli]);
+
+    // These are held back by the min_interval and coalesced into one FIR.
+    request(&mut r, KeyframeRequestKind::Pli);
+    request(&mut r, KeyframeRequestKind::Fir);
+    request(&mut r, KeyframeRequestKind::Pli);
+
+    let settle_time = l.duration() + Duration::from_millis(100);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    assert_eq!(count_requests(&l), vec![KeyframeRequestKind::Pli]);
+
+    let settle_time = l.duration() + Duration::from_millis(500);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    assert_eq!(
+        count_requests(&l),
+        vec![KeyframeRequestKind::Pli, KeyframeRequestKind::Fir]
+    );
+
+    Ok(())
+}
+
+#[test]
+pub fn keyframe_request_initial_rtt() -> Result<(), RtcError> {
+    init_log();
+    let l_rtc = Rtc::builder().build();
+    let r_rtc = Rtc::builder()
+        .set_initial_rtt(Some(Duration::from_millis(400)))
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    // wait for srtp success
+    let settle_time = l.duration() + Duration::from_millis(20);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    let count_requests = |l: &TestRtc| {
+        l.events
+            .iter()
+            .filter(|(_, e)| matches!(e, Event::KeyframeRequest(_)))
+            .count()
+    };
+
+    let request = |r: &mut TestRtc| {
+        r.direct_api()
+            .stream_rx_by_mid(mid, None)
+            .expect("Should has rx")
+            .request_keyframe(KeyframeRequestKind::Pli);
+    };
+
+    // The default policy has no min_interval, but the initial RTT holds back
+    // the second request.
+    request(&mut r);
+
+    let settle_time = l.duration() + Duration::from_millis(100);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    assert_eq!(count_requests(&l), 1);
+
+    request(&mut r);
+
+    let settle_time = l.duration() + Duration::from_millis(100);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    assert_eq!(count_requests(&l), 1);
+
+    let settle_time = l.duration() + Duration::from_millis(400);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    assert_eq!(count_requests(&l), 2);
+
+    Ok(())
+}
+
+#[test]
+pub fn keyframe_request_on_stream_start() -> Result<(), RtcError> {
+    init_log();
+    let l_rtc = Rtc::builder().build();
+    let r_rtc = Rtc::builder().build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    r.writer(mid)
+        .unwrap()
+        .set_keyframe_request_policy(KeyframeRequestPolicy {
+            force_kind: None,
+            min_interval: Duration::ZERO,
+            on_stream_start: Some(KeyframeRequestKind::Fir),
+        });
+
+    let count_requests = |l: &TestRtc| -> Vec<KeyframeRequestKind> {
+        l.events
+            .iter()
+            .filter_map(|(_, e)| {
+                if let Event::KeyframeRequest(r) = e {
+                    Some(r.kind)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    };
+
+    let pt = l.params_vp8().pt();
+    let mut index = 0_u64;
+
+    let mut send_for = |l: &mut TestRtc, r: &mut TestRtc, d: Duration| -> Result<(), RtcError> {
+        let until = l.duration() + d;
+        let mut write_at = l.last;
+        while l.duration() < until {
+            if l.last >= write_at {
+                write_at = l.last + Duration::from_millis(33);
+                let wallclock = l.start + l.duration();
+                let time = MediaTime::from_90khz(index * 3000);
+                index += 1;
+                l.writer(mid)
+                    .unwrap()
+                    .write(pt, wallclock, time, vec![1_u8; 80])?;
+            }
+            progress(l, r)?;
+        }
+        Ok(())
+    };
+
+    // No stream, no request.
+    assert!(count_requests(&l).is_empty());
+
+    // The first packets start the stream, which makes one request.
+    send_for(&mut l, &mut r, Duration::from_secs(1))?;
+    assert_eq!(count_requests(&l), vec![KeyframeRequestKind::Fir]);
+
+    // The stream pauses while nothing is sent.
+    let settle_time = l.duration() + Duration::from_secs(3);
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > settle_time {
+            break;
+        }
+    }
+
+    assert_eq!(count_requests(&l).len(), 1);
+
+    // Resuming starts the stream again.
+    send_for(&mut l, &mut r, Duration::from_secs(1))?;
+    assert_eq!(
+        count_requests(&l),
+        vec![KeyframeRequestKind::Fir, KeyframeRequestKind::Fir]
+    );
+
+    Ok(())
+}
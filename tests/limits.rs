@@ -0,0 +1,134 @@
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, Input, Limit, Limits, Output, Rtc, RtcError, Warning};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn remote_offer_above_mid_limit() {
+    init_log();
+
+    let limits = Limits {
+        max_mids: Some(2),
+        ..Default::default()
+    };
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), Rtc::builder().set_limits(limits).build());
+
+    let mut change = l.sdp_api();
+    for _ in 0..3 {
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    }
+    let (offer, _pending) = change.apply().unwrap();
+
+    let result = r.rtc.sdp_api().accept_offer(offer);
+
+    assert!(matches!(result, Err(RtcError::LimitReached(Limit::Mids))));
+}
+
+#[test]
+pub fn remote_channels_above_limit() -> Result<(), RtcError> {
+    init_log();
+
+    let limits = Limits {
+        max_channels: Some(2),
+        ..Default::default()
+    };
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), Rtc::builder().set_limits(limits).build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    for i in 0..3 {
+        change.add_channel(format!("chan {i}"));
+    }
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let opened = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::ChannelOpen(..)))
+        .count();
+    assert_eq!(opened, 2);
+
+    let warnings: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::Warning(w) => Some(w),
+            _ => None,
+        })
+        .collect();
+
+    assert!(warnings.iter().any(|w| matches!(
+        w,
+        Warning::LimitApproached {
+            limit: Limit::Channels,
+            max: 2,
+            ..
+        }
+    )));
+    assert!(warnings
+        .iter()
+        .any(|w| matches!(w, Warning::LimitReached(Limit::Channels))));
+
+    Ok(())
+}
+
+#[test]
+pub fn local_candidates_above_limit() -> Result<(), RtcError> {
+    init_log();
+
+    let limits = Limits {
+        max_local_candidates: Some(1),
+        ..Default::default()
+    };
+
+    let server = (Ipv4Addr::new(9, 9, 9, 9), 3478).into();
+    let mut rtc = Rtc::builder()
+        .set_limits(limits)
+        .set_stun_servers(vec![server])
+        .build();
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    rtc.add_local_candidate(host1.clone());
+    rtc.add_local_candidate(host2);
+
+    rtc.handle_input(Input::Timeout(Instant::now()))?;
+
+    let mut sources = vec![];
+    loop {
+        match rtc.poll_output()? {
+            Output::Transmit(t) => sources.push(t.source),
+            Output::Timeout(_) => break,
+            Output::Event(_) => {}
+        }
+    }
+
+    // Only the accepted candidate is used to gather server reflexive candidates.
+    assert_eq!(sources, vec![host1.addr()]);
+
+    Ok(())
+}
@@ -0,0 +1,135 @@
+use std::net::Ipv4Addr;
+use std::time::{Duration, Instant};
+
+use str0m::net::Receive;
+use str0m::{Candidate, Event, Input, Output, PollBudget, Reason, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, TestRtc};
+
+#[test]
+pub fn poll_budget_zero_is_unlimited() -> Result<(), RtcError> {
+    init_log();
+
+    let budget = PollBudget {
+        max_outputs: Some(0),
+    };
+
+    let mut rtc = Rtc::builder().set_poll_budget(budget).build();
+    rtc.handle_input(Input::Timeout(Instant::now()))?;
+
+    loop {
+        if let Output::Timeout(_) = rtc.poll_output()? {
+            break;
+        }
+    }
+
+    assert_ne!(rtc.last_timeout_reason(), Reason::PollAgain);
+
+    Ok(())
+}
+
+#[test]
+pub fn poll_budget_max_outputs() -> Result<(), RtcError> {
+    init_log();
+
+    let budget = PollBudget {
+        max_outputs: Some(3),
+        ..Default::default()
+    };
+
+    let rtc = Rtc::builder().set_poll_budget(budget).build();
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("Backlog".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.events
+            .iter()
+            .any(|(_, e)| matches!(e, Event::ChannelOpen(id, _) if *id == cid))
+        {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        if l.duration() > Duration::from_secs(10) {
+            panic!("channel did not open");
+        }
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    const COUNT: usize = 20;
+    let mut chan = l.channel(cid).unwrap();
+    for _ in 0..COUNT {
+        chan.write(true, &[42; 1000]).expect("to write");
+    }
+
+    // Drain the backlog by hand, checking no round exceeds the budget.
+    l.rtc.handle_input(Input::Timeout(l.last))?;
+
+    let mut poll_again = 0;
+    let mut outputs = 0;
+    loop {
+        match l.rtc.poll_output()? {
+            Output::Timeout(t) => {
+                assert!(outputs <= 3, "round produced {} outputs", outputs);
+                outputs = 0;
+
+                if l.rtc.last_timeout_reason() != Reason::PollAgain {
+                    break;
+                }
+                assert_eq!(t, l.last);
+                poll_again += 1;
+            }
+            Output::Transmit(v) => {
+                outputs += 1;
+                let input = Input::Receive(
+                    l.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*v.contents).try_into()?,
+                    },
+                );
+                r.rtc.handle_input(input)?;
+            }
+            Output::Event(e) => {
+                outputs += 1;
+                l.events.push((l.last, e));
+            }
+        }
+    }
+
+    assert!(poll_again > 0, "expected the budget to be used up");
+
+    let received = |r: &TestRtc| {
+        r.events
+            .iter()
+            .filter(|(_, e)| matches!(e, Event::ChannelData(_)))
+            .count()
+    };
+
+    while received(&r) < COUNT {
+        progress(&mut l, &mut r)?;
+        if l.duration() > Duration::from_secs(20) {
+            panic!("backlog not delivered");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,112 @@
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::Codec;
+use str0m::media::{Direction, MediaKind, MediaTime};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, progress, progress_with_loss, TestRtc};
+
+#[test]
+pub fn audio_red_recovers_lost_frames() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = Rtc::builder()
+        .enable_opus_red(true)
+        .set_red_distance(2)
+        .build();
+    let rtc_r = Rtc::builder().enable_opus_red(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let sdp = offer.to_sdp_string();
+    assert!(sdp.contains("a=rtpmap:63 red/48000/2"));
+    assert!(sdp.contains("a=fmtp:63 111/111"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+
+    // RED is its own payload type, naming the opus PT of its blocks.
+    let sdp = answer.to_sdp_string();
+    assert!(sdp.contains("a=rtpmap:63 red/48000/2"));
+    assert!(sdp.contains("a=fmtp:63 111/111"));
+
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = l.params_opus();
+    let pt = params.pt();
+
+    fastrand::seed(42);
+
+    let mut index = 0_u16;
+
+    loop {
+        let wallclock = l.start + l.duration();
+        let time = MediaTime::from_hundredths(2 * index as u64);
+
+        let mut data = index.to_be_bytes().to_vec();
+        data.resize(80, index as u8);
+
+        l.writer(mid).unwrap().write(pt, wallclock, time, data)?;
+        index += 1;
+
+        progress_with_loss(&mut l, &mut r, 0.1)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let mut seen = HashSet::new();
+
+    for (_, e) in &r.events {
+        let Event::MediaData(d) = e else {
+            continue;
+        };
+
+        // Recovered frames come out on the opus PT.
+        assert_eq!(d.params.spec().codec, Codec::Opus);
+        assert_eq!(d.pt, pt);
+
+        let n = u16::from_be_bytes([d.data[0], d.data[1]]);
+        assert_eq!(d.data.len(), 80);
+        assert!(d.data[2..].iter().all(|b| *b == n as u8));
+        assert_eq!(d.time.numer(), 960 * n as u64);
+
+        assert!(seen.insert(n), "Frame {n} emitted twice");
+    }
+
+    // With 10% loss and two redundant frames per packet, nearly all frames arrive.
+    let expected = index as usize * 98 / 100;
+    assert!(
+        seen.len() >= expected,
+        "Received {} of {} frames",
+        seen.len(),
+        index
+    );
+
+    Ok(())
+}
@@ -0,0 +1,100 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::change::DtlsRole;
+use str0m::media::{Direction, MediaKind, Mid};
+use str0m::{Candidate, Event, IceConnectionState, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn renegotiate_keeps_transport() -> Result<(), RtcError> {
+    init_log();
+
+    let mut l = TestRtc::new(info_span!("L"));
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let audio = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Audio, Direction::SendRecv, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let l_creds = l._local_ice_creds();
+    let r_creds = r._local_ice_creds();
+
+    // Add and remove mids, alternating the offering side.
+    let mut mids: Vec<Mid> = vec![audio];
+    for i in 0..6 {
+        let (offerer, answerer) = if i % 2 == 0 {
+            (&mut l, &mut r)
+        } else {
+            (&mut r, &mut l)
+        };
+
+        let remove = mids.get(1).copied().filter(|_| i % 3 == 2);
+
+        let added = negotiate(offerer, answerer, |change| {
+            if let Some(mid) = remove {
+                change.set_direction(mid, Direction::Inactive);
+            }
+            let kind = if i % 2 == 0 {
+                MediaKind::Video
+            } else {
+                MediaKind::Audio
+            };
+            change.add_media(kind, Direction::SendRecv, None, None)
+        });
+
+        if let Some(mid) = remove {
+            mids.retain(|m| *m != mid);
+        }
+        mids.push(added);
+
+        let until = l.duration() + Duration::from_secs(2);
+        while l.duration() < until {
+            progress(&mut l, &mut r)?;
+        }
+
+        assert!(l.is_connected());
+        assert!(r.is_connected());
+    }
+
+    // Same ICE credentials and DTLS roles as before the renegotiations.
+    assert_eq!(l_creds, l._local_ice_creds());
+    assert_eq!(r_creds, r._local_ice_creds());
+    assert_eq!(l.dtls_role(), Some(DtlsRole::Client));
+    assert_eq!(r.dtls_role(), Some(DtlsRole::Server));
+
+    for t in [&l, &r] {
+        // A single DTLS connection, and ICE never went away.
+        let connected = t
+            .events
+            .iter()
+            .filter(|(_, e)| matches!(e, Event::Connected))
+            .count();
+        assert_eq!(connected, 1);
+
+        let disconnected = t.events.iter().any(|(_, e)| {
+            matches!(
+                e,
+                Event::IceConnectionStateChange(IceConnectionState::Disconnected)
+            )
+        });
+        assert!(!disconnected);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use str0m::bwe::Bitrate;
+use str0m::format::Codec;
+use str0m::media::MediaKind;
+use str0m::rtp::rtcp::Rtcp;
+use str0m::rtp::{ExtensionValues, RawPacket, RtcpPacing, Ssrc};
+use str0m::{Rtc, RtcError};
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log, progress, progress_with_loss};
+
+#[test]
+pub fn rtcp_pacing() -> Result<(), RtcError> {
+    init_log();
+
+    // 2000 bytes/s after the initial burst.
+    let pacing = RtcpPacing {
+        burst: 1200,
+        rate: Bitrate::kbps(16),
+    };
+
+    let rtc1 = Rtc::builder()
+        .set_rtp_mode(true)
+        .enable_raw_packets(true)
+        .build();
+    let rtc2 = Rtc::builder()
+        .set_rtp_mode(true)
+        .enable_raw_packets(true)
+        .set_rtcp_pacing(Some(pacing))
+        .build();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(rtc1, rtc2);
+
+    let mid = "vid".into();
+    let ssrc: Ssrc = 42.into();
+    let ssrc_rtx: Ssrc = 44.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Video);
+    l.direct_api()
+        .declare_stream_tx(ssrc, Some(ssrc_rtx), mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Video);
+    r.direct_api()
+        .expect_stream_rx(ssrc, Some(ssrc_rtx), mid, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = l.params_vp8();
+    assert_eq!(params.spec().codec, Codec::Vp8);
+    let pt = params.pt();
+
+    let start = r.last;
+    let mut index: u64 = 0;
+    let mut write_at = l.last;
+
+    while l.duration() < Duration::from_secs(5) {
+        if l.last >= write_at {
+            write_at = l.last + Duration::from_millis(10);
+            let wallclock = l.start + l.duration();
+
+            let mut direct = l.direct_api();
+            let stream = direct.stream_tx(&ssrc).unwrap();
+
+            let time = (index * 1000 + 47_000_000) as u32;
+            let seq_no = (47_000 + index).into();
+            index += 1;
+
+            stream
+                .write_rtp(
+                    pt,
+                    seq_no,
+                    time,
+                    wallclock,
+                    false,
+                    ExtensionValues::default(),
+                    true,
+                    vec![0x1, 0x2, 0x3, 0x4],
+                )
+                .expect("clean write");
+        }
+
+        progress_with_loss(&mut l, &mut r, 0.1)?;
+    }
+
+    // Let the held back feedback drain.
+    let settle_time = l.duration() + Duration::from_secs(2);
+    while l.duration() < settle_time {
+        progress(&mut l, &mut r)?;
+    }
+
+    let elapsed = r.last - start;
+
+    let sent: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(t, e)| match e.as_raw_packet() {
+            Some(RawPacket::RtcpCompoundTx(c)) => Some((*t, c.data.len())),
+            _ => None,
+        })
+        .collect();
+
+    // The feedback never exceeds the burst plus what the rate allows, with some
+    // margin for the last datagram and the SRTCP overhead not counted here.
+    let bytes: usize = sent.iter().map(|(_, len)| len).sum();
+    let allowed = 1200.0 + 2000.0 * elapsed.as_secs_f64() + 1200.0;
+    assert!(bytes as f64 <= allowed, "{} > {}", bytes, allowed);
+
+    // The feedback doesn't stall, it keeps flowing up to the end.
+    let last = sent.last().map(|(t, _)| *t).unwrap();
+    assert!(r.last - last < Duration::from_secs(2));
+
+    // The losses are NACKed, and the NACKs reach the sender.
+    let nacks_rx = l
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e.as_raw_packet(), Some(RawPacket::RtcpRx(Rtcp::Nack(_)))))
+        .count();
+    assert!(nacks_rx > 0);
+
+    Ok(())
+}
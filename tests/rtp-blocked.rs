@@ -0,0 +1,156 @@
+use std::time::{Duration, Instant};
+
+use str0m::format::Codec;
+use str0m::media::{MediaKind, Mid};
+use str0m::rtp::{ExtensionValues, RawPacket, Ssrc};
+use str0m::{Event, RtcError};
+
+mod common;
+use common::{connect_l_r, init_log, progress, TestRtc};
+
+#[test]
+pub fn rtp_blocked() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid = "aud".into();
+    let ssrc: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+    r.direct_api()
+        .expect_stream_rx(ssrc, None, mid, None)
+        .set_blocked(true);
+
+    let mut writer = Writer::new(&mut l, &mut r, ssrc);
+
+    writer.write(&mut l, &mut r, Duration::from_secs(2))?;
+
+    // Nothing of the blocked stream reaches the app, not even as raw packets.
+    assert_eq!(received(&r), (0, 0));
+
+    r.direct_api().stream_rx(&ssrc).unwrap().set_blocked(false);
+
+    writer.write(&mut l, &mut r, Duration::from_secs(4))?;
+
+    let (rtp, raw) = received(&r);
+    assert!(rtp > 10);
+    assert!(raw > 10);
+
+    Ok(())
+}
+
+#[test]
+pub fn rtp_blocked_mid() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid: Mid = "aud".into();
+    let ssrc: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+
+    // Blocked before any stream exists for the mid.
+    r.direct_api().set_blocked_rx(mid, None, true);
+    assert!(r.direct_api().is_blocked_rx(mid, None));
+
+    let stream = r.direct_api().expect_stream_rx(ssrc, None, mid, None);
+    assert!(stream.is_blocked());
+
+    let mut writer = Writer::new(&mut l, &mut r, ssrc);
+
+    writer.write(&mut l, &mut r, Duration::from_secs(2))?;
+
+    assert_eq!(received(&r), (0, 0));
+
+    r.direct_api().set_blocked_rx(mid, None, false);
+    assert!(!r.direct_api().stream_rx(&ssrc).unwrap().is_blocked());
+
+    writer.write(&mut l, &mut r, Duration::from_secs(4))?;
+
+    let (rtp, raw) = received(&r);
+    assert!(rtp > 10);
+    assert!(raw > 10);
+
+    Ok(())
+}
+
+struct Writer {
+    ssrc: Ssrc,
+    count: u64,
+    write_at: Instant,
+}
+
+impl Writer {
+    fn new(l: &mut TestRtc, r: &mut TestRtc, ssrc: Ssrc) -> Self {
+        let max = l.last.max(r.last);
+        l.last = max;
+        r.last = max;
+
+        Writer {
+            ssrc,
+            count: 0,
+            write_at: l.last + Duration::from_millis(100),
+        }
+    }
+
+    fn write(&mut self, l: &mut TestRtc, r: &mut TestRtc, until: Duration) -> Result<(), RtcError> {
+        let params = l.params_opus();
+        assert_eq!(params.spec().codec, Codec::Opus);
+        let pt = params.pt();
+
+        loop {
+            if l.start + l.duration() > self.write_at {
+                self.write_at = l.last + Duration::from_millis(100);
+                let wallclock = l.start + l.duration();
+
+                let mut direct = l.direct_api();
+                let stream = direct.stream_tx(&self.ssrc).unwrap();
+
+                let time = (self.count * 960 + 47_000_000) as u32;
+                let seq_no = (47_000 + self.count).into();
+                self.count += 1;
+
+                stream
+                    .write_rtp(
+                        pt,
+                        seq_no,
+                        time,
+                        wallclock,
+                        false,
+                        ExtensionValues::default(),
+                        false,
+                        vec![0x1, 0x2, 0x3, 0x4],
+                    )
+                    .expect("clean write");
+            }
+
+            progress(l, r)?;
+
+            if l.duration() > until {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn received(r: &TestRtc) -> (usize, usize) {
+    let rtp = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::RtpPacket(_)))
+        .count();
+    let raw = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::RawPacket(p) if matches!(**p, RawPacket::RtpRx(..))))
+        .count();
+    (rtp, raw)
+}
@@ -0,0 +1,107 @@
+use std::time::Duration;
+
+use str0m::format::Codec;
+use str0m::media::{Frequency, MediaKind};
+use str0m::rtp::{ExtensionValues, Ssrc};
+use str0m::{Event, RtcError};
+
+mod common;
+use common::{connect_l_r, init_log, progress, TestRtc};
+
+#[test]
+pub fn rtp_clock_rate() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect_l_r();
+
+    let mid = "aud".into();
+    let ssrc: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Audio);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Audio);
+    r.direct_api()
+        .expect_stream_rx(ssrc, None, mid, None)
+        .set_clock_rate(Some(Frequency::EIGHT_KHZ));
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let params = l.params_opus();
+    assert_eq!(params.spec().codec, Codec::Opus);
+    let pt = params.pt();
+
+    let mut count: u64 = 0;
+    let mut write = |l: &mut TestRtc, r: &mut TestRtc, until: Duration| -> Result<(), RtcError> {
+        let mut write_at = l.last + Duration::from_millis(20);
+        while l.duration() < until {
+            if l.last >= write_at {
+                write_at = l.last + Duration::from_millis(20);
+                let wallclock = l.start + l.duration();
+
+                let mut direct = l.direct_api();
+                let stream = direct.stream_tx(&ssrc).unwrap();
+
+                let time = (count * 160 + 47_000_000) as u32;
+                let seq_no = (47_000 + count).into();
+                count += 1;
+
+                stream
+                    .write_rtp(
+                        pt,
+                        seq_no,
+                        time,
+                        wallclock,
+                        false,
+                        ExtensionValues::default(),
+                        false,
+                        vec![0x1, 0x2, 0x3, 0x4],
+                    )
+                    .expect("clean write");
+            }
+
+            progress(l, r)?;
+        }
+        Ok(())
+    };
+
+    write(&mut l, &mut r, Duration::from_secs(2))?;
+
+    // The override wins over the 48kHz of the Opus PT.
+    let rates = received_rates(&r);
+    assert!(rates.len() > 10);
+    assert!(rates.iter().all(|f| *f == Frequency::EIGHT_KHZ));
+
+    let received = rates.len();
+
+    // None goes back to the clock rate of the PT.
+    r.direct_api()
+        .stream_rx(&ssrc)
+        .unwrap()
+        .set_clock_rate(None);
+
+    write(&mut l, &mut r, Duration::from_secs(4))?;
+
+    let rates = received_rates(&r);
+    assert!(rates.len() > received + 10);
+    assert!(rates[received..]
+        .iter()
+        .all(|f| *f == Frequency::FORTY_EIGHT_KHZ));
+
+    Ok(())
+}
+
+fn received_rates(r: &TestRtc) -> Vec<Frequency> {
+    r.events
+        .iter()
+        .filter_map(|(_, e)| {
+            if let Event::RtpPacket(v) = e {
+                Some(v.time.frequency())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -105,6 +105,9 @@ pub fn rtp_direct_mid() -> Result<(), RtcError> {
 
     assert_eq!(media.len(), 3);
 
+    // Every audio packet ends a frame, even without the marker bit.
+    assert!(media.iter().all(|p| !p.header.marker && p.end_of_frame));
+
     assert!(l.media(mid).is_some());
     assert!(l.direct_api().stream_tx_by_mid(mid, None).is_some());
     l.direct_api().remove_media(mid);
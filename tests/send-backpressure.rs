@@ -0,0 +1,92 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::bwe::Bitrate;
+use str0m::media::{Direction, MediaKind};
+use str0m::{Candidate, Event, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn send_backpressure() -> Result<(), RtcError> {
+    init_log();
+
+    let l_rtc = Rtc::builder()
+        .enable_bwe(Some(Bitrate::kbps(100)))
+        .set_send_backpressure(Some(Duration::from_millis(200)))
+        .build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+
+    let backpressure = |l: &TestRtc, active: bool| {
+        l.events.iter().any(|(_, e)| match e {
+            Event::SendBackpressure(b) => b.mid == mid && b.active == active,
+            _ => false,
+        })
+    };
+
+    // Write far more than the BWE target until the writes are backpressured.
+    let mut next_frame = l.last;
+    loop {
+        if backpressure(&l, true) {
+            break;
+        }
+
+        if l.last >= next_frame {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            let mut writer = l.writer(mid).unwrap();
+            assert!(!writer.is_backpressured());
+            writer.write(pt, wallclock, time, vec![1_u8; 20_000])?;
+            next_frame = l.last + Duration::from_millis(33);
+        }
+
+        progress(&mut l, &mut r)?;
+        assert!(l.duration() < Duration::from_secs(10), "no backpressure");
+    }
+
+    assert!(l.writer(mid).unwrap().is_backpressured());
+    assert!(!backpressure(&l, false));
+
+    // Once the writes stop, the queue drains and the backpressure ends.
+    let stop = l.last;
+    loop {
+        if backpressure(&l, false) {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+        assert!(
+            l.last - stop < Duration::from_secs(30),
+            "backpressure never ended"
+        );
+    }
+
+    assert!(!l.writer(mid).unwrap().is_backpressured());
+
+    Ok(())
+}
@@ -1,9 +1,11 @@
 use std::net::Ipv4Addr;
+use std::sync::Arc;
 use std::time::Duration;
 
 use str0m::format::Codec;
 use str0m::media::{Direction, MediaKind};
-use str0m::stats::MediaEgressStats;
+use str0m::stats::{CandidatePairState, CandidatePairStats, ChannelStats, MediaEgressStats};
+use str0m::stats::{DefaultQualityScorer, QualityDirection};
 use str0m::{Candidate, Event, RtcConfig, RtcError};
 use tracing::info_span;
 
@@ -134,5 +136,240 @@ pub fn stats() -> Result<(), RtcError> {
         media_count_l
     );
 
+    let peer_stats_r: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::PeerStats(s) => Some(s),
+            _ => None,
+        })
+        .collect();
+
+    let last = peer_stats_r.last().expect("PeerStats at R");
+    assert!(last.activity.events > 0);
+    assert!(last.activity.packets_rx > 0);
+    assert!(last.activity.packets_tx > 0);
+    assert_eq!(last.activity.allocations, None);
+
+    // Media flows both ways during the whole last interval.
+    let rate = last.activity_rate;
+    assert!(rate.packets_rx > 1.0, "packets_rx: {}", rate.packets_rx);
+    assert!(rate.packets_tx > 1.0, "packets_tx: {}", rate.packets_tx);
+    assert!(rate.events > 1.0, "events: {}", rate.events);
+    assert_eq!(rate.allocations, None);
+
+    Ok(())
+}
+
+#[test]
+pub fn channel_stats() -> Result<(), RtcError> {
+    init_log();
+
+    let l_config = RtcConfig::new().set_stats_interval(Some(Duration::from_secs(1)));
+    let r_config = RtcConfig::new().set_stats_interval(Some(Duration::from_secs(1)));
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_config.build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_config.build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let cid = change.add_channel("stats".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    let mut written = 0;
+
+    loop {
+        if written < 10 {
+            if let Some(mut chan) = l.channel(cid) {
+                chan.write(true, &[1; 100]).expect("to write");
+                written += 1;
+            }
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    assert_eq!(written, 10);
+
+    let last = |t: &TestRtc| -> Option<ChannelStats> {
+        t.events
+            .iter()
+            .filter_map(|(_, e)| match e {
+                Event::ChannelStats(s) => Some(s.clone()),
+                _ => None,
+            })
+            .last()
+    };
+
+    let l_stats = last(&l).expect("channel stats for L");
+    assert_eq!(l_stats.id, cid);
+    assert_eq!(l_stats.messages_sent, 10);
+    assert_eq!(l_stats.bytes_sent, 1000);
+    assert_eq!(l_stats.messages_received, 0);
+
+    let r_stats = last(&r).expect("channel stats for R");
+    assert_eq!(r_stats.messages_received, 10);
+    assert_eq!(r_stats.bytes_received, 1000);
+    assert_eq!(r_stats.messages_sent, 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn candidate_pair_stats() -> Result<(), RtcError> {
+    init_log();
+
+    let l_config = RtcConfig::new().set_stats_interval(Some(Duration::from_secs(1)));
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_config.build());
+    let mut r = TestRtc::new(info_span!("R"));
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1.clone());
+    r.add_local_candidate(host2.clone());
+
+    let mut change = l.sdp_api();
+    change.add_channel("stats".into());
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    let selected: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::SelectedCandidatePairChange { local, remote } => {
+                Some((local.addr(), remote.addr()))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(selected, vec![(host1.addr(), host2.addr())]);
+
+    let stats: CandidatePairStats = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::CandidatePairStats(s) => Some(s.clone()),
+            _ => None,
+        })
+        .last()
+        .expect("candidate pair stats");
+
+    assert_eq!(stats.local.addr(), host1.addr());
+    assert_eq!(stats.remote.addr(), host2.addr());
+    assert_eq!(stats.state, CandidatePairState::Succeeded);
+    assert!(stats.nominated);
+    assert!(stats.selected);
+    assert!(stats.requests_sent > 0);
+    assert!(stats.responses_received > 0);
+    assert!(stats.requests_received > 0);
+    assert!(stats.rtt.is_some());
+    assert!(stats.min_rtt <= stats.rtt);
+    assert!(stats.smoothed_rtt.is_some());
+    // DTLS and SCTP traffic.
+    assert!(stats.bytes_sent > 0);
+    assert!(stats.bytes_received > 0);
+
+    Ok(())
+}
+
+#[test]
+pub fn quality_stats() -> Result<(), RtcError> {
+    init_log();
+
+    let config = || {
+        RtcConfig::new()
+            .set_stats_interval(Some(Duration::from_secs(1)))
+            .set_quality_scorer(Some(Arc::new(DefaultQualityScorer::default())))
+    };
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), config().build());
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), config().build());
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Audio, Direction::SendRecv, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_opus().pt();
+    let data = vec![1_u8; 80];
+
+    loop {
+        for t in [&mut l, &mut r] {
+            let wallclock = t.start + t.duration();
+            let time = t.duration().into();
+            t.writer(mid)
+                .unwrap()
+                .write(pt, wallclock, time, data.clone())?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(5) {
+            break;
+        }
+    }
+
+    let quality: Vec<_> = l
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::QualityStats(s) => Some(s.clone()),
+            _ => None,
+        })
+        .collect();
+
+    for direction in [QualityDirection::Egress, QualityDirection::Ingress] {
+        let last = quality
+            .iter()
+            .rev()
+            .find(|q| q.direction == direction)
+            .expect("quality stats for direction");
+
+        assert!(last.score >= 1.0 && last.score <= 4.5, "{:?}", last);
+        assert!(last.inputs.rtt.is_some(), "{:?}", last);
+        assert!(last.inputs.bitrate.is_some(), "{:?}", last);
+    }
+
     Ok(())
 }
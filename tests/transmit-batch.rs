@@ -0,0 +1,119 @@
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::media::{Direction, MediaKind};
+use str0m::net::Receive;
+use str0m::rtp::RawPacket;
+use str0m::{Candidate, Input, Output, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, progress, TestRtc};
+
+#[test]
+pub fn transmit_batch() -> Result<(), RtcError> {
+    init_log();
+
+    let l_rtc = Rtc::builder()
+        .set_max_transmit_batch(4)
+        .enable_raw_packets(true)
+        .build();
+    let r_rtc = Rtc::builder().enable_raw_packets(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    loop {
+        if l.is_connected() && r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    // A frame that is packetized into several RTP packets.
+    let pt = l.params_vp8().pt();
+    let wallclock = l.start + l.duration();
+    let time = l.duration().into();
+    l.writer(mid)
+        .unwrap()
+        .write(pt, wallclock, time, vec![1_u8; 6000])?;
+
+    let mut batch_sizes = vec![];
+
+    // Drive L alone, sending everything in batches.
+    let start = l.last;
+    while l.last - start < Duration::from_millis(100) {
+        l.rtc.handle_input(Input::Timeout(l.last))?;
+
+        match l.rtc.poll_output()? {
+            Output::Timeout(v) => {
+                let tick = l.last + Duration::from_millis(10);
+                l.last = if v == l.last { tick } else { tick.min(v) };
+            }
+            Output::Transmit(t) => {
+                let batch = l.rtc.batch_transmit(t);
+                batch_sizes.push(batch.contents.len());
+
+                for contents in &batch.contents {
+                    let input = Input::Receive(
+                        l.last,
+                        Receive {
+                            proto: batch.proto,
+                            source: batch.source,
+                            destination: batch.destination,
+                            contents: (&**contents).try_into()?,
+                        },
+                    );
+                    r.rtc.handle_input(input)?;
+                }
+            }
+            Output::Event(v) => l.events.push((l.last, v)),
+        }
+    }
+
+    assert!(batch_sizes.iter().all(|n| (1..=4).contains(n)));
+    assert!(
+        batch_sizes.iter().any(|n| *n > 1),
+        "no batch with several datagrams: {batch_sizes:?}"
+    );
+
+    // R gets every RTP packet L sent, in order.
+    r.last = l.last;
+    progress(&mut l, &mut r)?;
+
+    let seqs = |t: &TestRtc, tx: bool| -> Vec<_> {
+        t.events
+            .iter()
+            .filter_map(|(_, e)| match e.as_raw_packet() {
+                Some(RawPacket::RtpTx(h, _)) if tx => Some(h.sequence_number),
+                Some(RawPacket::RtpRx(h, _)) if !tx => Some(h.sequence_number),
+                _ => None,
+            })
+            .collect()
+    };
+
+    let sent = seqs(&l, true);
+    assert!(sent.len() > 1);
+    assert_eq!(sent, seqs(&r, false));
+
+    Ok(())
+}
+
+#[test]
+pub fn transmit_batch_at_least_one() {
+    let config = Rtc::builder().set_max_transmit_batch(0);
+    assert_eq!(config.max_transmit_batch(), 1);
+}
@@ -0,0 +1,195 @@
+use std::net::{Ipv4Addr, SocketAddr};
+use std::time::Duration;
+
+use str0m::_internal_test_exports::turn::TestTurnServer;
+use str0m::net::{Protocol, Receive};
+use str0m::turn::TurnConfig;
+use str0m::{Candidate, Event, Input, Output, Rtc, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{init_log, negotiate, TestRtc};
+
+fn server_addr() -> SocketAddr {
+    "192.0.2.1:3478".parse().unwrap()
+}
+
+fn relayed_addr() -> SocketAddr {
+    "192.0.2.1:50000".parse().unwrap()
+}
+
+#[test]
+pub fn turn_allocation_lost() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect()?;
+    let mut server = TestTurnServer::new(relayed_addr(), 4);
+
+    loop {
+        if l.is_connected() && r.is_connected() && has_relayed(&l) {
+            break;
+        }
+        progress_turn(&mut l, &mut r, &server)?;
+        assert!(
+            l.duration() < Duration::from_secs(10),
+            "no relayed candidate"
+        );
+    }
+
+    assert_eq!(gathered_relayed(&l), 1);
+
+    // The next refresh fails and the allocation is lost.
+    server.set_fail_refresh(true);
+    let lost = l.last;
+    while has_relayed(&l) {
+        progress_turn(&mut l, &mut r, &server)?;
+        assert!(
+            l.last - lost < Duration::from_secs(10),
+            "relayed candidate not removed"
+        );
+    }
+
+    // Nothing is sent from the lost relayed address, which progress_turn checks.
+    let stop = l.last + Duration::from_secs(5);
+    while l.last < stop {
+        progress_turn(&mut l, &mut r, &server)?;
+    }
+
+    assert!(l.is_connected());
+
+    Ok(())
+}
+
+#[test]
+pub fn turn_candidate_after_ice_restart() -> Result<(), RtcError> {
+    init_log();
+
+    let (mut l, mut r) = connect()?;
+    let server = TestTurnServer::new(relayed_addr(), 4);
+
+    loop {
+        if l.is_connected() && r.is_connected() && has_relayed(&l) {
+            break;
+        }
+        progress_turn(&mut l, &mut r, &server)?;
+        assert!(
+            l.duration() < Duration::from_secs(10),
+            "no relayed candidate"
+        );
+    }
+
+    // Restart without keeping the local candidates.
+    let (offer, pending) = l.span.in_scope(|| {
+        let mut change = l.rtc.sdp_api();
+        change.ice_restart(false);
+        change.apply().unwrap()
+    });
+    let answer = r.span.in_scope(|| r.rtc.sdp_api().accept_offer(offer))?;
+    l.span
+        .in_scope(|| l.rtc.sdp_api().accept_answer(pending, answer))?;
+
+    assert!(l._local_candidates().is_empty());
+
+    // The allocation is still there, and its candidate comes back.
+    let restart = l.last;
+    while !has_relayed(&l) {
+        progress_turn(&mut l, &mut r, &server)?;
+        assert!(
+            l.last - restart < Duration::from_secs(1),
+            "relayed candidate not added again"
+        );
+    }
+
+    assert_eq!(gathered_relayed(&l), 2);
+
+    Ok(())
+}
+
+fn connect() -> Result<(TestRtc, TestRtc), RtcError> {
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+
+    let turn = TurnConfig::new(Protocol::Udp, host1.addr(), server_addr(), "user", "pass");
+    let l_rtc = Rtc::builder().set_turn_servers(vec![turn]).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new(info_span!("R"));
+
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    negotiate(&mut l, &mut r, |change| {
+        change.add_channel("turn".into());
+    });
+
+    Ok((l, r))
+}
+
+fn has_relayed(t: &TestRtc) -> bool {
+    t._local_candidates()
+        .iter()
+        .any(|c| c.addr() == relayed_addr())
+}
+
+fn gathered_relayed(t: &TestRtc) -> usize {
+    t.events
+        .iter()
+        .filter(|(_, e)| matches!(e, Event::CandidateGathered(c) if c.addr() == relayed_addr()))
+        .count()
+}
+
+/// Like `progress`, with the TURN server answering what is sent to it.
+fn progress_turn(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    server: &TestTurnServer,
+) -> Result<(), RtcError> {
+    let (f, t) = if l.last < r.last { (l, r) } else { (r, l) };
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                // Anything from the relayed address must go via the TURN client.
+                assert_ne!(v.source, relayed_addr());
+
+                if v.destination == server_addr() {
+                    if let Some(reply) = server.reply(v.source, &v.contents) {
+                        let input = Input::Receive(
+                            f.last,
+                            Receive {
+                                proto: Protocol::Udp,
+                                source: server_addr(),
+                                destination: v.source,
+                                contents: (&*reply).try_into()?,
+                            },
+                        );
+                        f.span.in_scope(|| f.rtc.handle_input(input))?;
+                    }
+                    continue;
+                }
+
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*v.contents).try_into()?,
+                    },
+                );
+                t.span.in_scope(|| t.rtc.handle_input(input))?;
+            }
+            Output::Event(v) => f.events.push((f.last, v)),
+        }
+    }
+
+    Ok(())
+}
@@ -4,6 +4,7 @@ use std::time::Duration;
 use str0m::format::Codec;
 use str0m::media::{Direction, MediaKind};
 use str0m::rtp::rtcp::Twcc;
+use str0m::rtp::Extension;
 use str0m::{Candidate, Rtc, RtcError};
 use tracing::info_span;
 
@@ -117,3 +118,83 @@ pub fn twcc() -> Result<(), RtcError> {
 
     Ok(())
 }
+
+#[test]
+pub fn twcc_feedback_disabled() -> Result<(), RtcError> {
+    init_log();
+    let l_rtc = Rtc::builder().enable_raw_packets(true).build();
+    let r_rtc = Rtc::builder().enable_raw_packets(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), l_rtc);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), r_rtc);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mid = negotiate(&mut l, &mut r, |change| {
+        change.add_media(MediaKind::Video, Direction::SendOnly, None, None)
+    });
+
+    let id = l.media(mid).unwrap().twcc_extension_id();
+    assert!(id.is_some());
+    assert_eq!(r.media(mid).unwrap().twcc_extension_id(), id);
+
+    // R doesn't want to give feedback.
+    assert!(r.direct_api().twcc_feedback());
+    r.direct_api().set_twcc_feedback(false);
+    assert!(!r.direct_api().twcc_feedback());
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+    let data_a = [1_u8; 80];
+
+    loop {
+        {
+            let wallclock = l.start + l.duration();
+            let time = l.duration().into();
+            l.writer(mid).unwrap().write(pt, wallclock, time, data_a)?;
+        }
+
+        progress(&mut l, &mut r)?;
+
+        if l.duration() > Duration::from_secs(3) {
+            break;
+        }
+    }
+
+    use str0m::rtp::{rtcp::Rtcp, RawPacket};
+    let sent_twcc = r
+        .events
+        .iter()
+        .filter(|(_, e)| matches!(e.as_raw_packet(), Some(RawPacket::RtcpTx(Rtcp::Twcc(_)))))
+        .count();
+    assert_eq!(sent_twcc, 0, "Should not send TWCC");
+
+    // Moving the extension id is reflected on the media, but not in the negotiated extmap.
+    assert!(l.direct_api().set_twcc_extension_id(mid, Some(7)));
+    assert_eq!(l.media(mid).unwrap().twcc_extension_id(), Some(7));
+    let remote_extmap = l.media(mid).unwrap().remote_extmap();
+    assert_eq!(remote_extmap.id_of(Extension::TransportSequenceNumber), id);
+
+    // An id used by another extension is refused.
+    let mid_id = remote_extmap.id_of(Extension::RtpMid).unwrap();
+    assert!(!l.direct_api().set_twcc_extension_id(mid, Some(mid_id)));
+    assert_eq!(l.media(mid).unwrap().twcc_extension_id(), Some(7));
+
+    assert!(l.direct_api().set_twcc_extension_id(mid, None));
+    assert_eq!(l.media(mid).unwrap().twcc_extension_id(), None);
+
+    Ok(())
+}
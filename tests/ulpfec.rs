@@ -0,0 +1,282 @@
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use str0m::format::{Codec, FormatParams};
+use str0m::media::{Direction, Frequency, MediaKind, MediaTime, Pt};
+use str0m::net::Receive;
+use str0m::rtp::{ExtensionValues, Ssrc};
+use str0m::{Candidate, Event, Input, Output, Rtc, RtcConfig, RtcError};
+use tracing::info_span;
+
+mod common;
+use common::{connect_l_r_with_rtc, init_log, progress, TestRtc};
+
+fn config() -> RtcConfig {
+    let mut config = Rtc::builder()
+        .clear_codecs()
+        .set_stats_interval(Some(Duration::from_secs(1)));
+
+    // VP8 without RTX, so lost packets can only come back with ULPFEC.
+    config.codec_config().add_config(
+        96.into(),
+        None,
+        Codec::Vp8,
+        Frequency::NINETY_KHZ,
+        None,
+        FormatParams::default(),
+    );
+
+    config.enable_ulpfec(true)
+}
+
+#[test]
+pub fn ulpfec_recovers_lost_packets() -> Result<(), RtcError> {
+    init_log();
+
+    let rtc_l = config().set_ulpfec_window(3).build();
+    let rtc_r = config().set_rtp_mode(true).build();
+
+    let mut l = TestRtc::new_with_rtc(info_span!("L"), rtc_l);
+    let mut r = TestRtc::new_with_rtc(info_span!("R"), rtc_r);
+
+    let host1 = Candidate::host((Ipv4Addr::new(1, 1, 1, 1), 1000).into(), "udp")?;
+    let host2 = Candidate::host((Ipv4Addr::new(2, 2, 2, 2), 2000).into(), "udp")?;
+    l.add_local_candidate(host1);
+    r.add_local_candidate(host2);
+
+    let mut change = l.sdp_api();
+    let mid = change.add_media(MediaKind::Video, Direction::SendOnly, None, None);
+    let (offer, pending) = change.apply().unwrap();
+
+    let sdp = offer.to_sdp_string();
+    assert!(sdp.contains("a=rtpmap:116 red/90000"));
+    assert!(sdp.contains("a=rtpmap:117 ulpfec/90000"));
+
+    let answer = r.rtc.sdp_api().accept_offer(offer)?;
+    l.rtc.sdp_api().accept_answer(pending, answer)?;
+
+    loop {
+        if l.is_connected() || r.is_connected() {
+            break;
+        }
+        progress(&mut l, &mut r)?;
+    }
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let pt = l.params_vp8().pt();
+    let red_pt: Pt = 116.into();
+
+    let mut sent = 0;
+    let mut dropped = 0;
+
+    for index in 0..200_u32 {
+        let wallclock = l.start + l.duration();
+        let time = MediaTime::from_90khz(3000 * index as u64);
+
+        // Small enough for one RTP packet per frame.
+        l.writer(mid)
+            .unwrap()
+            .write(pt, wallclock, time, vec![1; 100])?;
+
+        // Drop every 5th RED packet. Each window of 3 media packets is followed by
+        // one FEC packet, which means at most one packet in each window is lost.
+        let lossy = (10..190).contains(&index);
+        progress_drop_red(&mut l, &mut r, red_pt, &mut sent, &mut dropped, lossy)?;
+    }
+
+    let settle_time = l.duration() + Duration::from_secs(2);
+    while l.duration() < settle_time {
+        progress(&mut l, &mut r)?;
+    }
+
+    // Both media and FEC packets are wrapped in RED.
+    assert!(sent > 250, "Sent {sent}");
+    assert!(dropped > 40, "Dropped {dropped}");
+
+    let mut seen = HashSet::new();
+
+    for (_, e) in &r.events {
+        let Event::RtpPacket(p) = e else {
+            continue;
+        };
+
+        // The RED is unwrapped and the FEC packets themselves are not emitted.
+        assert_eq!(p.header.payload_type, pt);
+
+        assert!(
+            seen.insert(*p.seq_no),
+            "Packet {:?} emitted twice",
+            p.seq_no
+        );
+    }
+
+    // Every lost media packet was recovered.
+    assert_eq!(seen.len(), 200);
+
+    let recovered = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaIngressStats(s) => Some(s.packets_recovered),
+            _ => None,
+        })
+        .max()
+        .expect("ingress stats");
+
+    assert!(recovered > 20, "Recovered {recovered}");
+    assert!(recovered <= dropped as u64);
+
+    Ok(())
+}
+
+#[test]
+pub fn ulpfec_bare_and_nested_red_are_dropped() -> Result<(), RtcError> {
+    init_log();
+
+    // The RED and ULPFEC PTs without enable_ulpfec(), for L to send them as is.
+    let config = || {
+        let mut config = Rtc::builder().clear_codecs();
+        for (pt, codec) in [(96, Codec::Vp8), (116, Codec::Red), (117, Codec::Ulpfec)] {
+            config.codec_config().add_config(
+                pt.into(),
+                None,
+                codec,
+                Frequency::NINETY_KHZ,
+                None,
+                FormatParams::default(),
+            );
+        }
+        config
+    };
+
+    let rtc_l = config().set_rtp_mode(true).build();
+    let rtc_r = config().build();
+
+    let (mut l, mut r) = connect_l_r_with_rtc(rtc_l, rtc_r);
+
+    let mid = "vid".into();
+    let ssrc: Ssrc = 42.into();
+
+    l.direct_api().declare_media(mid, MediaKind::Video);
+    l.direct_api().declare_stream_tx(ssrc, None, mid, None);
+
+    r.direct_api().declare_media(mid, MediaKind::Video);
+    r.direct_api().expect_stream_rx(ssrc, None, mid, None);
+
+    let max = l.last.max(r.last);
+    l.last = max;
+    r.last = max;
+
+    let packets: [(u8, Vec<u8>); 3] = [
+        // VP8, a whole frame.
+        (96, vec![0x10, 1, 2, 3]),
+        // ULPFEC outside of RED.
+        (117, vec![0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]),
+        // RED in RED.
+        (116, vec![116, 0x10, 1, 2, 3]),
+    ];
+
+    for index in 0..60_u64 {
+        let wallclock = l.start + l.duration();
+        let (pt, payload) = &packets[index as usize % packets.len()];
+
+        l.direct_api()
+            .stream_tx(&ssrc)
+            .unwrap()
+            .write_rtp(
+                (*pt).into(),
+                index.into(),
+                (3000 * index) as u32,
+                wallclock,
+                true,
+                ExtensionValues::default(),
+                false,
+                payload.clone(),
+            )
+            .expect("clean write");
+
+        progress(&mut l, &mut r)?;
+    }
+
+    let settle_time = l.duration() + Duration::from_secs(1);
+    while l.duration() < settle_time {
+        progress(&mut l, &mut r)?;
+    }
+
+    let media: Vec<_> = r
+        .events
+        .iter()
+        .filter_map(|(_, e)| match e {
+            Event::MediaData(v) => Some(v),
+            _ => None,
+        })
+        .collect();
+
+    // Only the VP8 comes through, the rest is dropped without taking down R.
+    assert!(media.len() > 10, "Received {}", media.len());
+    assert!(media.iter().all(|d| d.params.spec().codec == Codec::Vp8));
+
+    Ok(())
+}
+
+/// Like `progress`, but drops every 5th RED packet from L to R when `lossy`.
+fn progress_drop_red(
+    l: &mut TestRtc,
+    r: &mut TestRtc,
+    red_pt: Pt,
+    sent: &mut usize,
+    dropped: &mut usize,
+    lossy: bool,
+) -> Result<(), RtcError> {
+    let from_l = l.last < r.last;
+    let (f, t) = if from_l { (l, r) } else { (r, l) };
+
+    loop {
+        f.span
+            .in_scope(|| f.rtc.handle_input(Input::Timeout(f.last)))?;
+
+        match f.span.in_scope(|| f.rtc.poll_output())? {
+            Output::Timeout(v) => {
+                let tick = f.last + Duration::from_millis(10);
+                f.last = if v == f.last { tick } else { tick.min(v) };
+                break;
+            }
+            Output::Transmit(v) => {
+                let data = v.contents;
+
+                // The RTP header is not encrypted by SRTP.
+                let is_red =
+                    from_l && data.len() > 12 && data[0] >> 6 == 2 && data[1] & 0x7f == *red_pt;
+
+                if is_red {
+                    *sent += 1;
+
+                    if lossy && *sent % 5 == 0 {
+                        *dropped += 1;
+                        continue;
+                    }
+                }
+
+                let input = Input::Receive(
+                    f.last,
+                    Receive {
+                        proto: v.proto,
+                        source: v.source,
+                        destination: v.destination,
+                        contents: (&*data).try_into()?,
+                    },
+                );
+                t.span.in_scope(|| t.rtc.handle_input(input))?;
+            }
+            Output::Event(v) => {
+                f.events.push((f.last, v));
+            }
+        }
+    }
+
+    Ok(())
+}